@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Rules for turning raw artist tags into the artist names shown and grouped on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtistRules {
+    /// Separators that split one tag into several artists, matched case-insensitively.
+    pub separators: Vec<String>,
+    /// Names that are never split, e.g. "Simon & Garfunkel".
+    pub keep: Vec<String>,
+    /// Maps variant spellings to the canonical name, matched case-insensitively.
+    pub aliases: HashMap<String, String>,
+    /// Rewrite "Beatles, The" as "The Beatles".
+    pub move_articles: bool,
+}
+
+const ARTICLES: [&str; 3] = ["The", "A", "An"];
+
+impl Default for ArtistRules {
+    fn default() -> Self {
+        ArtistRules {
+            separators: vec![
+                " feat. ".into(),
+                " feat ".into(),
+                " ft. ".into(),
+                " featuring ".into(),
+                // " & " is left out, since it is part of as many duo names as it joins artists.
+                ";".into(),
+                " / ".into(),
+            ],
+            keep: vec![],
+            aliases: HashMap::new(),
+            move_articles: true,
+        }
+    }
+}
+
+impl ArtistRules {
+    /// Split, normalize and alias every entry in `artists`, dropping duplicates.
+    pub fn apply(&self, artists: &[String]) -> Vec<String> {
+        let mut result: Vec<String> = vec![];
+        for raw in artists {
+            for artist in self.split(raw) {
+                let artist = self.canonical(&artist);
+                if !artist.is_empty() && !result.iter().any(|a| a.eq_ignore_ascii_case(&artist)) {
+                    result.push(artist);
+                }
+            }
+        }
+        if result.is_empty() {
+            artists.to_vec()
+        } else {
+            result
+        }
+    }
+
+    fn is_kept(&self, name: &str) -> bool {
+        self.keep
+            .iter()
+            .any(|keep| keep.eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn split(&self, raw: &str) -> Vec<String> {
+        // A kept name is left whole even as one part of a longer tag, as in
+        // "Simon & Garfunkel feat. Paul Desmond".
+        let mut parts = vec![raw.to_string()];
+        for separator in &self.separators {
+            let separator = separator.to_lowercase();
+            parts = parts
+                .into_iter()
+                .flat_map(|part| {
+                    if self.is_kept(&part) {
+                        vec![part]
+                    } else {
+                        split_ignore_case(&part, &separator)
+                    }
+                })
+                .collect();
+        }
+        parts
+            .into_iter()
+            .map(|part| part.trim().to_string())
+            .collect()
+    }
+
+    fn canonical(&self, artist: &str) -> String {
+        let artist = if self.move_articles {
+            move_article(artist)
+        } else {
+            artist.to_string()
+        };
+        self.aliases
+            .iter()
+            .find(|(variant, _)| variant.eq_ignore_ascii_case(&artist))
+            .map(|(_, canonical)| canonical.clone())
+            .unwrap_or(artist)
+    }
+}
+
+fn split_ignore_case(text: &str, separator: &str) -> Vec<String> {
+    let lower = text.to_lowercase();
+    // Lowercasing can change byte lengths outside ASCII, so only split when offsets line up.
+    if lower.len() != text.len() || separator.is_empty() {
+        return vec![text.to_string()];
+    }
+
+    let mut parts = vec![];
+    let mut start = 0;
+    for (index, _) in lower.match_indices(separator) {
+        parts.push(text[start..index].to_string());
+        start = index + separator.len();
+    }
+    parts.push(text[start..].to_string());
+    parts
+}
+
+fn move_article(artist: &str) -> String {
+    for article in ARTICLES {
+        if let Some(name) = artist.strip_suffix(&format!(", {article}")) {
+            return format!("{article} {name}");
+        }
+    }
+    artist.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(rules: &ArtistRules, artists: &[&str]) -> Vec<String> {
+        let artists: Vec<String> = artists.iter().map(|a| a.to_string()).collect();
+        rules.apply(&artists)
+    }
+
+    #[test]
+    fn splits_on_separators_regardless_of_case() {
+        let rules = ArtistRules::default();
+        assert_eq!(
+            apply(&rules, &["Artist FEAT. Guest; Other / Last"]),
+            ["Artist", "Guest", "Other", "Last"]
+        );
+        assert_eq!(apply(&rules, &["Simon & Garfunkel"]), ["Simon & Garfunkel"]);
+    }
+
+    #[test]
+    fn keeps_kept_names_whole_inside_longer_tags() {
+        let rules = ArtistRules {
+            separators: vec![" feat. ".into(), " & ".into()],
+            keep: vec!["Simon & Garfunkel".into()],
+            ..ArtistRules::default()
+        };
+        assert_eq!(
+            apply(&rules, &["simon & garfunkel feat. Paul Desmond & Friend"]),
+            ["simon & garfunkel", "Paul Desmond", "Friend"]
+        );
+    }
+
+    #[test]
+    fn moves_articles_applies_aliases_and_drops_duplicates() {
+        let rules = ArtistRules {
+            aliases: HashMap::from([("Beatles".to_string(), "The Beatles".to_string())]),
+            ..ArtistRules::default()
+        };
+        assert_eq!(
+            apply(&rules, &["Beatles, The", "beatles", "The Beatles; Band, A"]),
+            ["The Beatles", "A Band"]
+        );
+
+        let rules = ArtistRules {
+            move_articles: false,
+            ..ArtistRules::default()
+        };
+        assert_eq!(apply(&rules, &["Beatles, The"]), ["Beatles, The"]);
+    }
+
+    #[test]
+    fn keeps_the_tags_when_nothing_is_left() {
+        let rules = ArtistRules::default();
+        assert_eq!(apply(&rules, &[" ; "]), [" ; "]);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+};
+
+use anyhow::anyhow;
+use bincode::config;
+use serde::{Deserialize, Serialize};
+
+use crate::{playback::Track, session::Session, uri};
+
+/// A named point in a long track, such as a movement, a mix segment or an audiobook chapter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    /// Seconds from the start of the track.
+    pub start: u64,
+}
+
+/// A change to the chapters of the current track, from the chapter editor.
+#[derive(Clone, Debug)]
+pub enum ChapterEdit {
+    /// Set a marker at the current position.
+    Add,
+    Rename(usize, String),
+    /// Move the chapter at the first index to the second.
+    Move(usize, usize),
+    Remove(usize),
+}
+
+/// Chapter markers for every track that has some, keyed by URI.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Chapters {
+    pub tracks: HashMap<String, Vec<Chapter>>,
+}
+
+impl Chapters {
+    pub fn get_chapters_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("chapters.bin"))
+    }
+
+    pub fn load() -> Chapters {
+        let Some(file_path) = Self::get_chapters_file() else {
+            return Chapters::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return Chapters::default();
+        };
+        match bincode::serde::decode_from_slice::<Chapters, _>(&data, config::standard()) {
+            Ok((mut chapters, _)) => {
+                chapters.tracks = uri::normalize_keys(chapters.tracks);
+                chapters
+            }
+            Err(e) => {
+                eprintln!("Failed to decode chapters: {}", e);
+                Chapters::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_chapters_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    /// The chapters of `uri` in the order they were arranged in the editor.
+    pub fn get(&self, uri: &str) -> Vec<Chapter> {
+        self.tracks.get(uri).cloned().unwrap_or_default()
+    }
+
+    /// Apply `edit` to the chapters of `uri`, with `position` used for new markers.
+    pub fn edit(&mut self, uri: &str, edit: ChapterEdit, position: u64) {
+        let chapters = self.tracks.entry(uri.to_string()).or_default();
+        match edit {
+            ChapterEdit::Add => {
+                let title = format!("Chapter {}", chapters.len() + 1);
+                chapters.push(Chapter {
+                    title,
+                    start: position,
+                });
+            }
+            ChapterEdit::Rename(index, title) => {
+                if let Some(chapter) = chapters.get_mut(index) {
+                    chapter.title = title;
+                }
+            }
+            ChapterEdit::Move(from, to) => {
+                if from < chapters.len() && to < chapters.len() {
+                    let chapter = chapters.remove(from);
+                    chapters.insert(to, chapter);
+                }
+            }
+            ChapterEdit::Remove(index) => {
+                if index < chapters.len() {
+                    chapters.remove(index);
+                }
+            }
+        }
+        if chapters.is_empty() {
+            self.tracks.remove(uri);
+        }
+    }
+
+    /// Start of the first chapter after `position`.
+    pub fn next(&self, uri: &str, position: u64) -> Option<u64> {
+        self.starts(uri).into_iter().find(|&start| start > position)
+    }
+
+    /// Start of the chapter before the one playing at `position`. Within the first few seconds
+    /// of a chapter this goes back one further, like the previous-track button.
+    pub fn previous(&self, uri: &str, position: u64) -> Option<u64> {
+        self.starts(uri)
+            .into_iter()
+            .rev()
+            .find(|&start| start + 3 < position)
+    }
+
+    fn starts(&self, uri: &str) -> Vec<u64> {
+        let mut starts: Vec<u64> = self.get(uri).iter().map(|c| c.start).collect();
+        starts.sort();
+        starts
+    }
+}
+
+/// Most tracks a cue sheet can number.
+const MAX_CUE_TRACKS: usize = 99;
+
+/// Write the chapters of `track` as a `.cue` sheet next to its file, returning where it went.
+/// Cue sheets need their tracks in time order, whatever the order in the editor. A sheet
+/// already there, as for a FLAC image, is left alone and the new one gets a name of its own.
+pub fn export_cue(track: &Track, chapters: &[Chapter]) -> anyhow::Result<PathBuf> {
+    let path = track
+        .path()
+        .ok_or(anyhow!("Only local files can have a cue sheet"))?;
+    let file_name = path
+        .file_name()
+        .ok_or(anyhow!("Track has no file name"))?
+        .to_string_lossy();
+    // Other text can lose its quotes, but the file has to be named as it is.
+    if file_name.contains('"') {
+        return Err(anyhow!("A cue sheet can't name a file with \" in its name"));
+    }
+    if chapters.len() > MAX_CUE_TRACKS {
+        return Err(anyhow!(
+            "A cue sheet holds at most {MAX_CUE_TRACKS} chapters, not {}",
+            chapters.len()
+        ));
+    }
+    let file_type = match path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+    {
+        Some(ext) if ext == "mp3" => "MP3",
+        _ => "WAVE",
+    };
+    let quote = |text: &str| text.replace('"', "'");
+    let performer = quote(&track.artists.join(", "));
+
+    let mut chapters = chapters.to_vec();
+    chapters.sort_by_key(|chapter| chapter.start);
+    let mut cue = format!(
+        "PERFORMER \"{performer}\"\nTITLE \"{}\"\nFILE \"{file_name}\" {file_type}\n",
+        quote(&track.title)
+    );
+    for (number, chapter) in chapters.iter().enumerate() {
+        cue.push_str(&format!("  TRACK {:02} AUDIO\n", number + 1));
+        cue.push_str(&format!("    TITLE \"{}\"\n", quote(&chapter.title)));
+        cue.push_str(&format!("    PERFORMER \"{performer}\"\n"));
+        cue.push_str(&format!(
+            "    INDEX 01 {:02}:{:02}:00\n",
+            chapter.start / 60,
+            chapter.start % 60
+        ));
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut cue_path = path.with_extension("cue");
+    let mut copy = 1;
+    loop {
+        match File::create_new(&cue_path) {
+            Ok(mut file) => {
+                file.write_all(cue.as_bytes())?;
+                return Ok(cue_path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                copy += 1;
+                cue_path = path.with_file_name(format!("{stem} ({copy}).cue"));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{playback::Playlist, uri};
+
+/// How exported playlists point at their files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportPaths {
+    /// From the playlist file's folder, so the playlist keeps working when the music and the
+    /// playlist are moved together. Files on another drive are still written in full.
+    Relative,
+    Absolute,
+}
+
+impl ExportPaths {
+    pub const ALL: [ExportPaths; 2] = [ExportPaths::Relative, ExportPaths::Absolute];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportPaths::Relative => "Relative paths",
+            ExportPaths::Absolute => "Absolute paths",
+        }
+    }
+}
+
+/// Write `playlist` to `path` as XSPF if it ends in `.xspf`, and as extended M3U in UTF-8
+/// otherwise. Streams are written as their URLs.
+pub fn write(playlist: &Playlist, path: &Path, paths: ExportPaths) -> anyhow::Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    // Track paths are resolved, so the folder has to be as well for them to share a prefix.
+    let base = fs::canonicalize(parent).unwrap_or(parent.to_path_buf());
+    let is_xspf = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xspf"));
+    let text = if is_xspf {
+        write_xspf(playlist, &base, paths)
+    } else {
+        write_m3u(playlist, &base, paths)
+    };
+    fs::write(path, text)?;
+    Ok(())
+}
+
+fn write_m3u(playlist: &Playlist, base: &Path, paths: ExportPaths) -> String {
+    let mut text = format!("#EXTM3U\n#PLAYLIST:{}\n", playlist.name);
+    for track in &playlist.tracks {
+        let name = if track.artists.is_empty() {
+            track.title.clone()
+        } else {
+            format!("{} - {}", track.artists.join(", "), track.title)
+        };
+        // -1 marks an unknown length.
+        let duration = match track.duration {
+            0 => -1,
+            seconds => seconds as i64,
+        };
+        text.push_str(&format!("#EXTINF:{duration},{name}\n"));
+        if !track.album.is_empty() {
+            text.push_str(&format!("#EXTALB:{}\n", track.album));
+        }
+        let location = match uri::to_path(&track.uri) {
+            Some(file) => match relative(&file, base, paths) {
+                Some(relative) => relative,
+                None => file.to_string_lossy().into_owned(),
+            },
+            None => track.uri.clone(),
+        };
+        text.push_str(&format!("{location}\n"));
+    }
+    text
+}
+
+fn write_xspf(playlist: &Playlist, base: &Path, paths: ExportPaths) -> String {
+    let mut text = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    text.push_str(&format!("  <title>{}</title>\n", escape(&playlist.name)));
+    text.push_str("  <trackList>\n");
+    for track in &playlist.tracks {
+        // Locations are URIs, so relative ones are percent-encoded but have no scheme.
+        let location = match uri::to_path(&track.uri) {
+            Some(file) => match relative(&file, base, paths) {
+                Some(relative) => uri::encode_relative(&relative),
+                None => uri::from_path(&file),
+            },
+            None => track.uri.clone(),
+        };
+        text.push_str("    <track>\n");
+        text.push_str(&format!(
+            "      <location>{}</location>\n",
+            escape(&location)
+        ));
+        text.push_str(&format!("      <title>{}</title>\n", escape(&track.title)));
+        if !track.artists.is_empty() {
+            let creator = escape(&track.artists.join(", "));
+            text.push_str(&format!("      <creator>{creator}</creator>\n"));
+        }
+        if !track.album.is_empty() {
+            text.push_str(&format!("      <album>{}</album>\n", escape(&track.album)));
+        }
+        if track.duration > 0 {
+            text.push_str(&format!(
+                "      <duration>{}</duration>\n",
+                track.duration * 1000
+            ));
+        }
+        text.push_str("    </track>\n");
+    }
+    text.push_str("  </trackList>\n</playlist>\n");
+    text
+}
+
+/// `file` from `base`, with `/` between folders, or `None` when absolute paths were asked for
+/// or the two don't share a drive or share.
+fn relative(file: &Path, base: &Path, paths: ExportPaths) -> Option<String> {
+    if paths == ExportPaths::Absolute {
+        return None;
+    }
+    let file: Vec<Component> = file.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let shared = file.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    // Another Windows drive or share, which no relative path reaches.
+    if shared == 0 {
+        return None;
+    }
+    let relative: PathBuf = std::iter::repeat_n(Component::ParentDir, base.len() - shared)
+        .chain(file[shared..].iter().copied())
+        .collect();
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
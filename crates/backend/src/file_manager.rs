@@ -0,0 +1,52 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    process::Command,
+};
+
+use anyhow::anyhow;
+
+/// Write `contents` to `path`, which only its owner may read or write, for files holding
+/// secrets.
+pub fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to a new file, so tighten one made some other way.
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(contents)
+}
+
+/// Reveal the given file in the system file manager, selecting it where the platform allows.
+pub fn reveal(path: &Path) -> anyhow::Result<()> {
+    if !path.exists() {
+        return Err(anyhow!("File does not exist: {}", path.display()));
+    }
+
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("explorer");
+        command.arg(format!("/select,{}", path.display()));
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else {
+        // Most Linux file managers have no portable "select" flag, so open the parent instead.
+        let mut command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+        command
+    };
+
+    command
+        .spawn()
+        .map_err(|e| anyhow!("Could not open file manager: {e}"))?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! Offering the app for opening audio files from the file manager. It's only added to the
+//! "Open with" choices; which app opens audio files by default stays the user's call.
+
+#[cfg(any(target_os = "linux", windows))]
+use anyhow::anyhow;
+
+/// Desktop entry listing the audio types the app opens on Linux.
+#[cfg(target_os = "linux")]
+const DESKTOP_FILE: &str = "reyvr.desktop";
+/// MIME types of [`AUDIO_EXTENSIONS`](crate::playback::AUDIO_EXTENSIONS), with the older
+/// `x-` names some systems still use.
+#[cfg(target_os = "linux")]
+const MIME_TYPES: [&str; 8] = [
+    "audio/mpeg",
+    "audio/flac",
+    "audio/x-flac",
+    "audio/wav",
+    "audio/x-wav",
+    "audio/ogg",
+    "audio/vorbis",
+    "audio/x-vorbis+ogg",
+];
+
+/// Register the running executable as able to open audio files. On Linux the desktop entry is
+/// only rewritten when the executable moved, like [`link::register`](crate::link::register)'s.
+/// macOS reads the types an app opens from its bundle, so there's nothing to do there.
+pub fn register() -> anyhow::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use directories::BaseDirs;
+        use std::fs;
+
+        let exe = std::env::current_exe()?;
+        let dir = BaseDirs::new()
+            .ok_or(anyhow!("Could not find home directory"))?
+            .data_dir()
+            .join("applications");
+        fs::create_dir_all(&dir)?;
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName=Reyvr\nComment=Play music\n\
+             Exec=\"{}\" %U\nTerminal=false\nCategories=AudioVideo;Audio;Player;\n\
+             MimeType={};\n",
+            exe.display(),
+            MIME_TYPES.join(";")
+        );
+        let path = dir.join(DESKTOP_FILE);
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == entry) {
+            return Ok(());
+        }
+        fs::write(&path, entry)?;
+        // Refresh the cache file managers read the "Open with" choices from.
+        std::process::Command::new("update-desktop-database")
+            .arg(&dir)
+            .status()?;
+    }
+
+    #[cfg(windows)]
+    {
+        use crate::playback::AUDIO_EXTENSIONS;
+
+        let exe = std::env::current_exe()?;
+        let name = exe
+            .file_name()
+            .ok_or(anyhow!("Could not find the executable's name"))?
+            .to_string_lossy()
+            .to_string();
+        let key = format!(r"HKCU\Software\Classes\Applications\{name}");
+        let command = format!("\"{}\" \"%1\"", exe.display());
+        let mut entries: Vec<(String, Vec<String>)> = vec![
+            (
+                key.clone(),
+                vec![
+                    "/v".into(),
+                    "FriendlyAppName".into(),
+                    "/d".into(),
+                    "Reyvr".into(),
+                ],
+            ),
+            (
+                format!(r"{key}\shell\open\command"),
+                vec!["/ve".into(), "/d".into(), command],
+            ),
+        ];
+        for extension in AUDIO_EXTENSIONS {
+            entries.push((
+                format!(r"{key}\SupportedTypes"),
+                vec![
+                    "/v".into(),
+                    format!(".{extension}"),
+                    "/d".into(),
+                    String::new(),
+                ],
+            ));
+            entries.push((
+                format!(r"HKCU\Software\Classes\.{extension}\OpenWithList\{name}"),
+                vec!["/ve".into(), "/d".into(), String::new()],
+            ));
+        }
+        for (key, values) in entries {
+            std::process::Command::new("reg")
+                .arg("add")
+                .arg(key)
+                .args(values)
+                .arg("/f")
+                .status()?;
+        }
+    }
+
+    Ok(())
+}
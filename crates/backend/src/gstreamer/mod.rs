@@ -1,19 +1,84 @@
-use crate::player::{Response, Thumbnail};
+use crate::{
+    loudness::{Loudness, METER_RATE, Meter},
+    memory::LOW_MEMORY_ARTWORK,
+    player::{Response, Thumbnail},
+    settings::{MAX_PREAMP_DB, MIN_PREAMP_DB},
+};
 
-use super::{Backend, playback::Track};
+use super::{
+    Backend,
+    playback::{Origin, Track},
+};
 use anyhow::anyhow;
 use async_trait::async_trait;
+use futures::{
+    StreamExt, future,
+    stream::{self, BoxStream},
+};
 use gstreamer::{ClockTime, MessageView, SeekFlags, State, prelude::*};
 use gstreamer_pbutils as gst_pbutils;
 use image::{EncodableLayout, ImageReader, Rgba, RgbaImage};
 use std::{
     io::Cursor,
-    sync::{Arc, Mutex},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
 };
 
+/// Loudness below which audio counts as silence when skipping silence.
+const SILENCE_THRESHOLD_DB: f64 = -50.0;
+/// Consecutive quiet level reports (100ms each) before fast-forwarding.
+const SILENCE_MIN_REPORTS: u32 = 5;
+/// Playback rate used to run through silent stretches.
+const SILENCE_SKIP_RATE: f64 = 8.0;
+/// Number of frequency bands in each spectrum frame.
+const SPECTRUM_BANDS: u32 = 32;
+/// Magnitudes at or below this level (in dB) are reported as 0.
+const SPECTRUM_THRESHOLD_DB: i32 = -80;
+/// How often the playback position is checked for changes.
+const POSITION_INTERVAL: Duration = Duration::from_millis(200);
+/// How long each step of a decode probe may take before the file is treated as broken.
+const PROBE_TIMEOUT: ClockTime = ClockTime::from_seconds(5);
+/// Network stream buffering in low-memory mode, in bytes and time.
+const LOW_MEMORY_BUFFER_SIZE: i32 = 256 * 1024;
+const LOW_MEMORY_BUFFER_DURATION: ClockTime = ClockTime::from_seconds(2);
+
+/// Codec details picked up from stream tags, merged into [`Response::StreamInfo`].
+#[derive(Debug, Default)]
+struct StreamTags {
+    codec: Option<String>,
+    bitrate: Option<u32>,
+}
+
+#[derive(Debug, Default)]
+struct SilenceState {
+    quiet_reports: u32,
+    skipping: bool,
+}
+
 #[derive(Debug)]
 pub struct GstBackend {
     pub playbin: Arc<Mutex<gstreamer::Element>>,
+    /// Fixed gain set in decibels by the user, ahead of the volume stages.
+    pub preamp: gstreamer::Element,
+    /// Gain stage in the audio filter bin, used for volume above 100%.
+    pub boost: gstreamer::Element,
+    pub panorama: gstreamer::Element,
+    /// Capsfilter that forces a single channel when mono output is enabled.
+    pub mono: gstreamer::Element,
+    /// Frequency analysis feeding visualizers, only posting messages while enabled.
+    pub spectrum: gstreamer::Element,
+    /// The whole filter chain, detached from playbin while in exclusive mode.
+    pub filters: gstreamer::Element,
+    skip_silence: AtomicBool,
+    low_memory: AtomicBool,
+    exclusive: AtomicBool,
+    /// Display name of the device chosen to play to, or `None` for the system default.
+    output_device: Mutex<Option<String>>,
+    silence: Mutex<SilenceState>,
+    stream_tags: Mutex<StreamTags>,
 }
 
 #[async_trait]
@@ -23,60 +88,53 @@ impl Backend for GstBackend {
     }
 
     async fn load(&self, uri: &str) -> anyhow::Result<()> {
-        let playbin = Arc::clone(&self.playbin);
-        playbin
+        *self.silence.lock().expect("Could not lock silence state") = SilenceState::default();
+        *self.stream_tags.lock().expect("Could not lock stream tags") = StreamTags::default();
+        #[cfg(feature = "video")]
+        crate::video::clear();
+        let playbin = self
+            .playbin
             .lock()
-            .expect("Could not lock playbin")
-            .set_property("uri", uri);
+            .map_err(|e| anyhow!("Could not lock playbin: {e}"))?
+            .clone();
+        let uri = uri.to_string();
+        // Off the player's thread like state changes, since the player gives up on it the same.
+        smol::unblock(move || playbin.set_property("uri", uri)).await;
         Ok(())
     }
 
     async fn play(&self) -> anyhow::Result<()> {
-        let playbin = Arc::clone(&self.playbin);
-        playbin
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Could not lock playbin: {e}"))?
-            .set_state(gstreamer::State::Playing)
-            .map_err(|e| anyhow!("Could not set playbin state to playing: {e}"))?;
-        Ok(())
+        self.change_state(gstreamer::State::Playing, "playing")
+            .await
     }
 
     async fn pause(&self) -> anyhow::Result<()> {
-        let playbin = Arc::clone(&self.playbin);
-        playbin
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Could not lock playbin: {e}"))?
-            .set_state(gstreamer::State::Paused)
-            .map_err(|e| anyhow!("Could not set playbin state to paused: {e}"))?;
-        Ok(())
+        self.change_state(gstreamer::State::Paused, "paused").await
     }
 
     async fn stop(&self) -> anyhow::Result<()> {
-        let playbin = Arc::clone(&self.playbin);
-        playbin
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Could not lock playbin: {e}"))?
-            .set_state(gstreamer::State::Null)
-            .map_err(|e| anyhow!("Could not set playbin state to stopped: {e}"))?;
-        Ok(())
+        self.change_state(gstreamer::State::Null, "stopped").await
     }
 
     async fn set_volume(&self, volume: f64) -> anyhow::Result<()> {
         let playbin = Arc::clone(&self.playbin);
+        // Anything above 100% goes through the boost stage, which sits in front of the limiter.
         playbin
             .lock()
             .map_err(|e| anyhow::anyhow!("Could not lock playbin: {e}"))?
-            .set_property("volume", volume);
+            .set_property("volume", volume.min(1.0));
+        self.boost.set_property("volume", volume.max(1.0));
         Ok(())
     }
 
     async fn get_volume(&self) -> anyhow::Result<f32> {
         let playbin = Arc::clone(&self.playbin);
-        let volume: f32 = playbin
+        let volume: f64 = playbin
             .lock()
             .map_err(|e| anyhow::anyhow!("Could not lock playbin: {e}"))?
             .property("volume");
-        Ok(volume)
+        let boost: f64 = self.boost.property("volume");
+        Ok((volume * boost) as f32)
     }
 
     async fn get_state(&self) -> anyhow::Result<State> {
@@ -101,11 +159,17 @@ impl Backend for GstBackend {
                 .get::<gstreamer::tags::Title>()
                 .and_then(|v| Some(v.get().to_string()))
                 .unwrap_or_else(|| uri.to_string()),
-            artists: vec![
-                tags.get::<gstreamer::tags::Artist>()
-                    .and_then(|v| Some(v.get().to_string()))
-                    .unwrap_or_else(|| "Unknown Artist".into()),
-            ],
+            artists: {
+                let artists: Vec<String> = tags
+                    .iter_tag::<gstreamer::tags::Artist>()
+                    .map(|v| v.get().to_string())
+                    .collect();
+                if artists.is_empty() {
+                    vec!["Unknown Artist".into()]
+                } else {
+                    artists
+                }
+            },
             album: tags
                 .get::<gstreamer::tags::Album>()
                 .and_then(|v| Some(v.get().to_string()))
@@ -116,43 +180,145 @@ impl Backend for GstBackend {
                 .unwrap_or(ClockTime::from_seconds(0))
                 .seconds(),
             thumbnail: {
-                if let Some(image) = tags.get::<gstreamer::tags::Image>() {
-                    let bytes = image.get();
-                    let buffer = bytes.buffer().unwrap().map_readable().unwrap();
-                    Some(retrieve_small_thumbnail(buffer.as_bytes().into()).unwrap())
-                } else {
-                    None
-                }
+                let image = tags.get::<gstreamer::tags::Image>();
+                image
+                    .and_then(|image| embedded_art(image.get()))
+                    .and_then(|bytes| match retrieve_small_thumbnail(bytes) {
+                        Ok(thumbnail) => Some(thumbnail),
+                        Err(e) => {
+                            eprintln!("Could not read the cover of {uri}: {e}");
+                            None
+                        }
+                    })
             },
+            track_number: tags
+                .get::<gstreamer::tags::TrackNumber>()
+                .map_or(0, |v| v.get()),
+            disc_number: tags
+                .get::<gstreamer::tags::AlbumVolumeNumber>()
+                .map_or(0, |v| v.get()),
+            genre: tags
+                .get::<gstreamer::tags::Genre>()
+                .map(|v| v.get().to_string())
+                .unwrap_or_default(),
+            year: tags
+                .get::<gstreamer::tags::DateTime>()
+                .map(|v| v.get().year().max(0) as u32)
+                .or_else(|| {
+                    tags.get::<gstreamer::tags::Date>()
+                        .map(|v| v.get().year() as u32)
+                })
+                .unwrap_or(0),
+            // Stored as 0 to 100; round to the nearest star.
+            rating: tags
+                .get::<gstreamer::tags::UserRating>()
+                .map_or(0, |v| ((v.get() + 10) / 20).min(5)),
+            added: 0,
+            corrupt: false,
+            missing: false,
+            origin: Origin::Unknown,
+            custom_title: String::new(),
+            note: String::new(),
         })
     }
 
-    async fn monitor(&self) -> Option<Response> {
-        let playbin = self.playbin.lock().expect("Could not lock playbin");
-        if let Some(bus) = playbin.bus() {
-            while let Some(msg) = bus.pop() {
-                return match msg.view() {
-                    MessageView::Tag(msg) => {
-                        if let Some(image) = msg.tags().get::<gstreamer::tags::Image>() {
-                            let bytes = image.get();
-                            let buffer = bytes.buffer().unwrap().map_readable().unwrap();
-                            Some(Response::Thumbnail(
-                                retrieve_thumbnail(buffer.as_bytes().into()).unwrap(),
-                            ))
-                        } else {
-                            Some(Response::Error("Could not get thumbnail".to_string()))
-                        }
-                    }
-                    MessageView::Eos(_) => Some(Response::Eos),
-                    MessageView::StreamStart(_) => Some(Response::StreamStart),
-                    MessageView::Error(e) => Some(Response::Error(e.to_string())),
-                    MessageView::Warning(w) => Some(Response::Warning(w.to_string())),
-                    MessageView::Info(i) => Some(Response::Info(i.to_string())),
-                    _ => None,
-                };
-            }
+    async fn probe(&self, uri: &str) -> anyhow::Result<()> {
+        let pipeline = decode_pipeline(
+            uri,
+            &[
+                gstreamer::ElementFactory::make("audioconvert").build()?,
+                gstreamer::ElementFactory::make("fakesink")
+                    .property("sync", false)
+                    .build()?,
+            ],
+        )
+        .map_err(|e| anyhow!("Could not build probe pipeline: {e}"))?;
+        let result = probe_pipeline(&pipeline);
+        let _ = pipeline.set_state(State::Null);
+        result
+    }
+
+    async fn waveform(&self, uri: &str, duration: u64, points: usize) -> anyhow::Result<Vec<f32>> {
+        if duration == 0 || points == 0 {
+            return Err(anyhow!("Track has no known duration"));
         }
-        None
+        let interval = ClockTime::from_seconds(duration).nseconds() / points as u64;
+        let pipeline = decode_pipeline(
+            uri,
+            &[
+                gstreamer::ElementFactory::make("audioconvert").build()?,
+                gstreamer::ElementFactory::make("level")
+                    .property("interval", interval)
+                    .property("post-messages", true)
+                    .build()?,
+                gstreamer::ElementFactory::make("fakesink")
+                    .property("sync", false)
+                    .build()?,
+            ],
+        )
+        .map_err(|e| anyhow!("Could not build waveform pipeline: {e}"))?;
+        let result = collect_peaks(&pipeline, points);
+        let _ = pipeline.set_state(State::Null);
+        result
+    }
+
+    async fn loudness(&self, uri: &str) -> anyhow::Result<Loudness> {
+        let caps = gstreamer::Caps::builder("audio/x-raw")
+            .field("format", "F32LE")
+            .field("layout", "interleaved")
+            .field("rate", METER_RATE as i32)
+            .build();
+        let pipeline = decode_pipeline(
+            uri,
+            &[
+                gstreamer::ElementFactory::make("audioconvert").build()?,
+                gstreamer::ElementFactory::make("audioresample").build()?,
+                gstreamer::ElementFactory::make("capsfilter")
+                    .property("caps", caps)
+                    .build()?,
+                gstreamer::ElementFactory::make("fakesink")
+                    .name("sink")
+                    .property("sync", false)
+                    .build()?,
+            ],
+        )
+        .map_err(|e| anyhow!("Could not build loudness pipeline: {e}"))?;
+        let result = measure_loudness(&pipeline);
+        let _ = pipeline.set_state(State::Null);
+        result
+    }
+
+    fn events(self: Arc<Self>) -> BoxStream<'static, Response> {
+        let bus = self
+            .playbin
+            .lock()
+            .expect("Could not lock playbin")
+            .bus()
+            .expect("Playbin has no bus");
+        let backend = self.clone();
+        let messages = bus.stream().flat_map(move |msg| {
+            stream::iter(
+                [backend.handle_message(&msg), embedded_lyrics(&msg)]
+                    .into_iter()
+                    .flatten(),
+            )
+        });
+
+        let mut last_position = None;
+        let positions = smol::Timer::interval(POSITION_INTERVAL).filter_map(move |_| {
+            let position = self
+                .playbin
+                .lock()
+                .expect("Could not lock playbin")
+                .query_position::<ClockTime>()
+                .map(|pos| pos.seconds())
+                .unwrap_or(0);
+            let changed = last_position != Some(position);
+            last_position = Some(position);
+            future::ready(changed.then_some(Response::Position(position)))
+        });
+
+        stream::select(messages, positions).boxed()
     }
 
     async fn get_position(&self) -> u64 {
@@ -174,12 +340,146 @@ impl Backend for GstBackend {
                 SeekFlags::FLUSH | SeekFlags::KEY_UNIT,
                 ClockTime::from_seconds(time),
             )
-            .expect("Could not seek");
+            .map_err(|e| anyhow!("Could not seek: {e}"))?;
+        Ok(())
+    }
+
+    async fn set_balance(&self, balance: f32) -> anyhow::Result<()> {
+        self.panorama
+            .set_property("panorama", balance.clamp(-1.0, 1.0));
+        Ok(())
+    }
+
+    async fn set_preamp(&self, gain_db: f64) -> anyhow::Result<()> {
+        let gain_db = gain_db.clamp(MIN_PREAMP_DB, MAX_PREAMP_DB);
+        self.preamp
+            .set_property("volume", 10f64.powf(gain_db / 20.0));
+        Ok(())
+    }
+
+    async fn set_mono(&self, mono: bool) -> anyhow::Result<()> {
+        let caps = if mono {
+            gstreamer::Caps::builder("audio/x-raw")
+                .field("channels", 1)
+                .build()
+        } else {
+            gstreamer::Caps::new_empty_simple("audio/x-raw")
+        };
+        self.mono.set_property("caps", &caps);
+        Ok(())
+    }
+
+    async fn set_spectrum(&self, enabled: bool) -> anyhow::Result<()> {
+        self.spectrum.set_property("post-messages", enabled);
+        Ok(())
+    }
+
+    async fn set_skip_silence(&self, enabled: bool) -> anyhow::Result<()> {
+        self.skip_silence.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            let playbin = self.playbin.lock().expect("Could not lock playbin");
+            let mut silence = self.silence.lock().expect("Could not lock silence state");
+            if silence.skipping {
+                set_rate(&playbin, 1.0)?;
+            }
+            *silence = SilenceState::default();
+        }
+        Ok(())
+    }
+
+    async fn set_exclusive(&self, exclusive: bool) -> anyhow::Result<()> {
+        self.exclusive.store(exclusive, Ordering::Relaxed);
+        let sink = if exclusive {
+            self.exclusive_sink()
+        } else {
+            self.output_sink()
+        };
+        let playbin = self
+            .playbin
+            .lock()
+            .map_err(|e| anyhow!("Could not lock playbin: {e}"))?
+            .clone();
+        let filters = self.filters.clone();
+        while_stopped(playbin, move |playbin| {
+            if exclusive {
+                playbin.set_property("audio-filter", None::<&gstreamer::Element>);
+                playbin.set_property("volume", 1.0f64);
+            } else {
+                playbin.set_property("audio-filter", &filters);
+            }
+            playbin.set_property("audio-sink", sink.as_ref());
+            set_flag(playbin, "soft-volume", !exclusive)
+        })
+        .await
+    }
+
+    async fn output_devices(&self) -> Vec<String> {
+        output_devices()
+            .iter()
+            .map(|device| device.display_name().to_string())
+            .collect()
+    }
+
+    async fn set_output_device(&self, name: Option<String>) -> anyhow::Result<()> {
+        *self
+            .output_device
+            .lock()
+            .expect("Could not lock output device") = name;
+        let sink = if self.exclusive.load(Ordering::Relaxed) {
+            self.exclusive_sink()
+        } else {
+            self.output_sink()
+        };
+        let playbin = self
+            .playbin
+            .lock()
+            .map_err(|e| anyhow!("Could not lock playbin: {e}"))?
+            .clone();
+        while_stopped(playbin, move |playbin| {
+            playbin.set_property("audio-sink", sink.as_ref());
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_low_memory(&self, enabled: bool) -> anyhow::Result<()> {
+        self.low_memory.store(enabled, Ordering::Relaxed);
+        let playbin = self.playbin.lock().expect("Could not lock playbin");
+        // -1 leaves the amount buffered to playbin.
+        let (size, duration) = if enabled {
+            (
+                LOW_MEMORY_BUFFER_SIZE,
+                LOW_MEMORY_BUFFER_DURATION.nseconds() as i64,
+            )
+        } else {
+            (-1, -1)
+        };
+        playbin.set_property("buffer-size", size);
+        playbin.set_property("buffer-duration", duration);
         Ok(())
     }
 }
 
 impl GstBackend {
+    /// Move playbin to `state` on a thread of its own. Changing state blocks for as long as the
+    /// source or sink takes to answer, which on a dead mount or stream is far longer than the
+    /// player waits before giving up, so it must not block the player while it does.
+    async fn change_state(&self, state: gstreamer::State, name: &str) -> anyhow::Result<()> {
+        let playbin = self
+            .playbin
+            .lock()
+            .map_err(|e| anyhow!("Could not lock playbin: {e}"))?
+            .clone();
+        let name = name.to_string();
+        smol::unblock(move || {
+            playbin
+                .set_state(state)
+                .map_err(|e| anyhow!("Could not set playbin state to {name}: {e}"))?;
+            Ok(())
+        })
+        .await
+    }
+
     /// Constructor to create a new GstBackend instance.
     pub fn new() -> Result<GstBackend, anyhow::Error> {
         let playbin = gstreamer::ElementFactory::make("playbin")
@@ -187,17 +487,482 @@ impl GstBackend {
             .build()
             .map_err(|e| anyhow!("Failed to create playbin: {:?}", e))?;
 
+        let mono = gstreamer::ElementFactory::make("capsfilter")
+            .name("mono")
+            .property("caps", gstreamer::Caps::new_empty_simple("audio/x-raw"))
+            .build()
+            .map_err(|e| anyhow!("Failed to create mono filter: {:?}", e))?;
+        let panorama = gstreamer::ElementFactory::make("audiopanorama")
+            .name("panorama")
+            .build()
+            .map_err(|e| anyhow!("Failed to create panorama element: {:?}", e))?;
+        let level = gstreamer::ElementFactory::make("level")
+            .name("level")
+            .property("interval", ClockTime::from_mseconds(100).nseconds())
+            .property("post-messages", true)
+            .build()
+            .map_err(|e| anyhow!("Failed to create level element: {:?}", e))?;
+        let spectrum = gstreamer::ElementFactory::make("spectrum")
+            .name("spectrum")
+            .property("bands", SPECTRUM_BANDS)
+            .property("threshold", SPECTRUM_THRESHOLD_DB)
+            .property("interval", ClockTime::from_mseconds(33).nseconds())
+            .property("post-messages", false)
+            .build()
+            .map_err(|e| anyhow!("Failed to create spectrum element: {:?}", e))?;
+        let preamp = gstreamer::ElementFactory::make("volume")
+            .name("preamp")
+            .build()
+            .map_err(|e| anyhow!("Failed to create preamp element: {:?}", e))?;
+        let boost = gstreamer::ElementFactory::make("volume")
+            .name("boost")
+            .build()
+            .map_err(|e| anyhow!("Failed to create boost element: {:?}", e))?;
+        // Compressor with a ratio of 0 limits everything above the threshold. The soft knee
+        // eases into the limit instead of clipping when the pre-amp or boost pushes past it.
+        let limiter = gstreamer::ElementFactory::make("audiodynamic")
+            .name("limiter")
+            .property_from_str("mode", "compressor")
+            .property_from_str("characteristics", "soft-knee")
+            .property("threshold", 0.95f32)
+            .property("ratio", 0.0f32)
+            .build()
+            .map_err(|e| anyhow!("Failed to create limiter: {:?}", e))?;
+
+        let filters = build_filter_bin(&[
+            &mono, &panorama, &level, &preamp, &boost, &limiter, &spectrum,
+        ])?
+        .upcast::<gstreamer::Element>();
+        playbin.set_property("audio-filter", &filters);
+        // Without video support, music videos play as audio rather than opening a window.
+        #[cfg(feature = "video")]
+        playbin.set_property("video-sink", &crate::video::sink());
+        #[cfg(not(feature = "video"))]
+        set_flag(&playbin, "video", false)?;
+
         Ok(GstBackend {
             playbin: Arc::new(Mutex::new(playbin)),
+            preamp,
+            boost,
+            panorama,
+            mono,
+            spectrum,
+            filters,
+            skip_silence: AtomicBool::new(false),
+            low_memory: AtomicBool::new(false),
+            exclusive: AtomicBool::new(false),
+            output_device: Mutex::new(None),
+            silence: Mutex::new(SilenceState::default()),
+            stream_tags: Mutex::new(StreamTags::default()),
         })
     }
+
+    /// A sink for the chosen output device, or `None` to leave it to playbin, which plays to the
+    /// system default. The default is also used while the chosen device isn't connected.
+    fn output_sink(&self) -> Option<gstreamer::Element> {
+        let name = self
+            .output_device
+            .lock()
+            .expect("Could not lock output device")
+            .clone()?;
+        let sink = output_devices()
+            .into_iter()
+            .find(|device| device.display_name() == name.as_str())
+            .and_then(|device| device.create_element(None).ok());
+        if sink.is_none() {
+            eprintln!("Output device {name:?} not found, playing to the default");
+        }
+        sink
+    }
+
+    /// A sink that talks to the chosen output device directly, or to the first one found when
+    /// none is chosen. Falls back to [`Self::output_sink`] when the device can't be opened, as
+    /// when another program holds it.
+    fn exclusive_sink(&self) -> Option<gstreamer::Element> {
+        let name = self
+            .output_device
+            .lock()
+            .expect("Could not lock output device")
+            .clone();
+        match exclusive_sink(name.as_deref()) {
+            Ok(sink) => Some(sink),
+            Err(e) => {
+                eprintln!("Could not open the device for exclusive output: {e}");
+                self.output_sink()
+            }
+        }
+    }
+
+    /// Turn a bus message into a response for the player, handling the ones the backend
+    /// deals with itself.
+    fn handle_message(&self, msg: &gstreamer::Message) -> Option<Response> {
+        let playbin = self.playbin.lock().expect("Could not lock playbin");
+        match msg.view() {
+            MessageView::Tag(msg) => {
+                let tags = msg.tags();
+                let art = tags
+                    .get::<gstreamer::tags::Image>()
+                    .and_then(|image| embedded_art(image.get()));
+                if let Some(bytes) = art {
+                    let max_size = self
+                        .low_memory
+                        .load(Ordering::Relaxed)
+                        .then_some(LOW_MEMORY_ARTWORK);
+                    match retrieve_thumbnail(bytes, max_size) {
+                        Ok(thumbnail) => Some(Response::Thumbnail(thumbnail)),
+                        Err(e) => {
+                            eprintln!("Could not read the cover: {e}");
+                            None
+                        }
+                    }
+                } else if self.update_stream_tags(&tags) {
+                    self.stream_info(&playbin)
+                } else {
+                    None
+                }
+            }
+            MessageView::Element(e) => {
+                let structure = e.structure()?;
+                match structure.name().as_str() {
+                    "level" => {
+                        self.on_level(&playbin, structure);
+                        None
+                    }
+                    "spectrum" => spectrum_frame(structure),
+                    _ => None,
+                }
+            }
+            // Only the pipeline's own changes once they've settled, not every element's or every
+            // step on the way.
+            MessageView::StateChanged(change) => {
+                let own = msg
+                    .src()
+                    .is_some_and(|src| src == playbin.upcast_ref::<gstreamer::Object>());
+                (own && change.pending() == State::VoidPending)
+                    .then(|| Response::PipelineState(change.current()))
+            }
+            MessageView::AsyncDone(_) => self.stream_info(&playbin),
+            MessageView::Buffering(b) => Some(Response::Buffering(b.percent())),
+            MessageView::Eos(_) => Some(Response::Eos),
+            MessageView::StreamStart(_) => Some(Response::StreamStart),
+            MessageView::Error(e) if is_undecodable(e) => {
+                Some(Response::Undecodable(e.to_string()))
+            }
+            MessageView::Error(e) => Some(Response::Error(e.to_string())),
+            MessageView::Warning(w) => Some(Response::Warning(w.to_string())),
+            MessageView::Info(i) => Some(Response::Info(i.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Record codec and bitrate tags, returning whether anything changed.
+    fn update_stream_tags(&self, tags: &gstreamer::TagList) -> bool {
+        let mut stream_tags = self.stream_tags.lock().expect("Could not lock stream tags");
+        let codec = tags
+            .get::<gstreamer::tags::AudioCodec>()
+            .map(|v| v.get().to_string());
+        let bitrate = tags
+            .get::<gstreamer::tags::Bitrate>()
+            .or_else(|| tags.get::<gstreamer::tags::NominalBitrate>())
+            .map(|v| v.get());
+
+        let mut changed = false;
+        if codec.is_some() && codec != stream_tags.codec {
+            stream_tags.codec = codec;
+            changed = true;
+        }
+        if bitrate.is_some() && bitrate != stream_tags.bitrate {
+            stream_tags.bitrate = bitrate;
+            changed = true;
+        }
+        changed
+    }
+
+    /// Describe the audio format currently reaching the sink.
+    fn stream_info(&self, playbin: &gstreamer::Element) -> Option<Response> {
+        // What the decoder puts out, before conversion for the filters or the device.
+        let decoded = playbin
+            .emit_by_name::<Option<gstreamer::Pad>>("get-audio-pad", &[&0i32])?
+            .current_caps()?;
+        let structure = decoded.structure(0)?;
+        // Only an explicit sink's caps are known; playbin's own sink converts freely anyway.
+        let played = playbin
+            .property::<Option<gstreamer::Element>>("audio-sink")
+            .and_then(|sink| sink.static_pad("sink"))
+            .and_then(|pad| pad.current_caps());
+        let bit_perfect = played
+            .as_ref()
+            .and_then(|caps| caps.structure(0))
+            .is_some_and(|played| same_format(structure, played));
+        let bit_depth = structure
+            .get::<&str>("format")
+            .ok()
+            .and_then(|format| {
+                format
+                    .chars()
+                    .filter(|c| c.is_ascii_digit())
+                    .collect::<String>()
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or(0);
+        let stream_tags = self.stream_tags.lock().expect("Could not lock stream tags");
+
+        Some(Response::StreamInfo {
+            codec: stream_tags
+                .codec
+                .clone()
+                .unwrap_or_else(|| "Unknown".to_string()),
+            bitrate: stream_tags.bitrate.unwrap_or(0),
+            sample_rate: structure.get::<i32>("rate").unwrap_or(0) as u32,
+            channels: structure.get::<i32>("channels").unwrap_or(0) as u32,
+            bit_depth,
+            bit_perfect,
+        })
+    }
+
+    /// Fast-forward through silent stretches while skip-silence is on, returning to normal speed
+    /// as soon as the level meter picks up sound again.
+    fn on_level(&self, playbin: &gstreamer::Element, structure: &gstreamer::StructureRef) {
+        if !self.skip_silence.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(rms) = structure.get::<gstreamer::glib::ValueArray>("rms") else {
+            return;
+        };
+        let loudest = rms
+            .iter()
+            .filter_map(|value| value.get::<f64>().ok())
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        let mut silence = self.silence.lock().expect("Could not lock silence state");
+        if loudest < SILENCE_THRESHOLD_DB {
+            silence.quiet_reports += 1;
+            if !silence.skipping && silence.quiet_reports >= SILENCE_MIN_REPORTS {
+                silence.skipping = set_rate(playbin, SILENCE_SKIP_RATE).is_ok();
+            }
+        } else {
+            silence.quiet_reports = 0;
+            if silence.skipping {
+                silence.skipping = set_rate(playbin, 1.0).is_err();
+            }
+        }
+    }
+}
+
+/// Whether `error` says the track's own data can't be read, as a demuxer or decoder reports
+/// for a damaged file, rather than something going wrong with the output or the system.
+fn is_undecodable(error: &gstreamer::message::Error) -> bool {
+    let from_sink = error
+        .src()
+        .and_then(|src| src.downcast_ref::<gstreamer::Element>())
+        .is_some_and(|src| src.element_flags().contains(gstreamer::ElementFlags::SINK));
+    !from_sink
+        && matches!(
+            error.error().kind::<gstreamer::StreamError>(),
+            Some(
+                gstreamer::StreamError::TypeNotFound
+                    | gstreamer::StreamError::WrongType
+                    | gstreamer::StreamError::CodecNotFound
+                    | gstreamer::StreamError::Decode
+                    | gstreamer::StreamError::Demux
+                    | gstreamer::StreamError::Format
+            )
+        )
+}
+
+/// Whether two raw audio formats match in sample format, rate and channel count.
+fn same_format(a: &gstreamer::StructureRef, b: &gstreamer::StructureRef) -> bool {
+    a.get::<&str>("format").ok() == b.get::<&str>("format").ok()
+        && a.get::<i32>("rate").ok() == b.get::<i32>("rate").ok()
+        && a.get::<i32>("channels").ok() == b.get::<i32>("channels").ok()
+}
+
+/// Lyrics in a tag message, which can carry the cover art and other tags as well.
+fn embedded_lyrics(msg: &gstreamer::Message) -> Option<Response> {
+    let MessageView::Tag(msg) = msg.view() else {
+        return None;
+    };
+    let lyrics = msg.tags().get::<gstreamer::tags::Lyrics>()?;
+    Some(Response::EmbeddedLyrics(lyrics.get().to_string()))
+}
+
+/// Scale the magnitudes of a spectrum message to 0.0..=1.0 per band.
+fn spectrum_frame(structure: &gstreamer::StructureRef) -> Option<Response> {
+    let magnitudes = structure.get::<gstreamer::List>("magnitude").ok()?;
+    let floor = SPECTRUM_THRESHOLD_DB as f32;
+    let frame = magnitudes
+        .iter()
+        .filter_map(|value| value.get::<f32>().ok())
+        .map(|db| ((db - floor) / -floor).clamp(0.0, 1.0))
+        .collect();
+    Some(Response::SpectrumFrame(frame))
+}
+
+/// Output sink that talks to a device directly, without software mixing or resampling. Uses the
+/// device named `name` when it is one the platform's direct sink can drive, otherwise the first
+/// such device found, and checks it can be opened before handing it over.
+fn exclusive_sink(name: Option<&str>) -> anyhow::Result<gstreamer::Element> {
+    let factory = if cfg!(target_os = "windows") {
+        "wasapisink"
+    } else if cfg!(target_os = "macos") {
+        "osxaudiosink"
+    } else {
+        "alsasink"
+    };
+    let direct: Vec<(String, gstreamer::Element)> = output_devices()
+        .into_iter()
+        .filter_map(|device| {
+            let sink = device.create_element(None).ok()?;
+            let is_direct = sink.factory().is_some_and(|f| f.name() == factory);
+            is_direct.then(|| (device.display_name().to_string(), sink))
+        })
+        .collect();
+    let chosen = direct
+        .iter()
+        .position(|(device, _)| Some(device.as_str()) == name)
+        .unwrap_or(0);
+    let sink = match direct.into_iter().nth(chosen) {
+        Some((_, sink)) => sink,
+        None => gstreamer::ElementFactory::make(factory)
+            .build()
+            .map_err(|e| anyhow!("Failed to create exclusive audio sink: {:?}", e))?,
+    };
+    if sink.find_property("exclusive").is_some() {
+        sink.set_property("exclusive", true);
+    }
+
+    sink.set_state(State::Ready)
+        .map_err(|e| anyhow!("Could not open the output device: {e}"))?;
+    let _ = sink.set_state(State::Null);
+    Ok(sink)
+}
+
+/// The system's audio output devices.
+fn output_devices() -> Vec<gstreamer::Device> {
+    let monitor = gstreamer::DeviceMonitor::new();
+    monitor.add_filter(Some("Audio/Sink"), None);
+    if monitor.start().is_err() {
+        return vec![];
+    }
+    let devices = monitor.devices().into_iter().collect();
+    monitor.stop();
+    devices
+}
+
+/// Make `change` to `playbin` while it's stopped, as swapping its sink or filter chain needs,
+/// then carry on from where playback was. Runs on a thread of its own, like
+/// [`GstBackend::change_state`], since stopping and restarting wait on the source and sink.
+async fn while_stopped(
+    playbin: gstreamer::Element,
+    change: impl FnOnce(&gstreamer::Element) -> anyhow::Result<()> + Send + 'static,
+) -> anyhow::Result<()> {
+    smol::unblock(move || {
+        let state = playbin.current_state();
+        let position = playbin.query_position::<ClockTime>();
+        playbin
+            .set_state(State::Ready)
+            .map_err(|e| anyhow!("Could not set playbin state to ready: {e}"))?;
+        change(&playbin)?;
+        if state > State::Ready {
+            playbin
+                .set_state(state)
+                .map_err(|e| anyhow!("Could not restore playbin state: {e}"))?;
+            let _ = playbin.state(ClockTime::from_seconds(2));
+            if let Some(position) = position {
+                let _ = playbin.seek_simple(SeekFlags::FLUSH | SeekFlags::KEY_UNIT, position);
+            }
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Toggle one of playbin's flags by nick, e.g. `soft-volume`, which inserts a software volume
+/// element.
+fn set_flag(playbin: &gstreamer::Element, nick: &str, enabled: bool) -> anyhow::Result<()> {
+    let flags = playbin.property_value("flags");
+    let flags_class = gstreamer::glib::FlagsClass::with_type(flags.type_())
+        .ok_or_else(|| anyhow!("playbin flags are not a flags type"))?;
+    let builder = flags_class
+        .builder_with_value(flags)
+        .ok_or_else(|| anyhow!("Could not read playbin flags"))?;
+    let flags = if enabled {
+        builder.set_by_nick(nick)
+    } else {
+        builder.unset_by_nick(nick)
+    }
+    .build()
+    .ok_or_else(|| anyhow!("Could not build playbin flags"))?;
+    playbin.set_property_from_value("flags", &flags);
+    Ok(())
+}
+
+/// Change the playback rate while keeping the current position.
+fn set_rate(playbin: &gstreamer::Element, rate: f64) -> anyhow::Result<()> {
+    let position = playbin
+        .query_position::<ClockTime>()
+        .ok_or_else(|| anyhow!("Could not query position"))?;
+    playbin.seek(
+        rate,
+        SeekFlags::FLUSH | SeekFlags::ACCURATE,
+        gstreamer::SeekType::Set,
+        position,
+        gstreamer::SeekType::None,
+        ClockTime::NONE,
+    )?;
+    Ok(())
 }
 
-fn retrieve_thumbnail(bytes: Box<[u8]>) -> anyhow::Result<Thumbnail> {
-    let img = ImageReader::new(Cursor::new(bytes.clone()))
+/// Chain `elements` into a single bin usable as playbin's `audio-filter`, with converters on
+/// either side so each element can negotiate the format it needs.
+fn build_filter_bin(elements: &[&gstreamer::Element]) -> anyhow::Result<gstreamer::Bin> {
+    let bin = gstreamer::Bin::with_name("audio-filters");
+    let convert_in = gstreamer::ElementFactory::make("audioconvert").build()?;
+    let convert_out = gstreamer::ElementFactory::make("audioconvert").build()?;
+
+    let mut chain = vec![&convert_in];
+    chain.extend_from_slice(elements);
+    chain.push(&convert_out);
+
+    bin.add_many(chain.iter().copied())?;
+    gstreamer::Element::link_many(chain.iter().copied())?;
+
+    let sink_pad = convert_in
+        .static_pad("sink")
+        .ok_or_else(|| anyhow!("audioconvert has no sink pad"))?;
+    let src_pad = convert_out
+        .static_pad("src")
+        .ok_or_else(|| anyhow!("audioconvert has no src pad"))?;
+    bin.add_pad(&gstreamer::GhostPad::with_target(&sink_pad)?)?;
+    bin.add_pad(&gstreamer::GhostPad::with_target(&src_pad)?)?;
+
+    Ok(bin)
+}
+
+/// The raw bytes of an embedded cover, or `None` when the sample carries no readable buffer.
+fn embedded_art(sample: &gstreamer::Sample) -> Option<Box<[u8]>> {
+    let buffer = sample.buffer()?.map_readable().ok()?;
+    Some(buffer.as_bytes().into())
+}
+
+/// Decode embedded artwork, scaled down to fit `max_size` across if given.
+fn retrieve_thumbnail(bytes: Box<[u8]>, max_size: Option<u32>) -> anyhow::Result<Thumbnail> {
+    let mut img = ImageReader::new(Cursor::new(bytes.clone()))
         .with_guessed_format()?
         .decode()?
         .into_rgba8();
+    if let Some(max_size) = max_size {
+        let (width, height) = img.dimensions();
+        if width.max(height) > max_size {
+            let scale = max_size as f64 / width.max(height) as f64;
+            img = image::imageops::thumbnail(
+                &img,
+                ((width as f64 * scale) as u32).max(1),
+                ((height as f64 * scale) as u32).max(1),
+            );
+        }
+    }
     let (width, height) = img.dimensions();
     let mut bgra_image = RgbaImage::new(width, height);
     for (x, y, pixel) in img.enumerate_pixels() {
@@ -232,3 +997,197 @@ fn retrieve_small_thumbnail(bytes: Box<[u8]>) -> anyhow::Result<Thumbnail> {
         height: 64,
     })
 }
+
+/// A pipeline decoding `uri` into `elements`, linked in order. The URI is set as a property
+/// rather than spliced into a launch line, so a quote or `!` in a path can't add elements.
+fn decode_pipeline(
+    uri: &str,
+    elements: &[gstreamer::Element],
+) -> anyhow::Result<gstreamer::Element> {
+    let first = elements
+        .first()
+        .ok_or_else(|| anyhow!("Nothing to decode into"))?;
+    let source = gstreamer::ElementFactory::make("uridecodebin")
+        .property("uri", uri)
+        .build()?;
+    let pipeline = gstreamer::Pipeline::new();
+    pipeline.add(&source)?;
+    pipeline.add_many(elements)?;
+    gstreamer::Element::link_many(elements)?;
+
+    // uridecodebin only exposes its pads once the stream is typed, so the audio one is linked
+    // as it appears. The element is held weakly to keep the pipeline from owning itself.
+    let first = first.downgrade();
+    source.connect_pad_added(move |_, pad| {
+        let Some(sink_pad) = first.upgrade().and_then(|first| first.static_pad("sink")) else {
+            return;
+        };
+        let is_audio = pad
+            .current_caps()
+            .unwrap_or_else(|| pad.query_caps(None))
+            .structure(0)
+            .is_some_and(|s| s.name().starts_with("audio/"));
+        if is_audio && !sink_pad.is_linked() {
+            if let Err(e) = pad.link(&sink_pad) {
+                eprintln!("Could not link decoded audio: {e}");
+            }
+        }
+    });
+    Ok(pipeline.upcast())
+}
+
+/// Decode the first buffer of `pipeline`, then seek near the end and decode again so truncated
+/// files are caught as well as broken headers.
+fn probe_pipeline(pipeline: &gstreamer::Element) -> anyhow::Result<()> {
+    let bus = pipeline
+        .bus()
+        .ok_or_else(|| anyhow!("Probe pipeline has no bus"))?;
+    pipeline
+        .set_state(State::Paused)
+        .map_err(|e| anyhow!("Could not start decoding: {e}"))?;
+    wait_for_preroll(&bus)?;
+
+    if let Some(duration) = pipeline.query_duration::<ClockTime>() {
+        let end = duration.saturating_sub(ClockTime::from_seconds(1));
+        if end > ClockTime::ZERO {
+            pipeline
+                .seek_simple(SeekFlags::FLUSH | SeekFlags::ACCURATE, end)
+                .map_err(|e| anyhow!("Could not seek to the end: {e}"))?;
+            wait_for_preroll(&bus)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `pipeline` to the end as fast as it decodes, collecting the loudest channel's peak from
+/// each level message.
+fn collect_peaks(pipeline: &gstreamer::Element, points: usize) -> anyhow::Result<Vec<f32>> {
+    let bus = pipeline
+        .bus()
+        .ok_or_else(|| anyhow!("Waveform pipeline has no bus"))?;
+    pipeline
+        .set_state(State::Playing)
+        .map_err(|e| anyhow!("Could not start decoding: {e}"))?;
+
+    let mut peaks = Vec::with_capacity(points);
+    loop {
+        let Some(msg) = bus.timed_pop_filtered(
+            PROBE_TIMEOUT,
+            &[
+                gstreamer::MessageType::Element,
+                gstreamer::MessageType::Eos,
+                gstreamer::MessageType::Error,
+            ],
+        ) else {
+            return Err(anyhow!("Timed out decoding"));
+        };
+        match msg.view() {
+            MessageView::Element(e) => {
+                let Some(structure) = e.structure().filter(|s| s.name() == "level") else {
+                    continue;
+                };
+                let Ok(peak) = structure.get::<gstreamer::glib::ValueArray>("peak") else {
+                    continue;
+                };
+                let loudest = peak
+                    .iter()
+                    .filter_map(|value| value.get::<f64>().ok())
+                    .fold(f64::NEG_INFINITY, f64::max);
+                peaks.push(10f64.powf(loudest / 20.0).clamp(0.0, 1.0) as f32);
+            }
+            MessageView::Eos(_) => break,
+            MessageView::Error(err) => return Err(anyhow!("{}", err.error())),
+            _ => {}
+        }
+    }
+    peaks.resize(points, 0.0);
+    Ok(peaks)
+}
+
+/// Run `pipeline` to the end as fast as it decodes, feeding what reaches its sink to a
+/// [`Meter`].
+fn measure_loudness(pipeline: &gstreamer::Element) -> anyhow::Result<Loudness> {
+    let sink = pipeline
+        .downcast_ref::<gstreamer::Bin>()
+        .and_then(|bin| bin.by_name("sink"))
+        .ok_or_else(|| anyhow!("Loudness pipeline has no sink"))?;
+    let pad = sink
+        .static_pad("sink")
+        .ok_or_else(|| anyhow!("Loudness sink has no pad"))?;
+    let meter: Arc<Mutex<Option<Meter>>> = Arc::new(Mutex::new(None));
+    let probe_meter = meter.clone();
+    // Nothing is posted until the end, so buffers are counted to tell a long file from a stuck
+    // one.
+    let buffers = Arc::new(AtomicU64::new(0));
+    let probe_buffers = buffers.clone();
+    pad.add_probe(gstreamer::PadProbeType::BUFFER, move |pad, info| {
+        let Some(Ok(map)) = info.buffer().map(|buffer| buffer.map_readable()) else {
+            return gstreamer::PadProbeReturn::Ok;
+        };
+        probe_buffers.fetch_add(1, Ordering::Relaxed);
+        let samples: Vec<f32> = map
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        let mut meter = probe_meter.lock().expect("Could not lock meter");
+        meter
+            .get_or_insert_with(|| {
+                let channels = pad
+                    .current_caps()
+                    .and_then(|caps| caps.structure(0)?.get::<i32>("channels").ok())
+                    .unwrap_or(2);
+                Meter::new(channels.max(1) as usize)
+            })
+            .push(&samples);
+        gstreamer::PadProbeReturn::Ok
+    });
+
+    let bus = pipeline
+        .bus()
+        .ok_or_else(|| anyhow!("Loudness pipeline has no bus"))?;
+    pipeline
+        .set_state(State::Playing)
+        .map_err(|e| anyhow!("Could not start decoding: {e}"))?;
+    let mut seen = 0;
+    loop {
+        let Some(msg) = bus.timed_pop_filtered(
+            PROBE_TIMEOUT,
+            &[gstreamer::MessageType::Eos, gstreamer::MessageType::Error],
+        ) else {
+            let decoded = buffers.load(Ordering::Relaxed);
+            if decoded == seen {
+                return Err(anyhow!("Timed out decoding"));
+            }
+            seen = decoded;
+            continue;
+        };
+        match msg.view() {
+            MessageView::Eos(_) => break,
+            MessageView::Error(err) => return Err(anyhow!("{}", err.error())),
+            _ => {}
+        }
+    }
+    let meter = meter.lock().expect("Could not lock meter").take();
+    meter
+        .and_then(Meter::finish)
+        .ok_or_else(|| anyhow!("No audio to measure"))
+}
+
+fn wait_for_preroll(bus: &gstreamer::Bus) -> anyhow::Result<()> {
+    loop {
+        let Some(msg) = bus.timed_pop_filtered(
+            PROBE_TIMEOUT,
+            &[
+                gstreamer::MessageType::AsyncDone,
+                gstreamer::MessageType::Error,
+            ],
+        ) else {
+            return Err(anyhow!("Timed out decoding"));
+        };
+        match msg.view() {
+            MessageView::AsyncDone(_) => return Ok(()),
+            MessageView::Error(err) => return Err(anyhow!("{}", err.error())),
+            _ => {}
+        }
+    }
+}
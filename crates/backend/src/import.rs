@@ -0,0 +1,249 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::uri;
+
+/// Extensions of the playlist files that can be imported.
+pub const PLAYLIST_EXTENSIONS: [&str; 4] = ["m3u", "m3u8", "pls", "xspf"];
+
+/// One track of an imported playlist: where it is, and whatever the playlist says about it,
+/// which stands in for the tags when the file can't be read.
+#[derive(Clone, Debug, Default)]
+pub struct Entry {
+    pub location: Location,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// Seconds.
+    pub duration: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Location {
+    File(PathBuf),
+    /// A stream or any other non-file URL.
+    Url(String),
+}
+
+impl Default for Location {
+    fn default() -> Self {
+        Location::File(PathBuf::new())
+    }
+}
+
+/// The entries of the M3U, M3U8, PLS or XSPF playlist at `path`, in order. Relative
+/// locations are taken from the playlist's folder.
+pub fn read(path: &Path) -> anyhow::Result<Vec<Entry>> {
+    let text = decode(&fs::read(path)?);
+    let base = path.parent().unwrap_or(Path::new(""));
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let start = text.trim_start();
+
+    let entries =
+        if extension == "xspf" || start.starts_with("<?xml") || start.starts_with("<playlist") {
+            parse_xspf(&text, base)
+        } else if extension == "pls" || start.starts_with("[playlist]") {
+            parse_pls(&text, base)
+        } else {
+            parse_m3u(&text, base)
+        };
+    if entries.is_empty() {
+        return Err(anyhow!("{} has no tracks", path.display()));
+    }
+    Ok(entries)
+}
+
+/// Playlist text as UTF-8, which M3U8, PLS and XSPF use, falling back to Latin-1 for the old
+/// M3U files written before that was settled on.
+fn decode(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+fn parse_m3u(text: &str, base: &Path) -> Vec<Entry> {
+    let mut entries = vec![];
+    let mut info: Option<Entry> = None;
+    for line in text.lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // `#EXTINF:<seconds> <attributes>,<artist> - <title>`
+            let (head, name) = extinf.split_once(',').unwrap_or((extinf, ""));
+            let seconds = head
+                .split_whitespace()
+                .next()
+                .and_then(|s| s.parse::<i64>().ok());
+            let (artist, title) = match name.split_once(" - ") {
+                Some((artist, title)) => (Some(artist.trim().to_string()), title.trim()),
+                None => (None, name.trim()),
+            };
+            info = Some(Entry {
+                title: (!title.is_empty()).then(|| title.to_string()),
+                artist,
+                // -1 marks streams of unknown length.
+                duration: seconds.filter(|&s| s > 0).map(|s| s as u64),
+                ..Entry::default()
+            });
+        } else if let Some(album) = line.strip_prefix("#EXTALB:") {
+            info.get_or_insert_with(Entry::default).album = Some(album.trim().to_string());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let mut entry = info.take().unwrap_or_default();
+            entry.location = locate(line, base);
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+fn parse_pls(text: &str, base: &Path) -> Vec<Entry> {
+    let mut entries: Vec<(u32, Entry)> = vec![];
+    for line in text.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let field = ["file", "title", "length"]
+            .into_iter()
+            .find(|field| key.starts_with(field));
+        let Some(field) = field else {
+            continue;
+        };
+        let Ok(number) = key[field.len()..].parse::<u32>() else {
+            continue;
+        };
+        let index = match entries.iter().position(|(n, _)| *n == number) {
+            Some(index) => index,
+            None => {
+                entries.push((number, Entry::default()));
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[index].1;
+        let value = value.trim();
+        match field {
+            "file" => entry.location = locate(value, base),
+            "title" => entry.title = Some(value.to_string()),
+            _ => {
+                entry.duration = value
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|&s| s > 0)
+                    .map(|s| s as u64)
+            }
+        }
+    }
+    entries.sort_by_key(|(number, _)| *number);
+    entries
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .filter(
+            |entry| !matches!(&entry.location, Location::File(path) if path.as_os_str().is_empty()),
+        )
+        .collect()
+}
+
+fn parse_xspf(text: &str, base: &Path) -> Vec<Entry> {
+    text.split("<track>")
+        .skip(1)
+        .filter_map(|track| {
+            let track = track.split("</track>").next().unwrap_or(track);
+            let location = element(track, "location")?;
+            // Locations are URIs, and relative ones still have their percent-encoding.
+            let location = if location.contains("://") || location.starts_with("file:") {
+                locate(&location, base)
+            } else {
+                locate(&uri::decode(&location), base)
+            };
+            Some(Entry {
+                location,
+                title: element(track, "title"),
+                artist: element(track, "creator"),
+                album: element(track, "album"),
+                duration: element(track, "duration")
+                    .and_then(|ms| ms.parse::<u64>().ok())
+                    .map(|ms| ms / 1000),
+            })
+        })
+        .collect()
+}
+
+/// Text of the first `<name>` element in `xml`, with entities replaced.
+fn element(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{name}>");
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&format!("</{name}>"))?;
+    let text = unescape(xml[start..end].trim());
+    (!text.is_empty()).then_some(text)
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match replacement {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Where a playlist line points: a URL, a `file:` URI, or a path, relative ones taken from
+/// `base`. Playlists written on Windows separate folders with backslashes.
+fn locate(location: &str, base: &Path) -> Location {
+    if location.contains("://") || location.starts_with("file:") {
+        return match uri::to_path(location) {
+            Some(path) => Location::File(path),
+            None => Location::Url(location.to_string()),
+        };
+    }
+    let location = if cfg!(windows) {
+        location.to_string()
+    } else {
+        location.replace('\\', "/")
+    };
+    let path = Path::new(&location);
+    if path.is_absolute() || is_drive_path(&location) {
+        Location::File(path.to_path_buf())
+    } else {
+        Location::File(base.join(path))
+    }
+}
+
+fn is_drive_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
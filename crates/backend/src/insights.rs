@@ -0,0 +1,112 @@
+//! Counts of which views, commands and settings get used, kept in `insights.toml` beside the
+//! settings file for the user to read. Nothing is recorded unless [`Settings::insights`] is
+//! turned on, and nothing here is ever sent anywhere.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Write as _},
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Insights {
+    /// When counting started, in seconds since the Unix epoch.
+    pub since: u64,
+    /// Times each view in the main area was opened, by name.
+    pub views: BTreeMap<String, u64>,
+    /// Times each player command was given, by name.
+    pub commands: BTreeMap<String, u64>,
+    /// Times each setting was changed, by its name in the settings file.
+    pub settings: BTreeMap<String, u64>,
+}
+
+impl Insights {
+    pub fn get_insights_file() -> Option<PathBuf> {
+        Settings::get_settings_file().map(|file| file.with_file_name("insights.toml"))
+    }
+
+    /// Read the counts so far, starting afresh when there are none or they can't be read.
+    pub fn load() -> Insights {
+        let loaded = Self::get_insights_file()
+            .and_then(|file_path| fs::read_to_string(file_path).ok())
+            .map(|contents| toml::from_str::<Insights>(&contents));
+        match loaded {
+            Some(Ok(insights)) => insights,
+            Some(Err(e)) => {
+                eprintln!("Failed to parse insights: {}", e);
+                Insights::new()
+            }
+            None => Insights::new(),
+        }
+    }
+
+    fn new() -> Insights {
+        Insights {
+            since: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            ..Default::default()
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_insights_file() {
+            let serialized = toml::to_string_pretty(self)?;
+            let mut file = File::create(file_path)?;
+            file.write_all(serialized.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn record_view(&mut self, view: &str) {
+        *self.views.entry(view.to_string()).or_default() += 1;
+    }
+
+    pub fn record_command(&mut self, command: String) {
+        *self.commands.entry(command).or_default() += 1;
+    }
+
+    pub fn record_settings(&mut self, settings: &[String]) {
+        for setting in settings {
+            *self.settings.entry(setting.clone()).or_default() += 1;
+        }
+    }
+
+    /// Forget everything counted and start again from now.
+    pub fn clear(&mut self) {
+        *self = Insights::new();
+    }
+}
+
+/// The variant name at the start of `value`'s `Debug` output. Formatting stops there, so large
+/// fields are never written out.
+pub fn variant_name(value: &impl Debug) -> String {
+    struct Name(String);
+
+    impl fmt::Write for Name {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            match s.find(|c: char| !c.is_alphanumeric() && c != '_') {
+                Some(end) => {
+                    self.0.push_str(&s[..end]);
+                    Err(fmt::Error)
+                }
+                None => {
+                    self.0.push_str(s);
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    let mut name = Name(String::new());
+    let _ = write!(name, "{value:?}");
+    name.0
+}
@@ -0,0 +1,297 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use directories::UserDirs;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{file_manager, link::PlayLink, playback::Origin, player::Controller};
+
+/// How long a query waits for the player before giving up.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Request line that keeps the connection open and streams a status line on every change.
+const FOLLOW: &str = "follow";
+/// Start of a request line handing the app a [`PlayLink`] to play, as a second launch does
+/// with the link it was opened with.
+const OPEN: &str = "open ";
+/// Start of a request line telling the app to do something, answered with `ok`.
+const CONTROL: &str = "control ";
+
+/// What a script can ask the running app for.
+#[derive(Clone, Copy, Debug)]
+pub enum Query {
+    Status,
+    Queue,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "status" => Some(Query::Status),
+            "queue" => Some(Query::Queue),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Query::Status => "status",
+            Query::Queue => "queue",
+        }
+    }
+}
+
+/// What a script can tell the running app to do.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Control {
+    Play,
+    Pause,
+    Next,
+    Previous,
+    /// Add a file or folder to the end of the queue. Relative paths are the caller's, so they
+    /// have to be made absolute before sending.
+    Enqueue(PathBuf),
+    /// Play these files in place of the queue, as a file manager opening them does.
+    Open(Vec<PathBuf>),
+}
+
+impl Control {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some(path) = text.strip_prefix("enqueue ") {
+            return Some(Control::Enqueue(PathBuf::from(path)));
+        }
+        if let Some(paths) = text.strip_prefix("open-files ") {
+            return serde_json::from_str(paths).ok().map(Control::Open);
+        }
+        match text {
+            "play" => Some(Control::Play),
+            "pause" => Some(Control::Pause),
+            "next" => Some(Control::Next),
+            "previous" => Some(Control::Previous),
+            _ => None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        match self {
+            Control::Play => "play".to_string(),
+            Control::Pause => "pause".to_string(),
+            Control::Next => "next".to_string(),
+            Control::Previous => "previous".to_string(),
+            Control::Enqueue(path) => format!("enqueue {}", path.display()),
+            // As JSON, since there's no character paths can't have.
+            Control::Open(paths) => format!(
+                "open-files {}",
+                serde_json::to_string(paths).unwrap_or_default()
+            ),
+        }
+    }
+
+    fn apply(self, controller: &Controller) {
+        match self {
+            Control::Play => controller.play(),
+            Control::Pause => controller.pause(),
+            Control::Next => controller.next(),
+            Control::Previous => controller.prev(),
+            Control::Enqueue(path) => {
+                controller.enqueue_paths(vec![path], Origin::CommandLine, false)
+            }
+            Control::Open(paths) => controller.play_files(paths, Origin::Remote),
+        }
+    }
+}
+
+/// Answer to [`Query::Status`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Status {
+    /// "playing", "paused" or "stopped".
+    pub state: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub playlist: String,
+    /// Seconds.
+    pub position: u64,
+    /// Seconds.
+    pub duration: u64,
+    pub volume: f64,
+    pub shuffle: bool,
+    pub repeat: bool,
+    /// Position of the current track in the queue, starting at 0.
+    pub index: usize,
+    pub queue_length: usize,
+}
+
+/// One entry of the answer to [`Query::Queue`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub duration: u64,
+    pub uri: String,
+    pub current: bool,
+}
+
+fn port_file() -> Option<PathBuf> {
+    let user_dirs = UserDirs::new()?;
+    Some(
+        user_dirs
+            .audio_dir()
+            .unwrap_or(user_dirs.home_dir())
+            .join("Kagi")
+            .join("ipc.port"),
+    )
+}
+
+/// Answer `reyvr-cli` and `reyvr` subcommands on a loopback socket. The port is written to
+/// `Kagi/ipc.port` so they can find it, with a token made up for this run; each connection
+/// sends the token, then one query line and gets one JSON line back, `ok` for a control, or a
+/// line per status change for `follow`. Only the owner can read the port file, so other users
+/// can't learn the token and drive the app.
+pub fn serve(controller: Controller) -> anyhow::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let path = port_file().ok_or(anyhow!("Could not find home directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let token = format!("{:032x}", rand::rng().random::<u128>());
+    let port = listener.local_addr()?.port();
+    file_manager::write_private(&path, format!("{port} {token}").as_bytes())?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let controller = controller.clone();
+            let token = token.clone();
+            // Followers hold their connection open, so each one gets its own thread.
+            thread::spawn(move || {
+                if let Err(e) = answer(stream, &controller, &token) {
+                    eprintln!("Could not answer query: {e}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn answer(stream: TcpStream, controller: &Controller, token: &str) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(QUERY_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if line.trim() != token {
+        return Err(anyhow!("Turned away a connection without the token"));
+    }
+    line.clear();
+    reader.read_line(&mut line)?;
+    if line.trim() == FOLLOW {
+        return stream_status(stream, controller);
+    }
+    if let Some(url) = line.strip_prefix(OPEN) {
+        controller.open_link(PlayLink::parse(url.trim())?, Origin::Remote);
+        let mut stream = stream;
+        stream.write_all(b"ok\n")?;
+        return Ok(());
+    }
+    if let Some(control) = line.strip_prefix(CONTROL) {
+        Control::parse(control)
+            .ok_or(anyhow!("Unknown control {:?}", control.trim()))?
+            .apply(controller);
+        let mut stream = stream;
+        stream.write_all(b"ok\n")?;
+        return Ok(());
+    }
+    let query = Query::parse(&line).ok_or(anyhow!("Unknown query {:?}", line.trim()))?;
+
+    let reply = controller.query(query);
+    let response = smol::block_on(smol::future::or(
+        async { reply.recv().await.map_err(|e| anyhow!("{e}")) },
+        async {
+            smol::Timer::after(QUERY_TIMEOUT).await;
+            Err(anyhow!("Player did not answer"))
+        },
+    ))?;
+    let mut stream = stream;
+    stream.write_all(response.as_bytes())?;
+    stream.write_all(b"\n")?;
+    Ok(())
+}
+
+fn stream_status(mut stream: TcpStream, controller: &Controller) -> anyhow::Result<()> {
+    let updates = controller.subscribe();
+    while let Ok(status) = updates.recv_blocking() {
+        // Fails once the follower disconnects, which drops the subscription.
+        stream.write_all(status.as_bytes())?;
+        stream.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Connect to the running app and send the token, ready for a request line.
+fn connect() -> anyhow::Result<TcpStream> {
+    let path = port_file().ok_or(anyhow!("Could not find home directory"))?;
+    let contents = fs::read_to_string(&path).map_err(|_| anyhow!("Kagi is not running"))?;
+    // A port alone is from a version before the token, which this can't talk to.
+    let (port, token) = contents
+        .trim()
+        .split_once(' ')
+        .ok_or(anyhow!("Kagi is not running"))?;
+    let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port.parse::<u16>()?))
+        .map_err(|_| anyhow!("Kagi is not running"))?;
+    stream.write_all(format!("{token}\n").as_bytes())?;
+    Ok(stream)
+}
+
+/// Send `query` to the running app and return its JSON answer.
+pub fn request(query: Query) -> anyhow::Result<String> {
+    let mut stream = connect()?;
+    stream.set_read_timeout(Some(QUERY_TIMEOUT * 2))?;
+    stream.write_all(format!("{}\n", query.as_str()).as_bytes())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+/// Hand `link` to the running app, which plays it and comes to the front. Fails when the app
+/// isn't running, so the caller can start it instead.
+pub fn open(link: &PlayLink) -> anyhow::Result<()> {
+    let mut stream = connect()?;
+    stream.set_read_timeout(Some(QUERY_TIMEOUT))?;
+    stream.write_all(format!("{OPEN}{}\n", link.to_url()).as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(anyhow!("Kagi did not take the link"))
+    }
+}
+
+/// Tell the running app to do `control`. Fails when the app isn't running.
+pub fn control(control: &Control) -> anyhow::Result<()> {
+    let mut stream = connect()?;
+    stream.set_read_timeout(Some(QUERY_TIMEOUT))?;
+    stream.write_all(format!("{CONTROL}{}\n", control.to_line()).as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(anyhow!("Kagi did not take {:?}", control.to_line()))
+    }
+}
+
+/// Stream JSON status lines from the running app as they change.
+pub fn follow() -> anyhow::Result<impl Iterator<Item = String>> {
+    let mut stream = connect()?;
+    stream.write_all(format!("{FOLLOW}\n").as_bytes())?;
+    Ok(BufReader::new(stream).lines().map_while(Result::ok))
+}
@@ -0,0 +1,58 @@
+//! Key bindings changed from the defaults, read from `keymap.toml` beside the settings file:
+//!
+//! ```toml
+//! ChangeState = "space"
+//! ToggleMono = "ctrl-alt-m"
+//! ListenLater = ""
+//! ```
+//!
+//! Actions are named as in the app's action list. Ones left out keep their default keys, and
+//! an empty string unbinds one.
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::anyhow;
+
+use crate::settings::Settings;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Keymap {
+    /// Keystrokes by action name.
+    pub bindings: BTreeMap<String, String>,
+}
+
+impl Keymap {
+    pub fn get_keymap_file() -> Option<PathBuf> {
+        Settings::get_settings_file().map(|file| file.with_file_name("keymap.toml"))
+    }
+
+    /// Read the keymap, which is empty while there is no file.
+    pub fn load() -> anyhow::Result<Keymap> {
+        let Some(file_path) = Self::get_keymap_file() else {
+            return Ok(Keymap::default());
+        };
+        if !file_path.exists() {
+            return Ok(Keymap::default());
+        }
+        let contents = fs::read_to_string(&file_path)?;
+        let bindings = toml::from_str(&contents)
+            .map_err(|e| anyhow!("Could not read {}: {e}", file_path.display()))?;
+        Ok(Keymap { bindings })
+    }
+
+    /// Actions bound differently in `self` and `other`.
+    pub fn changes(&self, other: &Keymap) -> Vec<String> {
+        let mut changed: Vec<String> = self
+            .bindings
+            .keys()
+            .chain(other.bindings.keys())
+            .filter(|action| self.bindings.get(*action) != other.bindings.get(*action))
+            .cloned()
+            .collect();
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
+
+impl gpui::Global for Keymap {}
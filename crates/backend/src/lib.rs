@@ -1,13 +1,55 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 use ::gstreamer::State;
 use async_trait::async_trait;
+use futures::stream::BoxStream;
+use loudness::Loudness;
 use playback::Track;
 use player::Response;
 
+pub mod artists;
+pub mod chapters;
+pub mod export;
+pub mod file_manager;
+pub mod file_types;
 pub mod gstreamer;
+pub mod import;
+pub mod insights;
+pub mod ipc;
+pub mod keymap;
+pub mod library;
+pub mod link;
+pub mod loudness;
+pub mod lyrics;
+pub mod lyrics_online;
+pub mod matching;
+pub mod memory;
+pub mod oplog;
+pub mod other_audio;
+pub mod palette;
 pub mod playback;
 pub mod player;
+pub mod ratings;
+pub mod report;
+pub mod roots;
+pub mod scanner;
+pub mod scrobble;
+pub mod session;
+pub mod settings;
+pub mod skips;
+pub mod smart;
+pub mod startup;
+pub mod stay_awake;
+pub mod system_events;
+pub mod tags;
+#[cfg(test)]
+mod testing;
+pub mod text;
+pub mod uri;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod waveform;
+pub mod watcher;
 
 /// Common backend trait. Can be used to implement multple backends.
 #[async_trait]
@@ -41,12 +83,52 @@ pub trait Backend: Send + Sync + Debug {
     /// Get metadata
     async fn get_meta(&self, uri: &str) -> anyhow::Result<Track>;
 
-    /// Monitor
-    async fn monitor(&self) -> Option<Response>;
+    /// Decode the start and end of `uri` to check that it isn't corrupt or truncated.
+    async fn probe(&self, uri: &str) -> anyhow::Result<()>;
 
-    /// Current playback postion
+    /// Decode all of `uri` and return `points` peak amplitudes from 0.0 to 1.0, evenly spread
+    /// over its `duration` in seconds.
+    async fn waveform(&self, uri: &str, duration: u64, points: usize) -> anyhow::Result<Vec<f32>>;
+
+    /// Decode all of `uri` and measure its integrated loudness, true peak and clipping.
+    async fn loudness(&self, uri: &str) -> anyhow::Result<Loudness>;
+
+    /// Subscribe to playback events. Positions are pushed as [`Response::Position`] whenever
+    /// they change, so the player never has to poll.
+    fn events(self: Arc<Self>) -> BoxStream<'static, Response>;
+
+    /// Current playback postion, for one-off queries.
     async fn get_position(&self) -> u64;
 
     /// Seek
     async fn seek(&self, time: u64) -> anyhow::Result<()>;
+
+    /// Set the left/right channel balance, from -1.0 (left) to 1.0 (right).
+    async fn set_balance(&self, balance: f32) -> anyhow::Result<()>;
+
+    /// Set the pre-amp gain in decibels, from -20 to +20.
+    async fn set_preamp(&self, gain_db: f64) -> anyhow::Result<()>;
+
+    /// Downmix playback to a single channel.
+    async fn set_mono(&self, mono: bool) -> anyhow::Result<()>;
+
+    /// Fast-forward through leading, trailing and long internal silences.
+    async fn set_skip_silence(&self, enabled: bool) -> anyhow::Result<()>;
+
+    /// Emit [`Response::SpectrumFrame`] at roughly 30Hz for visualizers. Off by default since
+    /// the analysis isn't free.
+    async fn set_spectrum(&self, enabled: bool) -> anyhow::Result<()>;
+
+    /// Bypass software volume, filters and resampling, handing audio to the device untouched.
+    async fn set_exclusive(&self, exclusive: bool) -> anyhow::Result<()>;
+
+    /// Buffer less of network streams ahead and decode artwork no larger than
+    /// [`LOW_MEMORY_ARTWORK`](memory::LOW_MEMORY_ARTWORK) across.
+    async fn set_low_memory(&self, enabled: bool) -> anyhow::Result<()>;
+
+    /// Names of the audio output devices that can be played to.
+    async fn output_devices(&self) -> Vec<String>;
+
+    /// Play to the output device of this name, or the system default with `None`.
+    async fn set_output_device(&self, name: Option<String>) -> anyhow::Result<()>;
 }
@@ -0,0 +1,469 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use nucleo::{
+    Config, Matcher,
+    pattern::{CaseMatching, Normalization, Pattern},
+};
+use rand::{Rng, seq::SliceRandom};
+
+use crate::{
+    artists::ArtistRules,
+    matching,
+    playback::{Playlist, SavedPlaylists, Track},
+    player::Thumbnail,
+    roots::LibraryRoots,
+    tags::TagEdits,
+    text,
+};
+
+/// Every known track, pieced together from the cached library folders, saved playlists and the
+/// loaded queue.
+#[derive(Clone, Default)]
+pub struct Library {
+    tracks: Vec<Track>,
+    /// Search text for each track, in the same order: title, artists, album and path, with
+    /// non-Latin text followed by its romanized form so either spelling matches.
+    index: Vec<String>,
+}
+
+/// An album as shown in the browse views. Albums are told apart by name and the first artist
+/// credited on their tracks.
+#[derive(Clone)]
+pub struct Album {
+    pub name: String,
+    pub artist: String,
+    pub track_count: usize,
+    /// Shared with the album's entries under each of its artists.
+    pub thumbnail: Option<Arc<Thumbnail>>,
+}
+
+#[derive(Clone)]
+pub struct Artist {
+    pub name: String,
+    /// Albums with at least one track by this artist.
+    pub albums: Vec<Album>,
+}
+
+impl Library {
+    /// Gather the tracks of every scanned library root and cached saved playlist plus `loaded`,
+    /// each file once, with artists grouped by `rules`.
+    pub async fn load(
+        roots: &LibraryRoots,
+        saved: &SavedPlaylists,
+        loaded: &[Track],
+        rules: &ArtistRules,
+        edits: &TagEdits,
+    ) -> Self {
+        let mut seen = HashSet::new();
+        let mut tracks = vec![];
+        let cached_names = roots.roots.iter().map(|root| &root.cached_name).chain(
+            saved
+                .playlists
+                .iter()
+                // Listen Later only repeats tracks from other playlists.
+                .filter(|playlist| !playlist.is_listen_later())
+                .map(|playlist| &playlist.cached_name),
+        );
+        for cached_name in cached_names {
+            if let Some(mut cached) = Playlist::read_cached(cached_name.clone()).await {
+                edits.apply(&mut cached.tracks);
+                cached.apply_artist_rules(rules);
+                tracks.extend(
+                    cached
+                        .tracks
+                        .into_iter()
+                        .filter(|track| seen.insert(track.uri.clone())),
+                );
+            }
+        }
+        tracks.extend(
+            loaded
+                .iter()
+                .filter(|track| seen.insert(track.uri.clone()))
+                .cloned()
+                .map(|mut track| {
+                    track.check_missing();
+                    track
+                }),
+        );
+        let index = tracks.iter().map(search_text).collect();
+        Library { tracks, index }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// How many tracks' files weren't there when the library was gathered.
+    pub fn missing_count(&self) -> usize {
+        self.tracks.iter().filter(|track| track.missing).count()
+    }
+
+    /// Where the files of the `missing` tracks went, as old URI to new: a track in the library
+    /// with the same title, album, first artist and length whose file is there.
+    pub fn moved<'a>(
+        &self,
+        missing: impl IntoIterator<Item = &'a Track>,
+    ) -> HashMap<String, String> {
+        let present: HashMap<String, &str> = self
+            .tracks
+            .iter()
+            .filter(|track| !track.missing && !track.is_remote())
+            .map(|track| (matching::match_key(track), track.uri.as_str()))
+            .collect();
+        missing
+            .into_iter()
+            .filter_map(|track| {
+                let uri = present.get(&matching::match_key(track))?;
+                (*uri != track.uri).then(|| (track.uri.clone(), uri.to_string()))
+            })
+            .collect()
+    }
+
+    /// Just the tracks `filter` lets through, or the whole library for an empty filter.
+    pub fn filtered(&self, filter: &LibraryFilter) -> Library {
+        if filter.is_empty() {
+            return self.clone();
+        }
+        let (tracks, index) = self
+            .tracks
+            .iter()
+            .zip(self.index.iter())
+            .filter(|(track, _)| filter.matches(track))
+            .map(|(track, text)| (track.clone(), text.clone()))
+            .unzip();
+        Library { tracks, index }
+    }
+
+    /// Every genre tagged in the library, each once whatever its case, sorted.
+    pub fn genres(&self) -> Vec<String> {
+        let mut genres: Vec<String> = vec![];
+        for track in self.tracks.iter() {
+            let genre = track.genre.trim();
+            if !genre.is_empty() && !genres.iter().any(|g| g.eq_ignore_ascii_case(genre)) {
+                genres.push(genre.to_string());
+            }
+        }
+        genres.sort_by(|a, b| text::collate(a, b));
+        genres
+    }
+
+    /// The first year of every decade with tagged tracks in it, oldest first.
+    pub fn decades(&self) -> Vec<u32> {
+        let mut decades: Vec<u32> = self
+            .tracks
+            .iter()
+            .filter(|track| track.year != 0)
+            .map(|track| track.year / 10 * 10)
+            .collect();
+        decades.sort_unstable();
+        decades.dedup();
+        decades
+    }
+
+    pub fn track(&self, uri: &str) -> Option<Track> {
+        self.tracks.iter().find(|track| track.uri == uri).cloned()
+    }
+
+    /// Up to `limit` tracks fuzzily matching `query`, best match first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Track> {
+        if query.trim().is_empty() {
+            return vec![];
+        }
+        let pattern = Pattern::parse(query, CaseMatching::Ignore, Normalization::Smart);
+        let mut matcher = Matcher::new(Config::DEFAULT);
+        let mut matches = pattern.match_list(
+            self.index
+                .iter()
+                .enumerate()
+                .map(|(i, text)| IndexEntry(i, text)),
+            &mut matcher,
+        );
+        // Equally good matches are listed by title.
+        matches.sort_by(|(a, score_a), (b, score_b)| {
+            score_b
+                .cmp(score_a)
+                .then_with(|| text::collate(&self.tracks[a.0].title, &self.tracks[b.0].title))
+        });
+        matches.truncate(limit);
+        matches
+            .into_iter()
+            .map(|(IndexEntry(i, _), _)| self.tracks[i].clone())
+            .collect()
+    }
+
+    fn album_artist(track: &Track) -> &str {
+        track
+            .artists
+            .first()
+            .map_or("Unknown Artist", |artist| artist.as_str())
+    }
+
+    /// Every album, sorted by name and then artist.
+    pub fn albums(&self) -> Vec<Album> {
+        let mut albums: Vec<Album> = vec![];
+        let mut found: HashMap<(&str, &str), usize> = HashMap::new();
+        for track in self.tracks.iter() {
+            let artist = Self::album_artist(track);
+            match found.get(&(track.album.as_str(), artist)) {
+                Some(&index) => {
+                    let album = &mut albums[index];
+                    album.track_count += 1;
+                    if album.thumbnail.is_none() {
+                        album.thumbnail = track.thumbnail.clone().map(Arc::new);
+                    }
+                }
+                None => {
+                    found.insert((track.album.as_str(), artist), albums.len());
+                    albums.push(Album {
+                        name: track.album.clone(),
+                        artist: artist.to_string(),
+                        track_count: 1,
+                        thumbnail: track.thumbnail.clone().map(Arc::new),
+                    });
+                }
+            }
+        }
+        albums.sort_by(|a, b| {
+            text::collate(&a.name, &b.name).then_with(|| text::collate(&a.artist, &b.artist))
+        });
+        albums
+    }
+
+    /// Every artist with the albums they appear on, sorted by name.
+    pub fn artists(&self) -> Vec<Artist> {
+        let albums = self.albums();
+        let by_key: HashMap<(&str, &str), &Album> = albums
+            .iter()
+            .map(|album| ((album.name.as_str(), album.artist.as_str()), album))
+            .collect();
+        let mut artists: Vec<Artist> = vec![];
+        let mut found: HashMap<&str, usize> = HashMap::new();
+        // Each artist's albums so far, by artist, album name and album artist.
+        let mut listed: HashSet<(&str, &str, &str)> = HashSet::new();
+        for track in self.tracks.iter() {
+            let key = (track.album.as_str(), Self::album_artist(track));
+            let Some(album) = by_key.get(&key) else {
+                continue;
+            };
+            for name in track.artists.iter() {
+                let index = *found.entry(name.as_str()).or_insert_with(|| {
+                    artists.push(Artist {
+                        name: name.clone(),
+                        albums: vec![],
+                    });
+                    artists.len() - 1
+                });
+                if listed.insert((name.as_str(), key.0, key.1)) {
+                    artists[index].albums.push((*album).clone());
+                }
+            }
+        }
+        artists.sort_by(|a, b| text::collate(&a.name, &b.name));
+        for artist in artists.iter_mut() {
+            artist
+                .albums
+                .sort_by(|a, b| text::collate(&a.name, &b.name));
+        }
+        artists
+    }
+
+    /// The tracks of an album in disc and track order. Tracks without a disc number count as
+    /// disc one; tracks without a track number go last on their disc, by title.
+    pub fn album_tracks(&self, name: &str, artist: &str) -> Vec<Track> {
+        let mut tracks: Vec<Track> = self
+            .tracks
+            .iter()
+            .filter(|track| track.album == name && Self::album_artist(track) == artist)
+            .cloned()
+            .collect();
+        tracks.sort_by(album_order);
+        tracks
+    }
+
+    /// Every track `artist` appears on, an album at a time in the order the artist view lists
+    /// them, each in disc and track order. Tracks on no album come last, by title.
+    pub fn artist_tracks(&self, artist: &str) -> Vec<Track> {
+        let mut tracks: Vec<Track> = self
+            .tracks
+            .iter()
+            .filter(|track| track.artists.iter().any(|name| name == artist))
+            .cloned()
+            .collect();
+        tracks.sort_by(|a, b| {
+            a.album
+                .is_empty()
+                .cmp(&b.album.is_empty())
+                .then_with(|| text::collate(&a.album, &b.album))
+                .then_with(|| text::collate(Self::album_artist(a), Self::album_artist(b)))
+                .then_with(|| album_order(a, b))
+        });
+        tracks
+    }
+}
+
+/// Order of two tracks on the same album. Tracks without a disc number count as disc one;
+/// tracks without a track number go last on their disc, by title.
+fn album_order(a: &Track, b: &Track) -> Ordering {
+    let position = |number: u32| if number == 0 { u32::MAX } else { number };
+    a.disc_number
+        .max(1)
+        .cmp(&b.disc_number.max(1))
+        .then(position(a.track_number).cmp(&position(b.track_number)))
+        .then_with(|| text::collate(&a.title, &b.title))
+}
+
+/// Shuffle `tracks` an album at a time: albums come in random order and each is played
+/// through in disc and track order. Tracks without an album are shuffled in as albums of
+/// their own.
+pub fn shuffle_albums(tracks: &mut Vec<Track>, rng: &mut impl Rng) {
+    let mut albums: Vec<Vec<Track>> = vec![];
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+    for track in tracks.drain(..) {
+        if track.album.is_empty() {
+            albums.push(vec![track]);
+            continue;
+        }
+        let key = (
+            track.album.clone(),
+            Library::album_artist(&track).to_string(),
+        );
+        match index.get(&key) {
+            Some(&album) => albums[album].push(track),
+            None => {
+                index.insert(key, albums.len());
+                albums.push(vec![track]);
+            }
+        }
+    }
+    albums.shuffle(rng);
+    for mut album in albums {
+        album.sort_by(album_order);
+        tracks.extend(album);
+    }
+}
+
+/// Narrows what the browse views and search show. A track has to match every part that is
+/// set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LibraryFilter {
+    /// Any of these genres, compared without regard to case. Empty allows every genre.
+    pub genres: Vec<String>,
+    /// Release years from the first to the last, both included. Untagged tracks never match.
+    pub years: Option<(u32, u32)>,
+}
+
+impl LibraryFilter {
+    pub fn is_empty(&self) -> bool {
+        self.genres.is_empty() && self.years.is_none()
+    }
+
+    pub fn matches(&self, track: &Track) -> bool {
+        let genre = self.genres.is_empty()
+            || self
+                .genres
+                .iter()
+                .any(|genre| genre.eq_ignore_ascii_case(track.genre.trim()));
+        let year = self
+            .years
+            .is_none_or(|(from, to)| track.year != 0 && (from..=to).contains(&track.year));
+        genre && year
+    }
+}
+
+struct IndexEntry<'a>(usize, &'a str);
+
+impl AsRef<str> for IndexEntry<'_> {
+    fn as_ref(&self) -> &str {
+        self.1
+    }
+}
+
+fn search_text(track: &Track) -> String {
+    let path = track
+        .path()
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|| track.uri.clone());
+    let text = format!(
+        "{} {} {} {}",
+        track.title,
+        track.artists.join(" "),
+        track.album,
+        path
+    );
+    if text.is_ascii() {
+        text
+    } else {
+        format!("{text} {}", text::romanize(&text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::testing;
+
+    fn track(album: &str, artist: &str, disc: u32, number: u32) -> Track {
+        Track {
+            track_number: number,
+            disc_number: disc,
+            ..testing::track(artist, album, &format!("{disc}-{number}"))
+        }
+    }
+
+    #[test]
+    fn shuffle_albums_plays_each_album_through_in_order() {
+        let original = vec![
+            track("Greatest Hits", "One", 2, 1),
+            track("Greatest Hits", "Two", 1, 2),
+            track("Single", "One", 0, 0),
+            track("Greatest Hits", "One", 1, 2),
+            track("", "Loose", 0, 0),
+            track("Greatest Hits", "Two", 1, 1),
+            track("Greatest Hits", "One", 1, 1),
+            track("", "Loose", 0, 0),
+            track("Greatest Hits", "One", 0, 3),
+        ];
+        for seed in 0..20 {
+            let mut tracks = original.clone();
+            shuffle_albums(&mut tracks, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(tracks.len(), original.len());
+
+            let mut seen: Vec<(String, String)> = vec![];
+            for pair in tracks.windows(2) {
+                let key = |track: &Track| {
+                    (
+                        track.album.clone(),
+                        Library::album_artist(track).to_string(),
+                    )
+                };
+                if pair[0].album.is_empty() || key(&pair[0]) != key(&pair[1]) {
+                    seen.push(key(&pair[0]));
+                    // An album's tracks all come together.
+                    assert!(pair[1].album.is_empty() || !seen.contains(&key(&pair[1])));
+                } else {
+                    assert_ne!(album_order(&pair[0], &pair[1]), Ordering::Greater);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn album_order_puts_untagged_numbers_last_on_disc_one() {
+        let untagged = track("Album", "Artist", 0, 0);
+        let first = track("Album", "Artist", 0, 1);
+        let second_disc = track("Album", "Artist", 2, 1);
+        assert_eq!(album_order(&first, &untagged), Ordering::Less);
+        assert_eq!(album_order(&untagged, &second_disc), Ordering::Less);
+    }
+}
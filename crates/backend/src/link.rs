@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+
+use crate::uri;
+
+/// URL scheme of links that open a file in the app.
+pub const SCHEME: &str = "reyvr";
+/// Desktop entry registered as the handler of [`SCHEME`] links on Linux.
+#[cfg(target_os = "linux")]
+const DESKTOP_FILE: &str = "reyvr-url-handler.desktop";
+
+/// A `reyvr://play?path=...&t=90` link: a file and the second to start playing it from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayLink {
+    pub path: PathBuf,
+    /// Seconds.
+    pub position: u64,
+}
+
+impl PlayLink {
+    /// Read a link. `path` may be a plain path or a `file:` URI, and `t` a number of seconds,
+    /// optionally followed by `s`, or `m:ss` or `h:mm:ss`.
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        let rest = url
+            .split_once("://")
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(SCHEME))
+            .map(|(_, rest)| rest)
+            .ok_or(anyhow!("Not a {SCHEME}:// link: {url}"))?;
+        let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+        if action.trim_end_matches('/') != "play" {
+            return Err(anyhow!("Unknown link action {action:?}"));
+        }
+
+        let mut path = None;
+        let mut position = 0;
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = uri::decode(value);
+            match key {
+                "path" => {
+                    path = Some(match uri::to_path(&value) {
+                        Some(path) => path,
+                        None => PathBuf::from(value),
+                    })
+                }
+                "t" => position = parse_time(&value).ok_or(anyhow!("Invalid time {value:?}"))?,
+                _ => {}
+            }
+        }
+        let path = path.ok_or(anyhow!("Link has no path"))?;
+        Ok(PlayLink { path, position })
+    }
+
+    pub fn to_url(&self) -> String {
+        let path = uri::encode_query(&self.path.to_string_lossy());
+        if self.position == 0 {
+            format!("{SCHEME}://play?path={path}")
+        } else {
+            format!("{SCHEME}://play?path={path}&t={}", self.position)
+        }
+    }
+}
+
+/// Seconds in `text`: `90`, `90s`, `1:30` or `1:01:30`.
+fn parse_time(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let text = text.strip_suffix('s').unwrap_or(text);
+    text.split(':').try_fold(0, |total: u64, part| {
+        Some(total * 60 + part.parse::<u64>().ok()?)
+    })
+}
+
+/// Make the OS open [`SCHEME`] links with the running executable. On Linux this writes a
+/// desktop entry for the scheme and makes it the default handler, and on Windows it registers
+/// the scheme for the current user. macOS reads URL schemes from the app bundle's Info.plist,
+/// so there is nothing to do there at runtime.
+pub fn register() -> anyhow::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use directories::BaseDirs;
+        use std::fs;
+
+        let exe = std::env::current_exe()?;
+        let dir = BaseDirs::new()
+            .ok_or(anyhow!("Could not find home directory"))?
+            .data_dir()
+            .join("applications");
+        fs::create_dir_all(&dir)?;
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName=Reyvr\nExec=\"{}\" %u\nNoDisplay=true\n\
+             MimeType=x-scheme-handler/{SCHEME};\n",
+            exe.display()
+        );
+        let path = dir.join(DESKTOP_FILE);
+        // Rewritten only when the executable moved, so the default isn't reset on every start.
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == entry) {
+            return Ok(());
+        }
+        fs::write(&path, entry)?;
+        std::process::Command::new("xdg-mime")
+            .args([
+                "default",
+                DESKTOP_FILE,
+                &format!("x-scheme-handler/{SCHEME}"),
+            ])
+            .status()?;
+    }
+
+    #[cfg(windows)]
+    {
+        let exe = std::env::current_exe()?;
+        let key = format!(r"HKCU\Software\Classes\{SCHEME}");
+        let command = format!("\"{}\" \"%1\"", exe.display());
+        let entries: [(String, &[&str]); 3] = [
+            (key.clone(), &["/ve", "/d", "URL:Reyvr link"]),
+            (key.clone(), &["/v", "URL Protocol", "/d", ""]),
+            (
+                format!(r"{key}\shell\open\command"),
+                &["/ve", "/d", &command],
+            ),
+        ];
+        for (key, values) in entries {
+            std::process::Command::new("reg")
+                .arg("add")
+                .arg(key)
+                .args(values)
+                .arg("/f")
+                .status()?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::HashMap,
+    f64::consts::PI,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use bincode::config;
+use ring_channel::RingSender as Sender;
+use serde::{Deserialize, Serialize};
+
+use crate::{Backend, playback::Track, player::Response, session::Session, uri};
+
+/// Sample rate audio is measured at. The K-weighting filters below are the BS.1770 ones for it.
+pub const METER_RATE: u32 = 48000;
+/// Integrated loudness reported for silence, the absolute gate of BS.1770.
+pub const MIN_LUFS: f32 = -70.0;
+/// Clipped stretches per minute from which a master counts as heavily clipped.
+pub const HEAVY_CLIPPING_PER_MINUTE: f32 = 20.0;
+/// Samples at or above this level count as full scale.
+const CLIP_LEVEL: f32 = 0.999;
+/// Full-scale samples in a row that make a clipped stretch rather than a loud peak.
+const CLIP_RUN: u32 = 3;
+/// Gating blocks are 400ms long and start every 100ms.
+const STEP_FRAMES: usize = METER_RATE as usize / 10;
+const STEPS_PER_BLOCK: usize = 4;
+/// True peak is found by upsampling four times with a windowed sinc of this many taps.
+const OVERSAMPLING: usize = 4;
+const TAPS: usize = 16;
+
+/// Loudness of a track as measured by [`Meter`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS, per EBU R128.
+    pub integrated: f32,
+    /// Highest inter-sample peak in dBTP.
+    pub true_peak: f32,
+    /// Stretches of full-scale samples per minute, a sign of a clipped master.
+    pub clip_rate: f32,
+}
+
+impl Loudness {
+    pub fn is_heavily_clipped(&self) -> bool {
+        self.clip_rate >= HEAVY_CLIPPING_PER_MINUTE
+    }
+}
+
+/// Measures integrated loudness, true peak and clipping of interleaved samples at
+/// [`METER_RATE`].
+pub struct Meter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+    /// Weighted sum of squares of each channel over the current 100ms step.
+    step: Vec<f64>,
+    step_frames: usize,
+    /// Mean square of each of the last steps, summed over channels with their weights.
+    recent: Vec<f64>,
+    /// Power of every 400ms block measured.
+    blocks: Vec<f64>,
+    /// Last samples of each channel, for upsampling.
+    history: Vec<[f32; TAPS]>,
+    phases: [[f32; TAPS]; OVERSAMPLING],
+    peak: f32,
+    runs: Vec<u32>,
+    clips: u32,
+    frames: u64,
+}
+
+impl Meter {
+    pub fn new(channels: usize) -> Self {
+        let channels = channels.max(1);
+        // Surround channels count for more; the LFE isn't measured at all.
+        let weights = (0..channels)
+            .map(|channel| match (channels, channel) {
+                (6, 3) => 0.0,
+                (6, 4 | 5) | (5, 3 | 4) => 1.41,
+                _ => 1.0,
+            })
+            .collect();
+
+        let mut phases = [[0.0; TAPS]; OVERSAMPLING];
+        for (phase, coefficients) in phases.iter_mut().enumerate() {
+            let offset = phase as f64 / OVERSAMPLING as f64;
+            for (tap, coefficient) in coefficients.iter_mut().enumerate() {
+                let t = tap as f64 - (TAPS / 2 - 1) as f64 - offset;
+                let sinc = if t == 0.0 {
+                    1.0
+                } else {
+                    (PI * t).sin() / (PI * t)
+                };
+                let window = 0.5 * (1.0 + (PI * t / (TAPS / 2) as f64).cos());
+                *coefficient = (sinc * window) as f32;
+            }
+        }
+
+        Meter {
+            channels,
+            weights,
+            filters: (0..channels)
+                .map(|_| [Biquad::shelf(), Biquad::high_pass()])
+                .collect(),
+            step: vec![0.0; channels],
+            step_frames: 0,
+            recent: vec![],
+            blocks: vec![],
+            history: vec![[0.0; TAPS]; channels],
+            phases,
+            peak: 0.0,
+            runs: vec![0; channels],
+            clips: 0,
+            frames: 0,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let [shelf, high_pass] = &mut self.filters[channel];
+                let weighted = high_pass.process(shelf.process(sample as f64));
+                self.step[channel] += weighted * weighted;
+
+                let history = &mut self.history[channel];
+                history.copy_within(1.., 0);
+                history[TAPS - 1] = sample;
+                for coefficients in &self.phases {
+                    let value: f32 = history.iter().zip(coefficients).map(|(x, c)| x * c).sum();
+                    self.peak = self.peak.max(value.abs());
+                }
+
+                if sample.abs() >= CLIP_LEVEL {
+                    self.runs[channel] += 1;
+                } else {
+                    if self.runs[channel] >= CLIP_RUN {
+                        self.clips += 1;
+                    }
+                    self.runs[channel] = 0;
+                }
+            }
+            self.frames += 1;
+            self.step_frames += 1;
+            if self.step_frames == STEP_FRAMES {
+                self.end_step();
+            }
+        }
+    }
+
+    fn end_step(&mut self) {
+        let power = self
+            .step
+            .iter()
+            .zip(&self.weights)
+            .map(|(sum, weight)| weight * sum / STEP_FRAMES as f64)
+            .sum();
+        self.step.iter_mut().for_each(|sum| *sum = 0.0);
+        self.step_frames = 0;
+        self.recent.push(power);
+        if self.recent.len() > STEPS_PER_BLOCK {
+            self.recent.remove(0);
+        }
+        if self.recent.len() == STEPS_PER_BLOCK {
+            self.blocks
+                .push(self.recent.iter().sum::<f64>() / STEPS_PER_BLOCK as f64);
+        }
+    }
+
+    /// The measurements, or `None` if no audio came through.
+    pub fn finish(mut self) -> Option<Loudness> {
+        if self.frames == 0 {
+            return None;
+        }
+        self.clips += self.runs.iter().filter(|&&run| run >= CLIP_RUN).count() as u32;
+
+        // Blocks under the absolute gate, then those 10 LU under the rest, are left out.
+        let gated = |blocks: Vec<f64>, gate: f64| -> Vec<f64> {
+            blocks
+                .into_iter()
+                .filter(|&power| lufs(power) > gate)
+                .collect()
+        };
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len().max(1) as f64;
+        let absolute = gated(self.blocks, MIN_LUFS as f64);
+        let relative = lufs(mean(&absolute)) - 10.0;
+        let integrated = match gated(absolute, relative) {
+            blocks if blocks.is_empty() => MIN_LUFS,
+            blocks => (lufs(mean(&blocks)) as f32).max(MIN_LUFS),
+        };
+
+        let minutes = self.frames as f32 / METER_RATE as f32 / 60.0;
+        Some(Loudness {
+            integrated,
+            true_peak: 20.0 * self.peak.max(f32::MIN_POSITIVE).log10(),
+            clip_rate: self.clips as f32 / minutes.max(1.0 / 60.0),
+        })
+    }
+}
+
+fn lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(f64::MIN_POSITIVE).log10()
+}
+
+/// Second-order filter stage of the K-weighting.
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    /// The high shelf modelling the head.
+    fn shelf() -> Self {
+        Biquad::new(
+            [1.53512485958697, -2.69169618940638, 1.19839281085285],
+            [-1.69065929318241, 0.73248077421585],
+        )
+    }
+
+    /// The RLB high-pass.
+    fn high_pass() -> Self {
+        Biquad::new([1.0, -2.0, 1.0], [-1.99004745483398, 0.99007225036621])
+    }
+
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// Measured loudness by URI, so each file is only decoded once.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LoudnessStore {
+    pub tracks: HashMap<String, Loudness>,
+}
+
+impl LoudnessStore {
+    pub fn get_loudness_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("loudness.bin"))
+    }
+
+    pub fn load() -> LoudnessStore {
+        let Some(file_path) = Self::get_loudness_file() else {
+            return LoudnessStore::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return LoudnessStore::default();
+        };
+        match bincode::serde::decode_from_slice::<LoudnessStore, _>(&data, config::standard()) {
+            Ok((mut store, _)) => {
+                store.tracks = uri::normalize_keys(store.tracks);
+                store
+            }
+            Err(e) => {
+                eprintln!("Failed to decode loudness: {}", e);
+                LoudnessStore::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_loudness_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+}
+
+/// Measures tracks one at a time on a worker thread, sending every measurement known so far
+/// after each new one. Tracks already measured are skipped.
+#[derive(Clone)]
+pub struct LoudnessQueue {
+    jobs: smol::channel::Sender<Track>,
+    pub store: Arc<Mutex<LoudnessStore>>,
+}
+
+impl LoudnessQueue {
+    pub fn new(backend: Arc<dyn Backend>, tx: Sender<Response>) -> Self {
+        let (jobs, rx) = smol::channel::unbounded::<Track>();
+        let store = Arc::new(Mutex::new(LoudnessStore::load()));
+        let worker_store = store.clone();
+
+        thread::spawn(move || {
+            while let Ok(track) = rx.recv_blocking() {
+                let known = worker_store
+                    .lock()
+                    .expect("Could not lock loudness")
+                    .tracks
+                    .contains_key(&track.uri);
+                if known {
+                    continue;
+                }
+                let loudness = match smol::block_on(backend.loudness(&track.uri)) {
+                    Ok(loudness) => loudness,
+                    Err(e) => {
+                        eprintln!("Could not measure loudness of {}: {e}", track.uri);
+                        continue;
+                    }
+                };
+                let tracks = {
+                    let mut store = worker_store.lock().expect("Could not lock loudness");
+                    store.tracks.insert(track.uri.clone(), loudness);
+                    if let Err(e) = store.save() {
+                        eprintln!("Could not save loudness: {e}");
+                    }
+                    store.tracks.clone()
+                };
+                let _ = tx.send(Response::Loudness(tracks));
+            }
+        });
+
+        LoudnessQueue { jobs, store }
+    }
+
+    /// Queue `track` for measuring, unless it's a stream or was measured before.
+    pub fn request(&self, track: &Track) {
+        if track.is_remote() {
+            return;
+        }
+        let _ = self.jobs.try_send(track.clone());
+    }
+
+    pub fn all(&self) -> HashMap<String, Loudness> {
+        self.store
+            .lock()
+            .expect("Could not lock loudness")
+            .tracks
+            .clone()
+    }
+}
@@ -0,0 +1,217 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::anyhow;
+
+use crate::playback::Track;
+
+/// One line of lyrics, with where it starts in milliseconds for synced lyrics.
+#[derive(Clone, Debug)]
+pub struct LyricLine {
+    pub time: Option<u64>,
+    pub text: String,
+}
+
+/// Lyrics read from a `.lrc` or `.txt` file next to the track.
+#[derive(Clone, Debug, Default)]
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>,
+    /// The LRC `[offset:]` tag: milliseconds every line shows up early by, or late by when
+    /// negative.
+    pub offset_ms: i64,
+    /// Other LRC tags, such as `ar` and `ti`, kept as they were.
+    pub tags: Vec<(String, String)>,
+    /// File the lyrics came from, if any.
+    pub source: Option<PathBuf>,
+}
+
+/// A correction to the current track's lyrics, from the lyrics editor.
+#[derive(Clone, Debug)]
+pub enum LyricsEdit {
+    SetText(usize, String),
+    /// Start the line at the current playback position.
+    Stamp(usize),
+    /// Add an empty line after the given one, or at the top for `None`.
+    Insert(Option<usize>),
+    Remove(usize),
+    /// Change the sync offset by this many milliseconds.
+    Nudge(i64),
+}
+
+impl Lyrics {
+    /// Read LRC text. Lines without a timestamp are kept unsynced; lines with several
+    /// timestamps are repeated at each of them.
+    pub fn parse(text: &str) -> Self {
+        let mut lyrics = Lyrics::default();
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut times = vec![];
+            while let Some((inner, after)) =
+                rest.strip_prefix('[').and_then(|tag| tag.split_once(']'))
+            {
+                match parse_time(inner) {
+                    Some(time) => times.push(time),
+                    None => {
+                        let Some((key, value)) = inner.split_once(':') else {
+                            break;
+                        };
+                        let value = value.trim();
+                        if key.trim() == "offset" {
+                            lyrics.offset_ms = value.parse().unwrap_or(0);
+                        } else {
+                            lyrics
+                                .tags
+                                .push((key.trim().to_string(), value.to_string()));
+                        }
+                    }
+                }
+                rest = after;
+            }
+            if times.is_empty() {
+                if !rest.is_empty() || line.trim().is_empty() {
+                    lyrics.lines.push(LyricLine {
+                        time: None,
+                        text: rest.to_string(),
+                    });
+                }
+            } else {
+                lyrics.lines.extend(times.into_iter().map(|time| LyricLine {
+                    time: Some(time),
+                    text: rest.trim().to_string(),
+                }));
+            }
+        }
+        // Drop the blank lines around the lyrics that plain text files tend to have.
+        while lyrics.lines.last().is_some_and(|line| line.is_blank()) {
+            lyrics.lines.pop();
+        }
+        while lyrics.lines.first().is_some_and(|line| line.is_blank()) {
+            lyrics.lines.remove(0);
+        }
+        if lyrics.is_synced() {
+            lyrics.lines.sort_by_key(|line| line.time);
+        }
+        lyrics
+    }
+
+    /// Write the lyrics out as LRC, or as plain text when no line has a timestamp.
+    pub fn to_lrc(&self) -> String {
+        let mut text = String::new();
+        for (key, value) in self.tags.iter() {
+            text.push_str(&format!("[{key}:{value}]\n"));
+        }
+        if self.offset_ms != 0 {
+            text.push_str(&format!("[offset:{:+}]\n", self.offset_ms));
+        }
+        for line in self.lines.iter() {
+            if let Some(time) = line.time {
+                text.push_str(&format!(
+                    "[{:02}:{:02}.{:02}]",
+                    time / 60_000,
+                    time / 1000 % 60,
+                    time % 1000 / 10
+                ));
+            }
+            text.push_str(&line.text);
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.lines.iter().any(|line| line.time.is_some())
+    }
+
+    /// The line being sung `position_ms` into the track, with the offset applied.
+    pub fn line_at(&self, position_ms: u64) -> Option<usize> {
+        let position = position_ms as i64 + self.offset_ms;
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.time.is_some_and(|time| time as i64 <= position))
+            .max_by_key(|(_, line)| line.time)
+            .map(|(index, _)| index)
+    }
+
+    /// Apply `edit`, with `position_ms` used for timestamps.
+    pub fn edit(&mut self, edit: LyricsEdit, position_ms: u64) {
+        match edit {
+            LyricsEdit::SetText(index, text) => {
+                if let Some(line) = self.lines.get_mut(index) {
+                    line.text = text;
+                }
+            }
+            LyricsEdit::Stamp(index) => {
+                // Stamp at the song position, undoing the offset so the line shows up now.
+                let time = (position_ms as i64 + self.offset_ms).max(0) as u64;
+                if let Some(line) = self.lines.get_mut(index) {
+                    line.time = Some(time);
+                }
+            }
+            LyricsEdit::Insert(after) => {
+                let index = after.map_or(0, |after| (after + 1).min(self.lines.len()));
+                self.lines.insert(
+                    index,
+                    LyricLine {
+                        time: None,
+                        text: String::new(),
+                    },
+                );
+            }
+            LyricsEdit::Remove(index) => {
+                if index < self.lines.len() {
+                    self.lines.remove(index);
+                }
+            }
+            LyricsEdit::Nudge(ms) => self.offset_ms += ms,
+        }
+    }
+}
+
+impl LyricLine {
+    fn is_blank(&self) -> bool {
+        self.time.is_none() && self.text.trim().is_empty()
+    }
+}
+
+/// `mm:ss`, `mm:ss.xx` or `mm:ss.xxx` in milliseconds.
+fn parse_time(text: &str) -> Option<u64> {
+    let (minutes, seconds) = text.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let seconds: u64 = seconds.trim().parse().ok()?;
+    let fraction = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<u64>().ok()? * 100,
+        2 => fraction.parse::<u64>().ok()? * 10,
+        _ => fraction.get(..3)?.parse::<u64>().ok()?,
+    };
+    Some(minutes * 60_000 + seconds * 1000 + fraction)
+}
+
+/// Lyrics for `track` from a `.lrc` file next to it, or failing that a `.txt` file.
+pub fn load(track: &Track) -> Option<Lyrics> {
+    let path = track.path()?;
+    ["lrc", "txt"].iter().find_map(|ext| {
+        let sidecar = path.with_extension(ext);
+        let text = fs::read_to_string(&sidecar).ok()?;
+        let mut lyrics = Lyrics::parse(&text);
+        lyrics.source = Some(sidecar);
+        Some(lyrics)
+    })
+}
+
+/// Save `lyrics` next to `track`, returning where they went. Synced lyrics always go to a
+/// `.lrc` file, so stamping lines of a plain text file leaves the original alone.
+pub fn save(track: &Track, lyrics: &Lyrics) -> anyhow::Result<PathBuf> {
+    let path = track
+        .path()
+        .ok_or(anyhow!("Only local files can have lyrics saved"))?;
+    let sidecar = match lyrics.source.clone() {
+        Some(source) if !lyrics.is_synced() || source.extension().is_some_and(|e| e == "lrc") => {
+            source
+        }
+        _ => path.with_extension("lrc"),
+    };
+    fs::write(&sidecar, lyrics.to_lrc())?;
+    Ok(sidecar)
+}
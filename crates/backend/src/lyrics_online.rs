@@ -0,0 +1,281 @@
+//! Lyrics looked up online for tracks without any of their own, from LRCLIB or other servers
+//! with the same API. Lookups are made on a worker thread of their own and kept in
+//! `lyrics.bin`, misses included, so a track is only looked up once unless it's searched for
+//! again by hand.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use bincode::config;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smol::channel::Sender;
+
+use crate::{playback::Track, session::Session, uri};
+
+/// The public LRCLIB server, asked by default.
+pub const LRCLIB: &str = "https://lrclib.net";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// LRCLIB asks apps to say who they are.
+const USER_AGENT: &str = concat!("Reyvr/", env!("CARGO_PKG_VERSION"));
+
+/// What lyrics are looked up by. Starts out as the track's tags, and can be corrected by hand
+/// when those find nothing or the wrong song.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LyricsQuery {
+    pub title: String,
+    pub artist: String,
+    /// Empty when unknown.
+    pub album: String,
+    /// Seconds, or 0 if unknown.
+    pub duration: u64,
+}
+
+impl LyricsQuery {
+    /// Look `track` up by its tags, or `None` if it has no title to go by.
+    pub fn of(track: &Track) -> Option<LyricsQuery> {
+        if track.title.is_empty() {
+            return None;
+        }
+        Some(LyricsQuery {
+            title: track.title.clone(),
+            artist: track.artists.join(", "),
+            album: track.album.clone(),
+            duration: track.duration,
+        })
+    }
+}
+
+/// The outcome of looking up lyrics for the track at `uri`.
+#[derive(Clone, Debug)]
+pub struct FoundLyrics {
+    pub uri: String,
+    /// LRC or plain text, `None` when no provider had any, or why none could be asked.
+    pub text: Result<Option<String>, String>,
+    /// Searched for by hand, so they replace whatever lyrics the track has.
+    pub searched: bool,
+}
+
+/// Lyrics looked up so far, by URI, with `None` for tracks no provider had.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LyricsCache {
+    pub tracks: HashMap<String, Option<String>>,
+}
+
+impl LyricsCache {
+    pub fn get_lyrics_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("lyrics.bin"))
+    }
+
+    pub fn load() -> LyricsCache {
+        let Some(file_path) = Self::get_lyrics_file() else {
+            return LyricsCache::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return LyricsCache::default();
+        };
+        match bincode::serde::decode_from_slice::<LyricsCache, _>(&data, config::standard()) {
+            Ok((mut cache, _)) => {
+                cache.tracks = uri::normalize_keys(cache.tracks);
+                cache
+            }
+            Err(e) => {
+                eprintln!("Failed to decode lyrics: {}", e);
+                LyricsCache::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_lyrics_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+}
+
+enum Job {
+    Providers(Vec<String>),
+    Find(String, LyricsQuery),
+    Search(String, LyricsQuery),
+}
+
+/// Looks lyrics up on a worker thread, sending what it finds back as [`FoundLyrics`].
+#[derive(Clone)]
+pub struct LyricsFinder {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl LyricsFinder {
+    pub fn new(found: Sender<FoundLyrics>) -> Self {
+        let (jobs, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut worker = Worker {
+                agent: ureq::AgentBuilder::new()
+                    .timeout(REQUEST_TIMEOUT)
+                    .user_agent(USER_AGENT)
+                    .build(),
+                providers: vec![],
+                cache: LyricsCache::load(),
+                found,
+            };
+            while let Ok(job) = rx.recv() {
+                worker.handle(job);
+            }
+        });
+        LyricsFinder { jobs }
+    }
+
+    /// Base URLs of the servers to ask, in order. None turns lookups off.
+    pub fn set_providers(&self, providers: Vec<String>) {
+        let _ = self.jobs.send(Job::Providers(providers));
+    }
+
+    /// Find lyrics for the track at `uri`, answering from the cache if it was looked up before.
+    pub fn find(&self, uri: String, query: LyricsQuery) {
+        let _ = self.jobs.send(Job::Find(uri, query));
+    }
+
+    /// Look lyrics up again by a corrected `query`, whatever the cache has.
+    pub fn search(&self, uri: String, query: LyricsQuery) {
+        let _ = self.jobs.send(Job::Search(uri, query));
+    }
+}
+
+struct Worker {
+    agent: ureq::Agent,
+    providers: Vec<String>,
+    cache: LyricsCache,
+    found: Sender<FoundLyrics>,
+}
+
+impl Worker {
+    fn handle(&mut self, job: Job) {
+        let (uri, query, searched) = match job {
+            Job::Providers(providers) => {
+                self.providers = providers;
+                return;
+            }
+            Job::Find(uri, query) => {
+                if let Some(text) = self.cache.tracks.get(&uri) {
+                    let text = Ok(text.clone());
+                    let _ = self.found.send_blocking(FoundLyrics {
+                        uri,
+                        text,
+                        searched: false,
+                    });
+                    return;
+                }
+                (uri, query, false)
+            }
+            Job::Search(uri, query) => (uri, query, true),
+        };
+        if self.providers.is_empty() && !searched {
+            return;
+        }
+        let text = self.look_up(&query).map_err(|e| e.to_string());
+        match &text {
+            Ok(found) => {
+                self.cache.tracks.insert(uri.clone(), found.clone());
+                if let Err(e) = self.cache.save() {
+                    eprintln!("Could not save lyrics: {e}");
+                }
+            }
+            // Most likely offline, so nothing is cached and the next play tries again.
+            Err(e) if !searched => {
+                eprintln!("Could not look up lyrics: {e}");
+                return;
+            }
+            Err(_) => {}
+        }
+        let _ = self.found.send_blocking(FoundLyrics {
+            uri,
+            text,
+            searched,
+        });
+    }
+
+    /// Ask each provider in turn, stopping at the first with lyrics. Fails only if none could
+    /// be asked at all.
+    fn look_up(&self, query: &LyricsQuery) -> anyhow::Result<Option<String>> {
+        if self.providers.is_empty() {
+            return Err(anyhow!("no lyrics providers are set"));
+        }
+        let mut error = None;
+        let mut answered = false;
+        for provider in self.providers.iter() {
+            match self.lrclib(provider.trim_end_matches('/'), query) {
+                Ok(Some(text)) => return Ok(Some(text)),
+                Ok(None) => answered = true,
+                Err(e) => {
+                    eprintln!("Could not ask {provider} for lyrics: {e}");
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match error {
+            Some(e) if !answered => Err(e),
+            _ => Ok(None),
+        }
+    }
+
+    /// The exact match for `query`, or the best search result when there's none. Matching
+    /// exactly takes the album and duration too, so without them it's straight to searching.
+    fn lrclib(&self, provider: &str, query: &LyricsQuery) -> anyhow::Result<Option<String>> {
+        if !query.album.is_empty() && query.duration > 0 {
+            let exact = self
+                .agent
+                .get(&format!("{provider}/api/get"))
+                .query("track_name", &query.title)
+                .query("artist_name", &query.artist)
+                .query("album_name", &query.album)
+                .query("duration", &query.duration.to_string())
+                .call();
+            match exact {
+                Ok(response) => {
+                    let record: Value = response.into_json()?;
+                    if let Some(text) = lyrics_of(&record) {
+                        return Ok(Some(text));
+                    }
+                }
+                Err(ureq::Error::Status(404, _)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let results: Value = self
+            .agent
+            .get(&format!("{provider}/api/search"))
+            .query("track_name", &query.title)
+            .query("artist_name", &query.artist)
+            .call()?
+            .into_json()?;
+        let results = results
+            .as_array()
+            .ok_or(anyhow!("{provider} sent back something other than a list"))?;
+        // Synced lyrics are worth passing over a closer match for.
+        Ok(results
+            .iter()
+            .find(|record| record["syncedLyrics"].is_string())
+            .or_else(|| results.first())
+            .and_then(lyrics_of))
+    }
+}
+
+/// The synced lyrics of an LRCLIB record, or the plain ones without.
+fn lyrics_of(record: &Value) -> Option<String> {
+    ["syncedLyrics", "plainLyrics"]
+        .iter()
+        .filter_map(|key| record[key].as_str())
+        .find(|text| !text.trim().is_empty())
+        .map(str::to_string)
+}
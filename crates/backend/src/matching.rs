@@ -0,0 +1,38 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{playback::Track, text};
+
+/// Key under which local and remote copies of the same recording are matched: title, album and
+/// first artist, romanized and lowercased, plus the duration rounded to a few seconds.
+pub fn match_key(track: &Track) -> String {
+    let normalize = |text: &str| text::romanize(text).trim().to_lowercase();
+    format!(
+        "{}|{}|{}|{}",
+        normalize(&track.title),
+        normalize(&track.album),
+        normalize(track.artists.first().map_or("", |artist| artist.as_str())),
+        (track.duration + 2) / 5
+    )
+}
+
+/// Collapse tracks that exist both locally and on a remote source into one entry, keeping the
+/// local file unless its key is in `prefer_remote`. The first copy's position is kept.
+pub fn dedupe_sources(tracks: Vec<Track>, prefer_remote: &HashSet<String>) -> Vec<Track> {
+    let mut result: Vec<Track> = Vec::with_capacity(tracks.len());
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for track in tracks {
+        let key = match_key(&track);
+        match seen.get(&key) {
+            Some(&index) if result[index].is_remote() != track.is_remote() => {
+                if track.is_remote() == prefer_remote.contains(&key) {
+                    result[index] = track;
+                }
+            }
+            _ => {
+                seen.entry(key).or_insert(result.len());
+                result.push(track);
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,25 @@
+//! How much memory the system has, to suggest low-memory mode where it would help.
+
+use std::{fs, sync::OnceLock};
+
+/// Systems with less memory than this are offered low-memory mode.
+pub const LOW_MEMORY_THRESHOLD: u64 = 4 * 1024 * 1024 * 1024;
+/// Widest or tallest artwork is decoded in low-memory mode, in pixels.
+pub const LOW_MEMORY_ARTWORK: u32 = 512;
+
+/// Total physical memory in bytes, or `None` where it can't be read.
+pub fn total_memory() -> Option<u64> {
+    static TOTAL: OnceLock<Option<u64>> = OnceLock::new();
+    *TOTAL.get_or_init(|| {
+        // Only Linux is covered for now, through `/proc/meminfo`.
+        let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+        let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+        let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kilobytes * 1024)
+    })
+}
+
+/// Whether the system has little enough memory that low-memory mode is worth suggesting.
+pub fn is_low_memory_system() -> bool {
+    total_memory().is_some_and(|total| total < LOW_MEMORY_THRESHOLD)
+}
@@ -0,0 +1,269 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::uri;
+
+/// One edit to a shared playlist.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    Add(String),
+    Remove(String),
+}
+
+/// Append-only edit log for a playlist kept in a synced folder (Syncthing, Dropbox, ...).
+///
+/// Every device only ever appends to its own `<device>.log`, so sync tools never see two
+/// devices writing the same file. The playlist is rebuilt at load time by replaying all logs
+/// in timestamp order. Files inside the synced folder are logged relative to it, since each
+/// device may mount the folder somewhere else.
+pub struct OpLog {
+    root: PathBuf,
+    dir: PathBuf,
+    device: String,
+}
+
+struct Entry {
+    timestamp: u128,
+    device: String,
+    line: usize,
+    op: Op,
+}
+
+impl OpLog {
+    /// Log for the playlist `name` kept in the synced folder `root`.
+    pub fn new(root: PathBuf, name: &str) -> Self {
+        let device = std::env::var("COMPUTERNAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_else(|_| "local".to_string())
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        OpLog {
+            dir: root.join("Kagi").join(name),
+            root,
+            device,
+        }
+    }
+
+    fn root_uri(&self) -> String {
+        format!("{}/", uri::from_path(&self.root))
+    }
+
+    fn to_shared(&self, uri: &str) -> String {
+        match uri.strip_prefix(&self.root_uri()) {
+            Some(relative) => format!("shared:{relative}"),
+            None => uri.to_string(),
+        }
+    }
+
+    /// The full URI of a logged track. Older logs hold unencoded URIs, so the result is
+    /// normalized either way.
+    fn from_shared(&self, logged: &str) -> String {
+        match logged.strip_prefix("shared:") {
+            Some(relative) => uri::normalize(&format!("{}{relative}", self.root_uri())),
+            None => uri::normalize(logged),
+        }
+    }
+
+    fn own_log(&self) -> PathBuf {
+        self.dir.join(format!("{}.log", self.device))
+    }
+
+    /// Whether this device has logged anything for the playlist yet.
+    pub fn started(&self) -> bool {
+        self.own_log().exists()
+    }
+
+    pub fn append(&self, op: Op) -> anyhow::Result<()> {
+        self.write(&[op])
+    }
+
+    /// Log the playlist as this device had it before sharing, so turning sharing on adds its
+    /// tracks to the shared list rather than dropping them. Does nothing once the device has a
+    /// log of its own.
+    pub fn seed(&self, uris: &[String]) -> anyhow::Result<()> {
+        if self.started() {
+            return Ok(());
+        }
+        let ops: Vec<Op> = uris.iter().cloned().map(Op::Add).collect();
+        self.write(&ops)
+    }
+
+    /// Append `ops` under one timestamp, which replay keeps in the order given.
+    fn write(&self, ops: &[Op]) -> anyhow::Result<()> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let lines: String = ops
+            .iter()
+            .map(|op| match op {
+                Op::Add(uri) => format!("{timestamp}\tadd\t{}\n", self.to_shared(uri)),
+                Op::Remove(uri) => format!("{timestamp}\tremove\t{}\n", self.to_shared(uri)),
+            })
+            .collect();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.own_log())?;
+        file.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+
+    /// Replay every device's log and return the resulting track URIs in order.
+    pub fn merge(&self) -> Vec<String> {
+        let mut entries = vec![];
+        let Ok(files) = fs::read_dir(&self.dir) else {
+            return vec![];
+        };
+        for path in files.filter_map(|file| file.ok().map(|file| file.path())) {
+            if path.extension().is_none_or(|ext| ext != "log") {
+                continue;
+            }
+            let device = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let Ok(contents) = fs::read_to_string(&path) else {
+                eprintln!("Could not read playlist log {:?}", path);
+                continue;
+            };
+            // A line cut short by an in-progress sync is skipped rather than failing the load.
+            for (line, text) in contents.lines().enumerate() {
+                let mut fields = text.splitn(3, '\t');
+                let (Some(timestamp), Some(kind), Some(uri)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                let Ok(timestamp) = timestamp.parse() else {
+                    continue;
+                };
+                let op = match kind {
+                    "add" => Op::Add(self.from_shared(uri)),
+                    "remove" => Op::Remove(self.from_shared(uri)),
+                    _ => continue,
+                };
+                entries.push(Entry {
+                    timestamp,
+                    device: device.clone(),
+                    line,
+                    op,
+                });
+            }
+        }
+        entries.sort_by(|a, b| {
+            (a.timestamp, &a.device, a.line).cmp(&(b.timestamp, &b.device, b.line))
+        });
+
+        let mut uris: Vec<String> = vec![];
+        for entry in entries {
+            match entry.op {
+                Op::Add(uri) => {
+                    if !uris.contains(&uri) {
+                        uris.push(uri);
+                    }
+                }
+                Op::Remove(uri) => uris.retain(|existing| *existing != uri),
+            }
+        }
+        uris
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh synced folder for one test, removed again when it is dropped.
+    struct Folder(PathBuf);
+
+    impl Folder {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("reyvr-oplog-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).expect("Could not create test folder");
+            Folder(path)
+        }
+
+        fn uri(&self, file: &str) -> String {
+            uri::from_path(&self.0.join(file))
+        }
+    }
+
+    impl Drop for Folder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn merge_replays_every_log_in_timestamp_order() {
+        let folder = Folder::new("merge");
+        let log = OpLog::new(folder.0.clone(), "Mix");
+        fs::create_dir_all(&log.dir).unwrap();
+        fs::write(
+            log.dir.join("desk.log"),
+            "1\tadd\tshared:a.flac\n\
+             3\tadd\tshared:c.flac\n\
+             5\tremove\tshared:a.flac\n\
+             6\tadd\thttps://example.com/stream\n\
+             7\tad",
+        )
+        .unwrap();
+        fs::write(
+            log.dir.join("laptop.log"),
+            "2\tadd\tshared:b.flac\n\
+             3\tadd\tshared:e.flac\n\
+             4\tadd\tshared:c.flac\n\
+             not a line\n\
+             6\tadd\tshared:a%20b.flac\n",
+        )
+        .unwrap();
+        fs::write(log.dir.join("notes.txt"), "8\tadd\tshared:z.flac\n").unwrap();
+
+        assert_eq!(
+            log.merge(),
+            [
+                folder.uri("b.flac"),
+                folder.uri("c.flac"),
+                folder.uri("e.flac"),
+                "https://example.com/stream".to_string(),
+                folder.uri("a b.flac"),
+            ]
+        );
+    }
+
+    #[test]
+    fn appended_ops_come_back_from_merge() {
+        let folder = Folder::new("append");
+        let log = OpLog::new(folder.0.clone(), "Mix");
+        assert!(!log.started());
+        assert!(log.merge().is_empty());
+
+        log.seed(&[folder.uri("a.flac"), folder.uri("b.flac")])
+            .unwrap();
+        assert!(log.started());
+        // Seeding again once started changes nothing.
+        log.seed(&[folder.uri("c.flac")]).unwrap();
+        log.append(Op::Remove(folder.uri("a.flac"))).unwrap();
+        log.append(Op::Add("https://example.com/stream".to_string()))
+            .unwrap();
+
+        let logged = fs::read_to_string(log.own_log()).unwrap();
+        assert!(logged.contains("\tadd\tshared:b.flac\n"));
+        assert_eq!(
+            log.merge(),
+            [
+                folder.uri("b.flac"),
+                "https://example.com/stream".to_string()
+            ]
+        );
+    }
+}
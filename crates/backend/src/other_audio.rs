@@ -0,0 +1,87 @@
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    thread,
+};
+
+use anyhow::anyhow;
+use smol::channel::Sender;
+
+/// Watches the sound server for other applications' playback and reports `true` when one
+/// starts and `false` once none is playing any more. The watch stops when this is dropped.
+///
+/// On Linux this follows the PulseAudio protocol through `pactl`, which PipeWire also speaks
+/// via pipewire-pulse. Other platforms have no monitor yet, so [`OtherAudio::start`] fails
+/// there.
+pub struct OtherAudio {
+    subscription: Child,
+}
+
+impl OtherAudio {
+    pub fn start(changes: Sender<bool>) -> anyhow::Result<Self> {
+        if !cfg!(target_os = "linux") {
+            return Err(anyhow!(
+                "Watching other audio isn't supported on this platform"
+            ));
+        }
+        let mut subscription = Command::new("pactl")
+            .arg("subscribe")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("Could not run pactl: {e}"))?;
+        let events = subscription
+            .stdout
+            .take()
+            .ok_or(anyhow!("Could not read pactl events"))?;
+
+        thread::spawn(move || {
+            let mut playing = others_playing();
+            if changes.send_blocking(playing).is_err() {
+                return;
+            }
+            for line in BufReader::new(events).lines().map_while(Result::ok) {
+                // Streams show up as sink inputs, and corking one (pausing it) is a change.
+                if !line.contains("sink-input") {
+                    continue;
+                }
+                let now = others_playing();
+                if now != playing {
+                    playing = now;
+                    if changes.send_blocking(playing).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(OtherAudio { subscription })
+    }
+}
+
+impl Drop for OtherAudio {
+    fn drop(&mut self) {
+        let _ = self.subscription.kill();
+        let _ = self.subscription.wait();
+    }
+}
+
+/// Whether a playback stream from another process is running, that is not corked.
+fn others_playing() -> bool {
+    let Ok(output) = Command::new("pactl")
+        .args(["list", "sink-inputs"])
+        // The listing is translated, and the "Corked" line is matched below.
+        .env("LC_ALL", "C")
+        .stderr(Stdio::null())
+        .output()
+    else {
+        return false;
+    };
+    let own = format!("application.process.id = \"{}\"", std::process::id());
+    String::from_utf8_lossy(&output.stdout)
+        .split("Sink Input #")
+        .skip(1)
+        .any(|stream| {
+            !stream.contains(&own) && stream.lines().any(|line| line.trim() == "Corked: no")
+        })
+}
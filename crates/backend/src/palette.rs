@@ -0,0 +1,87 @@
+//! Colors picked out of cover art, for tinting the app to match the playing track.
+
+use crate::player::Thumbnail;
+
+/// Pixels looked at along each side at most. Colors don't need every pixel of full size art.
+const SAMPLES_PER_SIDE: u32 = 96;
+/// Colors kept in [`ArtPalette::dominant`].
+const DOMINANT_COLORS: usize = 5;
+
+/// Colors of a cover, as `0xRRGGBB`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ArtPalette {
+    /// The most common colors, most common first.
+    pub dominant: Vec<u32>,
+    /// A color vivid enough to stand out as an accent, the more common and colorful the better,
+    /// if the art has one.
+    pub accent: Option<u32>,
+}
+
+impl ArtPalette {
+    pub fn of(thumbnail: &Thumbnail) -> Self {
+        // Colors are counted in buckets of 4 bits per channel, summing the pixels that fall in
+        // each so the color reported is their average rather than the bucket's corner.
+        let mut buckets = vec![(0u32, [0u64; 3]); 1 << 12];
+        let step_x = (thumbnail.width / SAMPLES_PER_SIDE).max(1);
+        let step_y = (thumbnail.height / SAMPLES_PER_SIDE).max(1);
+        for y in (0..thumbnail.height).step_by(step_y as usize) {
+            for x in (0..thumbnail.width).step_by(step_x as usize) {
+                let at = ((y * thumbnail.width + x) * 4) as usize;
+                // Kept as BGRA.
+                let Some(&[b, g, r, a]) = thumbnail.img.get(at..at + 4) else {
+                    continue;
+                };
+                if a < 128 {
+                    continue;
+                }
+                let bucket =
+                    ((r as usize >> 4) << 8) | ((g as usize >> 4) << 4) | (b as usize >> 4);
+                let (count, sum) = &mut buckets[bucket];
+                *count += 1;
+                sum[0] += r as u64;
+                sum[1] += g as u64;
+                sum[2] += b as u64;
+            }
+        }
+        let mut colors: Vec<(u32, u32)> = buckets
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, sum)| {
+                let [r, g, b] = sum.map(|channel| (channel / count as u64) as u32);
+                (count, (r << 16) | (g << 8) | b)
+            })
+            .collect();
+        colors.sort_by(|a, b| b.0.cmp(&a.0));
+        let accent = colors
+            .iter()
+            .filter(|(_, color)| is_vivid(*color))
+            .max_by_key(|(count, color)| (*count as f32 * saturation(*color)) as u64)
+            .map(|(_, color)| *color);
+        ArtPalette {
+            dominant: colors
+                .into_iter()
+                .take(DOMINANT_COLORS)
+                .map(|(_, color)| color)
+                .collect(),
+            accent,
+        }
+    }
+}
+
+fn channels(color: u32) -> [f32; 3] {
+    [color >> 16, color >> 8, color].map(|channel| (channel & 0xff) as f32 / 255.0)
+}
+
+fn saturation(color: u32) -> f32 {
+    let [r, g, b] = channels(color);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    if max == 0.0 { 0.0 } else { (max - min) / max }
+}
+
+/// Colorful, and neither so dark nor so light it would vanish against a dark or light theme.
+fn is_vivid(color: u32) -> bool {
+    let [r, g, b] = channels(color);
+    let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    saturation(color) >= 0.35 && (0.25..=0.85).contains(&luma)
+}
@@ -1,15 +1,18 @@
 use std::{
+    collections::{HashMap, HashSet},
     fs::{self, File},
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
+    time::UNIX_EPOCH,
 };
 
+use anyhow::anyhow;
 use bincode::config;
 use directories::UserDirs;
 use serde::{Deserialize, Serialize};
 
-use crate::{Backend, player::Thumbnail};
+use crate::{Backend, artists::ArtistRules, player::Thumbnail, text, uri};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Track {
@@ -19,6 +22,69 @@ pub struct Track {
     pub uri: String,
     pub duration: u64,
     pub thumbnail: Option<Thumbnail>,
+    /// Position on its disc, or 0 when untagged.
+    pub track_number: u32,
+    /// Disc of a multi-disc album, or 0 when untagged.
+    pub disc_number: u32,
+    /// Empty when untagged.
+    pub genre: String,
+    /// Release year, or 0 when untagged.
+    pub year: u32,
+    /// Stars from 0 to 5, with 0 also meaning unrated.
+    pub rating: u32,
+    /// When the file came into the library, in seconds since the Unix epoch, or 0 if unknown.
+    pub added: u64,
+    /// Failed the decode probe on the last verified scan, or failed to play.
+    pub corrupt: bool,
+    /// The file wasn't there when last checked. Worked out whenever the track is read from a
+    /// cache rather than saved in it, so tracks come back by themselves with their drive.
+    #[serde(skip)]
+    pub missing: bool,
+    /// How the track was first added.
+    pub origin: Origin,
+    /// Title this entry shows under in its playlist instead of the tagged one, or empty.
+    /// The file's tags are left alone.
+    pub custom_title: String,
+    /// The playlist's note on this entry, like a cue in a set list, or empty.
+    pub note: String,
+}
+
+/// How a track came into the library or the queue, shown with its details and in problem
+/// reports to explain why it is there.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Origin {
+    /// Added before origins were recorded, or read straight from a stream.
+    #[default]
+    Unknown,
+    /// Found scanning or watching a folder.
+    FolderScan,
+    /// Picked with Open Files.
+    OpenedFiles,
+    /// Dropped on the window from a file manager.
+    Dropped,
+    /// Named on the command line.
+    CommandLine,
+    /// Opened from a `reyvr://` link as the app started.
+    Link,
+    /// Sent to the running app through its query server, like a link opened elsewhere.
+    Remote,
+    /// Listed in an imported playlist.
+    Import,
+}
+
+impl Origin {
+    pub fn label(self) -> &'static str {
+        match self {
+            Origin::Unknown => "Unknown",
+            Origin::FolderScan => "Folder scan",
+            Origin::OpenedFiles => "Opened files",
+            Origin::Dropped => "Dropped on the window",
+            Origin::CommandLine => "Command line",
+            Origin::Link => "Link",
+            Origin::Remote => "Another program",
+            Origin::Import => "Playlist import",
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -32,11 +98,89 @@ pub struct SavedPlaylists {
     pub playlists: Vec<SavedPlaylist>,
 }
 
-#[derive(Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct SavedPlaylist {
     pub name: String,
     pub actual_path: String,
     pub cached_name: String,
+    #[serde(default)]
+    pub shuffle_mode: ShuffleMode,
+    /// Seed the playlist was last shuffled from, so reopening it brings the same order back.
+    #[serde(default)]
+    pub shuffle_seed: Option<u64>,
+}
+
+/// How a saved playlist is put in order when shuffled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ShuffleMode {
+    /// Every track in random order.
+    #[default]
+    Tracks,
+    /// Albums in random order, each played through in disc and track order.
+    Albums,
+}
+
+/// How merging one playlist into another treats tracks both of them have.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DuplicateTracks {
+    /// Leave the existing entry alone and don't add the track again.
+    Skip,
+    /// Add the track again at the end.
+    Keep,
+    /// Swap the existing entry for the merged one, keeping its place in the playlist.
+    Replace,
+}
+
+impl DuplicateTracks {
+    pub const ALL: [DuplicateTracks; 3] = [
+        DuplicateTracks::Skip,
+        DuplicateTracks::Keep,
+        DuplicateTracks::Replace,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DuplicateTracks::Skip => "Skip duplicates",
+            DuplicateTracks::Keep => "Keep duplicates",
+            DuplicateTracks::Replace => "Replace duplicates",
+        }
+    }
+}
+
+/// A change to the tracks of a saved playlist, by position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaylistEdit {
+    /// Take the track at `from` out and put it back in at `to`.
+    Move {
+        from: usize,
+        to: usize,
+    },
+    Remove(usize),
+}
+
+pub const LISTEN_LATER_NAME: &str = "Listen Later";
+pub const LISTEN_LATER_CACHE: &str = "listen_later";
+/// File extensions picked up when scanning folders or opening files.
+pub const AUDIO_EXTENSIONS: [&str; 4] = ["mp3", "flac", "wav", "ogg"];
+/// Music video extensions, picked up as well when built with the `video` feature.
+pub const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mkv", "webm", "mov"];
+
+pub fn is_audio(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        let ext = ext.to_string_lossy().to_lowercase();
+        AUDIO_EXTENSIONS.contains(&ext.as_str())
+            || (cfg!(feature = "video") && VIDEO_EXTENSIONS.contains(&ext.as_str()))
+    })
+}
+
+/// When `path` came into the library: its creation time where the filesystem keeps one,
+/// otherwise its last modification.
+fn added_time(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|meta| meta.created().or_else(|_| meta.modified()))
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_secs())
 }
 
 impl Track {
@@ -48,8 +192,36 @@ impl Track {
             title: "Unknown Track".into(),
             uri: "".to_string(),
             thumbnail: None,
+            track_number: 0,
+            disc_number: 0,
+            genre: String::new(),
+            year: 0,
+            rating: 0,
+            added: 0,
+            corrupt: false,
+            missing: false,
+            origin: Origin::Unknown,
+            custom_title: String::new(),
+            note: String::new(),
         }
     }
+
+    /// Local filesystem path of the track, if its URI points to a file.
+    pub fn path(&self) -> Option<PathBuf> {
+        uri::to_path(&self.uri)
+    }
+
+    /// Look again for the track's file, updating and returning [`Track::missing`]. Streams
+    /// are never missing.
+    pub fn check_missing(&mut self) -> bool {
+        self.missing = self.path().is_some_and(|path| !path.exists());
+        self.missing
+    }
+
+    /// Whether the track is streamed over the network rather than read from disk.
+    pub fn is_remote(&self) -> bool {
+        self.uri.starts_with("http://") || self.uri.starts_with("https://")
+    }
 }
 
 impl Playlist {
@@ -59,7 +231,10 @@ impl Playlist {
             tracks: vec![],
         }
     }
-    pub async fn from_dir(backend: &Arc<dyn Backend>, dir: PathBuf) -> Self {
+    /// Scan `dir` and its subfolders, down to `options.max_depth`, for audio files. With
+    /// `options.verify` set, every file is also decode-probed and flagged as corrupt if that
+    /// fails.
+    pub async fn from_dir(backend: &Arc<dyn Backend>, dir: PathBuf, options: &ScanOptions) -> Self {
         let mut playlist = Playlist {
             name: dir
                 .file_name()
@@ -68,38 +243,145 @@ impl Playlist {
             tracks: Vec::new(),
         };
 
-        if let Ok(entries) = std::fs::read_dir(&dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-
-                    if let Some(ext) = path.extension() {
-                        let ext = ext.to_string_lossy().to_lowercase();
-                        if ext == "mp3" || ext == "flac" || ext == "wav" || ext == "ogg" {
-                            let uri =
-                                format!("file:///{}", path.to_string_lossy().replace("\\", "/"));
-
-                            let track = match backend.get_meta(&uri).await {
-                                Ok(t) => t,
-                                Err(_) => {
-                                    eprintln!("Failed to load metadata for {:?}", uri);
-                                    Track {
-                                        title: path
-                                            .file_stem()
-                                            .map(|n| n.to_string_lossy().to_string())
-                                            .unwrap_or_else(|| "Unknown Track".into()),
-                                        uri: uri.clone(),
-                                        ..Track::default()
-                                    }
-                                }
-                            };
-                            playlist.tracks.push(track);
-                        }
-                    }
+        for path in scan_paths(&dir, options) {
+            playlist
+                .tracks
+                .push(Self::load_track(backend, &path, options.verify, Origin::FolderScan).await);
+        }
+        playlist
+    }
+
+    /// Build a playlist called `name` from individually picked files, in the order given.
+    /// Anything that isn't a supported audio file is skipped.
+    pub async fn from_files(
+        backend: &Arc<dyn Backend>,
+        name: String,
+        paths: Vec<PathBuf>,
+        verify: bool,
+        origin: Origin,
+    ) -> Self {
+        let mut playlist = Playlist {
+            name,
+            tracks: Vec::new(),
+        };
+        for path in paths.into_iter().filter(|path| is_audio(path)) {
+            playlist
+                .tracks
+                .push(Self::load_track(backend, &path, verify, origin).await);
+        }
+        playlist
+    }
+
+    pub async fn load_track(
+        backend: &Arc<dyn Backend>,
+        path: &Path,
+        verify: bool,
+        origin: Origin,
+    ) -> Track {
+        let uri = uri::from_path(path);
+
+        let mut track = match backend.get_meta(&uri).await {
+            Ok(t) => t,
+            Err(_) => {
+                eprintln!("Failed to load metadata for {:?}", uri);
+                Track {
+                    title: path
+                        .file_stem()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "Unknown Track".into()),
+                    uri: uri.clone(),
+                    ..Track::default()
                 }
             }
+        };
+        track.added = added_time(path);
+        track.origin = origin;
+        if verify {
+            if let Err(e) = backend.probe(&uri).await {
+                eprintln!("Failed to decode {:?}: {}", uri, e);
+                track.corrupt = true;
+            }
+        }
+        track
+    }
+
+    /// Add `tracks` to the end of this playlist, handling tracks it already has as
+    /// `duplicates` says. Returns how many tracks were added or replaced.
+    pub fn merge(&mut self, tracks: &[Track], duplicates: DuplicateTracks) -> usize {
+        let mut changed = 0;
+        for track in tracks {
+            let existing = self.tracks.iter().position(|t| t.uri == track.uri);
+            match (existing, duplicates) {
+                (Some(_), DuplicateTracks::Skip) => continue,
+                (Some(index), DuplicateTracks::Replace) => self.tracks[index] = track.clone(),
+                _ => self.tracks.push(track.clone()),
+            }
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Apply `edit`, returning the track it moved or removed, or `None` if a position is out of
+    /// range.
+    pub fn edit(&mut self, edit: PlaylistEdit) -> Option<Track> {
+        let len = self.tracks.len();
+        match edit {
+            PlaylistEdit::Move { from, to } => {
+                if from >= len || to >= len {
+                    return None;
+                }
+                let track = self.tracks.remove(from);
+                self.tracks.insert(to, track.clone());
+                Some(track)
+            }
+            PlaylistEdit::Remove(index) => (index < len).then(|| self.tracks.remove(index)),
+        }
+    }
+
+    pub fn check_missing(&mut self) {
+        for track in self.tracks.iter_mut() {
+            track.check_missing();
+        }
+    }
+
+    /// Drop the tracks flagged as missing, returning their URIs.
+    pub fn remove_missing(&mut self) -> Vec<String> {
+        let removed = self
+            .tracks
+            .iter()
+            .filter(|track| track.missing)
+            .map(|track| track.uri.clone())
+            .collect();
+        self.tracks.retain(|track| !track.missing);
+        removed
+    }
+
+    /// Point tracks whose files were moved at their new location. `moved` maps old URIs to
+    /// new ones. Returns how many tracks changed.
+    pub fn rebind(&mut self, moved: &HashMap<String, String>) -> usize {
+        let mut changed = 0;
+        for track in self.tracks.iter_mut() {
+            if let Some(uri) = moved.get(&track.uri) {
+                track.uri = uri.clone();
+                track.missing = false;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Bring track URIs saved by older versions into the form [`uri::from_path`] gives.
+    pub fn normalize_uris(&mut self) {
+        for track in self.tracks.iter_mut() {
+            track.uri = uri::normalize(&track.uri);
+        }
+    }
+
+    /// Re-derive every track's artists from `rules`.
+    pub fn apply_artist_rules(&mut self, rules: &ArtistRules) {
+        for track in self.tracks.iter_mut() {
+            track.artists = rules.apply(&track.artists);
         }
-        playlist
     }
 
     pub async fn write_cached(&self, cached_name: String) -> anyhow::Result<()> {
@@ -121,6 +403,21 @@ impl Playlist {
         Ok(())
     }
 
+    /// Delete the cache written under `cached_name`, if there is one.
+    pub fn remove_cached(cached_name: &str) -> io::Result<()> {
+        let cached_path = UserDirs::new()
+            .unwrap()
+            .audio_dir()
+            .unwrap_or(UserDirs::new().unwrap().home_dir())
+            .join("Kagi")
+            .join("cache")
+            .join(cached_name);
+        match fs::remove_file(cached_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
     pub async fn read_cached(cached_name: String) -> Option<Playlist> {
         let cached_path = UserDirs::new()
             .unwrap()
@@ -131,18 +428,136 @@ impl Playlist {
             .join(cached_name);
 
         if cached_path.exists() {
-            let cached_data = &fs::read(cached_path).expect("Could not read file");
-            let deserialized: Playlist =
-                bincode::serde::decode_from_slice(cached_data, config::standard())
-                    .expect("Could not decode playlist")
-                    .0;
-            return Some(deserialized);
+            let cached_data = match fs::read(&cached_path) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Could not read cached playlist: {}", e);
+                    return None;
+                }
+            };
+            // Caches written by older versions may not decode; the caller rescans the folder.
+            match bincode::serde::decode_from_slice(&cached_data, config::standard()) {
+                Ok((mut deserialized, _)) => {
+                    Playlist::normalize_uris(&mut deserialized);
+                    Playlist::check_missing(&mut deserialized);
+                    Some(deserialized)
+                }
+                Err(e) => {
+                    eprintln!("Could not decode cached playlist: {}", e);
+                    None
+                }
+            }
         } else {
             return None;
         }
     }
 }
 
+/// How folders are scanned into playlists.
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    /// Decode-probe every file to catch corrupt or truncated ones.
+    pub verify: bool,
+    /// Levels of subfolders to descend into. 0 only reads the folder itself.
+    pub max_depth: usize,
+    /// Skip folders whose names start with a dot.
+    pub skip_hidden: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            verify: false,
+            max_depth: 8,
+            skip_hidden: true,
+        }
+    }
+}
+
+/// Whether a scan of `root` with `options` would reach `path`.
+pub fn in_scan(root: &Path, path: &Path, options: &ScanOptions) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let folders: Vec<_> = relative.parent().map_or(vec![], |dir| dir.iter().collect());
+    folders.len() <= options.max_depth
+        && !(options.skip_hidden
+            && folders
+                .iter()
+                .any(|name| name.to_string_lossy().starts_with('.')))
+}
+
+/// Every audio file under `dir`, in the order they appear in the playlist.
+pub fn scan_paths(dir: &Path, options: &ScanOptions) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    collect_audio(dir, 0, options, &mut HashSet::new(), &mut paths);
+    paths
+}
+
+/// Append the audio files under `dir` to `paths` in collation order, with each subfolder's
+/// files in place of the subfolder. `visited` holds canonical paths so symlinked folders that
+/// loop back on themselves are only read once.
+fn collect_audio(
+    dir: &Path,
+    depth: usize,
+    options: &ScanOptions,
+    visited: &mut HashSet<PathBuf>,
+    paths: &mut Vec<PathBuf>,
+) {
+    match dir.canonicalize() {
+        Ok(canonical) => {
+            if !visited.insert(canonical) {
+                return;
+            }
+        }
+        Err(e) => {
+            eprintln!("Could not read {:?}: {}", dir, e);
+            return;
+        }
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort_by(|a, b| {
+        text::collate(
+            &a.file_name().unwrap_or_default().to_string_lossy(),
+            &b.file_name().unwrap_or_default().to_string_lossy(),
+        )
+    });
+    for path in entries {
+        if path.is_dir() {
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if depth < options.max_depth && !(options.skip_hidden && hidden) {
+                collect_audio(&path, depth + 1, options, visited, paths);
+            }
+        } else if is_audio(&path) {
+            paths.push(path);
+        }
+    }
+}
+
+impl SavedPlaylist {
+    /// The auto-created playlist that bookmarked tracks are added to. It has no backing folder
+    /// and only exists in the cache.
+    pub fn listen_later() -> Self {
+        SavedPlaylist {
+            name: LISTEN_LATER_NAME.to_string(),
+            actual_path: String::new(),
+            cached_name: LISTEN_LATER_CACHE.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_listen_later(&self) -> bool {
+        self.cached_name == LISTEN_LATER_CACHE
+    }
+}
+
 impl SavedPlaylists {
     pub fn default() -> Self {
         SavedPlaylists { playlists: vec![] }
@@ -185,6 +600,129 @@ impl SavedPlaylists {
             SavedPlaylists::default()
         }
     }
+    /// Save a copy of `playlist`, the contents of the saved playlist at `index`, under a name
+    /// no other playlist has. The copy isn't tied to a folder, so rescans never touch it.
+    pub async fn duplicate(
+        &mut self,
+        index: usize,
+        playlist: &Playlist,
+    ) -> anyhow::Result<SavedPlaylist> {
+        let source = self
+            .playlists
+            .get(index)
+            .ok_or(anyhow!("No playlist to duplicate"))?;
+        let copy = self.unused(
+            format!("{} (copy)", source.name),
+            format!("{}_copy", source.cached_name),
+        );
+
+        Playlist {
+            name: copy.name.clone(),
+            tracks: playlist.tracks.clone(),
+        }
+        .write_cached(copy.cached_name.clone())
+        .await?;
+        self.playlists.insert(index + 1, copy.clone());
+        Self::save_playlists(self)?;
+        Ok(copy)
+    }
+
+    /// Save `playlist` as a new playlist at the end, under its own name or a numbered one if
+    /// that is taken.
+    pub async fn add(
+        &mut self,
+        playlist: &Playlist,
+        cached_name: String,
+    ) -> anyhow::Result<SavedPlaylist> {
+        let saved = self.unused(playlist.name.clone(), cached_name);
+        Playlist {
+            name: saved.name.clone(),
+            tracks: playlist.tracks.clone(),
+        }
+        .write_cached(saved.cached_name.clone())
+        .await?;
+        self.playlists.push(saved.clone());
+        Self::save_playlists(self)?;
+        Ok(saved)
+    }
+
+    /// Rename the playlist at `index`, returning it as it was. Names have to be unique, as the
+    /// loaded playlist is told apart by its name.
+    pub fn rename(&mut self, index: usize, name: String) -> anyhow::Result<SavedPlaylist> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(anyhow!("Playlist names can't be empty"));
+        }
+        if self
+            .playlists
+            .iter()
+            .enumerate()
+            .any(|(other, p)| other != index && p.name == name)
+        {
+            return Err(anyhow!("There already is a playlist called {name}"));
+        }
+        let saved = self
+            .playlists
+            .get_mut(index)
+            .ok_or(anyhow!("No playlist to rename"))?;
+        if saved.is_listen_later() {
+            return Err(anyhow!("{LISTEN_LATER_NAME} can't be renamed"));
+        }
+        let old = saved.clone();
+        saved.name = name;
+        Self::save_playlists(self)?;
+        Ok(old)
+    }
+
+    /// Remember how the playlist called `name` is shuffled, and the seed its shuffled order
+    /// comes from when one is given.
+    pub fn set_shuffle(
+        &mut self,
+        name: &str,
+        mode: ShuffleMode,
+        seed: Option<u64>,
+    ) -> io::Result<()> {
+        let Some(saved) = self.playlists.iter_mut().find(|saved| saved.name == name) else {
+            return Ok(());
+        };
+        saved.shuffle_mode = mode;
+        saved.shuffle_seed = seed.or(saved.shuffle_seed);
+        Self::save_playlists(self)
+    }
+
+    /// Forget the playlist at `index` and drop its cache, returning it.
+    pub fn remove(&mut self, index: usize) -> anyhow::Result<SavedPlaylist> {
+        if index >= self.playlists.len() {
+            return Err(anyhow!("No playlist to delete"));
+        }
+        let saved = self.playlists.remove(index);
+        Self::save_playlists(self)?;
+        Playlist::remove_cached(&saved.cached_name)?;
+        Ok(saved)
+    }
+
+    /// A playlist without a folder named `name` and cached as `cached_name`, numbered when
+    /// either is already in use.
+    fn unused(&self, name: String, cached_name: String) -> SavedPlaylist {
+        let mut saved = SavedPlaylist {
+            name: name.clone(),
+            actual_path: String::new(),
+            cached_name: cached_name.clone(),
+            ..Default::default()
+        };
+        let mut number = 1;
+        while self
+            .playlists
+            .iter()
+            .any(|p| p.name == saved.name || p.cached_name == saved.cached_name)
+        {
+            number += 1;
+            saved.name = format!("{name} {number}");
+            saved.cached_name = format!("{cached_name}_{number}");
+        }
+        saved
+    }
+
     pub fn save_playlists(saved: &SavedPlaylists) -> io::Result<()> {
         if let Some(file_path) = Self::get_playlists_file() {
             let toml_str =
@@ -1,21 +1,61 @@
 use std::{
+    collections::{HashMap, HashSet},
     num::NonZeroUsize,
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
+use futures::{StreamExt, select};
 use gstreamer::State;
-use image::{Frame, RgbaImage, imageops::thumbnail};
-use rand::seq::SliceRandom;
+use image::{Frame, ImageFormat, RgbaImage, imageops::thumbnail};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 use ring_channel::{RingReceiver as Receiver, RingSender as Sender};
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 use crate::{
     Backend,
-    playback::{Playlist, SavedPlaylist, SavedPlaylists, Track},
+    artists::ArtistRules,
+    chapters::{self, Chapter, ChapterEdit, Chapters},
+    export::{self, ExportPaths},
+    file_manager,
+    import::{self, Location},
+    insights::{self, Insights},
+    ipc::{Query, QueueEntry, Status},
+    library::{self, Album, Artist, Library, LibraryFilter},
+    link::PlayLink,
+    loudness::{Loudness, LoudnessQueue},
+    lyrics::{self, Lyrics, LyricsEdit},
+    lyrics_online::{FoundLyrics, LyricsFinder, LyricsQuery},
+    matching,
+    oplog::{Op, OpLog},
+    other_audio::OtherAudio,
+    playback::{
+        self, AUDIO_EXTENSIONS, DuplicateTracks, LISTEN_LATER_CACHE, LISTEN_LATER_NAME, Origin,
+        Playlist, PlaylistEdit, SavedPlaylist, SavedPlaylists, ScanOptions, ShuffleMode, Track,
+    },
+    ratings::Ratings,
+    roots::{LibraryRoot, LibraryRoots, RootScan, RootStatus},
+    scanner::{ScanJob, ScanUpdate},
+    scrobble::{Listen, Listening, ScrobbleAccounts, Scrobbler},
+    session::{RecentFolders, RecentSessions, ResumeCard, Session, TrackPositions},
+    settings::{MAX_BOOSTED_VOLUME, Settings},
+    skips::{SkippedTrack, Skips},
+    smart::{PlayCounts, SmartPlaylist, SmartPlaylists},
+    stay_awake::{Inhibitor, StayAwake},
+    system_events::{SystemEvent, SystemEvents},
+    tags::{TagEdit, TagEdits},
+    text::{self, Collation},
+    uri,
+    watcher::FolderWatcher,
+    waveform::WaveformQueue,
 };
 
+#[derive(Debug)]
 pub enum Command {
     Play,
     Pause,
@@ -27,20 +67,241 @@ pub enum Command {
     Seek(u64),
     PlayId(usize),
     LoadFromFolder(SavedPlaylist),
+    /// Stop the folder scan in progress, keeping the tracks found so far.
+    CancelScan,
+    /// Queue up individually chosen audio files, in the order given.
+    LoadFiles(Vec<PathBuf>, Origin),
+    /// Pick audio files with a file dialog and load them.
+    OpenFiles,
+    /// Pick an M3U, PLS or XSPF playlist with a file dialog and add it to the saved playlists.
+    ImportPlaylist,
+    /// Play the file of a `reyvr://` link from its position, in place of the queue.
+    OpenLink(PlayLink, Origin),
+    /// Play these files in place of the queue, as when the app is opened with them.
+    PlayFiles(Vec<PathBuf>, Origin),
+    /// Make a link to the current track at the current position.
+    ShareLink,
     LoadFolder,
+    /// Open a folder from the recently used ones again.
+    OpenRecentFolder(PathBuf),
+    OpenContainingFolder,
+    RevealCurrent,
     LoadSavedPlaylists,
     WriteSavedPlaylists,
     RetrieveSavedPlaylists,
+    /// Save a copy of the saved playlist at this index, right after it.
+    DuplicatePlaylist(usize),
+    /// Add the tracks of one saved playlist to the end of another.
+    MergePlaylist {
+        from: usize,
+        into: usize,
+        duplicates: DuplicateTracks,
+    },
+    /// Send the tracks of the saved playlist at this index, for editing them.
+    GetPlaylistTracks(usize),
+    RenamePlaylist(usize, String),
+    DeletePlaylist(usize),
+    /// Move or remove a track of the saved playlist at this index.
+    EditPlaylist(usize, PlaylistEdit),
+    /// Give an entry of a saved playlist its own title and note within that playlist.
+    LabelPlaylistEntry {
+        playlist: usize,
+        entry: usize,
+        title: String,
+        note: String,
+    },
+    /// Write the saved playlist at this index to a playlist file picked by the user.
+    ExportPlaylist(usize, ExportPaths),
+    /// Write the queue, in playing order, to a playlist file picked by the user.
+    ExportQueue(ExportPaths),
     Shuffle,
+    /// Shuffle the loaded playlist by track or by album from now on, remembered with it.
+    SetShuffleMode(ShuffleMode),
+    SetBalance(f32),
+    SetMono(bool),
+    SetArtistRules(ArtistRules),
+    /// Sort names this way from now on, and send the library again in the new order.
+    SetCollation(Collation),
+    SetSkipSilence(bool),
+    SetExclusive(bool),
+    /// Buffer less ahead and decode smaller artwork, for systems short on memory.
+    SetLowMemory(bool),
+    /// Play to the output device of this name, or the system default with `None`.
+    SetOutputDevice(Option<String>),
+    GetOutputDevices,
+    ListenLater,
+    /// Take the current track out of the Listen Later playlist.
+    RemoveFromListenLater,
+    SetRepeat(bool),
+    /// Add similar library tracks to the queue when it runs out, rather than stop.
+    SetAutofill(bool),
+    /// Highest volume allowed, lowering the current one if it is above.
+    SetMaxVolume(f64),
+    /// Write the session now, answering on the channel once it is written.
+    SaveSession(smol::channel::Sender<()>),
+    RestoreSession,
+    /// Start at this volume rather than the restored session's, as asked on the command line.
+    StartVolume(f64),
+    ResumeRecent(usize),
+    /// Minimum track length in seconds for per-track resume, or 0 to disable it.
+    SetResumeThreshold(u64),
+    SetVerifyOnScan(bool),
+    /// Levels of subfolders scanned below a playlist folder.
+    SetScanDepth(usize),
+    /// Leave out folders whose names start with a dot while scanning.
+    SetSkipHidden(bool),
+    /// Pre-amp gain in decibels.
+    SetPreamp(f64),
+    SetSpectrum(bool),
+    /// Highest volume playback may start at after launch or an output switch, or `None` to
+    /// start at the saved level straight away.
+    SetLoudStartCap(Option<f64>),
+    /// Match keys of tracks whose remote copy is played instead of the local file.
+    SetPreferRemote(HashSet<String>),
+    /// Switch the current track between its local and remote copy.
+    ToggleSource,
+    /// Folder synced between devices that shared playlists keep their edit logs in.
+    SetSharedDir(Option<PathBuf>),
+    /// Pause while another application plays audio.
+    SetPauseOnOtherAudio(bool),
+    /// Carry on once the other application stops, after pausing for it.
+    SetResumeAfterOtherAudio(bool),
+    /// Pause when headphones or a Bluetooth device are disconnected, and before the system
+    /// suspends.
+    SetPauseOnDisconnect(bool),
+    /// Answer a scripting query with JSON on the given channel.
+    Query(Query, smol::channel::Sender<String>),
+    /// Change the chapter markers of the current track.
+    EditChapters(ChapterEdit),
+    NextChapter,
+    PreviousChapter,
+    /// Write the current track's chapters to a `.cue` file next to it.
+    ExportCue,
+    /// Correct the current track's lyrics and save them next to it.
+    EditLyrics(LyricsEdit),
+    /// Look lyrics up online for tracks without any of their own.
+    SetOnlineLyrics(bool),
+    /// Base URLs of LRCLIB-compatible servers to look lyrics up on, in order.
+    SetLyricsProviders(Vec<String>),
+    /// Look the current track's lyrics up online again by a corrected title and artist.
+    SearchLyrics(LyricsQuery),
+    /// Rebuild the library from the saved playlists and send its albums and artists.
+    GetLibrary,
+    GetLibraryRoots,
+    /// Send the loudness of every track measured so far.
+    GetLoudness,
+    /// Send how many times each track has been played through.
+    GetPlayCounts,
+    /// Pick a folder with a file dialog, add it to the library and scan it.
+    AddLibraryRoot,
+    /// Scan the library folder at this index again, in the background.
+    RescanLibraryRoot(usize),
+    /// Take the library folder at this index, and its tracks, out of the library.
+    RemoveLibraryRoot(usize),
+    /// Drop tracks whose files are gone from the library folders, saved playlists and queue.
+    RemoveMissing,
+    /// Narrow the albums, artists and search results to tracks matching the filter, then
+    /// send the library again.
+    FilterLibrary(LibraryFilter),
+    /// Search the library by title, artist, album and path.
+    Search(String),
+    /// Play the results of a search, best match first, as a queue that isn't saved anywhere.
+    PlaySearch(String),
+    /// Keep the queue as a saved playlist under the given name, or its own, and go on with that
+    /// playlist.
+    SaveQueue(Option<String>),
+    /// Add tracks, by URI, to the end of the queue.
+    Enqueue(Vec<String>),
+    /// Add audio files to the end of the queue, with folders replaced by the audio files in them
+    /// as a folder scan would find them. They are read in the background.
+    EnqueuePaths {
+        paths: Vec<PathBuf>,
+        origin: Origin,
+        /// Start playing the first of them once it is read.
+        play: bool,
+    },
+    /// Put tracks, by URI, right after the current one, in the order given.
+    PlayNext(Vec<String>),
+    /// Take the queue entry at this index out. Taking out the current track plays the next.
+    RemoveTrack(usize),
+    /// Take the queue entries at these indices out at once.
+    RemoveTracks(Vec<usize>),
+    /// Add tracks, by URI, to the end of the saved playlist at this index.
+    AddToPlaylist(usize, Vec<String>),
+    /// Change the tags of tracks, by URI, as the app shows them.
+    EditTags(Vec<String>, TagEdit),
+    /// Empty the queue and stop playing.
+    ClearQueue,
+    /// Move the queue entry at `from` to `to`. The current track stays current.
+    MoveTrack {
+        from: usize,
+        to: usize,
+    },
+    /// Put the queue in this order, by URI. The current track stays current.
+    ReorderQueue(Vec<String>),
+    /// Replace the queue with an album from the library, in disc and track order, and play it.
+    PlayAlbum {
+        album: String,
+        artist: String,
+    },
+    /// Add an album from the library to the end of the queue, in disc and track order.
+    QueueAlbum {
+        album: String,
+        artist: String,
+    },
+    /// Add every library track by this artist to the end of the queue, an album at a time.
+    QueueArtist(String),
+    GetSmartPlaylists,
+    /// Replace the smart playlist at `index`, or add a new one when `None`.
+    SaveSmartPlaylist {
+        index: Option<usize>,
+        playlist: SmartPlaylist,
+    },
+    DeleteSmartPlaylist(usize),
+    PlaySmartPlaylist(usize),
+    /// Rate the current track from 1 to 5 stars, or 0 to clear its rating.
+    Rate(u32),
+    GetMostSkipped,
+    /// Forget every recorded skip.
+    ResetSkips,
+    /// Count which views, commands and settings get used, or stop counting.
+    SetInsights(bool),
+    /// Keep the computer awake while playing, to this extent.
+    SetStayAwake(StayAwake),
+    /// Count a view of the main area being opened, while insights are on.
+    RecordView(String),
+    /// Count changes to these settings, by their names in the settings file, while insights
+    /// are on.
+    RecordSettings(Vec<String>),
+    GetInsights,
+    /// Forget everything counted so far.
+    ClearInsights,
+    /// Send listens to these accounts from now on.
+    SetScrobbleAccounts(ScrobbleAccounts),
+    /// Start signing in to Last.fm with the API key in the scrobble accounts.
+    ConnectLastFm,
+    /// Finish signing in to Last.fm once access was allowed in the browser.
+    FinishLastFm,
+    /// Check a ListenBrainz user token, and whose it is.
+    CheckListenBrainz(String),
+    /// Send a JSON status line on the given channel now and whenever the status changes, until
+    /// the receiving end is dropped.
+    Subscribe(smol::channel::Sender<String>),
 }
 
 #[derive(Clone)]
 pub enum Response {
     Error(String),
+    /// The current track's own data couldn't be demuxed or decoded. The player passes it on
+    /// to the UI as an [`Response::Error`].
+    Undecodable(String),
     Warning(String),
     Info(String),
     Metadata(Track),
     StateChanged(State),
+    /// The pipeline settled in this state, which the player checks its own against. Not sent
+    /// on to the UI.
+    PipelineState(State),
     Eos,
     StreamStart,
     Position(u64),
@@ -48,7 +309,375 @@ pub enum Response {
     Tracks(Vec<Track>),
     SavedPlaylists(SavedPlaylists),
     PlaylistName(String),
+    /// Tracks of the saved playlist at this index, in order.
+    PlaylistTracks(usize, Vec<Track>),
     Shuffle(bool),
+    /// How the loaded playlist is shuffled.
+    ShuffleMode(ShuffleMode),
+    Buffering(i32),
+    Stalled,
+    Recovered,
+    BackendReady,
+    /// Names of the audio output devices that can be played to.
+    OutputDevices(Vec<String>),
+    StreamInfo {
+        codec: String,
+        /// Bits per second, or 0 when the stream doesn't report one.
+        bitrate: u32,
+        sample_rate: u32,
+        channels: u32,
+        bit_depth: u32,
+        /// Whether the device is sent exactly what the decoder put out, with no conversion.
+        bit_perfect: bool,
+    },
+    Exclusive(bool),
+    ListenLater(usize),
+    Repeat(bool),
+    Volume(f64),
+    ResumeCards(Vec<ResumeCard>),
+    /// Folders opened as playlists, most recent first.
+    RecentFolders(Vec<PathBuf>),
+    /// Band magnitudes from 0.0 to 1.0, lowest frequency first.
+    SpectrumFrame(Vec<f32>),
+    /// Peak amplitudes from 0.0 to 1.0 across the current track.
+    Waveform(Vec<f32>),
+    /// Loudness of every track measured so far, by URI.
+    Loudness(HashMap<String, Loudness>),
+    /// Times each track has been played through, by URI.
+    PlayCounts(HashMap<String, u32>),
+    /// The updated set of tracks preferring their remote copy, for saving to settings.
+    PreferRemote(Vec<String>),
+    /// Files read so far by the folder scan in progress, out of `total`.
+    ScanProgress {
+        scanned: usize,
+        total: usize,
+    },
+    /// The folder scan completed or was cancelled.
+    ScanFinished,
+    /// Albums and artists with tracks the library filter lets through, along with every
+    /// genre and decade in the whole library to filter by and how many of its tracks are
+    /// missing their file.
+    Library {
+        albums: Vec<Album>,
+        artists: Vec<Artist>,
+        genres: Vec<String>,
+        decades: Vec<u32>,
+        missing: usize,
+    },
+    /// Library tracks matching the last search, best match first.
+    SearchResults(Vec<Track>),
+    /// The queue just loaded isn't saved anywhere, until the next [`Response::PlaylistName`].
+    UnsavedQueue,
+    /// A link was opened, so the window should come to the front.
+    LinkOpened,
+    /// `reyvr://` link to the current track at the current position.
+    ShareLink(String),
+    /// Every library folder and how its scan stands.
+    LibraryRoots(Vec<(LibraryRoot, RootStatus)>),
+    /// Chapter markers of the current track, in editor order.
+    Chapters(Vec<Chapter>),
+    /// Lyrics of the current track, if it has any.
+    Lyrics(Option<Lyrics>),
+    /// Lyrics found in the current track's tags, plain or in LRC form. The player answers with
+    /// [`Response::Lyrics`] unless a lyrics file next to the track takes precedence.
+    EmbeddedLyrics(String),
+    /// Every smart playlist with the number of tracks it matches right now.
+    SmartPlaylists(Vec<(SmartPlaylist, usize)>),
+    /// The current track was just given this many stars.
+    Rated(u32),
+    MostSkipped(Vec<SkippedTrack>),
+    /// What's been counted so far, or `None` while insights are off.
+    Insights(Option<Insights>),
+    /// The page to allow access to Last.fm on, to be opened in the browser.
+    LastFmAuthorize(String),
+    /// Signed in to Last.fm, with the session key to keep in the settings.
+    LastFmSession {
+        user: String,
+        session: String,
+    },
+    /// The ListenBrainz token checked belongs to this user.
+    ListenBrainzUser(String),
+    /// Listens waiting to be sent, kept while a service can't be reached.
+    PendingScrobbles(usize),
+    /// The named scrobbling service no longer takes its sign-in. Its listens wait until it's
+    /// signed in to again.
+    ScrobbleSignedOut(String),
+    /// Where playback is in the queue, sent whenever that changes.
+    QueueState(QueueState),
+}
+
+/// The queue's length and which of its tracks is loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QueueState {
+    /// Index of the loaded track, or `None` while nothing is loaded.
+    pub current: Option<usize>,
+    pub length: usize,
+}
+
+/// Where the queue entry at `current` is once the entry at `from` moves to `to`.
+fn index_after_move(current: usize, from: usize, to: usize) -> usize {
+    if from == current {
+        to
+    } else if from < current && to >= current {
+        current - 1
+    } else if from > current && to <= current {
+        current + 1
+    } else {
+        current
+    }
+}
+
+/// Where the queue entry at `current` is once the entries at `removed` are taken out. When it
+/// was taken out itself, this is the place of the first entry left after it, which is past the
+/// end if there is none.
+fn index_after_removal(current: usize, removed: &[usize]) -> usize {
+    current - removed.iter().filter(|&&index| index < current).count()
+}
+
+/// Where the player is with the current track. Every change goes through
+/// [`Player::transition`], which tells the UI about the ones it shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PlaybackState {
+    /// Nothing is loaded.
+    #[default]
+    Idle,
+    /// A new queue's first track is being loaded.
+    Loading,
+    Playing,
+    Paused,
+    /// A track is loaded but not started, or playback ran off the end of the queue.
+    Stopped,
+    /// Moving from one track to another.
+    Switching,
+}
+
+impl PlaybackState {
+    pub fn is_loaded(&self) -> bool {
+        *self != PlaybackState::Idle
+    }
+
+    pub fn is_playing(&self) -> bool {
+        *self == PlaybackState::Playing
+    }
+
+    /// Whether a track is being loaded or switched to, while the pipeline's own state changes
+    /// are the player's doing and not to be followed.
+    pub fn is_transitioning(&self) -> bool {
+        matches!(self, PlaybackState::Loading | PlaybackState::Switching)
+    }
+
+    /// The state shown in the UI, or `None` while it's about to change again.
+    fn reported(&self) -> Option<State> {
+        match self {
+            PlaybackState::Idle | PlaybackState::Stopped => Some(State::Null),
+            PlaybackState::Playing => Some(State::Playing),
+            PlaybackState::Paused => Some(State::Paused),
+            PlaybackState::Loading | PlaybackState::Switching => None,
+        }
+    }
+}
+
+/// An operation given up on after [`LOAD_TIMEOUT`], named by what it was doing.
+#[derive(Debug)]
+pub struct TimedOut(pub String);
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} timed out after {} seconds",
+            self.0,
+            LOAD_TIMEOUT.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Run `operation`, giving up on it once it has taken [`LOAD_TIMEOUT`] and failing with
+/// [`TimedOut`]. `what` names the operation in the error. The timer only gets its turn while
+/// `operation` is waiting, so anything that blocks has to do it on another thread, like with
+/// `smol::unblock`; giving up then stops waiting for that thread rather than stopping it.
+async fn timed<T>(
+    what: &str,
+    operation: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    smol::future::or(operation, async {
+        smol::Timer::after(LOAD_TIMEOUT).await;
+        Err(TimedOut(what.to_string()).into())
+    })
+    .await
+}
+
+/// Whether `command`, whose variant is `name`, is something the user did. Reads, settings pushed
+/// at startup and on reload, and restoring the last session are left out; settings are counted
+/// when they change instead.
+fn is_usage(command: &Command, name: &str) -> bool {
+    !name.starts_with("Get")
+        && !name.starts_with("Set")
+        && !matches!(
+            command,
+            Command::LoadSavedPlaylists
+                | Command::RetrieveSavedPlaylists
+                | Command::SaveSession(_)
+                | Command::RestoreSession
+                | Command::StartVolume(_)
+                | Command::Query(..)
+                | Command::Subscribe(_)
+                | Command::RecordView(_)
+                | Command::RecordSettings(_)
+                | Command::ClearInsights
+        )
+}
+
+/// File name for the cache of a playlist called `name`: lowercase letters, with spaces as
+/// underscores.
+fn cached_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter_map(|c| {
+            if c.is_ascii_alphabetic() {
+                Some(c)
+            } else if c == ' ' {
+                Some('_')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Pair freshly read `tracks` from a watched folder with the entries of `listed` they stand in
+/// for: the same file rewritten, or a file that is `gone` turning up under a new name with the
+/// same tags, as a rename does. Returns the replacements by the URI they replace, and the
+/// tracks that are new.
+fn match_changes(
+    listed: &[Track],
+    tracks: Vec<Track>,
+    gone: &impl Fn(&Track) -> bool,
+) -> (HashMap<String, Track>, Vec<Track>) {
+    let mut replacements: HashMap<String, Track> = HashMap::new();
+    let mut appended = vec![];
+    for track in tracks {
+        let old = listed.iter().find(|old| old.uri == track.uri).or_else(|| {
+            listed.iter().find(|old| {
+                gone(old)
+                    && !replacements.contains_key(&old.uri)
+                    && old.title == track.title
+                    && old.album == track.album
+                    && old.artists == track.artists
+                    && old.duration == track.duration
+            })
+        });
+        match old {
+            Some(old) => {
+                // Titles and notes belong to the playlist entry, not the file.
+                let track = Track {
+                    custom_title: old.custom_title.clone(),
+                    note: old.note.clone(),
+                    ..track
+                };
+                replacements.insert(old.uri.clone(), track);
+            }
+            None => appended.push(track),
+        }
+    }
+    (replacements, appended)
+}
+
+/// Swap the entries of `list` for their `replacements` where they stand, drop the rest that are
+/// `gone`, and add `appended` at the end.
+fn apply_changes(
+    list: &mut Vec<Track>,
+    replacements: &HashMap<String, Track>,
+    gone: &impl Fn(&Track) -> bool,
+    appended: &[Track],
+) {
+    let old = std::mem::take(list);
+    list.extend(
+        old.into_iter()
+            .filter_map(|track| match replacements.get(&track.uri) {
+                Some(new) => Some(new.clone()),
+                None => (!gone(&track)).then_some(track),
+            }),
+    );
+    list.extend(appended.iter().cloned());
+}
+
+/// How often the player loop wakes up for timed work when nothing else is happening.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+/// How long state changes settle before the session is written to disk.
+const SESSION_SAVE_DELAY: Duration = Duration::from_secs(3);
+/// How long a watched folder has to stay quiet before its changes are applied.
+const FOLDER_SETTLE_DELAY: Duration = Duration::from_millis(500);
+/// Shortest time between queue updates sent while a folder scan fills the queue.
+const SCAN_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+/// Most tracks a library search returns.
+const SEARCH_RESULTS: usize = 50;
+/// Tracks added each time autofill tops the queue up.
+const AUTOFILL_TRACKS: usize = 10;
+/// How long a capped start takes to ramp back up to the saved volume.
+const LOUD_START_RAMP: Duration = Duration::from_secs(1);
+/// How long loading or starting a track may take, like from a dead network mount, before it
+/// is given up on and the next one is tried.
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Volume fade from a capped start up to the saved level, begun once playback starts.
+#[derive(Clone, Copy)]
+pub struct VolumeRamp {
+    pub from: f64,
+    pub to: f64,
+    pub started: Option<Instant>,
+}
+
+/// Files being read in the background for adding to the queue.
+#[derive(Clone)]
+pub struct EnqueueScan {
+    pub job: ScanJob,
+    /// Tracks added to the queue so far.
+    pub added: usize,
+    /// Play the first track once it is added.
+    pub play: bool,
+}
+
+/// Files being read in the background for something that needs all of them at once.
+#[derive(Clone)]
+pub struct FileRead {
+    pub job: ScanJob,
+    /// Tracks read so far.
+    pub tracks: Vec<Track>,
+    pub then: AfterRead,
+}
+
+/// What to do with the tracks once a [`FileRead`] is done.
+#[derive(Clone)]
+pub enum AfterRead {
+    /// Save the playlist at `path` to the library.
+    Import {
+        path: PathBuf,
+        entries: Vec<import::Entry>,
+    },
+    /// Bring the queue in line with the watched folder, cached as `cached_name`.
+    FolderChanges {
+        cached_name: String,
+        removed: HashSet<String>,
+    },
+    /// Hand the tracks for `uris` to `then`.
+    Resolve { uris: Vec<String>, then: Resolved },
+}
+
+/// Where tracks resolved from URIs go.
+#[derive(Clone)]
+pub enum Resolved {
+    /// The end of the queue.
+    Enqueue,
+    /// Right after the current track.
+    PlayNext,
+    /// The end of the saved playlist cached under this name.
+    AddToPlaylist(String),
 }
 
 #[derive(Clone)]
@@ -57,11 +686,96 @@ pub struct Player {
     pub playlist: Arc<Mutex<Playlist>>,
     pub queue: Vec<Track>,
     pub volume: f64,
+    /// Highest volume the settings allow.
+    pub max_volume: f64,
     pub position: u64,
     pub current_index: usize,
-    pub loaded: bool,
-    pub playing: bool,
+    pub state: PlaybackState,
+    /// When playback last started, until it is heard to move. Playback that doesn't move
+    /// within [`LOAD_TIMEOUT`] is given up on.
+    pub started_at: Option<Instant>,
     pub shuffle: bool,
+    pub shuffle_mode: ShuffleMode,
+    pub repeat: bool,
+    /// Keep the queue from running out with tracks like the last one.
+    pub autofill: bool,
+    pub stalled: bool,
+    pub reconnect_attempts: u32,
+    pub reconnect_at: Option<Instant>,
+    pub artist_rules: ArtistRules,
+    pub exclusive: bool,
+    /// Output device last asked for, so settings reloads don't interrupt playback by picking the
+    /// same one again.
+    pub output_device: Option<Option<String>>,
+    pub session_dirty_since: Option<Instant>,
+    pub recent: RecentSessions,
+    pub recent_folders: RecentFolders,
+    pub resume_threshold: u64,
+    pub track_positions: TrackPositions,
+    pub chapters: Chapters,
+    pub pending_resume: Option<u64>,
+    pub scan: ScanOptions,
+    pub scan_job: Option<ScanJob>,
+    /// When the queue was last rebuilt from the scan in progress.
+    pub scan_refreshed_at: Option<Instant>,
+    pub scan_tx: smol::channel::Sender<ScanUpdate>,
+    pub scan_rx: smol::channel::Receiver<ScanUpdate>,
+    pub watcher: Option<Arc<FolderWatcher>>,
+    pub folder_tx: smol::channel::Sender<PathBuf>,
+    pub folder_rx: smol::channel::Receiver<PathBuf>,
+    pub folder_changes: HashSet<PathBuf>,
+    pub folder_changed_at: Option<Instant>,
+    /// Set while pausing for other applications' audio is turned on.
+    pub other_audio: Option<Arc<OtherAudio>>,
+    pub other_audio_tx: smol::channel::Sender<bool>,
+    pub other_audio_rx: smol::channel::Receiver<bool>,
+    pub resume_after_other_audio: bool,
+    /// Playback was paused because another application started playing, not by the user.
+    pub paused_for_other_audio: bool,
+    /// Set while pausing on disconnected outputs and suspend is turned on.
+    pub system_events: Option<Arc<SystemEvents>>,
+    pub system_events_tx: smol::channel::Sender<SystemEvent>,
+    pub system_events_rx: smol::channel::Receiver<SystemEvent>,
+    pub loud_start_cap: Option<f64>,
+    pub volume_ramp: Option<VolumeRamp>,
+    pub waveforms: WaveformQueue,
+    pub loudness: LoudnessQueue,
+    pub prefer_remote: HashSet<String>,
+    pub shared_dir: Option<PathBuf>,
+    pub subscribers: Vec<smol::channel::Sender<String>>,
+    pub last_status: Option<String>,
+    pub last_queue_state: Option<QueueState>,
+    pub library: Library,
+    pub library_filter: LibraryFilter,
+    pub roots: LibraryRoots,
+    /// Library folders being rescanned. These run alongside each other and any playlist scan,
+    /// and leave the queue alone.
+    pub root_scans: Vec<RootScan>,
+    /// Files being read for adding to the queue.
+    pub enqueue_scans: Vec<EnqueueScan>,
+    /// Files being read for an import or other change made once they are all in.
+    pub file_reads: Vec<FileRead>,
+    pub smart_playlists: SmartPlaylists,
+    /// Name of the smart playlist making up the queue, kept in step with the library.
+    pub smart_loaded: Option<String>,
+    pub plays: PlayCounts,
+    pub ratings: Ratings,
+    pub tag_edits: TagEdits,
+    pub skips: Skips,
+    /// Usage counted while insights are on.
+    pub insights: Option<Insights>,
+    /// Keeps the computer awake while playing.
+    pub stay_awake: Inhibitor,
+    pub scrobbler: Scrobbler,
+    /// The current track's progress toward a scrobble.
+    pub listening: Option<Listening>,
+    /// Lyrics from the current track's tags, used when there's no lyrics file next to it.
+    pub embedded_lyrics: Option<Lyrics>,
+    pub online_lyrics: bool,
+    pub lyrics_finder: LyricsFinder,
+    pub found_lyrics_rx: smol::channel::Receiver<FoundLyrics>,
+    /// Lyrics last looked up online, kept while their track is the current one.
+    pub fetched_lyrics: Option<FoundLyrics>,
     pub saved_playlists: SavedPlaylists,
     pub tx: Sender<Response>,
     pub rx: Receiver<Command>,
@@ -71,6 +785,8 @@ pub struct Player {
 pub struct Controller {
     pub tx: Sender<Command>,
     pub rx: Receiver<Response>,
+    ready: Arc<AtomicBool>,
+    pending: Arc<Mutex<Vec<Command>>>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -84,32 +800,133 @@ impl gpui::Global for Controller {}
 
 impl Player {
     pub fn new(backend: Arc<dyn Backend>, playlist: Arc<Mutex<Playlist>>) -> (Player, Controller) {
-        let (cmd_tx, cmd_rx) = ring_channel::ring_channel(NonZeroUsize::new(128).unwrap());
-        let (res_tx, res_rx) = ring_channel::ring_channel(NonZeroUsize::new(128).unwrap());
+        let (controller, res_tx, cmd_rx) = Controller::new();
+        controller.mark_ready();
         (
-            Player {
-                backend,
-                playlist,
-                queue: vec![],
-                volume: 0.5,
-                position: 0,
-                current_index: 0,
-                loaded: false,
-                playing: false,
-                saved_playlists: SavedPlaylists::default(),
-                tx: res_tx,
-                rx: cmd_rx,
-                shuffle: false,
-            },
-            Controller {
-                tx: cmd_tx,
-                rx: res_rx,
-            },
+            Player::with_channels(backend, playlist, res_tx, cmd_rx),
+            controller,
         )
     }
 
-    pub fn set_playing(&mut self) {
-        self.playing = !self.playing;
+    /// Create a player for channels handed out by [`Controller::new`], for when the backend is
+    /// only constructed after the controller is already in use.
+    pub fn with_channels(
+        backend: Arc<dyn Backend>,
+        playlist: Arc<Mutex<Playlist>>,
+        tx: Sender<Response>,
+        rx: Receiver<Command>,
+    ) -> Player {
+        let (scan_tx, scan_rx) = smol::channel::unbounded();
+        let (folder_tx, folder_rx) = smol::channel::unbounded();
+        let (other_audio_tx, other_audio_rx) = smol::channel::unbounded();
+        let (system_events_tx, system_events_rx) = smol::channel::unbounded();
+        let (found_lyrics_tx, found_lyrics_rx) = smol::channel::unbounded();
+        Player {
+            waveforms: WaveformQueue::new(backend.clone(), tx.clone()),
+            loudness: LoudnessQueue::new(backend.clone(), tx.clone()),
+            scrobbler: Scrobbler::new(tx.clone()),
+            backend,
+            playlist,
+            queue: vec![],
+            volume: 0.5,
+            max_volume: MAX_BOOSTED_VOLUME,
+            position: 0,
+            current_index: 0,
+            state: PlaybackState::Idle,
+            started_at: None,
+            saved_playlists: SavedPlaylists::default(),
+            tx,
+            rx,
+            shuffle: false,
+            shuffle_mode: ShuffleMode::default(),
+            repeat: false,
+            autofill: false,
+            stalled: false,
+            reconnect_attempts: 0,
+            reconnect_at: None,
+            artist_rules: ArtistRules::default(),
+            exclusive: false,
+            output_device: None,
+            session_dirty_since: None,
+            recent: RecentSessions::load(),
+            recent_folders: RecentFolders::load(),
+            resume_threshold: 0,
+            track_positions: TrackPositions::load(),
+            chapters: Chapters::load(),
+            pending_resume: None,
+            scan: ScanOptions::default(),
+            scan_job: None,
+            scan_refreshed_at: None,
+            scan_tx,
+            scan_rx,
+            watcher: None,
+            folder_tx,
+            folder_rx,
+            folder_changes: HashSet::new(),
+            folder_changed_at: None,
+            other_audio: None,
+            other_audio_tx,
+            other_audio_rx,
+            resume_after_other_audio: false,
+            paused_for_other_audio: false,
+            system_events: None,
+            system_events_tx,
+            system_events_rx,
+            loud_start_cap: None,
+            volume_ramp: None,
+            prefer_remote: HashSet::new(),
+            shared_dir: None,
+            subscribers: vec![],
+            last_status: None,
+            last_queue_state: None,
+            library: Library::default(),
+            library_filter: LibraryFilter::default(),
+            roots: LibraryRoots::load(),
+            root_scans: vec![],
+            enqueue_scans: vec![],
+            file_reads: vec![],
+            smart_playlists: SmartPlaylists::load(),
+            smart_loaded: None,
+            plays: PlayCounts::load(),
+            ratings: Ratings::load(),
+            tag_edits: TagEdits::load(),
+            skips: Skips::load(),
+            insights: None,
+            stay_awake: Inhibitor::default(),
+            listening: None,
+            embedded_lyrics: None,
+            online_lyrics: false,
+            lyrics_finder: LyricsFinder::new(found_lyrics_tx),
+            found_lyrics_rx,
+            fetched_lyrics: None,
+        }
+    }
+
+    /// Move to `state`, telling the UI when what it shows changes.
+    fn transition(&mut self, state: PlaybackState) {
+        let shown = self.state.reported();
+        self.state = state;
+        self.started_at = state.is_playing().then(Instant::now);
+        // Held across track changes, so it isn't let go of between every track.
+        let playing = state.is_playing() || state.is_transitioning();
+        if let Err(e) = self.stay_awake.set_playing(playing) {
+            eprintln!("Could not keep awake: {e}");
+        }
+        if let Some(reported) = state.reported().filter(|&reported| Some(reported) != shown) {
+            // Pausing or stopping is when the position is worth keeping.
+            self.mark_session_dirty();
+            self.tx
+                .send(Response::StateChanged(reported))
+                .expect("Could not send message");
+        }
+    }
+
+    /// Stop whatever is playing before a new queue's first track is loaded.
+    async fn begin_loading(&mut self) {
+        if self.state.is_loaded() {
+            let _ = self.backend.stop().await;
+        }
+        self.transition(PlaybackState::Loading);
     }
 
     pub async fn load(
@@ -118,390 +935,4779 @@ impl Player {
         current_index: usize,
     ) -> anyhow::Result<()> {
         let current_song = &self.queue[current_index];
-        backend.load(&current_song.uri).await?;
+        timed("Loading", backend.load(&current_song.uri)).await?;
+        self.waveforms.request(current_song);
+        self.loudness.request(current_song);
+        self.pending_resume = self.resume_position(current_index);
+        self.mark_session_dirty();
         Ok(())
     }
 
-    pub async fn play_next(&mut self, backend: &Arc<dyn Backend>) -> anyhow::Result<()> {
-        if self.current_index + 1 < self.queue.len() {
-            self.current_index += 1;
-            {
-                self.load(backend, self.current_index).await?;
-            }
-        }
+    pub async fn play_id(&mut self, backend: &Arc<dyn Backend>, id: usize) -> anyhow::Result<()> {
+        self.current_index = id;
+        let uri = self.queue[id].uri.clone();
+        timed("Loading", backend.load(&uri)).await?;
+        self.waveforms.request(&self.queue[id]);
+        self.loudness.request(&self.queue[id]);
+        self.pending_resume = self.resume_position(id);
+        self.mark_session_dirty();
         Ok(())
     }
 
-    pub async fn play_previous(&mut self, backend: &Arc<dyn Backend>) -> anyhow::Result<()> {
-        if self.current_index > 0 {
-            self.current_index -= 1;
-            {
-                self.load(backend, self.current_index).await?;
-            }
+    fn is_long_track(&self, index: usize) -> bool {
+        self.resume_threshold > 0
+            && self
+                .queue
+                .get(index)
+                .is_some_and(|track| track.duration >= self.resume_threshold)
+    }
+
+    /// Where to pick the track at `index` back up, if it is long enough to resume.
+    fn resume_position(&self, index: usize) -> Option<u64> {
+        if !self.is_long_track(index) {
+            return None;
         }
-        Ok(())
+        self.track_positions.get(&self.queue[index].uri)
     }
 
-    pub async fn play_id(&mut self, backend: &Arc<dyn Backend>, id: usize) -> anyhow::Result<()> {
-        self.current_index = id;
-        let uri = self.queue[id].uri.clone();
-        backend.load(&uri).await?;
-        Ok(())
+    /// Seek to the stored position once the newly loaded track has prerolled.
+    async fn apply_pending_resume(&mut self) {
+        let Some(position) = self.pending_resume else {
+            return;
+        };
+        if !matches!(
+            self.backend.get_state().await,
+            Ok(State::Playing | State::Paused)
+        ) {
+            return;
+        }
+        self.pending_resume = None;
+        if let Err(e) = self.backend.seek(position).await {
+            self.tx
+                .send(Response::Warning(format!("Could not resume track: {e}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.position = position;
+        self.tx
+            .send(Response::Position(position))
+            .expect("Could not send message");
     }
 
-    pub async fn run(&mut self) {
-        loop {
-            while let Ok(command) = self.rx.try_recv() {
-                match command {
-                    Command::Play => {
-                        let backend = self.backend.clone();
-                        if !self.queue.is_empty() {
-                            if !self.playing {
-                                if self.loaded {
-                                    let tx = self.tx.clone();
-                                    self.tx
-                                        .send(Response::StateChanged(State::Playing))
-                                        .expect("Could not send message");
-                                    let _ = backend
-                                        .play()
-                                        .await
-                                        .map_err(|e| tx.send(Response::Error(e.to_string())));
-                                    self.playing = true;
-                                } else {
-                                    println!("Playlist is not loaded.");
-                                    self.tx
-                                        .send(Response::Error(
-                                            "Playlist is not loaded.".to_string(),
-                                        ))
-                                        .expect("Could not send message");
-                                }
-                            }
-                        }
-                    }
-                    Command::Pause => {
-                        let backend = self.backend.clone();
-                        if self.playing {
-                            self.tx
-                                .send(Response::StateChanged(State::Paused))
-                                .expect("Could not send message");
-                            let _ = backend
-                                .pause()
-                                .await
-                                .map_err(|e| self.tx.send(Response::Error(e.to_string())));
-                            self.playing = false;
-                        }
-                    }
-                    Command::GetMeta => {
-                        if self.loaded {
-                            let track = self.queue[self.current_index].clone();
-                            self.tx
-                                .send(Response::Metadata(track))
-                                .expect("Could not send message");
-                        }
-                    }
-                    Command::GetTracks => {
-                        if self.loaded {
-                            let tracks = self.queue.clone();
-                            self.tx
-                                .send(Response::Tracks(tracks))
-                                .expect("Could not send message");
-                        }
-                    }
-                    Command::Volume(vol) => {
-                        let backend = self.backend.clone();
-                        if self.loaded {
-                            self.tx
-                                .send(Response::Info(format!("Volume set to {vol}")))
-                                .expect("Could not send message");
-                            backend.set_volume(vol).await.expect("Could not set volume");
-                            println!("Volume set to {vol}");
-                            self.volume = vol;
-                        }
-                    }
-                    Command::Next => {
-                        let backend = self.backend.clone();
-                        if self.loaded {
-                            backend.stop().await.expect("Could not stop");
-                            self.play_next(&backend)
-                                .await
-                                .expect("Could not play next.");
-                            self.tx
-                                .send(Response::StateChanged(State::Playing))
-                                .expect("Could not send message");
-                            backend.play().await.expect("Could not play");
-                            self.playing = true;
-                            backend
-                                .set_volume(self.volume)
-                                .await
-                                .expect("Could not set volume");
-                        }
-                    }
-                    Command::Previous => {
-                        let backend = self.backend.clone();
-                        if self.loaded {
-                            backend.stop().await.expect("Could not stop");
-                            self.play_previous(&backend)
-                                .await
-                                .expect("Could not play previous.");
-                            self.tx
-                                .send(Response::StateChanged(State::Playing))
-                                .expect("Could not send message");
-                            backend.play().await.expect("Could not play");
-                            self.playing = true;
-                            backend
-                                .set_volume(self.volume)
-                                .await
-                                .expect("Could not set volume");
-                        }
-                    }
-                    Command::PlayId(id) => {
-                        let backend = self.backend.clone();
-                        if self.loaded {
-                            backend.stop().await.expect("Could not stop");
-                            self.play_id(&backend, id)
-                                .await
-                                .expect("Could not play track");
-                            self.tx
-                                .send(Response::StateChanged(State::Playing))
-                                .expect("Could not send message");
-                            backend.play().await.expect("Could not play");
-                            self.playing = true;
-                            backend
-                                .set_volume(self.volume)
-                                .await
-                                .expect("Could not set volume");
-                        }
-                    }
-                    Command::LoadFromFolder(saved_playlist) => {
-                        let backend = self.backend.clone();
-                        let playlist: Playlist;
-                        if let Some(cached) =
-                            Playlist::read_cached(saved_playlist.cached_name).await
-                        {
-                            playlist = cached;
-                        } else {
-                            playlist = Playlist::from_dir(
-                                &backend,
-                                PathBuf::from(saved_playlist.actual_path),
-                            )
-                            .await;
-                        }
+    fn record_track_position(&mut self) {
+        if !self.state.is_loaded() || !self.is_long_track(self.current_index) {
+            return;
+        }
+        let track = &self.queue[self.current_index];
+        self.track_positions
+            .record(&track.uri, self.position, track.duration);
+    }
 
-                        self.loaded = true;
-                        self.playlist = Arc::new(Mutex::new(playlist.clone()));
-                        self.queue = playlist.clone().tracks;
+    /// The playlist's tracks in order, with local and remote copies collapsed.
+    fn playlist_queue(&self) -> Vec<Track> {
+        let tracks = self
+            .playlist
+            .lock()
+            .expect("Could not lock playlist")
+            .tracks
+            .clone();
+        matching::dedupe_sources(tracks, &self.prefer_remote)
+    }
 
-                        self.load(&backend, 0)
-                            .await
-                            .expect("Could not load first item");
-                        self.tx
-                            .send(Response::PlaylistName(playlist.name))
-                            .expect("Could not send message");
-                    }
-                    Command::LoadFolder => {
-                        let backend = self.backend.clone();
-                        if let Some(path) = rfd::AsyncFileDialog::new().pick_folder().await {
-                            let path = path.path().to_owned();
-                            let name = path
-                                .file_name()
-                                .and_then(|name| name.to_str())
-                                .unwrap_or("unknown playlist")
-                                .to_string();
-                            let cached_name: String = name
-                                .to_lowercase()
-                                .chars()
-                                .filter_map(|c| {
-                                    if c.is_ascii_alphabetic() {
-                                        Some(c)
-                                    } else if c == ' ' {
-                                        Some('_')
-                                    } else {
-                                        None
-                                    }
-                                })
-                                .collect();
-                            let new_saved_playlist = SavedPlaylist {
-                                name,
-                                actual_path: path.to_string_lossy().to_string(),
-                                cached_name: cached_name.clone(),
-                            };
-                            let playlist =
-                                Playlist::from_dir(&backend, PathBuf::from(path.clone())).await;
-
-                            self.loaded = true;
-                            self.playlist = Arc::new(Mutex::new(playlist.clone()));
-                            self.queue = playlist.clone().tracks;
-                            playlist
-                                .write_cached(cached_name)
-                                .await
-                                .expect("Could not write cache");
-                            self.tx
-                                .send(Response::PlaylistName(playlist.name))
-                                .expect("Could not send message");
-                            self.load(&backend, 0)
-                                .await
-                                .expect("Could not load first item");
-                            if !self
-                                .saved_playlists
-                                .playlists
-                                .iter()
-                                .any(|p| *p == new_saved_playlist)
-                            {
-                                self.saved_playlists.playlists.push(new_saved_playlist);
-                            }
-                        }
-                    }
-                    Command::LoadSavedPlaylists => {
-                        self.saved_playlists = SavedPlaylists::load();
-                        self.tx
-                            .send(Response::SavedPlaylists(self.saved_playlists.clone()))
-                            .expect("Could not send message");
-                    }
-                    Command::RetrieveSavedPlaylists => {
-                        self.tx
-                            .send(Response::SavedPlaylists(self.saved_playlists.clone()))
-                            .expect("Could not send message");
-                    }
-                    Command::WriteSavedPlaylists => {
-                        SavedPlaylists::save_playlists(&self.saved_playlists)
-                            .expect("Could not save to file");
-                    }
-                    Command::Seek(time) => {
-                        let backend = self.backend.clone();
-                        if self.playing {
-                            backend.seek(time).await.expect("Could not seek");
-                        }
-                    }
-                    Command::Shuffle => {
-                        let mut rng = rand::rng();
-                        if !self.shuffle {
-                            self.queue.shuffle(&mut rng);
-                            self.shuffle = true;
-                        } else {
-                            self.queue = self
-                                .playlist
-                                .lock()
-                                .expect("Could not lock playlist")
-                                .tracks
-                                .clone();
-                            self.shuffle = false;
-                        }
-                        self.tx
-                            .send(Response::Tracks(self.queue.clone()))
-                            .expect("Could not send message");
-                        self.tx
-                            .send(Response::Shuffle(self.shuffle.clone()))
-                            .expect("Could not send message");
+    /// Flip which copy of the current track is preferred and rebuild the queue around it.
+    async fn toggle_source(&mut self) {
+        let key = matching::match_key(&self.queue[self.current_index]);
+        if !self.prefer_remote.remove(&key) {
+            self.prefer_remote.insert(key.clone());
+        }
+        self.tx
+            .send(Response::PreferRemote(
+                self.prefer_remote.iter().cloned().collect(),
+            ))
+            .expect("Could not send message");
+
+        let tracks = self.playlist_queue();
+        let Some(replacement) = tracks
+            .iter()
+            .find(|track| matching::match_key(track) == key)
+            .cloned()
+        else {
+            return;
+        };
+        if replacement.uri == self.queue[self.current_index].uri {
+            self.tx
+                .send(Response::Info(
+                    "No other source for this track.".to_string(),
+                ))
+                .expect("Could not send message");
+            return;
+        }
+        if self.shuffle {
+            self.queue[self.current_index] = replacement;
+        } else {
+            self.current_index = tracks
+                .iter()
+                .position(|track| track.uri == replacement.uri)
+                .unwrap_or(self.current_index);
+            self.queue = tracks;
+        }
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        if self.state.is_playing() {
+            self.play_from(self.current_index).await;
+        }
+    }
+
+    /// Stop whatever is playing and start the track at `index`.
+    async fn start_track(&mut self, index: usize) -> anyhow::Result<()> {
+        let backend = self.backend.clone();
+        timed("Stopping the last track", backend.stop()).await?;
+        self.play_id(&backend, index).await?;
+        timed("Starting", backend.play()).await?;
+        if !self.exclusive {
+            backend.set_volume(self.volume).await?;
+        }
+        Ok(())
+    }
+
+    /// Play the track at `index`, moving on past any that fail to start.
+    async fn play_from(&mut self, index: usize) {
+        let mut index = index;
+        self.transition(PlaybackState::Switching);
+        loop {
+            if self.ensure_file(index).await {
+                match self.start_track(index).await {
+                    Ok(()) => {
+                        self.transition(PlaybackState::Playing);
+                        return;
                     }
+                    Err(e) => self.report_failed(index, e),
                 }
             }
-
-            if let Some(res) = self.backend.monitor().await {
-                self.tx.send(res).unwrap();
-            }
-            let curr_pos = self.backend.get_position().await;
-            if self.position != curr_pos {
-                self.tx
-                    .send(Response::Position(curr_pos))
-                    .expect("Could not send message.");
-                self.position = curr_pos;
+            match self.next_playable(index) {
+                Some(next) => index = next,
+                None => break,
             }
         }
+        self.stop_playback().await;
     }
-}
 
-impl Controller {
-    pub fn load(&self, saved_playlist: SavedPlaylist) {
+    /// Report a track that failed while playing, then carry on with the next one.
+    async fn skip_failed(&mut self, index: usize, error: anyhow::Error) {
+        self.report_failed(index, error);
+        match self.next_playable(index) {
+            Some(next) => self.play_from(next).await,
+            None => self.stop_playback().await,
+        }
+    }
+
+    /// Tell the UI why the track at `index` couldn't be played.
+    fn report_failed(&self, index: usize, error: anyhow::Error) {
+        let Some(track) = self.queue.get(index) else {
+            return;
+        };
         self.tx
-            .send(Command::LoadFromFolder(saved_playlist))
-            .expect("Could not send command");
+            .send(Response::Error(format!(
+                "Could not play {}: {error}",
+                track.title
+            )))
+            .expect("Could not send message");
     }
 
-    pub fn open_folder(&self) {
+    /// Flag the track at `index` in the queue and playlist so it is shown as broken and left
+    /// out of shuffle. Only for tracks whose own data can't be decoded, since the flag is
+    /// saved with them.
+    fn mark_corrupt(&mut self, index: usize) {
+        let Some(track) = self.queue.get_mut(index) else {
+            return;
+        };
+        track.corrupt = true;
+        let uri = track.uri.clone();
+        if let Some(track) = self
+            .playlist
+            .lock()
+            .expect("Could not lock playlist")
+            .tracks
+            .iter_mut()
+            .find(|track| track.uri == uri)
+        {
+            track.corrupt = true;
+        }
         self.tx
-            .send(Command::LoadFolder)
-            .expect("Could not send command");
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
     }
 
-    pub fn play(&self) {
-        self.tx.send(Command::Play).expect("Could not send command");
+    fn next_playable(&self, after: usize) -> Option<usize> {
+        (after + 1..self.queue.len())
+            .find(|&index| !self.queue[index].corrupt && !self.queue[index].missing)
     }
 
-    pub fn play_id(&self, id: usize) {
-        self.tx
-            .send(Command::PlayId(id))
-            .expect("Could not send command");
+    async fn stop_playback(&mut self) {
+        let _ = self.backend.stop().await;
+        self.transition(PlaybackState::Stopped);
     }
 
-    pub fn pause(&self) {
+    /// Scan `dir` into a new playlist, make it the current queue and remember it as a saved playlist.
+    pub async fn load_dir(&mut self, dir: PathBuf) {
+        let name = dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("unknown playlist")
+            .to_string();
+        self.remember_folder(dir.clone());
+        self.start_scan(SavedPlaylist {
+            cached_name: cached_name(&name),
+            name,
+            actual_path: dir.to_string_lossy().to_string(),
+            ..Default::default()
+        })
+        .await;
+    }
+
+    async fn open_recent_folder(&mut self, dir: PathBuf) {
+        if dir.is_dir() {
+            self.load_dir(dir).await;
+            return;
+        }
+        self.recent_folders.forget(&dir);
+        if let Err(e) = self.recent_folders.save() {
+            eprintln!("Could not save recent folders: {}", e);
+        }
+        self.send_recent_folders();
         self.tx
-            .send(Command::Pause)
-            .expect("Could not send command");
+            .send(Response::Error(format!(
+                "{} is no longer there",
+                dir.display()
+            )))
+            .expect("Could not send message");
     }
 
-    pub fn next(&self) {
-        self.tx.send(Command::Next).expect("Could not send command");
+    /// Offer `dir` first among the recently used folders.
+    fn remember_folder(&mut self, dir: PathBuf) {
+        self.recent_folders.record(dir);
+        if let Err(e) = self.recent_folders.save() {
+            eprintln!("Could not save recent folders: {}", e);
+        }
+        self.send_recent_folders();
     }
 
-    pub fn prev(&self) {
+    fn send_recent_folders(&self) {
         self.tx
-            .send(Command::Previous)
-            .expect("Could not send command");
+            .send(Response::RecentFolders(self.recent_folders.folders.clone()))
+            .expect("Could not send message");
     }
 
-    pub fn get_meta(&self) {
-        self.tx
-            .send(Command::GetMeta)
-            .expect("Could not send command");
+    /// Read the playlist file at `path` into a new saved playlist. Files it lists that aren't
+    /// there come in flagged as missing, with what the playlist says about them, so they can
+    /// be re-bound once they turn up. The files are read on a scan thread.
+    async fn import_playlist(&mut self, path: PathBuf) {
+        let entries = match import::read(&path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.tx
+                    .send(Response::Error(format!("Could not import playlist: {e}")))
+                    .expect("Could not send message");
+                return;
+            }
+        };
+        let files: Vec<PathBuf> = entries
+            .iter()
+            .filter_map(|entry| match &entry.location {
+                Location::File(file) => Some(file.clone()),
+                Location::Url(_) => None,
+            })
+            .collect();
+        if files.is_empty() {
+            self.finish_import(path, entries, vec![]).await;
+        } else {
+            self.read_files(files, Origin::Import, AfterRead::Import { path, entries });
+        }
     }
 
-    pub fn get_queue(&self) {
-        self.tx
-            .send(Command::GetTracks)
-            .expect("Could not send command");
+    /// Save the playlist at `path` once its files have been read into `read`.
+    async fn finish_import(
+        &mut self,
+        path: PathBuf,
+        entries: Vec<import::Entry>,
+        read: Vec<Track>,
+    ) {
+        let name = path
+            .file_stem()
+            .map_or("Imported playlist".to_string(), |name| {
+                name.to_string_lossy().into_owned()
+            });
+        let read: HashMap<String, Track> = read
+            .into_iter()
+            .map(|track| (track.uri.clone(), track))
+            .collect();
+
+        let mut tracks = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let mut track = match &entry.location {
+                Location::File(file) => {
+                    let uri = uri::from_path(file);
+                    // A playlist may list a file more than once, so each entry takes a copy.
+                    match read.get(&uri) {
+                        Some(track) => track.clone(),
+                        None => Track {
+                            title: file
+                                .file_stem()
+                                .map_or("Unknown Track".to_string(), |name| {
+                                    name.to_string_lossy().into_owned()
+                                }),
+                            uri,
+                            missing: !file.exists(),
+                            ..Track::default()
+                        },
+                    }
+                }
+                Location::Url(url) => match self.backend.get_meta(url).await {
+                    Ok(track) => track,
+                    Err(_) => Track {
+                        title: url.clone(),
+                        uri: url.clone(),
+                        ..Track::default()
+                    },
+                },
+            };
+            track.origin = Origin::Import;
+            // Tags win; the playlist only fills in what couldn't be read.
+            if track.missing || track.is_remote() || track.duration == 0 {
+                if let Some(title) = entry.title {
+                    track.title = title;
+                }
+                if let Some(artist) = entry.artist {
+                    track.artists = vec![artist];
+                }
+                if let Some(album) = entry.album {
+                    track.album = album;
+                }
+                track.duration = entry.duration.unwrap_or(track.duration);
+            }
+            tracks.push(track);
+        }
+        let missing = tracks.iter().filter(|track| track.missing).count();
+        let count = tracks.len();
+        let mut playlist = Playlist { name, tracks };
+        self.tag_edits.apply(&mut playlist.tracks);
+        playlist.apply_artist_rules(&self.artist_rules);
+
+        let cached = format!("imported_{}", cached_name(&playlist.name));
+        match self.saved_playlists.add(&playlist, cached).await {
+            Ok(saved) => {
+                self.tx
+                    .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+                    .expect("Could not send message");
+                self.tx
+                    .send(Response::Info(match missing {
+                        0 => format!("Imported {count} tracks as {}", saved.name),
+                        _ => format!(
+                            "Imported {count} tracks as {}, {missing} of them missing",
+                            saved.name
+                        ),
+                    }))
+                    .expect("Could not send message");
+                self.library_changed().await;
+            }
+            Err(e) => self
+                .tx
+                .send(Response::Error(format!("Could not import playlist: {e}")))
+                .expect("Could not send message"),
+        }
     }
 
-    pub fn volume(&self, vol: f64) {
+    /// Replace the queue with the contents of `saved`'s folder, scanned in the background so the
+    /// first tracks can play while the rest are still being read.
+    async fn start_scan(&mut self, saved: SavedPlaylist) {
+        self.cancel_scan();
+        self.stop_playback().await;
+        self.transition(PlaybackState::Idle);
+        self.current_index = 0;
+        self.queue = vec![];
+        self.playlist = Arc::new(Mutex::new(Playlist {
+            name: saved.name.clone(),
+            tracks: vec![],
+        }));
         self.tx
-            .send(Command::Volume(vol))
-            .expect("Could not send command");
+            .send(Response::PlaylistName(saved.name.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Tracks(vec![]))
+            .expect("Could not send message");
+        self.watch_folder(Some(saved.clone()));
+        self.scan_job = Some(ScanJob::start(
+            self.backend.clone(),
+            saved,
+            self.scan.clone(),
+            self.scan_tx.clone(),
+        ));
     }
 
-    pub fn load_saved_playlists(&self) {
-        self.tx
-            .send(Command::LoadSavedPlaylists)
-            .expect("Could not send command");
+    /// Stop the running scan, if any. Tracks it already delivered stay in the queue, but the
+    /// partial playlist isn't cached or saved.
+    fn cancel_scan(&mut self) {
+        if let Some(job) = self.scan_job.take() {
+            job.cancel();
+            // Batches since the last refresh haven't reached the queue yet.
+            self.refresh_scanned_queue();
+            self.scan_refreshed_at = None;
+            self.tx
+                .send(Response::ScanFinished)
+                .expect("Could not send message");
+        }
     }
 
-    pub fn write_playlist(&self) {
-        self.tx
-            .send(Command::WriteSavedPlaylists)
-            .expect("Could not send command");
+    async fn handle_scan(&mut self, update: ScanUpdate) {
+        if self
+            .root_scans
+            .iter()
+            .any(|scan| scan.job.id == update.id())
+        {
+            self.handle_root_scan(update).await;
+            return;
+        }
+        if self
+            .enqueue_scans
+            .iter()
+            .any(|scan| scan.job.id == update.id())
+        {
+            self.handle_enqueue_scan(update).await;
+            return;
+        }
+        if self
+            .file_reads
+            .iter()
+            .any(|read| read.job.id == update.id())
+        {
+            self.handle_file_read(update).await;
+            return;
+        }
+        let Some(current) = self.scan_job.as_ref().map(|job| job.id) else {
+            return;
+        };
+        match update {
+            ScanUpdate::Batch(id, mut tracks) if id == current => {
+                self.tag_edits.apply(&mut tracks);
+                for track in tracks.iter_mut() {
+                    track.artists = self.artist_rules.apply(&track.artists);
+                }
+                self.playlist
+                    .lock()
+                    .expect("Could not lock playlist")
+                    .tracks
+                    .extend(tracks);
+                // Rebuilding the whole queue for every batch adds up on large folders, so it
+                // is done every so often, and at once while there is nothing to play yet.
+                let due = self
+                    .scan_refreshed_at
+                    .is_none_or(|at| at.elapsed() >= SCAN_REFRESH_INTERVAL);
+                if due || self.queue.is_empty() {
+                    self.refresh_scanned_queue();
+                }
+
+                if !self.state.is_loaded() && !self.queue.is_empty() {
+                    let backend = self.backend.clone();
+                    self.begin_loading().await;
+                    self.current_index = 0;
+                    if let Err(e) = self.load(&backend, 0).await {
+                        self.tx
+                            .send(Response::Error(format!(
+                                "Could not load {}: {e}",
+                                self.queue[0].title
+                            )))
+                            .expect("Could not send message");
+                    }
+                    self.transition(PlaybackState::Stopped);
+                }
+            }
+            ScanUpdate::Progress { id, scanned, total } if id == current => {
+                self.tx
+                    .send(Response::ScanProgress { scanned, total })
+                    .expect("Could not send message");
+            }
+            ScanUpdate::Done(id) if id == current => {
+                self.refresh_scanned_queue();
+                self.scan_refreshed_at = None;
+                if let Some(job) = self.scan_job.take() {
+                    self.finish_scan(job.saved).await;
+                }
+            }
+            _ => {}
+        }
     }
 
-    pub fn retrieve_saved_playlists(&self) {
+    /// Rebuild the queue from the tracks scanned so far and send it on.
+    fn refresh_scanned_queue(&mut self) {
+        // Deduping can shift earlier tracks, so follow the current one by URI.
+        let playing = self.current_uri();
+        self.queue = self.playlist_queue();
+        self.reanchor(playing);
+        self.scan_refreshed_at = Some(Instant::now());
         self.tx
-            .send(Command::RetrieveSavedPlaylists)
-            .expect("Could not send command");
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
     }
 
-    pub fn seek(&self, time: u64) {
+    /// Cache a completed scan and remember its folder as a saved playlist.
+    async fn finish_scan(&mut self, saved: SavedPlaylist) {
         self.tx
-            .send(Command::Seek(time))
-            .expect("Could not send command");
+            .send(Response::ScanFinished)
+            .expect("Could not send message");
+        let playlist = self
+            .playlist
+            .lock()
+            .expect("Could not lock playlist")
+            .clone();
+        if playlist.tracks.is_empty() {
+            self.tx
+                .send(Response::Error(format!(
+                    "No audio files found in {}",
+                    saved.actual_path
+                )))
+                .expect("Could not send message");
+            return;
+        }
+        if let Err(e) = playlist.write_cached(saved.cached_name.clone()).await {
+            self.tx
+                .send(Response::Error(format!("Could not cache playlist: {e}")))
+                .expect("Could not send message");
+        }
+        if !self.saved_playlists.playlists.contains(&saved) {
+            self.saved_playlists.playlists.push(saved);
+            self.tx
+                .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+                .expect("Could not send message");
+            if let Err(e) = SavedPlaylists::save_playlists(&self.saved_playlists) {
+                self.tx
+                    .send(Response::Error(format!("Could not save playlists: {e}")))
+                    .expect("Could not send message");
+            }
+        }
+        self.library_changed().await;
     }
 
-    pub fn shuffle(&self) {
+    fn send_roots(&self) {
+        let roots = self
+            .roots
+            .roots
+            .iter()
+            .map(|root| {
+                let scan = self
+                    .root_scans
+                    .iter()
+                    .find(|scan| scan.job.saved.cached_name == root.cached_name);
+                let status = match scan {
+                    Some(scan) => RootStatus::Scanning {
+                        scanned: scan.scanned,
+                        total: scan.total,
+                    },
+                    None if !root.is_available() => RootStatus::Missing,
+                    None => RootStatus::Idle,
+                };
+                (root.clone(), status)
+            })
+            .collect();
         self.tx
-            .send(Command::Shuffle)
-            .expect("Could not send command");
+            .send(Response::LibraryRoots(roots))
+            .expect("Could not send message");
     }
-}
 
-impl Thumbnail {
-    pub fn to_frame(&self) -> SmallVec<[Frame; 1]> {
-        let img = RgbaImage::from_raw(self.width, self.height, self.img.clone())
-            .expect("Failed to reconstruct image from raw bytes");
-        SmallVec::from_vec(vec![Frame::new(thumbnail(&img, self.width, self.height))])
+    /// Scan `root` in the background, replacing a scan of it already running.
+    fn scan_root(&mut self, root: &LibraryRoot) {
+        if !root.is_available() {
+            self.tx
+                .send(Response::Error(format!("{} isn't available", root.path)))
+                .expect("Could not send message");
+            self.send_roots();
+            return;
+        }
+        self.cancel_root_scan(&root.cached_name);
+        self.root_scans.push(RootScan {
+            job: ScanJob::start(
+                self.backend.clone(),
+                root.saved(),
+                self.scan.clone(),
+                self.scan_tx.clone(),
+            ),
+            tracks: vec![],
+            scanned: 0,
+            total: 0,
+        });
+        self.send_roots();
+    }
+
+    fn cancel_root_scan(&mut self, cached_name: &str) {
+        self.root_scans.retain(|scan| {
+            let same = scan.job.saved.cached_name == cached_name;
+            if same {
+                scan.job.cancel();
+            }
+            !same
+        });
+    }
+
+    async fn handle_root_scan(&mut self, update: ScanUpdate) {
+        let Some(index) = self
+            .root_scans
+            .iter()
+            .position(|scan| scan.job.id == update.id())
+        else {
+            return;
+        };
+        match update {
+            ScanUpdate::Batch(_, tracks) => self.root_scans[index].tracks.extend(tracks),
+            ScanUpdate::Progress { scanned, total, .. } => {
+                self.root_scans[index].scanned = scanned;
+                self.root_scans[index].total = total;
+                self.send_roots();
+            }
+            ScanUpdate::Done(_) => {
+                let scan = self.root_scans.remove(index);
+                let saved = scan.job.saved;
+                let playlist = Playlist {
+                    name: saved.name,
+                    tracks: scan.tracks,
+                };
+                if let Err(e) = playlist.write_cached(saved.cached_name.clone()).await {
+                    self.tx
+                        .send(Response::Error(format!(
+                            "Could not cache library folder: {e}"
+                        )))
+                        .expect("Could not send message");
+                }
+                if let Some(root) = self
+                    .roots
+                    .roots
+                    .iter_mut()
+                    .find(|root| root.cached_name == saved.cached_name)
+                {
+                    root.scanned(playlist.tracks.len());
+                }
+                if let Err(e) = self.roots.save() {
+                    self.tx
+                        .send(Response::Error(format!(
+                            "Could not save library folders: {e}"
+                        )))
+                        .expect("Could not send message");
+                }
+                self.send_roots();
+                self.library_changed().await;
+                self.send_library();
+            }
+        }
+    }
+
+    /// Follow changes to `saved`'s folder, or stop watching when `None`.
+    fn watch_folder(&mut self, saved: Option<SavedPlaylist>) {
+        self.folder_changes.clear();
+        self.folder_changed_at = None;
+        self.watcher = saved
+            .filter(|saved| !saved.actual_path.is_empty())
+            .and_then(|saved| {
+                let dir = saved.actual_path.clone();
+                match FolderWatcher::start(saved, self.scan.max_depth > 0, self.folder_tx.clone()) {
+                    Ok(watcher) => Some(Arc::new(watcher)),
+                    Err(e) => {
+                        eprintln!("Could not watch {:?}: {}", dir, e);
+                        None
+                    }
+                }
+            });
+    }
+
+    /// Bring the queue in line with files added, removed, renamed or rewritten in the watched
+    /// folder since the last call.
+    async fn apply_folder_changes(&mut self) {
+        self.folder_changed_at = None;
+        let paths: Vec<PathBuf> = self.folder_changes.drain().collect();
+        let Some(watcher) = self.watcher.clone() else {
+            return;
+        };
+        let root = PathBuf::from(&watcher.saved.actual_path);
+
+        let mut removed: HashSet<String> = HashSet::new();
+        let mut added: Vec<PathBuf> = vec![];
+        for path in paths {
+            if !path.starts_with(&root) {
+                continue;
+            }
+            if path.is_dir() {
+                added.extend(
+                    playback::scan_paths(&path, &self.scan)
+                        .into_iter()
+                        .filter(|file| playback::in_scan(&root, file, &self.scan)),
+                );
+            } else if path.exists() {
+                if playback::is_audio(&path) && playback::in_scan(&root, &path, &self.scan) {
+                    // Rewritten files are read again for their new tags.
+                    removed.insert(uri::from_path(&path));
+                    added.push(path);
+                }
+            } else {
+                removed.insert(uri::from_path(&path));
+            }
+        }
+        if removed.is_empty() && added.is_empty() {
+            return;
+        }
+        let cached_name = watcher.saved.cached_name.clone();
+        if added.is_empty() {
+            self.finish_folder_changes(cached_name, removed, vec![])
+                .await;
+        } else {
+            // The new files are read on a scan thread, and the changes made once they are in.
+            self.read_files(
+                added,
+                Origin::FolderScan,
+                AfterRead::FolderChanges {
+                    cached_name,
+                    removed,
+                },
+            );
+        }
+    }
+
+    /// Make the changes found by [`Self::apply_folder_changes`], with the added files read into
+    /// `tracks`. Dropped if the queue has moved on to another folder in the meantime.
+    async fn finish_folder_changes(
+        &mut self,
+        cached_name: String,
+        removed: HashSet<String>,
+        mut tracks: Vec<Track>,
+    ) {
+        let Some(watcher) = self.watcher.clone() else {
+            return;
+        };
+        if watcher.saved.cached_name != cached_name {
+            return;
+        }
+        self.tag_edits.apply(&mut tracks);
+        for track in tracks.iter_mut() {
+            track.artists = self.artist_rules.apply(&track.artists);
+        }
+        // A removed folder only reports its own path, so match everything below it too.
+        let gone = |track: &Track| {
+            removed
+                .iter()
+                .any(|uri| track.uri == *uri || track.uri.starts_with(&format!("{uri}/")))
+        };
+
+        let mut playing = self.current_uri();
+        let playlist = {
+            let mut playlist = self.playlist.lock().expect("Could not lock playlist");
+            let (replacements, appended) = match_changes(&playlist.tracks, tracks, &gone);
+            apply_changes(&mut playlist.tracks, &replacements, &gone, &appended);
+            apply_changes(&mut self.queue, &replacements, &gone, &appended);
+            // A renamed current track is followed to its new name.
+            playing = playing.map(|uri| replacements.get(&uri).map_or(uri, |t| t.uri.clone()));
+            playlist.clone()
+        };
+        let position = playing.and_then(|uri| self.queue.iter().position(|t| t.uri == uri));
+        if let Some(index) = position {
+            self.current_index = index;
+        } else if self.queue.is_empty() {
+            self.current_index = 0;
+            self.stop_playback().await;
+            self.transition(PlaybackState::Idle);
+        } else if self.state.is_loaded() {
+            // The file under the current track is gone; move on to whatever took its place.
+            self.current_index = self.current_index.min(self.queue.len() - 1);
+            if self.state.is_playing() {
+                self.play_from(self.current_index).await;
+            } else {
+                let backend = self.backend.clone();
+                if let Err(e) = self.load(&backend, self.current_index).await {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            }
+        }
+        if let Err(e) = playlist
+            .write_cached(watcher.saved.cached_name.clone())
+            .await
+        {
+            self.tx
+                .send(Response::Error(format!("Could not cache playlist: {e}")))
+                .expect("Could not send message");
+        }
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.library_changed().await;
+    }
+
+    /// Make a queue out of `paths`, without saving it as a playlist since there is no folder
+    /// to rescan.
+    pub async fn load_files(
+        &mut self,
+        backend: &Arc<dyn Backend>,
+        paths: Vec<PathBuf>,
+        origin: Origin,
+    ) -> anyhow::Result<()> {
+        let name = match paths.as_slice() {
+            [path] => path
+                .file_stem()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Opened Files".into()),
+            _ => "Opened Files".to_string(),
+        };
+        let mut playlist =
+            Playlist::from_files(backend, name, paths, self.scan.verify, origin).await;
+        if playlist.tracks.is_empty() {
+            return Err(anyhow::anyhow!(
+                "None of the selected files are supported audio files"
+            ));
+        }
+        self.tag_edits.apply(&mut playlist.tracks);
+        playlist.apply_artist_rules(&self.artist_rules);
+
+        self.begin_loading().await;
+        self.current_index = 0;
+        self.playlist = Arc::new(Mutex::new(playlist.clone()));
+        self.queue = matching::dedupe_sources(playlist.tracks.clone(), &self.prefer_remote);
+        self.tx
+            .send(Response::PlaylistName(playlist.name))
+            .expect("Could not send message");
+        let loaded = self.load(backend, 0).await;
+        self.transition(PlaybackState::Stopped);
+        loaded
+    }
+
+    async fn open_link(&mut self, link: PlayLink, origin: Origin) {
+        let backend = self.backend.clone();
+        self.tx
+            .send(Response::LinkOpened)
+            .expect("Could not send message");
+        if let Err(e) = self.load_files(&backend, vec![link.path], origin).await {
+            self.tx
+                .send(Response::Error(format!("Could not open link: {e}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.play_from(0).await;
+        // Loading the track set its own resume position; the link's wins.
+        if link.position > 0 {
+            self.pending_resume = Some(link.position);
+        }
+    }
+
+    async fn play_files(&mut self, paths: Vec<PathBuf>, origin: Origin) {
+        let backend = self.backend.clone();
+        if let Err(e) = self.load_files(&backend, paths, origin).await {
+            self.tx
+                .send(Response::Error(e.to_string()))
+                .expect("Could not send message");
+            return;
+        }
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.play_from(0).await;
+    }
+
+    fn send_chapters(&self) {
+        let chapters = self
+            .queue
+            .get(self.current_index)
+            .map(|track| self.chapters.get(&track.uri))
+            .unwrap_or_default();
+        self.tx
+            .send(Response::Chapters(chapters))
+            .expect("Could not send message");
+    }
+
+    fn send_lyrics(&self, lyrics: Option<Lyrics>) {
+        self.tx
+            .send(Response::Lyrics(lyrics))
+            .expect("Could not send message");
+    }
+
+    /// The current track's lyrics: from a file next to it, searched for by hand, from its
+    /// tags, or looked up online, in that order.
+    fn current_lyrics(&self) -> Option<Lyrics> {
+        let track = self.queue.get(self.current_index)?;
+        let fetched = self
+            .fetched_lyrics
+            .as_ref()
+            .filter(|found| found.uri == track.uri);
+        let online = |searched: bool| {
+            fetched
+                .filter(|found| found.searched == searched)
+                .and_then(|found| found.text.as_ref().ok()?.as_deref())
+                .map(Lyrics::parse)
+        };
+        lyrics::load(track)
+            .or_else(|| online(true))
+            .or_else(|| self.embedded_lyrics.clone())
+            .or_else(|| online(false))
+    }
+
+    /// Start looking the current track's lyrics up online, when that's turned on.
+    fn find_lyrics(&self) {
+        if !self.online_lyrics {
+            return;
+        }
+        let Some(track) = self.queue.get(self.current_index) else {
+            return;
+        };
+        if let Some(query) = LyricsQuery::of(track) {
+            self.lyrics_finder.find(track.uri.clone(), query);
+        }
+    }
+
+    /// Show lyrics the finder came back with, if they're for the track still playing.
+    fn found_lyrics(&mut self, found: FoundLyrics) {
+        let current = self
+            .queue
+            .get(self.current_index)
+            .is_some_and(|track| track.uri == found.uri);
+        if !current {
+            return;
+        }
+        let message = match &found.text {
+            Ok(Some(_)) => None,
+            Ok(None) => Some(Response::Info("No lyrics found online".to_string())),
+            Err(e) => Some(Response::Error(format!("Could not look up lyrics: {e}"))),
+        };
+        if let Some(message) = message {
+            // Only searches by hand are worth telling about.
+            if found.searched {
+                self.tx.send(message).expect("Could not send message");
+            }
+            return;
+        }
+        self.fetched_lyrics = Some(found);
+        self.send_lyrics(self.current_lyrics());
+    }
+
+    /// Apply `edit` to the lyrics on disk, starting new ones if the track has none, and write
+    /// them back so the correction sticks.
+    fn edit_lyrics(&mut self, edit: LyricsEdit) {
+        // Corrections to lyrics from the tags or online are saved to a file next to the track.
+        let mut lyrics = self.current_lyrics().unwrap_or_default();
+        let track = &self.queue[self.current_index];
+        lyrics.edit(edit, self.position * 1000);
+        match lyrics::save(track, &lyrics) {
+            Ok(path) => lyrics.source = Some(path),
+            Err(e) => self
+                .tx
+                .send(Response::Error(format!("Could not save lyrics: {e}")))
+                .expect("Could not send message"),
+        }
+        self.send_lyrics(Some(lyrics));
+    }
+
+    async fn edit_chapters(&mut self, edit: ChapterEdit) {
+        let uri = self.queue[self.current_index].uri.clone();
+        self.chapters.edit(&uri, edit, self.position);
+        if let Err(e) = self.chapters.save() {
+            self.tx
+                .send(Response::Error(format!("Could not save chapters: {e}")))
+                .expect("Could not send message");
+        }
+        self.send_chapters();
+    }
+
+    /// Seek to the next chapter, or the previous one when `forward` is false.
+    async fn jump_chapter(&mut self, forward: bool) {
+        let uri = &self.queue[self.current_index].uri;
+        let target = if forward {
+            self.chapters.next(uri, self.position)
+        } else {
+            // Before the first chapter, go back to the start of the track.
+            self.chapters
+                .previous(uri, self.position)
+                .or_else(|| (!self.chapters.get(uri).is_empty()).then_some(0))
+        };
+        let Some(target) = target else {
+            return;
+        };
+        if let Err(e) = self.backend.seek(target).await {
+            self.tx
+                .send(Response::Error(format!("Could not seek: {e}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.position = target;
+        self.tx
+            .send(Response::Position(target))
+            .expect("Could not send message");
+    }
+
+    /// Rebuild the library from the saved playlists and whatever is loaded.
+    async fn refresh_library(&mut self) {
+        let loaded = self
+            .playlist
+            .lock()
+            .expect("Could not lock playlist")
+            .tracks
+            .clone();
+        self.library = Library::load(
+            &self.roots,
+            &self.saved_playlists,
+            &loaded,
+            &self.artist_rules,
+            &self.tag_edits,
+        )
+        .await;
+    }
+
+    fn send_library(&self) {
+        let visible = self.library.filtered(&self.library_filter);
+        self.tx
+            .send(Response::Library {
+                albums: visible.albums(),
+                artists: visible.artists(),
+                genres: self.library.genres(),
+                decades: self.library.decades(),
+                missing: self.library.missing_count(),
+            })
+            .expect("Could not send message");
+    }
+
+    /// Rebuild the library after its files changed, then bring the smart playlists up to date.
+    async fn library_changed(&mut self) {
+        self.refresh_library().await;
+        self.rebind_missing().await;
+        self.refresh_smart_playlist().await;
+        self.send_smart_playlists().await;
+    }
+
+    /// Point missing tracks whose files turned up elsewhere in the library at their new
+    /// location, in the saved playlists and queue, carrying over their ratings, play counts,
+    /// skips, chapters and resume positions.
+    async fn rebind_missing(&mut self) {
+        let missing = self.library.tracks().iter().chain(self.queue.iter());
+        let moved = self.library.moved(missing.filter(|track| track.missing));
+        if moved.is_empty() {
+            return;
+        }
+        for saved in self.saved_playlists.playlists.clone() {
+            let Some(mut playlist) = Playlist::read_cached(saved.cached_name.clone()).await else {
+                continue;
+            };
+            if playlist.rebind(&moved) == 0 {
+                continue;
+            }
+            if let Err(e) = playlist.write_cached(saved.cached_name).await {
+                eprintln!("Could not cache {}: {}", saved.name, e);
+            }
+        }
+        self.playlist
+            .lock()
+            .expect("Could not lock playlist")
+            .rebind(&moved);
+        for track in self.queue.iter_mut() {
+            if let Some(uri) = moved.get(&track.uri) {
+                track.uri = uri.clone();
+                track.missing = false;
+            }
+        }
+
+        uri::rebind_keys(&mut self.ratings.tracks, &moved);
+        uri::rebind_keys(&mut self.tag_edits.tracks, &moved);
+        uri::rebind_keys(&mut self.plays.tracks, &moved);
+        uri::rebind_keys(&mut self.skips.tracks, &moved);
+        uri::rebind_keys(&mut self.chapters.tracks, &moved);
+        uri::rebind_keys(&mut self.track_positions.positions, &moved);
+        let loudness = {
+            let mut store = self.loudness.store.lock().expect("Could not lock loudness");
+            uri::rebind_keys(&mut store.tracks, &moved);
+            store.save()
+        };
+        let saved = [
+            self.ratings.save(),
+            self.tag_edits.save(),
+            self.plays.save(),
+            self.skips.save(),
+            self.chapters.save(),
+            self.track_positions.save(),
+            loudness,
+        ];
+        for e in saved.into_iter().filter_map(Result::err) {
+            eprintln!("Could not save moved tracks: {}", e);
+        }
+
+        self.refresh_library().await;
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Info(match moved.len() {
+                1 => "Found 1 moved track".to_string(),
+                count => format!("Found {count} moved tracks"),
+            }))
+            .expect("Could not send message");
+    }
+
+    async fn remove_missing(&mut self) {
+        let mut removed: HashSet<String> = HashSet::new();
+        let cached_names: Vec<String> = self
+            .roots
+            .roots
+            .iter()
+            .map(|root| root.cached_name.clone())
+            .chain(
+                self.saved_playlists
+                    .playlists
+                    .iter()
+                    .map(|saved| saved.cached_name.clone()),
+            )
+            .collect();
+        for cached_name in cached_names {
+            let Some(mut playlist) = Playlist::read_cached(cached_name.clone()).await else {
+                continue;
+            };
+            let gone = playlist.remove_missing();
+            if gone.is_empty() {
+                continue;
+            }
+            removed.extend(gone);
+            if let Err(e) = playlist.write_cached(cached_name.clone()).await {
+                eprintln!("Could not cache {}: {}", playlist.name, e);
+            }
+            if let Some(root) = self
+                .roots
+                .roots
+                .iter_mut()
+                .find(|root| root.cached_name == cached_name)
+            {
+                root.track_count = playlist.tracks.len();
+            }
+        }
+        if let Err(e) = self.roots.save() {
+            eprintln!("Could not save library folders: {}", e);
+        }
+
+        removed.extend(
+            self.playlist
+                .lock()
+                .expect("Could not lock playlist")
+                .remove_missing(),
+        );
+        let playing = self.queue.get(self.current_index).map(|t| t.uri.clone());
+        removed.extend(
+            self.queue
+                .iter()
+                .filter(|track| track.missing)
+                .map(|track| track.uri.clone()),
+        );
+        self.queue.retain(|track| !track.missing);
+        self.current_index = playing
+            .and_then(|uri| self.queue.iter().position(|t| t.uri == uri))
+            .unwrap_or(0);
+        if self.queue.is_empty() {
+            self.transition(PlaybackState::Idle);
+        }
+
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.send_roots();
+        self.library_changed().await;
+        self.send_library();
+        self.tx
+            .send(Response::Info(match removed.len() {
+                0 => "No missing tracks".to_string(),
+                1 => "Removed 1 missing track".to_string(),
+                count => format!("Removed {count} missing tracks"),
+            }))
+            .expect("Could not send message");
+    }
+
+    /// Whether the file of the track at `index` is there to play. A missing file is looked for
+    /// elsewhere in the library first, in case it was moved; failing that the track is flagged
+    /// and left for the next one.
+    async fn ensure_file(&mut self, index: usize) -> bool {
+        let Some(track) = self.queue.get(index) else {
+            return false;
+        };
+        let was_missing = track.missing;
+        // Looking at a file on a mount that stopped answering blocks, so look from another
+        // thread and give up after a while.
+        let path = track.path();
+        let missing = timed(
+            "Opening the file",
+            smol::unblock(move || Ok(path.is_some_and(|path| !path.exists()))),
+        )
+        .await;
+        let missing = match missing {
+            Ok(missing) => missing,
+            Err(e) => {
+                self.report_failed(index, e);
+                return false;
+            }
+        };
+        self.queue[index].missing = missing;
+        if !missing {
+            if was_missing {
+                self.tx
+                    .send(Response::Tracks(self.queue.clone()))
+                    .expect("Could not send message");
+            }
+            return true;
+        }
+        self.rebind_missing().await;
+        let track = &self.queue[index];
+        if !track.missing {
+            return true;
+        }
+        let uri = track.uri.clone();
+        self.tx
+            .send(Response::Warning(format!(
+                "{} is missing. Its file may have been moved or its drive unplugged.",
+                track.title
+            )))
+            .expect("Could not send message");
+        if let Some(track) = self
+            .playlist
+            .lock()
+            .expect("Could not lock playlist")
+            .tracks
+            .iter_mut()
+            .find(|track| track.uri == uri)
+        {
+            track.missing = true;
+        }
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        false
+    }
+
+    /// Hand the tracks for `uris` to `then`, taking them from the library where it has them
+    /// and reading the rest from their files on a scan thread, so tracks of playlists outside
+    /// the library can be queued too. Tracks read from files are marked as coming from `origin`.
+    async fn resolve_tracks(&mut self, uris: Vec<String>, origin: Origin, then: Resolved) {
+        let paths: Vec<PathBuf> = uris
+            .iter()
+            .filter(|uri| self.library.track(uri).is_none())
+            .filter_map(|uri| uri::to_path(uri))
+            .collect();
+        if paths.is_empty() {
+            self.finish_resolve(uris, vec![], then).await;
+        } else {
+            self.read_files(paths, origin, AfterRead::Resolve { uris, then });
+        }
+    }
+
+    /// Hand the tracks for `uris` to `then`, with the files the library lacks read into `read`.
+    /// URIs with neither a library track nor a file are left out.
+    async fn finish_resolve(&mut self, uris: Vec<String>, read: Vec<Track>, then: Resolved) {
+        let read: HashMap<String, Track> = read
+            .into_iter()
+            .map(|track| (track.uri.clone(), track))
+            .collect();
+        let mut tracks: Vec<Track> = uris
+            .iter()
+            .filter_map(|uri| {
+                self.library.track(uri).or_else(|| {
+                    read.get(uri)
+                        .or_else(|| read.get(&uri::normalize(uri)))
+                        .cloned()
+                })
+            })
+            .collect();
+        self.tag_edits.apply(&mut tracks);
+        match then {
+            Resolved::Enqueue => self.append_tracks(tracks).await,
+            Resolved::PlayNext => self.play_next(tracks).await,
+            Resolved::AddToPlaylist(cached_name) => {
+                self.add_tracks_to_playlist(&cached_name, tracks).await
+            }
+        }
+    }
+
+    /// Up to [`AUTOFILL_TRACKS`] library tracks to follow the current one: ones sharing an
+    /// artist with it first, then ones of its genre, then ones never played, then any others,
+    /// each group in random order. Queued, missing and corrupt tracks are left out.
+    fn autofill_tracks(&self) -> Vec<Track> {
+        let Some(seed) = self.queue.get(self.current_index) else {
+            return vec![];
+        };
+        let queued: HashSet<&str> = self.queue.iter().map(|track| track.uri.as_str()).collect();
+        let mut groups: [Vec<&Track>; 4] = Default::default();
+        for track in self.library.tracks() {
+            if queued.contains(track.uri.as_str()) || track.missing || track.corrupt {
+                continue;
+            }
+            let same_artist = track
+                .artists
+                .iter()
+                .any(|artist| artist != "Unknown Artist" && seed.artists.contains(artist));
+            let group = if same_artist {
+                0
+            } else if !seed.genre.is_empty() && track.genre == seed.genre {
+                1
+            } else if self.plays.get(&track.uri) == 0 {
+                2
+            } else {
+                3
+            };
+            groups[group].push(track);
+        }
+        let mut rng = rand::rng();
+        groups
+            .into_iter()
+            .flat_map(|mut group| {
+                group.shuffle(&mut rng);
+                group
+            })
+            .take(AUTOFILL_TRACKS)
+            .cloned()
+            .collect()
+    }
+
+    /// Top the queue up with tracks like the current one, as it is about to run out.
+    fn autofill_queue(&mut self) {
+        let tracks = self.autofill_tracks();
+        if tracks.is_empty() {
+            return;
+        }
+        let count = tracks.len();
+        self.queue.extend(tracks);
+        self.mark_session_dirty();
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Info(format!("Autofill added {count} tracks")))
+            .expect("Could not send message");
+    }
+
+    /// Append the tracks at `uris` to the queue, loading the first one if nothing was loaded
+    /// yet. They stay out of the playlist, so a rescan or unshuffle drops them again.
+    async fn enqueue(&mut self, uris: Vec<String>, origin: Origin) {
+        self.resolve_tracks(uris, origin, Resolved::Enqueue).await;
+    }
+
+    /// Add `tracks` to the end of the queue, loading the first one if nothing was loaded yet.
+    async fn append_tracks(&mut self, tracks: Vec<Track>) {
+        let Some(first) = tracks.first().map(|track| track.title.clone()) else {
+            return;
+        };
+        let count = tracks.len();
+        self.extend_queue(tracks).await;
+        self.tx
+            .send(Response::Info(match count {
+                1 => format!("Added {first} to the queue"),
+                n => format!("Added {n} tracks to the queue"),
+            }))
+            .expect("Could not send message");
+    }
+
+    /// [`Self::append_tracks`] without announcing what was added.
+    async fn extend_queue(&mut self, tracks: Vec<Track>) {
+        if tracks.is_empty() {
+            return;
+        }
+        self.queue.extend(tracks);
+        self.mark_session_dirty();
+        if !self.state.is_loaded() {
+            let backend = self.backend.clone();
+            self.begin_loading().await;
+            self.current_index = 0;
+            if let Err(e) = self.load(&backend, 0).await {
+                self.tx
+                    .send(Response::Error(e.to_string()))
+                    .expect("Could not send message");
+            }
+            self.transition(PlaybackState::Stopped);
+        }
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+    }
+
+    /// Start reading `paths` on a scan thread, so walking a large folder doesn't hold up the
+    /// player. Tracks are added as each batch arrives.
+    fn enqueue_paths(&mut self, paths: Vec<PathBuf>, origin: Origin, play: bool) {
+        let job = ScanJob::files(
+            self.backend.clone(),
+            paths,
+            origin,
+            self.scan.clone(),
+            self.scan_tx.clone(),
+        );
+        self.enqueue_scans.push(EnqueueScan {
+            job,
+            added: 0,
+            play,
+        });
+    }
+
+    /// Start reading `paths` on a scan thread, and carry on with `then` once they are all in.
+    fn read_files(&mut self, paths: Vec<PathBuf>, origin: Origin, then: AfterRead) {
+        let job = ScanJob::files(
+            self.backend.clone(),
+            paths,
+            origin,
+            self.scan.clone(),
+            self.scan_tx.clone(),
+        );
+        self.file_reads.push(FileRead {
+            job,
+            tracks: vec![],
+            then,
+        });
+    }
+
+    async fn handle_file_read(&mut self, update: ScanUpdate) {
+        let Some(index) = self
+            .file_reads
+            .iter()
+            .position(|read| read.job.id == update.id())
+        else {
+            return;
+        };
+        match update {
+            ScanUpdate::Batch(_, tracks) => self.file_reads[index].tracks.extend(tracks),
+            ScanUpdate::Progress { .. } => {}
+            ScanUpdate::Done(_) => {
+                let read = self.file_reads.remove(index);
+                match read.then {
+                    AfterRead::Import { path, entries } => {
+                        self.finish_import(path, entries, read.tracks).await
+                    }
+                    AfterRead::FolderChanges {
+                        cached_name,
+                        removed,
+                    } => {
+                        self.finish_folder_changes(cached_name, removed, read.tracks)
+                            .await
+                    }
+                    AfterRead::Resolve { uris, then } => {
+                        self.finish_resolve(uris, read.tracks, then).await
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle_enqueue_scan(&mut self, update: ScanUpdate) {
+        let Some(index) = self
+            .enqueue_scans
+            .iter()
+            .position(|scan| scan.job.id == update.id())
+        else {
+            return;
+        };
+        match update {
+            ScanUpdate::Batch(_, tracks) => {
+                // Tracks the library already knows keep what it has, as with enqueued URIs.
+                let mut tracks: Vec<Track> = tracks
+                    .into_iter()
+                    .map(|track| self.library.track(&track.uri).unwrap_or(track))
+                    .collect();
+                self.tag_edits.apply(&mut tracks);
+                let start = self.queue.len();
+                let scan = &mut self.enqueue_scans[index];
+                scan.added += tracks.len();
+                let play = !tracks.is_empty() && std::mem::take(&mut scan.play);
+                self.extend_queue(tracks).await;
+                if play {
+                    self.play_from(start).await;
+                }
+            }
+            ScanUpdate::Progress { .. } => {}
+            ScanUpdate::Done(_) => {
+                let message = match self.enqueue_scans.remove(index).added {
+                    0 => {
+                        self.tx
+                            .send(Response::Error("No audio files to add".to_string()))
+                            .expect("Could not send message");
+                        return;
+                    }
+                    1 => "Added 1 track to the queue".to_string(),
+                    n => format!("Added {n} tracks to the queue"),
+                };
+                self.tx
+                    .send(Response::Info(message))
+                    .expect("Could not send message");
+            }
+        }
+    }
+
+    async fn remove_track(&mut self, index: usize) {
+        if index >= self.queue.len() {
+            return;
+        }
+        if self.queue.len() == 1 {
+            self.clear_queue().await;
+            return;
+        }
+        let removed = self.queue.remove(index);
+        let current = self.state.is_loaded() && index == self.current_index;
+        self.current_index = index_after_removal(self.current_index, &[index]);
+        if current {
+            self.current_removed().await;
+        }
+        // With nothing loaded, the entry after the removed last one may be past the end.
+        self.current_index = self.current_index.min(self.queue.len() - 1);
+        self.mark_session_dirty();
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Info(format!(
+                "Removed {} from the queue",
+                removed.title
+            )))
+            .expect("Could not send message");
+    }
+
+    /// Take the queue entries at `indices` out at once. Taking out the current track plays the
+    /// first one left after it.
+    async fn remove_tracks(&mut self, mut indices: Vec<usize>) {
+        indices.sort_unstable();
+        indices.dedup();
+        indices.retain(|&index| index < self.queue.len());
+        match indices.len() {
+            0 => return,
+            1 => return self.remove_track(indices[0]).await,
+            count if count == self.queue.len() => return self.clear_queue().await,
+            _ => {}
+        }
+        let current = self.state.is_loaded() && indices.contains(&self.current_index);
+        for &index in indices.iter().rev() {
+            self.queue.remove(index);
+        }
+        self.current_index = index_after_removal(self.current_index, &indices);
+        if current {
+            self.current_removed().await;
+        }
+        self.current_index = self.current_index.min(self.queue.len() - 1);
+        self.mark_session_dirty();
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Info(format!(
+                "Removed {} tracks from the queue",
+                indices.len()
+            )))
+            .expect("Could not send message");
+    }
+
+    /// Load the track that moved into the current track's place after it was taken out of the
+    /// queue, or stop if it was the last one.
+    async fn current_removed(&mut self) {
+        let index = self.current_index;
+        if index == self.queue.len() {
+            self.current_index = index - 1;
+            self.stop_playback().await;
+        } else if self.state.is_playing() {
+            self.play_from(index).await;
+        } else {
+            let backend = self.backend.clone();
+            let _ = backend.stop().await;
+            if let Err(e) = self.play_id(&backend, index).await {
+                self.report_failed(index, e);
+            }
+        }
+        self.tx
+            .send(Response::Metadata(self.queue[self.current_index].clone()))
+            .expect("Could not send message");
+    }
+
+    async fn clear_queue(&mut self) {
+        if self.queue.is_empty() {
+            return;
+        }
+        self.stop_playback().await;
+        self.queue.clear();
+        self.current_index = 0;
+        self.transition(PlaybackState::Idle);
+        self.volume_ramp = None;
+        self.mark_session_dirty();
+        self.tx
+            .send(Response::Tracks(vec![]))
+            .expect("Could not send message");
+    }
+
+    fn move_track(&mut self, from: usize, to: usize) {
+        if from == to || from >= self.queue.len() || to >= self.queue.len() {
+            return;
+        }
+        let track = self.queue.remove(from);
+        self.queue.insert(to, track);
+
+        self.current_index = index_after_move(self.current_index, from, to);
+        self.mark_session_dirty();
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+    }
+
+    /// URI of the current track, to find it again after the queue is rebuilt.
+    fn current_uri(&self) -> Option<String> {
+        self.queue
+            .get(self.current_index)
+            .map(|track| track.uri.clone())
+    }
+
+    /// Point `current_index` back at the track at `uri` after the queue was rebuilt, or keep it
+    /// in range if that track is gone.
+    fn reanchor(&mut self, uri: Option<String>) {
+        if let Some(index) = uri.and_then(|uri| self.queue.iter().position(|t| t.uri == uri)) {
+            self.current_index = index;
+        }
+        self.current_index = self.current_index.min(self.queue.len().saturating_sub(1));
+        self.mark_session_dirty();
+    }
+
+    /// Put the playlist back in its own order, keeping tracks added to the queue since at the
+    /// end and the current track current.
+    fn unshuffle_queue(&mut self) {
+        let current = self.current_uri();
+        let playlist = self.playlist_queue();
+        let listed: HashSet<&str> = playlist.iter().map(|track| track.uri.as_str()).collect();
+        let added: Vec<Track> = self
+            .queue
+            .iter()
+            .filter(|track| !listed.contains(track.uri.as_str()))
+            .cloned()
+            .collect();
+        self.queue = playlist;
+        self.queue.extend(added);
+        self.reanchor(current);
+    }
+
+    /// Shuffle the queue from `seed` the way its playlist is set to, keeping the current track
+    /// current.
+    fn shuffle_queue(&mut self, seed: u64) {
+        let current = self.current_uri();
+        let mut rng = StdRng::seed_from_u64(seed);
+        // Corrupt tracks stay out of shuffle until a rescan clears them, waiting at the end where
+        // playback skips over them.
+        let (mut queue, corrupt): (Vec<Track>, Vec<Track>) =
+            self.queue.drain(..).partition(|track| !track.corrupt);
+        match self.shuffle_mode {
+            // Often-skipped tracks drift towards the end.
+            ShuffleMode::Tracks => self.skips.shuffle(&mut queue, &mut rng),
+            ShuffleMode::Albums => library::shuffle_albums(&mut queue, &mut rng),
+        }
+        queue.extend(corrupt);
+        self.queue = queue;
+        self.reanchor(current);
+    }
+
+    /// Store the shuffle mode, and the seed when given, with the loaded saved playlist.
+    fn remember_shuffle(&mut self, seed: Option<u64>) {
+        let name = self
+            .playlist
+            .lock()
+            .expect("Could not lock playlist")
+            .name
+            .clone();
+        if let Err(e) = self
+            .saved_playlists
+            .set_shuffle(&name, self.shuffle_mode, seed)
+        {
+            self.tx
+                .send(Response::Error(format!("Could not save playlists: {e}")))
+                .expect("Could not send message");
+        }
+    }
+
+    /// Put the queue in the order of `uris`, keeping the current track current. Entries not
+    /// among them keep their order after the ones that are.
+    fn reorder_queue(&mut self, uris: Vec<String>) {
+        let playing = self
+            .state
+            .is_loaded()
+            .then(|| self.queue[self.current_index].uri.clone());
+        let order: HashMap<&str, usize> = uris
+            .iter()
+            .enumerate()
+            .map(|(index, uri)| (uri.as_str(), index))
+            .collect();
+        self.queue
+            .sort_by_key(|track| order.get(track.uri.as_str()).copied().unwrap_or(usize::MAX));
+        if let Some(index) = playing.and_then(|uri| self.queue.iter().position(|t| t.uri == uri)) {
+            self.current_index = index;
+        }
+        self.mark_session_dirty();
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+    }
+
+    async fn insert_next(&mut self, uris: Vec<String>) {
+        self.resolve_tracks(uris, Origin::Unknown, Resolved::PlayNext)
+            .await;
+    }
+
+    /// Put `tracks` right after the current one.
+    async fn play_next(&mut self, tracks: Vec<Track>) {
+        if !self.state.is_loaded() {
+            // Nothing to go after, so this is the same as adding them.
+            self.append_tracks(tracks).await;
+            return;
+        }
+        let Some(first) = tracks.first().map(|track| track.title.clone()) else {
+            return;
+        };
+        let count = tracks.len();
+        let at = (self.current_index + 1).min(self.queue.len());
+        self.queue.splice(at..at, tracks);
+        self.mark_session_dirty();
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Info(match count {
+                1 => format!("Playing {first} next"),
+                n => format!("Playing {n} tracks next"),
+            }))
+            .expect("Could not send message");
+    }
+
+    /// Make the tracks of `album` by `artist` the queue and start playing from the top.
+    async fn play_album(&mut self, album: String, artist: String) {
+        let tracks = self.library.album_tracks(&album, &artist);
+        if tracks.is_empty() {
+            self.tx
+                .send(Response::Error(format!("No tracks found for {album}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.play_tracks(album, tracks).await;
+    }
+
+    /// Add the tracks of `album` by `artist` to the end of the queue.
+    async fn queue_album(&mut self, album: String, artist: String) {
+        let tracks = self.library.album_tracks(&album, &artist);
+        if tracks.is_empty() {
+            self.tx
+                .send(Response::Error(format!("No tracks found for {album}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.append_tracks(tracks).await;
+    }
+
+    /// Add every track by `artist` to the end of the queue.
+    async fn queue_artist(&mut self, artist: String) {
+        let tracks = self.library.artist_tracks(&artist);
+        if tracks.is_empty() {
+            self.tx
+                .send(Response::Error(format!("No tracks found for {artist}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.append_tracks(tracks).await;
+    }
+
+    /// Make the results of searching for `query` the queue, in the order they were found.
+    async fn play_search(&mut self, query: String) {
+        if self.library.is_empty() {
+            self.refresh_library().await;
+        }
+        let tracks = self
+            .library
+            .filtered(&self.library_filter)
+            .search(&query, SEARCH_RESULTS);
+        if tracks.is_empty() {
+            self.tx
+                .send(Response::Warning(format!("Nothing found for {query}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.play_tracks(format!("Search: {}", query.trim()), tracks)
+            .await;
+        self.tx
+            .send(Response::UnsavedQueue)
+            .expect("Could not send message");
+    }
+
+    /// Save the queue, in playing order, as a playlist named like it.
+    async fn save_queue(&mut self, name: Option<String>) {
+        if self.queue.is_empty() {
+            return;
+        }
+        let name = match name.map(|name| name.trim().to_string()) {
+            Some(name) if !name.is_empty() => name,
+            _ => self
+                .playlist
+                .lock()
+                .expect("Could not lock playlist")
+                .name
+                .clone(),
+        };
+        let playlist = Playlist {
+            name,
+            tracks: self.queue.clone(),
+        };
+        let cached = format!("saved_{}", cached_name(&playlist.name));
+        match self.saved_playlists.add(&playlist, cached).await {
+            Ok(saved) => {
+                self.playlist.lock().expect("Could not lock playlist").name = saved.name.clone();
+                self.tx
+                    .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+                    .expect("Could not send message");
+                self.tx
+                    .send(Response::PlaylistName(saved.name.clone()))
+                    .expect("Could not send message");
+                self.tx
+                    .send(Response::Info(format!("Saved the queue as {}", saved.name)))
+                    .expect("Could not send message");
+                self.library_changed().await;
+            }
+            Err(e) => self
+                .tx
+                .send(Response::Error(format!("Could not save the queue: {e}")))
+                .expect("Could not send message"),
+        }
+    }
+
+    /// Replace the queue with `tracks` from the library, as a playlist called `name` with no
+    /// folder behind it, and start playing from the top.
+    async fn play_tracks(&mut self, name: String, tracks: Vec<Track>) {
+        self.cancel_scan();
+        self.watch_folder(None);
+        self.smart_loaded = None;
+        let playlist = Playlist { name, tracks };
+
+        self.begin_loading().await;
+        self.shuffle = false;
+        self.playlist = Arc::new(Mutex::new(playlist.clone()));
+        self.queue = matching::dedupe_sources(playlist.tracks, &self.prefer_remote);
+        self.tx
+            .send(Response::PlaylistName(playlist.name))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Shuffle(false))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.play_from(0).await;
+    }
+
+    /// Send the smart playlists with how many tracks each matches right now.
+    async fn send_smart_playlists(&mut self) {
+        if self.library.is_empty() {
+            self.refresh_library().await;
+        }
+        let playlists = self
+            .smart_playlists
+            .playlists
+            .iter()
+            .map(|playlist| {
+                let count = playlist
+                    .tracks(&self.library, &self.plays, &self.ratings)
+                    .len();
+                (playlist.clone(), count)
+            })
+            .collect();
+        self.tx
+            .send(Response::SmartPlaylists(playlists))
+            .expect("Could not send message");
+    }
+
+    async fn save_smart_playlist(&mut self, index: Option<usize>, playlist: SmartPlaylist) {
+        let name = playlist.name.clone();
+        match index.and_then(|index| self.smart_playlists.playlists.get_mut(index)) {
+            Some(existing) => {
+                // Follow a rename of the playlist that is playing.
+                if self.smart_loaded.as_ref() == Some(&existing.name) {
+                    self.smart_loaded = Some(name.clone());
+                    self.playlist.lock().expect("Could not lock playlist").name = name.clone();
+                    self.tx
+                        .send(Response::PlaylistName(name))
+                        .expect("Could not send message");
+                }
+                *existing = playlist;
+            }
+            None => self.smart_playlists.playlists.push(playlist),
+        }
+        if let Err(e) = self.smart_playlists.save() {
+            self.tx
+                .send(Response::Error(format!(
+                    "Could not save smart playlists: {e}"
+                )))
+                .expect("Could not send message");
+        }
+        self.refresh_smart_playlist().await;
+        self.send_smart_playlists().await;
+    }
+
+    async fn rate(&mut self, stars: u32) {
+        let uri = self.queue[self.current_index].uri.clone();
+        self.ratings.set(&uri, stars);
+        if let Err(e) = self.ratings.save() {
+            self.tx
+                .send(Response::Error(format!("Could not save rating: {e}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.tx
+            .send(Response::Rated(stars.min(5)))
+            .expect("Could not send message");
+        // Ratings feed smart playlist rules, so their counts may have moved.
+        self.send_smart_playlists().await;
+    }
+
+    /// Note a skip of the current track when the user moves away from it early.
+    fn record_skip(&mut self) {
+        if let Some(track) = self.queue.get(self.current_index) {
+            self.skips.record(track, self.position);
+            self.mark_session_dirty();
+        }
+    }
+
+    async fn send_most_skipped(&mut self) {
+        if self.library.is_empty() {
+            self.refresh_library().await;
+        }
+        let skipped = self
+            .skips
+            .most_skipped()
+            .into_iter()
+            .filter_map(|(uri, skips, typical_position)| {
+                let track = self
+                    .library
+                    .track(&uri)
+                    .or_else(|| self.queue.iter().find(|t| t.uri == uri).cloned())?;
+                Some(SkippedTrack {
+                    track,
+                    skips,
+                    typical_position,
+                })
+            })
+            .collect();
+        self.tx
+            .send(Response::MostSkipped(skipped))
+            .expect("Could not send message");
+    }
+
+    /// Start counting usage where it was left, or save what's been counted and stop.
+    fn set_insights(&mut self, enabled: bool) {
+        if enabled == self.insights.is_some() {
+            return;
+        }
+        if enabled {
+            self.insights = Some(Insights::load());
+        } else {
+            self.save_insights();
+            self.insights = None;
+        }
+        self.send_insights();
+    }
+
+    fn save_insights(&self) {
+        if let Some(Err(e)) = self.insights.as_ref().map(Insights::save) {
+            self.tx
+                .send(Response::Warning(format!("Could not save insights: {e}")))
+                .expect("Could not send message");
+        }
+    }
+
+    fn send_insights(&self) {
+        self.tx
+            .send(Response::Insights(self.insights.clone()))
+            .expect("Could not send message");
+    }
+
+    async fn delete_smart_playlist(&mut self, index: usize) {
+        if index >= self.smart_playlists.playlists.len() {
+            return;
+        }
+        let playlist = self.smart_playlists.playlists.remove(index);
+        // The queue stays as it is, but no longer follows the library.
+        if self.smart_loaded.as_ref() == Some(&playlist.name) {
+            self.smart_loaded = None;
+        }
+        if let Err(e) = self.smart_playlists.save() {
+            self.tx
+                .send(Response::Error(format!(
+                    "Could not save smart playlists: {e}"
+                )))
+                .expect("Could not send message");
+        }
+        self.send_smart_playlists().await;
+    }
+
+    async fn play_smart_playlist(&mut self, index: usize) {
+        let Some(smart) = self.smart_playlists.playlists.get(index).cloned() else {
+            return;
+        };
+        self.refresh_library().await;
+        let tracks = smart.tracks(&self.library, &self.plays, &self.ratings);
+        if tracks.is_empty() {
+            self.tx
+                .send(Response::Error(format!("No tracks match {}", smart.name)))
+                .expect("Could not send message");
+            return;
+        }
+        self.play_tracks(smart.name.clone(), tracks).await;
+        self.smart_loaded = Some(smart.name);
+    }
+
+    /// Bring the queue of a loaded smart playlist in line with the library. The track playing
+    /// stays even if it no longer matches, so a rescan or rule change never cuts it off.
+    async fn refresh_smart_playlist(&mut self) {
+        let Some(name) = self.smart_loaded.clone() else {
+            return;
+        };
+        let Some(smart) = self
+            .smart_playlists
+            .playlists
+            .iter()
+            .find(|playlist| playlist.name == name)
+            .cloned()
+        else {
+            self.smart_loaded = None;
+            return;
+        };
+        let mut tracks = smart.tracks(&self.library, &self.plays, &self.ratings);
+        let playing = self.queue.get(self.current_index).cloned();
+        if let Some(track) = playing
+            .clone()
+            .filter(|track| !tracks.iter().any(|t| t.uri == track.uri))
+        {
+            tracks.insert(self.current_index.min(tracks.len()), track);
+        }
+        {
+            let mut playlist = self.playlist.lock().expect("Could not lock playlist");
+            let unchanged = playlist.tracks.len() == tracks.len()
+                && playlist
+                    .tracks
+                    .iter()
+                    .zip(tracks.iter())
+                    .all(|(a, b)| a.uri == b.uri);
+            if unchanged {
+                return;
+            }
+            playlist.tracks = tracks;
+        }
+
+        if self.shuffle {
+            // Keep the shuffled order and deal newly matching tracks in at the end.
+            let playlist = self.playlist_queue();
+            self.queue
+                .retain(|track| playlist.iter().any(|t| t.uri == track.uri));
+            let fresh: Vec<Track> = playlist
+                .into_iter()
+                .filter(|track| !self.queue.iter().any(|t| t.uri == track.uri))
+                .collect();
+            self.queue.extend(fresh);
+        } else {
+            self.queue = self.playlist_queue();
+        }
+        if let Some(index) =
+            playing.and_then(|playing| self.queue.iter().position(|t| t.uri == playing.uri))
+        {
+            self.current_index = index;
+        }
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+    }
+
+    /// Track buffering and stream errors for network URIs, pausing while the buffer refills and
+    /// scheduling a reconnect when the stream drops.
+    async fn handle_stream_health(&mut self, res: &Response) {
+        let remote = self.state.is_loaded()
+            && self
+                .queue
+                .get(self.current_index)
+                .is_some_and(|track| track.is_remote());
+        if !remote {
+            return;
+        }
+
+        match res {
+            Response::Buffering(percent) => {
+                if *percent < 100 && !self.stalled {
+                    self.stalled = true;
+                    if self.state.is_playing() {
+                        let _ = self.backend.pause().await;
+                    }
+                    self.tx
+                        .send(Response::Stalled)
+                        .expect("Could not send message");
+                } else if *percent >= 100 && self.stalled {
+                    self.stalled = false;
+                    self.reconnect_attempts = 0;
+                    self.reconnect_at = None;
+                    if self.state.is_playing() {
+                        let _ = self.backend.play().await;
+                        // Time spent buffering doesn't count against the watchdog.
+                        if self.started_at.is_some() {
+                            self.started_at = Some(Instant::now());
+                        }
+                    }
+                    self.tx
+                        .send(Response::Recovered)
+                        .expect("Could not send message");
+                }
+            }
+            Response::Error(_) | Response::Undecodable(_) if self.state.is_playing() => {
+                self.schedule_reconnect()
+            }
+            _ => {}
+        }
+    }
+
+    fn schedule_reconnect(&mut self) {
+        if self.reconnect_attempts >= MAX_RECONNECT_ATTEMPTS {
+            self.stalled = false;
+            self.reconnect_attempts = 0;
+            self.reconnect_at = None;
+            self.transition(PlaybackState::Stopped);
+            self.tx
+                .send(Response::Error(
+                    "Stream could not be recovered.".to_string(),
+                ))
+                .expect("Could not send message");
+            return;
+        }
+
+        let delay =
+            (RECONNECT_BASE_DELAY * 2u32.pow(self.reconnect_attempts)).min(RECONNECT_MAX_DELAY);
+        self.reconnect_attempts += 1;
+        self.reconnect_at = Some(Instant::now() + delay);
+        if !self.stalled {
+            self.stalled = true;
+            self.tx
+                .send(Response::Stalled)
+                .expect("Could not send message");
+        }
+    }
+
+    async fn reconnect(&mut self) {
+        self.reconnect_at = None;
+        let backend = self.backend.clone();
+        let _ = backend.stop().await;
+        if self.load(&backend, self.current_index).await.is_err() || backend.play().await.is_err() {
+            self.schedule_reconnect();
+            return;
+        }
+        if !self.exclusive {
+            let _ = backend.set_volume(self.volume).await;
+        }
+    }
+
+    /// Edit log of the Listen Later playlist, if a shared folder is set. The first time the
+    /// folder is used, the log is seeded with the playlist as it was kept locally.
+    async fn listen_later_log(&self) -> Option<OpLog> {
+        let log = OpLog::new(self.shared_dir.clone()?, LISTEN_LATER_CACHE);
+        if !log.started() {
+            if let Some(local) = Playlist::read_cached(LISTEN_LATER_CACHE.to_string()).await {
+                let uris: Vec<String> = local.tracks.iter().map(|t| t.uri.clone()).collect();
+                if let Err(e) = log.seed(&uris) {
+                    eprintln!("Could not share the Listen Later playlist: {e}");
+                }
+            }
+        }
+        Some(log)
+    }
+
+    /// The Listen Later playlist as it currently stands. With a shared folder, every device's
+    /// edits are merged, reusing cached metadata for tracks that are already known.
+    async fn read_listen_later(&self) -> Option<Playlist> {
+        let cached = Playlist::read_cached(LISTEN_LATER_CACHE.to_string()).await;
+        let Some(log) = self.listen_later_log().await else {
+            return cached;
+        };
+        let known = cached.map(|playlist| playlist.tracks).unwrap_or_default();
+        let mut tracks = vec![];
+        for uri in log.merge() {
+            if let Some(track) = known.iter().find(|t| t.uri == uri) {
+                tracks.push(track.clone());
+                continue;
+            }
+            match self.backend.get_meta(&uri).await {
+                Ok(track) => tracks.push(track),
+                Err(e) => eprintln!("Failed to load metadata for {:?}: {}", uri, e),
+            }
+        }
+        Some(Playlist {
+            name: LISTEN_LATER_NAME.to_string(),
+            tracks,
+        })
+    }
+
+    /// Apply `op` to the Listen Later playlist, logging it to the shared folder if one is set.
+    async fn edit_listen_later(&mut self, op: Op) -> anyhow::Result<Playlist> {
+        if let Some(log) = self.listen_later_log().await {
+            log.append(op.clone())?;
+        }
+        let mut playlist = self.read_listen_later().await.unwrap_or_else(|| Playlist {
+            name: LISTEN_LATER_NAME.to_string(),
+            tracks: vec![],
+        });
+        // The merged log already reflects the edit; a local-only playlist still needs it applied.
+        let track = &self.queue[self.current_index];
+        match op {
+            Op::Add(_) => {
+                if !playlist.tracks.iter().any(|t| t.uri == track.uri) {
+                    playlist.tracks.push(track.clone());
+                }
+            }
+            Op::Remove(_) => playlist.tracks.retain(|t| t.uri != track.uri),
+        }
+        playlist
+            .write_cached(LISTEN_LATER_CACHE.to_string())
+            .await?;
+        Ok(playlist)
+    }
+
+    /// The tracks of `saved` as last cached, without scanning its folder.
+    async fn read_saved(&self, saved: &SavedPlaylist) -> Option<Playlist> {
+        if saved.is_listen_later() {
+            self.read_listen_later().await
+        } else {
+            Playlist::read_cached(saved.cached_name.clone()).await
+        }
+    }
+
+    async fn duplicate_playlist(&mut self, index: usize) {
+        let Some(saved) = self.saved_playlists.playlists.get(index).cloned() else {
+            return;
+        };
+        let Some(playlist) = self.read_saved(&saved).await else {
+            self.tx
+                .send(Response::Error(format!(
+                    "{} has not been scanned yet, so there is nothing to copy",
+                    saved.name
+                )))
+                .expect("Could not send message");
+            return;
+        };
+        match self.saved_playlists.duplicate(index, &playlist).await {
+            Ok(copy) => {
+                self.tx
+                    .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+                    .expect("Could not send message");
+                self.tx
+                    .send(Response::Info(format!("Saved a copy as {}", copy.name)))
+                    .expect("Could not send message");
+            }
+            Err(e) => self
+                .tx
+                .send(Response::Error(format!(
+                    "Could not duplicate playlist: {e}"
+                )))
+                .expect("Could not send message"),
+        }
+    }
+
+    /// Add the tracks of the saved playlist at `from` to the one at `into`, updating the queue
+    /// too when `into` is the one loaded.
+    async fn merge_playlist(&mut self, from: usize, into: usize, duplicates: DuplicateTracks) {
+        let playlists = &self.saved_playlists.playlists;
+        let (Some(source), Some(target)) = (playlists.get(from), playlists.get(into)) else {
+            return;
+        };
+        let (source, target) = (source.clone(), target.clone());
+        if from == into || target.is_listen_later() {
+            self.tx
+                .send(Response::Warning(format!(
+                    "Can't merge into {}",
+                    target.name
+                )))
+                .expect("Could not send message");
+            return;
+        }
+        let tracks = self
+            .read_saved(&source)
+            .await
+            .map(|playlist| playlist.tracks)
+            .unwrap_or_default();
+        match self.merge_into(&target, &tracks, duplicates).await {
+            Ok(changed) => {
+                self.tx
+                    .send(Response::Info(format!(
+                        "Merged {changed} tracks from {} into {}",
+                        source.name, target.name
+                    )))
+                    .expect("Could not send message");
+                self.library_changed().await;
+            }
+            Err(e) => self
+                .tx
+                .send(Response::Error(format!("Could not merge playlists: {e}")))
+                .expect("Could not send message"),
+        }
+    }
+
+    /// Add library tracks, by URI, to the end of the saved playlist at `index`, leaving out
+    /// ones it already has.
+    async fn add_to_playlist(&mut self, index: usize, uris: Vec<String>) {
+        let Some(target) = self.saved_playlists.playlists.get(index) else {
+            return;
+        };
+        let then = Resolved::AddToPlaylist(target.cached_name.clone());
+        self.resolve_tracks(uris, Origin::Unknown, then).await;
+    }
+
+    /// Add `tracks` to the end of the saved playlist cached as `cached_name`, leaving out ones
+    /// it already has.
+    async fn add_tracks_to_playlist(&mut self, cached_name: &str, tracks: Vec<Track>) {
+        let Some(target) = self
+            .saved_playlists
+            .playlists
+            .iter()
+            .find(|saved| saved.cached_name == cached_name)
+            .cloned()
+        else {
+            return;
+        };
+        match self
+            .merge_into(&target, &tracks, DuplicateTracks::Skip)
+            .await
+        {
+            Ok(added) => {
+                self.tx
+                    .send(Response::Info(format!(
+                        "Added {added} tracks to {}",
+                        target.name
+                    )))
+                    .expect("Could not send message");
+                self.library_changed().await;
+            }
+            Err(e) => self
+                .tx
+                .send(Response::Error(format!(
+                    "Could not add to {}: {e}",
+                    target.name
+                )))
+                .expect("Could not send message"),
+        }
+    }
+
+    /// Add `tracks` to the end of the saved playlist `target`, and to the queue too when it's
+    /// the one loaded. Returns how many were added or replaced.
+    async fn merge_into(
+        &mut self,
+        target: &SavedPlaylist,
+        tracks: &[Track],
+        duplicates: DuplicateTracks,
+    ) -> anyhow::Result<usize> {
+        let mut merged = self.read_saved(target).await.unwrap_or_else(|| Playlist {
+            name: target.name.clone(),
+            tracks: vec![],
+        });
+        let changed = merged.merge(tracks, duplicates);
+        merged.write_cached(target.cached_name.clone()).await?;
+
+        let loaded = {
+            let mut playlist = self.playlist.lock().expect("Could not lock playlist");
+            let loaded = playlist.name == target.name;
+            if loaded {
+                *playlist = merged.clone();
+            }
+            loaded
+        };
+        if loaded {
+            let mut queue = Playlist {
+                name: merged.name,
+                tracks: std::mem::take(&mut self.queue),
+            };
+            queue.merge(tracks, duplicates);
+            self.queue = queue.tracks;
+            self.tx
+                .send(Response::Tracks(self.queue.clone()))
+                .expect("Could not send message");
+        }
+        Ok(changed)
+    }
+
+    /// Change the tags of the tracks at `uris` wherever they show up, without touching their
+    /// files.
+    async fn edit_tags(&mut self, uris: Vec<String>, edit: TagEdit) {
+        if uris.is_empty() || edit.is_empty() {
+            return;
+        }
+        self.tag_edits.edit(&uris, &edit);
+        if let Err(e) = self.tag_edits.save() {
+            eprintln!("Could not save tag edits: {e}");
+        }
+        let edited: HashSet<&String> = uris.iter().collect();
+        let retag = |tracks: &mut [Track]| {
+            for track in tracks
+                .iter_mut()
+                .filter(|track| edited.contains(&track.uri))
+            {
+                edit.apply(track);
+                track.artists = self.artist_rules.apply(&track.artists);
+            }
+        };
+        retag(
+            &mut self
+                .playlist
+                .lock()
+                .expect("Could not lock playlist")
+                .tracks,
+        );
+        retag(&mut self.queue);
+
+        self.mark_session_dirty();
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        if self.state.is_loaded() && edited.contains(&self.queue[self.current_index].uri) {
+            self.tx
+                .send(Response::Metadata(self.queue[self.current_index].clone()))
+                .expect("Could not send message");
+        }
+        self.tx
+            .send(Response::Info(match uris.len() {
+                1 => "Edited the tags of 1 track".to_string(),
+                n => format!("Edited the tags of {n} tracks"),
+            }))
+            .expect("Could not send message");
+        self.library_changed().await;
+    }
+
+    async fn send_playlist_tracks(&self, index: usize) {
+        let Some(saved) = self.saved_playlists.playlists.get(index) else {
+            return;
+        };
+        let tracks = self
+            .read_saved(saved)
+            .await
+            .map(|playlist| playlist.tracks)
+            .unwrap_or_default();
+        self.tx
+            .send(Response::PlaylistTracks(index, tracks))
+            .expect("Could not send message");
+    }
+
+    async fn rename_playlist(&mut self, index: usize, name: String) {
+        let old = match self.saved_playlists.rename(index, name) {
+            Ok(old) => old,
+            Err(e) => {
+                self.tx
+                    .send(Response::Warning(format!("Could not rename playlist: {e}")))
+                    .expect("Could not send message");
+                return;
+            }
+        };
+        let name = self.saved_playlists.playlists[index].name.clone();
+        if let Some(mut playlist) = Playlist::read_cached(old.cached_name.clone()).await {
+            playlist.name = name.clone();
+            if let Err(e) = playlist.write_cached(old.cached_name.clone()).await {
+                eprintln!("Could not cache {}: {}", name, e);
+            }
+        }
+        let loaded = {
+            let mut playlist = self.playlist.lock().expect("Could not lock playlist");
+            let loaded = playlist.name == old.name;
+            if loaded {
+                playlist.name = name.clone();
+            }
+            loaded
+        };
+        if loaded {
+            self.tx
+                .send(Response::PlaylistName(name))
+                .expect("Could not send message");
+        }
+        self.tx
+            .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+            .expect("Could not send message");
+    }
+
+    /// Delete the saved playlist at `index`. Its folder is left alone, and if it is loaded the
+    /// queue stays, no longer tied to anything.
+    async fn delete_playlist(&mut self, index: usize) {
+        let saved = match self.saved_playlists.remove(index) {
+            Ok(saved) => saved,
+            Err(e) => {
+                self.tx
+                    .send(Response::Error(format!("Could not delete playlist: {e}")))
+                    .expect("Could not send message");
+                return;
+            }
+        };
+        if self
+            .watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.saved.cached_name == saved.cached_name)
+        {
+            self.watch_folder(None);
+        }
+        if saved.is_listen_later() {
+            self.tx
+                .send(Response::ListenLater(0))
+                .expect("Could not send message");
+        }
+        self.tx
+            .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Info(format!("Deleted {}", saved.name)))
+            .expect("Could not send message");
+        self.library_changed().await;
+    }
+
+    /// Move or remove a track of the saved playlist at `index`. A playlist that follows a
+    /// folder stops following it, since the next rescan would undo the edit otherwise.
+    async fn edit_playlist(&mut self, index: usize, edit: PlaylistEdit) {
+        let Some(saved) = self.saved_playlists.playlists.get(index).cloned() else {
+            return;
+        };
+        let log = if saved.is_listen_later() {
+            self.listen_later_log().await
+        } else {
+            None
+        };
+        if log.is_some() && matches!(edit, PlaylistEdit::Move { .. }) {
+            self.tx
+                .send(Response::Warning(format!(
+                    "{} is shared, so it keeps the order tracks were added in",
+                    saved.name
+                )))
+                .expect("Could not send message");
+            return;
+        }
+        let Some(mut playlist) = self.read_saved(&saved).await else {
+            return;
+        };
+        let Some(track) = playlist.edit(edit) else {
+            return;
+        };
+        let logged = match (log, edit) {
+            (Some(log), PlaylistEdit::Remove(_)) => log.append(Op::Remove(track.uri.clone())),
+            _ => Ok(()),
+        };
+        if let Err(e) = logged {
+            self.tx
+                .send(Response::Error(e.to_string()))
+                .expect("Could not send message");
+            return;
+        }
+        if let Err(e) = playlist.write_cached(saved.cached_name.clone()).await {
+            self.tx
+                .send(Response::Error(format!("Could not edit playlist: {e}")))
+                .expect("Could not send message");
+            return;
+        }
+
+        self.unfollow_folder(index);
+        if saved.is_listen_later() {
+            self.tx
+                .send(Response::ListenLater(playlist.tracks.len()))
+                .expect("Could not send message");
+        }
+
+        let loaded = {
+            let mut loaded = self.playlist.lock().expect("Could not lock playlist");
+            let is_loaded = loaded.name == saved.name;
+            if is_loaded {
+                *loaded = playlist.clone();
+            }
+            is_loaded
+        };
+        // A shuffled queue has its own order, so only removals carry over to it.
+        if loaded && (!self.shuffle || matches!(edit, PlaylistEdit::Remove(_))) {
+            let playing = self.queue.get(self.current_index).map(|t| t.uri.clone());
+            if self.shuffle {
+                if let Some(position) = self.queue.iter().position(|t| t.uri == track.uri) {
+                    self.queue.remove(position);
+                }
+            } else {
+                self.queue = matching::dedupe_sources(playlist.tracks.clone(), &self.prefer_remote);
+            }
+            self.current_index = playing
+                .and_then(|uri| self.queue.iter().position(|t| t.uri == uri))
+                .unwrap_or(self.current_index.min(self.queue.len().saturating_sub(1)));
+            if self.queue.is_empty() {
+                self.transition(PlaybackState::Idle);
+            }
+            self.tx
+                .send(Response::Tracks(self.queue.clone()))
+                .expect("Could not send message");
+        }
+        self.tx
+            .send(Response::PlaylistTracks(index, playlist.tracks))
+            .expect("Could not send message");
+        if matches!(edit, PlaylistEdit::Remove(_)) {
+            self.library_changed().await;
+        }
+    }
+
+    /// Stop the saved playlist at `index` following its folder, if it does, since the next
+    /// rescan would undo edits made to it otherwise.
+    fn unfollow_folder(&mut self, index: usize) {
+        let saved = self.saved_playlists.playlists[index].clone();
+        if saved.actual_path.is_empty() {
+            return;
+        }
+        self.saved_playlists.playlists[index].actual_path = String::new();
+        if let Err(e) = SavedPlaylists::save_playlists(&self.saved_playlists) {
+            eprintln!("Could not save playlists: {}", e);
+        }
+        if self
+            .watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.saved.cached_name == saved.cached_name)
+        {
+            self.watch_folder(None);
+        }
+        self.tx
+            .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Info(format!(
+                "{} no longer follows {}",
+                saved.name, saved.actual_path
+            )))
+            .expect("Could not send message");
+    }
+
+    /// Show the entry at `entry` of the saved playlist at `index` as `title`, with `note`, in
+    /// that playlist only. Empty ones go back to the tagged title and no note.
+    async fn label_playlist_entry(
+        &mut self,
+        index: usize,
+        entry: usize,
+        title: String,
+        note: String,
+    ) {
+        let Some(saved) = self.saved_playlists.playlists.get(index).cloned() else {
+            return;
+        };
+        let Some(mut playlist) = self.read_saved(&saved).await else {
+            return;
+        };
+        let Some(track) = playlist.tracks.get_mut(entry) else {
+            return;
+        };
+        track.custom_title = title.trim().to_string();
+        track.note = note.trim().to_string();
+        if let Err(e) = playlist.write_cached(saved.cached_name.clone()).await {
+            self.tx
+                .send(Response::Error(format!("Could not edit playlist: {e}")))
+                .expect("Could not send message");
+            return;
+        }
+        self.unfollow_folder(index);
+        {
+            let mut loaded = self.playlist.lock().expect("Could not lock playlist");
+            if loaded.name == saved.name {
+                *loaded = playlist.clone();
+            }
+        }
+        self.tx
+            .send(Response::PlaylistTracks(index, playlist.tracks))
+            .expect("Could not send message");
+    }
+
+    async fn export_playlist(&mut self, index: usize, paths: ExportPaths) {
+        let Some(saved) = self.saved_playlists.playlists.get(index).cloned() else {
+            return;
+        };
+        let Some(playlist) = self.read_saved(&saved).await else {
+            self.tx
+                .send(Response::Error(format!(
+                    "{} has not been scanned yet, so there is nothing to export",
+                    saved.name
+                )))
+                .expect("Could not send message");
+            return;
+        };
+        self.export(playlist, paths).await;
+    }
+
+    async fn export_queue(&mut self, paths: ExportPaths) {
+        if self.queue.is_empty() {
+            self.tx
+                .send(Response::Warning("The queue is empty".to_string()))
+                .expect("Could not send message");
+            return;
+        }
+        let name = self
+            .playlist
+            .lock()
+            .expect("Could not lock playlist")
+            .name
+            .clone();
+        let playlist = Playlist {
+            name,
+            tracks: self.queue.clone(),
+        };
+        self.export(playlist, paths).await;
+    }
+
+    /// Ask where to write `playlist`, as M3U8 unless an `.xspf` name is picked, and write it.
+    async fn export(&self, playlist: Playlist, paths: ExportPaths) {
+        let dialog = rfd::AsyncFileDialog::new()
+            .add_filter("M3U playlist", &["m3u8", "m3u"])
+            .add_filter("XSPF playlist", &["xspf"])
+            .set_file_name(format!("{}.m3u8", playlist.name.replace(['/', '\\'], "_")));
+        let Some(file) = dialog.save_file().await else {
+            return;
+        };
+        match export::write(&playlist, file.path(), paths) {
+            Ok(()) => self
+                .tx
+                .send(Response::Info(format!(
+                    "Exported {} tracks to {}",
+                    playlist.tracks.len(),
+                    file.file_name()
+                )))
+                .expect("Could not send message"),
+            Err(e) => self
+                .tx
+                .send(Response::Error(format!("Could not export playlist: {e}")))
+                .expect("Could not send message"),
+        }
+    }
+
+    /// Append the current track to the Listen Later playlist, creating it on first use.
+    async fn add_to_listen_later(&mut self) {
+        let track = self.queue[self.current_index].clone();
+        let playlist = match self.edit_listen_later(Op::Add(track.uri.clone())).await {
+            Ok(playlist) => playlist,
+            Err(e) => {
+                self.tx
+                    .send(Response::Error(e.to_string()))
+                    .expect("Could not send message");
+                return;
+            }
+        };
+
+        let saved = SavedPlaylist::listen_later();
+        if !self.saved_playlists.playlists.contains(&saved) {
+            self.saved_playlists.playlists.push(saved);
+            let _ = SavedPlaylists::save_playlists(&self.saved_playlists);
+            self.tx
+                .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+                .expect("Could not send message");
+        }
+        self.tx
+            .send(Response::ListenLater(playlist.tracks.len()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Info(format!(
+                "Added {} to {}",
+                track.title, LISTEN_LATER_NAME
+            )))
+            .expect("Could not send message");
+    }
+
+    async fn remove_from_listen_later(&mut self) {
+        let track = self.queue[self.current_index].clone();
+        match self.edit_listen_later(Op::Remove(track.uri.clone())).await {
+            Ok(playlist) => {
+                self.tx
+                    .send(Response::ListenLater(playlist.tracks.len()))
+                    .expect("Could not send message");
+                self.tx
+                    .send(Response::Info(format!(
+                        "Removed {} from {}",
+                        track.title, LISTEN_LATER_NAME
+                    )))
+                    .expect("Could not send message");
+            }
+            Err(e) => {
+                self.tx
+                    .send(Response::Error(e.to_string()))
+                    .expect("Could not send message");
+            }
+        }
+    }
+
+    async fn status(&self) -> Status {
+        let state = match self.backend.get_state().await {
+            Ok(State::Playing) => "playing",
+            Ok(State::Paused) => "paused",
+            _ => "stopped",
+        };
+        let mut status = Status {
+            state: state.to_string(),
+            playlist: self
+                .playlist
+                .lock()
+                .expect("Could not lock playlist")
+                .name
+                .clone(),
+            position: self.position,
+            volume: self.volume,
+            shuffle: self.shuffle,
+            repeat: self.repeat,
+            index: self.current_index,
+            queue_length: self.queue.len(),
+            ..Status::default()
+        };
+        if let Some(track) = self
+            .queue
+            .get(self.current_index)
+            .filter(|_| self.state.is_loaded())
+        {
+            status.title = track.title.clone();
+            status.artists = track.artists.clone();
+            status.album = track.album.clone();
+            status.duration = track.duration;
+        }
+        status
+    }
+
+    /// Push the status to followers if it changed since the last push.
+    async fn publish_status(&mut self) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let Ok(status) = serde_json::to_string(&self.status().await) else {
+            return;
+        };
+        if self.last_status.as_ref() == Some(&status) {
+            return;
+        }
+        // Followers that can't keep up skip a line rather than holding up the player.
+        self.subscribers
+            .retain(|subscriber| match subscriber.try_send(status.clone()) {
+                Ok(()) => true,
+                Err(e) => e.is_full(),
+            });
+        self.last_status = Some(status);
+    }
+
+    fn queue_entries(&self) -> Vec<QueueEntry> {
+        self.queue
+            .iter()
+            .enumerate()
+            .map(|(index, track)| QueueEntry {
+                title: track.title.clone(),
+                artists: track.artists.clone(),
+                album: track.album.clone(),
+                duration: track.duration,
+                uri: track.uri.clone(),
+                current: self.state.is_loaded() && index == self.current_index,
+            })
+            .collect()
+    }
+
+    fn mark_session_dirty(&mut self) {
+        if self.session_dirty_since.is_none() {
+            self.session_dirty_since = Some(Instant::now());
+        }
+    }
+
+    fn save_session(&mut self) {
+        self.session_dirty_since = None;
+        let session = self.state.is_loaded().then(|| Session {
+            playlist: self
+                .playlist
+                .lock()
+                .expect("Could not lock playlist")
+                .clone(),
+            queue: self.queue.clone(),
+            current_index: self.current_index,
+            position: self.position,
+            volume: self.volume,
+            shuffle: self.shuffle,
+            repeat: self.repeat,
+        });
+        let saved = match &session {
+            Some(session) => session.save(),
+            // Nothing is loaded, as after clearing the queue, so nothing should come back.
+            None => Session::clear(),
+        };
+        if let Err(e) = saved {
+            self.tx
+                .send(Response::Warning(format!("Could not save session: {e}")))
+                .expect("Could not send message");
+        }
+
+        if let Err(e) = self.track_positions.save() {
+            self.tx
+                .send(Response::Warning(format!(
+                    "Could not save track positions: {e}"
+                )))
+                .expect("Could not send message");
+        }
+        if let Err(e) = self.plays.save() {
+            self.tx
+                .send(Response::Warning(format!(
+                    "Could not save play counts: {e}"
+                )))
+                .expect("Could not send message");
+        }
+        if let Err(e) = self.skips.save() {
+            self.tx
+                .send(Response::Warning(format!("Could not save skips: {e}")))
+                .expect("Could not send message");
+        }
+        self.save_insights();
+        let Some(session) = session else {
+            return;
+        };
+        if self.recent.record(&session) {
+            if let Err(e) = self.recent.save() {
+                self.tx
+                    .send(Response::Warning(format!(
+                        "Could not save recent sessions: {e}"
+                    )))
+                    .expect("Could not send message");
+            }
+            self.send_resume_cards();
+        }
+    }
+
+    /// Reload the last session's queue and settings, leaving playback paused at the saved
+    /// position.
+    async fn restore_session(&mut self) {
+        self.send_resume_cards();
+        self.send_recent_folders();
+        let Some(session) = Session::load() else {
+            return;
+        };
+        if session.queue.is_empty() {
+            return;
+        }
+        self.volume = session.volume.clamp(0.0, self.max_volume);
+        self.shuffle = session.shuffle;
+        self.repeat = session.repeat;
+        self.load_session(session, false).await;
+        self.cap_start_volume().await;
+
+        self.tx
+            .send(Response::Volume(self.volume))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Shuffle(self.shuffle))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Repeat(self.repeat))
+            .expect("Could not send message");
+    }
+
+    /// Pick up one of the recent sessions where it was left and start playing.
+    async fn resume_recent(&mut self, index: usize) {
+        let Some(session) = self.recent.sessions.get(index).cloned() else {
+            return;
+        };
+        if session.queue.is_empty() {
+            return;
+        }
+        self.load_session(session, true).await;
+        self.mark_session_dirty();
+    }
+
+    async fn load_session(&mut self, session: Session, play: bool) {
+        let backend = self.backend.clone();
+        self.cancel_scan();
+        let saved = self
+            .saved_playlists
+            .playlists
+            .iter()
+            .find(|saved| saved.name == session.playlist.name)
+            .cloned();
+        self.shuffle_mode = saved
+            .as_ref()
+            .map_or(ShuffleMode::default(), |saved| saved.shuffle_mode);
+        self.tx
+            .send(Response::ShuffleMode(self.shuffle_mode))
+            .expect("Could not send message");
+        self.watch_folder(saved);
+
+        self.playlist = Arc::new(Mutex::new(session.playlist.clone()));
+        self.queue = session.queue;
+        self.current_index = session.current_index.min(self.queue.len() - 1);
+        self.begin_loading().await;
+
+        if let Err(e) = self.load(&backend, self.current_index).await {
+            self.tx
+                .send(Response::Error(e.to_string()))
+                .expect("Could not send message");
+            self.transition(PlaybackState::Stopped);
+            return;
+        }
+        // Preroll so the seek lands. A source too slow for that seeks once it gets there.
+        let _ = backend.pause().await;
+        let mut prerolled = false;
+        for _ in 0..40 {
+            if matches!(backend.get_state().await, Ok(State::Paused)) {
+                prerolled = true;
+                break;
+            }
+            smol::Timer::after(Duration::from_millis(50)).await;
+        }
+        self.pending_resume = None;
+        self.position = session.position;
+        if session.position > 0 {
+            if !prerolled {
+                self.pending_resume = Some(session.position);
+            } else if let Err(e) = backend.seek(session.position).await {
+                self.position = 0;
+                self.tx
+                    .send(Response::Warning(format!("Could not resume playback: {e}")))
+                    .expect("Could not send message");
+            }
+        }
+        if !self.exclusive {
+            let _ = backend.set_volume(self.volume).await;
+        }
+
+        let state = if play {
+            match backend.play().await {
+                Ok(()) => PlaybackState::Playing,
+                Err(e) => {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                    PlaybackState::Paused
+                }
+            }
+        } else {
+            PlaybackState::Paused
+        };
+
+        self.tx
+            .send(Response::PlaylistName(session.playlist.name))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Tracks(self.queue.clone()))
+            .expect("Could not send message");
+        self.tx
+            .send(Response::Metadata(self.queue[self.current_index].clone()))
+            .expect("Could not send message");
+        self.transition(state);
+        self.tx
+            .send(Response::Position(self.position))
+            .expect("Could not send message");
+    }
+
+    /// Drop the output volume to the loud-start cap, to be ramped back up once playback
+    /// starts.
+    async fn cap_start_volume(&mut self) {
+        let Some(cap) = self.loud_start_cap else {
+            return;
+        };
+        if self.exclusive || self.volume <= cap {
+            return;
+        }
+        let _ = self.backend.set_volume(cap).await;
+        self.volume_ramp = Some(VolumeRamp {
+            from: cap,
+            to: self.volume,
+            started: None,
+        });
+    }
+
+    async fn step_volume_ramp(&mut self) {
+        let Some(mut ramp) = self.volume_ramp else {
+            return;
+        };
+        if !self.state.is_playing() {
+            return;
+        }
+        let started = *ramp.started.get_or_insert_with(Instant::now);
+        let progress = (started.elapsed().as_secs_f64() / LOUD_START_RAMP.as_secs_f64()).min(1.0);
+        let _ = self
+            .backend
+            .set_volume(ramp.from + (ramp.to - ramp.from) * progress)
+            .await;
+        self.volume_ramp = (progress < 1.0).then_some(ramp);
+    }
+
+    fn send_resume_cards(&self) {
+        self.tx
+            .send(Response::ResumeCards(self.recent.cards()))
+            .expect("Could not send message");
+    }
+
+    pub async fn run(&mut self) {
+        let mut commands = self.rx.clone().fuse();
+        let mut events = self.backend.clone().events().fuse();
+        let mut scan_updates = self.scan_rx.clone().fuse();
+        let mut folder_changes = self.folder_rx.clone().fuse();
+        let mut other_audio = self.other_audio_rx.clone().fuse();
+        let mut system_events = self.system_events_rx.clone().fuse();
+        let mut found_lyrics = self.found_lyrics_rx.clone().fuse();
+        let mut ticks = smol::Timer::interval(TICK_INTERVAL).fuse();
+        loop {
+            select! {
+                command = commands.next() => match command {
+                    Some(command) => {
+                        self.handle_command(command).await;
+                        self.publish_status().await;
+                    }
+                    None => break,
+                },
+                res = events.next() => match res {
+                    Some(res) => {
+                        self.handle_event(res).await;
+                        self.publish_status().await;
+                    }
+                    None => break,
+                },
+                update = scan_updates.next() => {
+                    if let Some(update) = update {
+                        self.handle_scan(update).await;
+                    }
+                }
+                path = folder_changes.next() => {
+                    if let Some(path) = path {
+                        self.folder_changes.insert(path);
+                        self.folder_changed_at = Some(Instant::now());
+                    }
+                }
+                playing = other_audio.next() => {
+                    if let Some(playing) = playing {
+                        self.other_audio_changed(playing).await;
+                        self.publish_status().await;
+                    }
+                }
+                event = system_events.next() => {
+                    if let Some(event) = event {
+                        self.system_event(event).await;
+                        self.publish_status().await;
+                    }
+                }
+                found = found_lyrics.next() => {
+                    if let Some(found) = found {
+                        self.found_lyrics(found);
+                    }
+                }
+                _ = ticks.next() => self.tick().await,
+            }
+            self.send_queue_state();
+        }
+    }
+
+    /// Tell the UI where playback is in the queue, if that moved since it was last told.
+    fn send_queue_state(&mut self) {
+        let state = QueueState {
+            current: self.state.is_loaded().then_some(self.current_index),
+            length: self.queue.len(),
+        };
+        if self.last_queue_state == Some(state) {
+            return;
+        }
+        self.last_queue_state = Some(state);
+        self.tx
+            .send(Response::QueueState(state))
+            .expect("Could not send message");
+    }
+
+    /// Pause when another application starts playing, and pick up again once it stops if
+    /// playback was paused for it and resuming is turned on.
+    async fn other_audio_changed(&mut self, playing: bool) {
+        if self.other_audio.is_none() {
+            return;
+        }
+        if playing && self.state.is_playing() {
+            self.pause().await;
+            self.paused_for_other_audio = true;
+        } else if !playing && self.paused_for_other_audio {
+            self.paused_for_other_audio = false;
+            if self.resume_after_other_audio {
+                self.resume().await;
+            }
+        }
+    }
+
+    /// Pause before sound comes out of the speakers instead of the headphones, or carries on
+    /// unheard through a suspend.
+    async fn system_event(&mut self, event: SystemEvent) {
+        if self.system_events.is_none() || !self.state.is_playing() {
+            return;
+        }
+        self.pause().await;
+        let reason = match event {
+            SystemEvent::Unplugged => "Paused: the audio output was disconnected",
+            SystemEvent::Suspending => "Paused: the system is going to sleep",
+        };
+        self.tx
+            .send(Response::Info(reason.to_string()))
+            .expect("Could not send message");
+    }
+
+    /// Carry on playing the loaded track. Unlike [`Command::Play`], this isn't counted as
+    /// something done by hand, so automatic resumes can use it.
+    async fn resume(&mut self) {
+        if self.queue.is_empty() {
+            return;
+        }
+        match self.state {
+            PlaybackState::Paused | PlaybackState::Stopped => {
+                let backend = self.backend.clone();
+                match backend.play().await {
+                    Ok(()) => self.transition(PlaybackState::Playing),
+                    Err(e) => self.skip_failed(self.current_index, e).await,
+                }
+            }
+            PlaybackState::Idle => {
+                println!("Playlist is not loaded.");
+                self.tx
+                    .send(Response::Error("Playlist is not loaded.".to_string()))
+                    .expect("Could not send message");
+            }
+            _ => {}
+        }
+    }
+
+    /// Pause playback, if playing. Like [`Self::resume`], not counted as done by hand.
+    async fn pause(&mut self) {
+        if !self.state.is_playing() {
+            return;
+        }
+        let backend = self.backend.clone();
+        match backend.pause().await {
+            Ok(()) => self.transition(PlaybackState::Paused),
+            Err(e) => self
+                .tx
+                .send(Response::Error(e.to_string()))
+                .expect("Could not send message"),
+        }
+    }
+
+    async fn handle_command(&mut self, command: Command) {
+        if let Some(insights) = &mut self.insights {
+            let name = insights::variant_name(&command);
+            if is_usage(&command, &name) {
+                insights.record_command(name);
+            }
+        }
+        if matches!(command, Command::Play | Command::Pause) {
+            // Pausing or playing by hand overrides an automatic pause.
+            self.paused_for_other_audio = false;
+        }
+        match command {
+            Command::Play => self.resume().await,
+            Command::Pause => self.pause().await,
+            Command::GetMeta => {
+                if self.state.is_loaded() {
+                    let track = self.queue[self.current_index].clone();
+                    self.tx
+                        .send(Response::Metadata(track))
+                        .expect("Could not send message");
+                    self.send_chapters();
+                    let lyrics = self.current_lyrics();
+                    if lyrics.is_none() {
+                        self.find_lyrics();
+                    }
+                    self.send_lyrics(lyrics);
+                }
+            }
+            Command::GetTracks => {
+                if self.state.is_loaded() {
+                    let tracks = self.queue.clone();
+                    self.tx
+                        .send(Response::Tracks(tracks))
+                        .expect("Could not send message");
+                }
+            }
+            Command::Volume(vol) => {
+                let vol = vol.clamp(0.0, self.max_volume);
+                let backend = self.backend.clone();
+                if self.exclusive {
+                    self.tx
+                        .send(Response::Warning(
+                            "Volume is fixed in exclusive mode.".to_string(),
+                        ))
+                        .expect("Could not send message");
+                } else if self.state.is_loaded() {
+                    self.tx
+                        .send(Response::Info(format!("Volume set to {vol}")))
+                        .expect("Could not send message");
+                    self.volume_ramp = None;
+                    if let Err(e) = backend.set_volume(vol).await {
+                        self.tx
+                            .send(Response::Error(format!("Could not set volume: {e}")))
+                            .expect("Could not send message");
+                    }
+                    println!("Volume set to {vol}");
+                    self.volume = vol;
+                    self.mark_session_dirty();
+                }
+            }
+            Command::Next => {
+                if self.state.is_loaded() {
+                    self.record_skip();
+                    if self.autofill && self.current_index + 1 >= self.queue.len() {
+                        self.autofill_queue();
+                    }
+                    // Corrupt tracks left in the queue are passed over.
+                    let next = self
+                        .next_playable(self.current_index)
+                        .unwrap_or((self.current_index + 1).min(self.queue.len() - 1));
+                    self.play_from(next).await;
+                }
+            }
+            Command::Previous => {
+                if self.state.is_loaded() {
+                    self.play_from(self.current_index.saturating_sub(1)).await;
+                }
+            }
+            Command::PlayId(id) => {
+                if self.state.is_loaded() && id < self.queue.len() {
+                    if id != self.current_index {
+                        self.record_skip();
+                    }
+                    self.play_from(id).await;
+                }
+            }
+            Command::LoadFromFolder(saved_playlist) => {
+                let backend = self.backend.clone();
+                let Some(mut playlist) = self.read_saved(&saved_playlist).await else {
+                    self.start_scan(saved_playlist).await;
+                    return;
+                };
+                self.cancel_scan();
+                if !saved_playlist.actual_path.is_empty() {
+                    self.remember_folder(PathBuf::from(&saved_playlist.actual_path));
+                }
+                let seed = saved_playlist.shuffle_seed;
+                self.shuffle_mode = saved_playlist.shuffle_mode;
+                self.watch_folder(Some(saved_playlist));
+                self.tag_edits.apply(&mut playlist.tracks);
+                playlist.apply_artist_rules(&self.artist_rules);
+
+                self.begin_loading().await;
+                self.playlist = Arc::new(Mutex::new(playlist.clone()));
+                self.queue = matching::dedupe_sources(playlist.tracks.clone(), &self.prefer_remote);
+                if self.shuffle {
+                    // The same seed deals the playlist out in the order it was last shuffled to.
+                    self.shuffle_queue(seed.unwrap_or_else(|| rand::rng().random()));
+                }
+                self.tx
+                    .send(Response::ShuffleMode(self.shuffle_mode))
+                    .expect("Could not send message");
+
+                self.current_index = 0;
+                if let Err(e) = self.load(&backend, 0).await {
+                    self.tx
+                        .send(Response::Error(format!(
+                            "Could not load {}: {e}",
+                            self.queue.first().map_or("playlist", |t| t.title.as_str())
+                        )))
+                        .expect("Could not send message");
+                }
+                self.transition(PlaybackState::Stopped);
+                self.tx
+                    .send(Response::PlaylistName(playlist.name))
+                    .expect("Could not send message");
+            }
+            Command::LoadFolder => {
+                if let Some(path) = rfd::AsyncFileDialog::new().pick_folder().await {
+                    self.load_dir(path.path().to_owned()).await;
+                }
+            }
+            Command::OpenRecentFolder(dir) => self.open_recent_folder(dir).await,
+            Command::CancelScan => self.cancel_scan(),
+            Command::OpenLink(link, origin) => self.open_link(link, origin).await,
+            Command::PlayFiles(paths, origin) => self.play_files(paths, origin).await,
+            Command::ShareLink => {
+                let link = self
+                    .queue
+                    .get(self.current_index)
+                    .and_then(|track| track.path())
+                    .map(|path| PlayLink {
+                        path,
+                        position: self.position,
+                    });
+                match link {
+                    Some(link) => self
+                        .tx
+                        .send(Response::ShareLink(link.to_url()))
+                        .expect("Could not send message"),
+                    None => self
+                        .tx
+                        .send(Response::Warning(
+                            "Only local files can be shared as links".to_string(),
+                        ))
+                        .expect("Could not send message"),
+                }
+            }
+            Command::SetPauseOnOtherAudio(enabled) => {
+                self.paused_for_other_audio = false;
+                self.other_audio = None;
+                if enabled {
+                    match OtherAudio::start(self.other_audio_tx.clone()) {
+                        Ok(monitor) => self.other_audio = Some(Arc::new(monitor)),
+                        Err(e) => self
+                            .tx
+                            .send(Response::Warning(format!(
+                                "Could not watch for other audio: {e}"
+                            )))
+                            .expect("Could not send message"),
+                    }
+                }
+            }
+            Command::SetResumeAfterOtherAudio(enabled) => self.resume_after_other_audio = enabled,
+            Command::SetPauseOnDisconnect(enabled) => {
+                self.system_events = None;
+                if enabled {
+                    match SystemEvents::start(self.system_events_tx.clone()) {
+                        Ok(monitor) => self.system_events = Some(Arc::new(monitor)),
+                        // On by default, so platforms without a monitor aren't warned about.
+                        Err(e) => eprintln!("Could not watch for disconnected outputs: {e}"),
+                    }
+                }
+            }
+            Command::LoadFiles(paths, origin) => {
+                let backend = self.backend.clone();
+                if let Err(e) = self.load_files(&backend, paths, origin).await {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            }
+            Command::OpenFiles => {
+                let backend = self.backend.clone();
+                let dialog = rfd::AsyncFileDialog::new().add_filter("Audio", &AUDIO_EXTENSIONS);
+                #[cfg(feature = "video")]
+                let dialog = dialog.add_filter("Music videos", &playback::VIDEO_EXTENSIONS);
+                if let Some(files) = dialog.pick_files().await {
+                    let paths = files.iter().map(|file| file.path().to_owned()).collect();
+                    if let Err(e) = self.load_files(&backend, paths, Origin::OpenedFiles).await {
+                        self.tx
+                            .send(Response::Error(e.to_string()))
+                            .expect("Could not send message");
+                    }
+                }
+            }
+            Command::ImportPlaylist => {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .add_filter("Playlists", &import::PLAYLIST_EXTENSIONS);
+                if let Some(file) = dialog.pick_file().await {
+                    self.import_playlist(file.path().to_owned()).await;
+                }
+            }
+            Command::OpenContainingFolder => {
+                if self.state.is_loaded() {
+                    let dir = self.queue[self.current_index]
+                        .path()
+                        .and_then(|path| path.parent().map(|dir| dir.to_path_buf()));
+                    match dir {
+                        Some(dir) => self.load_dir(dir).await,
+                        None => {
+                            self.tx
+                                .send(Response::Error(
+                                    "Current track is not a local file.".to_string(),
+                                ))
+                                .expect("Could not send message");
+                        }
+                    }
+                }
+            }
+            Command::RevealCurrent => {
+                if self.state.is_loaded() {
+                    let result = match self.queue[self.current_index].path() {
+                        Some(path) => file_manager::reveal(&path),
+                        None => Err(anyhow::anyhow!("Current track is not a local file.")),
+                    };
+                    if let Err(e) = result {
+                        self.tx
+                            .send(Response::Error(e.to_string()))
+                            .expect("Could not send message");
+                    }
+                }
+            }
+            Command::LoadSavedPlaylists => {
+                self.saved_playlists = SavedPlaylists::load();
+                self.tx
+                    .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+                    .expect("Could not send message");
+                if let Some(listen_later) = self.read_listen_later().await {
+                    self.tx
+                        .send(Response::ListenLater(listen_later.tracks.len()))
+                        .expect("Could not send message");
+                }
+            }
+            Command::ListenLater => {
+                if self.state.is_loaded() {
+                    self.add_to_listen_later().await;
+                }
+            }
+            Command::RemoveFromListenLater => {
+                if self.state.is_loaded() {
+                    self.remove_from_listen_later().await;
+                }
+            }
+            Command::RetrieveSavedPlaylists => {
+                self.tx
+                    .send(Response::SavedPlaylists(self.saved_playlists.clone()))
+                    .expect("Could not send message");
+            }
+            Command::DuplicatePlaylist(index) => self.duplicate_playlist(index).await,
+            Command::MergePlaylist {
+                from,
+                into,
+                duplicates,
+            } => self.merge_playlist(from, into, duplicates).await,
+            Command::GetPlaylistTracks(index) => self.send_playlist_tracks(index).await,
+            Command::RenamePlaylist(index, name) => self.rename_playlist(index, name).await,
+            Command::DeletePlaylist(index) => self.delete_playlist(index).await,
+            Command::EditPlaylist(index, edit) => self.edit_playlist(index, edit).await,
+            Command::LabelPlaylistEntry {
+                playlist,
+                entry,
+                title,
+                note,
+            } => {
+                self.label_playlist_entry(playlist, entry, title, note)
+                    .await
+            }
+            Command::ExportPlaylist(index, paths) => self.export_playlist(index, paths).await,
+            Command::ExportQueue(paths) => self.export_queue(paths).await,
+            Command::WriteSavedPlaylists => {
+                if let Err(e) = SavedPlaylists::save_playlists(&self.saved_playlists) {
+                    self.tx
+                        .send(Response::Error(format!("Could not save playlists: {e}")))
+                        .expect("Could not send message");
+                }
+            }
+            Command::Seek(time) => {
+                let backend = self.backend.clone();
+                if self.state.is_playing() {
+                    if let Err(e) = backend.seek(time).await {
+                        self.tx
+                            .send(Response::Error(format!("Could not seek: {e}")))
+                            .expect("Could not send message");
+                    }
+                }
+            }
+            Command::SetBalance(balance) => {
+                if let Err(e) = self.backend.set_balance(balance).await {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            }
+            Command::SetSkipSilence(enabled) => {
+                if let Err(e) = self.backend.set_skip_silence(enabled).await {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            }
+            Command::SetLowMemory(enabled) => {
+                if let Err(e) = self.backend.set_low_memory(enabled).await {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            }
+            Command::SetOutputDevice(name) if self.output_device.as_ref() != Some(&name) => {
+                self.output_device = Some(name.clone());
+                match self.backend.set_output_device(name).await {
+                    Ok(()) => self.cap_start_volume().await,
+                    Err(e) => self
+                        .tx
+                        .send(Response::Error(format!("Could not switch output: {e}")))
+                        .expect("Could not send message"),
+                }
+            }
+            Command::SetOutputDevice(_) => {}
+            Command::GetOutputDevices => {
+                let devices = self.backend.output_devices().await;
+                self.tx
+                    .send(Response::OutputDevices(devices))
+                    .expect("Could not send message");
+            }
+            Command::SetRepeat(repeat) => {
+                self.repeat = repeat;
+                self.mark_session_dirty();
+            }
+            Command::SetAutofill(autofill) => {
+                self.autofill = autofill;
+            }
+            Command::SetMaxVolume(max) => {
+                self.max_volume = max.clamp(0.0, MAX_BOOSTED_VOLUME);
+                if self.volume > self.max_volume {
+                    self.volume = self.max_volume;
+                    self.volume_ramp = None;
+                    if self.state.is_loaded() && !self.exclusive {
+                        let _ = self.backend.set_volume(self.volume).await;
+                    }
+                    self.mark_session_dirty();
+                    self.tx
+                        .send(Response::Volume(self.volume))
+                        .expect("Could not send message");
+                }
+            }
+            Command::SaveSession(done) => {
+                self.save_session();
+                let _ = done.try_send(());
+            }
+            Command::RestoreSession => self.restore_session().await,
+            Command::StartVolume(vol) => {
+                self.volume = vol.clamp(0.0, self.max_volume);
+                self.volume_ramp = None;
+                if self.state.is_loaded() && !self.exclusive {
+                    let _ = self.backend.set_volume(self.volume).await;
+                }
+                self.cap_start_volume().await;
+                self.tx
+                    .send(Response::Volume(self.volume))
+                    .expect("Could not send message");
+            }
+            Command::ResumeRecent(index) => self.resume_recent(index).await,
+            Command::SetResumeThreshold(threshold) => {
+                self.resume_threshold = threshold;
+            }
+            Command::SetVerifyOnScan(verify) => {
+                self.scan.verify = verify;
+            }
+            Command::SetScanDepth(depth) => {
+                self.scan.max_depth = depth;
+            }
+            Command::SetSkipHidden(skip) => {
+                self.scan.skip_hidden = skip;
+            }
+            Command::SetPreamp(gain_db) => {
+                if let Err(e) = self.backend.set_preamp(gain_db).await {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            }
+            Command::SetPreferRemote(prefer_remote) => {
+                self.prefer_remote = prefer_remote;
+            }
+            Command::ToggleSource => {
+                if self.state.is_loaded() {
+                    self.toggle_source().await;
+                }
+            }
+            Command::SetSharedDir(dir) => {
+                self.shared_dir = dir;
+            }
+            Command::Query(query, reply) => {
+                let answer = match query {
+                    Query::Status => serde_json::to_string(&self.status().await),
+                    Query::Queue => serde_json::to_string(&self.queue_entries()),
+                };
+                match answer {
+                    Ok(answer) => {
+                        let _ = reply.try_send(answer);
+                    }
+                    Err(e) => eprintln!("Could not serialize query answer: {e}"),
+                }
+            }
+            Command::EditChapters(edit) => {
+                if self.state.is_loaded() {
+                    self.edit_chapters(edit).await;
+                }
+            }
+            Command::NextChapter => {
+                if self.state.is_loaded() {
+                    self.jump_chapter(true).await;
+                }
+            }
+            Command::PreviousChapter => {
+                if self.state.is_loaded() {
+                    self.jump_chapter(false).await;
+                }
+            }
+            Command::ExportCue => {
+                if self.state.is_loaded() {
+                    let track = &self.queue[self.current_index];
+                    let response = match chapters::export_cue(track, &self.chapters.get(&track.uri))
+                    {
+                        Ok(path) => Response::Info(format!("Saved chapters to {}", path.display())),
+                        Err(e) => Response::Error(format!("Could not export chapters: {e}")),
+                    };
+                    self.tx.send(response).expect("Could not send message");
+                }
+            }
+            Command::EditLyrics(edit) => {
+                if self.state.is_loaded() {
+                    self.edit_lyrics(edit);
+                }
+            }
+            Command::SetOnlineLyrics(enabled) => self.online_lyrics = enabled,
+            Command::SetLyricsProviders(providers) => self.lyrics_finder.set_providers(providers),
+            Command::SearchLyrics(query) => {
+                if let Some(track) = self.queue.get(self.current_index) {
+                    self.lyrics_finder.search(track.uri.clone(), query);
+                }
+            }
+            Command::GetLibrary => {
+                self.refresh_library().await;
+                self.send_library();
+            }
+            Command::GetLibraryRoots => self.send_roots(),
+            Command::GetLoudness => {
+                self.tx
+                    .send(Response::Loudness(self.loudness.all()))
+                    .expect("Could not send message");
+            }
+            Command::GetPlayCounts => {
+                self.tx
+                    .send(Response::PlayCounts(self.plays.tracks.clone()))
+                    .expect("Could not send message");
+            }
+            Command::RemoveMissing => self.remove_missing().await,
+            Command::AddLibraryRoot => {
+                if let Some(dir) = rfd::AsyncFileDialog::new().pick_folder().await {
+                    match self.roots.add(dir.path()) {
+                        Ok(root) => self.scan_root(&root),
+                        Err(e) => self
+                            .tx
+                            .send(Response::Error(format!(
+                                "Could not add library folder: {e}"
+                            )))
+                            .expect("Could not send message"),
+                    }
+                }
+            }
+            Command::RescanLibraryRoot(index) => {
+                if let Some(root) = self.roots.roots.get(index).cloned() {
+                    self.scan_root(&root);
+                }
+            }
+            Command::RemoveLibraryRoot(index) => match self.roots.remove(index) {
+                Ok(root) => {
+                    self.cancel_root_scan(&root.cached_name);
+                    if let Err(e) = Playlist::remove_cached(&root.cached_name) {
+                        eprintln!("Could not remove cache of {}: {}", root.path, e);
+                    }
+                    self.send_roots();
+                    self.library_changed().await;
+                    self.send_library();
+                }
+                Err(e) => self
+                    .tx
+                    .send(Response::Error(format!(
+                        "Could not remove library folder: {e}"
+                    )))
+                    .expect("Could not send message"),
+            },
+            Command::FilterLibrary(filter) => {
+                self.library_filter = filter;
+                if self.library.is_empty() {
+                    self.refresh_library().await;
+                }
+                self.send_library();
+            }
+            Command::PlayAlbum { album, artist } => self.play_album(album, artist).await,
+            Command::QueueAlbum { album, artist } => self.queue_album(album, artist).await,
+            Command::QueueArtist(artist) => self.queue_artist(artist).await,
+            Command::GetSmartPlaylists => self.send_smart_playlists().await,
+            Command::SaveSmartPlaylist { index, playlist } => {
+                self.save_smart_playlist(index, playlist).await
+            }
+            Command::DeleteSmartPlaylist(index) => self.delete_smart_playlist(index).await,
+            Command::PlaySmartPlaylist(index) => self.play_smart_playlist(index).await,
+            Command::GetMostSkipped => self.send_most_skipped().await,
+            Command::ResetSkips => {
+                self.skips.clear();
+                if let Err(e) = self.skips.save() {
+                    self.tx
+                        .send(Response::Error(format!("Could not save skips: {e}")))
+                        .expect("Could not send message");
+                }
+                self.send_most_skipped().await;
+            }
+            Command::SetInsights(enabled) => self.set_insights(enabled),
+            Command::SetStayAwake(mode) => {
+                if let Err(e) = self.stay_awake.set_mode(mode) {
+                    self.tx
+                        .send(Response::Warning(format!("Could not keep awake: {e}")))
+                        .expect("Could not send message");
+                }
+            }
+            Command::RecordView(view) => {
+                if let Some(insights) = &mut self.insights {
+                    insights.record_view(&view);
+                }
+            }
+            Command::RecordSettings(settings) => {
+                if let Some(insights) = &mut self.insights {
+                    insights.record_settings(&settings);
+                }
+            }
+            Command::GetInsights => self.send_insights(),
+            Command::ClearInsights => {
+                if let Some(insights) = &mut self.insights {
+                    insights.clear();
+                }
+                self.save_insights();
+                self.send_insights();
+            }
+            Command::SetScrobbleAccounts(accounts) => self.scrobbler.set_accounts(accounts),
+            Command::ConnectLastFm => self.scrobbler.connect_lastfm(),
+            Command::FinishLastFm => self.scrobbler.finish_lastfm(),
+            Command::CheckListenBrainz(token) => self.scrobbler.check_listenbrainz(token),
+            Command::Rate(stars) => {
+                if self.state.is_loaded() {
+                    self.rate(stars).await;
+                }
+            }
+            Command::Search(query) => {
+                if self.library.is_empty() {
+                    self.refresh_library().await;
+                }
+                self.tx
+                    .send(Response::SearchResults(
+                        self.library
+                            .filtered(&self.library_filter)
+                            .search(&query, SEARCH_RESULTS),
+                    ))
+                    .expect("Could not send message");
+            }
+            Command::PlaySearch(query) => self.play_search(query).await,
+            Command::SaveQueue(name) => self.save_queue(name).await,
+            Command::Enqueue(uris) => self.enqueue(uris, Origin::Unknown).await,
+            Command::PlayNext(uris) => self.insert_next(uris).await,
+            Command::EnqueuePaths {
+                paths,
+                origin,
+                play,
+            } => self.enqueue_paths(paths, origin, play),
+            Command::MoveTrack { from, to } => self.move_track(from, to),
+            Command::ReorderQueue(uris) => self.reorder_queue(uris),
+            Command::RemoveTrack(index) => self.remove_track(index).await,
+            Command::RemoveTracks(indices) => self.remove_tracks(indices).await,
+            Command::AddToPlaylist(index, uris) => self.add_to_playlist(index, uris).await,
+            Command::EditTags(uris, edit) => self.edit_tags(uris, edit).await,
+            Command::ClearQueue => self.clear_queue().await,
+            Command::Subscribe(subscriber) => {
+                if let Ok(status) = serde_json::to_string(&self.status().await) {
+                    if subscriber.try_send(status).is_ok() {
+                        self.subscribers.push(subscriber);
+                    }
+                }
+            }
+            Command::SetLoudStartCap(cap) => {
+                self.loud_start_cap = cap;
+            }
+            Command::SetSpectrum(enabled) => {
+                if let Err(e) = self.backend.set_spectrum(enabled).await {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            }
+            Command::SetExclusive(exclusive) => match self.backend.set_exclusive(exclusive).await {
+                Ok(()) => {
+                    self.exclusive = exclusive;
+                    if !exclusive {
+                        let _ = self.backend.set_volume(self.volume).await;
+                        self.cap_start_volume().await;
+                    }
+                    self.tx
+                        .send(Response::Exclusive(exclusive))
+                        .expect("Could not send message");
+                }
+                Err(e) => {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            },
+            Command::SetArtistRules(rules) => {
+                self.artist_rules = rules;
+                self.library_changed().await;
+            }
+            Command::SetCollation(collation) => {
+                text::set_collation(collation);
+                self.send_library();
+            }
+            Command::SetMono(mono) => {
+                if let Err(e) = self.backend.set_mono(mono).await {
+                    self.tx
+                        .send(Response::Error(e.to_string()))
+                        .expect("Could not send message");
+                }
+            }
+            Command::Shuffle => {
+                if !self.shuffle {
+                    let seed = rand::rng().random();
+                    self.shuffle_queue(seed);
+                    self.shuffle = true;
+                    self.remember_shuffle(Some(seed));
+                } else {
+                    self.unshuffle_queue();
+                    self.shuffle = false;
+                }
+                self.tx
+                    .send(Response::Tracks(self.queue.clone()))
+                    .expect("Could not send message");
+                self.tx
+                    .send(Response::Shuffle(self.shuffle.clone()))
+                    .expect("Could not send message");
+            }
+            Command::SetShuffleMode(mode) => {
+                self.shuffle_mode = mode;
+                if self.shuffle {
+                    let seed = rand::rng().random();
+                    self.unshuffle_queue();
+                    self.shuffle_queue(seed);
+                    self.remember_shuffle(Some(seed));
+                    self.tx
+                        .send(Response::Tracks(self.queue.clone()))
+                        .expect("Could not send message");
+                } else {
+                    self.remember_shuffle(None);
+                }
+                self.tx
+                    .send(Response::ShuffleMode(mode))
+                    .expect("Could not send message");
+            }
+        }
+    }
+
+    /// Follow the pipeline when it settles somewhere other than where the player put it, as
+    /// when an output going away pauses it or it stops on its own, so the UI shows what's
+    /// really happening.
+    async fn pipeline_settled(&mut self, settled: State) {
+        // Transitions and a stalled stream set the pipeline's state themselves.
+        if self.state.is_transitioning() || self.stalled {
+            return;
+        }
+        // Changes asked for since this message was posted make it stale.
+        if !self
+            .backend
+            .get_state()
+            .await
+            .is_ok_and(|state| state == settled)
+        {
+            return;
+        }
+        let state = match (settled, self.state) {
+            (State::Playing, PlaybackState::Paused | PlaybackState::Stopped) => {
+                PlaybackState::Playing
+            }
+            (State::Paused, PlaybackState::Playing) => PlaybackState::Paused,
+            (State::Null | State::Ready, PlaybackState::Playing | PlaybackState::Paused) => {
+                PlaybackState::Stopped
+            }
+            _ => return,
+        };
+        self.transition(state);
+    }
+
+    async fn handle_event(&mut self, res: Response) {
+        self.handle_stream_health(&res).await;
+        let local = self
+            .queue
+            .get(self.current_index)
+            .is_some_and(|track| !track.is_remote());
+        match &res {
+            Response::PipelineState(settled) => {
+                self.pipeline_settled(*settled).await;
+                return;
+            }
+            // A file that can't be decoded only errors once the pipeline starts on it.
+            Response::Undecodable(e) if self.state.is_playing() && local => {
+                self.mark_corrupt(self.current_index);
+                self.skip_failed(self.current_index, anyhow::anyhow!("{e}"))
+                    .await;
+                return;
+            }
+            Response::Undecodable(e) => {
+                self.tx
+                    .send(Response::Error(e.clone()))
+                    .expect("Could not send message");
+                return;
+            }
+            // Anything else, like the output device going away, would fail the next track the
+            // same way, so stop there instead of going through the queue.
+            Response::Error(_) if self.state.is_playing() && local => {
+                self.tx.send(res.clone()).expect("Could not send message");
+                self.started_at = None;
+                self.stop_playback().await;
+                return;
+            }
+            Response::Position(position) => {
+                if *position > 0 {
+                    self.started_at = None;
+                }
+                self.position = *position;
+                self.record_track_position();
+                let heard = self.listening.as_mut();
+                if let Some(listen) = heard.and_then(|listening| listening.advance(*position)) {
+                    self.scrobbler.scrobble(listen);
+                }
+            }
+            Response::StreamStart => {
+                self.embedded_lyrics = None;
+                let listen = self
+                    .queue
+                    .get(self.current_index)
+                    .and_then(Listen::starting);
+                if let Some(listen) = &listen {
+                    self.scrobbler.now_playing(listen.clone());
+                }
+                self.listening = listen.map(Listening::new);
+            }
+            // Tags come again as the stream goes on, so only their first lyrics are used.
+            Response::EmbeddedLyrics(text) => {
+                if self.embedded_lyrics.is_none() {
+                    self.embedded_lyrics = Some(Lyrics::parse(text));
+                    self.send_lyrics(self.current_lyrics());
+                }
+                return;
+            }
+            Response::Eos if self.current_index < self.queue.len() => {
+                let uri = self.queue[self.current_index].uri.clone();
+                if self.is_long_track(self.current_index) {
+                    self.track_positions.forget(&uri);
+                }
+                // Only tracks heard to the end count as played.
+                self.plays.record(&uri);
+                self.mark_session_dirty();
+                self.tx
+                    .send(Response::PlayCounts(self.plays.tracks.clone()))
+                    .expect("Could not send message");
+            }
+            _ => {}
+        }
+        self.tx.send(res).expect("Could not send message");
+    }
+
+    /// Give up on the current track if it was started but hasn't moved since, as when its
+    /// file sits on a mount that stopped answering, and go on to the next.
+    async fn check_started(&mut self) {
+        if self.stalled
+            || !self
+                .started_at
+                .is_some_and(|at| at.elapsed() >= LOAD_TIMEOUT)
+        {
+            return;
+        }
+        self.started_at = None;
+        let error = anyhow::anyhow!(
+            "Playback didn't start within {} seconds",
+            LOAD_TIMEOUT.as_secs()
+        );
+        self.skip_failed(self.current_index, error).await;
+    }
+
+    /// Timed work: reconnects, resuming a freshly loaded track and debounced session saves.
+    async fn tick(&mut self) {
+        if self.reconnect_at.is_some_and(|at| Instant::now() >= at) {
+            self.reconnect().await;
+        }
+        self.apply_pending_resume().await;
+        self.step_volume_ramp().await;
+        self.check_started().await;
+        // Changes wait for a running scan, which may be about to deliver the same files, and
+        // for the files of the last batch of changes to be read.
+        if self.scan_job.is_none()
+            && !self
+                .file_reads
+                .iter()
+                .any(|read| matches!(read.then, AfterRead::FolderChanges { .. }))
+            && self
+                .folder_changed_at
+                .is_some_and(|at| at.elapsed() >= FOLDER_SETTLE_DELAY)
+        {
+            self.apply_folder_changes().await;
+        }
+        if self
+            .session_dirty_since
+            .is_some_and(|since| since.elapsed() >= SESSION_SAVE_DELAY)
+        {
+            self.save_session();
+        }
+    }
+}
+
+impl Controller {
+    /// Create a controller along with the player-side ends of its channels.
+    pub fn new() -> (Controller, Sender<Response>, Receiver<Command>) {
+        let (cmd_tx, cmd_rx) = ring_channel::ring_channel(NonZeroUsize::new(128).unwrap());
+        let (res_tx, res_rx) = ring_channel::ring_channel(NonZeroUsize::new(128).unwrap());
+        (
+            Controller {
+                tx: cmd_tx,
+                rx: res_rx,
+                ready: Arc::new(AtomicBool::new(false)),
+                pending: Arc::new(Mutex::new(Vec::new())),
+            },
+            res_tx,
+            cmd_rx,
+        )
+    }
+
+    /// Send a command to the player, holding it back until the backend is ready.
+    fn send(&self, command: Command) {
+        let mut pending = self
+            .pending
+            .lock()
+            .expect("Could not lock pending commands");
+        if self.ready.load(Ordering::Acquire) {
+            self.tx.send(command).expect("Could not send command");
+        } else {
+            pending.push(command);
+        }
+    }
+
+    /// Mark the backend as ready and flush the commands queued during startup.
+    pub fn mark_ready(&self) {
+        let mut pending = self
+            .pending
+            .lock()
+            .expect("Could not lock pending commands");
+        self.ready.store(true, Ordering::Release);
+        for command in pending.drain(..) {
+            self.tx.send(command).expect("Could not send command");
+        }
+    }
+
+    /// Pass the settings that live in the player on to it, as done once at startup.
+    pub fn apply_settings(&self, settings: &Settings) {
+        self.set_max_volume(settings.max_volume());
+        self.set_mono(settings.mono);
+        self.set_balance(settings.balance);
+        self.set_skip_silence(settings.skip_silence);
+        if settings.exclusive_mode {
+            self.set_exclusive(true);
+        }
+        if settings.low_memory {
+            self.set_low_memory(true);
+        }
+        self.set_artist_rules(settings.artist_rules.clone());
+        self.set_collation(settings.collation.clone());
+        self.set_resume_threshold(settings.resume_threshold_minutes * 60);
+        self.set_verify_on_scan(settings.verify_on_scan);
+        self.set_scan_depth(settings.scan_depth);
+        self.set_skip_hidden(settings.skip_hidden_folders);
+        self.set_preamp(settings.preamp_db);
+        self.set_prefer_remote(settings.prefer_remote.iter().cloned().collect());
+        self.set_shared_dir(settings.shared_dir.clone());
+        self.set_resume_after_other_audio(settings.resume_after_other_audio);
+        self.set_autofill(settings.autofill);
+        self.set_insights(settings.insights);
+        self.set_stay_awake(settings.stay_awake);
+        self.set_output_device(settings.output_device.clone());
+        self.set_scrobble_accounts(settings.scrobbling.clone());
+        self.set_online_lyrics(settings.online_lyrics);
+        self.set_lyrics_providers(settings.lyrics_providers.clone());
+        if settings.pause_on_other_audio {
+            self.set_pause_on_other_audio(true);
+        }
+        self.set_pause_on_disconnect(settings.pause_on_disconnect);
+        self.set_loud_start_cap(
+            settings
+                .loud_start_protection
+                .then_some(settings.loud_start_max_volume),
+        );
+    }
+
+    pub fn load(&self, saved_playlist: SavedPlaylist) {
+        self.send(Command::LoadFromFolder(saved_playlist));
+    }
+
+    pub fn cancel_scan(&self) {
+        self.send(Command::CancelScan);
+    }
+
+    pub fn open_folder(&self) {
+        self.send(Command::LoadFolder);
+    }
+
+    pub fn open_recent_folder(&self, dir: PathBuf) {
+        self.send(Command::OpenRecentFolder(dir));
+    }
+
+    pub fn open_files(&self) {
+        self.send(Command::OpenFiles);
+    }
+
+    pub fn load_files(&self, paths: Vec<PathBuf>, origin: Origin) {
+        self.send(Command::LoadFiles(paths, origin));
+    }
+
+    pub fn set_pause_on_other_audio(&self, enabled: bool) {
+        self.send(Command::SetPauseOnOtherAudio(enabled));
+    }
+
+    pub fn set_pause_on_disconnect(&self, enabled: bool) {
+        self.send(Command::SetPauseOnDisconnect(enabled));
+    }
+
+    pub fn set_resume_after_other_audio(&self, enabled: bool) {
+        self.send(Command::SetResumeAfterOtherAudio(enabled));
+    }
+
+    pub fn open_link(&self, link: PlayLink, origin: Origin) {
+        self.send(Command::OpenLink(link, origin));
+    }
+
+    pub fn play_files(&self, paths: Vec<PathBuf>, origin: Origin) {
+        self.send(Command::PlayFiles(paths, origin));
+    }
+
+    pub fn share_link(&self) {
+        self.send(Command::ShareLink);
+    }
+
+    pub fn import_playlist(&self) {
+        self.send(Command::ImportPlaylist);
+    }
+
+    pub fn open_containing_folder(&self) {
+        self.send(Command::OpenContainingFolder);
+    }
+
+    pub fn reveal_current(&self) {
+        self.send(Command::RevealCurrent);
+    }
+
+    pub fn play(&self) {
+        self.send(Command::Play);
+    }
+
+    pub fn play_id(&self, id: usize) {
+        self.send(Command::PlayId(id));
+    }
+
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    pub fn next(&self) {
+        self.send(Command::Next);
+    }
+
+    pub fn prev(&self) {
+        self.send(Command::Previous);
+    }
+
+    pub fn get_meta(&self) {
+        self.send(Command::GetMeta);
+    }
+
+    pub fn get_queue(&self) {
+        self.send(Command::GetTracks);
+    }
+
+    pub fn volume(&self, vol: f64) {
+        self.send(Command::Volume(vol));
+    }
+
+    pub fn load_saved_playlists(&self) {
+        self.send(Command::LoadSavedPlaylists);
+    }
+
+    pub fn write_playlist(&self) {
+        self.send(Command::WriteSavedPlaylists);
+    }
+
+    pub fn retrieve_saved_playlists(&self) {
+        self.send(Command::RetrieveSavedPlaylists);
+    }
+
+    pub fn duplicate_playlist(&self, index: usize) {
+        self.send(Command::DuplicatePlaylist(index));
+    }
+
+    pub fn merge_playlist(&self, from: usize, into: usize, duplicates: DuplicateTracks) {
+        self.send(Command::MergePlaylist {
+            from,
+            into,
+            duplicates,
+        });
+    }
+
+    pub fn get_playlist_tracks(&self, index: usize) {
+        self.send(Command::GetPlaylistTracks(index));
+    }
+
+    pub fn rename_playlist(&self, index: usize, name: String) {
+        self.send(Command::RenamePlaylist(index, name));
+    }
+
+    pub fn delete_playlist(&self, index: usize) {
+        self.send(Command::DeletePlaylist(index));
+    }
+
+    pub fn move_playlist_track(&self, index: usize, from: usize, to: usize) {
+        self.send(Command::EditPlaylist(
+            index,
+            PlaylistEdit::Move { from, to },
+        ));
+    }
+
+    pub fn remove_playlist_track(&self, index: usize, track: usize) {
+        self.send(Command::EditPlaylist(index, PlaylistEdit::Remove(track)));
+    }
+
+    pub fn label_playlist_entry(&self, playlist: usize, entry: usize, title: String, note: String) {
+        self.send(Command::LabelPlaylistEntry {
+            playlist,
+            entry,
+            title,
+            note,
+        });
+    }
+
+    pub fn export_playlist(&self, index: usize, paths: ExportPaths) {
+        self.send(Command::ExportPlaylist(index, paths));
+    }
+
+    pub fn export_queue(&self, paths: ExportPaths) {
+        self.send(Command::ExportQueue(paths));
+    }
+
+    pub fn seek(&self, time: u64) {
+        self.send(Command::Seek(time));
+    }
+
+    pub fn shuffle(&self) {
+        self.send(Command::Shuffle);
+    }
+
+    pub fn set_shuffle_mode(&self, mode: ShuffleMode) {
+        self.send(Command::SetShuffleMode(mode));
+    }
+
+    pub fn set_balance(&self, balance: f32) {
+        self.send(Command::SetBalance(balance));
+    }
+
+    pub fn set_mono(&self, mono: bool) {
+        self.send(Command::SetMono(mono));
+    }
+
+    pub fn set_skip_silence(&self, enabled: bool) {
+        self.send(Command::SetSkipSilence(enabled));
+    }
+
+    pub fn set_low_memory(&self, enabled: bool) {
+        self.send(Command::SetLowMemory(enabled));
+    }
+
+    pub fn set_repeat(&self, repeat: bool) {
+        self.send(Command::SetRepeat(repeat));
+    }
+
+    pub fn set_autofill(&self, autofill: bool) {
+        self.send(Command::SetAutofill(autofill));
+    }
+
+    pub fn set_max_volume(&self, max: f64) {
+        self.send(Command::SetMaxVolume(max));
+    }
+
+    /// Ask for the session to be written, getting a message once it is.
+    pub fn save_session(&self) -> smol::channel::Receiver<()> {
+        let (done, saved) = smol::channel::bounded(1);
+        self.send(Command::SaveSession(done));
+        saved
+    }
+
+    pub fn restore_session(&self) {
+        self.send(Command::RestoreSession);
+    }
+
+    pub fn start_volume(&self, vol: f64) {
+        self.send(Command::StartVolume(vol));
+    }
+
+    pub fn resume_recent(&self, index: usize) {
+        self.send(Command::ResumeRecent(index));
+    }
+
+    pub fn set_resume_threshold(&self, seconds: u64) {
+        self.send(Command::SetResumeThreshold(seconds));
+    }
+
+    pub fn set_verify_on_scan(&self, verify: bool) {
+        self.send(Command::SetVerifyOnScan(verify));
+    }
+
+    pub fn set_scan_depth(&self, depth: usize) {
+        self.send(Command::SetScanDepth(depth));
+    }
+
+    pub fn set_skip_hidden(&self, skip: bool) {
+        self.send(Command::SetSkipHidden(skip));
+    }
+
+    pub fn set_preamp(&self, gain_db: f64) {
+        self.send(Command::SetPreamp(gain_db));
+    }
+
+    pub fn set_spectrum(&self, enabled: bool) {
+        self.send(Command::SetSpectrum(enabled));
+    }
+
+    pub fn set_prefer_remote(&self, prefer_remote: HashSet<String>) {
+        self.send(Command::SetPreferRemote(prefer_remote));
+    }
+
+    pub fn toggle_source(&self) {
+        self.send(Command::ToggleSource);
+    }
+
+    pub fn set_loud_start_cap(&self, cap: Option<f64>) {
+        self.send(Command::SetLoudStartCap(cap));
+    }
+
+    pub fn listen_later(&self) {
+        self.send(Command::ListenLater);
+    }
+
+    pub fn remove_from_listen_later(&self) {
+        self.send(Command::RemoveFromListenLater);
+    }
+
+    pub fn set_shared_dir(&self, dir: Option<PathBuf>) {
+        self.send(Command::SetSharedDir(dir));
+    }
+
+    pub fn edit_chapters(&self, edit: ChapterEdit) {
+        self.send(Command::EditChapters(edit));
+    }
+
+    pub fn next_chapter(&self) {
+        self.send(Command::NextChapter);
+    }
+
+    pub fn previous_chapter(&self) {
+        self.send(Command::PreviousChapter);
+    }
+
+    pub fn export_cue(&self) {
+        self.send(Command::ExportCue);
+    }
+
+    pub fn edit_lyrics(&self, edit: LyricsEdit) {
+        self.send(Command::EditLyrics(edit));
+    }
+
+    pub fn get_library(&self) {
+        self.send(Command::GetLibrary);
+    }
+
+    pub fn filter_library(&self, filter: LibraryFilter) {
+        self.send(Command::FilterLibrary(filter));
+    }
+
+    pub fn play_album(&self, album: String, artist: String) {
+        self.send(Command::PlayAlbum { album, artist });
+    }
+
+    pub fn queue_album(&self, album: String, artist: String) {
+        self.send(Command::QueueAlbum { album, artist });
+    }
+
+    pub fn queue_artist(&self, artist: String) {
+        self.send(Command::QueueArtist(artist));
+    }
+
+    pub fn get_library_roots(&self) {
+        self.send(Command::GetLibraryRoots);
+    }
+
+    pub fn get_loudness(&self) {
+        self.send(Command::GetLoudness);
+    }
+
+    pub fn get_play_counts(&self) {
+        self.send(Command::GetPlayCounts);
+    }
+
+    pub fn add_library_root(&self) {
+        self.send(Command::AddLibraryRoot);
+    }
+
+    pub fn rescan_library_root(&self, index: usize) {
+        self.send(Command::RescanLibraryRoot(index));
+    }
+
+    pub fn remove_library_root(&self, index: usize) {
+        self.send(Command::RemoveLibraryRoot(index));
+    }
+
+    pub fn remove_missing(&self) {
+        self.send(Command::RemoveMissing);
+    }
+
+    pub fn get_smart_playlists(&self) {
+        self.send(Command::GetSmartPlaylists);
+    }
+
+    pub fn save_smart_playlist(&self, index: Option<usize>, playlist: SmartPlaylist) {
+        self.send(Command::SaveSmartPlaylist { index, playlist });
+    }
+
+    pub fn delete_smart_playlist(&self, index: usize) {
+        self.send(Command::DeleteSmartPlaylist(index));
+    }
+
+    pub fn play_smart_playlist(&self, index: usize) {
+        self.send(Command::PlaySmartPlaylist(index));
+    }
+
+    pub fn get_most_skipped(&self) {
+        self.send(Command::GetMostSkipped);
+    }
+
+    pub fn reset_skips(&self) {
+        self.send(Command::ResetSkips);
+    }
+
+    pub fn set_insights(&self, enabled: bool) {
+        self.send(Command::SetInsights(enabled));
+    }
+
+    pub fn set_stay_awake(&self, mode: StayAwake) {
+        self.send(Command::SetStayAwake(mode));
+    }
+
+    pub fn set_output_device(&self, name: Option<String>) {
+        self.send(Command::SetOutputDevice(name));
+    }
+
+    pub fn get_output_devices(&self) {
+        self.send(Command::GetOutputDevices);
+    }
+
+    pub fn record_view(&self, view: impl Into<String>) {
+        self.send(Command::RecordView(view.into()));
+    }
+
+    pub fn record_settings(&self, settings: Vec<String>) {
+        self.send(Command::RecordSettings(settings));
+    }
+
+    pub fn get_insights(&self) {
+        self.send(Command::GetInsights);
+    }
+
+    pub fn clear_insights(&self) {
+        self.send(Command::ClearInsights);
+    }
+
+    pub fn set_scrobble_accounts(&self, accounts: ScrobbleAccounts) {
+        self.send(Command::SetScrobbleAccounts(accounts));
+    }
+
+    pub fn connect_lastfm(&self) {
+        self.send(Command::ConnectLastFm);
+    }
+
+    pub fn set_online_lyrics(&self, enabled: bool) {
+        self.send(Command::SetOnlineLyrics(enabled));
+    }
+
+    pub fn set_lyrics_providers(&self, providers: Vec<String>) {
+        self.send(Command::SetLyricsProviders(providers));
+    }
+
+    /// Look the current track's lyrics up online by `query`, replacing any it has but a lyrics
+    /// file.
+    pub fn search_lyrics(&self, query: LyricsQuery) {
+        self.send(Command::SearchLyrics(query));
+    }
+
+    pub fn finish_lastfm(&self) {
+        self.send(Command::FinishLastFm);
+    }
+
+    pub fn check_listenbrainz(&self, token: String) {
+        self.send(Command::CheckListenBrainz(token));
+    }
+
+    pub fn rate(&self, stars: u32) {
+        self.send(Command::Rate(stars));
+    }
+
+    pub fn search(&self, query: String) {
+        self.send(Command::Search(query));
+    }
+
+    pub fn play_search(&self, query: String) {
+        self.send(Command::PlaySearch(query));
+    }
+
+    pub fn save_queue(&self, name: Option<String>) {
+        self.send(Command::SaveQueue(name));
+    }
+
+    pub fn enqueue(&self, uris: Vec<String>) {
+        self.send(Command::Enqueue(uris));
+    }
+
+    pub fn remove_track(&self, index: usize) {
+        self.send(Command::RemoveTrack(index));
+    }
+
+    pub fn remove_tracks(&self, indices: Vec<usize>) {
+        self.send(Command::RemoveTracks(indices));
+    }
+
+    pub fn add_to_playlist(&self, index: usize, uris: Vec<String>) {
+        self.send(Command::AddToPlaylist(index, uris));
+    }
+
+    pub fn edit_tags(&self, uris: Vec<String>, edit: TagEdit) {
+        self.send(Command::EditTags(uris, edit));
+    }
+
+    pub fn clear_queue(&self) {
+        self.send(Command::ClearQueue);
+    }
+
+    /// Add `paths` to the end of the queue, playing the first of them once it is read if `play`
+    /// is set.
+    pub fn enqueue_paths(&self, paths: Vec<PathBuf>, origin: Origin, play: bool) {
+        self.send(Command::EnqueuePaths {
+            paths,
+            origin,
+            play,
+        });
+    }
+
+    pub fn play_next(&self, uris: Vec<String>) {
+        self.send(Command::PlayNext(uris));
+    }
+
+    pub fn move_track(&self, from: usize, to: usize) {
+        self.send(Command::MoveTrack { from, to });
+    }
+
+    pub fn reorder_queue(&self, uris: Vec<String>) {
+        self.send(Command::ReorderQueue(uris));
+    }
+
+    /// Follow status changes as JSON lines on the returned channel.
+    pub fn subscribe(&self) -> smol::channel::Receiver<String> {
+        let (subscriber, updates) = smol::channel::bounded(16);
+        self.send(Command::Subscribe(subscriber));
+        updates
+    }
+
+    /// Ask the player for `query`; the JSON answer arrives on the returned channel.
+    pub fn query(&self, query: Query) -> smol::channel::Receiver<String> {
+        let (reply, answer) = smol::channel::bounded(1);
+        self.send(Command::Query(query, reply));
+        answer
+    }
+
+    pub fn set_exclusive(&self, exclusive: bool) {
+        self.send(Command::SetExclusive(exclusive));
+    }
+
+    pub fn set_artist_rules(&self, rules: ArtistRules) {
+        self.send(Command::SetArtistRules(rules));
+    }
+
+    pub fn set_collation(&self, collation: Collation) {
+        self.send(Command::SetCollation(collation));
+    }
+}
+
+impl Thumbnail {
+    pub fn to_frame(&self) -> SmallVec<[Frame; 1]> {
+        let img = RgbaImage::from_raw(self.width, self.height, self.img.clone())
+            .expect("Failed to reconstruct image from raw bytes");
+        SmallVec::from_vec(vec![Frame::new(thumbnail(&img, self.width, self.height))])
+    }
+
+    /// Write the image to `path` as a PNG, for things outside the app that want artwork as a
+    /// file.
+    pub fn save_png(&self, path: &Path) -> anyhow::Result<()> {
+        let mut img = RgbaImage::from_raw(self.width, self.height, self.img.clone())
+            .ok_or(anyhow::anyhow!("Artwork doesn't match its size"))?;
+        // Kept as BGRA for the UI.
+        for pixel in img.pixels_mut() {
+            pixel.0.swap(0, 2);
+        }
+        img.save_with_format(path, ImageFormat::Png)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn titles(tracks: &[Track]) -> Vec<&str> {
+        tracks.iter().map(|track| track.title.as_str()).collect()
+    }
+
+    #[test]
+    fn moving_an_entry_keeps_the_current_track_current() {
+        for len in 1..6 {
+            for current in 0..len {
+                for from in 0..len {
+                    for to in 0..len {
+                        let mut queue: Vec<usize> = (0..len).collect();
+                        let entry = queue.remove(from);
+                        queue.insert(to, entry);
+                        let index = index_after_move(current, from, to);
+                        assert_eq!(queue[index], current, "{current} moving {from} to {to}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn removing_entries_keeps_the_current_track_or_the_next_one_current() {
+        for len in 1..7 {
+            // Taking out every entry clears the queue instead.
+            for mask in 0..(1u32 << len) - 1 {
+                let removed: Vec<usize> = (0..len).filter(|i| mask & (1 << i) != 0).collect();
+                let queue: Vec<usize> = (0..len).filter(|i| !removed.contains(i)).collect();
+                for current in 0..len {
+                    let index = index_after_removal(current, &removed);
+                    // The current track if it stays, else the first one left after it.
+                    let expected = queue
+                        .iter()
+                        .position(|&entry| entry >= current)
+                        .unwrap_or(queue.len());
+                    assert_eq!(index, expected, "{current} removing {removed:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn folder_changes_replace_entries_where_they_stand() {
+        let track = |title: &str| testing::track("Artist", "Album", title);
+        let intro = Track {
+            custom_title: "Opening".to_string(),
+            note: "Fade in".to_string(),
+            ..track("Intro")
+        };
+        let renamed_from = Track {
+            note: "Key change".to_string(),
+            ..track("Song")
+        };
+        let listed = vec![intro, track("Kept"), renamed_from, track("Deleted")];
+        let gone_uris = [track("Song").uri, track("Deleted").uri];
+        let gone = |track: &Track| gone_uris.contains(&track.uri);
+
+        let rewritten = Track {
+            duration: 200,
+            ..track("Intro")
+        };
+        let renamed = Track {
+            uri: "file:///music/01%20Song.flac".to_string(),
+            ..track("Song")
+        };
+        // Only one new file can stand in for a renamed one.
+        let copy = Track {
+            uri: "file:///music/Song%20(copy).flac".to_string(),
+            ..track("Song")
+        };
+        let fresh = vec![rewritten, renamed, copy, track("New")];
+        let (replacements, appended) = match_changes(&listed, fresh, &gone);
+        assert_eq!(replacements.len(), 2);
+        assert_eq!(titles(&appended), ["Song", "New"]);
+        assert_eq!(appended[0].uri, "file:///music/Song%20(copy).flac");
+
+        let mut list = listed;
+        apply_changes(&mut list, &replacements, &gone, &appended);
+        assert_eq!(titles(&list), ["Intro", "Kept", "Song", "Song", "New"]);
+        assert_eq!(list[0].duration, 200);
+        assert_eq!(list[0].custom_title, "Opening");
+        assert_eq!(list[0].note, "Fade in");
+        assert_eq!(list[2].uri, "file:///music/01%20Song.flac");
+        assert_eq!(list[2].note, "Key change");
     }
 }
@@ -0,0 +1,62 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+};
+
+use bincode::config;
+use serde::{Deserialize, Serialize};
+
+use crate::{playback::Track, session::Session, uri};
+
+/// Star ratings given in the app, keyed by URI. They take the place of any rating read from the
+/// file's tags.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Ratings {
+    pub tracks: HashMap<String, u32>,
+}
+
+impl Ratings {
+    pub fn get_ratings_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("ratings.bin"))
+    }
+
+    pub fn load() -> Ratings {
+        let Some(file_path) = Self::get_ratings_file() else {
+            return Ratings::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return Ratings::default();
+        };
+        match bincode::serde::decode_from_slice::<Ratings, _>(&data, config::standard()) {
+            Ok((mut ratings, _)) => {
+                ratings.tracks = uri::normalize_keys(ratings.tracks);
+                ratings
+            }
+            Err(e) => {
+                eprintln!("Failed to decode ratings: {}", e);
+                Ratings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_ratings_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    /// Stars for `track`, from 0 for unrated to 5.
+    pub fn get(&self, track: &Track) -> u32 {
+        self.tracks.get(&track.uri).copied().unwrap_or(track.rating)
+    }
+
+    /// Rate `uri` from 1 to 5 stars, or 0 to mark it unrated whatever its tags say.
+    pub fn set(&mut self, uri: &str, stars: u32) {
+        self.tracks.insert(uri.to_string(), stars.min(5));
+    }
+}
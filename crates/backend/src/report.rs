@@ -0,0 +1,172 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use directories::UserDirs;
+
+/// Where "Report a problem" sends people.
+pub const ISSUES_URL: &str = "https://github.com/anantnrg/reyvr/issues/new";
+/// Number of recent log lines kept for problem reports.
+pub const MAX_LOG_LINES: usize = 50;
+/// Browsers and GitHub reject very long URLs, so the issue body is cut down to this.
+const MAX_URL_BODY: usize = 6000;
+
+/// Recent errors and notices, newest last.
+#[derive(Clone, Default)]
+pub struct RecentLog {
+    lines: VecDeque<String>,
+}
+
+impl RecentLog {
+    pub fn push(&mut self, line: String) {
+        if self.lines.len() == MAX_LOG_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.iter().cloned().collect()
+    }
+}
+
+/// Everything a bug report carries. Nothing leaves the machine until the user has looked at
+/// [`ProblemReport::body`] and chosen to open an issue or save a file.
+#[derive(Clone)]
+pub struct ProblemReport {
+    pub app_version: String,
+    pub os: String,
+    pub backend: String,
+    pub diagnostics: Vec<(String, String)>,
+    pub log: Vec<String>,
+    pub include_diagnostics: bool,
+    pub include_log: bool,
+}
+
+impl ProblemReport {
+    /// A report for version `app_version` of the app, which is passed in since this crate's own
+    /// version isn't the app's.
+    pub fn new(
+        app_version: &str,
+        backend: String,
+        diagnostics: Vec<(String, String)>,
+        log: Vec<String>,
+    ) -> Self {
+        ProblemReport {
+            app_version: app_version.to_string(),
+            os: format!("{} ({})", std::env::consts::OS, std::env::consts::ARCH),
+            backend,
+            diagnostics,
+            log,
+            include_diagnostics: true,
+            include_log: true,
+        }
+    }
+
+    /// Markdown issue body, with home folder paths masked.
+    pub fn body(&self) -> String {
+        self.body_with_log(&self.log)
+    }
+
+    fn body_with_log(&self, log: &[String]) -> String {
+        let mut body = String::from("### What happened?\n\n\n\n### Environment\n\n");
+        body.push_str(&format!("- Version: {}\n", self.app_version));
+        body.push_str(&format!("- OS: {}\n", self.os));
+        body.push_str(&format!("- Backend: {}\n", self.backend));
+        if self.include_diagnostics && !self.diagnostics.is_empty() {
+            body.push_str("\n### Diagnostics\n\n");
+            for (key, value) in &self.diagnostics {
+                body.push_str(&format!("- {key}: {value}\n"));
+            }
+        }
+        if self.include_log && !log.is_empty() {
+            body.push_str("\n### Recent log\n\n```\n");
+            if log.len() < self.log.len() {
+                body.push_str("...\n");
+            }
+            for line in log {
+                body.push_str(line);
+                body.push('\n');
+            }
+            body.push_str("```\n");
+        }
+        redact(&body)
+    }
+
+    /// A new-issue URL with the body prefilled. When the body is too long, the oldest log lines
+    /// are left out, so the log's code block stays whole.
+    pub fn issue_url(&self) -> String {
+        let mut log = self.log.as_slice();
+        let mut body = self.body_with_log(log);
+        while body.len() > MAX_URL_BODY && !log.is_empty() {
+            log = &log[1..];
+            body = self.body_with_log(log);
+        }
+        if body.len() > MAX_URL_BODY {
+            let mut end = MAX_URL_BODY;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body.truncate(end);
+            body.push_str("\n...\n");
+        }
+        format!("{ISSUES_URL}?body={}", encode_query(&body))
+    }
+
+    /// Write the report to `Kagi/reports` and return its path.
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let user_dirs = UserDirs::new().ok_or(anyhow::anyhow!("Could not find home directory"))?;
+        let dir = user_dirs
+            .audio_dir()
+            .unwrap_or(user_dirs.home_dir())
+            .join("Kagi")
+            .join("reports");
+        fs::create_dir_all(&dir)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = dir.join(format!("report-{timestamp}.md"));
+        fs::write(&path, self.body())?;
+        Ok(path)
+    }
+}
+
+/// Mask the home folder where it starts a path, which is where the user name shows up in file
+/// paths and URIs. Other text that happens to contain the user name is left alone.
+pub fn redact(text: &str) -> String {
+    let Some(user_dirs) = UserDirs::new() else {
+        return text.to_string();
+    };
+    let home = user_dirs.home_dir().to_string_lossy().replace("\\", "/");
+    let home = home.trim_end_matches('/');
+    if home.is_empty() {
+        return text.to_string();
+    }
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(home) {
+        let after = &rest[at + home.len()..];
+        // `/home/ann` shouldn't match the start of `/home/anna`.
+        let in_name = |c: char| c.is_alphanumeric() || "_-.".contains(c);
+        let whole = after.chars().next().is_none_or(|c| !in_name(c));
+        redacted.push_str(&rest[..at]);
+        redacted.push_str(if whole { "~" } else { home });
+        rest = after;
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+fn encode_query(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
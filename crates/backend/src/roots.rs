@@ -0,0 +1,160 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use bincode::config;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    playback::{SavedPlaylist, Track},
+    scanner::ScanJob,
+    session::Session,
+};
+
+/// A folder registered as part of the library, like the music folder on the internal drive, a
+/// NAS mount or an external disk. Each root is scanned into a cache of its own, so one can be
+/// rescanned or removed without touching the others.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub struct LibraryRoot {
+    pub path: String,
+    pub cached_name: String,
+    /// When the last complete scan finished, in seconds since the Unix epoch.
+    pub last_scan: Option<u64>,
+    /// Tracks found by the last complete scan.
+    pub track_count: usize,
+}
+
+/// Where a root stands, as shown in the library settings.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RootStatus {
+    Idle,
+    Scanning {
+        scanned: usize,
+        total: usize,
+    },
+    /// The folder isn't there right now, like an unplugged disk or an unmounted share. Its
+    /// tracks stay in the library from the last scan.
+    Missing,
+}
+
+/// A rescan of one root, collecting its tracks until the scan is done.
+#[derive(Clone)]
+pub struct RootScan {
+    pub job: ScanJob,
+    pub tracks: Vec<Track>,
+    pub scanned: usize,
+    pub total: usize,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LibraryRoots {
+    pub roots: Vec<LibraryRoot>,
+}
+
+impl LibraryRoot {
+    /// Folder name shown for the root.
+    pub fn name(&self) -> String {
+        Path::new(&self.path)
+            .file_name()
+            .map_or(self.path.clone(), |name| {
+                name.to_string_lossy().into_owned()
+            })
+    }
+
+    /// The root as a playlist, for scanning and caching it like a playlist folder.
+    pub fn saved(&self) -> SavedPlaylist {
+        SavedPlaylist {
+            name: self.name(),
+            actual_path: self.path.clone(),
+            cached_name: self.cached_name.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        Path::new(&self.path).is_dir()
+    }
+
+    /// Note a finished scan that found `track_count` tracks.
+    pub fn scanned(&mut self, track_count: usize) {
+        self.track_count = track_count;
+        self.last_scan = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|since| since.as_secs());
+    }
+}
+
+impl LibraryRoots {
+    pub fn get_roots_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("library_roots.bin"))
+    }
+
+    pub fn load() -> LibraryRoots {
+        let Some(file_path) = Self::get_roots_file() else {
+            return LibraryRoots::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return LibraryRoots::default();
+        };
+        match bincode::serde::decode_from_slice(&data, config::standard()) {
+            Ok((roots, _)) => roots,
+            Err(e) => {
+                eprintln!("Failed to decode library roots: {}", e);
+                LibraryRoots::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_roots_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    /// Register `dir` as a new root, not yet scanned. Folders already covered by a root, or
+    /// holding one, are refused, so no file is in the library twice.
+    pub fn add(&mut self, dir: &Path) -> anyhow::Result<LibraryRoot> {
+        let dir = fs::canonicalize(dir)?;
+        if let Some(root) = self.roots.iter().find(|root| {
+            let path = Path::new(&root.path);
+            dir.starts_with(path) || path.starts_with(&dir)
+        }) {
+            return Err(anyhow!(
+                "{} overlaps the library folder {}",
+                dir.display(),
+                root.path
+            ));
+        }
+        let mut hasher = DefaultHasher::new();
+        dir.hash(&mut hasher);
+        let root = LibraryRoot {
+            path: dir.to_string_lossy().into_owned(),
+            cached_name: format!("root_{:016x}", hasher.finish()),
+            last_scan: None,
+            track_count: 0,
+        };
+        self.roots.push(root.clone());
+        self.save()?;
+        Ok(root)
+    }
+
+    /// Forget the root at `index`, returning it so its cache can be dropped.
+    pub fn remove(&mut self, index: usize) -> anyhow::Result<LibraryRoot> {
+        if index >= self.roots.len() {
+            return Err(anyhow!("No library folder at {index}"));
+        }
+        let root = self.roots.remove(index);
+        self.save()?;
+        Ok(root)
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread,
+};
+
+use smol::channel::Sender;
+
+use crate::{
+    Backend,
+    playback::{self, Origin, Playlist, SavedPlaylist, ScanOptions, Track},
+};
+
+/// Tracks are handed to the player in batches this size, so the queue fills in as the scan runs.
+pub const SCAN_BATCH: usize = 25;
+
+static NEXT_SCAN: AtomicU64 = AtomicU64::new(0);
+
+/// Progress from a background folder scan, tagged with the id of the [`ScanJob`] it belongs to.
+pub enum ScanUpdate {
+    /// The next tracks, in playlist order.
+    Batch(u64, Vec<Track>),
+    Progress {
+        id: u64,
+        scanned: usize,
+        total: usize,
+    },
+    /// Every file has been read.
+    Done(u64),
+}
+
+impl ScanUpdate {
+    /// The scan this update came from.
+    pub fn id(&self) -> u64 {
+        match self {
+            ScanUpdate::Batch(id, _) | ScanUpdate::Progress { id, .. } | ScanUpdate::Done(id) => {
+                *id
+            }
+        }
+    }
+}
+
+/// A folder scan running on its own thread. Cancelling the job stops the scan after the file it
+/// is reading.
+#[derive(Clone)]
+pub struct ScanJob {
+    pub id: u64,
+    /// The playlist the scanned tracks belong to, cached and saved once the scan completes. Empty
+    /// for files being added to the queue.
+    pub saved: SavedPlaylist,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ScanJob {
+    pub fn start(
+        backend: Arc<dyn Backend>,
+        saved: SavedPlaylist,
+        options: ScanOptions,
+        updates: Sender<ScanUpdate>,
+    ) -> Self {
+        let dir = PathBuf::from(&saved.actual_path);
+        let list = move |options: &ScanOptions| playback::scan_paths(&dir, options);
+        Self::spawn(backend, saved, list, Origin::FolderScan, options, updates)
+    }
+
+    /// Read `paths` in the background, taking in the audio files under any folders among them.
+    /// Paths that aren't there are left out.
+    pub fn files(
+        backend: Arc<dyn Backend>,
+        paths: Vec<PathBuf>,
+        origin: Origin,
+        options: ScanOptions,
+        updates: Sender<ScanUpdate>,
+    ) -> Self {
+        let list = move |options: &ScanOptions| {
+            paths
+                .iter()
+                .flat_map(|path| {
+                    if path.is_dir() {
+                        playback::scan_paths(path, options)
+                    } else if path.is_file() && playback::is_audio(path) {
+                        vec![path.clone()]
+                    } else {
+                        vec![]
+                    }
+                })
+                .collect()
+        };
+        Self::spawn(
+            backend,
+            SavedPlaylist::default(),
+            list,
+            origin,
+            options,
+            updates,
+        )
+    }
+
+    fn spawn(
+        backend: Arc<dyn Backend>,
+        saved: SavedPlaylist,
+        list: impl FnOnce(&ScanOptions) -> Vec<PathBuf> + Send + 'static,
+        origin: Origin,
+        options: ScanOptions,
+        updates: Sender<ScanUpdate>,
+    ) -> Self {
+        let id = NEXT_SCAN.fetch_add(1, Ordering::Relaxed);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let worker_cancelled = cancelled.clone();
+
+        thread::spawn(move || {
+            // Listing files is quick next to reading tags, so the total is known up front.
+            let paths = list(&options);
+            let total = paths.len();
+            let _ = updates.send_blocking(ScanUpdate::Progress {
+                id,
+                scanned: 0,
+                total,
+            });
+
+            let mut batch = Vec::with_capacity(SCAN_BATCH);
+            for (scanned, path) in paths.iter().enumerate() {
+                if worker_cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                batch.push(smol::block_on(Playlist::load_track(
+                    &backend,
+                    path,
+                    options.verify,
+                    origin,
+                )));
+                if batch.len() == SCAN_BATCH || scanned + 1 == total {
+                    let tracks = std::mem::take(&mut batch);
+                    if updates
+                        .send_blocking(ScanUpdate::Batch(id, tracks))
+                        .is_err()
+                    {
+                        return;
+                    }
+                    let _ = updates.send_blocking(ScanUpdate::Progress {
+                        id,
+                        scanned: scanned + 1,
+                        total,
+                    });
+                }
+            }
+            let _ = updates.send_blocking(ScanUpdate::Done(id));
+        });
+
+        ScanJob {
+            id,
+            saved,
+            cancelled,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
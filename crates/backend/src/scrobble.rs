@@ -0,0 +1,772 @@
+//! Scrobbling to Last.fm and ListenBrainz. Requests are made on a worker thread of their own;
+//! listens that can't be sent, like while offline, are kept in `scrobbles.bin` and sent once
+//! the service can be reached again.
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::PathBuf,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use bincode::config;
+use ring_channel::RingSender as Sender;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    file_manager, playback::Track, player::Response, session::Session, settings::Settings,
+};
+
+const LASTFM_API: &str = "https://ws.audioscrobbler.com/2.0/";
+const LASTFM_AUTH: &str = "https://www.last.fm/api/auth/";
+const LISTENBRAINZ_API: &str = "https://api.listenbrainz.org/1/";
+/// How long held-back listens wait before they're tried again.
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Most listens sent in one request, which is all Last.fm takes.
+const BATCH_SIZE: usize = 50;
+
+/// Accounts listens are sent to, kept in the settings. A service is used once its credentials
+/// are all filled in. The secret ones are written to `secrets.toml` beside the settings, which
+/// only its owner can read, rather than to the settings file; they're still read from a
+/// settings file written before that, and moved out of it on loading.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrobbleAccounts {
+    /// Key and shared secret of a Last.fm API account, from
+    /// <https://www.last.fm/api/account/create>.
+    pub lastfm_api_key: String,
+    #[serde(skip_serializing)]
+    pub lastfm_api_secret: String,
+    /// Last.fm user signed in as, and the session key that stands in for their password.
+    pub lastfm_user: String,
+    #[serde(skip_serializing)]
+    pub lastfm_session: String,
+    /// ListenBrainz user token, from the account's settings page.
+    #[serde(skip_serializing)]
+    pub listenbrainz_token: String,
+}
+
+/// The secret parts of [`ScrobbleAccounts`], as kept in `secrets.toml`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Secrets {
+    lastfm_api_secret: String,
+    lastfm_session: String,
+    listenbrainz_token: String,
+}
+
+impl ScrobbleAccounts {
+    pub fn get_secrets_file() -> Option<PathBuf> {
+        Settings::get_settings_file().map(|file| file.with_file_name("secrets.toml"))
+    }
+
+    /// Whether any secret is filled in, as when read from a settings file that still held them.
+    pub fn has_secrets(&self) -> bool {
+        !self.lastfm_api_secret.is_empty()
+            || !self.lastfm_session.is_empty()
+            || !self.listenbrainz_token.is_empty()
+    }
+
+    /// Fill the secrets in from `secrets.toml`, if there is one.
+    pub fn load_secrets(&mut self) {
+        let Some(file_path) = Self::get_secrets_file().filter(|file| file.exists()) else {
+            return;
+        };
+        let secrets = match fs::read_to_string(&file_path) {
+            Ok(contents) => match toml::from_str::<Secrets>(&contents) {
+                Ok(secrets) => secrets,
+                Err(e) => {
+                    eprintln!("Failed to parse {}: {e}", file_path.display());
+                    return;
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to read {}: {e}", file_path.display());
+                return;
+            }
+        };
+        self.lastfm_api_secret = secrets.lastfm_api_secret;
+        self.lastfm_session = secrets.lastfm_session;
+        self.listenbrainz_token = secrets.listenbrainz_token;
+    }
+
+    /// Write the secrets to `secrets.toml`, readable and writable by its owner alone.
+    pub fn save_secrets(&self) -> io::Result<()> {
+        let Some(file_path) = Self::get_secrets_file() else {
+            return Ok(());
+        };
+        let secrets = Secrets {
+            lastfm_api_secret: self.lastfm_api_secret.clone(),
+            lastfm_session: self.lastfm_session.clone(),
+            listenbrainz_token: self.listenbrainz_token.clone(),
+        };
+        let toml_str = toml::to_string_pretty(&secrets).expect("Failed to serialize secrets");
+        file_manager::write_private(&file_path, toml_str.as_bytes())
+    }
+
+    pub fn has_lastfm_key(&self) -> bool {
+        !self.lastfm_api_key.is_empty() && !self.lastfm_api_secret.is_empty()
+    }
+
+    pub fn has_lastfm(&self) -> bool {
+        self.has_lastfm_key() && !self.lastfm_session.is_empty()
+    }
+
+    pub fn has_listenbrainz(&self) -> bool {
+        !self.listenbrainz_token.is_empty()
+    }
+}
+
+/// A track heard, as the services want it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Listen {
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    /// Seconds, or 0 if unknown.
+    pub duration: u64,
+    /// When it started playing, in seconds since the Unix epoch.
+    pub started: u64,
+}
+
+impl Listen {
+    /// `track` as started at `started`, or `None` if it has no artist and title to go by.
+    pub fn new(track: &Track, started: u64) -> Option<Listen> {
+        let artist = track.artists.join(", ");
+        if artist.is_empty() || track.title.is_empty() {
+            return None;
+        }
+        Some(Listen {
+            artist,
+            title: track.title.clone(),
+            album: track.album.clone(),
+            duration: track.duration,
+            started,
+        })
+    }
+
+    /// `track` as starting now.
+    pub fn starting(track: &Track) -> Option<Listen> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        Listen::new(track, now)
+    }
+}
+
+/// Whether hearing `heard` seconds of a track `duration` seconds long makes it a listen: half
+/// of it or four minutes, whichever comes first, of a track longer than 30 seconds.
+pub fn counts_as_listen(heard: u64, duration: u64) -> bool {
+    duration > 30 && (heard * 2 >= duration || heard >= 240)
+}
+
+/// How far the current track has got toward counting as a listen.
+pub struct Listening {
+    listen: Listen,
+    /// Seconds heard, counted from the position moving on a little at a time, so seeking ahead
+    /// doesn't count.
+    heard: u64,
+    position: u64,
+    scrobbled: bool,
+}
+
+impl Listening {
+    pub fn new(listen: Listen) -> Self {
+        Listening {
+            listen,
+            heard: 0,
+            position: 0,
+            scrobbled: false,
+        }
+    }
+
+    /// Follow playback to `position`, giving back the listen the first time enough of it has
+    /// been heard.
+    pub fn advance(&mut self, position: u64) -> Option<Listen> {
+        if position > self.position && position - self.position <= 2 {
+            self.heard += position - self.position;
+        }
+        self.position = position;
+        if self.scrobbled || !counts_as_listen(self.heard, self.listen.duration) {
+            return None;
+        }
+        self.scrobbled = true;
+        Some(self.listen.clone())
+    }
+}
+
+/// Listens not sent yet, for each service.
+#[derive(Default, Serialize, Deserialize)]
+pub struct PendingScrobbles {
+    pub lastfm: Vec<Listen>,
+    pub listenbrainz: Vec<Listen>,
+}
+
+impl PendingScrobbles {
+    pub fn get_scrobbles_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("scrobbles.bin"))
+    }
+
+    pub fn load() -> PendingScrobbles {
+        let Some(file_path) = Self::get_scrobbles_file() else {
+            return PendingScrobbles::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return PendingScrobbles::default();
+        };
+        match bincode::serde::decode_from_slice(&data, config::standard()) {
+            Ok((pending, _)) => pending,
+            Err(e) => {
+                eprintln!("Failed to decode scrobbles: {}", e);
+                PendingScrobbles::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_scrobbles_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.lastfm.len().max(self.listenbrainz.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+enum Job {
+    Accounts(ScrobbleAccounts),
+    NowPlaying(Listen),
+    Scrobble(Listen),
+    ConnectLastFm,
+    FinishLastFm,
+    CheckListenBrainz(String),
+}
+
+/// Sends now-playing updates and listens to the signed-in services on a worker thread.
+#[derive(Clone)]
+pub struct Scrobbler {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl Scrobbler {
+    pub fn new(tx: Sender<Response>) -> Self {
+        let (jobs, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut worker = Worker {
+                agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+                accounts: ScrobbleAccounts::default(),
+                pending: PendingScrobbles::load(),
+                unsaved: false,
+                lastfm_token: None,
+                lastfm_signed_out: false,
+                listenbrainz_signed_out: false,
+                tx,
+            };
+            loop {
+                match rx.recv_timeout(RETRY_INTERVAL) {
+                    Ok(job) => worker.handle(job),
+                    Err(RecvTimeoutError::Timeout) => worker.flush(),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+        Scrobbler { jobs }
+    }
+
+    pub fn set_accounts(&self, accounts: ScrobbleAccounts) {
+        let _ = self.jobs.send(Job::Accounts(accounts));
+    }
+
+    pub fn now_playing(&self, listen: Listen) {
+        let _ = self.jobs.send(Job::NowPlaying(listen));
+    }
+
+    pub fn scrobble(&self, listen: Listen) {
+        let _ = self.jobs.send(Job::Scrobble(listen));
+    }
+
+    /// Start signing in to Last.fm. The page to allow access on comes back as
+    /// [`Response::LastFmAuthorize`].
+    pub fn connect_lastfm(&self) {
+        let _ = self.jobs.send(Job::ConnectLastFm);
+    }
+
+    /// Finish signing in once access was allowed, answered with [`Response::LastFmSession`].
+    pub fn finish_lastfm(&self) {
+        let _ = self.jobs.send(Job::FinishLastFm);
+    }
+
+    /// Check that `token` is a ListenBrainz user token, answered with
+    /// [`Response::ListenBrainzUser`].
+    pub fn check_listenbrainz(&self, token: String) {
+        let _ = self.jobs.send(Job::CheckListenBrainz(token));
+    }
+}
+
+/// Why a request failed, which decides whether it's worth making again.
+enum SendError {
+    /// Offline, or the service is down or busy.
+    Retry(anyhow::Error),
+    /// The service turned the request down, so making it again won't help.
+    Rejected(anyhow::Error),
+    /// The service no longer takes the credentials, like an expired session or revoked token,
+    /// so it's worth trying again once signed in again.
+    Auth(anyhow::Error),
+}
+
+impl From<ureq::Error> for SendError {
+    fn from(e: ureq::Error) -> Self {
+        match e {
+            ureq::Error::Status(code, response) => {
+                SendError::from_status(code, response.into_string().unwrap_or_default())
+            }
+            e => SendError::Retry(e.into()),
+        }
+    }
+}
+
+impl SendError {
+    fn from_status(code: u16, body: String) -> Self {
+        let error = anyhow!("{code}: {body}");
+        match code {
+            401 | 403 => SendError::Auth(error),
+            429 | 500.. => SendError::Retry(error),
+            _ => SendError::Rejected(error),
+        }
+    }
+
+    fn into_inner(self) -> anyhow::Error {
+        match self {
+            SendError::Retry(e) | SendError::Rejected(e) | SendError::Auth(e) => e,
+        }
+    }
+}
+
+/// The `track.scrobble` parameters for sending `listens` in one batch, each indexed by its
+/// place in it.
+fn lastfm_scrobble_params(session: &str, listens: &[Listen]) -> Vec<(String, String)> {
+    let mut params = vec![("sk".to_string(), session.to_string())];
+    for (index, listen) in listens.iter().enumerate() {
+        params.push((format!("artist[{index}]"), listen.artist.clone()));
+        params.push((format!("track[{index}]"), listen.title.clone()));
+        params.push((format!("timestamp[{index}]"), listen.started.to_string()));
+        if !listen.album.is_empty() {
+            params.push((format!("album[{index}]"), listen.album.clone()));
+        }
+        if listen.duration > 0 {
+            params.push((format!("duration[{index}]"), listen.duration.to_string()));
+        }
+    }
+    params
+}
+
+struct Worker {
+    agent: ureq::Agent,
+    accounts: ScrobbleAccounts,
+    pending: PendingScrobbles,
+    /// `pending` changed since it was last written out.
+    unsaved: bool,
+    /// Token Last.fm handed out for signing in, until access is allowed and it's traded for a
+    /// session.
+    lastfm_token: Option<String>,
+    /// Services that stopped taking their credentials. Their listens wait, unsent, until the
+    /// accounts change.
+    lastfm_signed_out: bool,
+    listenbrainz_signed_out: bool,
+    tx: Sender<Response>,
+}
+
+impl Worker {
+    fn handle(&mut self, job: Job) {
+        match job {
+            Job::Accounts(accounts) => {
+                self.accounts = accounts;
+                self.lastfm_signed_out = false;
+                self.listenbrainz_signed_out = false;
+                self.flush();
+            }
+            Job::NowPlaying(listen) => {
+                // Only a courtesy, so failures aren't kept or reported.
+                let lastfm = self
+                    .accounts
+                    .has_lastfm()
+                    .then(|| self.lastfm_now_playing(&listen));
+                if let Some(Err(e)) = lastfm {
+                    eprintln!("Could not update Last.fm: {}", e.into_inner());
+                }
+                let listenbrainz = self
+                    .accounts
+                    .has_listenbrainz()
+                    .then(|| self.listenbrainz_submit("playing_now", &[listen]));
+                if let Some(Err(e)) = listenbrainz {
+                    eprintln!("Could not update ListenBrainz: {}", e.into_inner());
+                }
+            }
+            Job::Scrobble(listen) => {
+                if self.accounts.has_lastfm() {
+                    self.pending.lastfm.push(listen.clone());
+                }
+                if self.accounts.has_listenbrainz() {
+                    self.pending.listenbrainz.push(listen);
+                }
+                self.unsaved = true;
+                self.flush();
+            }
+            Job::ConnectLastFm => match self.lastfm_call("auth.getToken", vec![]) {
+                Ok(body) => {
+                    let Some(token) = body["token"].as_str() else {
+                        self.report("Last.fm sent no sign-in token".to_string());
+                        return;
+                    };
+                    let url = format!(
+                        "{LASTFM_AUTH}?api_key={}&token={token}",
+                        self.accounts.lastfm_api_key
+                    );
+                    self.lastfm_token = Some(token.to_string());
+                    let _ = self.tx.send(Response::LastFmAuthorize(url));
+                }
+                Err(e) => self.report(format!("Could not sign in to Last.fm: {}", e.into_inner())),
+            },
+            Job::FinishLastFm => {
+                let Some(token) = self.lastfm_token.take() else {
+                    self.report("Connect to Last.fm first".to_string());
+                    return;
+                };
+                let params = vec![("token".to_string(), token)];
+                match self.lastfm_call("auth.getSession", params) {
+                    Ok(body) => {
+                        let session = &body["session"];
+                        let (Some(user), Some(key)) =
+                            (session["name"].as_str(), session["key"].as_str())
+                        else {
+                            self.report("Last.fm sent no session".to_string());
+                            return;
+                        };
+                        let _ = self.tx.send(Response::LastFmSession {
+                            user: user.to_string(),
+                            session: key.to_string(),
+                        });
+                    }
+                    Err(e) => {
+                        self.report(format!("Could not sign in to Last.fm: {}", e.into_inner()))
+                    }
+                }
+            }
+            Job::CheckListenBrainz(token) => match self.listenbrainz_user(&token) {
+                Ok(user) => {
+                    let _ = self.tx.send(Response::ListenBrainzUser(user));
+                }
+                Err(e) => self.report(format!(
+                    "Could not check ListenBrainz token: {}",
+                    e.into_inner()
+                )),
+            },
+        }
+    }
+
+    fn report(&self, error: String) {
+        let _ = self.tx.send(Response::Error(error));
+    }
+
+    /// Send whatever listens are held back, a batch at a time, until a service can't be reached
+    /// or needs signing in to again. Listens a service turns down are dropped.
+    fn flush(&mut self) {
+        while self.accounts.has_lastfm()
+            && !self.lastfm_signed_out
+            && !self.pending.lastfm.is_empty()
+        {
+            let count = self.pending.lastfm.len().min(BATCH_SIZE);
+            match self.lastfm_scrobble(&self.pending.lastfm[..count]) {
+                Ok(()) => {}
+                Err(SendError::Retry(e)) => {
+                    eprintln!("Could not reach Last.fm, will try again: {e}");
+                    break;
+                }
+                Err(SendError::Auth(e)) => {
+                    eprintln!("Last.fm no longer accepts the sign-in: {e}");
+                    self.lastfm_signed_out = true;
+                    let _ = self
+                        .tx
+                        .send(Response::ScrobbleSignedOut("Last.fm".to_string()));
+                    break;
+                }
+                Err(SendError::Rejected(e)) => {
+                    self.report(format!("Last.fm refused scrobbles: {e}"))
+                }
+            }
+            self.pending.lastfm.drain(..count);
+            self.unsaved = true;
+        }
+        while self.accounts.has_listenbrainz()
+            && !self.listenbrainz_signed_out
+            && !self.pending.listenbrainz.is_empty()
+        {
+            let count = self.pending.listenbrainz.len().min(BATCH_SIZE);
+            let kind = if count == 1 { "single" } else { "import" };
+            match self.listenbrainz_submit(kind, &self.pending.listenbrainz[..count]) {
+                Ok(()) => {}
+                Err(SendError::Retry(e)) => {
+                    eprintln!("Could not reach ListenBrainz, will try again: {e}");
+                    break;
+                }
+                Err(SendError::Auth(e)) => {
+                    eprintln!("ListenBrainz no longer accepts the token: {e}");
+                    self.listenbrainz_signed_out = true;
+                    let _ = self
+                        .tx
+                        .send(Response::ScrobbleSignedOut("ListenBrainz".to_string()));
+                    break;
+                }
+                Err(SendError::Rejected(e)) => {
+                    self.report(format!("ListenBrainz refused listens: {e}"))
+                }
+            }
+            self.pending.listenbrainz.drain(..count);
+            self.unsaved = true;
+        }
+        if self.unsaved {
+            self.unsaved = false;
+            if let Err(e) = self.pending.save() {
+                eprintln!("Could not save scrobbles: {e}");
+            }
+        }
+        let _ = self.tx.send(Response::PendingScrobbles(self.pending.len()));
+    }
+
+    /// Call a Last.fm API method, signed with the API secret.
+    fn lastfm_call(
+        &self,
+        method: &str,
+        mut params: Vec<(String, String)>,
+    ) -> Result<Value, SendError> {
+        params.push(("method".to_string(), method.to_string()));
+        params.push(("api_key".to_string(), self.accounts.lastfm_api_key.clone()));
+        params.sort();
+        let mut signed: String = params
+            .iter()
+            .map(|(key, value)| format!("{key}{value}"))
+            .collect();
+        signed.push_str(&self.accounts.lastfm_api_secret);
+        params.push(("api_sig".to_string(), format!("{:x}", md5::compute(signed))));
+        params.push(("format".to_string(), "json".to_string()));
+
+        let form: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let body: Value = match self.agent.post(LASTFM_API).send_form(&form) {
+            Ok(response) => response
+                .into_json()
+                .map_err(|e| SendError::Retry(e.into()))?,
+            // Last.fm reports errors in the body, with or without an error status. The error
+            // code in it says more than the status does.
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                match serde_json::from_str::<Value>(&body) {
+                    Ok(body) if body["error"].is_u64() => body,
+                    _ => return Err(SendError::from_status(code, body)),
+                }
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(code) = body["error"].as_u64() {
+            let error = anyhow!("{}", body["message"].as_str().unwrap_or("unknown error"));
+            return Err(match code {
+                // Service offline, temporarily unavailable, or rate limited.
+                11 | 16 | 29 => SendError::Retry(error),
+                // Authentication failed, session expired or revoked, or API key invalid or
+                // suspended.
+                4 | 9 | 10 | 26 => SendError::Auth(error),
+                _ => SendError::Rejected(error),
+            });
+        }
+        Ok(body)
+    }
+
+    fn lastfm_now_playing(&self, listen: &Listen) -> Result<(), SendError> {
+        let mut params = vec![
+            ("artist".to_string(), listen.artist.clone()),
+            ("track".to_string(), listen.title.clone()),
+            ("sk".to_string(), self.accounts.lastfm_session.clone()),
+        ];
+        if !listen.album.is_empty() {
+            params.push(("album".to_string(), listen.album.clone()));
+        }
+        if listen.duration > 0 {
+            params.push(("duration".to_string(), listen.duration.to_string()));
+        }
+        self.lastfm_call("track.updateNowPlaying", params)?;
+        Ok(())
+    }
+
+    fn lastfm_scrobble(&self, listens: &[Listen]) -> Result<(), SendError> {
+        let params = lastfm_scrobble_params(&self.accounts.lastfm_session, listens);
+        self.lastfm_call("track.scrobble", params)?;
+        Ok(())
+    }
+
+    /// Submit `listens` to ListenBrainz as `kind`: `playing_now`, `single` or `import`.
+    fn listenbrainz_submit(&self, kind: &str, listens: &[Listen]) -> Result<(), SendError> {
+        let payload: Vec<Value> = listens
+            .iter()
+            .map(|listen| {
+                let mut entry = json!({
+                    "track_metadata": {
+                        "artist_name": listen.artist,
+                        "track_name": listen.title,
+                        "additional_info": {
+                            "submission_client": "Reyvr",
+                        },
+                    },
+                });
+                if !listen.album.is_empty() {
+                    entry["track_metadata"]["release_name"] = json!(listen.album);
+                }
+                if listen.duration > 0 {
+                    entry["track_metadata"]["additional_info"]["duration_ms"] =
+                        json!(listen.duration * 1000);
+                }
+                if kind != "playing_now" {
+                    entry["listened_at"] = json!(listen.started);
+                }
+                entry
+            })
+            .collect();
+        self.agent
+            .post(&format!("{LISTENBRAINZ_API}submit-listens"))
+            .set(
+                "Authorization",
+                &format!("Token {}", self.accounts.listenbrainz_token),
+            )
+            .send_json(json!({ "listen_type": kind, "payload": payload }))?;
+        Ok(())
+    }
+
+    /// The user `token` belongs to.
+    fn listenbrainz_user(&self, token: &str) -> Result<String, SendError> {
+        let body: Value = self
+            .agent
+            .get(&format!("{LISTENBRAINZ_API}validate-token"))
+            .set("Authorization", &format!("Token {token}"))
+            .call()?
+            .into_json()
+            .map_err(|e| SendError::Retry(e.into()))?;
+        match body["user_name"].as_str() {
+            Some(user) if body["valid"].as_bool() == Some(true) => Ok(user.to_string()),
+            _ => Err(SendError::Rejected(anyhow!("The token isn't valid"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listen(duration: u64) -> Listen {
+        Listen {
+            artist: "Artist".to_string(),
+            title: "Title".to_string(),
+            album: "Album".to_string(),
+            duration,
+            started: 1_700_000_000,
+        }
+    }
+
+    fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        params
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn listen_needs_half_or_four_minutes_of_a_long_enough_track() {
+        assert!(!counts_as_listen(30, 30));
+        assert!(!counts_as_listen(99, 200));
+        assert!(counts_as_listen(100, 200));
+        assert!(!counts_as_listen(239, 600));
+        assert!(counts_as_listen(240, 600));
+        assert!(!counts_as_listen(0, 0));
+    }
+
+    #[test]
+    fn listening_counts_once_and_ignores_seeks() {
+        let mut listening = Listening::new(listen(100));
+        assert!(listening.advance(90).is_none());
+        for position in 91..=138 {
+            assert!(listening.advance(position).is_none());
+        }
+        // Seeking back and playing on counts again, but only the steps heard.
+        assert!(listening.advance(10).is_none());
+        assert!(listening.advance(11).is_none());
+        assert!(listening.advance(12).is_some());
+        assert!(listening.advance(13).is_none());
+        assert!(listening.advance(60).is_none());
+    }
+
+    #[test]
+    fn scrobble_params_index_every_listen_in_the_batch() {
+        let mut listens: Vec<Listen> = (0..BATCH_SIZE as u64)
+            .map(|index| Listen {
+                started: index,
+                ..listen(200)
+            })
+            .collect();
+        listens[1].album.clear();
+        listens[2].duration = 0;
+
+        let params = lastfm_scrobble_params("session", &listens);
+        assert_eq!(param(&params, "sk"), Some("session"));
+        for index in 0..BATCH_SIZE {
+            assert_eq!(param(&params, &format!("artist[{index}]")), Some("Artist"));
+            assert_eq!(param(&params, &format!("track[{index}]")), Some("Title"));
+            let started = index.to_string();
+            assert_eq!(
+                param(&params, &format!("timestamp[{index}]")),
+                Some(started.as_str())
+            );
+        }
+        assert_eq!(param(&params, &format!("artist[{BATCH_SIZE}]")), None);
+        assert_eq!(param(&params, "album[1]"), None);
+        assert_eq!(param(&params, "duration[2]"), None);
+        assert_eq!(param(&params, "album[2]"), Some("Album"));
+        assert_eq!(param(&params, "duration[1]"), Some("200"));
+    }
+
+    #[test]
+    fn status_codes_decide_whether_to_retry() {
+        assert!(matches!(
+            SendError::from_status(401, String::new()),
+            SendError::Auth(_)
+        ));
+        assert!(matches!(
+            SendError::from_status(403, String::new()),
+            SendError::Auth(_)
+        ));
+        assert!(matches!(
+            SendError::from_status(429, String::new()),
+            SendError::Retry(_)
+        ));
+        assert!(matches!(
+            SendError::from_status(503, String::new()),
+            SendError::Retry(_)
+        ));
+        assert!(matches!(
+            SendError::from_status(400, String::new()),
+            SendError::Rejected(_)
+        ));
+    }
+}
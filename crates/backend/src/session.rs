@@ -0,0 +1,305 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use bincode::config;
+use directories::UserDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    playback::{Playlist, Track},
+    uri,
+};
+
+/// Playback state saved on change and on exit, so the next launch can pick up where this one
+/// left off.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub playlist: Playlist,
+    pub queue: Vec<Track>,
+    pub current_index: usize,
+    pub position: u64,
+    pub volume: f64,
+    pub shuffle: bool,
+    pub repeat: bool,
+}
+
+impl Session {
+    pub fn get_session_file() -> Option<PathBuf> {
+        let user_dirs = UserDirs::new()?;
+        let proj_dir = user_dirs
+            .audio_dir()
+            .unwrap_or(user_dirs.home_dir())
+            .join("Kagi");
+        if let Err(e) = fs::create_dir_all(&proj_dir) {
+            eprintln!("Could not create config directory: {}", e);
+            return None;
+        }
+        Some(proj_dir.join("session.bin"))
+    }
+
+    pub fn load() -> Option<Session> {
+        let file_path = Self::get_session_file()?;
+        let data = fs::read(file_path).ok()?;
+        match bincode::serde::decode_from_slice(&data, config::standard()) {
+            Ok((mut session, _)) => {
+                Session::normalize_uris(&mut session);
+                Some(session)
+            }
+            Err(e) => {
+                eprintln!("Failed to decode session: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_session_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    /// Forget the saved session, so the next launch starts with nothing loaded.
+    pub fn clear() -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_session_file() {
+            match fs::remove_file(file_path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How many partially played collections are kept for the "Continue listening" cards.
+const MAX_RECENT_SESSIONS: usize = 8;
+
+/// Sessions for collections that were left part way through, most recent first.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RecentSessions {
+    pub sessions: Vec<Session>,
+}
+
+/// Summary of a recent session, enough to draw a "Continue listening" card.
+#[derive(Clone, Debug)]
+pub struct ResumeCard {
+    pub name: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub position: u64,
+    pub duration: u64,
+    pub track_number: usize,
+    pub track_count: usize,
+}
+
+impl Session {
+    /// Whether the session stopped somewhere between the first and last second of the queue.
+    pub fn is_partial(&self) -> bool {
+        let Some(track) = self.queue.get(self.current_index) else {
+            return false;
+        };
+        let started = self.current_index > 0 || self.position > 0;
+        let finished = self.current_index + 1 == self.queue.len()
+            && track.duration > 0
+            && self.position + 5 >= track.duration;
+        started && !finished
+    }
+
+    /// Bring track URIs saved by older versions into the current form.
+    pub fn normalize_uris(&mut self) {
+        self.playlist.normalize_uris();
+        for track in self.queue.iter_mut() {
+            track.uri = uri::normalize(&track.uri);
+        }
+    }
+
+    pub fn card(&self) -> Option<ResumeCard> {
+        let track = self.queue.get(self.current_index)?;
+        Some(ResumeCard {
+            name: self.playlist.name.clone(),
+            title: track.title.clone(),
+            artists: track.artists.clone(),
+            position: self.position,
+            duration: track.duration,
+            track_number: self.current_index + 1,
+            track_count: self.queue.len(),
+        })
+    }
+}
+
+impl RecentSessions {
+    pub fn get_recent_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("recent.bin"))
+    }
+
+    pub fn load() -> RecentSessions {
+        let Some(file_path) = Self::get_recent_file() else {
+            return RecentSessions::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return RecentSessions::default();
+        };
+        match bincode::serde::decode_from_slice::<RecentSessions, _>(&data, config::standard()) {
+            Ok((mut recent, _)) => {
+                recent.sessions.iter_mut().for_each(Session::normalize_uris);
+                recent
+            }
+            Err(e) => {
+                eprintln!("Failed to decode recent sessions: {}", e);
+                RecentSessions::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_recent_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    /// Move `session` to the front, or drop it once its collection has been played through.
+    /// Returns whether that changed anything, so the file is only written when it did.
+    pub fn record(&mut self, session: &Session) -> bool {
+        let existing = self
+            .sessions
+            .iter()
+            .position(|recent| recent.playlist.name == session.playlist.name);
+        if !session.is_partial() {
+            self.sessions
+                .retain(|recent| recent.playlist.name != session.playlist.name);
+            return existing.is_some();
+        }
+        let encode = |session: &Session| {
+            bincode::serde::encode_to_vec(session, config::standard()).unwrap_or_default()
+        };
+        if existing == Some(0) && encode(&self.sessions[0]) == encode(session) {
+            return false;
+        }
+        self.sessions
+            .retain(|recent| recent.playlist.name != session.playlist.name);
+        self.sessions.insert(0, session.clone());
+        self.sessions.truncate(MAX_RECENT_SESSIONS);
+        true
+    }
+
+    pub fn cards(&self) -> Vec<ResumeCard> {
+        self.sessions.iter().filter_map(Session::card).collect()
+    }
+}
+
+/// How many folders are offered again on the start screen.
+const MAX_RECENT_FOLDERS: usize = 6;
+
+/// Folders opened as playlists, most recent first.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RecentFolders {
+    pub folders: Vec<PathBuf>,
+}
+
+impl RecentFolders {
+    pub fn get_recent_folders_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("recent_folders.bin"))
+    }
+
+    pub fn load() -> RecentFolders {
+        let Some(file_path) = Self::get_recent_folders_file() else {
+            return RecentFolders::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return RecentFolders::default();
+        };
+        match bincode::serde::decode_from_slice::<RecentFolders, _>(&data, config::standard()) {
+            Ok((recent, _)) => recent,
+            Err(e) => {
+                eprintln!("Failed to decode recent folders: {}", e);
+                RecentFolders::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_recent_folders_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    /// Move `folder` to the front.
+    pub fn record(&mut self, folder: PathBuf) {
+        self.folders.retain(|recent| *recent != folder);
+        self.folders.insert(0, folder);
+        self.folders.truncate(MAX_RECENT_FOLDERS);
+    }
+
+    pub fn forget(&mut self, folder: &Path) {
+        self.folders.retain(|recent| recent != folder);
+    }
+}
+
+/// Last position in every long track that was left part way through, keyed by URI.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TrackPositions {
+    pub positions: HashMap<String, u64>,
+}
+
+impl TrackPositions {
+    pub fn get_positions_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("positions.bin"))
+    }
+
+    pub fn load() -> TrackPositions {
+        let Some(file_path) = Self::get_positions_file() else {
+            return TrackPositions::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return TrackPositions::default();
+        };
+        match bincode::serde::decode_from_slice::<TrackPositions, _>(&data, config::standard()) {
+            Ok((mut positions, _)) => {
+                positions.positions = uri::normalize_keys(positions.positions);
+                positions
+            }
+            Err(e) => {
+                eprintln!("Failed to decode track positions: {}", e);
+                TrackPositions::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_positions_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, uri: &str) -> Option<u64> {
+        self.positions.get(uri).copied()
+    }
+
+    /// Remember `position` for `uri`, forgetting it instead once the track is nearly over.
+    pub fn record(&mut self, uri: &str, position: u64, duration: u64) {
+        if position == 0 || position + 5 >= duration {
+            self.positions.remove(uri);
+        } else {
+            self.positions.insert(uri.to_string(), position);
+        }
+    }
+
+    pub fn forget(&mut self, uri: &str) {
+        self.positions.remove(uri);
+    }
+}
@@ -0,0 +1,389 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::anyhow;
+use directories::{ProjectDirs, UserDirs};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    artists::ArtistRules,
+    lyrics_online::LRCLIB,
+    scrobble::ScrobbleAccounts,
+    stay_awake::StayAwake,
+    text::{self, Collation},
+};
+
+/// Highest volume reachable when overamplification is enabled.
+pub const MAX_BOOSTED_VOLUME: f64 = 1.5;
+/// Range of the pre-amp gain, in decibels.
+pub const MIN_PREAMP_DB: f64 = -20.0;
+pub const MAX_PREAMP_DB: f64 = 20.0;
+/// Range of the interface scale.
+pub const MIN_UI_SCALE: f32 = 0.9;
+pub const MAX_UI_SCALE: f32 = 1.5;
+/// Range of the base text size, in pixels.
+pub const MIN_FONT_SIZE: f32 = 12.0;
+pub const MAX_FONT_SIZE: f32 = 24.0;
+/// Most frames a second the visualizer can draw, as many as the spectrum feed sends.
+pub const MAX_VISUALIZER_FPS: u32 = 30;
+/// Highest visualizer smoothing. At 1 the levels would never fall.
+pub const MAX_VISUALIZER_SMOOTHING: f32 = 0.95;
+
+/// Files once kept in `Kagi` in the music folder, now in the config folder.
+const MIGRATED_FILES: [&str; 3] = ["settings.toml", "keymap.toml", "insights.toml"];
+
+/// Settings file chosen with `--config` or `REYVR_CONFIG`, used instead of the default one.
+static SETTINGS_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Whether the built-in theme is the dark or the light one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Appearance {
+    /// Whichever the system is set to, switching along with it.
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+/// How tightly the interface is laid out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Density {
+    /// Smaller text and padding, fitting more rows on screen.
+    Compact,
+    #[default]
+    Comfortable,
+}
+
+/// How the visualizer draws the spectrum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum VisualizerStyle {
+    /// A bar for each band, lowest frequencies on the left.
+    #[default]
+    Bars,
+    /// A line swinging up and down through the bands, each swing as far as its band is loud.
+    Oscilloscope,
+    /// Bars standing out around a ring.
+    Circular,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub allow_overamplification: bool,
+    pub mono: bool,
+    /// Left/right balance, from -1.0 (left) to 1.0 (right).
+    pub balance: f32,
+    pub artist_rules: ArtistRules,
+    /// How names are put in order in the library, sorted lists and search results.
+    pub collation: Collation,
+    pub skip_silence: bool,
+    pub exclusive_mode: bool,
+    /// Tracks at least this many minutes long resume where they were left. 0 turns it off.
+    pub resume_threshold_minutes: u64,
+    /// Decode-probe files while scanning folders to catch corrupt or truncated ones.
+    pub verify_on_scan: bool,
+    /// Levels of subfolders scanned below a playlist folder, so a whole music library can be
+    /// opened at once. 0 only reads the folder itself.
+    pub scan_depth: usize,
+    /// Leave out folders whose names start with a dot while scanning.
+    pub skip_hidden_folders: bool,
+    /// Pre-amp gain in decibels.
+    pub preamp_db: f64,
+    /// Show titles, artists and albums in Latin script.
+    pub romanize: bool,
+    /// Start quietly after launch or an output switch and fade up to the saved volume.
+    pub loud_start_protection: bool,
+    /// Volume playback starts at while loud-start protection fades in.
+    pub loud_start_max_volume: f64,
+    /// Tracks, by match key, that play from their remote copy even when a local file exists.
+    pub prefer_remote: Vec<String>,
+    /// Folder synced between devices (Syncthing, Dropbox, ...) where the Listen Later playlist
+    /// keeps a mergeable edit log, so several people can add to it at once.
+    pub shared_dir: Option<PathBuf>,
+    /// Milliseconds between position, visualizer and video redraws while the window is in the
+    /// background or minimized. 0 keeps redrawing at the full rate.
+    pub background_redraw_ms: u64,
+    /// Pause while another application plays audio.
+    pub pause_on_other_audio: bool,
+    /// After pausing for another application, carry on once it stops.
+    pub resume_after_other_audio: bool,
+    /// Pause when headphones or a Bluetooth device are disconnected, and before the system
+    /// suspends.
+    pub pause_on_disconnect: bool,
+    /// Show measured loudness and true peak on track rows.
+    pub show_loudness: bool,
+    /// When the queue runs out, keep playing similar tracks from the library.
+    pub autofill: bool,
+    /// Keep artwork out of queue rows, show them compactly, buffer less of streams ahead and
+    /// decode smaller artwork.
+    pub low_memory: bool,
+    /// The suggestion to turn on low-memory mode was dismissed.
+    pub low_memory_dismissed: bool,
+    /// Count which views, commands and settings get used, in a file kept on this computer.
+    pub insights: bool,
+    /// Closing the window hides it to the tray icon and leaves playback going.
+    pub close_to_tray: bool,
+    /// Last.fm and ListenBrainz accounts to send listens to.
+    pub scrobbling: ScrobbleAccounts,
+    /// Keep the computer from sleeping while playing, and maybe the screen from locking.
+    pub stay_awake: StayAwake,
+    /// TOML or JSON theme file to color the app with, either a path or a file name in the
+    /// `themes` folder beside this file, or `None` for the built-in theme.
+    pub theme: Option<PathBuf>,
+    /// Dark or light built-in theme. Theme files are used as they are.
+    pub appearance: Appearance,
+    /// Tint accents and the now playing backdrop to match the playing track's cover art.
+    pub art_accent: bool,
+    /// Size of the whole interface, from 0.9 to 1.5 times the usual.
+    pub ui_scale: f32,
+    pub density: Density,
+    /// Font family for the interface, installed or in the `fonts` folder beside this file, or
+    /// `None` for the default.
+    pub font_family: Option<String>,
+    /// Base text size in pixels at the usual scale. Padding and row heights grow with it.
+    pub font_size: f32,
+    /// Volume to start at, from 0 to 1 or up to the boosted maximum, or `None` to start at the
+    /// one the last session ended with.
+    pub default_volume: Option<f64>,
+    /// Name of the audio output device to play to, or `None` for the system default.
+    pub output_device: Option<String>,
+    /// Look lyrics up online for tracks with no lyrics file or tags, keeping what's found.
+    pub online_lyrics: bool,
+    /// Base URLs of LRCLIB-compatible servers to look lyrics up on, in order.
+    pub lyrics_providers: Vec<String>,
+    pub visualizer: VisualizerStyle,
+    /// Most frames a second the visualizer draws, up to 30.
+    pub visualizer_fps: u32,
+    /// How slowly visualizer levels fall back after rising, from 0 for at once to 0.95.
+    pub visualizer_smoothing: f32,
+    /// Whether the sidebar and queue are shown, or were collapsed.
+    pub show_sidebar: bool,
+    pub show_queue: bool,
+    /// Widths in pixels the sidebar and queue were dragged to, or `None` for their share of
+    /// the window.
+    pub sidebar_width: Option<f32>,
+    pub queue_width: Option<f32>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            allow_overamplification: false,
+            mono: false,
+            balance: 0.0,
+            artist_rules: ArtistRules::default(),
+            collation: Collation::default(),
+            skip_silence: false,
+            exclusive_mode: false,
+            resume_threshold_minutes: 20,
+            verify_on_scan: false,
+            scan_depth: 8,
+            skip_hidden_folders: true,
+            preamp_db: 0.0,
+            romanize: false,
+            loud_start_protection: true,
+            loud_start_max_volume: 0.3,
+            prefer_remote: vec![],
+            shared_dir: None,
+            background_redraw_ms: 1000,
+            pause_on_other_audio: false,
+            resume_after_other_audio: true,
+            pause_on_disconnect: true,
+            show_loudness: false,
+            autofill: false,
+            low_memory: false,
+            low_memory_dismissed: false,
+            insights: false,
+            close_to_tray: false,
+            scrobbling: ScrobbleAccounts::default(),
+            stay_awake: StayAwake::default(),
+            theme: None,
+            appearance: Appearance::default(),
+            art_accent: false,
+            ui_scale: 1.0,
+            density: Density::default(),
+            font_family: None,
+            font_size: 16.0,
+            default_volume: None,
+            output_device: None,
+            online_lyrics: false,
+            lyrics_providers: vec![LRCLIB.to_string()],
+            visualizer: VisualizerStyle::default(),
+            visualizer_fps: MAX_VISUALIZER_FPS,
+            visualizer_smoothing: 0.6,
+            show_sidebar: true,
+            show_queue: true,
+            sidebar_width: None,
+            queue_width: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Read and write settings at `path` from now on. Only the first call counts.
+    pub fn use_file(path: PathBuf) {
+        let _ = SETTINGS_FILE.set(path);
+    }
+
+    /// `settings.toml` in the platform's config folder, unless another file was chosen with
+    /// [`Settings::use_file`]. The keymap and insights are kept beside it.
+    pub fn get_settings_file() -> Option<PathBuf> {
+        if let Some(path) = SETTINGS_FILE.get() {
+            return Some(path.clone());
+        }
+        let dirs = ProjectDirs::from("io.github", "anantnrg", "Reyvr")?;
+        let config_dir = dirs.config_dir();
+        if let Err(e) = fs::create_dir_all(config_dir) {
+            eprintln!("Could not create config directory: {}", e);
+            return None;
+        }
+        migrate(config_dir);
+        Some(config_dir.join("settings.toml"))
+    }
+
+    pub fn load() -> Self {
+        if let Some(file_path) = Self::get_settings_file() {
+            if file_path.exists() {
+                match fs::read_to_string(&file_path) {
+                    Ok(contents) => match toml::from_str::<Settings>(&contents) {
+                        Ok(settings) => settings.with_secrets(),
+                        Err(e) => {
+                            eprintln!("Failed to parse TOML: {}", e);
+                            Settings::default()
+                        }
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to read file: {}", e);
+                        Settings::default()
+                    }
+                }
+            } else {
+                Settings::default()
+            }
+        } else {
+            Settings::default()
+        }
+    }
+
+    /// Read the settings file like [`Settings::load`], but fail on a file that can't be read
+    /// or parsed rather than fall back to the defaults, for reloading it while running.
+    pub fn try_load() -> anyhow::Result<Self> {
+        let Some(file_path) = Self::get_settings_file() else {
+            return Ok(Settings::default());
+        };
+        if !file_path.exists() {
+            return Ok(Settings::default());
+        }
+        let contents = fs::read_to_string(&file_path)?;
+        toml::from_str::<Settings>(&contents)
+            .map(Settings::with_secrets)
+            .map_err(|e| anyhow!("Could not read {}: {e}", file_path.display()))
+    }
+
+    /// Fill the scrobbling secrets in from their own file. Secrets still in a settings file
+    /// from before they were kept apart are moved out of it.
+    fn with_secrets(mut self) -> Self {
+        let inline = self.scrobbling.has_secrets();
+        self.scrobbling.load_secrets();
+        if inline {
+            if let Err(e) = self.save() {
+                eprintln!("Could not move scrobbling secrets out of the settings: {e}");
+            }
+        }
+        self
+    }
+
+    /// Names of the settings that differ between `self` and `other`, as in the settings file.
+    pub fn changes(&self, other: &Settings) -> Vec<String> {
+        let (Ok(toml::Value::Table(before)), Ok(toml::Value::Table(after))) =
+            (toml::Value::try_from(self), toml::Value::try_from(other))
+        else {
+            return vec![];
+        };
+        let mut changed: Vec<String> = before
+            .keys()
+            .chain(after.keys())
+            .filter(|key| before.get(*key) != after.get(*key))
+            .cloned()
+            .collect();
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(file_path) = Self::get_settings_file() {
+            let toml_str = toml::to_string_pretty(self).expect("Failed to serialize Settings");
+            let mut file = fs::File::create(file_path)?;
+            file.write_all(toml_str.as_bytes())?;
+            self.scrobbling.save_secrets()?;
+        }
+        Ok(())
+    }
+
+    /// `text` as it should be displayed, romanized if that is turned on.
+    pub fn display(&self, text: &str) -> String {
+        if self.romanize {
+            text::romanize(text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Upper bound for the volume slider and volume keybinds.
+    pub fn max_volume(&self) -> f64 {
+        if self.allow_overamplification {
+            MAX_BOOSTED_VOLUME
+        } else {
+            1.0
+        }
+    }
+
+    /// Pixels in a rem, which text, padding and row heights are measured in, for the text size,
+    /// scale and density set.
+    pub fn rem_size(&self) -> f32 {
+        let density = match self.density {
+            Density::Compact => 0.875,
+            Density::Comfortable => 1.0,
+        };
+        self.font_size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE)
+            * self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+            * density
+    }
+}
+
+impl gpui::Global for Settings {}
+
+/// Move the files settings used to be kept in over to `config_dir` the first time it's used.
+/// The originals are removed once copied, as the music folder they were in is often readable
+/// by others and the settings held scrobbling secrets.
+fn migrate(config_dir: &Path) {
+    if config_dir.join("settings.toml").exists() {
+        return;
+    }
+    let Some(user_dirs) = UserDirs::new() else {
+        return;
+    };
+    let old_dir = user_dirs
+        .audio_dir()
+        .unwrap_or(user_dirs.home_dir())
+        .join("Kagi");
+    for name in MIGRATED_FILES {
+        let old = old_dir.join(name);
+        if !old.exists() {
+            continue;
+        }
+        let moved = fs::copy(&old, config_dir.join(name)).and_then(|_| fs::remove_file(&old));
+        if let Err(e) = moved {
+            eprintln!(
+                "Could not move {} to {}: {e}",
+                old.display(),
+                config_dir.display()
+            );
+        }
+    }
+}
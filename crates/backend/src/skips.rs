@@ -0,0 +1,116 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+};
+
+use bincode::config;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{playback::Track, session::Session, uri};
+
+/// Moving on within this many seconds of the end isn't a skip, so leaving during an outro, or
+/// the automatic advance once a track finishes, doesn't count against it.
+pub const SKIP_MARGIN: u64 = 10;
+
+/// A track from the "most skipped" view.
+#[derive(Clone)]
+pub struct SkippedTrack {
+    pub track: Track,
+    pub skips: usize,
+    /// Median position skipped at, in seconds.
+    pub typical_position: u64,
+}
+
+/// Where each track was skipped, in seconds, keyed by URI.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Skips {
+    pub tracks: HashMap<String, Vec<u64>>,
+}
+
+impl Skips {
+    pub fn get_skips_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("skips.bin"))
+    }
+
+    pub fn load() -> Skips {
+        let Some(file_path) = Self::get_skips_file() else {
+            return Skips::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return Skips::default();
+        };
+        match bincode::serde::decode_from_slice::<Skips, _>(&data, config::standard()) {
+            Ok((mut skips, _)) => {
+                skips.tracks = uri::normalize_keys(skips.tracks);
+                skips
+            }
+            Err(e) => {
+                eprintln!("Failed to decode skips: {}", e);
+                Skips::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_skips_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    /// Note that `track` was left at `position`, unless that was close enough to its end to
+    /// count as heard. Streams of unknown length are never counted.
+    pub fn record(&mut self, track: &Track, position: u64) {
+        if track.duration == 0 || position + SKIP_MARGIN >= track.duration {
+            return;
+        }
+        self.tracks
+            .entry(track.uri.clone())
+            .or_default()
+            .push(position);
+    }
+
+    pub fn count(&self, uri: &str) -> usize {
+        self.tracks.get(uri).map_or(0, |positions| positions.len())
+    }
+
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// URIs with their skip count and median skip position, most skipped first.
+    pub fn most_skipped(&self) -> Vec<(String, usize, u64)> {
+        let mut skipped: Vec<(String, usize, u64)> = self
+            .tracks
+            .iter()
+            .filter(|(_, positions)| !positions.is_empty())
+            .map(|(uri, positions)| {
+                let mut positions = positions.clone();
+                positions.sort();
+                (uri.clone(), positions.len(), positions[positions.len() / 2])
+            })
+            .collect();
+        skipped.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        skipped
+    }
+
+    /// Shuffle `tracks` so often-skipped ones tend to come later: a track skipped n times is
+    /// 1 / (n + 1) as likely as an unskipped one to be picked for any given spot.
+    pub fn shuffle(&self, tracks: &mut Vec<Track>, rng: &mut impl Rng) {
+        // Weighted random order: sort by u^(1/weight) for uniform u, highest first.
+        let mut keyed: Vec<(f64, Track)> = tracks
+            .drain(..)
+            .map(|track| {
+                let weight = 1.0 / (self.count(&track.uri) as f64 + 1.0);
+                (rng.random::<f64>().powf(1.0 / weight), track)
+            })
+            .collect();
+        keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+        tracks.extend(keyed.into_iter().map(|(_, track)| track));
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bincode::config;
+use serde::{Deserialize, Serialize};
+
+use crate::{library::Library, playback::Track, ratings::Ratings, session::Session, uri};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The part of a track a rule looks at.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Field {
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Year,
+    Rating,
+    Plays,
+    /// Days since the file came into the library.
+    DaysSinceAdded,
+}
+
+impl Field {
+    pub const ALL: [Field; 8] = [
+        Field::Title,
+        Field::Artist,
+        Field::Album,
+        Field::Genre,
+        Field::Year,
+        Field::Rating,
+        Field::Plays,
+        Field::DaysSinceAdded,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Title => "Title",
+            Field::Artist => "Artist",
+            Field::Album => "Album",
+            Field::Genre => "Genre",
+            Field::Year => "Year",
+            Field::Rating => "Rating",
+            Field::Plays => "Plays",
+            Field::DaysSinceAdded => "Days since added",
+        }
+    }
+
+    pub fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            Field::Year | Field::Rating | Field::Plays | Field::DaysSinceAdded
+        )
+    }
+
+    /// The comparisons that make sense for this field.
+    pub fn comparisons(self) -> &'static [Comparison] {
+        if self.is_numeric() {
+            &[
+                Comparison::Is,
+                Comparison::IsNot,
+                Comparison::GreaterThan,
+                Comparison::LessThan,
+                Comparison::AtLeast,
+                Comparison::AtMost,
+            ]
+        } else {
+            &[Comparison::Is, Comparison::IsNot, Comparison::Contains]
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Comparison {
+    Is,
+    IsNot,
+    Contains,
+    GreaterThan,
+    LessThan,
+    AtLeast,
+    AtMost,
+}
+
+impl Comparison {
+    pub fn label(self) -> &'static str {
+        match self {
+            Comparison::Is => "is",
+            Comparison::IsNot => "is not",
+            Comparison::Contains => "contains",
+            Comparison::GreaterThan => ">",
+            Comparison::LessThan => "<",
+            Comparison::AtLeast => ">=",
+            Comparison::AtMost => "<=",
+        }
+    }
+}
+
+/// One condition of a smart playlist, such as genre is "jazz" or year > 1990. Text compares
+/// without regard to case; numeric fields ignore a value that isn't a number.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub field: Field,
+    pub comparison: Comparison,
+    pub value: String,
+}
+
+impl Rule {
+    fn matches(&self, track: &Track, plays: u32, rating: u32, now: u64) -> bool {
+        if self.field.is_numeric() {
+            let Ok(value) = self.value.trim().parse::<u64>() else {
+                return true;
+            };
+            let actual = match self.field {
+                Field::Year => track.year as u64,
+                Field::Rating => rating as u64,
+                Field::Plays => plays as u64,
+                // Files of unknown age never count as recently added.
+                _ if track.added == 0 => u64::MAX,
+                _ => now.saturating_sub(track.added) / SECONDS_PER_DAY,
+            };
+            return match self.comparison {
+                Comparison::Is | Comparison::Contains => actual == value,
+                Comparison::IsNot => actual != value,
+                Comparison::GreaterThan => actual > value,
+                Comparison::LessThan => actual < value,
+                Comparison::AtLeast => actual >= value,
+                Comparison::AtMost => actual <= value,
+            };
+        }
+
+        let value = self.value.trim().to_lowercase();
+        let texts: Vec<String> = match self.field {
+            Field::Title => vec![track.title.to_lowercase()],
+            Field::Artist => track.artists.iter().map(|a| a.to_lowercase()).collect(),
+            Field::Album => vec![track.album.to_lowercase()],
+            _ => vec![track.genre.to_lowercase()],
+        };
+        match self.comparison {
+            Comparison::Contains => texts.iter().any(|text| text.contains(&value)),
+            Comparison::IsNot => texts.iter().all(|text| *text != value),
+            _ => texts.iter().any(|text| *text == value),
+        }
+    }
+}
+
+/// A playlist made of every library track matching all of its rules, worked out afresh
+/// whenever the library changes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SmartPlaylist {
+    pub name: String,
+    pub rules: Vec<Rule>,
+}
+
+impl SmartPlaylist {
+    /// The library tracks that currently match, in library order.
+    pub fn tracks(&self, library: &Library, plays: &PlayCounts, ratings: &Ratings) -> Vec<Track> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        library
+            .tracks()
+            .iter()
+            .filter(|track| {
+                let count = plays.get(&track.uri);
+                let rating = ratings.get(track);
+                self.rules
+                    .iter()
+                    .all(|rule| rule.matches(track, count, rating, now))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct SmartPlaylists {
+    pub playlists: Vec<SmartPlaylist>,
+}
+
+impl SmartPlaylists {
+    pub fn get_smart_playlists_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("smart_playlists.bin"))
+    }
+
+    pub fn load() -> SmartPlaylists {
+        let Some(file_path) = Self::get_smart_playlists_file() else {
+            return SmartPlaylists::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return SmartPlaylists::default();
+        };
+        match bincode::serde::decode_from_slice(&data, config::standard()) {
+            Ok((playlists, _)) => playlists,
+            Err(e) => {
+                eprintln!("Failed to decode smart playlists: {}", e);
+                SmartPlaylists::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_smart_playlists_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+}
+
+/// How many times each track has been played, keyed by URI.
+#[derive(Default, Serialize, Deserialize)]
+pub struct PlayCounts {
+    pub tracks: HashMap<String, u32>,
+}
+
+impl PlayCounts {
+    pub fn get_play_counts_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("plays.bin"))
+    }
+
+    pub fn load() -> PlayCounts {
+        let Some(file_path) = Self::get_play_counts_file() else {
+            return PlayCounts::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return PlayCounts::default();
+        };
+        match bincode::serde::decode_from_slice::<PlayCounts, _>(&data, config::standard()) {
+            Ok((mut plays, _)) => {
+                plays.tracks = uri::normalize_keys(plays.tracks);
+                plays
+            }
+            Err(e) => {
+                eprintln!("Failed to decode play counts: {}", e);
+                PlayCounts::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_play_counts_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, uri: &str) -> u32 {
+        self.tracks.get(uri).copied().unwrap_or(0)
+    }
+
+    pub fn record(&mut self, uri: &str) {
+        *self.tracks.entry(uri.to_string()).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn rule(field: Field, comparison: Comparison, value: &str) -> Rule {
+        Rule {
+            field,
+            comparison,
+            value: value.to_string(),
+        }
+    }
+
+    fn track() -> Track {
+        Track {
+            artists: vec!["Miles Davis".to_string(), "Bill Evans".to_string()],
+            genre: "Jazz".to_string(),
+            year: 1959,
+            added: NOW - 10 * SECONDS_PER_DAY,
+            ..testing::track("Miles Davis", "Kind of Blue", "Blue in Green")
+        }
+    }
+
+    fn matches(rule: &Rule) -> bool {
+        rule.matches(&track(), 3, 4, NOW)
+    }
+
+    #[test]
+    fn text_compares_without_regard_to_case() {
+        assert!(matches(&rule(Field::Genre, Comparison::Is, " jazz ")));
+        assert!(!matches(&rule(Field::Genre, Comparison::IsNot, "JAZZ")));
+        assert!(matches(&rule(Field::Title, Comparison::Contains, "GREEN")));
+        assert!(matches(&rule(
+            Field::Album,
+            Comparison::IsNot,
+            "Blue Train"
+        )));
+    }
+
+    #[test]
+    fn artist_rules_look_at_every_artist() {
+        assert!(matches(&rule(Field::Artist, Comparison::Is, "bill evans")));
+        assert!(matches(&rule(Field::Artist, Comparison::Contains, "davis")));
+        assert!(!matches(&rule(
+            Field::Artist,
+            Comparison::IsNot,
+            "Bill Evans"
+        )));
+        assert!(matches(&rule(Field::Artist, Comparison::IsNot, "Coltrane")));
+    }
+
+    #[test]
+    fn numbers_compare_as_numbers() {
+        assert!(matches(&rule(Field::Year, Comparison::GreaterThan, "1950")));
+        assert!(!matches(&rule(Field::Year, Comparison::LessThan, "1959")));
+        assert!(matches(&rule(Field::Year, Comparison::AtMost, "1959")));
+        assert!(matches(&rule(Field::Plays, Comparison::AtLeast, "3")));
+        assert!(matches(&rule(Field::Rating, Comparison::Is, "4")));
+        assert!(matches(&rule(Field::Rating, Comparison::IsNot, "5")));
+        assert!(matches(&rule(Field::DaysSinceAdded, Comparison::Is, "10")));
+    }
+
+    #[test]
+    fn numeric_rules_ignore_values_that_are_not_numbers() {
+        assert!(matches(&rule(Field::Year, Comparison::Is, "fifties")));
+        assert!(matches(&rule(Field::Plays, Comparison::GreaterThan, "")));
+    }
+
+    #[test]
+    fn files_of_unknown_age_are_never_recently_added() {
+        let rule = rule(Field::DaysSinceAdded, Comparison::AtMost, "30");
+        let unknown = Track {
+            added: 0,
+            ..track()
+        };
+        assert!(matches(&rule));
+        assert!(!rule.matches(&unknown, 0, 0, NOW));
+    }
+}
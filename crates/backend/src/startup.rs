@@ -0,0 +1,166 @@
+//! Options the app is started with.
+//!
+//! ```text
+//! reyvr [--config FILE] [--backend NAME] [--headless] [--volume LEVEL] [PATH | LINK]...
+//! ```
+//!
+//! `reyvr play`, `reyvr status` and the other subcommands control an instance that's already
+//! running instead of starting one.
+//!
+//! Each option can also come from a `REYVR_*` environment variable. Like everything else in
+//! [`Settings`](crate::settings::Settings), the command line wins over the environment, which
+//! wins over the settings file, which wins over the defaults.
+
+use std::{env, fs, path::PathBuf};
+
+use anyhow::anyhow;
+
+use crate::{link::PlayLink, playback::is_audio, settings::MAX_BOOSTED_VOLUME, uri};
+
+pub const USAGE: &str = "Usage: reyvr [--config FILE] [--backend NAME] [--headless] \
+                         [--volume LEVEL] [PATH | reyvr://LINK]...";
+
+/// Environment variables read by [`StartupOptions::parse`].
+pub const CONFIG_ENV: &str = "REYVR_CONFIG";
+pub const BACKEND_ENV: &str = "REYVR_BACKEND";
+pub const HEADLESS_ENV: &str = "REYVR_HEADLESS";
+pub const VOLUME_ENV: &str = "REYVR_VOLUME";
+
+/// Audio backends the app can play through.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BackendKind {
+    #[default]
+    Gstreamer,
+}
+
+impl BackendKind {
+    pub const ALL: [BackendKind; 1] = [BackendKind::Gstreamer];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::Gstreamer => "gstreamer",
+        }
+    }
+
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or(anyhow!(
+                "Unknown backend {name:?}, expected one of: {}",
+                Self::ALL.map(|kind| kind.name()).join(", ")
+            ))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StartupOptions {
+    /// Settings file to read and write instead of the one in the config folder.
+    pub config: Option<PathBuf>,
+    pub backend: BackendKind,
+    /// Run without a window, controlled through `reyvr-cli` and links.
+    pub headless: bool,
+    /// Volume to start at instead of the one the last session ended with.
+    pub volume: Option<f64>,
+    /// Audio files to play, from paths or `file:` URIs, with folders replaced by the audio files
+    /// directly in them.
+    pub paths: Vec<PathBuf>,
+    /// A `reyvr://` link the app was opened from.
+    pub link: Option<PlayLink>,
+}
+
+impl StartupOptions {
+    /// Read the options from the process's arguments and environment.
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::parse(env::args().skip(1), |name| env::var(name).ok())
+    }
+
+    /// Read the options from `args`, without the program name, over the variables `var` finds.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Self> {
+        let mut options = StartupOptions {
+            config: var(CONFIG_ENV).map(PathBuf::from),
+            backend: var(BACKEND_ENV)
+                .map(|name| BackendKind::parse(&name))
+                .transpose()?
+                .unwrap_or_default(),
+            headless: var(HEADLESS_ENV).is_some_and(|value| is_true(&value)),
+            volume: var(VOLUME_ENV)
+                .map(|value| parse_volume(&value))
+                .transpose()?,
+            ..Default::default()
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Both `--volume 0.5` and `--volume=0.5`.
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or(anyhow!("Missing value for {flag}\n{USAGE}"))
+            };
+            match flag {
+                "--config" => options.config = Some(PathBuf::from(value()?)),
+                "--backend" => options.backend = BackendKind::parse(&value()?)?,
+                "--headless" => options.headless = true,
+                "--volume" => options.volume = Some(parse_volume(&value()?)?),
+                "-h" | "--help" => return Err(anyhow!("{USAGE}")),
+                _ if flag.starts_with("--") => {
+                    return Err(anyhow!("Unknown option {flag}\n{USAGE}"));
+                }
+                _ => match PlayLink::parse(&arg) {
+                    Ok(link) => options.link = Some(link),
+                    // File managers hand over `file:` URIs as often as paths.
+                    Err(_) => {
+                        let path = uri::to_path(&arg).unwrap_or_else(|| PathBuf::from(&arg));
+                        options.paths.extend(audio_files(path)?);
+                    }
+                },
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn is_true(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// A volume from 0 to the boosted maximum, as a fraction like `0.5` or a percentage like `50%`.
+fn parse_volume(value: &str) -> anyhow::Result<f64> {
+    let volume = match value.trim().strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|percent| percent / 100.0),
+        None => value.trim().parse::<f64>(),
+    }
+    .map_err(|_| anyhow!("Invalid volume {value:?}"))?;
+    if !(0.0..=MAX_BOOSTED_VOLUME).contains(&volume) {
+        return Err(anyhow!(
+            "Volume {value} is out of range, expected 0 to {MAX_BOOSTED_VOLUME}"
+        ));
+    }
+    Ok(volume)
+}
+
+/// `path` made absolute, or the audio files directly in it, by name, when it's a folder.
+fn audio_files(path: PathBuf) -> anyhow::Result<Vec<PathBuf>> {
+    let path = fs::canonicalize(&path).map_err(|e| anyhow!("Could not open {path:?}: {e}"))?;
+    if !path.is_dir() {
+        return Ok(vec![path]);
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(&path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.is_file() && is_audio(file))
+        .collect();
+    files.sort();
+    Ok(files)
+}
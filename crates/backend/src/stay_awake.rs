@@ -0,0 +1,65 @@
+//! Keeping the computer awake while music plays: through logind and the screensaver service
+//! over D-Bus on Linux, `SetThreadExecutionState` on Windows and IOKit power assertions on
+//! macOS.
+
+use keepawake::KeepAwake;
+use serde::{Deserialize, Serialize};
+
+/// How much staying awake while playing keeps from happening, set from the settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum StayAwake {
+    /// Let the computer idle and sleep as usual.
+    Off,
+    /// Keep the computer from sleeping, but let the screen blank and lock.
+    #[default]
+    System,
+    /// Keep the screen on and unlocked as well.
+    Screen,
+}
+
+/// Holds the platform's inhibitor while playing and lets go of it otherwise.
+#[derive(Default)]
+pub struct Inhibitor {
+    mode: StayAwake,
+    playing: bool,
+    /// Released when dropped.
+    held: Option<KeepAwake>,
+}
+
+impl Inhibitor {
+    pub fn set_mode(&mut self, mode: StayAwake) -> anyhow::Result<()> {
+        if mode != self.mode {
+            self.mode = mode;
+            self.held = None;
+        }
+        self.update()
+    }
+
+    pub fn set_playing(&mut self, playing: bool) -> anyhow::Result<()> {
+        if playing == self.playing {
+            return Ok(());
+        }
+        self.playing = playing;
+        self.update()
+    }
+
+    fn update(&mut self) -> anyhow::Result<()> {
+        if !self.playing || self.mode == StayAwake::Off {
+            self.held = None;
+            return Ok(());
+        }
+        if self.held.is_none() {
+            self.held = Some(
+                keepawake::Builder::default()
+                    .idle(true)
+                    .sleep(true)
+                    .display(self.mode == StayAwake::Screen)
+                    .reason("Playing music")
+                    .app_name("Reyvr")
+                    .app_reverse_domain("io.github.anantnrg.reyvr")
+                    .create()?,
+            );
+        }
+        Ok(())
+    }
+}
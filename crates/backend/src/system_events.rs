@@ -0,0 +1,171 @@
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, ChildStdout, Command, Stdio},
+    thread,
+};
+
+use anyhow::anyhow;
+use smol::channel::Sender;
+
+/// Something the system did that playback should stop for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SystemEvent {
+    /// Sound moved off headphones or a Bluetooth device, which was unplugged or disconnected,
+    /// so it would otherwise come out of the speakers.
+    Unplugged,
+    /// The system is about to suspend.
+    Suspending,
+}
+
+/// Watches for the audio output being unplugged and for the system suspending, and reports
+/// either as a [`SystemEvent`]. The watch stops when this is dropped.
+///
+/// On Linux outputs are followed through `pactl`, like [`OtherAudio`](crate::other_audio),
+/// and suspending through logind's `PrepareForSleep` signal via `gdbus`. Other platforms have
+/// no monitor yet, so [`SystemEvents::start`] fails there.
+pub struct SystemEvents {
+    watchers: Vec<Child>,
+}
+
+impl SystemEvents {
+    /// Start watching. Succeeds as long as either kind of event can be watched for.
+    pub fn start(events: Sender<SystemEvent>) -> anyhow::Result<Self> {
+        if !cfg!(target_os = "linux") {
+            return Err(anyhow!(
+                "Watching for unplugged outputs isn't supported on this platform"
+            ));
+        }
+        let mut watchers = vec![];
+        let mut errors = vec![];
+        for started in [watch_output(events.clone()), watch_sleep(events)] {
+            match started {
+                Ok(watcher) => watchers.push(watcher),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        if watchers.is_empty() {
+            return Err(anyhow!(errors.join("; ")));
+        }
+        for error in errors {
+            eprintln!("{error}");
+        }
+        Ok(SystemEvents { watchers })
+    }
+}
+
+impl Drop for SystemEvents {
+    fn drop(&mut self) {
+        for watcher in &mut self.watchers {
+            let _ = watcher.kill();
+            let _ = watcher.wait();
+        }
+    }
+}
+
+fn spawn(program: &str, args: &[&str]) -> anyhow::Result<(Child, ChildStdout)> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("Could not run {program}: {e}"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or(anyhow!("Could not read {program} events"))?;
+    Ok((child, stdout))
+}
+
+/// Report [`SystemEvent::Unplugged`] whenever the output changes from a private one to one
+/// that isn't. Plugging headphones in changes the output too, but that's no reason to pause.
+fn watch_output(events: Sender<SystemEvent>) -> anyhow::Result<Child> {
+    let (child, lines) = spawn("pactl", &["subscribe"])?;
+    thread::spawn(move || {
+        let mut output = current_output();
+        for line in BufReader::new(lines).lines().map_while(Result::ok) {
+            // Unplugging switches the sink's port, disconnecting removes the sink, and both
+            // can change the server's default sink.
+            if !line.contains("on sink #") && !line.contains("on server") {
+                continue;
+            }
+            let now = current_output();
+            if now == output {
+                continue;
+            }
+            let unplugged = output.as_deref().is_some_and(is_private)
+                && !now.as_deref().is_some_and(is_private);
+            output = now;
+            if unplugged && events.send_blocking(SystemEvent::Unplugged).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(child)
+}
+
+/// The default sink and its active port, as `sink:port`.
+fn current_output() -> Option<String> {
+    let sink = Command::new("pactl")
+        .arg("get-default-sink")
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let sink = String::from_utf8_lossy(&sink.stdout).trim().to_string();
+    let sinks = Command::new("pactl")
+        .args(["list", "sinks"])
+        // The listing is translated, and the lines below are matched in English.
+        .env("LC_ALL", "C")
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let listing = String::from_utf8_lossy(&sinks.stdout);
+    let port = listing
+        .split("Sink #")
+        .find(|entry| {
+            entry
+                .lines()
+                .any(|line| line.trim() == format!("Name: {sink}"))
+        })
+        .and_then(|entry| {
+            entry
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("Active Port: "))
+        })
+        .unwrap_or_default();
+    Some(format!("{sink}:{port}"))
+}
+
+/// Whether `output` is one only the listener hears: headphones, a headset or a Bluetooth
+/// device.
+fn is_private(output: &str) -> bool {
+    let output = output.to_lowercase();
+    ["headphone", "headset", "bluez", "bluetooth"]
+        .iter()
+        .any(|kind| output.contains(kind))
+}
+
+/// Report [`SystemEvent::Suspending`] when logind announces the system is going to sleep.
+fn watch_sleep(events: Sender<SystemEvent>) -> anyhow::Result<Child> {
+    let (child, lines) = spawn(
+        "gdbus",
+        &[
+            "monitor",
+            "--system",
+            "--dest",
+            "org.freedesktop.login1",
+            "--object-path",
+            "/org/freedesktop/login1",
+        ],
+    )?;
+    thread::spawn(move || {
+        for line in BufReader::new(lines).lines().map_while(Result::ok) {
+            // Sent with `true` going to sleep and `false` waking up.
+            if line.contains("PrepareForSleep (true")
+                && events.send_blocking(SystemEvent::Suspending).is_err()
+            {
+                return;
+            }
+        }
+    });
+    Ok(child)
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+};
+
+use bincode::config;
+use serde::{Deserialize, Serialize};
+
+use crate::{playback::Track, session::Session, uri};
+
+/// Tags changed in the app, for one track or many at once. Fields left as `None` keep what the
+/// file says.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagEdit {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+}
+
+impl TagEdit {
+    pub fn is_empty(&self) -> bool {
+        *self == TagEdit::default()
+    }
+
+    pub fn apply(&self, track: &mut Track) {
+        if let Some(artist) = &self.artist {
+            track.artists = vec![artist.clone()];
+        }
+        if let Some(album) = &self.album {
+            track.album = album.clone();
+        }
+        if let Some(genre) = &self.genre {
+            track.genre = genre.clone();
+        }
+        if let Some(year) = self.year {
+            track.year = year;
+        }
+    }
+
+    /// Take on the fields `other` sets, keeping the rest.
+    fn merge(&mut self, other: &TagEdit) {
+        if other.artist.is_some() {
+            self.artist = other.artist.clone();
+        }
+        if other.album.is_some() {
+            self.album = other.album.clone();
+        }
+        if other.genre.is_some() {
+            self.genre = other.genre.clone();
+        }
+        if other.year.is_some() {
+            self.year = other.year;
+        }
+    }
+}
+
+/// Tag edits by URI. Like ratings given in the app, they take the place of the file's own tags
+/// wherever the track shows up, and the files themselves are left alone.
+#[derive(Default, Serialize, Deserialize)]
+pub struct TagEdits {
+    pub tracks: HashMap<String, TagEdit>,
+}
+
+impl TagEdits {
+    pub fn get_tags_file() -> Option<PathBuf> {
+        Session::get_session_file().map(|file| file.with_file_name("tags.bin"))
+    }
+
+    pub fn load() -> TagEdits {
+        let Some(file_path) = Self::get_tags_file() else {
+            return TagEdits::default();
+        };
+        let Ok(data) = fs::read(file_path) else {
+            return TagEdits::default();
+        };
+        match bincode::serde::decode_from_slice::<TagEdits, _>(&data, config::standard()) {
+            Ok((mut edits, _)) => {
+                edits.tracks = uri::normalize_keys(edits.tracks);
+                edits
+            }
+            Err(e) => {
+                eprintln!("Failed to decode tag edits: {}", e);
+                TagEdits::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(file_path) = Self::get_tags_file() {
+            let serialized = bincode::serde::encode_to_vec(self, config::standard())?;
+            let mut file = File::create(file_path)?;
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
+    /// Record `edit` for every track in `uris`, on top of their earlier edits.
+    pub fn edit(&mut self, uris: &[String], edit: &TagEdit) {
+        for uri in uris {
+            self.tracks.entry(uri.clone()).or_default().merge(edit);
+        }
+    }
+
+    /// Put the edited tags on whichever of `tracks` have any.
+    pub fn apply(&self, tracks: &mut [Track]) {
+        if self.tracks.is_empty() {
+            return;
+        }
+        for track in tracks.iter_mut() {
+            if let Some(edit) = self.tracks.get(&track.uri) {
+                edit.apply(track);
+            }
+        }
+    }
+}
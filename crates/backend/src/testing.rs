@@ -0,0 +1,17 @@
+//! Helpers shared by the unit tests.
+
+use crate::{playback::Track, uri};
+
+/// A three-minute track titled `title` by `artist` on `album`, at a file URI made from the
+/// three.
+pub fn track(artist: &str, album: &str, title: &str) -> Track {
+    let file = uri::encode_relative(&format!("{artist}/{album}/{title}.flac"));
+    Track {
+        title: title.to_string(),
+        artists: vec![artist.to_string()],
+        album: album.to_string(),
+        uri: format!("file:///music/{file}"),
+        duration: 180,
+        ..Track::default()
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    cmp::Ordering,
+    sync::{LazyLock, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Collation every list in the app is sorted with, set from the settings.
+static COLLATION: LazyLock<RwLock<Collation>> = LazyLock::new(Default::default);
+
+/// How names are put in order: in library views, sorted queues and ties between equally good
+/// search results.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Collation {
+    /// Sort "The Beatles" under B. Matched case-insensitively, as whole leading words.
+    pub articles: Vec<String>,
+    /// Sort "Track 2" before "Track 10" by comparing runs of digits as numbers.
+    pub numeric: bool,
+    /// Sort non-Latin names by their romanized form, so "Кино" sits with the Ks, rather than
+    /// after every Latin name.
+    pub romanize: bool,
+}
+
+impl Default for Collation {
+    fn default() -> Self {
+        Collation {
+            articles: vec!["The".into(), "A".into(), "An".into()],
+            numeric: true,
+            romanize: true,
+        }
+    }
+}
+
+impl Collation {
+    /// Compare two names the way a reader expects them to be listed: case-insensitively and
+    /// as these options say, falling back to code point order between names that only differ
+    /// in case or accents.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let (key_a, key_b) = (self.key(a), self.key(b));
+        let (key_a, key_b) = (self.strip_article(&key_a), self.strip_article(&key_b));
+        let order = if self.numeric {
+            compare_numeric(key_a, key_b)
+        } else {
+            key_a.cmp(key_b)
+        };
+        order.then_with(|| a.cmp(b))
+    }
+
+    fn key(&self, text: &str) -> String {
+        if self.romanize {
+            romanize(text).to_lowercase()
+        } else {
+            text.trim().to_lowercase()
+        }
+    }
+
+    /// `key` without a leading article, unless the article is all there is.
+    fn strip_article<'a>(&self, key: &'a str) -> &'a str {
+        self.articles
+            .iter()
+            .find_map(|article| {
+                let rest = key.strip_prefix(&article.to_lowercase())?;
+                let rest = rest.strip_prefix(' ')?.trim_start();
+                (!rest.is_empty()).then_some(rest)
+            })
+            .unwrap_or(key)
+    }
+}
+
+/// Use `collation` for every comparison from now on.
+pub fn set_collation(collation: Collation) {
+    *COLLATION.write().expect("Could not lock collation") = collation;
+}
+
+/// Latin-script rendering of `text`, e.g. "Кино" becomes "Kino". ASCII text is returned as is.
+pub fn romanize(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        deunicode::deunicode(text).trim().to_string()
+    }
+}
+
+/// Compare two names with the collation in use. See [`Collation::compare`].
+pub fn collate(a: &str, b: &str) -> Ordering {
+    COLLATION
+        .read()
+        .expect("Could not lock collation")
+        .compare(a, b)
+}
+
+/// Compare `a` and `b` character by character, except that runs of digits compare by the
+/// number they spell.
+fn compare_numeric(mut a: &str, mut b: &str) -> Ordering {
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (digits_a, rest_a) = split_digits(a);
+            let (digits_b, rest_b) = split_digits(b);
+            let (number_a, number_b) = (
+                digits_a.trim_start_matches('0'),
+                digits_b.trim_start_matches('0'),
+            );
+            // Without leading zeros, the longer number is the bigger one.
+            let order = number_a
+                .len()
+                .cmp(&number_b.len())
+                .then_with(|| number_a.cmp(number_b));
+            if order != Ordering::Equal {
+                return order;
+            }
+            (a, b) = (rest_a, rest_b);
+        } else if x != y {
+            return x.cmp(&y);
+        } else {
+            (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+        }
+    }
+}
+
+/// The digits `text` starts with, and what comes after them.
+fn split_digits(text: &str) -> (&str, &str) {
+    text.split_at(
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len()),
+    )
+}
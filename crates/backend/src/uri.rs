@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Characters left as they are in the path of a `file://` URI: RFC 3986 unreserved and
+/// sub-delimiters, plus the separators. Everything else is percent-encoded.
+const KEEP: &[u8] = b"-._~!$&'()*+,;=:@/";
+/// Characters left as they are in a query value, where `&`, `=` and `+` have a meaning of
+/// their own.
+const KEEP_IN_QUERY: &[u8] = b"-._~!$'()*,:@/";
+
+/// The playback URI of the file at `path`. Symlinks and `..` are resolved, also for files that
+/// no longer exist, so every way of reaching a file gives the same URI. Windows drive paths
+/// become `file:///C:/...` and UNC shares `file://server/share/...`.
+pub fn from_path(path: &Path) -> String {
+    encode_path(&resolve(path))
+}
+
+/// The local file a `file:` URI points to, or `None` for any other scheme. Accepts the
+/// encoded URIs made by [`from_path`] as well as the unencoded ones of older caches.
+pub fn to_path(uri: &str) -> Option<PathBuf> {
+    let rest = decode(uri.strip_prefix("file:")?);
+    let local = match rest.strip_prefix("//") {
+        // Older caches put a fourth slash in front of Unix paths.
+        Some(after) if after.starts_with('/') => after.trim_start_matches('/').to_string(),
+        Some(after) => {
+            let (host, path) = after.split_once('/').unwrap_or((after, ""));
+            if host.is_empty() || host.eq_ignore_ascii_case("localhost") {
+                path.to_string()
+            } else {
+                let share = format!("//{host}/{path}");
+                return Some(PathBuf::from(if cfg!(windows) {
+                    share.replace('/', "\\")
+                } else {
+                    share
+                }));
+            }
+        }
+        None => rest.trim_start_matches('/').to_string(),
+    };
+    if is_drive(&local) {
+        Some(PathBuf::from(local))
+    } else {
+        Some(PathBuf::from(format!("/{local}")))
+    }
+}
+
+/// `uri` in the form [`from_path`] gives, so tracks and keys saved before URIs were
+/// normalized still match. URIs already in that form, and other schemes, come back as is
+/// without touching the disk.
+pub fn normalize(uri: &str) -> String {
+    let Some(path) = to_path(uri) else {
+        return uri.to_string();
+    };
+    if encode_path(&path) == uri {
+        uri.to_string()
+    } else {
+        from_path(&path)
+    }
+}
+
+/// `map` with every URI key normalized. When two old keys name the same file, one of them
+/// is kept.
+pub fn normalize_keys<V>(map: HashMap<String, V>) -> HashMap<String, V> {
+    map.into_iter()
+        .map(|(uri, value)| (normalize(&uri), value))
+        .collect()
+}
+
+/// Move what `map` holds under the old URIs in `moved` to their new ones, leaving anything
+/// already stored under a new URI alone.
+pub fn rebind_keys<V>(map: &mut HashMap<String, V>, moved: &HashMap<String, String>) {
+    for (old, new) in moved {
+        if let Some(value) = map.remove(old) {
+            map.entry(new.clone()).or_insert(value);
+        }
+    }
+}
+
+/// `path` made absolute with links resolved. For a path that doesn't exist, the deepest
+/// existing folder above it is resolved and the rest joined back on.
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut missing = vec![];
+    let mut existing = absolute.as_path();
+    loop {
+        if let Ok(resolved) = fs::canonicalize(existing) {
+            return missing
+                .iter()
+                .rev()
+                .fold(resolved, |path, part| path.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return absolute,
+        }
+    }
+}
+
+fn encode_path(path: &Path) -> String {
+    let text = path.to_string_lossy().replace('\\', "/");
+    // Canonical paths on Windows come back in verbatim form.
+    let text = match text.strip_prefix("//?/") {
+        Some(verbatim) => match verbatim.strip_prefix("UNC/") {
+            Some(share) => format!("//{share}"),
+            None => verbatim.to_string(),
+        },
+        None => text,
+    };
+    match text.strip_prefix("//") {
+        // The server of a UNC share is the URI's host.
+        Some(share) => format!("file://{}", encode(share, KEEP)),
+        None => format!("file:///{}", encode(text.trim_start_matches('/'), KEEP)),
+    }
+}
+
+/// Whether `path` starts with a Windows drive letter, like `C:/`.
+fn is_drive(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// A relative path, with `/` between folders, percent-encoded like the path of a `file://` URI.
+pub fn encode_relative(path: &str) -> String {
+    encode(path, KEEP)
+}
+
+/// `text` percent-encoded for use as a value in a URL's query.
+pub fn encode_query(text: &str) -> String {
+    encode(text, KEEP_IN_QUERY)
+}
+
+fn encode(text: &str, keep: &[u8]) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || keep.contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Undo percent-encoding. A `%` not followed by two hex digits is kept as it is, as older
+/// caches have file names with bare percent signs.
+pub fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_keeps_bare_percent_signs() {
+        assert_eq!(decode("a%20b"), "a b");
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("50%off"), "50%off");
+        assert_eq!(decode("%C3%A9"), "é");
+    }
+
+    #[test]
+    fn encode_keeps_separators_and_sub_delimiters() {
+        assert_eq!(encode_relative("a b/c&d#e.flac"), "a%20b/c&d%23e.flac");
+        assert_eq!(encode_query("a&b=c+d"), "a%26b%3Dc%2Bd");
+    }
+
+    #[test]
+    fn to_path_reads_hosts_drives_and_older_uris() {
+        assert_eq!(
+            to_path("file:///music/a%20b.flac"),
+            Some("/music/a b.flac".into())
+        );
+        assert_eq!(
+            to_path("file:////music/a.flac"),
+            Some("/music/a.flac".into())
+        );
+        assert_eq!(
+            to_path("file:///music/50%.flac"),
+            Some("/music/50%.flac".into())
+        );
+        assert_eq!(
+            to_path("file://localhost/music/a.flac"),
+            Some("/music/a.flac".into())
+        );
+        assert_eq!(
+            to_path("file:///C:/Music/a.flac"),
+            Some("C:/Music/a.flac".into())
+        );
+        assert_eq!(to_path("https://example.com/a.mp3"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn to_path_keeps_the_server_of_a_share() {
+        assert_eq!(
+            to_path("file://server/share/a.flac"),
+            Some("//server/share/a.flac".into())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn normalize_gives_the_uri_from_path_would() {
+        let path = Path::new("/reyvr-missing/Some Album/01 #1 & more.flac");
+        let uri = from_path(path);
+        assert_eq!(
+            uri,
+            "file:///reyvr-missing/Some%20Album/01%20%231%20&%20more.flac"
+        );
+        assert_eq!(normalize(&uri), uri);
+        assert_eq!(
+            normalize("file:////reyvr-missing/Some Album/01 #1 & more.flac"),
+            uri
+        );
+        assert_eq!(
+            normalize("file:///reyvr-missing/Some%20Album/01%20%231%20%26%20more.flac"),
+            uri
+        );
+        assert_eq!(to_path(&uri).as_deref(), Some(path));
+        assert_eq!(
+            normalize("https://example.com/a.mp3"),
+            "https://example.com/a.mp3"
+        );
+    }
+
+    #[test]
+    fn rebind_keys_keeps_values_already_at_the_new_uri() {
+        let mut map = HashMap::from([
+            ("old".to_string(), 1),
+            ("gone".to_string(), 2),
+            ("taken".to_string(), 3),
+        ]);
+        let moved = HashMap::from([
+            ("old".to_string(), "new".to_string()),
+            ("gone".to_string(), "taken".to_string()),
+        ]);
+        rebind_keys(&mut map, &moved);
+        assert_eq!(
+            map,
+            HashMap::from([("new".to_string(), 1), ("taken".to_string(), 3)])
+        );
+    }
+}
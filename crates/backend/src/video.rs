@@ -0,0 +1,82 @@
+//! Video output for music videos, built with the `video` feature. Playbin decodes into an app
+//! sink that keeps only the latest frame; the UI picks it up whenever it redraws. The sink
+//! syncs against the pipeline clock, so frames land in step with the audio and follow pause,
+//! seek and the other transport controls without extra plumbing.
+
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, Ordering},
+};
+
+use gstreamer::{FlowError, FlowSuccess, prelude::*};
+use gstreamer_app::{AppSink, AppSinkCallbacks};
+use gstreamer_video::{VideoCapsBuilder, VideoFormat, VideoFrameRef, VideoInfo};
+
+/// A decoded video frame.
+pub struct VideoFrame {
+    pub width: u32,
+    pub height: u32,
+    /// BGRA pixels, row by row without padding, which is the layout gpui renders.
+    pub data: Vec<u8>,
+}
+
+static LATEST: Mutex<Option<Arc<VideoFrame>>> = Mutex::new(None);
+/// Bumped on every new frame, so the UI only re-uploads frames it hasn't seen.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The most recent frame and its generation, or `None` when the current track has no video.
+pub fn latest() -> Option<(u64, Arc<VideoFrame>)> {
+    let frame = LATEST.lock().expect("Could not lock video frame").clone()?;
+    Some((GENERATION.load(Ordering::Acquire), frame))
+}
+
+/// Forget the last frame, so an audio-only track goes back to showing its cover art.
+pub fn clear() {
+    *LATEST.lock().expect("Could not lock video frame") = None;
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+fn publish(frame: VideoFrame) {
+    *LATEST.lock().expect("Could not lock video frame") = Some(Arc::new(frame));
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+/// Video sink for playbin that feeds [`latest`].
+pub(crate) fn sink() -> gstreamer::Element {
+    let sink = AppSink::builder()
+        .caps(&VideoCapsBuilder::new().format(VideoFormat::Bgra).build())
+        .max_buffers(1)
+        .drop(true)
+        .sync(true)
+        .build();
+    sink.set_callbacks(
+        AppSinkCallbacks::builder()
+            .new_sample(|sink| {
+                let sample = sink.pull_sample().map_err(|_| FlowError::Eos)?;
+                let info = sample
+                    .caps()
+                    .and_then(|caps| VideoInfo::from_caps(caps).ok())
+                    .ok_or(FlowError::NotNegotiated)?;
+                let buffer = sample.buffer().ok_or(FlowError::Error)?;
+                let frame = VideoFrameRef::from_buffer_ref_readable(buffer, &info)
+                    .map_err(|_| FlowError::Error)?;
+
+                // Rows may be padded out to the stride, so copy them one at a time.
+                let row_len = info.width() as usize * 4;
+                let stride = frame.plane_stride()[0] as usize;
+                let plane = frame.plane_data(0).map_err(|_| FlowError::Error)?;
+                let mut data = Vec::with_capacity(row_len * info.height() as usize);
+                for row in plane.chunks(stride).take(info.height() as usize) {
+                    data.extend_from_slice(&row[..row_len]);
+                }
+                publish(VideoFrame {
+                    width: info.width(),
+                    height: info.height(),
+                    data,
+                });
+                Ok(FlowSuccess::Ok)
+            })
+            .build(),
+    );
+    sink.upcast()
+}
@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher, event::ModifyKind};
+use smol::channel::Sender;
+
+use crate::playback::SavedPlaylist;
+
+/// Watches the folder behind the loaded playlist and forwards the paths of files and folders
+/// that are added, removed, renamed or rewritten in it. The watch stops when this is dropped.
+pub struct FolderWatcher {
+    pub saved: SavedPlaylist,
+    _watcher: RecommendedWatcher,
+}
+
+impl FolderWatcher {
+    pub fn start(
+        saved: SavedPlaylist,
+        recursive: bool,
+        changes: Sender<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) => {
+                    let relevant = matches!(
+                        event.kind,
+                        EventKind::Create(_)
+                            | EventKind::Remove(_)
+                            | EventKind::Modify(ModifyKind::Name(_) | ModifyKind::Data(_))
+                    );
+                    // Renames may come as one event carrying both the old and new path, so
+                    // every path is passed on and checked against the disk later.
+                    if relevant {
+                        for path in event.paths {
+                            let _ = changes.try_send(path);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Folder watch error: {e}"),
+            })?;
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(Path::new(&saved.actual_path), mode)?;
+        Ok(FolderWatcher {
+            saved,
+            _watcher: watcher,
+        })
+    }
+}
+
+/// Watches the settings and keymap files and forwards the path of whichever changed. Their
+/// folder is watched rather than the files themselves, since editors often save by writing a
+/// new file and renaming it over the old one. The watch stops when this is dropped.
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    pub fn start(files: Vec<PathBuf>, changes: Sender<PathBuf>) -> anyhow::Result<Self> {
+        let watched = files.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) => {
+                    if matches!(event.kind, EventKind::Access(_)) {
+                        return;
+                    }
+                    for path in event.paths {
+                        if watched
+                            .iter()
+                            .any(|file| file.file_name() == path.file_name())
+                        {
+                            let _ = changes.try_send(path);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Config watch error: {e}"),
+            })?;
+        let mut folders: Vec<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+        folders.dedup();
+        for folder in folders {
+            watcher.watch(folder, RecursiveMode::NonRecursive)?;
+        }
+        Ok(ConfigWatcher { _watcher: watcher })
+    }
+}
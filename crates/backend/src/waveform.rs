@@ -0,0 +1,118 @@
+use std::{
+    fs::{self, File},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use bincode::config;
+use directories::UserDirs;
+use ring_channel::RingSender as Sender;
+
+use crate::{Backend, playback::Track, player::Response};
+
+/// Number of peaks in a generated waveform.
+pub const WAVEFORM_POINTS: usize = 400;
+
+/// Generates waveforms one at a time on a worker thread. Requests that pile up while a track is
+/// being decoded are collapsed so only the latest one is worked on.
+#[derive(Clone)]
+pub struct WaveformQueue {
+    jobs: smol::channel::Sender<Track>,
+    wanted: Arc<Mutex<Option<String>>>,
+    tx: Sender<Response>,
+}
+
+impl WaveformQueue {
+    pub fn new(backend: Arc<dyn Backend>, tx: Sender<Response>) -> Self {
+        let (jobs, rx) = smol::channel::unbounded::<Track>();
+        let wanted: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+        let worker_wanted = wanted.clone();
+        let worker_tx = tx.clone();
+
+        thread::spawn(move || {
+            while let Ok(mut track) = rx.recv_blocking() {
+                while let Ok(newer) = rx.try_recv() {
+                    track = newer;
+                }
+                let peaks = match read_cached(&track.uri) {
+                    Some(peaks) => peaks,
+                    None => {
+                        match smol::block_on(backend.waveform(
+                            &track.uri,
+                            track.duration,
+                            WAVEFORM_POINTS,
+                        )) {
+                            Ok(peaks) => {
+                                if let Err(e) = write_cached(&track.uri, &peaks) {
+                                    eprintln!("Could not cache waveform: {e}");
+                                }
+                                peaks
+                            }
+                            Err(e) => {
+                                eprintln!("Could not generate waveform for {}: {e}", track.uri);
+                                continue;
+                            }
+                        }
+                    }
+                };
+                let still_wanted = worker_wanted
+                    .lock()
+                    .expect("Could not lock waveform queue")
+                    .as_deref()
+                    == Some(track.uri.as_str());
+                if still_wanted {
+                    let _ = worker_tx.send(Response::Waveform(peaks));
+                }
+            }
+        });
+
+        WaveformQueue { jobs, wanted, tx }
+    }
+
+    /// Queue a waveform for `track`, replacing any that hasn't been delivered yet. The previous
+    /// track's waveform is cleared straight away.
+    pub fn request(&self, track: &Track) {
+        *self.wanted.lock().expect("Could not lock waveform queue") = Some(track.uri.clone());
+        let _ = self.tx.send(Response::Waveform(Vec::new()));
+        if track.is_remote() {
+            return;
+        }
+        let _ = self.jobs.try_send(track.clone());
+    }
+}
+
+fn cache_path(uri: &str) -> Option<PathBuf> {
+    let user_dirs = UserDirs::new()?;
+    let cache_dir = user_dirs
+        .audio_dir()
+        .unwrap_or(user_dirs.home_dir())
+        .join("Kagi")
+        .join("cache")
+        .join("waveforms");
+    if let Err(e) = fs::create_dir_all(&cache_dir) {
+        eprintln!("Could not create waveform cache: {}", e);
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    uri.hash(&mut hasher);
+    Some(cache_dir.join(format!("{:016x}", hasher.finish())))
+}
+
+fn read_cached(uri: &str) -> Option<Vec<f32>> {
+    let data = fs::read(cache_path(uri)?).ok()?;
+    bincode::serde::decode_from_slice(&data, config::standard())
+        .ok()
+        .map(|(peaks, _)| peaks)
+}
+
+fn write_cached(uri: &str, peaks: &[f32]) -> anyhow::Result<()> {
+    if let Some(path) = cache_path(uri) {
+        let serialized = bincode::serde::encode_to_vec(peaks, config::standard())?;
+        let mut file = File::create(path)?;
+        file.write_all(&serialized)?;
+    }
+    Ok(())
+}
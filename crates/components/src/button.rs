@@ -1,4 +1,6 @@
-use gpui::{App, MouseButton, MouseDownEvent, SharedString, Window, div, prelude::*, px, rgb};
+use gpui::{App, MouseButton, MouseDownEvent, Rgba, SharedString, Window, div, prelude::*, px};
+
+use crate::theme::{Theme, flag_unthemed};
 
 #[derive(IntoElement)]
 pub struct Button {
@@ -6,12 +8,9 @@ pub struct Button {
     w: f32,
     h: f32,
     px: f32,
-    bg_color: u32,
-    text_color: u32,
-    border_color: u32,
-    hover_bg_color: u32,
-    hover_text_color: u32,
-    hover_border_color: u32,
+    bg_color: Option<Rgba>,
+    text_color: Option<Rgba>,
+    border_color: Option<Rgba>,
     rounded: f32,
     on_click: Box<dyn Fn(MouseDownEvent, &mut Window, &mut App) + 'static>,
 }
@@ -24,12 +23,9 @@ impl Button {
             w: 0.0,
             h: 40.0,
             px: 20.0,
-            bg_color: 0x45475a,
-            text_color: 0xcdd6f4,
-            border_color: 0xcba6f7,
-            hover_bg_color: 0xcba6f7,
-            hover_text_color: 0x1e1e2d,
-            hover_border_color: 0xcba6f7,
+            bg_color: None,
+            text_color: None,
+            border_color: None,
             rounded: 8.0,
             on_click: Box::new(|_, _, _| println!("Clicked!")),
         }
@@ -46,18 +42,18 @@ impl Button {
         self
     }
 
-    pub fn bg(mut self, color: u32) -> Self {
-        self.bg_color = color;
+    pub fn bg(mut self, color: Rgba) -> Self {
+        self.bg_color = Some(color);
         self
     }
 
-    pub fn text_color(mut self, color: u32) -> Self {
-        self.text_color = color;
+    pub fn text_color(mut self, color: Rgba) -> Self {
+        self.text_color = Some(color);
         self
     }
 
-    pub fn border_color(mut self, color: u32) -> Self {
-        self.border_color = color;
+    pub fn border_color(mut self, color: Rgba) -> Self {
+        self.border_color = Some(color);
         self
     }
 
@@ -76,26 +72,34 @@ impl Button {
 }
 
 impl RenderOnce for Button {
-    fn render(self, _: &mut Window, _: &mut App) -> impl IntoElement {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
         let on_click = self.on_click;
+        let style = cx.global::<Theme>().components.button;
+        let color = |color: Option<Rgba>, fallback: Rgba| match color {
+            Some(color) => {
+                flag_unthemed("button", color, cx);
+                color
+            }
+            None => fallback,
+        };
         div()
             .flex()
             .h(px(self.h))
             .when(self.w != 0.0, |this| this.w(px(self.w)))
             .when(self.w == 0.0, |this| this.w_auto().px(px(self.px)))
-            .bg(rgb(self.bg_color))
-            .text_color(rgb(self.text_color))
+            .bg(color(self.bg_color, style.bg))
+            .text_color(color(self.text_color, style.text))
             .border_2()
             .rounded(px(self.rounded))
-            .border_color(rgb(self.border_color))
+            .border_color(color(self.border_color, style.border))
             .justify_center()
             .content_center()
             .items_center()
             .child(self.text)
             .hover(move |this| {
-                this.bg(rgb(self.hover_bg_color))
-                    .text_color(rgb(self.hover_text_color))
-                    .border_color(rgb(self.hover_border_color))
+                this.bg(style.hover_bg)
+                    .text_color(style.hover_text)
+                    .border_color(style.hover_border)
             })
             .on_mouse_down(MouseButton::Left, move |event, win, cx| {
                 (on_click)(event.clone(), win, cx);
@@ -1,5 +1,7 @@
 use gpui::*;
 
+use crate::theme::{Theme, flag_unthemed};
+
 pub enum Icons {
     Close,
     Maximize,
@@ -15,6 +17,8 @@ pub enum Icons {
     Shuffle,
     Repeat,
     Search,
+    Loading,
+    Warning,
 }
 
 impl Icons {
@@ -34,6 +38,8 @@ impl Icons {
             Self::Shuffle => "icons/shuffle.svg",
             Self::Repeat => "icons/repeat.svg",
             Self::Search => "icons/search.svg",
+            Self::Loading => "icons/loading.svg",
+            Self::Warning => "icons/warning.svg",
         }
         .into()
     }
@@ -43,8 +49,10 @@ impl Icons {
 pub struct Icon {
     pub icon: Icons,
     pub size: f32,
-    pub color: Rgba,
-    pub hover: Rgba,
+    /// Defaults to the theme's icon color.
+    pub color: Option<Rgba>,
+    pub hover: Option<Rgba>,
+    pub transformation: Option<Transformation>,
 }
 
 impl Icon {
@@ -52,8 +60,9 @@ impl Icon {
         Icon {
             icon,
             size: 24.0,
-            color: rgb(0xcdd6f4),
-            hover: rgb(0xcdd6f4),
+            color: None,
+            hover: None,
+            transformation: None,
         }
     }
 
@@ -63,25 +72,42 @@ impl Icon {
     }
 
     pub fn color(mut self, color: Rgba) -> Self {
-        self.color = color;
+        self.color = Some(color);
         self
     }
 
     pub fn hover(mut self, color: Rgba) -> Self {
-        self.hover = color;
+        self.hover = Some(color);
+        self
+    }
+
+    pub fn transformation(mut self, transformation: Transformation) -> Self {
+        self.transformation = Some(transformation);
         self
     }
 }
 
 impl RenderOnce for Icon {
-    fn render(self, _win: &mut Window, _cx: &mut App) -> impl IntoElement {
-        svg()
+    fn render(self, _win: &mut Window, cx: &mut App) -> impl IntoElement {
+        let color = |color: Option<Rgba>| match color {
+            Some(color) => {
+                flag_unthemed("icon", color, cx);
+                color
+            }
+            None => cx.global::<Theme>().icon,
+        };
+        let (color, hover) = (color(self.color), color(self.hover));
+        let svg = svg()
             .flex()
             .items_center()
             .justify_center()
             .size(px(self.size))
-            .text_color(self.color)
+            .text_color(color)
             .path(self.icon.path())
-            .hover(|this| this.text_color(self.hover))
+            .hover(|this| this.text_color(hover));
+        match self.transformation {
+            Some(transformation) => svg.with_transformation(transformation),
+            None => svg,
+        }
     }
 }
@@ -1,5 +1,5 @@
 use crate::theme::Theme;
-use gpui::*;
+use gpui::{prelude::FluentBuilder, *};
 
 #[derive(Clone, Render)]
 pub struct Thumb(EntityId);
@@ -13,6 +13,9 @@ pub struct Slider {
     max: f32,
     step: f32,
     value: f32,
+    warn_above: Option<f32>,
+    disabled: bool,
+    waveform: Vec<f32>,
     bounds: Bounds<Pixels>,
     theme: Theme,
 }
@@ -26,6 +29,9 @@ impl Slider {
             max: 100.0,
             step: 1.0,
             value: 0.0,
+            warn_above: None,
+            disabled: false,
+            waveform: Vec::new(),
             bounds: Bounds::default(),
             theme,
         }
@@ -51,14 +57,41 @@ impl Slider {
         self
     }
 
+    /// Highlight the part of the track above `value` in the theme's warning color.
+    pub fn warn_above(mut self, value: f32) -> Self {
+        self.warn_above = Some(value);
+        self
+    }
+
+    pub fn set_disabled(&mut self, disabled: bool, cx: &mut Context<Self>) {
+        self.disabled = disabled;
+        cx.notify();
+    }
+
+    /// Draw the track as peak bars, e.g. a song's amplitude profile. Empty restores the plain
+    /// track.
+    pub fn set_waveform(&mut self, waveform: Vec<f32>, cx: &mut Context<Self>) {
+        self.waveform = waveform;
+        cx.notify();
+    }
+
+    pub fn set_max(&mut self, max: f32, cx: &mut Context<Self>) {
+        self.max = max;
+        self.value = self.value.min(max);
+        cx.notify();
+    }
+
     pub fn value(&mut self, value: f32, cx: &mut Context<Self>) {
         self.value = value;
         cx.notify();
     }
 
     fn relative_value(&self) -> f32 {
+        self.relative(self.value)
+    }
+
+    fn relative(&self, value: f32) -> f32 {
         let step = self.step;
-        let value = self.value;
         let min = self.min;
         let max = self.max;
 
@@ -70,6 +103,9 @@ impl Slider {
     }
 
     fn on_drag(&mut self, position: Point<Pixels>, cx: &mut Context<Self>) {
+        if self.disabled {
+            return;
+        }
         let bounds = self.bounds;
         let min = self.min;
         let max = self.max;
@@ -118,26 +154,36 @@ impl Slider {
             .size_4()
             .rounded_full()
             .border(px(1.5))
-            .border_color(self.theme.secondary)
-            .bg(self.theme.accent)
+            .border_color(self.theme.components.slider.thumb_border)
+            .bg(self.theme.components.slider.thumb)
     }
 }
 
 impl Render for Slider {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let warn_start = self
+            .warn_above
+            .filter(|warn_above| *warn_above < self.max)
+            .map(|warn_above| self.relative(warn_above));
+
+        if !self.waveform.is_empty() {
+            return self.render_waveform(cx).into_any_element();
+        }
+
         div()
             .id("slider")
             .on_mouse_down(MouseButton::Left, cx.listener(Self::on_mouse_down))
             .h_5()
             .w_full()
+            .when(self.disabled, |this| this.opacity(0.4))
             .child(
                 div()
                     .id("bar")
                     .relative()
                     .w_full()
                     .h_1p5()
-                    .bg(self.theme.secondary)
-                    .active(|this| this.bg(self.theme.accent))
+                    .bg(self.theme.components.slider.track)
+                    .active(|this| this.bg(self.theme.components.slider.fill))
                     .rounded(px(3.))
                     .child(
                         div()
@@ -146,9 +192,34 @@ impl Render for Slider {
                             .left_0()
                             .h_full()
                             .w(relative(self.relative_value()))
-                            .bg(self.theme.accent)
+                            .bg(self.theme.components.slider.fill)
                             .rounded_l(px(3.)),
                     )
+                    .when_some(warn_start, |this, warn_start| {
+                        let relative_value = self.relative_value();
+                        this.child(
+                            div()
+                                .absolute()
+                                .top_0()
+                                .left(relative(warn_start))
+                                .h_full()
+                                .w(relative(1.0 - warn_start))
+                                .bg(self.theme.components.slider.warning)
+                                .opacity(0.25)
+                                .rounded_r(px(3.)),
+                        )
+                        .when(relative_value > warn_start, |this| {
+                            this.child(
+                                div()
+                                    .absolute()
+                                    .top_0()
+                                    .left(relative(warn_start))
+                                    .h_full()
+                                    .w(relative(relative_value - warn_start))
+                                    .bg(self.theme.components.slider.warning),
+                            )
+                        })
+                    })
                     .child(self.render_thumb(cx))
                     .child({
                         let view = cx.entity().clone();
@@ -160,5 +231,49 @@ impl Render for Slider {
                         .size_full()
                     }),
             )
+            .into_any_element()
+    }
+}
+
+impl Slider {
+    fn render_waveform(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let style = self.theme.components.slider;
+        let relative_value = self.relative_value();
+        let count = self.waveform.len() as f32;
+
+        div()
+            .id("slider")
+            .on_mouse_down(MouseButton::Left, cx.listener(Self::on_mouse_down))
+            .w_full()
+            .when(self.disabled, |this| this.opacity(0.4))
+            .child(
+                div()
+                    .id("bar")
+                    .relative()
+                    .w_full()
+                    .h_4()
+                    // Centered on where the plain track would be drawn.
+                    .mt(px(-5.))
+                    .flex()
+                    .items_center()
+                    .gap(px(1.))
+                    .children(self.waveform.iter().enumerate().map(|(index, peak)| {
+                        let played = (index as f32 + 0.5) / count <= relative_value;
+                        div()
+                            .flex_1()
+                            .h(relative(peak.max(0.05)))
+                            .rounded(px(1.))
+                            .bg(if played { style.fill } else { style.track })
+                    }))
+                    .child({
+                        let view = cx.entity().clone();
+                        canvas(
+                            move |bounds, _, cx| view.update(cx, |r, _| r.bounds = bounds),
+                            |_, _, _, _| {},
+                        )
+                        .absolute()
+                        .size_full()
+                    }),
+            )
     }
 }
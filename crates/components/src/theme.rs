@@ -1,5 +1,9 @@
-use gpui::{Global, Rgba, rgb};
+use std::sync::Mutex;
 
+use gpui::{App, Global, Rgba, rgb, rgba};
+use serde::Deserialize;
+
+/// Flat palette used throughout the views, derived from [`Tokens`] and [`Components`].
 #[derive(Clone, Copy)]
 pub struct Theme {
     pub accent: Rgba,
@@ -11,45 +15,573 @@ pub struct Theme {
     pub main_bg: Rgba,
     pub titlebar_bg: Rgba,
     pub highlight: Rgba,
+    pub warning: Rgba,
+    pub tokens: Tokens,
+    pub components: Components,
+}
+
+#[derive(Clone, Copy)]
+pub struct Tokens {
+    pub surface: Surfaces,
+    pub text: TextColors,
+    pub accent: AccentScale,
+    pub state: StateColors,
+}
+
+/// Background layers, from the window background up to floating overlays.
+#[derive(Clone, Copy)]
+pub struct Surfaces {
+    pub base: Rgba,
+    pub raised: Rgba,
+    pub overlay: Rgba,
+    pub border: Rgba,
+    /// Dims the window behind dialogs.
+    pub scrim: Rgba,
+}
+
+#[derive(Clone, Copy)]
+pub struct TextColors {
+    pub primary: Rgba,
+    pub secondary: Rgba,
+    pub muted: Rgba,
+    pub on_accent: Rgba,
+}
+
+#[derive(Clone, Copy)]
+pub struct AccentScale {
+    pub base: Rgba,
+    pub hover: Rgba,
+    pub muted: Rgba,
+    pub subtle: Rgba,
+}
+
+#[derive(Clone, Copy)]
+pub struct StateColors {
+    pub hover: Rgba,
+    pub active: Rgba,
+    pub selected: Rgba,
+    pub disabled: Rgba,
+    pub warning: Rgba,
+    pub error: Rgba,
+    pub success: Rgba,
+}
+
+/// Per-component styles. Defaults come from the tokens, and theme files may override any field.
+#[derive(Clone, Copy)]
+pub struct Components {
+    pub slider: SliderStyle,
+    pub button: ButtonStyle,
+    pub row: RowStyle,
+    pub titlebar: TitlebarStyle,
+}
+
+#[derive(Clone, Copy)]
+pub struct SliderStyle {
+    pub track: Rgba,
+    pub fill: Rgba,
+    pub thumb: Rgba,
+    pub thumb_border: Rgba,
+    pub warning: Rgba,
+}
+
+#[derive(Clone, Copy)]
+pub struct ButtonStyle {
+    pub bg: Rgba,
+    pub text: Rgba,
+    pub border: Rgba,
+    pub hover_bg: Rgba,
+    pub hover_text: Rgba,
+    pub hover_border: Rgba,
+}
+
+#[derive(Clone, Copy)]
+pub struct RowStyle {
+    pub bg: Rgba,
+    pub hover_bg: Rgba,
+    pub selected_bg: Rgba,
+    pub text: Rgba,
+    pub subtext: Rgba,
+}
+
+#[derive(Clone, Copy)]
+pub struct TitlebarStyle {
+    pub icon: Rgba,
+    pub button_hover: Rgba,
+    pub close_hover: Rgba,
+}
+
+impl Tokens {
+    pub fn default() -> Self {
+        Tokens {
+            surface: Surfaces {
+                base: rgb(0x11111B),
+                raised: rgb(0x1e1e2d),
+                overlay: rgb(0x313244),
+                border: rgb(0x1e1e2d),
+                scrim: rgba(0x00000099),
+            },
+            text: TextColors {
+                primary: rgb(0xcdd6f4),
+                secondary: rgb(0xa6adc8),
+                muted: rgb(0x6c7086),
+                on_accent: rgb(0x1e1e2d),
+            },
+            accent: AccentScale {
+                base: rgb(0xcba6f7),
+                hover: rgb(0xd9bdfa),
+                muted: rgb(0x9d7cd8),
+                subtle: rgb(0x52cba6f7),
+            },
+            state: StateColors {
+                hover: rgb(0x1e1e2d),
+                active: rgb(0x313244),
+                selected: rgb(0x1e1e2d),
+                disabled: rgb(0x45475a),
+                warning: rgb(0xfab387),
+                error: rgb(0xf38ba8),
+                success: rgb(0xa6e3a1),
+            },
+        }
+    }
+
+    /// Light counterpart of the default tokens.
+    pub fn light() -> Self {
+        Tokens {
+            surface: Surfaces {
+                base: rgb(0xeff1f5),
+                raised: rgb(0xe6e9ef),
+                overlay: rgb(0xccd0da),
+                border: rgb(0xdce0e8),
+                scrim: rgba(0x00000066),
+            },
+            text: TextColors {
+                primary: rgb(0x4c4f69),
+                secondary: rgb(0x6c6f85),
+                muted: rgb(0x8c8fa1),
+                on_accent: rgb(0xeff1f5),
+            },
+            accent: AccentScale {
+                base: rgb(0x8839ef),
+                hover: rgb(0x9c5af2),
+                muted: rgb(0x7c3ad8),
+                subtle: rgba(0x8839ef33),
+            },
+            state: StateColors {
+                hover: rgb(0xe6e9ef),
+                active: rgb(0xccd0da),
+                selected: rgb(0xdce0e8),
+                disabled: rgb(0xbcc0cc),
+                warning: rgb(0xfe640b),
+                error: rgb(0xd20f39),
+                success: rgb(0x40a02b),
+            },
+        }
+    }
+}
+
+impl Components {
+    pub fn from_tokens(tokens: &Tokens) -> Self {
+        Components {
+            slider: SliderStyle {
+                track: tokens.surface.raised,
+                fill: tokens.accent.base,
+                thumb: tokens.accent.base,
+                thumb_border: tokens.surface.raised,
+                warning: tokens.state.warning,
+            },
+            button: ButtonStyle {
+                bg: tokens.state.disabled,
+                text: tokens.text.primary,
+                border: tokens.accent.base,
+                hover_bg: tokens.accent.base,
+                hover_text: tokens.text.on_accent,
+                hover_border: tokens.accent.base,
+            },
+            row: RowStyle {
+                bg: tokens.surface.base,
+                hover_bg: tokens.state.hover,
+                selected_bg: tokens.state.selected,
+                text: tokens.text.primary,
+                subtext: tokens.text.secondary,
+            },
+            titlebar: TitlebarStyle {
+                icon: tokens.text.primary,
+                button_hover: tokens.state.disabled,
+                close_hover: tokens.state.error,
+            },
+        }
+    }
 }
 
 impl Theme {
     pub fn default() -> Self {
-        Theme {
-            accent: rgb(0xcba6f7),
-            text: rgb(0xcdd6f4),
-            icon: rgb(0xcdd6f4),
-            background: rgb(0x11111B),
-            secondary: rgb(0x1e1e2d),
-            sidebar_bg: rgb(0x11111B),
-            main_bg: rgb(0x11111B),
-            titlebar_bg: rgb(0x11111B),
-            highlight: rgb(0x52cba6f7),
+        let tokens = Tokens::default();
+        Theme::new(tokens, Components::from_tokens(&tokens))
+    }
+
+    pub fn light() -> Self {
+        let tokens = Tokens::light();
+        Theme::new(tokens, Components::from_tokens(&tokens))
+    }
+
+    /// This theme with its accent scale built from `base`, for tinting it to match cover art.
+    /// Component colors taken from the old scale follow along.
+    pub fn with_accent(&self, base: Rgba) -> Self {
+        let old = self.tokens.accent;
+        let new = AccentScale {
+            base,
+            hover: mix(base, rgb(0xffffff), 0.2),
+            muted: mix(base, rgb(0x000000), 0.2),
+            subtle: Rgba { a: 0.32, ..base },
+        };
+        let retint = |color: &mut Rgba| {
+            let scales = [
+                (old.base, new.base),
+                (old.hover, new.hover),
+                (old.muted, new.muted),
+                (old.subtle, new.subtle),
+            ];
+            if let Some((_, to)) = scales.into_iter().find(|(from, _)| from == color) {
+                *color = to;
+            }
+        };
+        let mut tokens = self.tokens;
+        tokens.accent = new;
+        let mut components = self.components;
+        let Components {
+            slider,
+            button,
+            row,
+            titlebar,
+        } = &mut components;
+        for color in [
+            &mut slider.track,
+            &mut slider.fill,
+            &mut slider.thumb,
+            &mut slider.thumb_border,
+            &mut button.bg,
+            &mut button.text,
+            &mut button.border,
+            &mut button.hover_bg,
+            &mut button.hover_text,
+            &mut button.hover_border,
+            &mut row.hover_bg,
+            &mut row.selected_bg,
+            &mut titlebar.button_hover,
+        ] {
+            retint(color);
         }
+        Theme::new(tokens, components)
     }
-    pub fn new(
-        accent: Rgba,
-        text: Rgba,
-        icon: Rgba,
-        background: Rgba,
-        secondary: Rgba,
-        sidebar_bg: Rgba,
-        main_bg: Rgba,
-        titlebar_bg: Rgba,
-        highlight: Rgba,
-    ) -> Self {
+
+    pub fn new(tokens: Tokens, components: Components) -> Self {
         Self {
-            accent,
-            text,
-            icon,
-            background,
-            secondary,
-            sidebar_bg,
-            main_bg,
-            titlebar_bg,
-            highlight,
+            accent: tokens.accent.base,
+            text: tokens.text.primary,
+            icon: tokens.text.primary,
+            background: tokens.surface.base,
+            secondary: tokens.surface.raised,
+            sidebar_bg: tokens.surface.base,
+            main_bg: tokens.surface.base,
+            titlebar_bg: tokens.surface.base,
+            highlight: tokens.accent.subtle,
+            warning: tokens.state.warning,
+            tokens,
+            components,
         }
     }
+
+    /// Build a theme from a TOML theme file. Anything the file leaves out falls back to the
+    /// default tokens, and component styles not overridden are derived from the final tokens.
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        Ok(Theme::from_file(toml::from_str(source)?))
+    }
+
+    /// Build a theme from a JSON theme file, laid out like the TOML ones.
+    pub fn from_json(source: &str) -> Result<Self, serde_json::Error> {
+        Ok(Theme::from_file(serde_json::from_str(source)?))
+    }
+
+    fn from_file(file: ThemeFile) -> Self {
+        let mut tokens = Tokens::default();
+        file.apply_tokens(&mut tokens);
+        let mut components = Components::from_tokens(&tokens);
+        file.apply_components(&mut components);
+        Theme::new(tokens, components)
+    }
+}
+
+impl Theme {
+    /// Every token color. Views should only ever paint with these.
+    pub fn palette(&self) -> Vec<Rgba> {
+        let Tokens {
+            surface,
+            text,
+            accent,
+            state,
+        } = self.tokens;
+        vec![
+            surface.base,
+            surface.raised,
+            surface.overlay,
+            surface.border,
+            surface.scrim,
+            text.primary,
+            text.secondary,
+            text.muted,
+            text.on_accent,
+            accent.base,
+            accent.hover,
+            accent.muted,
+            accent.subtle,
+            state.hover,
+            state.active,
+            state.selected,
+            state.disabled,
+            state.warning,
+            state.error,
+            state.success,
+        ]
+    }
+
+    pub fn is_themed(&self, color: Rgba) -> bool {
+        self.palette().contains(&color)
+    }
+
+    /// Component styles set to a color that isn't one of the tokens, as `component.field`.
+    pub fn unthemed(&self) -> Vec<String> {
+        let Components {
+            slider,
+            button,
+            row,
+            titlebar,
+        } = self.components;
+        [
+            ("slider.track", slider.track),
+            ("slider.fill", slider.fill),
+            ("slider.thumb", slider.thumb),
+            ("slider.thumb_border", slider.thumb_border),
+            ("slider.warning", slider.warning),
+            ("button.bg", button.bg),
+            ("button.text", button.text),
+            ("button.border", button.border),
+            ("button.hover_bg", button.hover_bg),
+            ("button.hover_text", button.hover_text),
+            ("button.hover_border", button.hover_border),
+            ("row.bg", row.bg),
+            ("row.hover_bg", row.hover_bg),
+            ("row.selected_bg", row.selected_bg),
+            ("row.text", row.text),
+            ("row.subtext", row.subtext),
+            ("titlebar.icon", titlebar.icon),
+            ("titlebar.button_hover", titlebar.button_hover),
+            ("titlebar.close_hover", titlebar.close_hover),
+        ]
+        .into_iter()
+        .filter(|(_, color)| !self.is_themed(*color))
+        .map(|(field, color)| format!("{field} is {color:?}"))
+        .collect()
+    }
+}
+
+/// Colors already reported by [`flag_unthemed`], so each is only reported once.
+static FLAGGED: Mutex<Vec<(&str, Rgba)>> = Mutex::new(Vec::new());
+
+/// In debug builds, report `color` given to `component` when it isn't one of the theme's
+/// tokens. Components call this for colors passed in by views rather than taken from the theme.
+pub fn flag_unthemed(component: &'static str, color: Rgba, cx: &App) {
+    if !cfg!(debug_assertions) || cx.global::<Theme>().is_themed(color) {
+        return;
+    }
+    let mut flagged = FLAGGED.lock().expect("Could not lock unthemed colors");
+    if !flagged.contains(&(component, color)) {
+        eprintln!("Unthemed color in {component}: {color:?}");
+        flagged.push((component, color));
+    }
 }
 
 impl Global for Theme {}
+
+/// A color written as `#rrggbb` or `#rrggbbaa` in theme files.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+struct Color(Rgba);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let hex = value.trim_start_matches('#');
+        let parsed = u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid color: {value}"))?;
+        match hex.len() {
+            6 => Ok(Color(rgb(parsed))),
+            8 => Ok(Color(rgba(parsed))),
+            _ => Err(format!("Invalid color: {value}")),
+        }
+    }
+}
+
+/// `from` moved `amount` of the way to `to`, keeping its alpha.
+fn mix(from: Rgba, to: Rgba, amount: f32) -> Rgba {
+    Rgba {
+        r: from.r + (to.r - from.r) * amount,
+        g: from.g + (to.g - from.g) * amount,
+        b: from.b + (to.b - from.b) * amount,
+        a: from.a,
+    }
+}
+
+fn set(target: &mut Rgba, value: Option<Color>) {
+    if let Some(Color(color)) = value {
+        *target = color;
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ThemeFile {
+    surface: SurfacesFile,
+    text: TextColorsFile,
+    accent: AccentScaleFile,
+    state: StateColorsFile,
+    slider: SliderStyleFile,
+    button: ButtonStyleFile,
+    row: RowStyleFile,
+    titlebar: TitlebarStyleFile,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SurfacesFile {
+    base: Option<Color>,
+    raised: Option<Color>,
+    overlay: Option<Color>,
+    border: Option<Color>,
+    scrim: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TextColorsFile {
+    primary: Option<Color>,
+    secondary: Option<Color>,
+    muted: Option<Color>,
+    on_accent: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct AccentScaleFile {
+    base: Option<Color>,
+    hover: Option<Color>,
+    muted: Option<Color>,
+    subtle: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct StateColorsFile {
+    hover: Option<Color>,
+    active: Option<Color>,
+    selected: Option<Color>,
+    disabled: Option<Color>,
+    warning: Option<Color>,
+    error: Option<Color>,
+    success: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SliderStyleFile {
+    track: Option<Color>,
+    fill: Option<Color>,
+    thumb: Option<Color>,
+    thumb_border: Option<Color>,
+    warning: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ButtonStyleFile {
+    bg: Option<Color>,
+    text: Option<Color>,
+    border: Option<Color>,
+    hover_bg: Option<Color>,
+    hover_text: Option<Color>,
+    hover_border: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RowStyleFile {
+    bg: Option<Color>,
+    hover_bg: Option<Color>,
+    selected_bg: Option<Color>,
+    text: Option<Color>,
+    subtext: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TitlebarStyleFile {
+    icon: Option<Color>,
+    button_hover: Option<Color>,
+    close_hover: Option<Color>,
+}
+
+impl ThemeFile {
+    fn apply_tokens(&self, tokens: &mut Tokens) {
+        set(&mut tokens.surface.base, self.surface.base);
+        set(&mut tokens.surface.raised, self.surface.raised);
+        set(&mut tokens.surface.overlay, self.surface.overlay);
+        set(&mut tokens.surface.border, self.surface.border);
+        set(&mut tokens.surface.scrim, self.surface.scrim);
+
+        set(&mut tokens.text.primary, self.text.primary);
+        set(&mut tokens.text.secondary, self.text.secondary);
+        set(&mut tokens.text.muted, self.text.muted);
+        set(&mut tokens.text.on_accent, self.text.on_accent);
+
+        set(&mut tokens.accent.base, self.accent.base);
+        set(&mut tokens.accent.hover, self.accent.hover);
+        set(&mut tokens.accent.muted, self.accent.muted);
+        set(&mut tokens.accent.subtle, self.accent.subtle);
+
+        set(&mut tokens.state.hover, self.state.hover);
+        set(&mut tokens.state.active, self.state.active);
+        set(&mut tokens.state.selected, self.state.selected);
+        set(&mut tokens.state.disabled, self.state.disabled);
+        set(&mut tokens.state.warning, self.state.warning);
+        set(&mut tokens.state.error, self.state.error);
+        set(&mut tokens.state.success, self.state.success);
+    }
+
+    fn apply_components(&self, components: &mut Components) {
+        let slider = &mut components.slider;
+        set(&mut slider.track, self.slider.track);
+        set(&mut slider.fill, self.slider.fill);
+        set(&mut slider.thumb, self.slider.thumb);
+        set(&mut slider.thumb_border, self.slider.thumb_border);
+        set(&mut slider.warning, self.slider.warning);
+
+        let button = &mut components.button;
+        set(&mut button.bg, self.button.bg);
+        set(&mut button.text, self.button.text);
+        set(&mut button.border, self.button.border);
+        set(&mut button.hover_bg, self.button.hover_bg);
+        set(&mut button.hover_text, self.button.hover_text);
+        set(&mut button.hover_border, self.button.hover_border);
+
+        let row = &mut components.row;
+        set(&mut row.bg, self.row.bg);
+        set(&mut row.hover_bg, self.row.hover_bg);
+        set(&mut row.selected_bg, self.row.selected_bg);
+        set(&mut row.text, self.row.text);
+        set(&mut row.subtext, self.row.subtext);
+
+        let titlebar = &mut components.titlebar;
+        set(&mut titlebar.icon, self.titlebar.icon);
+        set(&mut titlebar.button_hover, self.titlebar.button_hover);
+        set(&mut titlebar.close_hover, self.titlebar.close_hover);
+    }
+}
@@ -0,0 +1,336 @@
+//! Generates synthetic music libraries for exercising the scanner, library and queue with
+//! awkward input: thousands of tiny files, odd tag encodings, missing tags, huge cover art,
+//! nested and hidden folders, symlink loops and truncated files.
+//!
+//! ```text
+//! cargo run -p fixtures -- <output dir> [--tracks N] [--seconds S] [--seed N]
+//! ```
+
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use gstreamer::{ClockTime, MessageView, State, TagMergeMode, prelude::*};
+use image::{ImageFormat, Rgb, RgbImage};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+/// Every this many tracks carries a cover large enough to stress thumbnail decoding.
+const HUGE_ART_EVERY: usize = 100;
+const HUGE_ART_SIZE: u32 = 3000;
+const SMALL_ART_SIZE: u32 = 64;
+/// Tracks per album folder.
+const ALBUM_SIZE: usize = 12;
+
+const TITLES: [&str; 10] = [
+    "Morning Light",
+    "東京の夜",
+    "Ночной город",
+    "أغنية المطر",
+    "Caf\u{e9} del Mar",
+    "Cafe\u{301} del Mar",
+    "🎧 Emoji Title 🎶",
+    "  Leading and trailing spaces  ",
+    "Tab\tand\nnewline",
+    "ÀÉÎÕÜ ñ ß ø",
+];
+
+const ARTISTS: [&str; 8] = [
+    "The Testers",
+    "Alice feat. Bob",
+    "Alice; Bob",
+    "Alice & Bob",
+    "坂本龍一",
+    "Björk",
+    "AC/DC",
+    "",
+];
+
+#[derive(Clone, Copy)]
+enum Format {
+    Mp3,
+    Flac,
+    Ogg,
+    Wav,
+}
+
+impl Format {
+    const ALL: [Format; 4] = [Format::Mp3, Format::Flac, Format::Ogg, Format::Wav];
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Mp3 => "mp3",
+            Format::Flac => "flac",
+            Format::Ogg => "ogg",
+            Format::Wav => "wav",
+        }
+    }
+
+    fn encoder(self) -> &'static str {
+        match self {
+            Format::Mp3 => "lamemp3enc ! id3v2mux",
+            Format::Flac => "flacenc",
+            Format::Ogg => "vorbisenc ! oggmux",
+            Format::Wav => "wavenc",
+        }
+    }
+}
+
+/// How a generated track's tags are filled in.
+enum Tags {
+    Full,
+    Missing,
+    Odd,
+    LongTitle,
+}
+
+struct Options {
+    out: PathBuf,
+    tracks: usize,
+    seconds: u32,
+    seed: u64,
+}
+
+fn main() -> anyhow::Result<()> {
+    let options = parse_args()?;
+    gstreamer::init()?;
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    let small_art = cover_art(SMALL_ART_SIZE, &mut rng)?;
+    let huge_art = cover_art(HUGE_ART_SIZE, &mut rng)?;
+
+    for index in 0..options.tracks {
+        let format = Format::ALL[index % Format::ALL.len()];
+        let tags = match index % 7 {
+            0 | 1 | 2 => Tags::Full,
+            3 => Tags::Missing,
+            4 | 5 => Tags::Odd,
+            _ => Tags::LongTitle,
+        };
+        let dir = options
+            .out
+            .join(format!("Artist {:03}", index / (ALBUM_SIZE * 4)))
+            .join(format!("Album {:04}", index / ALBUM_SIZE));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{:05}.{}", index, format.extension()));
+
+        let art = if index % HUGE_ART_EVERY == 0 {
+            Some(huge_art.as_slice())
+        } else if rng.random_bool(0.5) {
+            Some(small_art.as_slice())
+        } else {
+            None
+        };
+        let freq = rng.random_range(220..880);
+        if let Err(e) = encode(&path, format, options.seconds, freq, &tags, index, art) {
+            eprintln!("Could not generate {:?}: {}", path, e);
+        }
+        if (index + 1) % 100 == 0 {
+            println!("{}/{}", index + 1, options.tracks);
+        }
+    }
+
+    extras(&options, &mut rng)?;
+    println!("Generated {} tracks in {:?}", options.tracks, options.out);
+    Ok(())
+}
+
+fn parse_args() -> anyhow::Result<Options> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        out: PathBuf::new(),
+        tracks: 1000,
+        seconds: 1,
+        seed: 0,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(anyhow!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--tracks" => options.tracks = value()?.parse()?,
+            "--seconds" => options.seconds = value()?.parse()?,
+            "--seed" => options.seed = value()?.parse()?,
+            _ if options.out.as_os_str().is_empty() => options.out = PathBuf::from(arg),
+            _ => return Err(anyhow!("Unexpected argument {arg}")),
+        }
+    }
+    if options.out.as_os_str().is_empty() {
+        return Err(anyhow!(
+            "Usage: fixtures <output dir> [--tracks N] [--seconds S] [--seed N]"
+        ));
+    }
+    Ok(options)
+}
+
+/// Encode a short sine tone to `path` with tags picked by `tags`.
+fn encode(
+    path: &Path,
+    format: Format,
+    seconds: u32,
+    freq: u32,
+    tags: &Tags,
+    index: usize,
+    art: Option<&[u8]>,
+) -> anyhow::Result<()> {
+    let pipeline = gstreamer::parse::launch(&format!(
+        "audiotestsrc num-buffers={} samplesperbuffer=4410 freq={freq} ! audioconvert ! {} ! \
+         filesink name=sink",
+        seconds * 10,
+        format.encoder()
+    ))?
+    .downcast::<gstreamer::Pipeline>()
+    .map_err(|_| anyhow!("Not a pipeline"))?;
+    pipeline
+        .by_name("sink")
+        .ok_or(anyhow!("No file sink"))?
+        .set_property("location", path.to_string_lossy().to_string());
+
+    if let Some(tag_list) = tag_list(tags, index, art) {
+        for element in pipeline
+            .iterate_all_by_interface(gstreamer::TagSetter::static_type())
+            .into_iter()
+            .flatten()
+        {
+            if let Some(setter) = element.dynamic_cast_ref::<gstreamer::TagSetter>() {
+                setter.merge_tags(&tag_list, TagMergeMode::Replace);
+            }
+        }
+    }
+
+    pipeline.set_state(State::Playing)?;
+    let bus = pipeline.bus().ok_or(anyhow!("Pipeline has no bus"))?;
+    let mut result = Ok(());
+    for msg in bus.iter_timed(ClockTime::NONE) {
+        match msg.view() {
+            MessageView::Eos(_) => break,
+            MessageView::Error(err) => {
+                result = Err(anyhow!("{}", err.error()));
+                break;
+            }
+            _ => {}
+        }
+    }
+    pipeline.set_state(State::Null)?;
+    result
+}
+
+fn tag_list(tags: &Tags, index: usize, art: Option<&[u8]>) -> Option<gstreamer::TagList> {
+    let (title, artist, album) = match tags {
+        Tags::Missing => return None,
+        Tags::Full => (
+            format!("Track {index}"),
+            ARTISTS[0].to_string(),
+            format!("Album {:04}", index / ALBUM_SIZE),
+        ),
+        Tags::Odd => (
+            TITLES[index % TITLES.len()].to_string(),
+            ARTISTS[index % ARTISTS.len()].to_string(),
+            TITLES[(index + 3) % TITLES.len()].to_string(),
+        ),
+        Tags::LongTitle => (
+            "Very long title ".repeat(40),
+            ARTISTS[index % ARTISTS.len()].to_string(),
+            String::new(),
+        ),
+    };
+
+    let mut list = gstreamer::TagList::new();
+    {
+        let list = list.get_mut().expect("New tag list is writable");
+        list.add::<gstreamer::tags::Title>(&title.as_str(), TagMergeMode::Append);
+        if !artist.is_empty() {
+            list.add::<gstreamer::tags::Artist>(&artist.as_str(), TagMergeMode::Append);
+        }
+        if !album.is_empty() {
+            list.add::<gstreamer::tags::Album>(&album.as_str(), TagMergeMode::Append);
+        }
+        list.add::<gstreamer::tags::TrackNumber>(
+            &((index % ALBUM_SIZE) as u32 + 1),
+            TagMergeMode::Append,
+        );
+        if let Some(art) = art {
+            let sample = gstreamer::Sample::builder()
+                .buffer(&gstreamer::Buffer::from_slice(art.to_vec()))
+                .caps(&gstreamer::Caps::builder("image/png").build())
+                .build();
+            list.add::<gstreamer::tags::Image>(&sample, TagMergeMode::Append);
+        }
+    }
+    Some(list)
+}
+
+/// Noise compresses badly, so the PNG stays close to its raw size.
+fn cover_art(size: u32, rng: &mut StdRng) -> anyhow::Result<Vec<u8>> {
+    let image = RgbImage::from_fn(size, size, |_, _| Rgb(rng.random()));
+    let mut bytes = Cursor::new(vec![]);
+    image.write_to(&mut bytes, ImageFormat::Png)?;
+    Ok(bytes.into_inner())
+}
+
+/// Files the scanner has to cope with besides well-formed audio.
+fn extras(options: &Options, rng: &mut StdRng) -> anyhow::Result<()> {
+    let out = &options.out;
+
+    // Should be skipped when hidden folders are excluded.
+    let hidden = out.join(".hidden");
+    fs::create_dir_all(&hidden)?;
+    encode(
+        &hidden.join("hidden.ogg"),
+        Format::Ogg,
+        options.seconds,
+        440,
+        &Tags::Full,
+        0,
+        None,
+    )?;
+
+    // Deeper than the default scan depth.
+    let deep = (0..12).fold(out.join("deep"), |dir, level| dir.join(format!("{level}")));
+    fs::create_dir_all(&deep)?;
+    encode(
+        &deep.join("deep.flac"),
+        Format::Flac,
+        options.seconds,
+        440,
+        &Tags::Full,
+        0,
+        None,
+    )?;
+
+    // Truncated halfway through, for the decode probe.
+    let broken = out.join("Broken");
+    fs::create_dir_all(&broken)?;
+    for format in Format::ALL {
+        let path = broken.join(format!("truncated.{}", format.extension()));
+        encode(
+            &path,
+            format,
+            options.seconds.max(2),
+            440,
+            &Tags::Full,
+            0,
+            None,
+        )?;
+        let bytes = fs::read(&path)?;
+        fs::write(&path, &bytes[..bytes.len() / 2])?;
+    }
+    fs::write(broken.join("not_audio.mp3"), b"This is not an mp3 file")?;
+    fs::write(broken.join("empty.flac"), b"")?;
+
+    // Non-audio files that sit next to music in real libraries.
+    fs::write(out.join("cover.png"), cover_art(SMALL_ART_SIZE, rng)?)?;
+    fs::write(out.join("notes.txt"), "Generated by fixtures")?;
+
+    // A symlink pointing back up the tree.
+    #[cfg(unix)]
+    {
+        let parent = out.join("Artist 000");
+        fs::create_dir_all(&parent)?;
+        let link = parent.join("loop");
+        if fs::symlink_metadata(&link).is_err() {
+            std::os::unix::fs::symlink(out.canonicalize()?, link)?;
+        }
+    }
+    Ok(())
+}
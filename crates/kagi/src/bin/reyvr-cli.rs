@@ -0,0 +1,158 @@
+//! Query the running app from scripts and status bars.
+//!
+//! ```text
+//! reyvr-cli status [--json]
+//! reyvr-cli queue [--json]
+//! reyvr-cli now-playing [--format "{artist} - {title}"]
+//! ```
+//!
+//! `status --follow` prints a JSON line on every change instead of exiting, and
+//! `now-playing --follow` a formatted one. Add `--waybar` to `now-playing --follow` for lines a
+//! waybar custom module with `"return-type": "json"` reads directly.
+
+use std::{thread, time::Duration};
+
+use anyhow::{Error, anyhow};
+use backend::ipc::{self, Query, QueueEntry, Status};
+
+const USAGE: &str = "Usage: reyvr-cli <status|queue|now-playing> [--json] [--follow] [--waybar] \
+                     [--format FORMAT]";
+const DEFAULT_FORMAT: &str = "{artist} - {title}";
+/// How often `--follow` tries to reconnect while the app isn't running.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+fn main() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let json = args.iter().any(|arg| arg == "--json");
+    let waybar = args.iter().any(|arg| arg == "--waybar");
+    let follow = args.iter().any(|arg| arg == "--follow");
+    let format = args
+        .iter()
+        .position(|arg| arg == "--format")
+        .map(|index| {
+            args.get(index + 1)
+                .cloned()
+                .ok_or(anyhow!("Missing value for --format"))
+        })
+        .transpose()?;
+
+    match args.first().map(String::as_str) {
+        Some("status") if follow => follow_status(|line, _| line.to_string()),
+        Some("now-playing") if follow => {
+            let format = format.unwrap_or(DEFAULT_FORMAT.to_string());
+            follow_status(move |_, status| match status {
+                Some(status) if waybar => waybar_line(status, &format),
+                Some(status) if !status.title.is_empty() => render(status, &format),
+                _ if waybar => waybar_line(&Status::default(), &format),
+                _ => String::new(),
+            })
+        }
+        Some("status") => {
+            let answer = ipc::request(Query::Status)?;
+            if json {
+                print!("{answer}");
+            } else {
+                let status: Status = serde_json::from_str(&answer)?;
+                println!(
+                    "{}: {}",
+                    status.state,
+                    render(&status, "{artist} - {title} ({position}/{duration})")
+                );
+                println!(
+                    "Track {}/{} of {}, volume {:.0}%",
+                    status.index + 1,
+                    status.queue_length,
+                    status.playlist,
+                    status.volume * 100.0
+                );
+            }
+        }
+        Some("queue") => {
+            let answer = ipc::request(Query::Queue)?;
+            if json {
+                print!("{answer}");
+            } else {
+                let queue: Vec<QueueEntry> = serde_json::from_str(&answer)?;
+                for (index, entry) in queue.iter().enumerate() {
+                    println!(
+                        "{} {:>4}. {} - {}",
+                        if entry.current { ">" } else { " " },
+                        index + 1,
+                        entry.artists.join(", "),
+                        entry.title
+                    );
+                }
+            }
+        }
+        Some("now-playing") => {
+            // Status bars poll this, so print nothing rather than fail when the app is closed.
+            let Ok(answer) = ipc::request(Query::Status) else {
+                return Ok(());
+            };
+            let status: Status = serde_json::from_str(&answer)?;
+            if !status.title.is_empty() {
+                println!(
+                    "{}",
+                    render(&status, format.as_deref().unwrap_or(DEFAULT_FORMAT))
+                );
+            }
+        }
+        _ => return Err(anyhow!(USAGE)),
+    }
+    Ok(())
+}
+
+/// Print `line(json, status)` for every status change, forever. While the app isn't running
+/// the status is `None` and the JSON empty.
+fn follow_status(line: impl Fn(&str, Option<&Status>) -> String) -> ! {
+    loop {
+        if let Ok(updates) = ipc::follow() {
+            for update in updates {
+                let status = serde_json::from_str::<Status>(&update).ok();
+                println!("{}", line(&update, status.as_ref()));
+            }
+        }
+        println!("{}", line("{}", None));
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+/// A line for waybar's custom module JSON protocol.
+fn waybar_line(status: &Status, format: &str) -> String {
+    let text = if status.title.is_empty() {
+        String::new()
+    } else {
+        render(status, format)
+    };
+    let tooltip = if status.title.is_empty() {
+        String::new()
+    } else {
+        render(status, "{title}\n{artist}\n{album}\n{position}/{duration}")
+    };
+    let state = if status.state.is_empty() {
+        "stopped"
+    } else {
+        status.state.as_str()
+    };
+    serde_json::json!({
+        "text": text,
+        "tooltip": tooltip,
+        "alt": state,
+        "class": state,
+    })
+    .to_string()
+}
+
+/// Fill `{title}`, `{artist}`, `{album}`, `{playlist}`, `{state}`, `{position}` and
+/// `{duration}` in `format`.
+fn render(status: &Status, format: &str) -> String {
+    let time = |seconds: u64| format!("{:02}:{:02}", seconds / 60, seconds % 60);
+    format
+        .replace("{title}", &status.title)
+        .replace("{artist}", &status.artists.join(", "))
+        .replace("{album}", &status.album)
+        .replace("{playlist}", &status.playlist)
+        .replace("{state}", &status.state)
+        .replace("{position}", &time(status.position))
+        .replace("{duration}", &time(status.duration))
+}
@@ -0,0 +1,70 @@
+//! Playing without a window, driven by `reyvr-cli` and `reyvr://` links.
+
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Error;
+use backend::{
+    Backend, ipc,
+    playback::{Origin, Playlist},
+    player::{Controller, Player, Response},
+    settings::Settings,
+    startup::StartupOptions,
+};
+
+/// Start the player and do what the window would otherwise do with its responses: go on to
+/// the next track at the end of one, and report errors. Runs until the process is stopped.
+pub fn run<F>(options: StartupOptions, init_backend: F) -> Result<(), Error>
+where
+    F: Future<Output = anyhow::Result<Arc<dyn Backend>>>,
+{
+    let settings = Settings::load();
+    let backend = smol::block_on(init_backend)?;
+    let (controller, res_tx, cmd_rx) = Controller::new();
+    let mut player = Player::with_channels(
+        backend,
+        Arc::new(Mutex::new(Playlist::default())),
+        res_tx,
+        cmd_rx,
+    );
+    thread::spawn(move || {
+        player
+            .tx
+            .send(Response::BackendReady)
+            .expect("Could not send message");
+        smol::block_on(player.run());
+    });
+
+    // Without a window the query server is the only way in, so it has to start.
+    ipc::serve(controller.clone())?;
+    controller.apply_settings(&settings);
+    if let Some(link) = options.link {
+        controller.open_link(link, Origin::Link);
+    }
+    controller.restore_session();
+    if let Some(volume) = options.volume.or(settings.default_volume) {
+        controller.start_volume(volume);
+    }
+    if !options.paths.is_empty() {
+        controller.play_files(options.paths, Origin::CommandLine);
+    }
+
+    let mut repeat = false;
+    loop {
+        while let Ok(res) = controller.rx.try_recv() {
+            match res {
+                Response::BackendReady => controller.mark_ready(),
+                Response::Repeat(enabled) => repeat = enabled,
+                Response::Eos if repeat => controller.seek(0),
+                Response::Eos => controller.next(),
+                Response::Error(e) => eprintln!("Error: {e}"),
+                Response::Warning(warning) => eprintln!("Warning: {warning}"),
+                _ => {}
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -1,15 +1,61 @@
+mod headless;
+mod remote;
+
 use anyhow::Error;
-use backend::{Backend as _, gstreamer::GstBackend};
-use std::sync::Arc;
+use backend::{
+    Backend, file_types,
+    gstreamer::GstBackend,
+    ipc::{self, Control},
+    link,
+    settings::Settings,
+    startup::{BackendKind, StartupOptions},
+};
+use std::{sync::Arc, thread};
 use ui::run_app;
 
 fn main() -> Result<(), Error> {
-    smol::block_on(async {
-        GstBackend::init()
-            .await
-            .expect("Could not initialize GStreamer backend: {e}");
-        let backend = GstBackend::new().expect("Could not create GStreamer backend");
-        run_app(Arc::new(backend)).expect("Could not run app");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = remote::run(&args) {
+        return result;
+    }
+    let options = StartupOptions::from_env()?;
+    if let Some(config) = options.config.clone() {
+        Settings::use_file(config);
+    }
+    // Opened from a `reyvr://` link: let the running instance play it if there is one.
+    if options
+        .link
+        .as_ref()
+        .is_some_and(|link| ipc::open(link).is_ok())
+    {
+        return Ok(());
+    }
+    // Same for files opened from the file manager while it's running.
+    if !options.paths.is_empty() && ipc::control(&Control::Open(options.paths.clone())).is_ok() {
+        return Ok(());
+    }
+    thread::spawn(|| {
+        if let Err(e) = link::register() {
+            eprintln!("Could not register {}:// links: {e}", link::SCHEME);
+        }
+        if let Err(e) = file_types::register() {
+            eprintln!("Could not register as an audio player: {e}");
+        }
     });
-    Ok(())
+
+    let backend = options.backend;
+    let init_backend = async move {
+        match backend {
+            BackendKind::Gstreamer => {
+                GstBackend::init().await?;
+                let backend = GstBackend::new()?;
+                Ok(Arc::new(backend) as Arc<dyn Backend>)
+            }
+        }
+    };
+    if options.headless {
+        headless::run(options, init_backend)
+    } else {
+        run_app(options, init_backend)
+    }
 }
@@ -0,0 +1,70 @@
+//! Controlling the running instance from the command line, for scripts and window-manager key
+//! bindings.
+//!
+//! ```text
+//! reyvr play | pause | next | previous
+//! reyvr enqueue PATH...
+//! reyvr status [--json]
+//! ```
+//!
+//! A file named like a subcommand can still be opened as `./play`.
+
+use std::path::PathBuf;
+
+use anyhow::{Error, anyhow};
+use backend::ipc::{self, Control, Query, Status};
+
+pub const USAGE: &str = "Usage: reyvr <play|pause|next|previous|enqueue PATH...|status [--json]>";
+
+/// Run `args`, without the program name, against the running instance if they start with a
+/// subcommand. `None` if they don't, so the app starts as usual.
+pub fn run(args: &[String]) -> Option<Result<(), Error>> {
+    let (command, rest) = args.split_first()?;
+    let result = match command.as_str() {
+        "play" => ipc::control(&Control::Play),
+        "pause" => ipc::control(&Control::Pause),
+        "next" => ipc::control(&Control::Next),
+        "previous" => ipc::control(&Control::Previous),
+        "enqueue" => enqueue(rest),
+        "status" => status(rest.iter().any(|arg| arg == "--json")),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn enqueue(paths: &[String]) -> Result<(), Error> {
+    if paths.is_empty() {
+        return Err(anyhow!("Nothing to enqueue\n{USAGE}"));
+    }
+    for path in paths {
+        // The app runs elsewhere, so relative paths have to be resolved here.
+        let path = PathBuf::from(path)
+            .canonicalize()
+            .map_err(|e| anyhow!("Can't enqueue {path}: {e}"))?;
+        ipc::control(&Control::Enqueue(path))?;
+    }
+    Ok(())
+}
+
+fn status(json: bool) -> Result<(), Error> {
+    let answer = ipc::request(Query::Status)?;
+    if json {
+        print!("{answer}");
+        return Ok(());
+    }
+    let status: Status = serde_json::from_str(&answer)?;
+    let time = |seconds: u64| format!("{:02}:{:02}", seconds / 60, seconds % 60);
+    if status.title.is_empty() {
+        println!("{}", status.state);
+    } else {
+        println!(
+            "{}: {} - {} ({}/{})",
+            status.state,
+            status.artists.join(", "),
+            status.title,
+            time(status.position),
+            time(status.duration)
+        );
+    }
+    Ok(())
+}
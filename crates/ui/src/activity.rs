@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+use backend::settings::Settings;
+use gpui::Global;
+
+/// Things that redraw continuously and slow down while the window is in the background.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Redraw {
+    Position,
+    Spectrum,
+    Video,
+}
+
+const REDRAWS: usize = 3;
+
+/// Whether the window is in front, and how often continuously updating parts of the UI may
+/// redraw while it isn't. Updates ask [`Activity::due`] first, so a background or minimized
+/// window ticks over at the configured rate and catches up as soon as it is focused again.
+pub struct Activity {
+    pub active: bool,
+    /// Time between redraws in the background, or zero to always redraw at full rate.
+    pub background_interval: Duration,
+    last: [Option<Instant>; REDRAWS],
+}
+
+impl Global for Activity {}
+
+impl Activity {
+    pub fn new(settings: &Settings) -> Self {
+        Activity {
+            active: true,
+            background_interval: Duration::from_millis(settings.background_redraw_ms),
+            last: [None; REDRAWS],
+        }
+    }
+
+    /// Whether `redraw` should happen now. While in the background this is true at most once
+    /// per interval for each kind of redraw.
+    pub fn due(&mut self, redraw: Redraw) -> bool {
+        if self.active || self.background_interval.is_zero() {
+            return true;
+        }
+        let last = &mut self.last[redraw as usize];
+        if last.is_some_and(|at| at.elapsed() < self.background_interval) {
+            return false;
+        }
+        *last = Some(Instant::now());
+        true
+    }
+
+    /// How long a background view should wait before redrawing again, or `None` when it may
+    /// redraw every frame.
+    pub fn throttle(&self) -> Option<Duration> {
+        if self.active || self.background_interval.is_zero() {
+            None
+        } else {
+            Some(self.background_interval)
+        }
+    }
+}
@@ -1,10 +1,42 @@
 use super::{res_handler::*, titlebar::Titlebar};
 use crate::{
-    control_bar::ControlBar, layout::Layout, main_view::MainView, queue_list::QueueList, sidebar::*,
+    chapter_editor::ChapterEditor,
+    control_bar::ControlBar,
+    fonts::UiFont,
+    keybinds::{FocusSearch, RemoveFromQueue},
+    layout::{Layout, Panel},
+    library_settings::LibrarySettings,
+    lyrics_editor::LyricsEditor,
+    main_view::{BrowseMode, MainView, browse},
+    now_playing::PlayerContext,
+    playlist_editor::PlaylistEditor,
+    queue_list::QueueList,
+    rating_overlay::RatingOverlay,
+    report_view::ReportView,
+    scrobble_settings::ScrobbleSettings,
+    settings_view::SettingsView,
+    sidebar::*,
+    smart_editor::SmartPlaylistEditor,
+    toast::Toast,
+    visualizer,
 };
+use backend::{playback::Origin, player::Controller, settings::Settings};
 use components::theme::Theme;
 use gpui::*;
 
+const FONT_FALLBACKS: [&str; 6] = [
+    "Noto Sans",
+    "Noto Sans CJK JP",
+    "Noto Sans Arabic",
+    "Microsoft YaHei",
+    "Segoe UI",
+    "PingFang SC",
+];
+
+/// A panel edge being dragged.
+#[derive(Clone, Render)]
+struct Splitter(Panel);
+
 #[derive(Clone)]
 pub struct Kagi {
     pub titlebar: Entity<Titlebar>,
@@ -14,6 +46,16 @@ pub struct Kagi {
     pub main_view: Entity<MainView>,
     pub layout: Entity<Layout>,
     pub res_handler: Entity<ResHandler>,
+    pub report_view: Entity<ReportView>,
+    pub chapter_editor: Entity<ChapterEditor>,
+    pub lyrics_editor: Entity<LyricsEditor>,
+    pub library_settings: Entity<LibrarySettings>,
+    pub scrobble_settings: Entity<ScrobbleSettings>,
+    pub settings_view: Entity<SettingsView>,
+    pub smart_editor: Entity<SmartPlaylistEditor>,
+    pub playlist_editor: Entity<PlaylistEditor>,
+    pub rating_overlay: Entity<RatingOverlay>,
+    pub toast: Entity<Toast>,
 }
 
 impl Render for Kagi {
@@ -23,15 +65,69 @@ impl Render for Kagi {
         let control_bar = self.clone().control_bar;
         let main_view = self.clone().main_view;
         let queue_list = self.clone().queue_list;
+        let report_view = self.clone().report_view;
+        let chapter_editor = self.clone().chapter_editor;
+        let lyrics_editor = self.clone().lyrics_editor;
+        let library_settings = self.clone().library_settings;
+        let scrobble_settings = self.clone().scrobble_settings;
+        let settings_view = self.clone().settings_view;
+        let smart_editor = self.clone().smart_editor;
+        let playlist_editor = self.clone().playlist_editor;
+        let rating_overlay = self.clone().rating_overlay;
+        let toast = self.clone().toast;
+        win.set_rem_size(px(cx.global::<Settings>().rem_size()));
         self.layout.update(cx, |layout, _| {
             *layout = layout.clone().layout(win.bounds().size.width.0);
         });
+        let layout = self.layout.read(cx).clone();
+        // Drawers aren't resized, only panels beside the main view.
+        let left_splitter = (layout.left_sidebar.show && !layout.left_sidebar.drawer)
+            .then(|| self.render_splitter(Panel::Left, cx));
+        let right_splitter = (layout.right_sidebar.show && !layout.right_sidebar.drawer)
+            .then(|| self.render_splitter(Panel::Right, cx));
+        let scrim = layout.drawer.map(|_| self.render_scrim(cx));
+        let visualizer = cx.global::<PlayerContext>().visualizer.clone();
+        let fullscreen = visualizer
+            .read(cx)
+            .fullscreen
+            .then(|| visualizer::render_fullscreen(visualizer.clone(), cx));
         let theme = cx.global::<Theme>();
+        let accent = theme.accent;
+
+        let mut root = div();
+        let text_style = root.text_style().get_or_insert_with(Default::default);
+        text_style.font_family = cx.global::<UiFont>().0.clone();
+        // Tags can be in any script, so fall back to fonts with CJK, Cyrillic and Arabic
+        // coverage when the chosen or default UI font is missing glyphs.
+        text_style.font_fallbacks = Some(FontFallbacks::from_fonts(
+            FONT_FALLBACKS.iter().map(|font| font.to_string()).collect(),
+        ));
 
-        div()
-            .w_full()
+        root.w_full()
             .h_full()
             .flex_col()
+            // Files and folders dropped from a file manager join the queue, or replace it while
+            // Shift is held.
+            .drag_over::<ExternalPaths>(move |style, _, _, _| style.border_2().border_color(accent))
+            // Actions that need a view rather than just the player.
+            .on_action(cx.listener(|this, _: &FocusSearch, window, cx| {
+                browse(&this.layout, BrowseMode::Search, cx);
+                this.main_view.read(cx).focus_search(window);
+            }))
+            .on_action(cx.listener(|this, _: &RemoveFromQueue, _, cx| {
+                this.queue_list
+                    .update(cx, |queue_list, cx| queue_list.remove_selected(cx));
+            }))
+            .on_drop(|paths: &ExternalPaths, window, cx| {
+                let paths = paths.paths().to_vec();
+                let controller = cx.global::<Controller>();
+                if window.modifiers().shift {
+                    controller.clear_queue();
+                    controller.enqueue_paths(paths, Origin::Dropped, true);
+                } else {
+                    controller.enqueue_paths(paths, Origin::Dropped, false);
+                }
+            })
             .child(titlebar.clone())
             .child(
                 div()
@@ -40,12 +136,107 @@ impl Render for Kagi {
                         win.window_bounds().get_bounds().size.height.0 - (32.0 + 96.0)
                     ))
                     .bg(theme.background)
+                    .relative()
                     .flex()
                     .overflow_hidden()
+                    .on_drag_move(cx.listener(|this, event: &DragMoveEvent<Splitter>, _, cx| {
+                        let panel = event.drag(cx).0;
+                        let position = event.event.position.x;
+                        let width = match panel {
+                            Panel::Left => position - event.bounds.left(),
+                            Panel::Right => event.bounds.right() - position,
+                        };
+                        this.layout.update(cx, |layout, cx| {
+                            layout.resize(panel, width.0);
+                            cx.notify();
+                        });
+                    }))
+                    .on_drop(cx.listener(|this, _: &Splitter, _, cx| {
+                        let layout = this.layout.read(cx).clone();
+                        layout.save(cx);
+                    }))
+                    .children(scrim)
                     .child(sidebar)
+                    .children(left_splitter)
                     .child(main_view)
+                    .children(right_splitter)
                     .child(queue_list),
             )
             .child(control_bar)
+            .children(fullscreen)
+            .child(report_view)
+            .child(chapter_editor)
+            .child(lyrics_editor)
+            .child(library_settings)
+            .child(scrobble_settings)
+            .child(settings_view)
+            .child(smart_editor)
+            .child(playlist_editor)
+            .child(rating_overlay)
+            .child(toast)
+    }
+}
+
+impl Kagi {
+    /// A handle on the inner edge of `panel` to drag it wider or narrower, or double-click to
+    /// give it back its share of the window.
+    fn render_splitter(&self, panel: Panel, cx: &mut Context<Self>) -> impl IntoElement {
+        let layout = self.layout.clone();
+        let theme = cx.global::<Theme>();
+        let id = match panel {
+            Panel::Left => "sidebar_splitter",
+            Panel::Right => "queue_splitter",
+        };
+        div().relative().h_full().w_0().child(
+            // Over the panels, which are drawn deferred themselves.
+            deferred(
+                div()
+                    .id(id)
+                    .absolute()
+                    .top_0()
+                    .left(px(-3.0))
+                    .w(px(6.0))
+                    .h_full()
+                    .cursor_col_resize()
+                    .hover(|this| this.bg(theme.secondary))
+                    .on_drag(Splitter(panel), |splitter, _, _, cx| {
+                        cx.stop_propagation();
+                        cx.new(|_| splitter.clone())
+                    })
+                    .on_mouse_down(MouseButton::Left, move |event, _, cx| {
+                        if event.click_count != 2 {
+                            return;
+                        }
+                        layout.update(cx, |layout, cx| {
+                            layout.panel(panel).dragged_width = None;
+                            layout.save(cx);
+                            cx.notify();
+                        });
+                    }),
+            )
+            .with_priority(1),
+        )
+    }
+
+    /// Dims the main view under an open drawer, closing it when clicked.
+    fn render_scrim(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let layout = self.layout.clone();
+        let theme = cx.global::<Theme>();
+        // Deferred like the drawers, and added before them so it's drawn underneath.
+        deferred(
+            div()
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .on_mouse_down(MouseButton::Left, move |_, _, cx| {
+                    layout.update(cx, |layout, cx| {
+                        layout.drawer = None;
+                        cx.notify();
+                    });
+                }),
+        )
     }
 }
@@ -0,0 +1,281 @@
+use backend::{
+    chapters::{Chapter, ChapterEdit},
+    player::Controller,
+};
+use components::{
+    input::TextInput,
+    theme::{ButtonStyle, Theme},
+};
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::now_playing::PlayerContext;
+
+/// Chapter editor for the current track: set markers at the playback position, name them,
+/// reorder them and export them as a cue sheet.
+pub struct ChapterEditor {
+    /// Chapter being renamed and the input holding its new title.
+    renaming: Option<(usize, Entity<TextInput>)>,
+    draft: String,
+}
+
+pub(crate) fn action(
+    id: impl Into<ElementId>,
+    label: impl Into<SharedString>,
+    button: ButtonStyle,
+) -> Stateful<Div> {
+    div()
+        .id(id)
+        .px_2()
+        .rounded_md()
+        .text_sm()
+        .bg(button.bg)
+        .text_color(button.text)
+        .border_1()
+        .border_color(button.border)
+        .hover(|this| {
+            this.bg(button.hover_bg)
+                .text_color(button.hover_text)
+                .border_color(button.hover_border)
+        })
+        .child(label.into())
+}
+
+impl Render for ChapterEditor {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let context = cx.global::<PlayerContext>().clone();
+        if !*context.editing_chapters.read(cx) {
+            self.renaming = None;
+            return deferred(div().track_focus(&cx.focus_handle())).with_priority(2);
+        }
+        let chapters = context.chapters.read(cx).clone();
+        let position = context.state.read(cx).position;
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let count = chapters.len();
+
+        deferred(
+            div()
+                .track_focus(&cx.focus_handle())
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .child(
+                    div()
+                        .w(px(420.0))
+                        .max_w_full()
+                        .max_h(relative(0.8))
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_color(theme.text)
+                        .child(
+                            div()
+                                .text_xl()
+                                .font_weight(FontWeight::BOLD)
+                                .child("Chapters"),
+                        )
+                        .child(
+                            div()
+                                .id("chapter_list")
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .overflow_y_scroll()
+                                .when(chapters.is_empty(), |this| {
+                                    this.child(
+                                        div().text_sm().text_color(theme.tokens.text.muted).child(
+                                            "No chapters yet. Add a marker while listening.",
+                                        ),
+                                    )
+                                })
+                                .children(chapters.into_iter().enumerate().map(
+                                    |(index, chapter)| {
+                                        self.render_chapter(index, chapter, count, cx)
+                                    },
+                                )),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .justify_end()
+                                .gap_2()
+                                .child(
+                                    action(
+                                        "chapter_add",
+                                        format!(
+                                            "Add marker at {:02}:{:02}",
+                                            position / 60,
+                                            position % 60
+                                        ),
+                                        button,
+                                    )
+                                    .on_click(|_, _, cx| {
+                                        cx.global::<Controller>().edit_chapters(ChapterEdit::Add);
+                                    }),
+                                )
+                                .child(action("chapter_export", "Export .cue", button).on_click(
+                                    |_, _, cx| {
+                                        cx.global::<Controller>().export_cue();
+                                    },
+                                ))
+                                .child(action("chapter_close", "Close", button).on_click({
+                                    let editing = context.editing_chapters.clone();
+                                    move |_, _, cx| {
+                                        editing.update(cx, |this, cx| {
+                                            *this = false;
+                                            cx.notify();
+                                        });
+                                    }
+                                })),
+                        ),
+                ),
+        )
+        .with_priority(2)
+    }
+}
+
+impl ChapterEditor {
+    pub fn new() -> Self {
+        ChapterEditor {
+            renaming: None,
+            draft: String::new(),
+        }
+    }
+
+    /// One marker: its start (click to seek there), its title (click to rename) and buttons to
+    /// move or remove it.
+    fn render_chapter(
+        &self,
+        index: usize,
+        chapter: Chapter,
+        count: usize,
+        cx: &Context<Self>,
+    ) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let row = theme.components.row;
+        let start = chapter.start;
+        let renaming = self
+            .renaming
+            .as_ref()
+            .filter(|(renaming, _)| *renaming == index)
+            .map(|(_, input)| input.clone());
+
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .hover(|this| this.bg(row.hover_bg))
+            .child(
+                div()
+                    .id(("chapter_start", index))
+                    .text_sm()
+                    .text_color(row.subtext)
+                    .hover(|this| this.text_color(theme.accent))
+                    .child(format!("{:02}:{:02}", start / 60, start % 60))
+                    .on_click(move |_, _, cx| {
+                        cx.global::<Controller>().seek(start);
+                    }),
+            )
+            .map(|this| match renaming {
+                Some(input) => this
+                    .child(
+                        div()
+                            .flex_grow()
+                            .px_1()
+                            .rounded_sm()
+                            .border_1()
+                            .border_color(theme.accent)
+                            .child(input),
+                    )
+                    .child(
+                        action(("chapter_save", index), "Save", button).on_click(cx.listener(
+                            |this, _, _, cx| {
+                                this.finish_rename(cx);
+                            },
+                        )),
+                    ),
+                None => this.child(
+                    div()
+                        .id(("chapter_title", index))
+                        .flex_grow()
+                        .truncate()
+                        .text_color(row.text)
+                        .child(chapter.title.clone())
+                        .on_click(cx.listener(move |this, _, window, cx| {
+                            this.start_rename(index, chapter.title.clone(), window, cx);
+                        })),
+                ),
+            })
+            .when(index > 0, |this| {
+                this.child(
+                    action(("chapter_up", index), "↑", button).on_click(move |_, _, cx| {
+                        cx.global::<Controller>()
+                            .edit_chapters(ChapterEdit::Move(index, index - 1));
+                    }),
+                )
+            })
+            .when(index + 1 < count, |this| {
+                this.child(action(("chapter_down", index), "↓", button).on_click(
+                    move |_, _, cx| {
+                        cx.global::<Controller>()
+                            .edit_chapters(ChapterEdit::Move(index, index + 1));
+                    },
+                ))
+            })
+            .child(
+                action(("chapter_remove", index), "✕", button).on_click(cx.listener(
+                    move |this, _, _, cx| {
+                        this.renaming = None;
+                        cx.global::<Controller>()
+                            .edit_chapters(ChapterEdit::Remove(index));
+                    },
+                )),
+            )
+    }
+
+    fn start_rename(
+        &mut self,
+        index: usize,
+        title: String,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let handle = cx.focus_handle();
+        let input = TextInput::new(cx, handle.clone(), Some(title.clone().into()), None);
+        cx.subscribe(&input, |this: &mut Self, _, text: &String, _| {
+            this.draft = text.clone();
+        })
+        .detach();
+        window.focus(&handle);
+        self.draft = title;
+        self.renaming = Some((index, input));
+        cx.notify();
+    }
+
+    fn finish_rename(&mut self, cx: &mut Context<Self>) {
+        if let Some((index, _)) = self.renaming.take() {
+            let title = self.draft.trim().to_string();
+            if !title.is_empty() {
+                cx.global::<Controller>()
+                    .edit_chapters(ChapterEdit::Rename(index, title));
+            }
+        }
+        cx.notify();
+    }
+}
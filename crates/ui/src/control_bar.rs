@@ -1,4 +1,4 @@
-use backend::player::Controller;
+use backend::{playback::ShuffleMode, player::Controller};
 use components::{
     icon::{Icon, Icons},
     slider::Slider,
@@ -6,8 +6,9 @@ use components::{
 };
 use gpui::{prelude::FluentBuilder, *};
 use gstreamer::State;
+use std::time::Duration;
 
-use crate::now_playing::PlayerContext;
+use crate::{layout::COMPACT_CONTROLS_WIDTH, now_playing::PlayerContext};
 
 #[derive(Clone)]
 pub struct ControlBar {
@@ -23,6 +24,8 @@ impl Render for ControlBar {
 
         let meta = cx.global::<PlayerContext>().metadata.read(cx);
         let state = cx.global::<PlayerContext>().state.read(cx);
+        // Narrow windows keep only the playbar and the buttons, packed closer together.
+        let compact = win.bounds().size.width.0 < COMPACT_CONTROLS_WIDTH;
 
         div()
             .track_focus(&cx.focus_handle())
@@ -48,6 +51,7 @@ impl Render for ControlBar {
                     .justify_center()
                     .gap_x_2()
                     .px_3()
+                    .when(compact, |this| this.px_0().text_sm())
                     .mt_3()
                     .pb_1()
                     .text_color(theme.text)
@@ -78,14 +82,7 @@ impl Render for ControlBar {
                     .flex()
                     .items_center()
                     .justify_center()
-                    .child(
-                        div()
-                            .w_full()
-                            .h_full()
-                            .when(win.bounds().size.width.0 < 400.0, |this| {
-                                this.w_auto().flex_grow()
-                            }),
-                    )
+                    .when(!compact, |this| this.child(div().w_full().h_full()))
                     .child(
                         div()
                             .flex()
@@ -95,9 +92,11 @@ impl Render for ControlBar {
                             .justify_center()
                             .overflow_hidden()
                             .gap_x_4()
+                            .when(compact, |this| this.gap_x_3())
                             .child(
                                 div()
                                     .size_6()
+                                    .relative()
                                     .flex()
                                     .flex_col()
                                     .items_center()
@@ -109,6 +108,18 @@ impl Render for ControlBar {
                                             .hover(theme.accent)
                                             .when(state.shuffle, |this| this.color(theme.accent)),
                                     )
+                                    // Marks a playlist shuffled an album at a time.
+                                    .when(state.shuffle_mode == ShuffleMode::Albums, |this| {
+                                        this.child(
+                                            div()
+                                                .absolute()
+                                                .bottom(px(-6.0))
+                                                .right(px(-6.0))
+                                                .text_xs()
+                                                .text_color(theme.accent)
+                                                .child("A"),
+                                        )
+                                    })
                                     .on_mouse_down(MouseButton::Left, {
                                         {
                                             let controller = controller.clone();
@@ -116,6 +127,15 @@ impl Render for ControlBar {
                                                 controller.shuffle();
                                             }
                                         }
+                                    })
+                                    // Right-click switches between shuffling tracks and albums.
+                                    .on_mouse_down(MouseButton::Right, {
+                                        let controller = controller.clone();
+                                        let mode = match state.shuffle_mode {
+                                            ShuffleMode::Tracks => ShuffleMode::Albums,
+                                            ShuffleMode::Albums => ShuffleMode::Tracks,
+                                        };
+                                        move |_, _, _| controller.set_shuffle_mode(mode)
                                     }),
                             )
                             .child(
@@ -148,7 +168,23 @@ impl Render for ControlBar {
                                     .flex_col()
                                     .items_center()
                                     .justify_center()
-                                    .when(state.state == State::Null, |this| {
+                                    .when(state.stalled, |this| {
+                                        this.child(
+                                            Icon::new(Icons::Loading)
+                                                .size(24.0)
+                                                .color(theme.accent)
+                                                .with_animation(
+                                                    "buffering",
+                                                    Animation::new(Duration::from_secs(1)).repeat(),
+                                                    |icon, delta| {
+                                                        icon.transformation(Transformation::rotate(
+                                                            percentage(delta),
+                                                        ))
+                                                    },
+                                                ),
+                                        )
+                                    })
+                                    .when(!state.stalled && state.state == State::Null, |this| {
                                         this.child(
                                             Icon::new(Icons::Stopped)
                                                 .size(24.0)
@@ -156,7 +192,7 @@ impl Render for ControlBar {
                                                 .hover(theme.accent),
                                         )
                                     })
-                                    .when(state.state == State::Playing, |this| {
+                                    .when(!state.stalled && state.state == State::Playing, |this| {
                                         this.child(
                                             Icon::new(Icons::Pause)
                                                 .size(24.0)
@@ -164,7 +200,7 @@ impl Render for ControlBar {
                                                 .hover(theme.accent),
                                         )
                                     })
-                                    .when(state.state == State::Paused, |this| {
+                                    .when(!state.stalled && state.state == State::Paused, |this| {
                                         this.child(
                                             Icon::new(Icons::Play)
                                                 .size(24.0)
@@ -227,33 +263,49 @@ impl Render for ControlBar {
                                     )
                                     .on_mouse_down(MouseButton::Left, {
                                         {
+                                            let controller = controller.clone();
                                             move |_, _, cx| {
-                                                state_write.update(cx, |this, _| {
-                                                    this.repeat = !this.repeat
-                                                })
+                                                let repeat = state_write.update(cx, |this, _| {
+                                                    this.repeat = !this.repeat;
+                                                    this.repeat
+                                                });
+                                                controller.set_repeat(repeat);
                                             }
                                         }
                                     }),
                             ),
                     )
-                    .child(
-                        div()
-                            .flex()
-                            .items_center()
-                            .justify_end()
-                            .h_auto()
-                            .w_full()
-                            .px_2()
-                            .child(div().w_20().child(self.vol_slider.clone()))
-                            .child(
-                                div()
-                                    .child(format!("{:.0}%", state.volume * 100.0))
-                                    .text_color(theme.text)
-                                    .ml_4()
-                                    .w_10()
-                                    .overflow_hidden(),
-                            ),
-                    ),
+                    .when(!compact, |this| {
+                        this.child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .justify_end()
+                                .h_auto()
+                                .w_full()
+                                .px_2()
+                                .child(div().w_20().child(self.vol_slider.clone()))
+                                .child(
+                                    div()
+                                        .child(match (&meta.stream_info, state.exclusive) {
+                                            (Some(info), true) => format!(
+                                                "{}/{}",
+                                                info.sample_rate / 1000,
+                                                info.bit_depth
+                                            ),
+                                            (None, true) => "Excl.".to_string(),
+                                            _ => format!("{:.0}%", state.volume * 100.0),
+                                        })
+                                        .text_color(match meta.stream_info.as_ref() {
+                                            Some(info) if info.bit_perfect => theme.accent,
+                                            _ => theme.text,
+                                        })
+                                        .ml_4()
+                                        .w_10()
+                                        .overflow_hidden(),
+                                ),
+                        )
+                    }),
             )
     }
 }
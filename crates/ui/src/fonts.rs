@@ -0,0 +1,72 @@
+//! The interface font: any installed family, or one from a font file in the `fonts` folder
+//! beside the settings file, which are registered at startup.
+
+use std::{borrow::Cow, fs, path::PathBuf};
+
+use backend::settings::Settings;
+use gpui::*;
+
+use crate::toast;
+
+/// Where font files to register are kept.
+pub fn fonts_dir() -> Option<PathBuf> {
+    Settings::get_settings_file().map(|file| file.with_file_name("fonts"))
+}
+
+/// Register the font files in [`fonts_dir`], so their families can be picked like installed
+/// ones.
+pub fn register(cx: &mut App) {
+    let Some(entries) = fonts_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return;
+    };
+    let fonts: Vec<Cow<'static, [u8]>> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|extension| {
+                ["ttf", "otf", "ttc"]
+                    .iter()
+                    .any(|font| extension.eq_ignore_ascii_case(font))
+            })
+        })
+        .filter_map(|path| match fs::read(&path) {
+            Ok(bytes) => Some(Cow::Owned(bytes)),
+            Err(e) => {
+                eprintln!("Could not read font {}: {e}", path.display());
+                None
+            }
+        })
+        .collect();
+    if let Err(e) = cx.text_system().add_fonts(fonts) {
+        eprintln!("Could not register fonts: {e}");
+    }
+}
+
+/// Family the views are drawn in, or `None` for gpui's default.
+pub struct UiFont(pub Option<SharedString>);
+
+impl Global for UiFont {}
+
+/// Switch to the family the settings name, keeping the default when it isn't available.
+pub fn apply(cx: &mut App) {
+    let Some(wanted) = cx.global::<Settings>().font_family.clone() else {
+        cx.set_global(UiFont(None));
+        cx.refresh_windows();
+        return;
+    };
+    let family = find(&wanted, cx);
+    if family.is_none() {
+        let message = format!("Font {wanted} isn't installed, so the default is used");
+        eprintln!("{message}");
+        toast::show(message, cx);
+    }
+    cx.set_global(UiFont(family.map(SharedString::from)));
+    cx.refresh_windows();
+}
+
+/// The installed or registered family named `family`, however it's capitalized.
+pub fn find(family: &str, cx: &App) -> Option<String> {
+    cx.text_system()
+        .all_font_names()
+        .into_iter()
+        .find(|name| name.eq_ignore_ascii_case(family.trim()))
+}
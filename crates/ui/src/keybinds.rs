@@ -1,7 +1,18 @@
-use crate::now_playing::PlayerContext;
-use backend::player::Controller;
+use crate::{
+    activity::Activity, fonts, now_playing::PlayerContext, settings_view::save_settings, themes,
+    toast,
+};
+use backend::{
+    keymap::Keymap,
+    playback::Origin,
+    player::Controller,
+    report::ProblemReport,
+    settings::{MAX_PREAMP_DB, MAX_UI_SCALE, MIN_PREAMP_DB, MIN_UI_SCALE, Settings},
+};
+use components::input;
 use gpui::*;
 use gstreamer::State;
+use std::{collections::HashSet, time::Duration};
 
 actions!(kagi, [
     ChangeState,
@@ -10,9 +21,262 @@ actions!(kagi, [
     VolUp,
     VolDown,
     SeekForward,
-    SeekBackward
+    SeekBackward,
+    RevealTrack,
+    OpenContainingFolder,
+    ToggleMono,
+    BalanceLeft,
+    BalanceRight,
+    ToggleSkipSilence,
+    ToggleExclusive,
+    TogglePauseOnOtherAudio,
+    ListenLater,
+    PreampUp,
+    PreampDown,
+    ToggleRomanize,
+    ToggleLoudness,
+    ToggleLowMemory,
+    ToggleSource,
+    RemoveListenLater,
+    ReportProblem,
+    EditChapters,
+    NextChapter,
+    PreviousChapter,
+    EditLyrics,
+    ShareLink,
+    RateOne,
+    RateTwo,
+    RateThree,
+    RateFour,
+    RateFive,
+    FocusSearch,
+    OpenFolder,
+    RemoveFromQueue,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom
 ]);
 
+/// Keys each action is bound to unless the keymap says otherwise, with the context they apply in.
+/// An action can be listed more than once to have several default keys.
+const DEFAULT_KEYS: &[(&str, &str, Option<&str>)] = &[
+    ("ChangeState", "space", None),
+    ("Prev", "ctrl-left", None),
+    ("Prev", "p", Some("!TextInput")),
+    ("Next", "ctrl-right", None),
+    ("Next", "n", Some("!TextInput")),
+    ("SeekBackward", "left", None),
+    ("SeekForward", "right", None),
+    ("VolUp", "up", None),
+    ("VolDown", "down", None),
+    ("RevealTrack", "ctrl-shift-r", None),
+    ("OpenContainingFolder", "ctrl-shift-o", None),
+    ("ToggleMono", "ctrl-m", None),
+    ("BalanceLeft", "alt-left", None),
+    ("BalanceRight", "alt-right", None),
+    ("ToggleSkipSilence", "ctrl-shift-s", None),
+    ("ToggleExclusive", "ctrl-shift-e", None),
+    ("TogglePauseOnOtherAudio", "ctrl-shift-a", None),
+    ("ListenLater", "ctrl-l", None),
+    ("PreampUp", "ctrl-alt-up", None),
+    ("PreampDown", "ctrl-alt-down", None),
+    ("ToggleRomanize", "ctrl-shift-t", None),
+    ("ToggleLoudness", "ctrl-shift-u", None),
+    ("ToggleLowMemory", "ctrl-shift-m", None),
+    ("ToggleSource", "ctrl-shift-l", None),
+    ("RemoveListenLater", "ctrl-alt-l", None),
+    ("ReportProblem", "f1", None),
+    ("EditChapters", "ctrl-shift-c", None),
+    ("NextChapter", "ctrl-shift-right", None),
+    ("PreviousChapter", "ctrl-shift-left", None),
+    ("EditLyrics", "ctrl-shift-y", None),
+    ("ShareLink", "ctrl-shift-k", None),
+    // Number keys rate the current track, except while typing.
+    ("RateOne", "1", Some("!TextInput")),
+    ("RateTwo", "2", Some("!TextInput")),
+    ("RateThree", "3", Some("!TextInput")),
+    ("RateFour", "4", Some("!TextInput")),
+    ("RateFive", "5", Some("!TextInput")),
+    ("FocusSearch", "ctrl-f", None),
+    ("OpenFolder", "ctrl-o", None),
+    // Removes the selected queue tracks, or the current one.
+    ("RemoveFromQueue", "delete", Some("!TextInput")),
+    ("ZoomIn", "ctrl-=", None),
+    ("ZoomIn", "ctrl-+", None),
+    ("ZoomOut", "ctrl--", None),
+    ("ResetZoom", "ctrl-0", None),
+];
+
+fn binding(action: &str, keys: &str, context: Option<&str>) -> Option<KeyBinding> {
+    Some(match action {
+        "ChangeState" => KeyBinding::new(keys, ChangeState, context),
+        "Prev" => KeyBinding::new(keys, Prev, context),
+        "Next" => KeyBinding::new(keys, Next, context),
+        "SeekBackward" => KeyBinding::new(keys, SeekBackward, context),
+        "SeekForward" => KeyBinding::new(keys, SeekForward, context),
+        "VolUp" => KeyBinding::new(keys, VolUp, context),
+        "VolDown" => KeyBinding::new(keys, VolDown, context),
+        "RevealTrack" => KeyBinding::new(keys, RevealTrack, context),
+        "OpenContainingFolder" => KeyBinding::new(keys, OpenContainingFolder, context),
+        "ToggleMono" => KeyBinding::new(keys, ToggleMono, context),
+        "BalanceLeft" => KeyBinding::new(keys, BalanceLeft, context),
+        "BalanceRight" => KeyBinding::new(keys, BalanceRight, context),
+        "ToggleSkipSilence" => KeyBinding::new(keys, ToggleSkipSilence, context),
+        "ToggleExclusive" => KeyBinding::new(keys, ToggleExclusive, context),
+        "TogglePauseOnOtherAudio" => KeyBinding::new(keys, TogglePauseOnOtherAudio, context),
+        "ListenLater" => KeyBinding::new(keys, ListenLater, context),
+        "PreampUp" => KeyBinding::new(keys, PreampUp, context),
+        "PreampDown" => KeyBinding::new(keys, PreampDown, context),
+        "ToggleRomanize" => KeyBinding::new(keys, ToggleRomanize, context),
+        "ToggleLoudness" => KeyBinding::new(keys, ToggleLoudness, context),
+        "ToggleLowMemory" => KeyBinding::new(keys, ToggleLowMemory, context),
+        "ToggleSource" => KeyBinding::new(keys, ToggleSource, context),
+        "RemoveListenLater" => KeyBinding::new(keys, RemoveListenLater, context),
+        "ReportProblem" => KeyBinding::new(keys, ReportProblem, context),
+        "EditChapters" => KeyBinding::new(keys, EditChapters, context),
+        "NextChapter" => KeyBinding::new(keys, NextChapter, context),
+        "PreviousChapter" => KeyBinding::new(keys, PreviousChapter, context),
+        "EditLyrics" => KeyBinding::new(keys, EditLyrics, context),
+        "ShareLink" => KeyBinding::new(keys, ShareLink, context),
+        "RateOne" => KeyBinding::new(keys, RateOne, context),
+        "RateTwo" => KeyBinding::new(keys, RateTwo, context),
+        "RateThree" => KeyBinding::new(keys, RateThree, context),
+        "RateFour" => KeyBinding::new(keys, RateFour, context),
+        "RateFive" => KeyBinding::new(keys, RateFive, context),
+        "FocusSearch" => KeyBinding::new(keys, FocusSearch, context),
+        "OpenFolder" => KeyBinding::new(keys, OpenFolder, context),
+        "RemoveFromQueue" => KeyBinding::new(keys, RemoveFromQueue, context),
+        "ZoomIn" => KeyBinding::new(keys, ZoomIn, context),
+        "ZoomOut" => KeyBinding::new(keys, ZoomOut, context),
+        "ResetZoom" => KeyBinding::new(keys, ResetZoom, context),
+        _ => return None,
+    })
+}
+
+/// Replace every key binding with the defaults, overridden by `keymap`. Returns what was wrong
+/// with the keymap; bad entries leave the action on its default keys.
+pub fn bind_keys(keymap: &Keymap, cx: &mut App) -> Vec<String> {
+    let mut problems: Vec<String> = keymap
+        .bindings
+        .keys()
+        .filter(|action| !DEFAULT_KEYS.iter().any(|(name, _, _)| name == action))
+        .map(|action| format!("Unknown action {action} in keymap"))
+        .collect();
+
+    let mut bindings = vec![];
+    let mut overridden = HashSet::new();
+    for &(action, default, context) in DEFAULT_KEYS {
+        let keys = match keymap.bindings.get(action) {
+            None => default,
+            // Keys from the keymap replace all of an action's default keys, so bind them once.
+            Some(_) if !overridden.insert(action) => continue,
+            // An empty binding unbinds the action.
+            Some(keys) if keys.trim().is_empty() => continue,
+            // Checked first, since a binding that doesn't parse panics.
+            Some(keys) if !parses(keys) => {
+                problems.push(format!("Invalid keys {keys:?} for {action}"));
+                default
+            }
+            Some(keys) => keys.trim(),
+        };
+        bindings.extend(binding(action, keys, context));
+    }
+    // Up and down move through library search results rather than the volume.
+    bindings.push(KeyBinding::new(
+        "up",
+        input::Previous,
+        Some("LibrarySearch"),
+    ));
+    bindings.push(KeyBinding::new("down", input::Next, Some("LibrarySearch")));
+
+    cx.clear_key_bindings();
+    input::bind_actions(cx);
+    cx.bind_keys(bindings);
+    problems
+}
+
+fn parses(keys: &str) -> bool {
+    keys.split_whitespace()
+        .all(|key| Keystroke::parse(key).is_ok())
+}
+
+/// Switch to `settings`, applying whatever differs from the ones running, and return the names
+/// of the settings that changed.
+pub fn apply_settings(settings: Settings, cx: &mut App) -> Vec<String> {
+    let previous = cx.global::<Settings>().clone();
+    let changed = previous.changes(&settings);
+    if changed.is_empty() {
+        return changed;
+    }
+    let controller = cx.global::<Controller>();
+    // Applying settings only ever turns these on.
+    if previous.exclusive_mode && !settings.exclusive_mode {
+        controller.set_exclusive(false);
+    }
+    if previous.low_memory && !settings.low_memory {
+        controller.set_low_memory(false);
+    }
+    if previous.pause_on_other_audio && !settings.pause_on_other_audio {
+        controller.set_pause_on_other_audio(false);
+    }
+    controller.apply_settings(&settings);
+    controller.record_settings(changed.clone());
+    controller.get_meta();
+    controller.get_queue();
+    cx.global_mut::<Activity>().background_interval =
+        Duration::from_millis(settings.background_redraw_ms);
+    let retheme = previous.theme != settings.theme || previous.appearance != settings.appearance;
+    let theme = retheme.then(|| {
+        themes::watch(&settings, cx);
+        themes::load(&settings, cx)
+    });
+    cx.set_global(settings);
+    // Tinting reads the settings just switched to.
+    if let Some(theme) = theme {
+        themes::set(theme, cx);
+    } else if previous.art_accent != cx.global::<Settings>().art_accent {
+        themes::apply(cx);
+    }
+    if previous.font_family != cx.global::<Settings>().font_family {
+        fonts::apply(cx);
+    }
+    changed
+}
+
+/// Read the settings and keymap files again after either changed on disk, apply whatever
+/// differs from what's running, and sum it up in a toast.
+pub fn reload_config(cx: &mut App) {
+    let mut summary = vec![];
+
+    match Settings::try_load() {
+        Ok(settings) => {
+            let changed = apply_settings(settings, cx);
+            if !changed.is_empty() {
+                summary.push(format!("Settings changed: {}", changed.join(", ")));
+            }
+        }
+        Err(e) => summary.push(format!("Settings not reloaded: {e}")),
+    }
+
+    match Keymap::load() {
+        Ok(keymap) => {
+            let changed = cx.global::<Keymap>().changes(&keymap);
+            if !changed.is_empty() {
+                let problems = bind_keys(&keymap, cx);
+                summary.push(format!("Keys changed: {}", changed.join(", ")));
+                summary.extend(problems);
+                cx.set_global(keymap);
+            }
+        }
+        Err(e) => summary.push(format!("Keymap not reloaded: {e}")),
+    }
+
+    if !summary.is_empty() {
+        cx.refresh_windows();
+        toast::show(summary.join("; "), cx);
+    }
+}
+
 pub fn register(cx: &mut App) {
     cx.on_action(change_state);
     cx.on_action(next);
@@ -21,15 +285,49 @@ pub fn register(cx: &mut App) {
     cx.on_action(vol_down);
     cx.on_action(seek_forward);
     cx.on_action(seek_backward);
-    cx.bind_keys([
-        KeyBinding::new("space", ChangeState, None),
-        KeyBinding::new("ctrl-left", Prev, None),
-        KeyBinding::new("ctrl-right", Next, None),
-        KeyBinding::new("left", SeekBackward, None),
-        KeyBinding::new("right", SeekForward, None),
-        KeyBinding::new("up", VolUp, None),
-        KeyBinding::new("down", VolDown, None),
-    ]);
+    cx.on_action(reveal_track);
+    cx.on_action(open_containing_folder);
+    cx.on_action(toggle_mono);
+    cx.on_action(balance_left);
+    cx.on_action(balance_right);
+    cx.on_action(toggle_skip_silence);
+    cx.on_action(toggle_exclusive);
+    cx.on_action(toggle_pause_on_other_audio);
+    cx.on_action(listen_later);
+    cx.on_action(preamp_up);
+    cx.on_action(preamp_down);
+    cx.on_action(toggle_romanize);
+    cx.on_action(toggle_loudness);
+    cx.on_action(toggle_low_memory);
+    cx.on_action(toggle_source);
+    cx.on_action(remove_listen_later);
+    cx.on_action(report_problem);
+    cx.on_action(edit_chapters);
+    cx.on_action(next_chapter);
+    cx.on_action(previous_chapter);
+    cx.on_action(edit_lyrics);
+    cx.on_action(share_link);
+    cx.on_action(rate_one);
+    cx.on_action(rate_two);
+    cx.on_action(rate_three);
+    cx.on_action(rate_four);
+    cx.on_action(rate_five);
+    cx.on_action(open_folder);
+    cx.on_action(zoom_in);
+    cx.on_action(zoom_out);
+    cx.on_action(reset_zoom);
+    let keymap = Keymap::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        Keymap::default()
+    });
+    for problem in bind_keys(&keymap, cx) {
+        eprintln!("{problem}");
+    }
+    cx.set_global(keymap);
+    cx.set_menus(vec![Menu {
+        name: "Help".into(),
+        items: vec![MenuItem::action("Report a problem", ReportProblem)],
+    }]);
 }
 
 pub fn change_state(_: &ChangeState, cx: &mut App) {
@@ -51,24 +349,24 @@ fn previous(_: &Prev, cx: &mut App) {
 }
 
 fn vol_up(_: &VolUp, cx: &mut App) {
-    let state = cx.global_mut::<PlayerContext>().state.clone();
-
     let curr_vol = cx.global::<PlayerContext>().state.read(cx).volume;
-    let new_vol = (curr_vol + 0.05).clamp(0.0, 1.0);
-
-    state.update(cx, |this, cx| {
-        this.volume = new_vol.clone();
-        this.vol(cx, new_vol);
-        cx.notify();
-    });
-    cx.global::<Controller>().volume(new_vol);
+    set_volume(curr_vol + 0.05, cx);
 }
 
 fn vol_down(_: &VolDown, cx: &mut App) {
+    let curr_vol = cx.global::<PlayerContext>().state.read(cx).volume;
+    set_volume(curr_vol - 0.05, cx);
+}
+
+/// Set the volume, kept within what the settings allow. Exclusive mode holds it where it is.
+pub fn set_volume(volume: f64, cx: &mut App) {
+    if cx.global::<PlayerContext>().state.read(cx).exclusive {
+        return;
+    }
     let state = cx.global_mut::<PlayerContext>().state.clone();
 
-    let curr_vol = cx.global::<PlayerContext>().state.read(cx).volume;
-    let new_vol = (curr_vol - 0.05).clamp(0.0, 1.0);
+    let max_vol = cx.global::<Settings>().max_volume();
+    let new_vol = volume.clamp(0.0, max_vol);
 
     state.update(cx, |this, cx| {
         this.volume = new_vol.clone();
@@ -90,3 +388,240 @@ fn seek_backward(_: &SeekBackward, cx: &mut App) {
     cx.global::<Controller>()
         .seek(current_pos.saturating_sub(5));
 }
+
+fn open_folder(_: &OpenFolder, cx: &mut App) {
+    cx.global::<Controller>().open_folder();
+}
+
+fn zoom_in(_: &ZoomIn, cx: &mut App) {
+    let scale = cx.global::<Settings>().ui_scale;
+    set_ui_scale(scale + 0.1, cx);
+}
+
+fn zoom_out(_: &ZoomOut, cx: &mut App) {
+    let scale = cx.global::<Settings>().ui_scale;
+    set_ui_scale(scale - 0.1, cx);
+}
+
+fn reset_zoom(_: &ResetZoom, cx: &mut App) {
+    set_ui_scale(1.0, cx);
+}
+
+fn set_ui_scale(scale: f32, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    // Steps of a tenth, so repeated zooming doesn't drift.
+    settings.ui_scale = ((scale * 10.0).round() / 10.0).clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+    let scale = settings.ui_scale;
+    save_settings(cx);
+    cx.refresh_windows();
+    toast::show(format!("Zoom {:.0}%", scale * 100.0), cx);
+}
+
+fn reveal_track(_: &RevealTrack, cx: &mut App) {
+    cx.global::<Controller>().reveal_current();
+}
+
+fn open_containing_folder(_: &OpenContainingFolder, cx: &mut App) {
+    cx.global::<Controller>().open_containing_folder();
+}
+
+fn listen_later(_: &ListenLater, cx: &mut App) {
+    cx.global::<Controller>().listen_later();
+}
+
+fn remove_listen_later(_: &RemoveListenLater, cx: &mut App) {
+    cx.global::<Controller>().remove_from_listen_later();
+}
+
+fn edit_chapters(_: &EditChapters, cx: &mut App) {
+    let editing = cx.global::<PlayerContext>().editing_chapters.clone();
+    editing.update(cx, |this, cx| {
+        *this = !*this;
+        cx.notify();
+    });
+}
+
+fn next_chapter(_: &NextChapter, cx: &mut App) {
+    cx.global::<Controller>().next_chapter();
+}
+
+fn previous_chapter(_: &PreviousChapter, cx: &mut App) {
+    cx.global::<Controller>().previous_chapter();
+}
+
+fn edit_lyrics(_: &EditLyrics, cx: &mut App) {
+    let editing = cx.global::<PlayerContext>().editing_lyrics.clone();
+    editing.update(cx, |this, cx| {
+        *this = !*this;
+        cx.notify();
+    });
+}
+
+/// Copy a `reyvr://` link to the current track at the current position.
+fn share_link(_: &ShareLink, cx: &mut App) {
+    cx.global::<Controller>().share_link();
+}
+
+fn rate_one(_: &RateOne, cx: &mut App) {
+    cx.global::<Controller>().rate(1);
+}
+
+fn rate_two(_: &RateTwo, cx: &mut App) {
+    cx.global::<Controller>().rate(2);
+}
+
+fn rate_three(_: &RateThree, cx: &mut App) {
+    cx.global::<Controller>().rate(3);
+}
+
+fn rate_four(_: &RateFour, cx: &mut App) {
+    cx.global::<Controller>().rate(4);
+}
+
+fn rate_five(_: &RateFive, cx: &mut App) {
+    cx.global::<Controller>().rate(5);
+}
+
+/// Open the problem report composer with the current environment filled in.
+fn report_problem(_: &ReportProblem, cx: &mut App) {
+    let context = cx.global::<PlayerContext>().clone();
+    let settings = cx.global::<Settings>();
+    let state = context.state.read(cx);
+    let meta = context.metadata.read(cx);
+
+    let mut diagnostics = vec![
+        ("Playback state", format!("{:?}", state.state)),
+        ("Queue length", context.tracks.read(cx).len().to_string()),
+        ("Exclusive mode", settings.exclusive_mode.to_string()),
+        ("Mono", settings.mono.to_string()),
+        ("Skip silence", settings.skip_silence.to_string()),
+        ("Pre-amp", format!("{:+.0} dB", settings.preamp_db)),
+        ("Balance", format!("{:.1}", settings.balance)),
+    ];
+    if let Some(info) = &meta.stream_info {
+        diagnostics.push((
+            "Stream",
+            format!(
+                "{}, {} Hz, {} channels",
+                info.codec, info.sample_rate, info.channels
+            ),
+        ));
+    }
+    if meta.origin != Origin::Unknown {
+        diagnostics.push(("Track origin", meta.origin.label().to_string()));
+    }
+    let report = ProblemReport::new(
+        env!("CARGO_PKG_VERSION"),
+        gstreamer::version_string().to_string(),
+        diagnostics
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+        context.log.read(cx).lines(),
+    );
+    context.report.update(cx, |this, cx| {
+        *this = Some(report);
+        cx.notify();
+    });
+}
+
+fn toggle_source(_: &ToggleSource, cx: &mut App) {
+    cx.global::<Controller>().toggle_source();
+}
+
+fn toggle_mono(_: &ToggleMono, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    settings.mono = !settings.mono;
+    let mono = settings.mono;
+    save_settings(cx);
+    cx.global::<Controller>().set_mono(mono);
+}
+
+fn toggle_skip_silence(_: &ToggleSkipSilence, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    settings.skip_silence = !settings.skip_silence;
+    let skip_silence = settings.skip_silence;
+    save_settings(cx);
+    cx.global::<Controller>().set_skip_silence(skip_silence);
+}
+
+fn toggle_exclusive(_: &ToggleExclusive, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    settings.exclusive_mode = !settings.exclusive_mode;
+    let exclusive = settings.exclusive_mode;
+    save_settings(cx);
+    cx.global::<Controller>().set_exclusive(exclusive);
+}
+
+fn toggle_pause_on_other_audio(_: &TogglePauseOnOtherAudio, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    settings.pause_on_other_audio = !settings.pause_on_other_audio;
+    let enabled = settings.pause_on_other_audio;
+    save_settings(cx);
+    cx.global::<Controller>().set_pause_on_other_audio(enabled);
+}
+
+fn toggle_loudness(_: &ToggleLoudness, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    settings.show_loudness = !settings.show_loudness;
+    save_settings(cx);
+    cx.refresh_windows();
+}
+
+fn toggle_low_memory(_: &ToggleLowMemory, cx: &mut App) {
+    let enabled = !cx.global::<Settings>().low_memory;
+    set_low_memory(enabled, cx);
+    cx.refresh_windows();
+}
+
+/// Turn low-memory mode on or off, fetching the queue again so its rows gain or drop artwork.
+pub fn set_low_memory(enabled: bool, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    settings.low_memory = enabled;
+    save_settings(cx);
+    let controller = cx.global::<Controller>();
+    controller.set_low_memory(enabled);
+    controller.get_queue();
+}
+
+fn toggle_romanize(_: &ToggleRomanize, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    settings.romanize = !settings.romanize;
+    save_settings(cx);
+    // Views show whatever was last received, so fetch everything again to redraw it.
+    let controller = cx.global::<Controller>();
+    controller.get_meta();
+    controller.get_queue();
+}
+
+fn balance_left(_: &BalanceLeft, cx: &mut App) {
+    adjust_balance(cx, -0.1);
+}
+
+fn balance_right(_: &BalanceRight, cx: &mut App) {
+    adjust_balance(cx, 0.1);
+}
+
+fn adjust_balance(cx: &mut App, delta: f32) {
+    let settings = cx.global_mut::<Settings>();
+    settings.balance = (settings.balance + delta).clamp(-1.0, 1.0);
+    let balance = settings.balance;
+    save_settings(cx);
+    cx.global::<Controller>().set_balance(balance);
+}
+
+fn preamp_up(_: &PreampUp, cx: &mut App) {
+    adjust_preamp(cx, 1.0);
+}
+
+fn preamp_down(_: &PreampDown, cx: &mut App) {
+    adjust_preamp(cx, -1.0);
+}
+
+fn adjust_preamp(cx: &mut App, delta: f64) {
+    let settings = cx.global_mut::<Settings>();
+    settings.preamp_db = (settings.preamp_db + delta).clamp(MIN_PREAMP_DB, MAX_PREAMP_DB);
+    let preamp_db = settings.preamp_db;
+    save_settings(cx);
+    cx.global::<Controller>().set_preamp(preamp_db);
+}
@@ -1,7 +1,27 @@
+use backend::settings::Settings;
+use gpui::{App, Context};
+
+use crate::main_view::BrowseMode;
+
 const MIN_CENTRAL_WIDTH: f32 = 200.0;
 const LEFT_PCT: f32 = 0.20;
 const RIGHT_PCT: f32 = 0.33;
+/// Below this width every panel is a drawer.
 const OVERLAY_THRESHOLD: f32 = 640.0;
+/// Below this width the control bar drops the volume and packs the buttons closer.
+pub const COMPACT_CONTROLS_WIDTH: f32 = 560.0;
+/// Narrowest the sidebar and queue can be dragged. Dragging one under half of this collapses it.
+pub const MIN_LEFT_WIDTH: f32 = 200.0;
+pub const MIN_RIGHT_WIDTH: f32 = 280.0;
+
+/// The panels either side of the main view.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Panel {
+    /// The sidebar.
+    Left,
+    /// The queue.
+    Right,
+}
 
 #[derive(Clone, PartialEq)]
 pub enum LayoutMode {
@@ -16,6 +36,10 @@ pub struct Layout {
     pub right_sidebar: SidebarLayout,
     pub central_width: f32,
     pub mode: LayoutMode,
+    /// What the main view shows, switched from its tabs and from the sidebar.
+    pub browse: BrowseMode,
+    /// Panel opened over the main view because it doesn't fit beside it.
+    pub drawer: Option<Panel>,
 }
 
 #[derive(Clone)]
@@ -23,6 +47,12 @@ pub struct SidebarLayout {
     pub show: bool,
     pub width: f32,
     pub should_show: bool,
+    /// Width the panel was dragged to, or `None` for its share of the window.
+    pub dragged_width: Option<f32>,
+    /// There's room for it beside the main view.
+    pub fits: bool,
+    /// Drawn over the main view as a drawer, since it doesn't fit beside it.
+    pub drawer: bool,
 }
 
 #[derive(Clone)]
@@ -32,23 +62,65 @@ pub enum CentralLayout {
 }
 
 impl SidebarLayout {
-    pub fn new() -> Self {
+    pub fn new(should_show: bool, dragged_width: Option<f32>) -> Self {
         SidebarLayout {
-            show: true,
+            show: should_show,
             width: 0.0,
-            should_show: true,
+            should_show,
+            dragged_width,
+            fits: true,
+            drawer: false,
         }
     }
 }
 
 impl Layout {
-    pub fn new() -> Layout {
+    /// The panels as they were last left, from the settings.
+    pub fn new(settings: &Settings) -> Layout {
         Layout {
-            left_sidebar: SidebarLayout::new(),
+            left_sidebar: SidebarLayout::new(settings.show_sidebar, settings.sidebar_width),
             central: CentralLayout::List,
-            right_sidebar: SidebarLayout::new(),
+            right_sidebar: SidebarLayout::new(settings.show_queue, settings.queue_width),
             central_width: 0.0,
             mode: LayoutMode::Inline,
+            browse: BrowseMode::NowPlaying,
+            drawer: None,
+        }
+    }
+
+    pub fn panel(&mut self, panel: Panel) -> &mut SidebarLayout {
+        match panel {
+            Panel::Left => &mut self.left_sidebar,
+            Panel::Right => &mut self.right_sidebar,
+        }
+    }
+
+    /// Drag `panel` to `width`, as far as the main view leaves room for. Dragging it well under
+    /// its narrowest collapses it instead.
+    pub fn resize(&mut self, panel: Panel, width: f32) {
+        let min = match panel {
+            Panel::Left => MIN_LEFT_WIDTH,
+            Panel::Right => MIN_RIGHT_WIDTH,
+        };
+        // Whatever the main view can spare beyond its narrowest.
+        let max = (self.central_width + self.panel(panel).width - MIN_CENTRAL_WIDTH).max(min);
+        let sidebar = self.panel(panel);
+        if width < min / 2.0 {
+            sidebar.should_show = false;
+            return;
+        }
+        sidebar.dragged_width = Some(width.clamp(min, max));
+    }
+
+    /// Remember which panels are shown and how wide they were dragged in the settings.
+    pub fn save(&self, cx: &mut App) {
+        let settings = cx.global_mut::<Settings>();
+        settings.show_sidebar = self.left_sidebar.should_show;
+        settings.show_queue = self.right_sidebar.should_show;
+        settings.sidebar_width = self.left_sidebar.dragged_width;
+        settings.queue_width = self.right_sidebar.dragged_width;
+        if let Err(e) = settings.save() {
+            eprintln!("Could not save the layout: {e}");
         }
     }
 
@@ -60,94 +132,74 @@ impl Layout {
         self.right_sidebar.clone()
     }
 
+    /// Open or close `panel` from its titlebar button: as a drawer over the main view when it
+    /// doesn't fit beside it, or else by showing or collapsing it.
+    pub fn toggle(&mut self, panel: Panel, cx: &mut Context<Self>) {
+        if self.drawer == Some(panel) {
+            self.drawer = None;
+        } else if self.panel(panel).fits {
+            let sidebar = self.panel(panel);
+            sidebar.should_show = !sidebar.should_show;
+            self.save(cx);
+        } else {
+            self.drawer = Some(panel);
+        }
+        cx.notify();
+    }
+
     /// Recalculates the layout based on the provided window_width.
     pub fn layout(mut self, window_width: f32) -> Self {
-        if window_width < OVERLAY_THRESHOLD {
-            // Enter overlay mode
-            self.mode = LayoutMode::Overlay;
-            // Main content always takes the full width in overlay mode.
-            self.central_width = window_width;
-
-            // Set each sidebar's width to the full window width if they are toggled on.
-            if self.left_sidebar.should_show {
-                self.left_sidebar.show = true;
-                self.left_sidebar.width = window_width;
-            } else {
-                self.left_sidebar.show = false;
-                self.left_sidebar.width = 0.0;
-            }
-            if self.right_sidebar.should_show {
-                self.right_sidebar.show = true;
-                self.right_sidebar.width = window_width;
-            } else {
-                self.right_sidebar.show = false;
-                self.right_sidebar.width = 0.0;
-            }
+        let left_width = self
+            .left_sidebar
+            .dragged_width
+            .unwrap_or(window_width * LEFT_PCT)
+            .max(MIN_LEFT_WIDTH);
+        let right_width = self
+            .right_sidebar
+            .dragged_width
+            .unwrap_or(window_width * RIGHT_PCT)
+            .max(MIN_RIGHT_WIDTH);
+
+        // Below the threshold every panel is a drawer. Above it, panels go beside the main view
+        // as long as it keeps its narrowest width, the queue first.
+        let room = window_width - MIN_CENTRAL_WIDTH;
+        self.mode = if window_width < OVERLAY_THRESHOLD {
+            LayoutMode::Overlay
         } else {
-            // Enter inline mode
-            self.mode = LayoutMode::Inline;
-            let potential_left_width = window_width * LEFT_PCT;
-            let potential_right_width = window_width * RIGHT_PCT;
-
-            // Priority: main view > right sidebar > left sidebar
-            if self.left_sidebar.should_show && self.right_sidebar.should_show {
-                if window_width
-                    >= (potential_left_width + potential_right_width + MIN_CENTRAL_WIDTH)
-                {
-                    self.left_sidebar.show = true;
-                    self.left_sidebar.width = potential_left_width;
-                    self.right_sidebar.show = true;
-                    self.right_sidebar.width = potential_right_width;
-                } else if window_width >= (potential_right_width + MIN_CENTRAL_WIDTH) {
-                    self.left_sidebar.show = false;
-                    self.left_sidebar.width = 0.0;
-                    self.right_sidebar.show = true;
-                    self.right_sidebar.width = potential_right_width;
-                } else {
-                    self.left_sidebar.show = false;
-                    self.left_sidebar.width = 0.0;
-                    self.right_sidebar.show = false;
-                    self.right_sidebar.width = 0.0;
-                }
-            } else if self.right_sidebar.should_show {
-                if window_width >= (potential_right_width + MIN_CENTRAL_WIDTH) {
-                    self.right_sidebar.show = true;
-                    self.right_sidebar.width = potential_right_width;
-                } else {
-                    self.right_sidebar.show = false;
-                    self.right_sidebar.width = 0.0;
-                }
-                self.left_sidebar.show = false;
-                self.left_sidebar.width = 0.0;
-            } else if self.left_sidebar.should_show {
-                if window_width >= (potential_left_width + MIN_CENTRAL_WIDTH) {
-                    self.left_sidebar.show = true;
-                    self.left_sidebar.width = potential_left_width;
-                } else {
-                    self.left_sidebar.show = false;
-                    self.left_sidebar.width = 0.0;
-                }
-                self.right_sidebar.show = false;
-                self.right_sidebar.width = 0.0;
-            } else {
-                self.left_sidebar.show = false;
-                self.left_sidebar.width = 0.0;
-                self.right_sidebar.show = false;
-                self.right_sidebar.width = 0.0;
-            }
+            LayoutMode::Inline
+        };
+        let inline = self.mode == LayoutMode::Inline;
+        self.right_sidebar.fits = inline && right_width <= room;
+        let right_used = if self.right_sidebar.should_show && self.right_sidebar.fits {
+            right_width
+        } else {
+            0.0
+        };
+        self.left_sidebar.fits = inline && left_width + right_used <= room;
 
-            let used_width = if self.left_sidebar.show {
-                self.left_sidebar.width
-            } else {
-                0.0
-            } + if self.right_sidebar.show {
-                self.right_sidebar.width
+        let drawer = self.drawer;
+        let mut used_width = 0.0;
+        for (panel, width) in [(Panel::Left, left_width), (Panel::Right, right_width)] {
+            let sidebar = self.panel(panel);
+            let beside = sidebar.should_show && sidebar.fits;
+            sidebar.drawer = !beside && drawer == Some(panel);
+            sidebar.show = beside || sidebar.drawer;
+            sidebar.width = if sidebar.drawer {
+                width.min(window_width)
+            } else if beside {
+                width
             } else {
                 0.0
             };
-            let computed_central = window_width - used_width;
-            self.central_width = computed_central.max(MIN_CENTRAL_WIDTH);
+            if beside {
+                used_width += width;
+            }
+        }
+        // A drawer for a panel that now fits beside the main view isn't needed.
+        if drawer.is_some_and(|panel| !self.panel(panel).drawer) {
+            self.drawer = None;
         }
+        self.central_width = (window_width - used_width).max(MIN_CENTRAL_WIDTH);
 
         self
     }
@@ -1,22 +1,47 @@
+pub mod activity;
 pub mod app;
 pub mod assets;
+pub mod chapter_editor;
 pub mod control_bar;
+pub mod fonts;
 mod keybinds;
 pub mod layout;
+pub mod library_settings;
+pub mod lyrics_editor;
 pub mod main_view;
+pub mod media_controls;
 pub mod now_playing;
+pub mod playlist_editor;
 pub mod queue_list;
+pub mod rating_overlay;
+pub mod report_view;
 pub mod res_handler;
+pub mod scrobble_settings;
+pub mod selection;
+pub mod settings_view;
 pub mod sidebar;
+pub mod smart_editor;
+pub mod sort;
+pub mod themes;
 pub mod titlebar;
+pub mod toast;
+pub mod tray;
+pub mod visualizer;
 
+use activity::{Activity, Redraw};
 use app::Kagi;
 use assets::*;
 use backend::{
     Backend,
-    playback::{Playlist, SavedPlaylists},
+    keymap::Keymap,
+    palette::ArtPalette,
+    playback::{Origin, Playlist, SavedPlaylists},
     player::{Controller, Player, Response},
+    settings::Settings,
+    startup::StartupOptions,
+    watcher::ConfigWatcher,
 };
+use chapter_editor::ChapterEditor;
 use components::{
     slider::{Slider, SliderEvent},
     theme::Theme,
@@ -24,17 +49,29 @@ use components::{
 use control_bar::ControlBar;
 use gpui::*;
 use layout::Layout;
+use library_settings::LibrarySettings;
+use lyrics_editor::LyricsEditor;
 use main_view::MainView;
-use now_playing::{PlayerContext, PlayerStateEvent, Thumbnail, Track};
+use now_playing::{
+    Album, Artist, PlayerContext, PlayerStateEvent, SkippedTrack, StreamInfo, Thumbnail, Track,
+};
+use playlist_editor::PlaylistEditor;
 use queue_list::QueueList;
+use rating_overlay::{QUICK_RATING_DURATION, RatingOverlay};
+use report_view::ReportView;
 use res_handler::ResHandler;
+use scrobble_settings::ScrobbleSettings;
+use settings_view::{SettingsView, save_settings};
 use sidebar::LeftSidebar;
+use smart_editor::SmartPlaylistEditor;
 use std::{
+    collections::HashMap,
     path::PathBuf,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use titlebar::Titlebar;
+use toast::Toast;
 
 actions!(text_input, [
     Backspace,
@@ -51,7 +88,19 @@ actions!(text_input, [
     Copy,
 ]);
 
-pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
+/// Longest the app waits on quitting for the session to be written.
+const QUIT_SAVE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Longest the artwork placeholder waits for the full artwork, which some tracks never send.
+const ARTWORK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Run the app. `init_backend` is awaited in the background once the window is up, so pipeline
+/// construction stays off the startup path; commands issued before then are queued by the
+/// [`Controller`] until the player reports [`Response::BackendReady`]. The link or files in
+/// `options` are played once the player is ready.
+pub fn run_app<F>(options: StartupOptions, init_backend: F) -> anyhow::Result<()>
+where
+    F: Future<Output = anyhow::Result<Arc<dyn Backend>>> + Send + 'static,
+{
     let app = Application::new().with_assets(Assets {
         base: PathBuf::from("assets"),
     });
@@ -71,20 +120,21 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                 }),
                 ..Default::default()
             },
-            |_, cx| {
+            |window, cx| {
                 cx.new(|cx| {
-                    let theme = Theme::default();
+                    let settings = Settings::load();
+                    let theme = themes::load(&settings, cx);
                     let now_playing = PlayerContext::new(cx);
                     let res_handler = cx.new(|_| ResHandler {});
                     let arc_res = Arc::new(res_handler.clone());
-                    let (mut player, controller) =
-                        Player::new(backend.clone(), Arc::new(Mutex::new(Playlist::default())));
+                    let (controller, res_tx, cmd_rx) = Controller::new();
                     let vol_slider = cx.new(|_| {
                         Slider::new(theme)
                             .min(0.0)
-                            .max(1.0)
+                            .max(settings.max_volume() as f32)
                             .step(0.005)
                             .default(0.2)
+                            .warn_above(1.0)
                     });
                     let playbar = cx.new(|_| {
                         Slider::new(theme)
@@ -96,14 +146,72 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                     let recv_controller = controller.clone();
                     let saved_playlists = cx.new(|_| SavedPlaylists::default());
                     let playlists = saved_playlists.clone();
+                    let listen_later = cx.new(|_| 0usize);
+                    let listen_later_count = listen_later.clone();
 
                     keybinds::register(cx);
+                    media_controls::register(window, cx);
+                    window.on_window_should_close(cx, |window, cx| {
+                        if tray::hides_on_close(cx) {
+                            window.minimize_window();
+                            return false;
+                        }
+                        true
+                    });
+                    cx.observe_window_activation(window, |_: &mut Kagi, window, cx| {
+                        cx.global_mut::<Activity>().active = window.is_window_active();
+                        // Catch up on whatever was held back while in the background.
+                        window.refresh();
+                    })
+                    .detach();
+                    controller.apply_settings(&settings);
+                    if let Err(e) = backend::ipc::serve(controller.clone()) {
+                        eprintln!("Could not start query server: {e}");
+                    }
+                    if let Some(link) = options.link.clone() {
+                        controller.open_link(link, Origin::Link);
+                    }
                     cx.set_global(controller);
+                    // Theme files can set component colors outside the palette.
+                    if cfg!(debug_assertions) {
+                        for field in theme.unthemed() {
+                            eprintln!("Unthemed color: {field}");
+                        }
+                    }
                     cx.set_global(theme);
+                    themes::start(&settings, cx);
+                    cx.observe_window_appearance(window, |_, _, cx| themes::follow_system(cx))
+                        .detach();
+                    tray::register(window.window_handle(), cx);
+                    cx.set_global(Activity::new(&settings));
+                    cx.set_global(settings);
                     cx.set_global(now_playing.clone());
+                    fonts::register(cx);
+                    fonts::apply(cx);
                     cx.background_executor()
                         .spawn(async move {
-                            player.run().await;
+                            match init_backend.await {
+                                Ok(backend) => {
+                                    let mut player = Player::with_channels(
+                                        backend,
+                                        Arc::new(Mutex::new(Playlist::default())),
+                                        res_tx,
+                                        cmd_rx,
+                                    );
+                                    player
+                                        .tx
+                                        .send(Response::BackendReady)
+                                        .expect("Could not send message");
+                                    player.run().await;
+                                }
+                                Err(e) => {
+                                    res_tx
+                                        .send(Response::Error(format!(
+                                            "Could not initialize backend: {e}"
+                                        )))
+                                        .expect("Could not send message");
+                                }
+                            }
                         })
                         .detach();
                     cx.spawn(|_, cx: AsyncApp| async move {
@@ -161,6 +269,13 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                         }
                     })
                     .detach();
+                    // The slider's range follows the volume limit in the settings.
+                    let vol_slider_limit = vol_slider.clone();
+                    cx.observe_global::<Settings>(move |_: &mut Kagi, cx| {
+                        let max = cx.global::<Settings>().max_volume() as f32;
+                        vol_slider_limit.update(cx, |this, cx| this.set_max(max, cx));
+                    })
+                    .detach();
                     let vol_slider_clone = vol_slider.clone();
                     cx.subscribe(
                         &now_playing.state,
@@ -176,10 +291,12 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                     .detach();
 
                     let playbar_clone = playbar.clone();
+                    let vol_slider_res = vol_slider.clone();
                     cx.subscribe(
                         &res_handler,
                         move |_: &mut Kagi, _, event: &Response, cx| match event {
                             Response::Eos => {
+                                clear_placeholder(cx);
                                 if cx.global::<PlayerContext>().state.read(cx).repeat {
                                     cx.global::<Controller>().seek(0);
                                 } else {
@@ -187,6 +304,10 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                                 }
                             }
                             Response::Position(pos) => {
+                                media_controls::set_position(*pos, cx);
+                                if !cx.global_mut::<Activity>().due(Redraw::Position) {
+                                    return;
+                                }
                                 let state = cx.global_mut::<PlayerContext>().state.clone();
                                 state.update(cx, |state, cx| {
                                     state.position = *pos;
@@ -204,31 +325,97 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                                 });
                                 cx.notify();
                             }
-                            Response::StreamStart => cx.global::<Controller>().get_meta(),
+                            Response::BackendReady => cx.global::<Controller>().mark_ready(),
+                            Response::StreamStart => {
+                                clear_placeholder(cx);
+                                cx.global::<Controller>().get_meta();
+                            }
                             Response::Metadata(track) => {
+                                media_controls::set_track(track, cx);
+                                tray::set_track(track, cx);
                                 let metadata = cx.global_mut::<PlayerContext>().metadata.clone();
+                                let settings = cx.global::<Settings>().clone();
+                                if metadata.read(cx).uri != track.uri {
+                                    // Tinted from the small artwork until the full one arrives.
+                                    let palette = track.thumbnail.as_ref().map(ArtPalette::of);
+                                    themes::set_art(palette, cx);
+                                    let uri = track.uri.clone();
+                                    let metadata = metadata.clone();
+                                    cx.spawn(move |_, cx: AsyncApp| async move {
+                                        cx.background_executor().timer(ARTWORK_TIMEOUT).await;
+                                        let _ = metadata.update(&mut cx.clone(), |meta, cx| {
+                                            if meta.uri == uri && meta.placeholder {
+                                                meta.placeholder = false;
+                                                cx.notify();
+                                            }
+                                        });
+                                    })
+                                    .detach();
+                                }
                                 metadata.update(cx, |meta, cx| {
                                     let track = track.clone();
-                                    meta.title = track.title.into();
-                                    meta.album = track.album.into();
-                                    meta.artists =
-                                        track.artists.iter().map(|s| s.clone().into()).collect();
+                                    // Stand in with the small artwork right away rather than
+                                    // keep the last track's until the full artwork arrives.
+                                    if meta.uri != track.uri {
+                                        meta.thumbnail =
+                                            track.thumbnail.as_ref().map(|thumbnail| Thumbnail {
+                                                img: ImageSource::Render(
+                                                    RenderImage::new(thumbnail.to_frame()).into(),
+                                                ),
+                                                width: thumbnail.width,
+                                                height: thumbnail.height,
+                                            });
+                                        meta.placeholder = true;
+                                    }
+                                    meta.uri = track.uri.clone();
+                                    meta.title = settings.display(&track.title).into();
+                                    meta.album = settings.display(&track.album).into();
+                                    meta.artists = track
+                                        .artists
+                                        .iter()
+                                        .map(|s| settings.display(s).into())
+                                        .collect();
                                     meta.duration = track.duration;
+                                    meta.origin = track.origin;
                                     cx.notify();
                                 });
                             }
                             Response::Thumbnail(thumbnail) => {
                                 let metadata = cx.global_mut::<PlayerContext>().metadata.clone();
-                                metadata.update(cx, |meta, cx| {
-                                    meta.thumbnail = Some(Thumbnail {
-                                        img: ImageSource::Render(
-                                            RenderImage::new(thumbnail.clone().to_frame()).into(),
-                                        ),
-                                        width: thumbnail.width,
-                                        height: thumbnail.height,
+                                let uri = metadata.read(cx).uri.clone();
+                                media_controls::set_artwork(uri.clone(), thumbnail.clone(), cx);
+                                let thumbnail = thumbnail.clone();
+                                // Full size artwork takes a moment to copy into frames, so do
+                                // it off the UI thread and keep the placeholder up meanwhile.
+                                cx.spawn(move |_, cx: AsyncApp| async move {
+                                    let (width, height) = (thumbnail.width, thumbnail.height);
+                                    let (frames, palette) = cx
+                                        .background_executor()
+                                        .spawn(async move {
+                                            (thumbnail.to_frame(), ArtPalette::of(&thumbnail))
+                                        })
+                                        .await;
+                                    let shown = metadata.update(&mut cx.clone(), |meta, cx| {
+                                        // The track changed while decoding.
+                                        if meta.uri != uri {
+                                            return false;
+                                        }
+                                        meta.thumbnail = Some(Thumbnail {
+                                            img: ImageSource::Render(
+                                                RenderImage::new(frames).into(),
+                                            ),
+                                            width,
+                                            height,
+                                        });
+                                        meta.placeholder = false;
+                                        cx.notify();
+                                        true
                                     });
-                                    cx.notify();
-                                });
+                                    if let Ok(true) = shown {
+                                        let _ = cx.update(|cx| themes::set_art(Some(palette), cx));
+                                    }
+                                })
+                                .detach();
                             }
                             Response::StateChanged(new_state) => {
                                 let state = cx.global_mut::<PlayerContext>().state.clone();
@@ -236,31 +423,122 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                                     state.state = new_state.clone();
                                     cx.notify();
                                 });
+                                media_controls::set_state(*new_state, cx);
                             }
                             Response::Tracks(new_tracks) => {
                                 let tracks = cx.global_mut::<PlayerContext>().tracks.clone();
+                                let settings = cx.global::<Settings>().clone();
                                 tracks.update(cx, |tracks, cx| {
-                                    let mut np_tracks = vec![];
-                                    for track in new_tracks {
-                                        if let Some(thumbnail) = track.thumbnail.clone() {
-                                            np_tracks.push(Track {
-                                                album: track.album.clone(),
-                                                artists: track.artists.clone(),
-                                                duration: track.duration,
-                                                thumbnail: Some(Thumbnail {
-                                                    img: ImageSource::Render(
-                                                        RenderImage::new(thumbnail.to_frame())
-                                                            .into(),
-                                                    ),
-                                                    width: thumbnail.width,
-                                                    height: thumbnail.height,
-                                                }),
-                                                title: track.title.clone(),
-                                                uri: track.uri.clone(),
-                                            });
-                                        }
-                                    }
-                                    *tracks = np_tracks;
+                                    *tracks = new_tracks
+                                        .iter()
+                                        .filter(|track| track.thumbnail.is_some())
+                                        .map(|track| Track::new(track, &settings))
+                                        .collect();
+                                    cx.notify();
+                                });
+                            }
+                            Response::LibraryRoots(roots) => {
+                                let library_roots =
+                                    cx.global::<PlayerContext>().library_roots.clone();
+                                library_roots.update(cx, |this, cx| {
+                                    *this = roots;
+                                    cx.notify();
+                                });
+                            }
+                            Response::SearchResults(results) => {
+                                let search_results =
+                                    cx.global::<PlayerContext>().search_results.clone();
+                                let settings = cx.global::<Settings>().clone();
+                                search_results.update(cx, |this, cx| {
+                                    *this = results
+                                        .iter()
+                                        .map(|track| Track::new(track, &settings))
+                                        .collect();
+                                    cx.notify();
+                                });
+                            }
+                            Response::Library {
+                                albums,
+                                artists,
+                                genres,
+                                decades,
+                                missing,
+                            } => {
+                                let context = cx.global::<PlayerContext>().clone();
+                                let settings = cx.global::<Settings>().clone();
+                                let albums: Vec<Album> = albums
+                                    .iter()
+                                    .map(|album| Album {
+                                        key: (album.name.clone(), album.artist.clone()),
+                                        name: settings.display(&album.name).into(),
+                                        artist: settings.display(&album.artist).into(),
+                                        track_count: album.track_count,
+                                        thumbnail: album.thumbnail.as_ref().map(|thumbnail| {
+                                            Thumbnail {
+                                                img: ImageSource::Render(
+                                                    RenderImage::new(thumbnail.to_frame()).into(),
+                                                ),
+                                                width: thumbnail.width,
+                                                height: thumbnail.height,
+                                            }
+                                        }),
+                                    })
+                                    .collect();
+                                // Reuse the decoded covers rather than decoding each album again.
+                                let by_key: HashMap<(&str, &str), &Album> = albums
+                                    .iter()
+                                    .map(|a| ((a.key.0.as_str(), a.key.1.as_str()), a))
+                                    .collect();
+                                let artists: Vec<Artist> = artists
+                                    .iter()
+                                    .map(|artist| Artist {
+                                        name: settings.display(&artist.name).into(),
+                                        albums: artist
+                                            .albums
+                                            .iter()
+                                            .filter_map(|album| {
+                                                by_key
+                                                    .get(&(
+                                                        album.name.as_str(),
+                                                        album.artist.as_str(),
+                                                    ))
+                                                    .map(|a| (*a).clone())
+                                            })
+                                            .collect(),
+                                    })
+                                    .collect();
+                                context.albums.update(cx, |this, cx| {
+                                    *this = albums;
+                                    cx.notify();
+                                });
+                                context.artists.update(cx, |this, cx| {
+                                    *this = artists;
+                                    cx.notify();
+                                });
+                                context.genres.update(cx, |this, cx| {
+                                    *this = genres.clone();
+                                    cx.notify();
+                                });
+                                context.decades.update(cx, |this, cx| {
+                                    *this = decades.clone();
+                                    cx.notify();
+                                });
+                                context.missing_tracks.update(cx, |this, cx| {
+                                    *this = missing;
+                                    cx.notify();
+                                });
+                            }
+                            Response::PlaylistTracks(index, tracks) => {
+                                let context = cx.global::<PlayerContext>().clone();
+                                if *context.editing_playlist.read(cx) != Some(*index) {
+                                    return;
+                                }
+                                let settings = cx.global::<Settings>().clone();
+                                context.playlist_tracks.update(cx, |this, cx| {
+                                    *this = tracks
+                                        .iter()
+                                        .map(|track| Track::new(track, &settings))
+                                        .collect();
                                     cx.notify();
                                 });
                             }
@@ -270,12 +548,96 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                                     cx.notify();
                                 })
                             }
+                            Response::Repeat(repeat) => {
+                                let state = cx.global_mut::<PlayerContext>().state.clone();
+                                state.update(cx, |state, cx| {
+                                    state.repeat = *repeat;
+                                    cx.notify();
+                                });
+                            }
+                            Response::Volume(volume) => {
+                                let state = cx.global_mut::<PlayerContext>().state.clone();
+                                state.update(cx, |state, cx| {
+                                    state.volume = *volume;
+                                    state.vol(cx, *volume);
+                                });
+                            }
+                            Response::ListenLater(count) => listen_later.update(cx, |this, cx| {
+                                *this = *count;
+                                cx.notify();
+                            }),
+                            Response::PreferRemote(prefer_remote) => {
+                                let settings = cx.global_mut::<Settings>();
+                                settings.prefer_remote = prefer_remote.clone();
+                                save_settings(cx);
+                            }
+                            Response::Loudness(tracks) => {
+                                let loudness = cx.global::<PlayerContext>().loudness.clone();
+                                loudness.update(cx, |this, cx| {
+                                    *this = tracks.clone();
+                                    cx.notify();
+                                });
+                            }
+                            Response::PlayCounts(counts) => {
+                                let play_counts = cx.global::<PlayerContext>().play_counts.clone();
+                                play_counts.update(cx, |this, cx| {
+                                    *this = counts.clone();
+                                    cx.notify();
+                                });
+                            }
+                            Response::Waveform(peaks) => {
+                                playbar_clone.update(cx, |this, cx| {
+                                    this.set_waveform(peaks.clone(), cx);
+                                });
+                            }
+                            Response::SpectrumFrame(frame) => {
+                                if !cx.global_mut::<Activity>().due(Redraw::Spectrum) {
+                                    return;
+                                }
+                                let visualizer = cx.global::<PlayerContext>().visualizer.clone();
+                                visualizer.update(cx, |visualizer, cx| visualizer.push(frame, cx));
+                            }
+                            Response::ResumeCards(cards) => {
+                                let resume_cards =
+                                    cx.global_mut::<PlayerContext>().resume_cards.clone();
+                                resume_cards.update(cx, |resume_cards, cx| {
+                                    *resume_cards = cards.clone();
+                                    cx.notify();
+                                });
+                            }
+                            Response::QueueState(new_state) => {
+                                let queue_state = cx.global::<PlayerContext>().queue_state.clone();
+                                queue_state.update(cx, |queue_state, cx| {
+                                    *queue_state = *new_state;
+                                    cx.notify();
+                                });
+                            }
+                            Response::RecentFolders(folders) => {
+                                let recent_folders =
+                                    cx.global_mut::<PlayerContext>().recent_folders.clone();
+                                recent_folders.update(cx, |recent_folders, cx| {
+                                    *recent_folders = folders.clone();
+                                    cx.notify();
+                                });
+                            }
                             Response::PlaylistName(name) => {
                                 let meta = cx.global_mut::<PlayerContext>().metadata.clone();
                                 meta.update(cx, |meta, cx| {
                                     meta.playlist_name = name.clone().into();
                                     cx.notify();
                                 });
+                                let unsaved = cx.global::<PlayerContext>().unsaved_queue.clone();
+                                unsaved.update(cx, |this, cx| {
+                                    *this = false;
+                                    cx.notify();
+                                });
+                            }
+                            Response::UnsavedQueue => {
+                                let unsaved = cx.global::<PlayerContext>().unsaved_queue.clone();
+                                unsaved.update(cx, |this, cx| {
+                                    *this = true;
+                                    cx.notify();
+                                });
                             }
                             Response::Shuffle(shuffle) => {
                                 let state = cx.global_mut::<PlayerContext>().state.clone();
@@ -284,22 +646,303 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                                     cx.notify();
                                 });
                             }
+                            Response::ShuffleMode(mode) => {
+                                let state = cx.global_mut::<PlayerContext>().state.clone();
+                                state.update(cx, |state, cx| {
+                                    state.shuffle_mode = *mode;
+                                    cx.notify();
+                                });
+                            }
+                            Response::StreamInfo {
+                                codec,
+                                bitrate,
+                                sample_rate,
+                                channels,
+                                bit_depth,
+                                bit_perfect,
+                            } => {
+                                let metadata = cx.global_mut::<PlayerContext>().metadata.clone();
+                                metadata.update(cx, |meta, cx| {
+                                    meta.stream_info = Some(StreamInfo {
+                                        codec: codec.clone().into(),
+                                        bitrate: *bitrate,
+                                        sample_rate: *sample_rate,
+                                        channels: *channels,
+                                        bit_depth: *bit_depth,
+                                        bit_perfect: *bit_perfect,
+                                    });
+                                    cx.notify();
+                                });
+                            }
+                            Response::Exclusive(exclusive) => {
+                                let state = cx.global_mut::<PlayerContext>().state.clone();
+                                state.update(cx, |state, cx| {
+                                    state.exclusive = *exclusive;
+                                    cx.notify();
+                                });
+                                vol_slider_res.update(cx, |this, cx| {
+                                    this.set_disabled(*exclusive, cx);
+                                });
+                            }
+                            Response::Buffering(percent) => {
+                                let state = cx.global_mut::<PlayerContext>().state.clone();
+                                state.update(cx, |state, cx| {
+                                    state.buffering = *percent;
+                                    cx.notify();
+                                });
+                            }
+                            Response::Stalled => {
+                                let state = cx.global_mut::<PlayerContext>().state.clone();
+                                state.update(cx, |state, cx| {
+                                    state.stalled = true;
+                                    cx.notify();
+                                });
+                            }
+                            Response::Recovered => {
+                                let state = cx.global_mut::<PlayerContext>().state.clone();
+                                state.update(cx, |state, cx| {
+                                    state.stalled = false;
+                                    state.buffering = 100;
+                                    cx.notify();
+                                });
+                            }
+                            Response::ScanProgress { scanned, total } => {
+                                let progress = cx.global::<PlayerContext>().scan_progress.clone();
+                                progress.update(cx, |this, cx| {
+                                    *this = Some((*scanned, *total));
+                                    cx.notify();
+                                });
+                            }
+                            Response::Chapters(new_chapters) => {
+                                let chapters = cx.global::<PlayerContext>().chapters.clone();
+                                chapters.update(cx, |this, cx| {
+                                    *this = new_chapters.clone();
+                                    cx.notify();
+                                });
+                            }
+                            Response::Lyrics(new_lyrics) => {
+                                let lyrics = cx.global::<PlayerContext>().lyrics.clone();
+                                lyrics.update(cx, |this, cx| {
+                                    *this = new_lyrics.clone();
+                                    cx.notify();
+                                });
+                            }
+                            Response::SmartPlaylists(playlists) => {
+                                let smart_playlists =
+                                    cx.global::<PlayerContext>().smart_playlists.clone();
+                                smart_playlists.update(cx, |this, cx| {
+                                    *this = playlists.clone();
+                                    cx.notify();
+                                });
+                            }
+                            Response::MostSkipped(skipped) => {
+                                let most_skipped =
+                                    cx.global::<PlayerContext>().most_skipped.clone();
+                                let settings = cx.global::<Settings>().clone();
+                                most_skipped.update(cx, |this, cx| {
+                                    *this = skipped
+                                        .iter()
+                                        .map(|skipped| SkippedTrack {
+                                            track: Track::new(&skipped.track, &settings),
+                                            skips: skipped.skips,
+                                            typical_position: skipped.typical_position,
+                                        })
+                                        .collect();
+                                    cx.notify();
+                                });
+                            }
+                            Response::Insights(counted) => {
+                                let insights = cx.global::<PlayerContext>().insights.clone();
+                                insights.update(cx, |this, cx| {
+                                    *this = counted.clone();
+                                    cx.notify();
+                                });
+                            }
+                            Response::LastFmAuthorize(url) => {
+                                cx.open_url(url);
+                                let authorizing =
+                                    cx.global::<PlayerContext>().lastfm_authorizing.clone();
+                                authorizing.update(cx, |this, cx| {
+                                    *this = true;
+                                    cx.notify();
+                                });
+                            }
+                            Response::LastFmSession { user, session } => {
+                                scrobble_settings::lastfm_signed_in(
+                                    user.clone(),
+                                    session.clone(),
+                                    cx,
+                                );
+                            }
+                            Response::ListenBrainzUser(user) => {
+                                toast::show(format!("ListenBrainz token is {user}'s"), cx);
+                            }
+                            Response::ScrobbleSignedOut(service) => {
+                                toast::show(
+                                    format!(
+                                        "{service} needs signing in to again. Listens are kept \
+                                         until then."
+                                    ),
+                                    cx,
+                                );
+                                let open = cx.global::<PlayerContext>().scrobble_settings.clone();
+                                open.update(cx, |this, cx| {
+                                    *this = true;
+                                    cx.notify();
+                                });
+                            }
+                            Response::PendingScrobbles(count) => {
+                                let pending =
+                                    cx.global::<PlayerContext>().pending_scrobbles.clone();
+                                pending.update(cx, |this, cx| {
+                                    *this = *count;
+                                    cx.notify();
+                                });
+                            }
+                            Response::OutputDevices(devices) => {
+                                let output_devices =
+                                    cx.global::<PlayerContext>().output_devices.clone();
+                                output_devices.update(cx, |this, cx| {
+                                    *this = devices.clone();
+                                    cx.notify();
+                                });
+                            }
+                            Response::Rated(stars) => {
+                                let quick_rating =
+                                    cx.global::<PlayerContext>().quick_rating.clone();
+                                let shown = Instant::now();
+                                quick_rating.update(cx, |this, cx| {
+                                    *this = Some((*stars, shown));
+                                    cx.notify();
+                                });
+                                cx.spawn(move |_, cx: AsyncApp| async move {
+                                    cx.background_executor().timer(QUICK_RATING_DURATION).await;
+                                    // A newer rating keeps its own confirmation up.
+                                    let _ = quick_rating.update(&mut cx.clone(), |this, cx| {
+                                        if this.is_some_and(|(_, at)| at == shown) {
+                                            *this = None;
+                                            cx.notify();
+                                        }
+                                    });
+                                })
+                                .detach();
+                            }
+                            Response::ScanFinished => {
+                                let progress = cx.global::<PlayerContext>().scan_progress.clone();
+                                progress.update(cx, |this, cx| {
+                                    *this = None;
+                                    cx.notify();
+                                });
+                            }
+                            Response::Error(message) => {
+                                let log = cx.global::<PlayerContext>().log.clone();
+                                log.update(cx, |this, _| this.push(format!("error: {message}")));
+                            }
+                            Response::Info(message) => {
+                                let log = cx.global::<PlayerContext>().log.clone();
+                                log.update(cx, |this, _| this.push(format!("info: {message}")));
+                            }
+                            Response::ShareLink(url) => {
+                                cx.write_to_clipboard(ClipboardItem::new_string(url.clone()));
+                                let log = cx.global::<PlayerContext>().log.clone();
+                                log.update(cx, |this, _| this.push(format!("info: copied {url}")));
+                            }
                             _ => {}
                         },
                     )
                     .detach();
-                    let layout = cx.new(|_| Layout::new());
+                    // A link opened from elsewhere brings the window to the front.
+                    cx.subscribe_in(
+                        &res_handler,
+                        window,
+                        |_: &mut Kagi, _, event: &Response, window, _| {
+                            if let Response::LinkOpened = event {
+                                window.activate_window();
+                            }
+                        },
+                    )
+                    .detach();
+                    let layout = cx.new(|cx| Layout::new(cx.global::<Settings>()));
 
                     let titlebar = cx.new(|_| Titlebar::new(layout.clone()));
 
                     let control_bar =
                         cx.new(|_| ControlBar::new(vol_slider.clone(), playbar.clone()));
-                    let main_view = cx.new(|_| MainView::new(layout.clone()));
-                    let queue_list = cx.new(|cx| QueueList::new(cx, layout.clone()));
+                    let main_view =
+                        cx.new(|cx| MainView::new(cx, layout.clone(), saved_playlists.clone()));
+                    let queue_list =
+                        cx.new(|cx| QueueList::new(cx, layout.clone(), saved_playlists.clone()));
+                    let report_view = cx.new(|_| ReportView::new());
+                    let chapter_editor = cx.new(|_| ChapterEditor::new());
+                    let lyrics_editor = cx.new(|_| LyricsEditor::new());
+                    let library_settings = cx.new(|_| LibrarySettings);
+                    let scrobble_settings = cx.new(|_| ScrobbleSettings::new());
+                    let settings_view = cx.new(|_| SettingsView::new());
+                    let smart_editor = cx.new(|_| SmartPlaylistEditor::new());
+                    let playlist_editor = cx.new(|_| PlaylistEditor::new(saved_playlists.clone()));
+                    let rating_overlay = cx.new(|_| RatingOverlay);
+                    let toast = cx.new(|_| Toast);
                     let layout_sidebar = layout.clone();
-                    let left_sidebar = cx
-                        .new(move |_| LeftSidebar::new(playlists.clone(), layout_sidebar.clone()));
+                    let left_sidebar = cx.new(move |_| {
+                        LeftSidebar::new(
+                            playlists.clone(),
+                            listen_later_count.clone(),
+                            layout_sidebar.clone(),
+                        )
+                    });
                     cx.global::<Controller>().load_saved_playlists();
+                    cx.global::<Controller>().restore_session();
+                    let volume = options.volume.or(cx.global::<Settings>().default_volume);
+                    if let Some(volume) = volume {
+                        cx.global::<Controller>().start_volume(volume);
+                    }
+                    if !options.paths.is_empty() {
+                        cx.global::<Controller>()
+                            .play_files(options.paths.clone(), Origin::CommandLine);
+                    }
+                    cx.global::<Controller>().get_smart_playlists();
+                    cx.global::<Controller>().get_library();
+                    cx.global::<Controller>().get_loudness();
+                    cx.global::<Controller>().get_play_counts();
+                    // Edits to the settings and keymap files apply without a restart.
+                    let (config_tx, config_rx) = smol::channel::unbounded::<PathBuf>();
+                    let config_files = [Settings::get_settings_file(), Keymap::get_keymap_file()]
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                    match ConfigWatcher::start(config_files, config_tx) {
+                        Ok(watcher) => {
+                            cx.spawn(move |_, cx: AsyncApp| async move {
+                                let _watcher = watcher;
+                                while config_rx.recv().await.is_ok() {
+                                    // Saving often takes a few writes, so let them settle.
+                                    cx.background_executor()
+                                        .timer(Duration::from_millis(200))
+                                        .await;
+                                    while config_rx.try_recv().is_ok() {}
+                                    let _ = cx.update(|cx| keybinds::reload_config(cx));
+                                }
+                            })
+                            .detach();
+                        }
+                        Err(e) => eprintln!("Could not watch settings: {e}"),
+                    }
+                    cx.on_app_quit(|_: &mut Kagi, cx| {
+                        let saved = cx.global::<Controller>().save_session();
+                        // Wait for the player loop to write the session, but not for long if it is
+                        // stuck on something else.
+                        let timer = cx.background_executor().timer(QUIT_SAVE_TIMEOUT);
+                        async move {
+                            smol::future::or(
+                                async {
+                                    let _ = saved.recv().await;
+                                },
+                                timer,
+                            )
+                            .await;
+                        }
+                    })
+                    .detach();
 
                     Kagi {
                         layout,
@@ -309,6 +952,16 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
                         control_bar,
                         main_view,
                         queue_list,
+                        report_view,
+                        chapter_editor,
+                        lyrics_editor,
+                        library_settings,
+                        scrobble_settings,
+                        settings_view,
+                        smart_editor,
+                        playlist_editor,
+                        rating_overlay,
+                        toast,
                     }
                 })
             },
@@ -317,3 +970,14 @@ pub fn run_app(backend: Arc<dyn Backend>) -> anyhow::Result<()> {
     });
     Ok(())
 }
+
+/// Stop the artwork placeholder, for when no more artwork is coming for the track.
+fn clear_placeholder(cx: &mut App) {
+    let metadata = cx.global::<PlayerContext>().metadata.clone();
+    metadata.update(cx, |meta, cx| {
+        if meta.placeholder {
+            meta.placeholder = false;
+            cx.notify();
+        }
+    });
+}
@@ -0,0 +1,195 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use backend::{
+    player::Controller,
+    roots::{LibraryRoot, RootStatus},
+};
+use components::theme::Theme;
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::{chapter_editor::action, now_playing::PlayerContext};
+
+/// Library settings: the folders making up the library and how their scans stand, with
+/// buttons to add a folder and to rescan or remove each one.
+pub struct LibrarySettings;
+
+impl Render for LibrarySettings {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let context = cx.global::<PlayerContext>().clone();
+        if !*context.library_settings.read(cx) {
+            return deferred(div().track_focus(&cx.focus_handle())).with_priority(2);
+        }
+        let roots = context.library_roots.read(cx).clone();
+        let missing = *context.missing_tracks.read(cx);
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        deferred(
+            div()
+                .track_focus(&cx.focus_handle())
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .child(
+                    div()
+                        .w(px(560.0))
+                        .max_w_full()
+                        .max_h(relative(0.8))
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_color(theme.text)
+                        .child(
+                            div()
+                                .text_xl()
+                                .font_weight(FontWeight::BOLD)
+                                .child("Library folders"),
+                        )
+                        .child(
+                            div()
+                                .id("library_roots")
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .overflow_y_scroll()
+                                .when(roots.is_empty(), |this| {
+                                    this.child(
+                                        div().text_sm().text_color(theme.tokens.text.muted).child(
+                                            "No folders yet. Add one to build your library.",
+                                        ),
+                                    )
+                                })
+                                .children(roots.into_iter().enumerate().map(
+                                    |(index, (root, status))| {
+                                        self.render_root(index, root, status, cx)
+                                    },
+                                )),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .justify_end()
+                                .gap_2()
+                                .when(missing > 0, |this| {
+                                    this.child(
+                                        action(
+                                            "roots_remove_missing",
+                                            format!("Remove {missing} missing"),
+                                            button,
+                                        )
+                                        .on_click(
+                                            |_, _, cx| {
+                                                cx.global::<Controller>().remove_missing();
+                                            },
+                                        ),
+                                    )
+                                })
+                                .child(action("roots_add", "Add folder…", button).on_click(
+                                    |_, _, cx| {
+                                        cx.global::<Controller>().add_library_root();
+                                    },
+                                ))
+                                .child(action("roots_close", "Close", button).on_click({
+                                    let open = context.library_settings.clone();
+                                    move |_, _, cx| {
+                                        open.update(cx, |this, cx| {
+                                            *this = false;
+                                            cx.notify();
+                                        });
+                                    }
+                                })),
+                        ),
+                ),
+        )
+        .with_priority(2)
+    }
+}
+
+impl LibrarySettings {
+    /// One folder: its path, what its scan is doing or found last time, and its buttons.
+    fn render_root(
+        &self,
+        index: usize,
+        root: LibraryRoot,
+        status: RootStatus,
+        cx: &Context<Self>,
+    ) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let row = theme.components.row;
+        let scanning = matches!(status, RootStatus::Scanning { .. });
+        let summary = match status {
+            RootStatus::Scanning { scanned, total } => format!("Scanning {scanned}/{total}"),
+            RootStatus::Missing => "Not available".to_string(),
+            RootStatus::Idle => match root.last_scan {
+                Some(at) => format!("{} tracks · scanned {}", root.track_count, ago(at)),
+                None => "Not scanned yet".to_string(),
+            },
+        };
+
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .hover(|this| this.bg(row.hover_bg))
+            .child(
+                div()
+                    .flex_grow()
+                    .min_w_0()
+                    .flex()
+                    .flex_col()
+                    .child(div().truncate().text_color(row.text).child(root.path))
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(if status == RootStatus::Missing {
+                                theme.accent
+                            } else {
+                                row.subtext
+                            })
+                            .child(summary),
+                    ),
+            )
+            .when(!scanning && status != RootStatus::Missing, |this| {
+                this.child(action(("root_rescan", index), "Rescan", button).on_click(
+                    move |_, _, cx| {
+                        cx.global::<Controller>().rescan_library_root(index);
+                    },
+                ))
+            })
+            .child(
+                action(("root_remove", index), "Remove", button).on_click(move |_, _, cx| {
+                    cx.global::<Controller>().remove_library_root(index);
+                }),
+            )
+    }
+}
+
+/// How long ago `at`, in seconds since the Unix epoch, was, roughly.
+pub fn ago(at: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(at, |since| since.as_secs());
+    let seconds = now.saturating_sub(at);
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
@@ -0,0 +1,294 @@
+use backend::{
+    lyrics::{LyricLine, LyricsEdit},
+    player::Controller,
+};
+use components::{input::TextInput, theme::Theme};
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::{chapter_editor::action, now_playing::PlayerContext};
+
+/// How far one press of the offset buttons moves the lyrics.
+const NUDGE_MS: i64 = 100;
+
+/// Lyrics editor for the current track: fix the words, stamp lines at the playback position
+/// and shift the whole file's timing. Every change is written back to the file next to the
+/// track.
+pub struct LyricsEditor {
+    /// Line being edited and the input holding its new text.
+    editing: Option<(usize, Entity<TextInput>)>,
+    draft: String,
+}
+
+impl Render for LyricsEditor {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let context = cx.global::<PlayerContext>().clone();
+        if !*context.editing_lyrics.read(cx) {
+            self.editing = None;
+            return deferred(div().track_focus(&cx.focus_handle())).with_priority(2);
+        }
+        let lyrics = context.lyrics.read(cx).clone().unwrap_or_default();
+        let position = context.state.read(cx).position;
+        let current = lyrics.line_at(position * 1000);
+        let synced = lyrics.is_synced();
+        let offset = lyrics.offset_ms;
+        let count = lyrics.lines.len();
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        deferred(
+            div()
+                .track_focus(&cx.focus_handle())
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .child(
+                    div()
+                        .w(px(520.0))
+                        .max_w_full()
+                        .max_h(relative(0.8))
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_color(theme.text)
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .justify_between()
+                                .child(
+                                    div()
+                                        .text_xl()
+                                        .font_weight(FontWeight::BOLD)
+                                        .child("Lyrics"),
+                                )
+                                .when(synced, |this| this.child(self.render_offset(offset, cx))),
+                        )
+                        .child(
+                            div()
+                                .id("lyrics_lines")
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .overflow_y_scroll()
+                                .when(count == 0, |this| {
+                                    this.child(
+                                        div()
+                                            .text_sm()
+                                            .text_color(theme.tokens.text.muted)
+                                            .child("No lyrics yet. Add a line to start some."),
+                                    )
+                                })
+                                .children(lyrics.lines.into_iter().enumerate().map(
+                                    |(index, line)| {
+                                        self.render_line(
+                                            index,
+                                            line,
+                                            offset,
+                                            current == Some(index),
+                                            cx,
+                                        )
+                                    },
+                                )),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .justify_end()
+                                .gap_2()
+                                .child(action("lyrics_add", "Add line", button).on_click(
+                                    move |_, _, cx| {
+                                        cx.global::<Controller>()
+                                            .edit_lyrics(LyricsEdit::Insert(count.checked_sub(1)));
+                                    },
+                                ))
+                                .child(action("lyrics_close", "Close", button).on_click({
+                                    let editing = context.editing_lyrics.clone();
+                                    move |_, _, cx| {
+                                        editing.update(cx, |this, cx| {
+                                            *this = false;
+                                            cx.notify();
+                                        });
+                                    }
+                                })),
+                        ),
+                ),
+        )
+        .with_priority(2)
+    }
+}
+
+impl LyricsEditor {
+    pub fn new() -> Self {
+        LyricsEditor {
+            editing: None,
+            draft: String::new(),
+        }
+    }
+
+    /// Buttons moving every line earlier or later, around the current offset.
+    fn render_offset(&self, offset: i64, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .child(
+                action("lyrics_earlier", "−100 ms", button).on_click(|_, _, cx| {
+                    cx.global::<Controller>()
+                        .edit_lyrics(LyricsEdit::Nudge(-NUDGE_MS));
+                }),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(theme.tokens.text.muted)
+                    .child(format!("Offset {offset:+} ms")),
+            )
+            .child(
+                action("lyrics_later", "+100 ms", button).on_click(|_, _, cx| {
+                    cx.global::<Controller>()
+                        .edit_lyrics(LyricsEdit::Nudge(NUDGE_MS));
+                }),
+            )
+    }
+
+    /// One line: its timestamp (click to seek there), its text (click to edit) and buttons to
+    /// stamp it at the current position, add a line after it or remove it.
+    fn render_line(
+        &self,
+        index: usize,
+        line: LyricLine,
+        offset: i64,
+        current: bool,
+        cx: &Context<Self>,
+    ) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let row = theme.components.row;
+        let editing = self
+            .editing
+            .as_ref()
+            .filter(|(editing, _)| *editing == index)
+            .map(|(_, input)| input.clone());
+
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .hover(|this| this.bg(row.hover_bg))
+            .child(
+                div()
+                    .id(("lyrics_time", index))
+                    .w(px(48.0))
+                    .flex_shrink_0()
+                    .text_sm()
+                    .text_color(row.subtext)
+                    .hover(|this| this.text_color(theme.accent))
+                    .map(|this| match line.time {
+                        Some(time) => this
+                            .child(format!("{:02}:{:02}", time / 60_000, time / 1000 % 60))
+                            .on_click(move |_, _, cx| {
+                                let start = (time as i64 - offset).max(0) as u64 / 1000;
+                                cx.global::<Controller>().seek(start);
+                            }),
+                        None => this.child("--:--"),
+                    }),
+            )
+            .map(|this| match editing {
+                Some(input) => this
+                    .child(
+                        div()
+                            .flex_grow()
+                            .px_1()
+                            .rounded_sm()
+                            .border_1()
+                            .border_color(theme.accent)
+                            .child(input),
+                    )
+                    .child(
+                        action(("lyrics_save", index), "Save", button).on_click(cx.listener(
+                            |this, _, _, cx| {
+                                this.finish_edit(cx);
+                            },
+                        )),
+                    ),
+                None => this.child(
+                    div()
+                        .id(("lyrics_text", index))
+                        .flex_grow()
+                        .truncate()
+                        .text_color(if current { theme.accent } else { row.text })
+                        .when(current, |this| this.font_weight(FontWeight::BOLD))
+                        .child(line.text.clone())
+                        .on_click(cx.listener(move |this, _, window, cx| {
+                            this.start_edit(index, line.text.clone(), window, cx);
+                        })),
+                ),
+            })
+            .child(
+                action(("lyrics_stamp", index), "Stamp", button).on_click(move |_, _, cx| {
+                    cx.global::<Controller>()
+                        .edit_lyrics(LyricsEdit::Stamp(index));
+                }),
+            )
+            .child(
+                action(("lyrics_insert", index), "+", button).on_click(move |_, _, cx| {
+                    cx.global::<Controller>()
+                        .edit_lyrics(LyricsEdit::Insert(Some(index)));
+                }),
+            )
+            .child(
+                action(("lyrics_remove", index), "✕", button).on_click(cx.listener(
+                    move |this, _, _, cx| {
+                        this.editing = None;
+                        cx.global::<Controller>()
+                            .edit_lyrics(LyricsEdit::Remove(index));
+                    },
+                )),
+            )
+    }
+
+    fn start_edit(
+        &mut self,
+        index: usize,
+        text: String,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let handle = cx.focus_handle();
+        let input = TextInput::new(cx, handle.clone(), Some(text.clone().into()), None);
+        cx.subscribe(&input, |this: &mut Self, _, text: &String, _| {
+            this.draft = text.clone();
+        })
+        .detach();
+        window.focus(&handle);
+        self.draft = text;
+        self.editing = Some((index, input));
+        cx.notify();
+    }
+
+    fn finish_edit(&mut self, cx: &mut Context<Self>) {
+        if let Some((index, _)) = self.editing.take() {
+            cx.global::<Controller>()
+                .edit_lyrics(LyricsEdit::SetText(index, self.draft.trim().to_string()));
+        }
+        cx.notify();
+    }
+}
@@ -1,19 +1,196 @@
-use components::theme::Theme;
-use gpui::*;
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
-use crate::{layout::Layout, now_playing::PlayerContext};
+use backend::{
+    insights::Insights,
+    library::LibraryFilter,
+    lyrics_online::LyricsQuery,
+    playback::{Origin, SavedPlaylists},
+    player::Controller,
+    settings::Settings,
+};
+use components::{
+    input::{Accept, Next, Previous, TextInput},
+    theme::Theme,
+};
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{
+    chapter_editor::action,
+    layout::Layout,
+    library_settings::ago,
+    now_playing::{Album, Artist, PlayerContext, Track, loudness_label},
+    queue_list::queue_menu,
+    selection::TrackSelection,
+    settings_view::{change_settings, save_settings},
+    sort::{TrackSort, sort_bar},
+    themes,
+    visualizer::STYLES,
+};
+
+/// Most-used entries listed in each section of the insights view.
+const INSIGHTS_SHOWN: usize = 10;
+/// Width of a card in the album grid, and the gap around each.
+const ALBUM_WIDTH: f32 = 160.0;
+const ALBUM_GAP: f32 = 16.0;
+/// Space on either side of the album grid.
+const ALBUM_GRID_PADDING: f32 = 16.0;
+
+/// What the central area shows.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BrowseMode {
+    NowPlaying,
+    Albums,
+    Artists,
+    Search,
+    Skipped,
+    Insights,
+    Lyrics,
+    Visualizer,
+}
+
+impl BrowseMode {
+    /// Name the view is counted under in insights.
+    fn name(&self) -> &'static str {
+        match self {
+            BrowseMode::NowPlaying => "Now Playing",
+            BrowseMode::Albums => "Albums",
+            BrowseMode::Artists => "Artists",
+            BrowseMode::Search => "Search",
+            BrowseMode::Skipped => "Most Skipped",
+            BrowseMode::Insights => "Insights",
+            BrowseMode::Lyrics => "Lyrics",
+            BrowseMode::Visualizer => "Visualizer",
+        }
+    }
+}
+
+/// Switch the main view to `mode`, asking the player for whatever it shows.
+pub fn browse(layout: &Entity<Layout>, mode: BrowseMode, cx: &mut App) {
+    let controller = cx.global::<Controller>();
+    if mode != layout.read(cx).browse {
+        controller.record_view(mode.name());
+    }
+    if !matches!(
+        mode,
+        BrowseMode::NowPlaying | BrowseMode::Insights | BrowseMode::Lyrics | BrowseMode::Visualizer
+    ) {
+        controller.get_library();
+    }
+    if mode == BrowseMode::Skipped {
+        controller.get_most_skipped();
+    }
+    if mode == BrowseMode::Insights {
+        controller.get_insights();
+    }
+    layout.update(cx, |this, cx| {
+        this.browse = mode;
+        cx.notify();
+    });
+}
 
 #[derive(Clone)]
 pub struct MainView {
     pub layout: Entity<Layout>,
+    pub show_details: bool,
+    /// Mode shown in the last frame, to notice when the tabs or the sidebar switch it.
+    shown: BrowseMode,
+    /// Artists whose albums are unfolded in the artist list.
+    pub expanded: HashSet<SharedString>,
+    search_input: Entity<TextInput>,
+    search_focus: FocusHandle,
+    search_query: String,
+    /// Genres and years the browse views are narrowed to.
+    filter: LibraryFilter,
+    /// Inputs for a custom year range, and what they hold.
+    year_inputs: (Entity<TextInput>, Entity<TextInput>),
+    year_text: (String, String),
+    /// Highlighted search result, which Enter adds to the queue.
+    search_selected: usize,
+    /// Search result whose menu is open.
+    track_menu: Option<usize>,
+    /// Scroll of the search results, kept on the highlighted one as it moves.
+    search_scroll: UniformListScrollHandle,
+    /// Search results picked with Ctrl- and Shift-click.
+    selection: Entity<TrackSelection>,
+    /// Order the search results are shown in, instead of by relevance.
+    sort: Option<TrackSort>,
+    /// Scroll of the lyrics view, and the line it last scrolled to.
+    lyrics_scroll: ScrollHandle,
+    lyrics_line: Option<usize>,
+    /// Title and artist inputs while searching for lyrics online, and what they hold.
+    lyrics_search: Option<(Entity<TextInput>, Entity<TextInput>)>,
+    lyrics_query: LyricsQuery,
+    /// The video frame on screen and its generation, while a music video plays.
+    #[cfg(feature = "video")]
+    video: Option<(u64, Arc<RenderImage>)>,
+    /// Whether a throttled redraw is already on its way, while video plays in the background.
+    #[cfg(feature = "video")]
+    redraw_scheduled: bool,
 }
 
 impl Render for MainView {
-    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let mode = self.layout.read(cx).browse;
+        if mode != self.shown {
+            if mode == BrowseMode::Search {
+                window.focus(&self.search_focus);
+            }
+            let visualizing = mode == BrowseMode::Visualizer;
+            if visualizing != (self.shown == BrowseMode::Visualizer) {
+                let visualizer = cx.global::<PlayerContext>().visualizer.clone();
+                visualizer.update(cx, |visualizer, cx| {
+                    visualizer.show(visualizing, window, cx)
+                });
+            }
+            self.shown = mode;
+        }
+        let video = if mode == BrowseMode::NowPlaying {
+            self.video_frame(window, cx)
+        } else {
+            None
+        };
         let meta = cx.global::<PlayerContext>().metadata.clone();
+        let nothing_loaded = cx.global::<PlayerContext>().tracks.read(cx).is_empty();
         let theme = cx.global::<Theme>();
         let layout = self.layout.clone().read(cx);
 
+        if mode != BrowseMode::NowPlaying {
+            return div()
+                .track_focus(&cx.focus_handle())
+                .w(px(layout.central_width))
+                .h_full()
+                .flex()
+                .flex_grow()
+                .flex_col()
+                .overflow_hidden()
+                .child(self.render_tabs(mode, cx))
+                .when(
+                    !matches!(
+                        mode,
+                        BrowseMode::Skipped
+                            | BrowseMode::Insights
+                            | BrowseMode::Lyrics
+                            | BrowseMode::Visualizer
+                    ),
+                    |this| this.child(self.render_filters(cx)),
+                )
+                .child(match mode {
+                    BrowseMode::Albums => self.render_albums(cx),
+                    BrowseMode::Search => self.render_search(cx),
+                    BrowseMode::Skipped => self.render_skipped(cx),
+                    BrowseMode::Insights => self.render_insights(cx),
+                    BrowseMode::Lyrics => self.render_lyrics(cx),
+                    BrowseMode::Visualizer => self.render_visualizer(cx),
+                    _ => self.render_artists(cx),
+                });
+        }
+
+        let backdrop = themes::art_backdrop(cx);
+
         div()
             .track_focus(&cx.focus_handle())
             .w(px(layout.central_width))
@@ -24,23 +201,50 @@ impl Render for MainView {
             .justify_center()
             .flex_col()
             .overflow_hidden()
+            .when_some(backdrop, |this, color| {
+                this.bg(linear_gradient(
+                    180.0,
+                    linear_color_stop(Rgba { a: 0.35, ..color }, 0.0),
+                    linear_color_stop(Rgba { a: 0.0, ..color }, 1.0),
+                ))
+            })
+            .child(self.render_tabs(mode, cx).absolute().top_0())
+            .when(nothing_loaded, |this| this.child(self.render_start(cx)))
             .child({
-                if let Some(thumbnail) = meta.read(cx).thumbnail.clone() {
-                    div()
+                // A playing music video takes the place of the cover art.
+                let placeholder = video.is_none() && meta.read(cx).placeholder;
+                let artwork = video.map(ImageSource::Render).or_else(|| {
+                    meta.read(cx)
+                        .thumbnail
+                        .clone()
+                        .map(|thumbnail| thumbnail.img)
+                });
+                let artwork = match artwork {
+                    Some(artwork) => Some(Self::pulsing(
+                        img(artwork).size_full().object_fit(ObjectFit::Contain),
+                        placeholder,
+                    )),
+                    // Tracks without any artwork get a block in the accent colour instead.
+                    None if !meta.read(cx).title.is_empty() => Some(Self::pulsing(
+                        div()
+                            .size(px(layout.central_width / 2.0))
+                            .rounded_md()
+                            .bg(theme.accent),
+                        placeholder,
+                    )),
+                    None => None,
+                };
+                match artwork {
+                    Some(artwork) => div()
                         .w(px(layout.central_width))
                         .max_h(px(layout.central_width))
                         .flex_col()
                         .flex()
-                        .items_end()
+                        .items_center()
                         .justify_end()
                         .flex_grow()
-                        .child(
-                            img(thumbnail.img)
-                                .size_full()
-                                .object_fit(ObjectFit::Contain),
-                        )
-                } else {
-                    div()
+                        .child(artwork),
+                    None => div(),
                 }
             })
             .child(div().h(px(16.0)))
@@ -83,13 +287,1557 @@ impl Render for MainView {
                         } else {
                             div()
                         }
+                    })
+                    .child({
+                        let meta = meta.read(cx);
+                        let origin = meta.origin;
+                        let loudness = cx
+                            .global::<PlayerContext>()
+                            .loudness
+                            .read(cx)
+                            .get(&meta.uri)
+                            .copied();
+                        if let Some(info) = meta.stream_info.clone() {
+                            div()
+                                .flex()
+                                .flex_col()
+                                .items_center()
+                                .gap_1()
+                                .child(
+                                    div()
+                                        .id("stream_details")
+                                        .text_sm()
+                                        .text_color(theme.text)
+                                        .hover(|this| this.text_color(theme.accent))
+                                        .child(if self.show_details {
+                                            "Hide details"
+                                        } else {
+                                            "Show details"
+                                        })
+                                        .on_click(cx.listener(|this, _, _, cx| {
+                                            this.show_details = !this.show_details;
+                                            cx.notify();
+                                        })),
+                                )
+                                .when(self.show_details, |this| {
+                                    this.child(
+                                        div()
+                                            .flex()
+                                            .gap_3()
+                                            .text_sm()
+                                            .text_color(theme.text)
+                                            .child(info.codec.clone())
+                                            .when(info.bitrate > 0, |this| {
+                                                this.child(format!("{} kbps", info.bitrate / 1000))
+                                            })
+                                            .child(format!(
+                                                "{:.1} kHz",
+                                                info.sample_rate as f32 / 1000.0
+                                            ))
+                                            .when(info.bit_depth > 0, |this| {
+                                                this.child(format!("{}-bit", info.bit_depth))
+                                            })
+                                            .when(info.bit_perfect, |this| {
+                                                this.child("Bit-perfect")
+                                            })
+                                            .child(match info.channels {
+                                                1 => "Mono".to_string(),
+                                                2 => "Stereo".to_string(),
+                                                n => format!("{n} channels"),
+                                            })
+                                            .when(origin != Origin::Unknown, |this| {
+                                                this.child(format!("Origin: {}", origin.label()))
+                                            })
+                                            .when_some(loudness, |this, loudness| {
+                                                this.child(loudness_label(&loudness)).when(
+                                                    loudness.is_heavily_clipped(),
+                                                    |this| {
+                                                        this.child(
+                                                            div()
+                                                                .text_color(theme.warning)
+                                                                .child("Heavily clipped"),
+                                                        )
+                                                    },
+                                                )
+                                            }),
+                                    )
+                                })
+                        } else {
+                            div()
+                        }
+                    })
+                    .when(!meta.read(cx).title.is_empty(), |this| {
+                        let context = cx.global::<PlayerContext>();
+                        let count = context.chapters.read(cx).len();
+                        let editing = context.editing_chapters.clone();
+                        this.child(
+                            div()
+                                .id("edit_chapters")
+                                .text_sm()
+                                .text_color(theme.text)
+                                .hover(|this| this.text_color(theme.accent))
+                                .child(match count {
+                                    0 => "Add chapters".to_string(),
+                                    1 => "1 chapter".to_string(),
+                                    n => format!("{n} chapters"),
+                                })
+                                .on_click(move |_, _, cx| {
+                                    editing.update(cx, |this, cx| {
+                                        *this = true;
+                                        cx.notify();
+                                    });
+                                }),
+                        )
+                    })
+                    .when(!meta.read(cx).title.is_empty(), |this| {
+                        let context = cx.global::<PlayerContext>();
+                        let has_lyrics = context
+                            .lyrics
+                            .read(cx)
+                            .as_ref()
+                            .is_some_and(|lyrics| !lyrics.lines.is_empty());
+                        let editing = context.editing_lyrics.clone();
+                        this.child(
+                            div()
+                                .id("edit_lyrics")
+                                .text_sm()
+                                .text_color(theme.text)
+                                .hover(|this| this.text_color(theme.accent))
+                                .child(if has_lyrics { "Lyrics" } else { "Add lyrics" })
+                                .on_click(move |_, _, cx| {
+                                    editing.update(cx, |this, cx| {
+                                        *this = true;
+                                        cx.notify();
+                                    });
+                                }),
+                        )
                     }),
             )
+            .child(self.render_resume_cards(cx))
     }
 }
 
 impl MainView {
-    pub fn new(layout: Entity<Layout>) -> Self {
-        MainView { layout }
+    /// The latest frame of the playing music video, uploaded once per new frame.
+    #[cfg(feature = "video")]
+    fn video_frame(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Option<Arc<RenderImage>> {
+        let Some((generation, frame)) = backend::video::latest() else {
+            if let Some((_, old)) = self.video.take() {
+                let _ = window.drop_image(old);
+            }
+            return None;
+        };
+        // Keep redrawing while the video runs; paused video sits on its last frame. In the
+        // background it only moves on at the throttled rate.
+        if cx.global::<PlayerContext>().state.read(cx).state == gstreamer::State::Playing {
+            match cx.global::<crate::activity::Activity>().throttle() {
+                None => window.request_animation_frame(),
+                Some(interval) => self.schedule_redraw(interval, cx),
+            }
+        }
+        let stale = self
+            .video
+            .as_ref()
+            .is_some_and(|(seen, _)| *seen != generation);
+        let held = stale
+            && !cx
+                .global_mut::<crate::activity::Activity>()
+                .due(crate::activity::Redraw::Video);
+        if let Some((_, image)) = self
+            .video
+            .as_ref()
+            .filter(|(seen, _)| *seen == generation || held)
+        {
+            return Some(image.clone());
+        }
+        let pixels = image::RgbaImage::from_raw(frame.width, frame.height, frame.data.clone())?;
+        let image = Arc::new(RenderImage::new(smallvec::smallvec![image::Frame::new(
+            pixels
+        )]));
+        if let Some((_, old)) = self.video.replace((generation, image.clone())) {
+            let _ = window.drop_image(old);
+        }
+        Some(image)
+    }
+
+    /// Redraw once after `interval`, for video playing in a background window.
+    #[cfg(feature = "video")]
+    fn schedule_redraw(&mut self, interval: std::time::Duration, cx: &mut Context<Self>) {
+        if self.redraw_scheduled {
+            return;
+        }
+        self.redraw_scheduled = true;
+        cx.spawn(move |this: WeakEntity<Self>, cx: AsyncApp| async move {
+            cx.background_executor().timer(interval).await;
+            let _ = this.update(&mut cx.clone(), |this, cx| {
+                this.redraw_scheduled = false;
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    #[cfg(not(feature = "video"))]
+    fn video_frame(&mut self, _: &mut Window, _: &mut Context<Self>) -> Option<Arc<RenderImage>> {
+        None
+    }
+
+    fn render_tabs(&self, current: BrowseMode, cx: &mut Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let tab = |id: &'static str, label: &'static str, mode: BrowseMode| {
+            div()
+                .id(id)
+                .text_sm()
+                .text_color(if current == mode {
+                    theme.accent
+                } else {
+                    theme.text
+                })
+                .hover(|this| this.text_color(theme.accent))
+                .child(label)
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    browse(&this.layout, mode, cx);
+                }))
+        };
+
+        div()
+            .w_full()
+            .flex()
+            .flex_shrink_0()
+            .justify_center()
+            .gap_4()
+            .py_2()
+            .child(tab(
+                "mode_now_playing",
+                "Now Playing",
+                BrowseMode::NowPlaying,
+            ))
+            .child(tab("mode_albums", "Albums", BrowseMode::Albums))
+            .child(tab("mode_artists", "Artists", BrowseMode::Artists))
+            .child(tab("mode_search", "Search", BrowseMode::Search))
+            .child(tab("mode_skipped", "Most Skipped", BrowseMode::Skipped))
+            .child(tab("mode_insights", "Insights", BrowseMode::Insights))
+            .child(tab("mode_lyrics", "Lyrics", BrowseMode::Lyrics))
+            .child(tab("mode_visualizer", "Visualizer", BrowseMode::Visualizer))
+    }
+
+    /// Chips narrowing the library to some genres, a decade or a range of years. Genres can be
+    /// combined; a decade replaces whatever range was typed.
+    fn render_filters(&self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let context = cx.global::<PlayerContext>();
+        let genres = context.genres.read(cx).clone();
+        let decades = context.decades.read(cx).clone();
+        let theme = cx.global::<Theme>();
+        let chip = |id: ElementId, label: String, selected: bool| {
+            div()
+                .id(id)
+                .flex_shrink_0()
+                .px_2()
+                .rounded_full()
+                .text_sm()
+                .border_1()
+                .border_color(if selected {
+                    theme.accent
+                } else {
+                    theme.secondary
+                })
+                .when(selected, |this| this.bg(theme.accent))
+                .text_color(if selected {
+                    theme.background
+                } else {
+                    theme.text
+                })
+                .hover(|this| this.border_color(theme.accent))
+                .child(label)
+        };
+        let year_input = |input: Entity<TextInput>| {
+            div()
+                .w(px(56.0))
+                .px_1()
+                .rounded_md()
+                .text_sm()
+                .border_1()
+                .border_color(theme.secondary)
+                .child(input)
+        };
+
+        div()
+            .id("library_filters")
+            .flex()
+            .flex_shrink_0()
+            .flex_wrap()
+            .items_center()
+            .gap_2()
+            .px_4()
+            .pb_2()
+            .max_h(px(96.0))
+            .overflow_y_scroll()
+            .when(!self.filter.is_empty(), |this| {
+                this.child(
+                    chip("filter_clear".into(), "Clear filters".into(), false).on_click(
+                        cx.listener(|this, _, _, cx| {
+                            this.filter = LibraryFilter::default();
+                            this.clear_year_inputs(cx);
+                            this.apply_filter(cx);
+                        }),
+                    ),
+                )
+            })
+            .children(decades.into_iter().map(|decade| {
+                let years = (decade, decade + 9);
+                let selected = self.filter.years == Some(years);
+                chip(
+                    ("filter_decade", decade as usize).into(),
+                    format!("{decade}s"),
+                    selected,
+                )
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    this.filter.years = (!selected).then_some(years);
+                    this.clear_year_inputs(cx);
+                    this.apply_filter(cx);
+                }))
+            }))
+            .child(
+                div()
+                    .flex()
+                    .flex_shrink_0()
+                    .items_center()
+                    .gap_1()
+                    .text_sm()
+                    .text_color(theme.tokens.text.muted)
+                    .child(year_input(self.year_inputs.0.clone()))
+                    .child("–")
+                    .child(year_input(self.year_inputs.1.clone())),
+            )
+            .children(genres.into_iter().enumerate().map(|(index, genre)| {
+                let selected = self
+                    .filter
+                    .genres
+                    .iter()
+                    .any(|g| g.eq_ignore_ascii_case(&genre));
+                chip(("filter_genre", index).into(), genre.clone(), selected).on_click(cx.listener(
+                    move |this, _, _, cx| {
+                        if selected {
+                            this.filter
+                                .genres
+                                .retain(|g| !g.eq_ignore_ascii_case(&genre));
+                        } else {
+                            this.filter.genres.push(genre.clone());
+                        }
+                        this.apply_filter(cx);
+                    },
+                ))
+            }))
+    }
+
+    /// Send the filter to the player and search again with it.
+    fn apply_filter(&mut self, cx: &mut Context<Self>) {
+        let controller = cx.global::<Controller>();
+        controller.filter_library(self.filter.clone());
+        if !self.search_query.trim().is_empty() {
+            controller.search(self.search_query.clone());
+        }
+        cx.notify();
+    }
+
+    fn clear_year_inputs(&mut self, cx: &mut Context<Self>) {
+        for input in [&self.year_inputs.0, &self.year_inputs.1] {
+            input.update(cx, |this, cx| {
+                this.reset();
+                cx.notify();
+            });
+        }
+        self.year_text = (String::new(), String::new());
+    }
+
+    /// Filter by the years typed in, either end left open when blank or not a number.
+    fn typed_years(&mut self, cx: &mut Context<Self>) {
+        let from = self.year_text.0.trim().parse::<u32>().ok();
+        let to = self.year_text.1.trim().parse::<u32>().ok();
+        self.filter.years = match (from, to) {
+            (None, None) => None,
+            (from, to) => Some((from.unwrap_or(0), to.unwrap_or(u32::MAX))),
+        };
+        self.apply_filter(cx);
+    }
+
+    /// `artwork`, pulsing while it stands in for the full artwork that is on its way.
+    fn pulsing<E: IntoElement + Styled + 'static>(artwork: E, pulse: bool) -> AnyElement {
+        if !pulse {
+            return artwork.into_any_element();
+        }
+        artwork
+            .with_animation(
+                "artwork_placeholder",
+                Animation::new(Duration::from_millis(1200))
+                    .repeat()
+                    .with_easing(pulsating_between(0.5, 0.9)),
+                |artwork, delta| artwork.opacity(delta),
+            )
+            .into_any_element()
+    }
+
+    fn render_cover(album: &Album, size: Pixels, placeholder: Rgba) -> AnyElement {
+        match album.thumbnail.clone() {
+            Some(thumbnail) => img(thumbnail.img)
+                .size(size)
+                .flex_shrink_0()
+                .rounded_md()
+                .object_fit(ObjectFit::Cover)
+                .into_any_element(),
+            None => div()
+                .size(size)
+                .flex_shrink_0()
+                .rounded_md()
+                .bg(placeholder)
+                .into_any_element(),
+        }
+    }
+
+    fn render_empty(cx: &mut Context<Self>) -> Stateful<Div> {
+        let theme = cx.global::<Theme>();
+        div()
+            .id("library_empty")
+            .flex_grow()
+            .flex()
+            .items_center()
+            .justify_center()
+            .text_color(theme.tokens.text.muted)
+            .child("Open a folder to fill your library.")
+    }
+
+    /// Grid of album covers. Clicking one plays the album from its first track; right-clicking
+    /// adds it to the queue. Only the rows in view are built.
+    fn render_albums(&self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let albums = cx.global::<PlayerContext>().albums.read(cx).clone();
+        if albums.is_empty() {
+            return Self::render_empty(cx);
+        }
+        // As many covers as fit across, less the padding, with a gap after each.
+        let width = self.layout.read(cx).central_width - 2.0 * ALBUM_GRID_PADDING + ALBUM_GAP;
+        let columns = ((width / (ALBUM_WIDTH + ALBUM_GAP)) as usize).max(1);
+        let rows = albums.len().div_ceil(columns);
+
+        div().id("album_grid").flex_grow().child(
+            uniform_list(cx.entity(), "album_rows", rows, move |_, range, _, cx| {
+                let theme = cx.global::<Theme>();
+                let row = theme.components.row;
+                let placeholder = theme.secondary;
+
+                range
+                    .map(|line| {
+                        let start = line * columns;
+                        let end = (start + columns).min(albums.len());
+                        div().flex().gap(px(ALBUM_GAP)).pb(px(ALBUM_GAP)).children(
+                            albums[start..end]
+                                .iter()
+                                .enumerate()
+                                .map(|(offset, album)| {
+                                    let (name, artist) = album.key.clone();
+                                    let (queue_name, queue_artist) = album.key.clone();
+                                    div()
+                                        .id(("album", start + offset))
+                                        .w(px(ALBUM_WIDTH))
+                                        .flex()
+                                        .flex_col()
+                                        .gap_1()
+                                        .p_2()
+                                        .rounded_md()
+                                        .hover(|this| this.bg(row.hover_bg))
+                                        .child(Self::render_cover(album, px(144.0), placeholder))
+                                        .child(
+                                            div()
+                                                .text_color(row.text)
+                                                .font_weight(FontWeight::MEDIUM)
+                                                .truncate()
+                                                .child(album.name.clone()),
+                                        )
+                                        .child(
+                                            div()
+                                                .text_sm()
+                                                .text_color(row.subtext)
+                                                .truncate()
+                                                .child(album.artist.clone()),
+                                        )
+                                        .on_click(move |_, _, cx| {
+                                            cx.global::<Controller>()
+                                                .play_album(name.clone(), artist.clone());
+                                        })
+                                        .on_mouse_down(MouseButton::Right, move |_, _, cx| {
+                                            cx.global::<Controller>().queue_album(
+                                                queue_name.clone(),
+                                                queue_artist.clone(),
+                                            );
+                                        })
+                                }),
+                        )
+                    })
+                    .collect()
+            })
+            .h_full()
+            .px(px(ALBUM_GRID_PADDING))
+            .pt_4(),
+        )
+    }
+
+    /// Artists in name order; clicking one unfolds their albums, which play like in the album
+    /// grid. Queue adds everything by the artist to the queue. Artists and unfolded albums are
+    /// rows of one height, so only those in view are built.
+    fn render_artists(&self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let artists = cx.global::<PlayerContext>().artists.read(cx).clone();
+        if artists.is_empty() {
+            return Self::render_empty(cx);
+        }
+        // Each artist, then their albums while unfolded.
+        let rows: Vec<(usize, Option<usize>)> = artists
+            .iter()
+            .enumerate()
+            .flat_map(|(index, artist)| {
+                let albums = if self.expanded.contains(&artist.name) {
+                    artist.albums.len()
+                } else {
+                    0
+                };
+                std::iter::once((index, None)).chain((0..albums).map(move |i| (index, Some(i))))
+            })
+            .collect();
+
+        div().id("artist_list").flex_grow().child(
+            uniform_list(
+                cx.entity(),
+                "artist_rows",
+                rows.len(),
+                move |_, range, _, cx| {
+                    range
+                        .map(|line| match rows[line] {
+                            (index, None) => Self::render_artist(index, &artists[index], cx),
+                            (index, Some(i)) => {
+                                Self::render_artist_album(index, i, &artists[index].albums[i], cx)
+                            }
+                        })
+                        .collect()
+                },
+            )
+            .h_full()
+            .p_4(),
+        )
+    }
+
+    fn render_artist(index: usize, artist: &Artist, cx: &mut Context<Self>) -> Stateful<Div> {
+        let theme = cx.global::<Theme>();
+        let row = theme.components.row;
+        let button = theme.components.button;
+        let name = artist.name.clone();
+        let queued = artist.name.clone();
+
+        div()
+            .id(("artist", index))
+            .h_10()
+            .flex()
+            .items_center()
+            .justify_between()
+            .px_2()
+            .rounded_md()
+            .text_color(row.text)
+            .hover(|this| this.bg(row.hover_bg))
+            .child(div().flex_grow().truncate().child(artist.name.clone()))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .child(div().text_sm().text_color(row.subtext).child(
+                        match artist.albums.len() {
+                            1 => "1 album".to_string(),
+                            n => format!("{n} albums"),
+                        },
+                    ))
+                    .child(action(("artist_queue", index), "Queue", button).on_click(
+                        move |_, _, cx| {
+                            // Not a click on the row as well.
+                            cx.stop_propagation();
+                            cx.global::<Controller>().queue_artist(queued.clone());
+                        },
+                    )),
+            )
+            .on_click(cx.listener(move |this, _, _, cx| {
+                if !this.expanded.remove(&name) {
+                    this.expanded.insert(name.clone());
+                }
+                cx.notify();
+            }))
+    }
+
+    /// Album `i` of the artist at `index`, unfolded under them.
+    fn render_artist_album(
+        index: usize,
+        i: usize,
+        album: &Album,
+        cx: &mut Context<Self>,
+    ) -> Stateful<Div> {
+        let theme = cx.global::<Theme>();
+        let row = theme.components.row;
+        let placeholder = theme.secondary;
+        let (name, artist) = album.key.clone();
+        let (queue_name, queue_artist) = album.key.clone();
+
+        div()
+            .id(SharedString::from(format!("artist_album_{index}_{i}")))
+            .h_10()
+            .flex()
+            .items_center()
+            .gap_2()
+            .pl_6()
+            .pr_2()
+            .rounded_md()
+            .hover(|this| this.bg(row.hover_bg))
+            .child(Self::render_cover(album, px(32.0), placeholder))
+            .child(
+                div()
+                    .flex()
+                    .items_baseline()
+                    .gap_2()
+                    .overflow_hidden()
+                    .child(
+                        div()
+                            .text_color(row.text)
+                            .truncate()
+                            .child(album.name.clone()),
+                    )
+                    .child(
+                        div()
+                            .flex_shrink_0()
+                            .text_sm()
+                            .text_color(row.subtext)
+                            .child(match album.track_count {
+                                1 => "1 track".to_string(),
+                                n => format!("{n} tracks"),
+                            }),
+                    ),
+            )
+            .on_click(move |_, _, cx| {
+                cx.global::<Controller>()
+                    .play_album(name.clone(), artist.clone());
+            })
+            .on_mouse_down(MouseButton::Right, move |_, _, cx| {
+                cx.global::<Controller>()
+                    .queue_album(queue_name.clone(), queue_artist.clone());
+            })
+    }
+
+    /// Search box over the whole library with results as you type. Up and down move the
+    /// highlight, Enter or a click adds the track to the queue, a right-click offers to play it
+    /// next, and Play all plays the results as a queue of their own. Ctrl- and Shift-click pick
+    /// several results to act on at once.
+    fn render_search(&self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let results = self.sorted_results(cx);
+        let uris: Vec<String> = results.iter().map(|track| track.uri.clone()).collect();
+        self.selection
+            .update(cx, |selection, _| selection.retain(&uris));
+        let selected = self.search_selected.min(results.len().saturating_sub(1));
+        let count = results.len();
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        div()
+            .id("library_search")
+            .key_context("LibrarySearch")
+            .flex_grow()
+            .flex()
+            .flex_col()
+            .overflow_hidden()
+            .on_action(cx.listener(move |this, _: &Next, _, cx| {
+                this.select_result((selected + 1).min(count.saturating_sub(1)), cx);
+            }))
+            .on_action(cx.listener(move |this, _: &Previous, _, cx| {
+                this.select_result(selected.saturating_sub(1), cx);
+            }))
+            .on_action(cx.listener(move |this, _: &Accept, _, cx| {
+                if let Some(track) = this.sorted_results(cx).get(selected) {
+                    cx.global::<Controller>().enqueue(vec![track.uri.clone()]);
+                }
+            }))
+            .child(
+                div()
+                    .mx_4()
+                    .h_10()
+                    .py_1()
+                    .px_2()
+                    .flex_shrink_0()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(theme.secondary)
+                    .child(self.search_input.clone()),
+            )
+            .when(count > 0, |this| {
+                this.child(
+                    div()
+                        .mx_4()
+                        .mt_2()
+                        .flex()
+                        .items_center()
+                        .justify_between()
+                        .gap_2()
+                        .child(sort_bar(
+                            "search_sort",
+                            self.sort,
+                            |this: &mut Self, sort| this.sort = sort,
+                            cx,
+                        ))
+                        .child(
+                            action("search_play_all", format!("Play all {count}"), button)
+                                .flex_shrink_0()
+                                .on_click(cx.listener(|this, _, _, cx| {
+                                    cx.global::<Controller>()
+                                        .play_search(this.search_query.clone());
+                                })),
+                        ),
+                )
+            })
+            .child(div().mx_4().mt_2().child(self.selection.clone()))
+            .child(
+                div().id("search_results").flex_grow().child(
+                    uniform_list(
+                        cx.entity(),
+                        "search_rows",
+                        count,
+                        move |this, range, _, cx| {
+                            range
+                                .map(|index| {
+                                    let track = results[index].clone();
+                                    let menu = this.track_menu == Some(index);
+                                    let picked = this.selection.read(cx).contains(&track.uri);
+                                    Self::render_result(index, track, selected, menu, picked, cx)
+                                })
+                                .collect()
+                        },
+                    )
+                    .track_scroll(self.search_scroll.clone())
+                    .h_full()
+                    .p_4(),
+                ),
+            )
+    }
+
+    /// Move the search selection to `index`, keeping it in the middle of the list where the
+    /// results around it can be seen.
+    fn select_result(&mut self, index: usize, cx: &mut Context<Self>) {
+        self.search_selected = index;
+        self.search_scroll
+            .scroll_to_item(index, ScrollStrategy::Center);
+        cx.notify();
+    }
+
+    /// The search results in the order picked for them.
+    fn sorted_results(&self, cx: &App) -> Vec<Track> {
+        let context = cx.global::<PlayerContext>();
+        let mut results = context.search_results.read(cx).clone();
+        if let Some(sort) = self.sort {
+            sort.apply(&mut results, context.play_counts.read(cx));
+        }
+        results
     }
+
+    /// One search result. Right-click it for its menu, shown over the results below it when
+    /// `menu` is set, so every result stays the same height. `picked` marks it as part of the
+    /// selection.
+    fn render_result(
+        index: usize,
+        track: Track,
+        selected: usize,
+        menu: bool,
+        picked: bool,
+        cx: &mut Context<Self>,
+    ) -> Stateful<Div> {
+        let theme = cx.global::<Theme>();
+        let row = theme.components.row;
+        let uri = track.uri.clone();
+        let menu_uri = track.uri.clone();
+
+        let result = div()
+            .id(("search_result", index))
+            .mb_1()
+            .flex()
+            .justify_between()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .when(index == selected, |this| this.bg(row.hover_bg))
+            .when(picked, |this| this.border_1().border_color(theme.accent))
+            .hover(|this| this.bg(row.hover_bg))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .overflow_hidden()
+                    .child(div().text_color(row.text).truncate().child(track.title))
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(row.subtext)
+                            .truncate()
+                            .child(format!("{} • {}", track.artists.join(", "), track.album)),
+                    ),
+            )
+            .child(
+                div()
+                    .flex_shrink_0()
+                    .text_sm()
+                    .text_color(row.subtext)
+                    .child(format!(
+                        "{:02}:{:02}",
+                        track.duration / 60,
+                        track.duration % 60
+                    )),
+            )
+            .on_click(cx.listener(move |this, _, window, cx| {
+                let rows: Vec<String> = this
+                    .sorted_results(cx)
+                    .into_iter()
+                    .map(|track| track.uri)
+                    .collect();
+                let modifiers = window.modifiers();
+                let picked = this.selection.update(cx, |selection, cx| {
+                    selection.click(index, &rows, modifiers, cx)
+                });
+                if !picked {
+                    cx.global::<Controller>().enqueue(vec![uri.clone()]);
+                }
+                this.search_selected = index;
+                cx.notify();
+            }))
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(move |this, _, _, cx| {
+                    this.track_menu = Some(index);
+                    cx.notify();
+                }),
+            );
+        if !menu {
+            return result;
+        }
+
+        let view = cx.entity().downgrade();
+        let close = move |cx: &mut App| {
+            let _ = view.update(cx, |this, cx| {
+                this.track_menu = None;
+                cx.notify();
+            });
+        };
+        result.child(
+            deferred(
+                anchored().anchor(Corner::TopRight).snap_to_window().child(
+                    div()
+                        .w_48()
+                        .mt_8()
+                        .child(queue_menu(index, menu_uri, close, cx)),
+                ),
+            )
+            .with_priority(3),
+        )
+    }
+
+    /// Tracks skipped most often, which shuffle plays later, and a button to start afresh.
+    /// The current track's lyrics. Synced ones follow along, keeping the line being sung in
+    /// view, and clicking a line seeks to it.
+    fn render_lyrics(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let context = cx.global::<PlayerContext>();
+        let lyrics = context.lyrics.read(cx).clone().unwrap_or_default();
+        let current = lyrics.line_at(context.state.read(cx).position * 1000);
+        let editing = context.editing_lyrics.clone();
+        if current != self.lyrics_line {
+            self.lyrics_line = current;
+            if let Some(line) = current {
+                self.lyrics_scroll.scroll_to_item(line);
+            }
+        }
+        let search = self.render_lyrics_search(cx);
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let muted = theme.tokens.text.muted;
+        let synced = lyrics.is_synced();
+        let offset = lyrics.offset_ms;
+        let view = div()
+            .id("lyrics")
+            .track_scroll(&self.lyrics_scroll)
+            .flex_grow()
+            .overflow_y_scroll()
+            .flex()
+            .flex_col()
+            .items_center()
+            .gap_2()
+            .p_4();
+
+        let view = if lyrics.lines.is_empty() {
+            view.child(div().text_color(muted).child(
+                "No lyrics for this track. They're read from its tags, from a .lrc or .txt file \
+                 with the same name next to it, or looked up online when that's turned on.",
+            ))
+            .child(
+                action("lyrics_start", "Add lyrics", button).on_click(move |_, _, cx| {
+                    editing.update(cx, |this, cx| {
+                        *this = true;
+                        cx.notify();
+                    });
+                }),
+            )
+        } else {
+            view.children(lyrics.lines.into_iter().enumerate().map(|(index, line)| {
+                let sung = current == Some(index);
+                // The line's start in the track, undoing the offset it's shown with.
+                let start = line
+                    .time
+                    .map(|time| (time as i64 - offset).max(0) as u64 / 1000);
+                div()
+                    .id(("lyric", index))
+                    .text_xl()
+                    .text_align(TextAlign::Center)
+                    .text_color(if sung || !synced { theme.text } else { muted })
+                    .when(sung, |this| {
+                        this.text_color(theme.accent).font_weight(FontWeight::BOLD)
+                    })
+                    .child(line.text)
+                    .when_some(start, |this, start| {
+                        this.cursor_pointer()
+                            .hover(|this| this.text_color(theme.accent))
+                            .on_click(move |_, _, cx| cx.global::<Controller>().seek(start))
+                    })
+            }))
+        };
+        div()
+            .id("lyrics_view")
+            .flex_grow()
+            .flex()
+            .flex_col()
+            .child(search)
+            .child(view)
+    }
+
+    /// A link to search for the current track's lyrics online, or the search once it's open,
+    /// for when its tags find nothing or the wrong song.
+    fn render_lyrics_search(&self, cx: &mut Context<Self>) -> Div {
+        let open = cx.listener(|this, _, _, cx| this.open_lyrics_search(cx));
+        let search = cx.listener(|this, _, _, cx| this.search_lyrics(cx));
+        let cancel = cx.listener(|this, _, _, cx| {
+            this.lyrics_search = None;
+            cx.notify();
+        });
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let row = div()
+            .flex()
+            .items_center()
+            .justify_end()
+            .gap_2()
+            .px_4()
+            .pt_2();
+        let Some((title, artist)) = self.lyrics_search.clone() else {
+            return row.child(
+                div()
+                    .id("lyrics_search_open")
+                    .text_sm()
+                    .text_color(theme.text)
+                    .hover(|this| this.text_color(theme.accent))
+                    .child("Search online…")
+                    .on_click(open),
+            );
+        };
+        let field = |input: Entity<TextInput>| {
+            div()
+                .h_8()
+                .w(px(200.0))
+                .px_2()
+                .py_1()
+                .rounded_md()
+                .border_1()
+                .border_color(theme.secondary)
+                .child(input)
+        };
+        row.child(field(title))
+            .child(field(artist))
+            .child(action("lyrics_search", "Search", button).on_click(search))
+            .child(action("lyrics_search_cancel", "Cancel", button).on_click(cancel))
+    }
+
+    /// Open the lyrics search, filled in from the current track's tags.
+    fn open_lyrics_search(&mut self, cx: &mut Context<Self>) {
+        let meta = cx.global::<PlayerContext>().metadata.read(cx);
+        self.lyrics_query = LyricsQuery {
+            title: meta.title.to_string(),
+            artist: meta.artists.join(", "),
+            // Left out so a corrected title isn't held to an album it isn't on.
+            album: String::new(),
+            duration: meta.duration,
+        };
+        let handle = cx.focus_handle();
+        let title = TextInput::new(
+            cx,
+            handle,
+            Some(self.lyrics_query.title.clone().into()),
+            Some("Title".into()),
+        );
+        cx.subscribe(&title, |this: &mut Self, _, text: &String, _| {
+            this.lyrics_query.title = text.clone();
+        })
+        .detach();
+        let handle = cx.focus_handle();
+        let artist = TextInput::new(
+            cx,
+            handle,
+            Some(self.lyrics_query.artist.clone().into()),
+            Some("Artist".into()),
+        );
+        cx.subscribe(&artist, |this: &mut Self, _, text: &String, _| {
+            this.lyrics_query.artist = text.clone();
+        })
+        .detach();
+        self.lyrics_search = Some((title, artist));
+        cx.notify();
+    }
+
+    fn search_lyrics(&mut self, cx: &mut Context<Self>) {
+        if self.lyrics_query.title.trim().is_empty() {
+            return;
+        }
+        cx.global::<Controller>()
+            .search_lyrics(self.lyrics_query.clone());
+        self.lyrics_search = None;
+        cx.notify();
+    }
+
+    /// The visualizer with its styles to pick from, or a note while it fills the screen.
+    fn render_visualizer(&self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let visualizer = cx.global::<PlayerContext>().visualizer.clone();
+        let fullscreen = visualizer.read(cx).fullscreen;
+        let chosen = cx.global::<Settings>().visualizer;
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let styles = STYLES
+            .into_iter()
+            .enumerate()
+            .map(|(index, (style, label))| {
+                div()
+                    .id(("visualizer_style", index))
+                    .text_sm()
+                    .text_color(if style == chosen {
+                        theme.accent
+                    } else {
+                        theme.text
+                    })
+                    .hover(|this| this.text_color(theme.accent))
+                    .child(label)
+                    .on_click(move |_, _, cx| {
+                        change_settings(cx, |settings| settings.visualizer = style);
+                    })
+            });
+
+        div()
+            .id("visualizer")
+            .flex_grow()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_4()
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_4()
+                    .children(styles)
+                    .child(div().flex_grow())
+                    .child(
+                        action("visualizer_fullscreen", "Full screen", button).on_click({
+                            let visualizer = visualizer.clone();
+                            move |_, window, cx| {
+                                visualizer.update(cx, |visualizer, cx| {
+                                    visualizer.set_fullscreen(true, window, cx);
+                                });
+                            }
+                        }),
+                    ),
+            )
+            .child(if fullscreen {
+                div()
+                    .flex_grow()
+                    .text_color(theme.tokens.text.muted)
+                    .child("Showing full screen.")
+            } else {
+                div().flex_grow().child(visualizer)
+            })
+    }
+
+    fn render_skipped(&self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let skipped = cx.global::<PlayerContext>().most_skipped.read(cx).clone();
+        let theme = cx.global::<Theme>();
+        let row = theme.components.row;
+        let button = theme.components.button;
+
+        div()
+            .id("most_skipped")
+            .flex_grow()
+            .overflow_y_scroll()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .p_4()
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .pb_2()
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(theme.tokens.text.muted)
+                            .child("Shuffle plays often-skipped tracks later."),
+                    )
+                    .when(!skipped.is_empty(), |this| {
+                        this.child(action("reset_skips", "Reset", button).on_click(|_, _, cx| {
+                            cx.global::<Controller>().reset_skips();
+                        }))
+                    }),
+            )
+            .when(skipped.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_color(theme.tokens.text.muted)
+                        .child("Nothing skipped yet."),
+                )
+            })
+            .children(skipped.into_iter().map(|skipped| {
+                let position = skipped.typical_position;
+                div()
+                    .flex()
+                    .justify_between()
+                    .gap_2()
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .hover(|this| this.bg(row.hover_bg))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .overflow_hidden()
+                            .child(
+                                div()
+                                    .text_color(row.text)
+                                    .truncate()
+                                    .child(skipped.track.title),
+                            )
+                            .child(
+                                div()
+                                    .text_sm()
+                                    .text_color(row.subtext)
+                                    .truncate()
+                                    .child(skipped.track.artists.join(", ")),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .flex_shrink_0()
+                            .text_sm()
+                            .text_color(row.subtext)
+                            .child(format!(
+                                "{} • usually at {:02}:{:02}",
+                                match skipped.skips {
+                                    1 => "1 skip".to_string(),
+                                    n => format!("{n} skips"),
+                                },
+                                position / 60,
+                                position % 60
+                            )),
+                    )
+            }))
+    }
+
+    /// The views, commands and settings used most, from counts kept in a file on this computer,
+    /// or what insights are and a button to turn them on.
+    fn render_insights(&self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let insights = cx.global::<PlayerContext>().insights.read(cx).clone();
+        let theme = cx.global::<Theme>();
+        let row = theme.components.row;
+        let button = theme.components.button;
+        let muted = theme.tokens.text.muted;
+        let view = div()
+            .id("insights")
+            .flex_grow()
+            .overflow_y_scroll()
+            .flex()
+            .flex_col()
+            .gap_4()
+            .p_4();
+
+        let Some(insights) = insights else {
+            return view
+                .child(div().text_color(muted).child(
+                    "Insights count which views, commands and settings you use, to show you how \
+                     you use the app. The counts stay in a file on this computer and are never \
+                     sent anywhere.",
+                ))
+                .child(
+                    action("insights_on", "Turn on", button)
+                        .on_click(|_, _, cx| set_insights(true, cx)),
+                );
+        };
+        let file = Insights::get_insights_file()
+            .map(|file| file.display().to_string())
+            .unwrap_or_default();
+        let section = |title: &'static str, counts: &BTreeMap<String, u64>| {
+            let mut counts: Vec<(&String, &u64)> = counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            div()
+                .flex()
+                .flex_col()
+                .gap_1()
+                .child(div().text_sm().text_color(muted).child(title))
+                .when(counts.is_empty(), |this| {
+                    this.child(div().px_2().text_color(row.subtext).child("Nothing yet."))
+                })
+                .children(
+                    counts
+                        .into_iter()
+                        .take(INSIGHTS_SHOWN)
+                        .map(|(name, count)| {
+                            div()
+                                .flex()
+                                .justify_between()
+                                .gap_2()
+                                .px_2()
+                                .py_1()
+                                .rounded_md()
+                                .hover(|this| this.bg(row.hover_bg))
+                                .child(div().text_color(row.text).truncate().child(name.clone()))
+                                .child(
+                                    div()
+                                        .flex_shrink_0()
+                                        .text_sm()
+                                        .text_color(row.subtext)
+                                        .child(count.to_string()),
+                                )
+                        }),
+                )
+        };
+
+        view.child(
+            div()
+                .flex()
+                .items_center()
+                .justify_between()
+                .gap_2()
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(muted)
+                        .child(format!("Counting since {}, in {file}", ago(insights.since))),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .gap_1()
+                        .child(
+                            action("insights_clear", "Clear", button).on_click(|_, _, cx| {
+                                cx.global::<Controller>().clear_insights();
+                            }),
+                        )
+                        .child(
+                            action("insights_off", "Turn off", button)
+                                .on_click(|_, _, cx| set_insights(false, cx)),
+                        ),
+                ),
+        )
+        .child(section("Views", &insights.views))
+        .child(section("Commands", &insights.commands))
+        .child(section("Settings changed", &insights.settings))
+    }
+
+    /// Ways to get music in, shown while the queue is empty, with the folders opened lately.
+    fn render_start(&self, cx: &mut Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let row = theme.components.row;
+        let folders = cx.global::<PlayerContext>().recent_folders.read(cx).clone();
+        let card = |id: &'static str, title: &'static str, text: &'static str| {
+            div()
+                .id(id)
+                .w(px(180.0))
+                .flex_shrink_0()
+                .flex()
+                .flex_col()
+                .gap_1()
+                .p_3()
+                .rounded_md()
+                .bg(row.bg)
+                .hover(|this| this.bg(row.hover_bg))
+                .child(
+                    div()
+                        .text_color(row.text)
+                        .font_weight(FontWeight::MEDIUM)
+                        .child(title),
+                )
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(row.subtext)
+                        .whitespace_normal()
+                        .child(text),
+                )
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .items_center()
+            .gap_4()
+            .px_4()
+            .child(
+                div()
+                    .text_xl()
+                    .text_color(theme.text)
+                    .font_weight(FontWeight::BOLD)
+                    .child("Nothing playing yet"),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_wrap()
+                    .justify_center()
+                    .gap_2()
+                    .child(
+                        card(
+                            "start_open_folder",
+                            "Open folder",
+                            "Play a folder of music as a playlist.",
+                        )
+                        .on_click(|_, _, cx| cx.global::<Controller>().open_folder()),
+                    )
+                    .child(
+                        card(
+                            "start_add_root",
+                            "Add library folder",
+                            "Keep a folder in your library and watch it for new music.",
+                        )
+                        .on_click(|_, _, cx| cx.global::<Controller>().add_library_root()),
+                    )
+                    .child(
+                        card(
+                            "start_import",
+                            "Import playlist",
+                            "Read an M3U, PLS or XSPF playlist.",
+                        )
+                        .on_click(|_, _, cx| cx.global::<Controller>().import_playlist()),
+                    ),
+            )
+            .when(!folders.is_empty(), |this| {
+                this.child(
+                    div()
+                        .w(px(556.0))
+                        .max_w_full()
+                        .flex()
+                        .flex_col()
+                        .gap_1()
+                        .child(
+                            div()
+                                .text_color(theme.text)
+                                .font_weight(FontWeight::BOLD)
+                                .child("Recently used folders"),
+                        )
+                        .children(folders.into_iter().enumerate().map(|(index, folder)| {
+                            let name = folder
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_else(|| folder.to_string_lossy().to_string());
+                            let path = folder.to_string_lossy().to_string();
+                            div()
+                                .id(("recent_folder", index))
+                                .flex()
+                                .flex_col()
+                                .px_2()
+                                .py_1()
+                                .rounded_md()
+                                .hover(|this| this.bg(row.hover_bg))
+                                .child(div().text_color(row.text).truncate().child(name))
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(row.subtext)
+                                        .truncate()
+                                        .child(path),
+                                )
+                                .on_click(move |_, _, cx| {
+                                    cx.global::<Controller>().open_recent_folder(folder.clone());
+                                })
+                        })),
+                )
+            })
+    }
+
+    /// "Continue listening" cards for recently left collections other than the loaded one.
+    fn render_resume_cards(&self, cx: &mut Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let row = theme.components.row;
+        let context = cx.global::<PlayerContext>();
+        let current = context.metadata.read(cx).playlist_name.clone();
+        let cards: Vec<_> = context
+            .resume_cards
+            .read(cx)
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|(_, card)| card.name.as_str() != current.as_ref())
+            .collect();
+        if cards.is_empty() {
+            return div();
+        }
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .flex_shrink_0()
+            .gap_2()
+            .px_4()
+            .py_4()
+            .child(
+                div()
+                    .text_color(theme.text)
+                    .font_weight(FontWeight::BOLD)
+                    .child("Continue listening"),
+            )
+            .child(
+                div()
+                    .id("resume_cards")
+                    .flex()
+                    .gap_2()
+                    .overflow_x_scroll()
+                    .children(cards.into_iter().map(|(index, card)| {
+                        let progress = if card.duration > 0 {
+                            (card.position as f32 / card.duration as f32).min(1.0)
+                        } else {
+                            0.0
+                        };
+                        div()
+                            .id(("resume_card", index))
+                            .w(px(200.0))
+                            .flex_shrink_0()
+                            .flex()
+                            .flex_col()
+                            .gap_1()
+                            .p_2()
+                            .rounded_md()
+                            .bg(row.bg)
+                            .hover(|this| this.bg(row.hover_bg))
+                            .child(
+                                div()
+                                    .text_color(row.text)
+                                    .font_weight(FontWeight::MEDIUM)
+                                    .truncate()
+                                    .child(card.name.clone()),
+                            )
+                            .child(div().text_sm().text_color(row.subtext).truncate().child(
+                                format!(
+                                    "{}/{} • {}",
+                                    card.track_number, card.track_count, card.title
+                                ),
+                            ))
+                            .child(
+                                div()
+                                    .w_full()
+                                    .h(px(3.0))
+                                    .rounded_full()
+                                    .bg(theme.components.slider.track)
+                                    .child(
+                                        div()
+                                            .h_full()
+                                            .w(relative(progress))
+                                            .rounded_full()
+                                            .bg(theme.components.slider.fill),
+                                    ),
+                            )
+                            .on_click(move |_, _, cx| {
+                                cx.global::<Controller>().resume_recent(index);
+                            })
+                    })),
+            )
+    }
+
+    pub fn new(
+        cx: &mut Context<Self>,
+        layout: Entity<Layout>,
+        playlists: Entity<SavedPlaylists>,
+    ) -> Self {
+        let search_focus = cx.focus_handle();
+        let search_input = TextInput::new(
+            cx,
+            search_focus.clone(),
+            None,
+            Some("Search your library...".into()),
+        );
+        cx.subscribe(&search_input, |this: &mut Self, _, text: &String, cx| {
+            this.search_selected = 0;
+            this.search_query = text.clone();
+            cx.global::<Controller>().search(text.clone());
+            cx.notify();
+        })
+        .detach();
+
+        let handle = cx.focus_handle();
+        let year_from = TextInput::new(cx, handle, None, Some("From".into()));
+        let handle = cx.focus_handle();
+        let year_to = TextInput::new(cx, handle, None, Some("To".into()));
+        cx.subscribe(&year_from, |this: &mut Self, _, text: &String, cx| {
+            this.year_text.0 = text.clone();
+            this.typed_years(cx);
+        })
+        .detach();
+        cx.subscribe(&year_to, |this: &mut Self, _, text: &String, cx| {
+            this.year_text.1 = text.clone();
+            this.typed_years(cx);
+        })
+        .detach();
+
+        MainView {
+            layout,
+            show_details: false,
+            shown: BrowseMode::NowPlaying,
+            expanded: HashSet::new(),
+            search_input,
+            search_focus,
+            search_selected: 0,
+            track_menu: None,
+            search_scroll: UniformListScrollHandle::new(),
+            selection: cx.new(|_| TrackSelection::new(false, playlists)),
+            sort: None,
+            lyrics_scroll: ScrollHandle::new(),
+            lyrics_line: None,
+            lyrics_search: None,
+            lyrics_query: LyricsQuery::default(),
+            search_query: String::new(),
+            filter: LibraryFilter::default(),
+            year_inputs: (year_from, year_to),
+            year_text: (String::new(), String::new()),
+            #[cfg(feature = "video")]
+            video: None,
+            #[cfg(feature = "video")]
+            redraw_scheduled: false,
+        }
+    }
+
+    /// Move focus to the search box, so typing searches the library.
+    pub fn focus_search(&self, window: &mut Window) {
+        window.focus(&self.search_focus);
+    }
+}
+
+/// Start or stop counting insights, remembering the choice in the settings.
+fn set_insights(enabled: bool, cx: &mut App) {
+    let settings = cx.global_mut::<Settings>();
+    settings.insights = enabled;
+    save_settings(cx);
+    cx.global::<Controller>().set_insights(enabled);
 }
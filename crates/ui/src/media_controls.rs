@@ -0,0 +1,277 @@
+//! The system's media controls: the keyboard's media keys, and the OS's own now-playing
+//! display, which shows the current track with its artwork and transport buttons:
+//!
+//! - Linux: an MPRIS server (`org.mpris.MediaPlayer2.reyvr`) for desktop media widgets,
+//!   `playerctl` and headset buttons.
+//! - Windows: the System Media Transport Controls, tied to the app's window.
+//! - macOS: the Now Playing info center in Control Center and on the lock screen.
+//!
+//! The OS hands these to whichever player claimed them rather than to the focused window, so
+//! they keep working while the app is in the background. Everything is fed from the player's
+//! responses; see [`set_track`], [`set_state`], [`set_position`] and [`set_artwork`].
+
+use std::{
+    ffi::c_void,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::PathBuf,
+    time::Duration,
+};
+
+use backend::{
+    link::PlayLink,
+    playback::{Origin, Track},
+    player::{Controller, Thumbnail},
+    settings::Settings,
+    uri,
+};
+use gpui::*;
+use gstreamer::State;
+use souvlaki::{
+    MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig,
+    SeekDirection,
+};
+
+use crate::{
+    keybinds::{ChangeState, change_state, set_volume},
+    now_playing::PlayerContext,
+    toast,
+};
+
+/// How far the seek buttons of a media widget move, in seconds, like the arrow keys.
+const SEEK_STEP: u64 = 5;
+
+/// Keeps the media controls claimed for as long as the app runs, with what they were last told
+/// about the current track.
+pub struct SystemControls {
+    controls: MediaControls,
+    state: State,
+    /// Seconds into the current track, as last reported.
+    position: u64,
+    uri: String,
+    title: String,
+    artist: String,
+    album: String,
+    duration: u64,
+    /// The current track's artwork, written out for widgets that show it.
+    artwork: Option<PathBuf>,
+}
+
+impl Global for SystemControls {}
+
+impl SystemControls {
+    fn send_playback(&mut self) {
+        let progress = Some(MediaPosition(Duration::from_secs(self.position)));
+        let playback = match self.state {
+            State::Playing => MediaPlayback::Playing { progress },
+            State::Paused => MediaPlayback::Paused { progress },
+            _ => MediaPlayback::Stopped,
+        };
+        if let Err(e) = self.controls.set_playback(playback) {
+            eprintln!("Could not update media controls: {e:?}");
+        }
+    }
+
+    fn send_metadata(&mut self) {
+        let cover_url = self.artwork.as_deref().map(uri::from_path);
+        let metadata = MediaMetadata {
+            title: Some(&self.title),
+            artist: Some(&self.artist),
+            album: Some(&self.album),
+            cover_url: cover_url.as_deref(),
+            duration: (self.duration > 0).then(|| Duration::from_secs(self.duration)),
+        };
+        if let Err(e) = self.controls.set_metadata(metadata) {
+            eprintln!("Could not update media controls: {e:?}");
+        }
+    }
+}
+
+/// Claim the media controls and route them to the player. If the platform won't hand them
+/// over, this only logs why, since the window's own key bindings still work.
+pub fn register(window: &Window, cx: &mut App) {
+    let hwnd = hwnd(window);
+    if cfg!(target_os = "windows") && hwnd.is_none() {
+        eprintln!("Could not claim media keys: the window has no Win32 handle");
+        return;
+    }
+    let config = PlatformConfig {
+        dbus_name: "reyvr",
+        display_name: "Reyvr",
+        hwnd,
+    };
+    let mut controls = match MediaControls::new(config) {
+        Ok(controls) => controls,
+        Err(e) => {
+            eprintln!("Could not claim media keys: {e:?}");
+            return;
+        }
+    };
+    // Events arrive on a thread of the platform's, so hand them over to the app's.
+    let (tx, rx) = smol::channel::unbounded();
+    if let Err(e) = controls.attach(move |event| {
+        let _ = tx.try_send(event);
+    }) {
+        eprintln!("Could not listen for media keys: {e:?}");
+        return;
+    }
+    cx.set_global(SystemControls {
+        controls,
+        state: State::Null,
+        position: 0,
+        uri: String::new(),
+        title: String::new(),
+        artist: String::new(),
+        album: String::new(),
+        duration: 0,
+        artwork: None,
+    });
+    cx.spawn(|cx: AsyncApp| async move {
+        while let Ok(event) = rx.recv().await {
+            if cx.update(|cx| handle(event, cx)).is_err() {
+                break;
+            }
+        }
+    })
+    .detach();
+}
+
+fn handle(event: MediaControlEvent, cx: &mut App) {
+    let position = cx.global::<PlayerContext>().state.read(cx).position;
+    let duration = cx.global::<PlayerContext>().metadata.read(cx).duration;
+    let seek = |by: Duration, direction: SeekDirection| match direction {
+        SeekDirection::Forward => (position + by.as_secs()).min(duration),
+        SeekDirection::Backward => position.saturating_sub(by.as_secs()),
+    };
+    match event {
+        // A single play/pause key only says to toggle, so it goes by what's playing now.
+        MediaControlEvent::Toggle => change_state(&ChangeState, cx),
+        MediaControlEvent::Play => cx.global::<Controller>().play(),
+        MediaControlEvent::Pause | MediaControlEvent::Stop => cx.global::<Controller>().pause(),
+        MediaControlEvent::Next => cx.global::<Controller>().next(),
+        MediaControlEvent::Previous => cx.global::<Controller>().prev(),
+        MediaControlEvent::Seek(direction) => {
+            let target = seek(Duration::from_secs(SEEK_STEP), direction);
+            cx.global::<Controller>().seek(target);
+        }
+        MediaControlEvent::SeekBy(direction, by) => {
+            cx.global::<Controller>().seek(seek(by, direction));
+        }
+        MediaControlEvent::SetPosition(MediaPosition(at)) => {
+            cx.global::<Controller>().seek(at.as_secs().min(duration));
+        }
+        MediaControlEvent::SetVolume(volume) => set_volume(volume, cx),
+        MediaControlEvent::OpenUri(link) => open_uri(&link, cx),
+        MediaControlEvent::Raise => cx.activate(true),
+        MediaControlEvent::Quit => cx.quit(),
+    }
+}
+
+/// Play a `reyvr://` link or a `file:` URI handed over by another program.
+fn open_uri(link: &str, cx: &mut App) {
+    let controller = cx.global::<Controller>();
+    if let Ok(link) = PlayLink::parse(link) {
+        controller.open_link(link, Origin::Remote);
+    } else if let Some(path) = uri::to_path(link) {
+        controller.load_files(vec![path], Origin::Remote);
+    } else {
+        toast::show(format!("Can't open {link}"), cx);
+    }
+}
+
+/// Tell the media controls whether anything is playing. Some platforms only send the media
+/// keys to a player that says it is.
+pub fn set_state(state: State, cx: &mut App) {
+    if !cx.has_global::<SystemControls>() {
+        return;
+    }
+    let system = cx.global_mut::<SystemControls>();
+    system.state = state;
+    system.send_playback();
+}
+
+/// Keep the media controls' position in step. Widgets work out the position themselves while
+/// playing, so it's only sent again when it jumps, as after a seek.
+pub fn set_position(position: u64, cx: &mut App) {
+    if !cx.has_global::<SystemControls>() {
+        return;
+    }
+    let system = cx.global_mut::<SystemControls>();
+    let jumped = position < system.position || position > system.position + 2;
+    system.position = position;
+    if jumped {
+        system.send_playback();
+    }
+}
+
+/// Show `track` as the one playing, keeping its artwork if it was already showing.
+pub fn set_track(track: &Track, cx: &mut App) {
+    if !cx.has_global::<SystemControls>() {
+        return;
+    }
+    let settings = cx.global::<Settings>();
+    let title = settings.display(&track.title);
+    let artist = settings.display(&track.artists.join(", "));
+    let album = settings.display(&track.album);
+    let system = cx.global_mut::<SystemControls>();
+    if system.uri != track.uri {
+        if let Some(artwork) = system.artwork.take() {
+            let _ = std::fs::remove_file(artwork);
+        }
+        system.uri = track.uri.clone();
+        system.position = 0;
+    }
+    system.title = title;
+    system.artist = artist;
+    system.album = album;
+    system.duration = track.duration;
+    system.send_metadata();
+}
+
+/// Write out the current track's artwork, which is `uri`'s, and point the media controls at
+/// it. Each track's gets its own file, since widgets keep showing a file they've already read.
+pub fn set_artwork(uri: String, thumbnail: Thumbnail, cx: &mut App) {
+    if !cx.has_global::<SystemControls>() {
+        return;
+    }
+    let mut hasher = DefaultHasher::new();
+    uri.hash(&mut hasher);
+    let path = std::env::temp_dir().join(format!("reyvr-artwork-{:x}.png", hasher.finish()));
+    cx.spawn(|cx: AsyncApp| async move {
+        let written = path.clone();
+        let saved = cx
+            .background_executor()
+            .spawn(async move { thumbnail.save_png(&written) })
+            .await;
+        if let Err(e) = saved {
+            eprintln!("Could not write artwork for media controls: {e}");
+            return;
+        }
+        let _ = cx.update(|cx| {
+            let system = cx.global_mut::<SystemControls>();
+            // The track changed while writing.
+            if system.uri != uri {
+                let _ = std::fs::remove_file(&path);
+                return;
+            }
+            system.artwork = Some(path);
+            system.send_metadata();
+        });
+    })
+    .detach();
+}
+
+/// The window the media keys are tied to on Windows, which has no system-wide player service.
+#[cfg(target_os = "windows")]
+fn hwnd(window: &Window) -> Option<*mut c_void> {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    match window.window_handle().ok()?.as_raw() {
+        RawWindowHandle::Win32(handle) => Some(handle.hwnd.get() as *mut c_void),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn hwnd(_: &Window) -> Option<*mut c_void> {
+    None
+}
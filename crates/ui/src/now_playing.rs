@@ -1,21 +1,119 @@
+use backend::{
+    chapters::Chapter,
+    insights::Insights,
+    loudness::Loudness,
+    lyrics::Lyrics,
+    playback::{self, Origin, ShuffleMode},
+    player::QueueState,
+    report::{ProblemReport, RecentLog},
+    roots::{LibraryRoot, RootStatus},
+    session::ResumeCard,
+    settings::Settings,
+    smart::SmartPlaylist,
+};
 use gpui::*;
 use gstreamer::State;
+use std::{collections::HashMap, path::PathBuf, time::Instant};
+
+use crate::visualizer::Visualizer;
 
 #[derive(Clone)]
 pub struct PlayerContext {
     pub metadata: Entity<Metadata>,
     pub state: Entity<PlayerState>,
     pub tracks: Entity<Vec<Track>>,
+    /// Library tracks matching the search in the main view.
+    pub search_results: Entity<Vec<Track>>,
+    /// Loudness of every track measured so far, by URI.
+    pub loudness: Entity<HashMap<String, Loudness>>,
+    /// Times each track has been played through, by URI.
+    pub play_counts: Entity<HashMap<String, u32>>,
+    /// The queue was built on the fly, like from search results, and isn't saved anywhere.
+    pub unsaved_queue: Entity<bool>,
+    pub most_skipped: Entity<Vec<SkippedTrack>>,
+    /// Usage counted so far, or `None` while insights are off.
+    pub insights: Entity<Option<Insights>>,
+    pub albums: Entity<Vec<Album>>,
+    pub artists: Entity<Vec<Artist>>,
+    /// Every genre and decade in the library, offered as filters in the browse views.
+    pub genres: Entity<Vec<String>>,
+    pub decades: Entity<Vec<u32>>,
+    /// Library tracks whose files are gone, offered for cleanup in the library settings.
+    pub missing_tracks: Entity<usize>,
+    pub resume_cards: Entity<Vec<ResumeCard>>,
+    /// Folders opened as playlists, most recent first, offered again while nothing is loaded.
+    pub recent_folders: Entity<Vec<PathBuf>>,
+    /// Which queue entry is loaded, for highlighting and scrolling to it.
+    pub queue_state: Entity<QueueState>,
+    /// Drawn from the spectrum feed, which is only on while it's shown.
+    pub visualizer: Entity<Visualizer>,
+    /// Recent errors and notices, attached to problem reports.
+    pub log: Entity<RecentLog>,
+    /// Files scanned and total, while a folder scan is running.
+    pub scan_progress: Entity<Option<(usize, usize)>>,
+    /// Library folders and how their scans stand.
+    pub library_roots: Entity<Vec<(LibraryRoot, RootStatus)>>,
+    /// Whether the library settings page is open.
+    pub library_settings: Entity<bool>,
+    /// Whether the scrobbling settings page is open.
+    pub scrobble_settings: Entity<bool>,
+    /// Signing in to Last.fm is waiting on access being allowed in the browser.
+    pub lastfm_authorizing: Entity<bool>,
+    /// Listens held back until their service can be reached.
+    pub pending_scrobbles: Entity<usize>,
+    /// Whether the settings page is open.
+    pub settings_view: Entity<bool>,
+    /// Audio outputs playback can be sent to, by name.
+    pub output_devices: Entity<Vec<String>>,
+    /// Chapter markers of the current track.
+    pub chapters: Entity<Vec<Chapter>>,
+    /// Whether the chapter editor is open.
+    pub editing_chapters: Entity<bool>,
+    /// Lyrics of the current track, if it has any.
+    pub lyrics: Entity<Option<Lyrics>>,
+    /// Whether the lyrics editor is open.
+    pub editing_lyrics: Entity<bool>,
+    /// Smart playlists and how many tracks each matches.
+    pub smart_playlists: Entity<Vec<(SmartPlaylist, usize)>>,
+    /// `Some` while the rule editor is open, holding the index of the smart playlist being
+    /// edited, or `None` for a new one.
+    pub editing_smart_playlist: Entity<Option<Option<usize>>>,
+    /// Index of the saved playlist open in the playlist editor.
+    pub editing_playlist: Entity<Option<usize>>,
+    /// Tracks of the playlist open in the playlist editor.
+    pub playlist_tracks: Entity<Vec<Track>>,
+    /// Rating just given with the number keys and when, while its confirmation is showing.
+    pub quick_rating: Entity<Option<(u32, Instant)>>,
+    /// Message showing as a toast and when it went up.
+    pub toast: Entity<Option<(String, Instant)>>,
+    /// The problem report being reviewed, if the composer is open.
+    pub report: Entity<Option<ProblemReport>>,
 }
 
 #[derive(Clone)]
 pub struct Metadata {
     pub playlist_name: SharedString,
+    pub uri: String,
     pub title: SharedString,
     pub album: SharedString,
     pub artists: Vec<SharedString>,
     pub duration: u64,
     pub thumbnail: Option<Thumbnail>,
+    /// The full artwork is still on its way, so the track's small copy from the queue, or the
+    /// accent block for a track without one, stands in for it meanwhile.
+    pub placeholder: bool,
+    pub stream_info: Option<StreamInfo>,
+    pub origin: Origin,
+}
+
+#[derive(Clone)]
+pub struct StreamInfo {
+    pub codec: SharedString,
+    pub bitrate: u32,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bit_depth: u32,
+    pub bit_perfect: bool,
 }
 
 #[derive(Clone)]
@@ -24,7 +122,12 @@ pub struct PlayerState {
     pub state: State,
     pub volume: f64,
     pub shuffle: bool,
+    /// How the loaded playlist is shuffled, by track or by album.
+    pub shuffle_mode: ShuffleMode,
     pub repeat: bool,
+    pub stalled: bool,
+    pub buffering: i32,
+    pub exclusive: bool,
 }
 
 #[derive(Clone)]
@@ -41,7 +144,41 @@ pub struct Track {
     pub album: String,
     pub uri: String,
     pub duration: u64,
+    /// When the file came into the library, in seconds since the Unix epoch, or 0 if unknown.
+    pub added: u64,
     pub thumbnail: Option<Thumbnail>,
+    pub corrupt: bool,
+    /// The file wasn't there when last checked.
+    pub missing: bool,
+    /// Streamed over the network rather than read from disk.
+    pub remote: bool,
+    /// Title and note given to the entry by the playlist it was read from, or empty.
+    pub custom_title: String,
+    pub note: String,
+}
+
+#[derive(Clone)]
+pub struct SkippedTrack {
+    pub track: Track,
+    pub skips: usize,
+    /// Median position skipped at, in seconds.
+    pub typical_position: u64,
+}
+
+#[derive(Clone)]
+pub struct Album {
+    /// Album name and artist as the library knows them, for asking the player to play it.
+    pub key: (String, String),
+    pub name: SharedString,
+    pub artist: SharedString,
+    pub track_count: usize,
+    pub thumbnail: Option<Thumbnail>,
+}
+
+#[derive(Clone)]
+pub struct Artist {
+    pub name: SharedString,
+    pub albums: Vec<Album>,
 }
 
 pub enum PlayerContextEvent {
@@ -64,11 +201,56 @@ impl Metadata {
     pub fn new() -> Self {
         Metadata {
             playlist_name: "".into(),
+            uri: String::new(),
             title: "".into(),
             album: "".into(),
             artists: vec!["".into()],
             duration: 0,
             thumbnail: None,
+            placeholder: false,
+            stream_info: None,
+            origin: Origin::Unknown,
+        }
+    }
+}
+
+/// Integrated loudness and true peak, like `-9.3 LUFS · +0.4 dBTP`.
+pub fn loudness_label(loudness: &Loudness) -> String {
+    format!(
+        "{:.1} LUFS · {:+.1} dBTP",
+        loudness.integrated, loudness.true_peak
+    )
+}
+
+impl Track {
+    /// The UI's copy of a backend track, with names as `settings` displays them.
+    pub fn new(track: &playback::Track, settings: &Settings) -> Self {
+        Track {
+            album: settings.display(&track.album),
+            artists: track
+                .artists
+                .iter()
+                .map(|artist| settings.display(artist))
+                .collect(),
+            duration: track.duration,
+            added: track.added,
+            // Low-memory mode keeps queue artwork out of memory altogether.
+            thumbnail: track
+                .thumbnail
+                .as_ref()
+                .filter(|_| !settings.low_memory)
+                .map(|thumbnail| Thumbnail {
+                    img: ImageSource::Render(RenderImage::new(thumbnail.to_frame()).into()),
+                    width: thumbnail.width,
+                    height: thumbnail.height,
+                }),
+            title: settings.display(&track.title),
+            uri: track.uri.clone(),
+            corrupt: track.corrupt,
+            missing: track.missing,
+            remote: track.is_remote(),
+            custom_title: track.custom_title.clone(),
+            note: track.note.clone(),
         }
     }
 }
@@ -80,7 +262,11 @@ impl PlayerState {
             state: State::Null,
             volume: 0.2,
             shuffle: false,
+            shuffle_mode: ShuffleMode::default(),
             repeat: false,
+            stalled: false,
+            buffering: 100,
+            exclusive: false,
         }
     }
     pub fn vol(&mut self, cx: &mut Context<Self>, vol: f64) {
@@ -95,6 +281,41 @@ impl PlayerContext {
             metadata: cx.new(|_| Metadata::new()),
             state: cx.new(|_| PlayerState::new()),
             tracks: cx.new(|_| vec![]),
+            search_results: cx.new(|_| vec![]),
+            loudness: cx.new(|_| HashMap::new()),
+            play_counts: cx.new(|_| HashMap::new()),
+            unsaved_queue: cx.new(|_| false),
+            most_skipped: cx.new(|_| vec![]),
+            insights: cx.new(|_| None),
+            albums: cx.new(|_| vec![]),
+            artists: cx.new(|_| vec![]),
+            genres: cx.new(|_| vec![]),
+            decades: cx.new(|_| vec![]),
+            missing_tracks: cx.new(|_| 0),
+            resume_cards: cx.new(|_| vec![]),
+            recent_folders: cx.new(|_| vec![]),
+            queue_state: cx.new(|_| QueueState::default()),
+            visualizer: cx.new(|_| Visualizer::new()),
+            log: cx.new(|_| RecentLog::default()),
+            scan_progress: cx.new(|_| None),
+            library_roots: cx.new(|_| vec![]),
+            library_settings: cx.new(|_| false),
+            scrobble_settings: cx.new(|_| false),
+            lastfm_authorizing: cx.new(|_| false),
+            pending_scrobbles: cx.new(|_| 0),
+            settings_view: cx.new(|_| false),
+            output_devices: cx.new(|_| vec![]),
+            chapters: cx.new(|_| vec![]),
+            editing_chapters: cx.new(|_| false),
+            lyrics: cx.new(|_| None),
+            editing_lyrics: cx.new(|_| false),
+            smart_playlists: cx.new(|_| vec![]),
+            editing_smart_playlist: cx.new(|_| None),
+            editing_playlist: cx.new(|_| None),
+            playlist_tracks: cx.new(|_| vec![]),
+            quick_rating: cx.new(|_| None),
+            toast: cx.new(|_| None),
+            report: cx.new(|_| None),
         }
     }
 
@@ -0,0 +1,428 @@
+use backend::{playback::SavedPlaylists, player::Controller};
+use components::{input::TextInput, theme::Theme};
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::{
+    chapter_editor::action,
+    now_playing::{PlayerContext, Track},
+    queue_list::queue_menu,
+};
+
+/// Editor for a saved playlist: its name, and its tracks with buttons to move each one up or
+/// down, give it a title and note of its own, or take it out. Every change is sent to the
+/// player right away.
+pub struct PlaylistEditor {
+    playlists: Entity<SavedPlaylists>,
+    /// What the editor was last opened for, so reopening it starts from the saved name.
+    editing: Option<usize>,
+    name_input: Option<Entity<TextInput>>,
+    name: String,
+    /// Delete was clicked once, so the next click deletes the playlist.
+    confirm_delete: bool,
+    /// Track whose menu is open.
+    track_menu: Option<usize>,
+    /// Track whose title and note are being edited, with their inputs.
+    labeling: Option<(usize, Entity<TextInput>, Entity<TextInput>)>,
+    label_title: String,
+    label_note: String,
+}
+
+impl Render for PlaylistEditor {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let context = cx.global::<PlayerContext>().clone();
+        let editing = *context.editing_playlist.read(cx);
+        if editing != self.editing {
+            self.open(editing, cx);
+        }
+        let (Some(index), Some(name_input)) = (editing, self.name_input.clone()) else {
+            return deferred(div().track_focus(&cx.focus_handle())).with_priority(2);
+        };
+        let tracks = context.playlist_tracks.read(cx).clone();
+        let count = tracks.len();
+        let confirm_delete = self.confirm_delete;
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        deferred(
+            div()
+                .track_focus(&cx.focus_handle())
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .child(
+                    div()
+                        .w(px(560.0))
+                        .max_w_full()
+                        .max_h(relative(0.8))
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_color(theme.text)
+                        .child(
+                            div()
+                                .text_xl()
+                                .font_weight(FontWeight::BOLD)
+                                .child("Edit playlist"),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(
+                                    div()
+                                        .flex_grow()
+                                        .h_8()
+                                        .px_2()
+                                        .py_1()
+                                        .rounded_md()
+                                        .border_1()
+                                        .border_color(theme.secondary)
+                                        .child(name_input),
+                                )
+                                .child(action("playlist_rename", "Rename", button).on_click(
+                                    cx.listener(move |this, _, _, cx| {
+                                        cx.global::<Controller>()
+                                            .rename_playlist(index, this.name.clone());
+                                    }),
+                                )),
+                        )
+                        .child(
+                            div()
+                                .id("playlist_tracks")
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .overflow_y_scroll()
+                                .when(tracks.is_empty(), |this| {
+                                    this.child(
+                                        div()
+                                            .text_sm()
+                                            .text_color(theme.tokens.text.muted)
+                                            .child("No tracks in this playlist."),
+                                    )
+                                })
+                                .children(tracks.into_iter().enumerate().map(|(row, track)| {
+                                    self.render_track(index, row, count, track, cx)
+                                })),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .justify_end()
+                                .gap_2()
+                                .child(
+                                    action(
+                                        "playlist_delete",
+                                        if confirm_delete {
+                                            "Really delete?"
+                                        } else {
+                                            "Delete playlist"
+                                        },
+                                        button,
+                                    )
+                                    .on_click(cx.listener(
+                                        move |this, _, _, cx| {
+                                            if this.confirm_delete {
+                                                cx.global::<Controller>().delete_playlist(index);
+                                                close(cx);
+                                            } else {
+                                                this.confirm_delete = true;
+                                                cx.notify();
+                                            }
+                                        },
+                                    )),
+                                )
+                                .child(action("playlist_close", "Close", button).on_click(
+                                    |_, _, cx| {
+                                        close(cx);
+                                    },
+                                )),
+                        ),
+                ),
+        )
+        .with_priority(2)
+    }
+}
+
+fn close(cx: &mut App) {
+    let editing = cx.global::<PlayerContext>().editing_playlist.clone();
+    editing.update(cx, |this, cx| {
+        *this = None;
+        cx.notify();
+    });
+}
+
+impl PlaylistEditor {
+    pub fn new(playlists: Entity<SavedPlaylists>) -> Self {
+        PlaylistEditor {
+            playlists,
+            editing: None,
+            name_input: None,
+            name: String::new(),
+            confirm_delete: false,
+            track_menu: None,
+            labeling: None,
+            label_title: String::new(),
+            label_note: String::new(),
+        }
+    }
+
+    /// Load the name of the playlist being edited and ask the player for its tracks.
+    fn open(&mut self, editing: Option<usize>, cx: &mut Context<Self>) {
+        self.editing = editing;
+        self.name_input = None;
+        self.confirm_delete = false;
+        self.track_menu = None;
+        self.labeling = None;
+        let Some(index) = editing else {
+            return;
+        };
+        let name = self
+            .playlists
+            .read(cx)
+            .playlists
+            .get(index)
+            .map(|saved| saved.name.clone())
+            .unwrap_or_default();
+        let tracks = cx.global::<PlayerContext>().playlist_tracks.clone();
+        tracks.update(cx, |this, _| this.clear());
+        cx.global::<Controller>().get_playlist_tracks(index);
+
+        let handle = cx.focus_handle();
+        let input = TextInput::new(
+            cx,
+            handle,
+            Some(name.clone().into()),
+            Some("Playlist name".into()),
+        );
+        cx.subscribe(&input, |this: &mut Self, _, text: &String, _| {
+            this.name = text.clone();
+        })
+        .detach();
+        self.name = name;
+        self.name_input = Some(input);
+    }
+
+    /// Start editing the title and note of the track at `row`, from what it has now.
+    fn start_labeling(&mut self, row: usize, track: &Track, cx: &mut Context<Self>) {
+        let handle = cx.focus_handle();
+        let title = TextInput::new(
+            cx,
+            handle,
+            Some(track.custom_title.clone().into()),
+            Some(SharedString::from(track.title.clone())),
+        );
+        cx.subscribe(&title, |this: &mut Self, _, text: &String, _| {
+            this.label_title = text.clone();
+        })
+        .detach();
+        let handle = cx.focus_handle();
+        let note = TextInput::new(
+            cx,
+            handle,
+            Some(track.note.clone().into()),
+            Some("Note".into()),
+        );
+        cx.subscribe(&note, |this: &mut Self, _, text: &String, _| {
+            this.label_note = text.clone();
+        })
+        .detach();
+        self.label_title = track.custom_title.clone();
+        self.label_note = track.note.clone();
+        self.labeling = Some((row, title, note));
+        cx.notify();
+    }
+
+    /// Inputs for the title and note of the track at `row` within this playlist, under it.
+    fn render_label_editor(
+        &self,
+        index: usize,
+        row: usize,
+        title: Entity<TextInput>,
+        note: Entity<TextInput>,
+        cx: &Context<Self>,
+    ) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let field = |input: Entity<TextInput>| {
+            div()
+                .h_8()
+                .px_2()
+                .py_1()
+                .rounded_md()
+                .border_1()
+                .border_color(theme.secondary)
+                .child(input)
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .p_1()
+            .rounded_lg()
+            .border_1()
+            .border_color(theme.secondary)
+            .child(field(title))
+            .child(field(note))
+            .child(
+                div()
+                    .flex()
+                    .justify_end()
+                    .gap_1()
+                    .child(
+                        action(("playlist_label_save", row), "Save", button).on_click(cx.listener(
+                            move |this, _, _, cx| {
+                                cx.global::<Controller>().label_playlist_entry(
+                                    index,
+                                    row,
+                                    this.label_title.clone(),
+                                    this.label_note.clone(),
+                                );
+                                this.labeling = None;
+                                cx.notify();
+                            },
+                        )),
+                    )
+                    .child(
+                        action(("playlist_label_cancel", row), "Cancel", button).on_click(
+                            cx.listener(|this, _, _, cx| {
+                                this.labeling = None;
+                                cx.notify();
+                            }),
+                        ),
+                    ),
+            )
+    }
+
+    /// One track with its buttons, under the title the playlist gives it and with its note.
+    /// The first can't move up and the last can't move down. Right-click it to queue it.
+    fn render_track(
+        &self,
+        index: usize,
+        row: usize,
+        count: usize,
+        track: Track,
+        cx: &Context<Self>,
+    ) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let style = theme.components.row;
+        let uri = track.uri.clone();
+        let title = if track.custom_title.is_empty() {
+            track.title.clone()
+        } else {
+            track.custom_title.clone()
+        };
+        let label_track = track.clone();
+
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .hover(|this| this.bg(style.hover_bg))
+            .when(track.missing, |this| this.opacity(0.5))
+            .child(
+                div()
+                    .flex_grow()
+                    .min_w_0()
+                    .flex()
+                    .flex_col()
+                    .child(div().truncate().text_color(style.text).child(title))
+                    .child(
+                        div()
+                            .truncate()
+                            .text_sm()
+                            .text_color(style.subtext)
+                            .child(track.artists.join(", ")),
+                    )
+                    .when(!track.note.is_empty(), |this| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .italic()
+                                .text_color(theme.tokens.text.muted)
+                                .child(track.note.clone()),
+                        )
+                    }),
+            )
+            .when(row > 0, |this| {
+                this.child(action(("playlist_track_up", row), "↑", button).on_click(
+                    move |_, _, cx| {
+                        cx.global::<Controller>()
+                            .move_playlist_track(index, row, row - 1);
+                    },
+                ))
+            })
+            .when(row + 1 < count, |this| {
+                this.child(action(("playlist_track_down", row), "↓", button).on_click(
+                    move |_, _, cx| {
+                        cx.global::<Controller>()
+                            .move_playlist_track(index, row, row + 1);
+                    },
+                ))
+            })
+            .child(
+                action(("playlist_track_label", row), "✎", button).on_click(cx.listener(
+                    move |this, _, _, cx| {
+                        this.start_labeling(row, &label_track, cx);
+                    },
+                )),
+            )
+            .child(
+                action(("playlist_track_remove", row), "✕", button).on_click(move |_, _, cx| {
+                    cx.global::<Controller>().remove_playlist_track(index, row);
+                }),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(move |this, _, _, cx| {
+                    this.track_menu = Some(row);
+                    cx.notify();
+                }),
+            )
+            .map(|this| match self.labeling.clone() {
+                Some((labeling, title, note)) if labeling == row => div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(this)
+                    .child(self.render_label_editor(index, row, title, note, cx)),
+                _ => this,
+            })
+            .map(|this| {
+                if self.track_menu != Some(row) {
+                    return this;
+                }
+                let view = cx.entity().downgrade();
+                let close = move |cx: &mut App| {
+                    let _ = view.update(cx, |this, cx| {
+                        this.track_menu = None;
+                        cx.notify();
+                    });
+                };
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(this)
+                    .child(queue_menu(row, uri, close, cx))
+            })
+    }
+}
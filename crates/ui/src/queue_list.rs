@@ -1,5 +1,11 @@
-use backend::player::Controller;
-use components::{input::TextInput, theme::Theme};
+use backend::{
+    export::ExportPaths, memory, playback::SavedPlaylists, player::Controller, settings::Settings,
+};
+use components::{
+    icon::{Icon, Icons},
+    input::TextInput,
+    theme::Theme,
+};
 use gpui::{prelude::FluentBuilder, *};
 use nucleo::pattern::{CaseMatching, Normalization};
 use nucleo::{Config, Nucleo};
@@ -7,16 +13,105 @@ use std::collections::HashSet;
 use std::sync::Arc;
 
 use crate::{
-    layout::{Layout, LayoutMode},
-    now_playing::{PlayerContext, Track},
+    chapter_editor::action,
+    keybinds::set_low_memory,
+    layout::{Layout, MIN_RIGHT_WIDTH},
+    now_playing::{PlayerContext, Track, loudness_label},
+    selection::TrackSelection,
+    settings_view::save_settings,
+    sort::{TrackSort, sort_bar},
 };
 
+/// Menu opened by right-clicking a track in a list: play it after the current track, or add it
+/// to the end of the queue. `close` is called once a choice is made.
+pub(crate) fn queue_menu(
+    index: usize,
+    uri: String,
+    close: impl Fn(&mut App) + Clone + 'static,
+    cx: &App,
+) -> Div {
+    let theme = cx.global::<Theme>();
+    let item = |id: &'static str, label: &'static str| {
+        div()
+            .id((id, index))
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .text_sm()
+            .text_color(theme.text)
+            .hover(|this| this.bg(theme.secondary).text_color(theme.accent))
+            .child(label)
+    };
+    let next_uri = uri.clone();
+    let (next_close, add_close) = (close.clone(), close.clone());
+
+    div()
+        .w_full()
+        .flex()
+        .flex_col()
+        .p_1()
+        .rounded_lg()
+        .border_1()
+        .border_color(theme.secondary)
+        .bg(theme.tokens.surface.raised)
+        .child(
+            item("queue_menu_next", "Play next").on_click(move |_, _, cx| {
+                cx.global::<Controller>().play_next(vec![next_uri.clone()]);
+                next_close(cx);
+            }),
+        )
+        .child(
+            item("queue_menu_add", "Add to queue").on_click(move |_, _, cx| {
+                cx.global::<Controller>().enqueue(vec![uri.clone()]);
+                add_close(cx);
+            }),
+        )
+        .child(item("queue_menu_cancel", "Cancel").on_click(move |_, _, cx| close(cx)))
+}
+
+/// A queue row being dragged to a new place, shown under the pointer by its title.
+#[derive(Clone)]
+struct DraggedTrack {
+    index: usize,
+    title: SharedString,
+}
+
+impl Render for DraggedTrack {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .bg(theme.tokens.surface.overlay)
+            .border_1()
+            .border_color(theme.accent)
+            .text_sm()
+            .text_color(theme.text)
+            .child(self.title.clone())
+    }
+}
+
 pub struct QueueList {
     pub layout: Entity<Layout>,
     pub nucleo: Nucleo<(usize, String)>,
     pub query: Entity<String>,
     pub tracks: Vec<Track>,
     text_input: Entity<TextInput>,
+    /// Whether the choices for exporting the queue are showing.
+    export_menu: bool,
+    /// Whether the sort choices are showing.
+    sort_menu: bool,
+    /// Order the queue is shown in, while it differs from the order it plays in.
+    sort: Option<TrackSort>,
+    /// Name input for saving the queue as a playlist, while that prompt is showing.
+    save_input: Option<Entity<TextInput>>,
+    save_name: String,
+    scroll: UniformListScrollHandle,
+    /// Queue entry last scrolled into view, so the list only follows playback when it moves.
+    followed: Option<usize>,
+    selection: Entity<TrackSelection>,
     focus_handle: FocusHandle,
 }
 
@@ -29,9 +124,51 @@ impl Focusable for QueueList {
 impl Render for QueueList {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let tracks = cx.global::<PlayerContext>().tracks.clone();
-        let tracks = self.search(tracks.read(cx).clone(), self.query.read(cx).clone());
+        let unsaved = *cx.global::<PlayerContext>().unsaved_queue.read(cx);
+        let loudness = cx
+            .global::<Settings>()
+            .show_loudness
+            .then(|| cx.global::<PlayerContext>().loudness.read(cx).clone());
+        // Rows only match queue positions while the search doesn't filter any out and the
+        // queue is shown in the order it plays in.
+        let reorderable = self.query.read(cx).trim().is_empty() && self.sort.is_none();
+        let queued: Arc<Vec<String>> =
+            Arc::new(tracks.read(cx).iter().map(|t| t.uri.clone()).collect());
+        self.selection
+            .update(cx, |selection, _| selection.retain(&queued));
+        let mut tracks = self.search(tracks.read(cx).clone(), self.query.read(cx).clone());
+        if let Some(sort) = self.sort {
+            sort.apply(
+                &mut tracks,
+                cx.global::<PlayerContext>().play_counts.read(cx),
+            );
+        }
+        let rows: Arc<Vec<String>> = Arc::new(tracks.iter().map(|t| t.uri.clone()).collect());
+        let selection = self.selection.clone();
+        let queue_state = *cx.global::<PlayerContext>().queue_state.read(cx);
+        let current_row = if reorderable {
+            queue_state.current
+        } else {
+            let uri = &cx.global::<PlayerContext>().metadata.read(cx).uri;
+            queue_state
+                .current
+                .and(tracks.iter().position(|track| &track.uri == uri))
+        };
+        // Keep the current track in view as playback moves through the queue.
+        if queue_state.current != self.followed {
+            self.followed = queue_state.current;
+            if let Some(row) = current_row {
+                self.scroll.scroll_to_item(row, ScrollStrategy::Top);
+            }
+        }
 
+        let autofill = cx.global::<Settings>().autofill;
+        let compact = cx.global::<Settings>().low_memory;
+        let suggest_low_memory = !compact
+            && !cx.global::<Settings>().low_memory_dismissed
+            && memory::is_low_memory_system();
         let theme = cx.global::<Theme>();
+        let button = theme.components.button;
         let layout = self.layout.clone().read(cx);
 
         if layout.right_sidebar.show {
@@ -43,9 +180,9 @@ impl Render for QueueList {
                     .w(px(layout.right_sidebar.width))
                     .flex()
                     .flex_col()
-                    .min_w(px(280.0))
-                    .when(layout.mode == LayoutMode::Overlay, |this| {
-                        this.absolute().border_0()
+                    .min_w(px(MIN_RIGHT_WIDTH))
+                    .when(layout.right_sidebar.drawer, |this| {
+                        this.absolute().top_0().right_0()
                     })
                     .border_l_1()
                     .border_color(theme.secondary)
@@ -56,10 +193,76 @@ impl Render for QueueList {
                             .h_10()
                             .py_1()
                             .px_1()
+                            .flex()
+                            .items_center()
+                            .gap_1()
                             .border_b_1()
                             .border_color(theme.secondary)
-                            .child(self.text_input.clone()),
+                            .child(div().flex_grow().child(self.text_input.clone()))
+                            .child(action("queue_locate", "⌖", button).on_click(cx.listener(
+                                move |this, _, _, cx| {
+                                    if let Some(row) = current_row {
+                                        this.scroll.scroll_to_item(row, ScrollStrategy::Top);
+                                        cx.notify();
+                                    }
+                                },
+                            )))
+                            .child(
+                                // Lit up while similar tracks are added as the queue runs out.
+                                action("queue_autofill", "Autofill", button)
+                                    .when(autofill, |this| {
+                                        this.bg(button.hover_bg).text_color(button.hover_text)
+                                    })
+                                    .on_click(cx.listener(|_, _, _, cx| {
+                                        let settings = cx.global_mut::<Settings>();
+                                        settings.autofill = !settings.autofill;
+                                        let autofill = settings.autofill;
+                                        save_settings(cx);
+                                        cx.global::<Controller>().set_autofill(autofill);
+                                        cx.notify();
+                                    })),
+                            )
+                            .child(action("queue_clear", "Clear", button).on_click(|_, _, cx| {
+                                cx.global::<Controller>().clear_queue();
+                            }))
+                            .child(
+                                action("queue_save_as", "Save…", button).on_click(cx.listener(
+                                    |this, _, _, cx| {
+                                        this.toggle_save_prompt(cx);
+                                    },
+                                )),
+                            )
+                            .child(
+                                action("queue_export", "Export…", button).on_click(cx.listener(
+                                    |this, _, _, cx| {
+                                        this.export_menu = !this.export_menu;
+                                        cx.notify();
+                                    },
+                                )),
+                            )
+                            .child(
+                                action("queue_sort", "Sort…", button)
+                                    .when(self.sort.is_some(), |this| {
+                                        this.bg(button.hover_bg).text_color(button.hover_text)
+                                    })
+                                    .on_click(cx.listener(|this, _, _, cx| {
+                                        this.sort_menu = !this.sort_menu;
+                                        cx.notify();
+                                    })),
+                            ),
                     )
+                    .child(self.selection.clone())
+                    .when_some(self.save_input.clone(), |this, input| {
+                        this.child(self.render_save_prompt(input, cx))
+                    })
+                    .when(self.export_menu, |this| {
+                        this.child(self.render_export_menu(cx))
+                    })
+                    .when(self.sort_menu, |this| this.child(self.render_sort_menu(cx)))
+                    .when(unsaved, |this| this.child(self.render_unsaved(cx)))
+                    .when(suggest_low_memory, |this| {
+                        this.child(self.render_low_memory_hint(cx))
+                    })
                     .child(
                         uniform_list(
                             cx.entity(),
@@ -67,16 +270,31 @@ impl Render for QueueList {
                             tracks.len(),
                             move |_, range, _, cx| {
                                 let theme = cx.global::<Theme>();
+                                let button = theme.components.button;
 
                                 range
                                     .map(|id| {
                                         let track = &tracks[id];
+                                        let measured = loudness
+                                            .as_ref()
+                                            .and_then(|loudness| loudness.get(&track.uri))
+                                            .copied();
+                                        let selected = selection.read(cx).contains(&track.uri);
+                                        let (selection, rows) = (selection.clone(), rows.clone());
+                                        let queued = queued.clone();
 
                                         div()
+                                            .id(("queue_track", id))
                                             .w_full()
-                                            .h_16()
+                                            .map(|this| {
+                                                // Compact rows go without artwork and artists.
+                                                if compact {
+                                                    this.h_8().mt_1()
+                                                } else {
+                                                    this.h_16().mt_2()
+                                                }
+                                            })
                                             .flex()
-                                            .mt_2()
                                             .gap_2()
                                             .text_color(theme.text)
                                             .items_center()
@@ -85,24 +303,69 @@ impl Render for QueueList {
                                             .rounded_lg()
                                             .overflow_hidden()
                                             .hover(|this| this.bg(theme.secondary))
-                                            .on_mouse_down(MouseButton::Left, move |_, _, cx| {
-                                                let controller = cx.global::<Controller>().clone();
-                                                controller.play_id(id);
+                                            .when(Some(id) == current_row, |this| {
+                                                this.bg(theme.components.row.selected_bg)
+                                                    .text_color(theme.accent)
                                             })
-                                            .child({
-                                                if let Some(thumbnail) = &track.thumbnail {
-                                                    img(thumbnail.img.clone())
-                                                        .min_h(px(56.0))
-                                                        .min_w(px(56.0))
-                                                        .rounded_md()
-                                                } else {
-                                                    img("")
+                                            .when(selected, |this| {
+                                                this.border_1().border_color(theme.accent)
+                                            })
+                                            // Ctrl- and Shift-click pick tracks rather than
+                                            // play them.
+                                            .on_click(move |_, window, cx| {
+                                                let modifiers = window.modifiers();
+                                                let picked = selection.update(cx, |this, cx| {
+                                                    this.click(id, &rows, modifiers, cx)
+                                                });
+                                                // Rows are searched or sorted out of queue
+                                                // order, so find the row's queue entry.
+                                                let index =
+                                                    queued.iter().position(|uri| *uri == rows[id]);
+                                                if let Some(index) = index.filter(|_| !picked) {
+                                                    cx.global::<Controller>().play_id(index);
                                                 }
                                             })
+                                            .when(reorderable, |this| {
+                                                let accent = theme.accent;
+                                                this.on_drag(
+                                                    DraggedTrack {
+                                                        index: id,
+                                                        title: track.title.clone().into(),
+                                                    },
+                                                    |drag, _, _, cx| cx.new(|_| drag.clone()),
+                                                )
+                                                // The line marks the side the track lands on.
+                                                .drag_over::<DraggedTrack>(
+                                                    move |style, drag, _, _| {
+                                                        if drag.index > id {
+                                                            style.border_t_2().border_color(accent)
+                                                        } else {
+                                                            style.border_b_2().border_color(accent)
+                                                        }
+                                                    },
+                                                )
+                                                .on_drop(move |drag: &DraggedTrack, _, cx| {
+                                                    cx.global::<Controller>()
+                                                        .move_track(drag.index, id);
+                                                })
+                                            })
+                                            .when(!compact, |this| {
+                                                this.child(
+                                                    if let Some(thumbnail) = &track.thumbnail {
+                                                        img(thumbnail.img.clone())
+                                                            .min_h(rems(3.5))
+                                                            .min_w(rems(3.5))
+                                                            .rounded_md()
+                                                    } else {
+                                                        img("")
+                                                    },
+                                                )
+                                            })
                                             .child(
                                                 div()
                                                     .w_full()
-                                                    .h(px(56.0))
+                                                    .when(compact, |this| this.justify_center())
+                                                    .when(!compact, |this| this.h(rems(3.5)))
                                                     .flex()
                                                     .flex_col()
                                                     .gap(px(1.0))
@@ -114,19 +377,85 @@ impl Render for QueueList {
                                                             .text_base()
                                                             .font_weight(FontWeight::MEDIUM),
                                                     )
-                                                    .child(
-                                                        div()
-                                                            .child(track.artists.join(", "))
-                                                            .truncate()
-                                                            .text_ellipsis()
-                                                            .text_sm()
-                                                            .font_weight(FontWeight::NORMAL),
-                                                    ),
+                                                    .when(!compact, |this| {
+                                                        this.child(
+                                                            div()
+                                                                .child(track.artists.join(", "))
+                                                                .truncate()
+                                                                .text_ellipsis()
+                                                                .text_sm()
+                                                                .font_weight(FontWeight::NORMAL),
+                                                        )
+                                                    }),
                                             )
+                                            .when(track.remote, |this| {
+                                                this.child(
+                                                    div()
+                                                        .flex_shrink_0()
+                                                        .px_1()
+                                                        .rounded_sm()
+                                                        .border_1()
+                                                        .border_color(theme.secondary)
+                                                        .text_xs()
+                                                        .child("Stream"),
+                                                )
+                                            })
+                                            .when_some(measured, |this, loudness| {
+                                                this.child(
+                                                    div()
+                                                        .flex_shrink_0()
+                                                        .px_1()
+                                                        .rounded_sm()
+                                                        .border_1()
+                                                        .border_color(
+                                                            if loudness.is_heavily_clipped() {
+                                                                theme.warning
+                                                            } else {
+                                                                theme.secondary
+                                                            },
+                                                        )
+                                                        .text_xs()
+                                                        .child(loudness_label(&loudness)),
+                                                )
+                                            })
+                                            .when(track.missing, |this| {
+                                                this.opacity(0.5).child(
+                                                    div()
+                                                        .flex_shrink_0()
+                                                        .px_1()
+                                                        .rounded_sm()
+                                                        .border_1()
+                                                        .border_color(theme.warning)
+                                                        .text_xs()
+                                                        .child("Missing"),
+                                                )
+                                            })
+                                            .when(track.corrupt, |this| {
+                                                this.child(
+                                                    div().flex_shrink_0().child(
+                                                        Icon::new(Icons::Warning)
+                                                            .size(18.0)
+                                                            .color(theme.warning),
+                                                    ),
+                                                )
+                                            })
+                                            .when(reorderable, |this| {
+                                                this.child(
+                                                    action(("queue_remove", id), "✕", button)
+                                                        .flex_shrink_0()
+                                                        .on_click(move |_, _, cx| {
+                                                            // Not a click on the row as well.
+                                                            cx.stop_propagation();
+                                                            cx.global::<Controller>()
+                                                                .remove_track(id);
+                                                        }),
+                                                )
+                                            })
                                     })
                                     .collect()
                             },
                         )
+                        .track_scroll(self.scroll.clone())
                         .h_full()
                         .px_1(),
                     ),
@@ -139,7 +468,11 @@ impl Render for QueueList {
 }
 
 impl QueueList {
-    pub fn new(cx: &mut Context<QueueList>, layout: Entity<Layout>) -> Self {
+    pub fn new(
+        cx: &mut Context<QueueList>,
+        layout: Entity<Layout>,
+        playlists: Entity<SavedPlaylists>,
+    ) -> Self {
         let query = cx.new(|_| String::new());
         let handle = cx.focus_handle();
 
@@ -162,17 +495,282 @@ impl QueueList {
             query,
             tracks: vec![],
             text_input,
+            export_menu: false,
+            sort_menu: false,
+            sort: None,
+            save_input: None,
+            save_name: String::new(),
+            scroll: UniformListScrollHandle::new(),
+            followed: None,
+            selection: cx.new(|_| TrackSelection::new(true, playlists)),
             focus_handle: cx.focus_handle(),
         }
     }
 
+    /// Take the selected tracks out of the queue, or the current track while none are selected.
+    pub fn remove_selected(&mut self, cx: &mut Context<Self>) {
+        if self
+            .selection
+            .update(cx, |selection, cx| selection.remove_from_queue(cx))
+        {
+            return;
+        }
+        if let Some(current) = cx.global::<PlayerContext>().queue_state.read(cx).current {
+            cx.global::<Controller>().remove_tracks(vec![current]);
+        }
+    }
+
+    /// Note that the queue isn't saved anywhere, with a button to save it as a playlist.
+    fn render_unsaved(&self, cx: &Context<Self>) -> Div {
+        let name = cx
+            .global::<PlayerContext>()
+            .metadata
+            .read(cx)
+            .playlist_name
+            .clone();
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        div()
+            .w_full()
+            .flex()
+            .items_center()
+            .justify_between()
+            .gap_2()
+            .p_1()
+            .border_b_1()
+            .border_color(theme.secondary)
+            .child(
+                div()
+                    .min_w_0()
+                    .truncate()
+                    .text_sm()
+                    .text_color(theme.tokens.text.muted)
+                    .child(format!("{name} · unsaved")),
+            )
+            .child(
+                action("queue_save", "Save as playlist", button).on_click(|_, _, cx| {
+                    cx.global::<Controller>().save_queue(None);
+                }),
+            )
+    }
+
+    /// Suggest low-memory mode on a system short on memory, until it is turned on or dismissed.
+    fn render_low_memory_hint(&self, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let gigabytes = memory::total_memory().unwrap_or(0) as f64 / (1024.0 * 1024.0 * 1024.0);
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .p_1()
+            .border_b_1()
+            .border_color(theme.secondary)
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(theme.tokens.text.muted)
+                    .child(format!(
+                        "This computer has {gigabytes:.1} GB of memory. Low-memory mode keeps \
+                         artwork out of the queue and buffers less."
+                    )),
+            )
+            .child(
+                div()
+                    .flex()
+                    .justify_end()
+                    .gap_1()
+                    .child(
+                        action("queue_low_memory_on", "Turn on", button).on_click(cx.listener(
+                            |_, _, _, cx| {
+                                set_low_memory(true, cx);
+                                cx.notify();
+                            },
+                        )),
+                    )
+                    .child(
+                        action("queue_low_memory_dismiss", "Not now", button).on_click(
+                            cx.listener(|_, _, _, cx| {
+                                let settings = cx.global_mut::<Settings>();
+                                settings.low_memory_dismissed = true;
+                                save_settings(cx);
+                                cx.notify();
+                            }),
+                        ),
+                    ),
+            )
+    }
+
+    /// Show the prompt for saving the queue as a playlist, named after the queue to begin
+    /// with, or hide it again.
+    fn toggle_save_prompt(&mut self, cx: &mut Context<Self>) {
+        if self.save_input.take().is_none() {
+            let name = cx
+                .global::<PlayerContext>()
+                .metadata
+                .read(cx)
+                .playlist_name
+                .to_string();
+            let handle = cx.focus_handle();
+            let input = TextInput::new(
+                cx,
+                handle,
+                Some(name.clone().into()),
+                Some("Playlist name".into()),
+            );
+            cx.subscribe(&input, |this: &mut Self, _, text: &String, _| {
+                this.save_name = text.clone();
+            })
+            .detach();
+            self.save_name = name;
+            self.save_input = Some(input);
+        }
+        cx.notify();
+    }
+
+    /// Name for the new playlist, with buttons to save or cancel, shown under the search bar.
+    fn render_save_prompt(&self, input: Entity<TextInput>, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        div()
+            .w_full()
+            .flex()
+            .items_center()
+            .gap_1()
+            .p_1()
+            .border_b_1()
+            .border_color(theme.secondary)
+            .child(
+                div()
+                    .flex_grow()
+                    .h_8()
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(theme.secondary)
+                    .child(input),
+            )
+            .child(
+                action("queue_save_confirm", "Save", button).on_click(cx.listener(
+                    |this, _, _, cx| {
+                        cx.global::<Controller>()
+                            .save_queue(Some(this.save_name.clone()));
+                        this.save_input = None;
+                        cx.notify();
+                    },
+                )),
+            )
+            .child(
+                action("queue_save_cancel", "Cancel", button).on_click(cx.listener(
+                    |this, _, _, cx| {
+                        this.save_input = None;
+                        cx.notify();
+                    },
+                )),
+            )
+    }
+
+    /// Choices for exporting the queue, shown under the search bar.
+    fn render_export_menu(&self, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        div()
+            .w_full()
+            .flex()
+            .flex_wrap()
+            .items_center()
+            .gap_1()
+            .p_1()
+            .border_b_1()
+            .border_color(theme.secondary)
+            .children(
+                ExportPaths::ALL
+                    .into_iter()
+                    .enumerate()
+                    .map(|(choice, paths)| {
+                        action(("queue_export_paths", choice), paths.label(), button).on_click(
+                            cx.listener(move |this, _, _, cx| {
+                                this.export_menu = false;
+                                cx.global::<Controller>().export_queue(paths);
+                                cx.notify();
+                            }),
+                        )
+                    }),
+            )
+            .child(
+                action("queue_export_cancel", "Cancel", button).on_click(cx.listener(
+                    |this, _, _, cx| {
+                        this.export_menu = false;
+                        cx.notify();
+                    },
+                )),
+            )
+    }
+
+    /// Sort choices for how the queue is shown, with a button to play it in that order too.
+    fn render_sort_menu(&self, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .p_1()
+            .border_b_1()
+            .border_color(theme.secondary)
+            .child(sort_bar(
+                "queue_sort_key",
+                self.sort,
+                |this: &mut Self, sort| this.sort = sort,
+                cx,
+            ))
+            .when_some(self.sort, |this, sort| {
+                this.child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .justify_between()
+                        .gap_1()
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(theme.tokens.text.muted)
+                                .child("Only the view is sorted; the queue plays in its order."),
+                        )
+                        .child(
+                            action("queue_sort_apply", "Apply sort to queue", button).on_click(
+                                cx.listener(move |this, _, _, cx| {
+                                    let context = cx.global::<PlayerContext>();
+                                    let mut tracks = context.tracks.read(cx).clone();
+                                    sort.apply(&mut tracks, context.play_counts.read(cx));
+                                    cx.global::<Controller>().reorder_queue(
+                                        tracks.into_iter().map(|track| track.uri).collect(),
+                                    );
+                                    this.sort = None;
+                                    this.sort_menu = false;
+                                    cx.notify();
+                                }),
+                            ),
+                        ),
+                )
+            })
+    }
+
     pub fn search(&mut self, tracks: Vec<Track>, query: String) -> Vec<Track> {
-        if self.tracks.len() != tracks.len()
-            && if self.tracks.len() > 0 && tracks.len() > 0 {
-                self.tracks[0].title != tracks[0].title
-            } else {
-                true
-            }
+        // Reordering keeps the length and often the first track, so compare the whole order.
+        if self
+            .tracks
+            .iter()
+            .map(|track| &track.uri)
+            .ne(tracks.iter().map(|track| &track.uri))
         {
             self.nucleo = Nucleo::new(Config::DEFAULT, Arc::new(|| {}), None, 1);
             let injector = self.nucleo.injector();
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use components::theme::Theme;
+use gpui::*;
+
+use crate::now_playing::PlayerContext;
+
+/// How long the confirmation stays up after rating with the number keys.
+pub const QUICK_RATING_DURATION: Duration = Duration::from_millis(1500);
+
+/// Brief confirmation near the top of the window after rating the current track with the
+/// number keys. It doesn't take clicks, so it never gets in the way.
+pub struct RatingOverlay;
+
+impl Render for RatingOverlay {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let context = cx.global::<PlayerContext>();
+        let Some((stars, _)) = *context.quick_rating.read(cx) else {
+            return deferred(div()).with_priority(3);
+        };
+        let title = context.metadata.read(cx).title.clone();
+        let theme = cx.global::<Theme>();
+        let label = if stars == 0 {
+            "Rating cleared".to_string()
+        } else {
+            format!(
+                "{}{}",
+                "★".repeat(stars as usize),
+                "☆".repeat(5 - stars as usize)
+            )
+        };
+
+        deferred(
+            div()
+                .absolute()
+                .top(px(48.0))
+                .left_0()
+                .w_full()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .items_center()
+                        .gap_1()
+                        .px_4()
+                        .py_2()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .child(div().text_xl().text_color(theme.accent).child(label))
+                        .child(
+                            div()
+                                .text_sm()
+                                .text_color(theme.text)
+                                .max_w(px(320.0))
+                                .truncate()
+                                .child(title),
+                        ),
+                ),
+        )
+        .with_priority(3)
+    }
+}
@@ -0,0 +1,181 @@
+use backend::report::ProblemReport;
+use components::theme::Theme;
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::now_playing::PlayerContext;
+
+/// "Report a problem" composer. Shows the report exactly as it will be sent and lets the user
+/// drop sections before opening a GitHub issue or saving it to a file.
+#[derive(Clone)]
+pub struct ReportView;
+
+impl Render for ReportView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let report_entity = cx.global::<PlayerContext>().report.clone();
+        let Some(report) = report_entity.read(cx).clone() else {
+            return deferred(div().track_focus(&cx.focus_handle())).with_priority(2);
+        };
+        let button = theme.components.button;
+
+        let toggle = |id: &'static str, label: &'static str, on: bool| {
+            div()
+                .id(id)
+                .flex()
+                .items_center()
+                .gap_2()
+                .text_sm()
+                .child(
+                    div()
+                        .size_3()
+                        .rounded_sm()
+                        .border_1()
+                        .border_color(theme.accent)
+                        .when(on, |this| this.bg(theme.accent)),
+                )
+                .child(label)
+        };
+        let action = |id: &'static str, label: &'static str| {
+            div()
+                .id(id)
+                .px_3()
+                .py_1()
+                .rounded_lg()
+                .text_sm()
+                .bg(button.bg)
+                .text_color(button.text)
+                .border_1()
+                .border_color(button.border)
+                .hover(|this| {
+                    this.bg(button.hover_bg)
+                        .text_color(button.hover_text)
+                        .border_color(button.hover_border)
+                })
+                .child(label)
+        };
+        let edit = |report_entity: &Entity<Option<ProblemReport>>, f: fn(&mut ProblemReport)| {
+            let report_entity = report_entity.clone();
+            move |_: &ClickEvent, _: &mut Window, cx: &mut App| {
+                report_entity.update(cx, |this, cx| {
+                    if let Some(report) = this {
+                        f(report);
+                    }
+                    cx.notify();
+                });
+            }
+        };
+
+        deferred(
+            div()
+                .track_focus(&cx.focus_handle())
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .child(
+                    div()
+                        .w(px(480.0))
+                        .max_w_full()
+                        .h(relative(0.8))
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_color(theme.text)
+                        .child(
+                            div()
+                                .text_xl()
+                                .font_weight(FontWeight::BOLD)
+                                .child("Report a problem"),
+                        )
+                        .child(div().text_sm().text_color(theme.tokens.text.muted).child(
+                            "This is everything that will be sent. Your home folder and user name \
+                             are masked; untick anything else you'd rather not share.",
+                        ))
+                        .child(
+                            div()
+                                .id("report_preview")
+                                .flex_grow()
+                                .overflow_y_scroll()
+                                .p_2()
+                                .rounded_md()
+                                .bg(theme.tokens.surface.base)
+                                .text_xs()
+                                .child(report.body()),
+                        )
+                        .child(
+                            toggle(
+                                "report_diagnostics",
+                                "Include diagnostics",
+                                report.include_diagnostics,
+                            )
+                            .on_click(edit(&report_entity, |report| {
+                                report.include_diagnostics = !report.include_diagnostics
+                            })),
+                        )
+                        .child(
+                            toggle("report_log", "Include recent log", report.include_log)
+                                .on_click(edit(&report_entity, |report| {
+                                    report.include_log = !report.include_log
+                                })),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .justify_end()
+                                .gap_2()
+                                .child(action("report_cancel", "Cancel").on_click({
+                                    let report_entity = report_entity.clone();
+                                    move |_, _, cx| {
+                                        report_entity.update(cx, |this, cx| {
+                                            *this = None;
+                                            cx.notify();
+                                        });
+                                    }
+                                }))
+                                .child(action("report_save", "Save report").on_click({
+                                    let report_entity = report_entity.clone();
+                                    let report = report.clone();
+                                    move |_, _, cx| match report.save() {
+                                        Ok(path) => {
+                                            cx.reveal_path(&path);
+                                            report_entity.update(cx, |this, cx| {
+                                                *this = None;
+                                                cx.notify();
+                                            });
+                                        }
+                                        Err(e) => eprintln!("Could not save report: {}", e),
+                                    }
+                                }))
+                                .child(action("report_open", "Open GitHub issue").on_click({
+                                    let report_entity = report_entity.clone();
+                                    move |_, _, cx| {
+                                        cx.open_url(&report.issue_url());
+                                        report_entity.update(cx, |this, cx| {
+                                            *this = None;
+                                            cx.notify();
+                                        });
+                                    }
+                                })),
+                        ),
+                ),
+        )
+        .with_priority(2)
+    }
+}
+
+impl ReportView {
+    pub fn new() -> Self {
+        ReportView
+    }
+}
@@ -0,0 +1,280 @@
+use backend::{player::Controller, scrobble::ScrobbleAccounts, settings::Settings};
+use components::{input::TextInput, theme::Theme};
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::{
+    chapter_editor::action, now_playing::PlayerContext, settings_view::save_settings, toast,
+};
+
+/// Scrobbling settings: the Last.fm API account to sign in with and the ListenBrainz token,
+/// with how many listens are still waiting to be sent.
+pub struct ScrobbleSettings {
+    /// Whether the inputs were filled in for the page being open, so reopening it starts from
+    /// the saved accounts.
+    open: bool,
+    inputs: Option<[Entity<TextInput>; 3]>,
+    api_key: String,
+    api_secret: String,
+    token: String,
+}
+
+impl Render for ScrobbleSettings {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let context = cx.global::<PlayerContext>().clone();
+        let open = *context.scrobble_settings.read(cx);
+        if open != self.open {
+            self.open(open, cx);
+        }
+        let Some([api_key_input, api_secret_input, token_input]) = self.inputs.clone() else {
+            return deferred(div().track_focus(&cx.focus_handle())).with_priority(2);
+        };
+        let authorizing = *context.lastfm_authorizing.read(cx);
+        let pending = *context.pending_scrobbles.read(cx);
+        let accounts = cx.global::<Settings>().scrobbling.clone();
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let muted = theme.tokens.text.muted;
+        let field = |input: Entity<TextInput>| {
+            div()
+                .h_8()
+                .px_2()
+                .py_1()
+                .rounded_md()
+                .border_1()
+                .border_color(theme.secondary)
+                .child(input)
+        };
+        let heading = |text: &'static str| div().font_weight(FontWeight::BOLD).child(text);
+
+        let lastfm_status = if authorizing {
+            "Allow access on the page that opened in your browser, then finish here.".to_string()
+        } else if accounts.has_lastfm() {
+            format!("Signed in as {}.", accounts.lastfm_user)
+        } else {
+            "Not signed in. Create an API account on last.fm to get a key and secret.".to_string()
+        };
+        let lastfm_button = if authorizing {
+            action("scrobble_lastfm_finish", "Finish", button).on_click(|_, _, cx| {
+                cx.global::<Controller>().finish_lastfm();
+            })
+        } else if accounts.has_lastfm() {
+            action("scrobble_lastfm_sign_out", "Sign out", button).on_click(|_, _, cx| {
+                update_accounts(cx, |accounts| {
+                    accounts.lastfm_user.clear();
+                    accounts.lastfm_session.clear();
+                });
+            })
+        } else {
+            action("scrobble_lastfm_connect", "Connect…", button).on_click(cx.listener(
+                |this, _, _, cx| {
+                    let (api_key, api_secret) = (this.api_key.clone(), this.api_secret.clone());
+                    update_accounts(cx, |accounts| {
+                        accounts.lastfm_api_key = api_key.trim().to_string();
+                        accounts.lastfm_api_secret = api_secret.trim().to_string();
+                    });
+                    if cx.global::<Settings>().scrobbling.has_lastfm_key() {
+                        cx.global::<Controller>().connect_lastfm();
+                    }
+                },
+            ))
+        };
+
+        deferred(
+            div()
+                .track_focus(&cx.focus_handle())
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .child(
+                    div()
+                        .w(px(520.0))
+                        .max_w_full()
+                        .max_h(relative(0.8))
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_color(theme.text)
+                        .child(
+                            div()
+                                .text_xl()
+                                .font_weight(FontWeight::BOLD)
+                                .child("Scrobbling"),
+                        )
+                        .child(div().text_sm().text_color(muted).child(
+                            "Tracks count once half of them, or four minutes, has been heard.",
+                        ))
+                        .child(heading("Last.fm"))
+                        .when(!accounts.has_lastfm(), |this| {
+                            this.child(field(api_key_input))
+                                .child(field(api_secret_input))
+                        })
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(
+                                    div()
+                                        .flex_grow()
+                                        .text_sm()
+                                        .text_color(muted)
+                                        .child(lastfm_status),
+                                )
+                                .child(lastfm_button),
+                        )
+                        .child(heading("ListenBrainz"))
+                        .child(field(token_input))
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(div().flex_grow().text_sm().text_color(muted).child(
+                                    if accounts.has_listenbrainz() {
+                                        "Sending listens with this token."
+                                    } else {
+                                        "Paste the user token from your ListenBrainz settings."
+                                    },
+                                ))
+                                .child(
+                                    action("scrobble_listenbrainz_save", "Save", button).on_click(
+                                        cx.listener(|this, _, _, cx| {
+                                            let token = this.token.trim().to_string();
+                                            update_accounts(cx, |accounts| {
+                                                accounts.listenbrainz_token = token.clone();
+                                            });
+                                            if !token.is_empty() {
+                                                cx.global::<Controller>().check_listenbrainz(token);
+                                            }
+                                        }),
+                                    ),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .justify_between()
+                                .gap_2()
+                                .child(div().text_sm().text_color(muted).child(match pending {
+                                    0 => String::new(),
+                                    1 => "1 listen waiting to be sent".to_string(),
+                                    _ => format!("{pending} listens waiting to be sent"),
+                                }))
+                                .child(action("scrobble_close", "Close", button).on_click({
+                                    let open = context.scrobble_settings.clone();
+                                    let authorizing = context.lastfm_authorizing.clone();
+                                    move |_, _, cx| {
+                                        authorizing.update(cx, |this, _| *this = false);
+                                        open.update(cx, |this, cx| {
+                                            *this = false;
+                                            cx.notify();
+                                        });
+                                    }
+                                })),
+                        ),
+                ),
+        )
+        .with_priority(2)
+    }
+}
+
+impl ScrobbleSettings {
+    pub fn new() -> Self {
+        ScrobbleSettings {
+            open: false,
+            inputs: None,
+            api_key: String::new(),
+            api_secret: String::new(),
+            token: String::new(),
+        }
+    }
+
+    /// Fill the inputs in from the saved accounts when the page opens, and drop them when it
+    /// closes.
+    fn open(&mut self, open: bool, cx: &mut Context<Self>) {
+        self.open = open;
+        self.inputs = None;
+        if !open {
+            return;
+        }
+        let accounts = cx.global::<Settings>().scrobbling.clone();
+        self.api_key = accounts.lastfm_api_key;
+        self.api_secret = accounts.lastfm_api_secret;
+        self.token = accounts.listenbrainz_token;
+
+        let api_key = self.input(self.api_key.clone(), "Last.fm API key", cx, |this, text| {
+            this.api_key = text;
+        });
+        let api_secret = self.input(
+            self.api_secret.clone(),
+            "Last.fm shared secret",
+            cx,
+            |this, text| {
+                this.api_secret = text;
+            },
+        );
+        let token = self.input(
+            self.token.clone(),
+            "ListenBrainz user token",
+            cx,
+            |this, text| {
+                this.token = text;
+            },
+        );
+        self.inputs = Some([api_key, api_secret, token]);
+    }
+
+    fn input(
+        &self,
+        content: String,
+        placeholder: &'static str,
+        cx: &mut Context<Self>,
+        on_change: fn(&mut Self, String),
+    ) -> Entity<TextInput> {
+        let handle = cx.focus_handle();
+        let input = TextInput::new(cx, handle, Some(content.into()), Some(placeholder.into()));
+        cx.subscribe(&input, move |this: &mut Self, _, text: &String, _| {
+            on_change(this, text.clone());
+        })
+        .detach();
+        input
+    }
+}
+
+/// Change the scrobble accounts, remembering them in the settings and sending listens to them
+/// from now on.
+fn update_accounts(cx: &mut App, change: impl FnOnce(&mut ScrobbleAccounts)) {
+    let settings = cx.global_mut::<Settings>();
+    change(&mut settings.scrobbling);
+    let accounts = settings.scrobbling.clone();
+    save_settings(cx);
+    cx.global::<Controller>().set_scrobble_accounts(accounts);
+    cx.refresh_windows();
+}
+
+/// Signed in to Last.fm as `user`, with `session` standing in for their password.
+pub fn lastfm_signed_in(user: String, session: String, cx: &mut App) {
+    let authorizing = cx.global::<PlayerContext>().lastfm_authorizing.clone();
+    authorizing.update(cx, |this, cx| {
+        *this = false;
+        cx.notify();
+    });
+    toast::show(format!("Signed in to Last.fm as {user}"), cx);
+    update_accounts(cx, |accounts| {
+        accounts.lastfm_user = user;
+        accounts.lastfm_session = session;
+    });
+}
@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+
+use backend::{playback::SavedPlaylists, player::Controller, tags::TagEdit};
+use components::{input::TextInput, theme::Theme};
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{chapter_editor::action, now_playing::PlayerContext};
+
+/// Tag fields offered for editing a selection, in order.
+const TAG_FIELDS: [&str; 4] = ["Artist", "Album", "Genre", "Year"];
+
+/// Prompt opened under the selection bar.
+enum Prompt {
+    /// Saved playlists to add the selection to.
+    Playlists,
+    /// An input for each of [`TAG_FIELDS`], with what it holds.
+    Tags([Entity<TextInput>; 4], [String; 4]),
+}
+
+/// Tracks picked out of a list with Ctrl- and Shift-click, and a bar of actions on all of them
+/// at once. Tracks are kept by URI, so the selection holds while the list around it changes.
+pub struct TrackSelection {
+    /// Selected tracks, in the order they were picked.
+    uris: Vec<String>,
+    /// Row Shift-click ranges start from, the last one clicked without Shift.
+    anchor: Option<usize>,
+    /// Whether tracks are picked from the queue, which can also take them out.
+    in_queue: bool,
+    playlists: Entity<SavedPlaylists>,
+    prompt: Option<Prompt>,
+}
+
+impl TrackSelection {
+    pub fn new(in_queue: bool, playlists: Entity<SavedPlaylists>) -> Self {
+        TrackSelection {
+            uris: vec![],
+            anchor: None,
+            in_queue,
+            playlists,
+            prompt: None,
+        }
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.uris.iter().any(|selected| selected == uri)
+    }
+
+    /// Handle a click on `row` of a list of tracks, given by URI. Ctrl-click adds or drops the
+    /// row and Shift-click selects every row from the anchor to it, and both return true. A
+    /// plain click clears the selection and returns false, so the row does what it usually does.
+    pub fn click(
+        &mut self,
+        row: usize,
+        rows: &[String],
+        modifiers: Modifiers,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let Some(uri) = rows.get(row) else {
+            return false;
+        };
+        let picked = match self.anchor {
+            Some(anchor) if modifiers.shift && anchor < rows.len() => {
+                let mut seen = HashSet::new();
+                self.uris = rows[anchor.min(row)..=anchor.max(row)]
+                    .iter()
+                    .filter(|uri| seen.insert(*uri))
+                    .cloned()
+                    .collect();
+                true
+            }
+            _ if modifiers.secondary() || modifiers.shift => {
+                match self.uris.iter().position(|selected| selected == uri) {
+                    Some(index) => {
+                        self.uris.remove(index);
+                    }
+                    None => self.uris.push(uri.clone()),
+                }
+                self.anchor = Some(row);
+                true
+            }
+            _ => {
+                self.uris.clear();
+                self.prompt = None;
+                self.anchor = Some(row);
+                false
+            }
+        };
+        cx.notify();
+        picked
+    }
+
+    /// Drop selected tracks that are no longer among `tracks`, by URI.
+    pub fn retain(&mut self, tracks: &[String]) {
+        if self.uris.is_empty() {
+            return;
+        }
+        let known: HashSet<&String> = tracks.iter().collect();
+        self.uris.retain(|uri| known.contains(uri));
+        if self.uris.is_empty() {
+            self.prompt = None;
+        }
+    }
+
+    /// Take the selected tracks out of the queue. Returns false if none are selected.
+    pub fn remove_from_queue(&mut self, cx: &mut Context<Self>) -> bool {
+        if self.uris.is_empty() {
+            return false;
+        }
+        let tracks = cx.global::<PlayerContext>().tracks.read(cx);
+        let indices = tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, track)| self.contains(&track.uri))
+            .map(|(index, _)| index)
+            .collect();
+        cx.global::<Controller>().remove_tracks(indices);
+        self.clear(cx);
+        true
+    }
+
+    fn clear(&mut self, cx: &mut Context<Self>) {
+        self.uris.clear();
+        self.prompt = None;
+        cx.notify();
+    }
+
+    fn toggle_tags(&mut self, cx: &mut Context<Self>) {
+        if matches!(self.prompt, Some(Prompt::Tags(..))) {
+            self.prompt = None;
+            cx.notify();
+            return;
+        }
+        let inputs = TAG_FIELDS.map(|field| {
+            let handle = cx.focus_handle();
+            TextInput::new(cx, handle, None, Some(field.into()))
+        });
+        for (field, input) in inputs.iter().enumerate() {
+            cx.subscribe(input, move |this: &mut Self, _, text: &String, _| {
+                if let Some(Prompt::Tags(_, texts)) = &mut this.prompt {
+                    texts[field] = text.clone();
+                }
+            })
+            .detach();
+        }
+        self.prompt = Some(Prompt::Tags(inputs, Default::default()));
+        cx.notify();
+    }
+
+    /// The typed tags as an edit, with blank fields left alone.
+    fn typed_edit(texts: &[String; 4]) -> TagEdit {
+        let text = |field: usize| {
+            let text = texts[field].trim();
+            (!text.is_empty()).then(|| text.to_string())
+        };
+        TagEdit {
+            artist: text(0),
+            album: text(1),
+            genre: text(2),
+            year: text(3).and_then(|year| year.parse().ok()),
+        }
+    }
+
+    /// Saved playlists to add the selection to, as buttons.
+    fn render_playlists(&self, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let playlists = self.playlists.read(cx).playlists.clone();
+
+        div()
+            .flex()
+            .flex_wrap()
+            .gap_1()
+            .when(playlists.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_sm()
+                        .text_color(theme.tokens.text.muted)
+                        .child("No saved playlists yet."),
+                )
+            })
+            .children(playlists.into_iter().enumerate().map(|(index, playlist)| {
+                action(("selection_playlist", index), playlist.name, button).on_click(cx.listener(
+                    move |this, _, _, cx| {
+                        cx.global::<Controller>()
+                            .add_to_playlist(index, this.uris.clone());
+                        this.clear(cx);
+                    },
+                ))
+            }))
+    }
+
+    fn render_tags(&self, inputs: &[Entity<TextInput>; 4], cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .children(inputs.iter().map(|input| {
+                div()
+                    .h_8()
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(theme.secondary)
+                    .child(input.clone())
+            }))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .gap_1()
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(theme.tokens.text.muted)
+                            .child("Blank fields stay as they are."),
+                    )
+                    .child(
+                        action("selection_tags_apply", "Apply", button).on_click(cx.listener(
+                            |this, _, _, cx| {
+                                let Some(Prompt::Tags(_, texts)) = &this.prompt else {
+                                    return;
+                                };
+                                let edit = Self::typed_edit(texts);
+                                cx.global::<Controller>().edit_tags(this.uris.clone(), edit);
+                                this.clear(cx);
+                            },
+                        )),
+                    ),
+            )
+    }
+}
+
+impl Render for TrackSelection {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if self.uris.is_empty() {
+            return div();
+        }
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let count = self.uris.len();
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .px_2()
+            .py_1()
+            .border_b_1()
+            .border_color(theme.secondary)
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_1()
+                    .child(
+                        div()
+                            .flex_grow()
+                            .text_sm()
+                            .text_color(theme.text)
+                            .child(format!("{count} selected")),
+                    )
+                    .child(
+                        action("selection_next", "Play next", button).on_click(cx.listener(
+                            |this, _, _, cx| {
+                                cx.global::<Controller>().play_next(this.uris.clone());
+                                this.clear(cx);
+                            },
+                        )),
+                    )
+                    .when(self.in_queue, |this| {
+                        this.child(action("selection_remove", "Remove", button).on_click(
+                            cx.listener(|this, _, _, cx| {
+                                this.remove_from_queue(cx);
+                            }),
+                        ))
+                    })
+                    .child(
+                        action("selection_add", "Add to…", button).on_click(cx.listener(
+                            |this, _, _, cx| {
+                                this.prompt = match this.prompt {
+                                    Some(Prompt::Playlists) => None,
+                                    _ => Some(Prompt::Playlists),
+                                };
+                                cx.notify();
+                            },
+                        )),
+                    )
+                    .child(
+                        action("selection_tags", "Tags…", button)
+                            .on_click(cx.listener(|this, _, _, cx| this.toggle_tags(cx))),
+                    )
+                    .child(
+                        action("selection_clear", "✕", button)
+                            .on_click(cx.listener(|this, _, _, cx| this.clear(cx))),
+                    ),
+            )
+            .map(|this| match &self.prompt {
+                Some(Prompt::Playlists) => this.child(self.render_playlists(cx)),
+                Some(Prompt::Tags(inputs, _)) => this.child(self.render_tags(inputs, cx)),
+                None => this,
+            })
+    }
+}
@@ -0,0 +1,615 @@
+use std::path::PathBuf;
+
+use backend::{
+    player::Controller,
+    settings::{Appearance, Density, MAX_BOOSTED_VOLUME, Settings},
+    stay_awake::StayAwake,
+};
+use components::{input::TextInput, theme::Theme};
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::{
+    chapter_editor::action, fonts, keybinds, now_playing::PlayerContext, themes, toast,
+    visualizer::STYLES,
+};
+
+/// Behavior settings shown as switches, by what they do.
+const TOGGLES: [(&str, fn(&mut Settings) -> &mut bool); 17] = [
+    ("Skip silence", |settings| &mut settings.skip_silence),
+    ("Mono output", |settings| &mut settings.mono),
+    ("Exclusive output, bypassing effects", |settings| {
+        &mut settings.exclusive_mode
+    }),
+    ("Start quietly and fade up", |settings| {
+        &mut settings.loud_start_protection
+    }),
+    ("Pause while other apps play", |settings| {
+        &mut settings.pause_on_other_audio
+    }),
+    ("Carry on once they stop", |settings| {
+        &mut settings.resume_after_other_audio
+    }),
+    (
+        "Pause when headphones disconnect or the system sleeps",
+        |settings| &mut settings.pause_on_disconnect,
+    ),
+    ("Keep playing similar tracks after the queue", |settings| {
+        &mut settings.autofill
+    }),
+    ("Tint accents to match the cover art", |settings| {
+        &mut settings.art_accent
+    }),
+    ("Show names in Latin script", |settings| {
+        &mut settings.romanize
+    }),
+    ("Show loudness on tracks", |settings| {
+        &mut settings.show_loudness
+    }),
+    ("Look up missing lyrics online", |settings| {
+        &mut settings.online_lyrics
+    }),
+    ("Check files for damage while scanning", |settings| {
+        &mut settings.verify_on_scan
+    }),
+    ("Skip hidden folders while scanning", |settings| {
+        &mut settings.skip_hidden_folders
+    }),
+    ("Low-memory mode", |settings| &mut settings.low_memory),
+    ("Close to the tray", |settings| &mut settings.close_to_tray),
+    ("Count usage insights on this computer", |settings| {
+        &mut settings.insights
+    }),
+];
+
+const APPEARANCES: [(Appearance, &str); 3] = [
+    (Appearance::System, "Follow the system"),
+    (Appearance::Dark, "Dark"),
+    (Appearance::Light, "Light"),
+];
+
+const FONT_SIZES: [f32; 7] = [12.0, 14.0, 15.0, 16.0, 18.0, 20.0, 24.0];
+
+const UI_SCALES: [f32; 7] = [0.9, 1.0, 1.1, 1.2, 1.3, 1.4, 1.5];
+
+const DENSITIES: [(Density, &str); 2] = [
+    (Density::Comfortable, "Comfortable"),
+    (Density::Compact, "Compact"),
+];
+
+const VISUALIZER_FPS: [u32; 3] = [15, 20, 30];
+
+const SMOOTHING: [(f32, &str); 3] = [(0.0, "Off"), (0.6, "Smooth"), (0.85, "Smoothest")];
+
+const STAY_AWAKE: [(StayAwake, &str); 3] = [
+    (StayAwake::Off, "Let the computer sleep"),
+    (StayAwake::System, "Keep the computer awake while playing"),
+    (StayAwake::Screen, "Keep the screen on too"),
+];
+
+/// Settings page: the theme, how playback starts and where it goes, links to the library and
+/// scrobbling pages, and behavior switches. Changes are applied and saved to the settings file
+/// right away.
+pub struct SettingsView {
+    /// Whether the inputs were filled in for the page being open, so reopening it starts from
+    /// the saved settings.
+    open: bool,
+    theme_input: Option<Entity<TextInput>>,
+    theme: String,
+    volume_input: Option<Entity<TextInput>>,
+    volume: String,
+    font_input: Option<Entity<TextInput>>,
+    font: String,
+}
+
+impl Render for SettingsView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let context = cx.global::<PlayerContext>().clone();
+        let open = *context.settings_view.read(cx);
+        if open != self.open {
+            self.open(open, cx);
+        }
+        let (Some(theme_input), Some(volume_input), Some(font_input)) = (
+            self.theme_input.clone(),
+            self.volume_input.clone(),
+            self.font_input.clone(),
+        ) else {
+            return deferred(div().track_focus(&cx.focus_handle())).with_priority(2);
+        };
+        let devices = context.output_devices.read(cx).clone();
+        let settings = cx.global::<Settings>().clone();
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let muted = theme.tokens.text.muted;
+        let field = |input: Entity<TextInput>| {
+            div()
+                .flex_grow()
+                .h_8()
+                .px_2()
+                .py_1()
+                .rounded_md()
+                .border_1()
+                .border_color(theme.secondary)
+                .child(input)
+        };
+        let heading = |text: &'static str| div().pt_2().font_weight(FontWeight::BOLD).child(text);
+        let mut choices: Vec<Option<PathBuf>> = vec![None];
+        choices.extend(
+            themes::installed()
+                .into_iter()
+                .filter_map(|path| path.file_name().map(PathBuf::from).map(Some)),
+        );
+        // A theme file kept somewhere else.
+        if !choices.contains(&settings.theme) {
+            choices.push(settings.theme.clone());
+        }
+        let themes = choices.into_iter().enumerate().map(|(index, choice)| {
+            let chosen = settings.theme == choice;
+            let label = match &choice {
+                Some(path) if path.is_relative() => path
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                Some(path) => path.display().to_string(),
+                None => "Built-in".to_string(),
+            };
+            self.render_choice(("theme", index), label, chosen, cx)
+                .on_click(move |_, _, cx| use_theme(choice.clone(), cx))
+        });
+        let devices = [None]
+            .into_iter()
+            .chain(devices.into_iter().map(Some))
+            .enumerate()
+            .map(|(index, device)| {
+                let chosen = settings.output_device == device;
+                let label = device.clone().unwrap_or("System default".to_string());
+                self.render_choice(("output_device", index), label, chosen, cx)
+                    .on_click(move |_, _, cx| {
+                        let device = device.clone();
+                        change_settings(cx, |settings| settings.output_device = device);
+                    })
+            });
+        let appearances =
+            APPEARANCES
+                .into_iter()
+                .enumerate()
+                .map(|(index, (appearance, label))| {
+                    let chosen = settings.appearance == appearance;
+                    self.render_choice(("appearance", index), label, chosen, cx)
+                        .on_click(move |_, _, cx| {
+                            change_settings(cx, |settings| settings.appearance = appearance);
+                        })
+                });
+        let scales = UI_SCALES.into_iter().enumerate().map(|(index, scale)| {
+            let chosen = (settings.ui_scale * 10.0).round() == (scale * 10.0).round();
+            let label = format!("{:.0}%", scale * 100.0);
+            self.render_choice(("ui_scale", index), label, chosen, cx)
+                .on_click(move |_, _, cx| {
+                    change_settings(cx, |settings| settings.ui_scale = scale);
+                })
+        });
+        let font_sizes = FONT_SIZES.into_iter().enumerate().map(|(index, size)| {
+            let chosen = settings.font_size == size;
+            self.render_choice(("font_size", index), format!("{size:.0}"), chosen, cx)
+                .on_click(move |_, _, cx| {
+                    change_settings(cx, |settings| settings.font_size = size);
+                })
+        });
+        let densities = DENSITIES
+            .into_iter()
+            .enumerate()
+            .map(|(index, (density, label))| {
+                let chosen = settings.density == density;
+                self.render_choice(("density", index), label, chosen, cx)
+                    .on_click(move |_, _, cx| {
+                        change_settings(cx, |settings| settings.density = density);
+                    })
+            });
+        let visualizers = STYLES
+            .into_iter()
+            .enumerate()
+            .map(|(index, (style, label))| {
+                let chosen = settings.visualizer == style;
+                self.render_choice(("visualizer", index), label, chosen, cx)
+                    .on_click(move |_, _, cx| {
+                        change_settings(cx, |settings| settings.visualizer = style);
+                    })
+            });
+        let visualizer_fps = VISUALIZER_FPS.into_iter().enumerate().map(|(index, fps)| {
+            let chosen = settings.visualizer_fps == fps;
+            self.render_choice(("visualizer_fps", index), format!("{fps} FPS"), chosen, cx)
+                .on_click(move |_, _, cx| {
+                    change_settings(cx, |settings| settings.visualizer_fps = fps);
+                })
+        });
+        let smoothing = SMOOTHING
+            .into_iter()
+            .enumerate()
+            .map(|(index, (smoothing, label))| {
+                let chosen = settings.visualizer_smoothing == smoothing;
+                self.render_choice(("smoothing", index), label, chosen, cx)
+                    .on_click(move |_, _, cx| {
+                        change_settings(cx, |settings| settings.visualizer_smoothing = smoothing);
+                    })
+            });
+        let stay_awake = STAY_AWAKE
+            .into_iter()
+            .enumerate()
+            .map(|(index, (mode, label))| {
+                let chosen = settings.stay_awake == mode;
+                self.render_choice(("stay_awake", index), label, chosen, cx)
+                    .on_click(move |_, _, cx| {
+                        change_settings(cx, |settings| settings.stay_awake = mode);
+                    })
+            });
+        let toggles = TOGGLES
+            .into_iter()
+            .enumerate()
+            .map(|(index, (label, toggle))| {
+                let on = *toggle(&mut settings.clone());
+                self.render_choice(("toggle", index), label, on, cx)
+                    .child(div().text_sm().child(if on { "On" } else { "Off" }))
+                    .on_click(move |_, _, cx| {
+                        change_settings(cx, |settings| *toggle(settings) = !*toggle(settings));
+                    })
+            });
+        let file = Settings::get_settings_file()
+            .map(|file| format!("Saved to {}", file.display()))
+            .unwrap_or_default();
+
+        deferred(
+            div()
+                .track_focus(&cx.focus_handle())
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .child(
+                    div()
+                        .w(px(600.0))
+                        .max_w_full()
+                        .max_h(relative(0.8))
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_color(theme.text)
+                        .child(
+                            div()
+                                .text_xl()
+                                .font_weight(FontWeight::BOLD)
+                                .child("Settings"),
+                        )
+                        .child(
+                            div()
+                                .id("settings")
+                                .flex()
+                                .flex_col()
+                                .gap_2()
+                                .overflow_y_scroll()
+                                .child(heading("Theme"))
+                                .children(themes)
+                                .child(
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap_2()
+                                        .child(field(theme_input))
+                                        .child(action("settings_theme", "Apply", button).on_click(
+                                            cx.listener(|this, _, _, cx| {
+                                                this.apply_theme(cx);
+                                            }),
+                                        )),
+                                )
+                                .child(div().text_sm().text_color(muted).child(
+                                    "Themes are TOML or JSON files in the themes folder beside \
+                                     the settings file, or anywhere else by path. Saving the \
+                                     one in use shows the change right away; sliders pick it \
+                                     up after a restart.",
+                                ))
+                                .child(heading("Appearance"))
+                                .children(appearances)
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(muted)
+                                        .child("Whether the built-in theme is dark or light."),
+                                )
+                                .child(heading("Font"))
+                                .child(
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap_2()
+                                        .child(field(font_input))
+                                        .child(action("settings_font", "Apply", button).on_click(
+                                            cx.listener(|this, _, _, cx| {
+                                                this.apply_font(cx);
+                                            }),
+                                        ))
+                                        .child(
+                                            action("settings_font_default", "Default", button)
+                                                .on_click(|_, _, cx| {
+                                                    change_settings(cx, |settings| {
+                                                        settings.font_family = None
+                                                    });
+                                                }),
+                                        ),
+                                )
+                                .child(div().flex().flex_wrap().gap_1().children(font_sizes))
+                                .child(div().text_sm().text_color(muted).child(
+                                    "Any installed family, or one from a font file in the fonts \
+                                     folder beside the settings file, read at startup.",
+                                ))
+                                .child(heading("Size"))
+                                .child(div().flex().flex_wrap().gap_1().children(scales))
+                                .children(densities)
+                                .child(div().text_sm().text_color(muted).child(
+                                    "Also changed with Ctrl and + or -, and Ctrl+0 to reset.",
+                                ))
+                                .child(heading("Starting volume"))
+                                .child(
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap_2()
+                                        .child(field(volume_input))
+                                        .child(
+                                            action("settings_volume", "Apply", button).on_click(
+                                                cx.listener(|this, _, _, cx| {
+                                                    this.apply_volume(cx);
+                                                }),
+                                            ),
+                                        ),
+                                )
+                                .child(div().text_sm().text_color(muted).child(
+                                    "A percentage, or empty to start where the last session \
+                                     ended.",
+                                ))
+                                .child(heading("Visualizer"))
+                                .child(div().flex().flex_wrap().gap_1().children(visualizers))
+                                .child(div().flex().flex_wrap().gap_1().children(visualizer_fps))
+                                .child(div().flex().flex_wrap().gap_1().children(smoothing))
+                                .child(div().text_sm().text_color(muted).child(
+                                    "Lower frame rates go easier on the battery. Smoothing lets \
+                                     levels fall back slowly instead of jumping.",
+                                ))
+                                .child(heading("Output device"))
+                                .children(devices)
+                                .child(heading("Sleep"))
+                                .children(stay_awake)
+                                .child(heading("Library"))
+                                .child(
+                                    div()
+                                        .flex()
+                                        .gap_2()
+                                        .child(
+                                            action("settings_folders", "Library folders…", button)
+                                                .on_click({
+                                                    let page = context.library_settings.clone();
+                                                    move |_, _, cx| {
+                                                        cx.global::<Controller>()
+                                                            .get_library_roots();
+                                                        switch_to(&page, cx);
+                                                    }
+                                                }),
+                                        )
+                                        .child(
+                                            action("settings_scrobbling", "Scrobbling…", button)
+                                                .on_click({
+                                                    let page = context.scrobble_settings.clone();
+                                                    move |_, _, cx| switch_to(&page, cx)
+                                                }),
+                                        ),
+                                )
+                                .child(heading("Behavior"))
+                                .children(toggles),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .justify_between()
+                                .gap_2()
+                                .child(div().text_sm().text_color(muted).truncate().child(file))
+                                .child(action("settings_close", "Close", button).on_click(
+                                    |_, _, cx| {
+                                        close(cx);
+                                    },
+                                )),
+                        ),
+                ),
+        )
+        .with_priority(2)
+    }
+}
+
+impl SettingsView {
+    pub fn new() -> Self {
+        SettingsView {
+            open: false,
+            theme_input: None,
+            theme: String::new(),
+            volume_input: None,
+            volume: String::new(),
+            font_input: None,
+            font: String::new(),
+        }
+    }
+
+    /// Fill the inputs in from the settings when the page opens, and drop them when it closes.
+    fn open(&mut self, open: bool, cx: &mut Context<Self>) {
+        self.open = open;
+        self.theme_input = None;
+        self.volume_input = None;
+        self.font_input = None;
+        if !open {
+            return;
+        }
+        cx.global::<Controller>().get_output_devices();
+        let settings = cx.global::<Settings>();
+        self.theme = String::new();
+        self.volume = settings
+            .default_volume
+            .map(|volume| format!("{:.0}%", volume * 100.0))
+            .unwrap_or_default();
+
+        let handle = cx.focus_handle();
+        let theme_input = TextInput::new(
+            cx,
+            handle,
+            Some(self.theme.clone().into()),
+            Some("Another theme file".into()),
+        );
+        cx.subscribe(&theme_input, |this: &mut Self, _, text: &String, _| {
+            this.theme = text.clone();
+        })
+        .detach();
+        let handle = cx.focus_handle();
+        let volume_input = TextInput::new(
+            cx,
+            handle,
+            Some(self.volume.clone().into()),
+            Some("Last session's".into()),
+        );
+        cx.subscribe(&volume_input, |this: &mut Self, _, text: &String, _| {
+            this.volume = text.clone();
+        })
+        .detach();
+        let handle = cx.focus_handle();
+        self.font = cx
+            .global::<Settings>()
+            .font_family
+            .clone()
+            .unwrap_or_default();
+        let font_input = TextInput::new(
+            cx,
+            handle,
+            Some(self.font.clone().into()),
+            Some("Default font".into()),
+        );
+        cx.subscribe(&font_input, |this: &mut Self, _, text: &String, _| {
+            this.font = text.clone();
+        })
+        .detach();
+        self.theme_input = Some(theme_input);
+        self.volume_input = Some(volume_input);
+        self.font_input = Some(font_input);
+    }
+
+    /// One of a set of choices, or a switch, marked when it's the one in effect.
+    fn render_choice(
+        &self,
+        id: impl Into<ElementId>,
+        label: impl Into<SharedString>,
+        chosen: bool,
+        cx: &Context<Self>,
+    ) -> Stateful<Div> {
+        let theme = cx.global::<Theme>();
+        let row = theme.components.row;
+        div()
+            .id(id)
+            .flex()
+            .items_center()
+            .justify_between()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .text_color(if chosen { theme.accent } else { row.text })
+            .when(chosen, |this| this.bg(row.selected_bg))
+            .hover(|this| this.bg(row.hover_bg))
+            .child(div().truncate().child(label.into()))
+    }
+
+    fn apply_theme(&mut self, cx: &mut Context<Self>) {
+        let path = self.theme.trim();
+        if !path.is_empty() {
+            use_theme(Some(path.into()), cx);
+        }
+    }
+
+    fn apply_font(&mut self, cx: &mut Context<Self>) {
+        let family = self.font.trim();
+        if family.is_empty() {
+            change_settings(cx, |settings| settings.font_family = None);
+            return;
+        }
+        let Some(family) = fonts::find(family, cx) else {
+            toast::show(format!("Font {family} isn't installed"), cx);
+            return;
+        };
+        change_settings(cx, |settings| settings.font_family = Some(family));
+    }
+
+    fn apply_volume(&mut self, cx: &mut Context<Self>) {
+        let text = self.volume.trim();
+        let volume = if text.is_empty() {
+            None
+        } else {
+            match text.trim_end_matches('%').trim().parse::<f64>() {
+                Ok(percent) => Some((percent / 100.0).clamp(0.0, MAX_BOOSTED_VOLUME)),
+                Err(_) => {
+                    toast::show(format!("{text} isn't a percentage"), cx);
+                    return;
+                }
+            }
+        };
+        change_settings(cx, |settings| settings.default_volume = volume);
+    }
+}
+
+fn close(cx: &mut App) {
+    let open = cx.global::<PlayerContext>().settings_view.clone();
+    open.update(cx, |this, cx| {
+        *this = false;
+        cx.notify();
+    });
+}
+
+/// Close the settings for another page of them.
+fn switch_to(page: &Entity<bool>, cx: &mut App) {
+    close(cx);
+    page.update(cx, |this, cx| {
+        *this = true;
+        cx.notify();
+    });
+}
+
+/// Change the settings, applying the change and saving it to the settings file right away.
+pub fn change_settings(cx: &mut App, change: impl FnOnce(&mut Settings)) {
+    let mut settings = cx.global::<Settings>().clone();
+    change(&mut settings);
+    keybinds::apply_settings(settings, cx);
+    save_settings(cx);
+    cx.refresh_windows();
+}
+
+/// Write the settings as they are to the settings file, saying so if that fails.
+pub fn save_settings(cx: &mut App) {
+    if let Err(e) = cx.global::<Settings>().save() {
+        eprintln!("Could not save settings: {e}");
+        toast::show(format!("Could not save settings: {e}"), cx);
+    }
+}
+
+/// Switch to `theme`, as long as it can be read.
+fn use_theme(theme: Option<PathBuf>, cx: &mut App) {
+    if let Some(Err(e)) = theme
+        .as_ref()
+        .map(|theme| themes::read(&themes::resolve(theme)))
+    {
+        toast::show(format!("Could not use theme: {e}"), cx);
+        return;
+    }
+    change_settings(cx, |settings| settings.theme = theme);
+}
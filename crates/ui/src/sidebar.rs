@@ -1,25 +1,82 @@
-use backend::{playback::SavedPlaylists, player::Controller};
+use std::collections::HashSet;
+
+use backend::{
+    export::ExportPaths,
+    playback::{DuplicateTracks, SavedPlaylist, SavedPlaylists},
+    player::Controller,
+};
 use components::theme::Theme;
 use gpui::{prelude::FluentBuilder, *};
 
 use crate::{
-    layout::{Layout, LayoutMode},
+    layout::{Layout, MIN_LEFT_WIDTH},
+    main_view::{BrowseMode, browse},
     now_playing::PlayerContext,
 };
 
 #[derive(Clone)]
 pub struct LeftSidebar {
     pub playlists: Entity<SavedPlaylists>,
+    pub listen_later: Entity<usize>,
     pub layout: Entity<Layout>,
+    /// Saved playlist whose context menu is open, and which step of it is showing.
+    menu: Option<(usize, PlaylistMenu)>,
+    /// Sources whose sections are folded away.
+    collapsed: HashSet<Source>,
+    /// Whether the recently used folders are showing under the Open Folder button.
+    recent_menu: bool,
+}
+
+/// A top-level source of music. Each has a collapsible section in the sidebar with a count,
+/// and opening something from it switches the main view to the source's mode. New kinds of
+/// source get a variant here and a section of their own.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    Library,
+    Playlists,
+    SmartPlaylists,
+}
+
+impl Source {
+    pub const ALL: [Source; 3] = [Source::Library, Source::Playlists, Source::SmartPlaylists];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Source::Library => "Library",
+            Source::Playlists => "Playlists",
+            Source::SmartPlaylists => "Smart playlists",
+        }
+    }
+
+    /// The main view mode for what this source opens.
+    pub fn mode(self) -> BrowseMode {
+        match self {
+            Source::Library => BrowseMode::Albums,
+            Source::Playlists | Source::SmartPlaylists => BrowseMode::NowPlaying,
+        }
+    }
+}
+
+/// Steps of the menu opened by right-clicking a saved playlist.
+#[derive(Clone, Copy, PartialEq)]
+enum PlaylistMenu {
+    Actions,
+    /// Picking the playlist to merge into.
+    MergeTarget,
+    /// Picking what to do with tracks the playlist at this index already has.
+    MergeDuplicates(usize),
+    /// Picking how the exported file points at the tracks.
+    Export,
+    /// Making sure the playlist should go.
+    ConfirmDelete,
 }
 
 impl Render for LeftSidebar {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let controller = cx.global::<Controller>().clone();
-        let playlists = self.playlists.read(cx).clone().playlists;
-        let current_index = cx.global::<PlayerContext>().metadata.clone();
         let layout = self.layout.clone().read(cx);
+        let scan_progress = *cx.global::<PlayerContext>().scan_progress.read(cx);
 
         if layout.left_sidebar.show {
             deferred(
@@ -28,9 +85,9 @@ impl Render for LeftSidebar {
                     .bg(theme.background)
                     .h_full()
                     .w(px(layout.left_sidebar.width))
-                    .min_w(px(200.0))
-                    .when(layout.mode == LayoutMode::Overlay, |this| {
-                        this.absolute().border_0()
+                    .min_w(px(MIN_LEFT_WIDTH))
+                    .when(layout.left_sidebar.drawer, |this| {
+                        this.absolute().top_0().left_0()
                     })
                     .occlude()
                     .border_r_1()
@@ -40,45 +97,144 @@ impl Render for LeftSidebar {
                     .flex()
                     .flex_col()
                     .gap_2()
-                    .children(playlists.into_iter().map(|playlist| {
-                        let controller = controller.clone();
-                        let curr_index = current_index.clone();
-                        let current_index = curr_index.read(cx).playlist_name.clone();
-
+                    .child(
+                        div()
+                            .id("sidebar_sources")
+                            .flex_grow()
+                            .overflow_y_scroll()
+                            .flex()
+                            .flex_col()
+                            .gap_3()
+                            .children(Source::ALL.map(|source| self.render_source(source, cx))),
+                    )
+                    .when_some(scan_progress, |this, (scanned, total)| {
+                        let progress = if total > 0 {
+                            scanned as f32 / total as f32
+                        } else {
+                            0.0
+                        };
+                        this.child(
+                            div()
+                                .w_full()
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .text_sm()
+                                .text_color(theme.text)
+                                .child(
+                                    div()
+                                        .flex()
+                                        .justify_between()
+                                        .child(format!("Scanning {scanned}/{total}"))
+                                        .child(
+                                            div()
+                                                .id("cancel_scan")
+                                                .hover(|this| this.text_color(theme.accent))
+                                                .child("Cancel")
+                                                .on_click({
+                                                    let controller = controller.clone();
+                                                    move |_, _, _| controller.cancel_scan()
+                                                }),
+                                        ),
+                                )
+                                .child(
+                                    div()
+                                        .w_full()
+                                        .h(px(3.0))
+                                        .rounded_full()
+                                        .bg(theme.components.slider.track)
+                                        .child(
+                                            div()
+                                                .h_full()
+                                                .w(relative(progress))
+                                                .rounded_full()
+                                                .bg(theme.components.slider.fill),
+                                        ),
+                                ),
+                        )
+                    })
+                    .child(
+                        div()
+                            .w_full()
+                            .flex()
+                            .gap_1()
+                            .child(
+                                div()
+                                    .flex_grow()
+                                    .h_10()
+                                    .child("Open Folder")
+                                    .flex()
+                                    .items_center()
+                                    .justify_center()
+                                    .rounded_lg()
+                                    .text_color(theme.text)
+                                    .border_1()
+                                    .border_color(theme.secondary)
+                                    .hover(|this| {
+                                        this.bg(theme.secondary).border_color(theme.accent)
+                                    })
+                                    .on_mouse_down(MouseButton::Left, {
+                                        let controller = controller.clone();
+                                        move |_, _, _| {
+                                            controller.open_folder();
+                                            controller.get_queue();
+                                            controller.write_playlist();
+                                            controller.retrieve_saved_playlists();
+                                        }
+                                    }),
+                            )
+                            .child(
+                                div()
+                                    .id("recent_folders")
+                                    .w_10()
+                                    .h_10()
+                                    .flex_shrink_0()
+                                    .child(if self.recent_menu { "▴" } else { "▾" })
+                                    .flex()
+                                    .items_center()
+                                    .justify_center()
+                                    .rounded_lg()
+                                    .text_color(theme.text)
+                                    .border_1()
+                                    .border_color(theme.secondary)
+                                    .hover(|this| {
+                                        this.bg(theme.secondary).border_color(theme.accent)
+                                    })
+                                    .on_click(cx.listener(|this, _, _, cx| {
+                                        this.recent_menu = !this.recent_menu;
+                                        cx.notify();
+                                    })),
+                            ),
+                    )
+                    .when(self.recent_menu, |this| {
+                        this.child(self.render_recent_folders(cx))
+                    })
+                    .child(
                         div()
-                            .bg(theme.background)
-                            .border_1()
-                            .border_color(theme.secondary)
-                            .hover(|this| this.border_color(theme.accent))
-                            .when(playlist.name == current_index.clone(), |this| {
-                                this.bg(theme.secondary)
-                            })
-                            .text_color(theme.text)
-                            .font_weight(FontWeight::MEDIUM)
                             .w_full()
-                            .rounded_lg()
                             .h_10()
+                            .child("Open Files…")
                             .flex()
                             .items_center()
-                            .justify_start()
-                            .px_3()
-                            .child(playlist.name.clone())
-                            .truncate()
+                            .justify_center()
+                            .rounded_lg()
+                            .text_color(theme.text)
+                            .border_1()
+                            .border_color(theme.secondary)
+                            .hover(|this| this.bg(theme.secondary).border_color(theme.accent))
                             .on_mouse_down(MouseButton::Left, {
-                                move |_, _, cx| {
-                                    curr_index.update(cx, |this, _| {
-                                        this.playlist_name = playlist.name.clone().into();
-                                    });
-                                    controller.load(playlist.clone());
+                                let controller = controller.clone();
+                                move |_, _, _| {
+                                    controller.open_files();
                                     controller.get_queue();
                                 }
-                            })
-                    }))
+                            }),
+                    )
                     .child(
                         div()
                             .w_full()
                             .h_10()
-                            .child("Open Folder")
+                            .child("Import Playlist…")
                             .flex()
                             .items_center()
                             .justify_center()
@@ -88,10 +244,7 @@ impl Render for LeftSidebar {
                             .border_color(theme.secondary)
                             .hover(|this| this.bg(theme.secondary).border_color(theme.accent))
                             .on_mouse_down(MouseButton::Left, move |_, _, _| {
-                                controller.open_folder();
-                                controller.get_queue();
-                                controller.write_playlist();
-                                controller.retrieve_saved_playlists();
+                                controller.import_playlist();
                             }),
                     ),
             )
@@ -103,7 +256,587 @@ impl Render for LeftSidebar {
 }
 
 impl LeftSidebar {
-    pub fn new(playlists: Entity<SavedPlaylists>, layout: Entity<Layout>) -> Self {
-        LeftSidebar { playlists, layout }
+    /// Folders opened lately, most recent first. Clicking one opens it again.
+    fn render_recent_folders(&self, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let folders = cx.global::<PlayerContext>().recent_folders.read(cx).clone();
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .p_1()
+            .rounded_lg()
+            .border_1()
+            .border_color(theme.secondary)
+            .bg(theme.tokens.surface.raised)
+            .when(folders.is_empty(), |this| {
+                this.child(
+                    div()
+                        .px_2()
+                        .py_1()
+                        .text_sm()
+                        .text_color(theme.tokens.text.muted)
+                        .child("No recent folders"),
+                )
+            })
+            .children(folders.into_iter().enumerate().map(|(index, folder)| {
+                let name = folder
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| folder.to_string_lossy().to_string());
+                div()
+                    .id(("recent_folder", index))
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .text_sm()
+                    .text_color(theme.text)
+                    .truncate()
+                    .hover(|this| this.bg(theme.secondary).text_color(theme.accent))
+                    .child(name)
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        this.recent_menu = false;
+                        let controller = cx.global::<Controller>();
+                        controller.open_recent_folder(folder.clone());
+                        controller.get_queue();
+                        controller.write_playlist();
+                        controller.retrieve_saved_playlists();
+                        cx.notify();
+                    }))
+            }))
+    }
+
+    /// A source's section: a header with its count that folds the section away when clicked,
+    /// then what the source offers.
+    fn render_source(&self, source: Source, cx: &Context<Self>) -> Div {
+        let context = cx.global::<PlayerContext>();
+        let (count, body) = match source {
+            Source::Library => (context.albums.read(cx).len(), self.render_library(cx)),
+            Source::Playlists => (
+                self.playlists.read(cx).playlists.len(),
+                self.render_playlists(cx),
+            ),
+            Source::SmartPlaylists => (
+                context.smart_playlists.read(cx).len(),
+                self.render_smart_playlists(cx),
+            ),
+        };
+        let collapsed = self.collapsed.contains(&source);
+        let theme = cx.global::<Theme>();
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(
+                div()
+                    .id(("source", source as usize))
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .text_sm()
+                    .text_color(theme.tokens.text.muted)
+                    .hover(|this| this.text_color(theme.accent))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_1()
+                            .child(if collapsed { "▸" } else { "▾" })
+                            .child(source.title()),
+                    )
+                    .child(count.to_string())
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        if !this.collapsed.remove(&source) {
+                            this.collapsed.insert(source);
+                        }
+                        cx.notify();
+                    })),
+            )
+            .when(!collapsed, |this| this.child(body))
+    }
+
+    /// Ways into the library, each opening its view in the main area.
+    fn render_library(&self, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let context = cx.global::<PlayerContext>();
+        let current = self.layout.read(cx).browse;
+        let views = [
+            (
+                "Albums",
+                BrowseMode::Albums,
+                Some(context.albums.read(cx).len()),
+            ),
+            (
+                "Artists",
+                BrowseMode::Artists,
+                Some(context.artists.read(cx).len()),
+            ),
+            ("Search", BrowseMode::Search, None),
+            ("Most Skipped", BrowseMode::Skipped, None),
+            ("Insights", BrowseMode::Insights, None),
+        ];
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .children(
+                views
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (label, mode, count))| {
+                        let layout = self.layout.clone();
+                        div()
+                            .id(("library_view", index))
+                            .w_full()
+                            .h_8()
+                            .px_3()
+                            .flex()
+                            .items_center()
+                            .justify_between()
+                            .rounded_lg()
+                            .text_color(theme.text)
+                            .when(current == mode, |this| this.bg(theme.secondary))
+                            .hover(|this| this.text_color(theme.accent))
+                            .child(label)
+                            .when_some(count, |this, count| {
+                                this.child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(theme.tokens.text.muted)
+                                        .child(count.to_string()),
+                                )
+                            })
+                            .on_click(move |_, _, cx| browse(&layout, mode, cx))
+                    }),
+            )
+            .child(
+                div()
+                    .id("library_folders")
+                    .w_full()
+                    .h_8()
+                    .px_3()
+                    .flex()
+                    .items_center()
+                    .rounded_lg()
+                    .text_color(theme.tokens.text.muted)
+                    .hover(|this| this.text_color(theme.accent))
+                    .child("Library folders…")
+                    .on_click({
+                        let open = context.library_settings.clone();
+                        move |_, _, cx| {
+                            cx.global::<Controller>().get_library_roots();
+                            open.update(cx, |this, cx| {
+                                *this = true;
+                                cx.notify();
+                            });
+                        }
+                    }),
+            )
+            .child(
+                div()
+                    .id("scrobbling")
+                    .w_full()
+                    .h_8()
+                    .px_3()
+                    .flex()
+                    .items_center()
+                    .rounded_lg()
+                    .text_color(theme.tokens.text.muted)
+                    .hover(|this| this.text_color(theme.accent))
+                    .child("Scrobbling…")
+                    .on_click({
+                        let open = context.scrobble_settings.clone();
+                        move |_, _, cx| {
+                            open.update(cx, |this, cx| {
+                                *this = true;
+                                cx.notify();
+                            });
+                        }
+                    }),
+            )
+            .child(
+                div()
+                    .id("settings")
+                    .w_full()
+                    .h_8()
+                    .px_3()
+                    .flex()
+                    .items_center()
+                    .rounded_lg()
+                    .text_color(theme.tokens.text.muted)
+                    .hover(|this| this.text_color(theme.accent))
+                    .child("Settings…")
+                    .on_click({
+                        let open = context.settings_view.clone();
+                        move |_, _, cx| {
+                            open.update(cx, |this, cx| {
+                                *this = true;
+                                cx.notify();
+                            });
+                        }
+                    }),
+            )
+    }
+
+    /// Saved playlists. Right-click one for its menu.
+    fn render_playlists(&self, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let controller = cx.global::<Controller>().clone();
+        let playlists = self.playlists.read(cx).clone().playlists;
+        let current_index = cx.global::<PlayerContext>().metadata.clone();
+        let listen_later = *self.listen_later.read(cx);
+
+        div().w_full().flex().flex_col().gap_2().children(
+            playlists
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(index, playlist)| {
+                    let controller = controller.clone();
+                    let layout = self.layout.clone();
+                    let curr_index = current_index.clone();
+                    let current_index = curr_index.read(cx).playlist_name.clone();
+
+                    div()
+                        .bg(theme.background)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .hover(|this| this.border_color(theme.accent))
+                        .when(playlist.name == current_index.clone(), |this| {
+                            this.bg(theme.secondary)
+                        })
+                        .text_color(theme.text)
+                        .font_weight(FontWeight::MEDIUM)
+                        .w_full()
+                        .rounded_lg()
+                        .h_10()
+                        .flex()
+                        .items_center()
+                        .justify_between()
+                        .gap_2()
+                        .px_3()
+                        .child(div().child(playlist.name.clone()).truncate())
+                        .when(playlist.is_listen_later() && listen_later > 0, |this| {
+                            this.child(
+                                div()
+                                    .flex_shrink_0()
+                                    .px_2()
+                                    .rounded_full()
+                                    .bg(theme.accent)
+                                    .text_color(theme.background)
+                                    .text_xs()
+                                    .child(listen_later.to_string()),
+                            )
+                        })
+                        .on_mouse_down(MouseButton::Left, {
+                            move |_, _, cx| {
+                                curr_index.update(cx, |this, _| {
+                                    this.playlist_name = playlist.name.clone().into();
+                                });
+                                controller.load(playlist.clone());
+                                controller.get_queue();
+                                browse(&layout, Source::Playlists.mode(), cx);
+                            }
+                        })
+                        .on_mouse_down(
+                            MouseButton::Right,
+                            cx.listener(move |this, _, _, cx| {
+                                this.menu = Some((index, PlaylistMenu::Actions));
+                                cx.notify();
+                            }),
+                        )
+                        .map(|row| match self.menu {
+                            Some((open, step)) if open == index => div()
+                                .w_full()
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .child(row)
+                                .child(self.render_playlist_menu(index, step, &playlists, cx)),
+                            _ => row,
+                        })
+                }),
+        )
+    }
+
+    /// Smart playlists with how many tracks each matches, and a button to make a new one.
+    fn render_smart_playlists(&self, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let context = cx.global::<PlayerContext>();
+        let playlists = context.smart_playlists.read(cx).clone();
+        let current = context.metadata.read(cx).playlist_name.clone();
+        let editing = context.editing_smart_playlist.clone();
+
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .children(
+                playlists
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (playlist, count))| {
+                        let editing = editing.clone();
+                        let layout = self.layout.clone();
+                        div()
+                            .id(("smart_playlist", index))
+                            .bg(theme.background)
+                            .border_1()
+                            .border_color(theme.secondary)
+                            .hover(|this| this.border_color(theme.accent))
+                            .when(current.as_ref() == playlist.name, |this| {
+                                this.bg(theme.secondary)
+                            })
+                            .text_color(theme.text)
+                            .font_weight(FontWeight::MEDIUM)
+                            .w_full()
+                            .rounded_lg()
+                            .h_10()
+                            .flex()
+                            .items_center()
+                            .justify_between()
+                            .gap_2()
+                            .px_3()
+                            .child(div().child(playlist.name.clone()).truncate())
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_shrink_0()
+                                    .items_center()
+                                    .gap_2()
+                                    .child(
+                                        div()
+                                            .text_xs()
+                                            .text_color(theme.tokens.text.muted)
+                                            .child(count.to_string()),
+                                    )
+                                    .child(
+                                        div()
+                                            .id(("edit_smart_playlist", index))
+                                            .text_sm()
+                                            .hover(|this| this.text_color(theme.accent))
+                                            .child("Edit")
+                                            .on_click(move |_, _, cx| {
+                                                cx.stop_propagation();
+                                                editing.update(cx, |this, cx| {
+                                                    *this = Some(Some(index));
+                                                    cx.notify();
+                                                });
+                                            }),
+                                    ),
+                            )
+                            .on_click(move |_, _, cx| {
+                                cx.global::<Controller>().play_smart_playlist(index);
+                                browse(&layout, Source::SmartPlaylists.mode(), cx);
+                            })
+                    }),
+            )
+            .child(
+                div()
+                    .id("new_smart_playlist")
+                    .w_full()
+                    .h_10()
+                    .child("New Smart Playlist…")
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .rounded_lg()
+                    .text_color(theme.text)
+                    .border_1()
+                    .border_color(theme.secondary)
+                    .hover(|this| this.bg(theme.secondary).border_color(theme.accent))
+                    .on_click(move |_, _, cx| {
+                        editing.update(cx, |this, cx| {
+                            *this = Some(None);
+                            cx.notify();
+                        });
+                    }),
+            )
+    }
+
+    /// Context menu of the saved playlist at `index`: duplicate it, or merge it into another
+    /// playlist after choosing which one and how tracks it already has are handled.
+    fn render_playlist_menu(
+        &self,
+        index: usize,
+        step: PlaylistMenu,
+        playlists: &[SavedPlaylist],
+        cx: &Context<Self>,
+    ) -> Div {
+        let theme = cx.global::<Theme>();
+        let name = playlists[index].name.clone();
+        let item = |id: (&'static str, usize), label: String| {
+            div()
+                .id(id)
+                .px_2()
+                .py_1()
+                .rounded_md()
+                .text_sm()
+                .text_color(theme.text)
+                .hover(|this| this.bg(theme.secondary).text_color(theme.accent))
+                .child(label)
+        };
+        let heading = |label: String| {
+            div()
+                .px_2()
+                .text_xs()
+                .text_color(theme.tokens.text.muted)
+                .truncate()
+                .child(label)
+        };
+        let close = cx.listener(|this, _, _, cx| {
+            this.menu = None;
+            cx.notify();
+        });
+
+        let menu = div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .p_1()
+            .rounded_lg()
+            .border_1()
+            .border_color(theme.secondary)
+            .bg(theme.tokens.surface.raised);
+        match step {
+            PlaylistMenu::Actions => menu
+                .child(
+                    item(("edit_playlist", index), "Rename or edit…".into()).on_click(cx.listener(
+                        move |this, _, _, cx| {
+                            this.menu = None;
+                            let editing = cx.global::<PlayerContext>().editing_playlist.clone();
+                            editing.update(cx, |this, cx| {
+                                *this = Some(index);
+                                cx.notify();
+                            });
+                            cx.notify();
+                        },
+                    )),
+                )
+                .child(
+                    item(("duplicate_playlist", index), "Duplicate playlist".into()).on_click(
+                        cx.listener(move |this, _, _, cx| {
+                            this.menu = None;
+                            cx.global::<Controller>().duplicate_playlist(index);
+                            cx.notify();
+                        }),
+                    ),
+                )
+                .child(
+                    item(("merge_playlist", index), "Merge into…".into()).on_click(cx.listener(
+                        move |this, _, _, cx| {
+                            this.menu = Some((index, PlaylistMenu::MergeTarget));
+                            cx.notify();
+                        },
+                    )),
+                )
+                .child(
+                    item(("export_playlist", index), "Export…".into()).on_click(cx.listener(
+                        move |this, _, _, cx| {
+                            this.menu = Some((index, PlaylistMenu::Export));
+                            cx.notify();
+                        },
+                    )),
+                )
+                .child(
+                    item(("delete_playlist", index), "Delete…".into()).on_click(cx.listener(
+                        move |this, _, _, cx| {
+                            this.menu = Some((index, PlaylistMenu::ConfirmDelete));
+                            cx.notify();
+                        },
+                    )),
+                )
+                .child(item(("close_playlist_menu", index), "Cancel".into()).on_click(close)),
+            PlaylistMenu::MergeTarget => {
+                let targets: Vec<(usize, String)> = playlists
+                    .iter()
+                    .enumerate()
+                    .filter(|(into, target)| *into != index && !target.is_listen_later())
+                    .map(|(into, target)| (into, target.name.clone()))
+                    .collect();
+                menu.child(heading(format!("Merge {name} into")))
+                    .when(targets.is_empty(), |this| {
+                        this.child(heading("No other playlists".into()))
+                    })
+                    .children(targets.into_iter().map(|(into, target)| {
+                        item(("merge_target", into), target).on_click(cx.listener(
+                            move |this, _, _, cx| {
+                                this.menu = Some((index, PlaylistMenu::MergeDuplicates(into)));
+                                cx.notify();
+                            },
+                        ))
+                    }))
+                    .child(item(("close_playlist_menu", index), "Cancel".into()).on_click(close))
+            }
+            PlaylistMenu::MergeDuplicates(into) => menu
+                .child(heading(format!(
+                    "Tracks already in {}",
+                    playlists
+                        .get(into)
+                        .map_or("", |target| target.name.as_str())
+                )))
+                .children(DuplicateTracks::ALL.into_iter().enumerate().map(
+                    |(choice, duplicates)| {
+                        item(("merge_duplicates", choice), duplicates.label().into()).on_click(
+                            cx.listener(move |this, _, _, cx| {
+                                this.menu = None;
+                                cx.global::<Controller>()
+                                    .merge_playlist(index, into, duplicates);
+                                cx.notify();
+                            }),
+                        )
+                    },
+                ))
+                .child(item(("close_playlist_menu", index), "Cancel".into()).on_click(close)),
+            PlaylistMenu::ConfirmDelete => menu
+                .child(heading(format!("Delete {name}?")))
+                .child(
+                    item(("confirm_delete_playlist", index), "Delete".into()).on_click(
+                        cx.listener(move |this, _, _, cx| {
+                            this.menu = None;
+                            cx.global::<Controller>().delete_playlist(index);
+                            cx.notify();
+                        }),
+                    ),
+                )
+                .child(item(("close_playlist_menu", index), "Cancel".into()).on_click(close)),
+            PlaylistMenu::Export => menu
+                .child(heading(format!("Export {name} with")))
+                .children(
+                    ExportPaths::ALL
+                        .into_iter()
+                        .enumerate()
+                        .map(|(choice, paths)| {
+                            item(("export_paths", choice), paths.label().into()).on_click(
+                                cx.listener(move |this, _, _, cx| {
+                                    this.menu = None;
+                                    cx.global::<Controller>().export_playlist(index, paths);
+                                    cx.notify();
+                                }),
+                            )
+                        }),
+                )
+                .child(item(("close_playlist_menu", index), "Cancel".into()).on_click(close)),
+        }
+    }
+
+    pub fn new(
+        playlists: Entity<SavedPlaylists>,
+        listen_later: Entity<usize>,
+        layout: Entity<Layout>,
+    ) -> Self {
+        LeftSidebar {
+            playlists,
+            listen_later,
+            layout,
+            menu: None,
+            collapsed: HashSet::new(),
+            recent_menu: false,
+        }
     }
 }
@@ -0,0 +1,305 @@
+use backend::{
+    player::Controller,
+    smart::{Comparison, Field, Rule, SmartPlaylist},
+};
+use components::{input::TextInput, theme::Theme};
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::{chapter_editor::action, now_playing::PlayerContext};
+
+/// Rule editor for smart playlists: a name and the conditions a library track has to meet,
+/// all of them, to be in the playlist.
+pub struct SmartPlaylistEditor {
+    /// What the editor was last opened for, so reopening it starts from the saved rules.
+    editing: Option<Option<usize>>,
+    name_input: Option<Entity<TextInput>>,
+    name: String,
+    /// Each rule with the input holding its value.
+    rules: Vec<(Rule, Entity<TextInput>)>,
+}
+
+impl Render for SmartPlaylistEditor {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let context = cx.global::<PlayerContext>().clone();
+        let editing = *context.editing_smart_playlist.read(cx);
+        if editing != self.editing {
+            self.open(editing, cx);
+        }
+        let (Some(index), Some(name_input)) = (editing, self.name_input.clone()) else {
+            return deferred(div().track_focus(&cx.focus_handle())).with_priority(2);
+        };
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+
+        deferred(
+            div()
+                .track_focus(&cx.focus_handle())
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .bg(theme.tokens.surface.scrim)
+                .occlude()
+                .child(
+                    div()
+                        .w(px(520.0))
+                        .max_w_full()
+                        .max_h(relative(0.8))
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_color(theme.text)
+                        .child(div().text_xl().font_weight(FontWeight::BOLD).child(
+                            if index.is_some() {
+                                "Edit smart playlist"
+                            } else {
+                                "New smart playlist"
+                            },
+                        ))
+                        .child(
+                            div()
+                                .h_8()
+                                .px_2()
+                                .py_1()
+                                .rounded_md()
+                                .border_1()
+                                .border_color(theme.secondary)
+                                .child(name_input),
+                        )
+                        .child(
+                            div()
+                                .text_sm()
+                                .text_color(theme.tokens.text.muted)
+                                .child("Tracks matching every rule. Ratings go from 0 to 5 stars."),
+                        )
+                        .child(
+                            div()
+                                .id("smart_rules")
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .overflow_y_scroll()
+                                .children(
+                                    (0..self.rules.len()).map(|row| self.render_rule(row, cx)),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .justify_between()
+                                .gap_2()
+                                .child(action("smart_add_rule", "Add rule", button).on_click(
+                                    cx.listener(|this, _, _, cx| {
+                                        this.push_rule(
+                                            Rule {
+                                                field: Field::Genre,
+                                                comparison: Comparison::Is,
+                                                value: String::new(),
+                                            },
+                                            cx,
+                                        );
+                                        cx.notify();
+                                    }),
+                                ))
+                                .child(
+                                    div()
+                                        .flex()
+                                        .gap_2()
+                                        .when_some(index, |this, index| {
+                                            this.child(
+                                                action("smart_delete", "Delete", button).on_click(
+                                                    move |_, _, cx| {
+                                                        cx.global::<Controller>()
+                                                            .delete_smart_playlist(index);
+                                                        close(cx);
+                                                    },
+                                                ),
+                                            )
+                                        })
+                                        .child(action("smart_cancel", "Cancel", button).on_click(
+                                            |_, _, cx| {
+                                                close(cx);
+                                            },
+                                        ))
+                                        .child(action("smart_save", "Save", button).on_click(
+                                            cx.listener(move |this, _, _, cx| {
+                                                this.save(index, cx);
+                                            }),
+                                        )),
+                                ),
+                        ),
+                ),
+        )
+        .with_priority(2)
+    }
+}
+
+fn close(cx: &mut App) {
+    let editing = cx.global::<PlayerContext>().editing_smart_playlist.clone();
+    editing.update(cx, |this, cx| {
+        *this = None;
+        cx.notify();
+    });
+}
+
+impl SmartPlaylistEditor {
+    pub fn new() -> Self {
+        SmartPlaylistEditor {
+            editing: None,
+            name_input: None,
+            name: String::new(),
+            rules: vec![],
+        }
+    }
+
+    /// Load the playlist being edited into the editor, or a blank one with a single rule.
+    fn open(&mut self, editing: Option<Option<usize>>, cx: &mut Context<Self>) {
+        self.editing = editing;
+        self.name_input = None;
+        self.rules.clear();
+        let Some(index) = editing else {
+            return;
+        };
+        let playlist = index
+            .and_then(|index| {
+                let playlists = cx.global::<PlayerContext>().smart_playlists.read(cx);
+                playlists.get(index).map(|(playlist, _)| playlist.clone())
+            })
+            .unwrap_or_else(|| SmartPlaylist {
+                name: String::new(),
+                rules: vec![],
+            });
+
+        let handle = cx.focus_handle();
+        let input = TextInput::new(
+            cx,
+            handle,
+            Some(playlist.name.clone().into()),
+            Some("Playlist name".into()),
+        );
+        cx.subscribe(&input, |this: &mut Self, _, text: &String, _| {
+            this.name = text.clone();
+        })
+        .detach();
+        self.name = playlist.name;
+        self.name_input = Some(input);
+
+        for rule in playlist.rules {
+            self.push_rule(rule, cx);
+        }
+        if self.rules.is_empty() {
+            self.push_rule(
+                Rule {
+                    field: Field::Genre,
+                    comparison: Comparison::Is,
+                    value: String::new(),
+                },
+                cx,
+            );
+        }
+    }
+
+    fn push_rule(&mut self, rule: Rule, cx: &mut Context<Self>) {
+        let handle = cx.focus_handle();
+        let input = TextInput::new(
+            cx,
+            handle,
+            Some(rule.value.clone().into()),
+            Some("Value".into()),
+        );
+        cx.subscribe(&input, |this: &mut Self, input, text: &String, _| {
+            if let Some((rule, _)) = this.rules.iter_mut().find(|(_, i)| *i == input) {
+                rule.value = text.clone();
+            }
+        })
+        .detach();
+        self.rules.push((rule, input));
+    }
+
+    /// One rule: click the field or the comparison to step through the choices for it.
+    fn render_rule(&self, row: usize, cx: &Context<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+        let button = theme.components.button;
+        let (rule, input) = self.rules[row].clone();
+
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .child(
+                action(("smart_field", row), rule.field.label(), button)
+                    .w(px(130.0))
+                    .flex_shrink_0()
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        let (rule, _) = &mut this.rules[row];
+                        let next = Field::ALL
+                            .iter()
+                            .position(|field| *field == rule.field)
+                            .map_or(0, |i| (i + 1) % Field::ALL.len());
+                        rule.field = Field::ALL[next];
+                        if !rule.field.comparisons().contains(&rule.comparison) {
+                            rule.comparison = rule.field.comparisons()[0];
+                        }
+                        cx.notify();
+                    })),
+            )
+            .child(
+                action(("smart_comparison", row), rule.comparison.label(), button)
+                    .w(px(72.0))
+                    .flex_shrink_0()
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        let (rule, _) = &mut this.rules[row];
+                        let comparisons = rule.field.comparisons();
+                        let next = comparisons
+                            .iter()
+                            .position(|comparison| *comparison == rule.comparison)
+                            .map_or(0, |i| (i + 1) % comparisons.len());
+                        rule.comparison = comparisons[next];
+                        cx.notify();
+                    })),
+            )
+            .child(
+                div()
+                    .flex_grow()
+                    .h_8()
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(theme.secondary)
+                    .child(input),
+            )
+            .child(
+                action(("smart_remove_rule", row), "✕", button).on_click(cx.listener(
+                    move |this, _, _, cx| {
+                        this.rules.remove(row);
+                        cx.notify();
+                    },
+                )),
+            )
+    }
+
+    fn save(&mut self, index: Option<usize>, cx: &mut Context<Self>) {
+        let name = self.name.trim();
+        let playlist = SmartPlaylist {
+            name: if name.is_empty() {
+                "Smart playlist".to_string()
+            } else {
+                name.to_string()
+            },
+            rules: self.rules.iter().map(|(rule, _)| rule.clone()).collect(),
+        };
+        cx.global::<Controller>()
+            .save_smart_playlist(index, playlist);
+        close(cx);
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use backend::text::collate;
+use components::theme::Theme;
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{chapter_editor::action, now_playing::Track};
+
+/// What a list of tracks can be sorted on.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Title,
+    Artist,
+    Album,
+    Duration,
+    Added,
+    Plays,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 6] = [
+        SortKey::Title,
+        SortKey::Artist,
+        SortKey::Album,
+        SortKey::Duration,
+        SortKey::Added,
+        SortKey::Plays,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Title => "Title",
+            SortKey::Artist => "Artist",
+            SortKey::Album => "Album",
+            SortKey::Duration => "Duration",
+            SortKey::Added => "Date added",
+            SortKey::Plays => "Play count",
+        }
+    }
+}
+
+/// Order a list of tracks is shown in, apart from the order they play in.
+#[derive(Clone, Copy, PartialEq)]
+pub struct TrackSort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl TrackSort {
+    /// The sort after `key` is picked while `current` is in use: ascending first, then
+    /// descending, then back to the list's own order.
+    pub fn pick(current: Option<TrackSort>, key: SortKey) -> Option<TrackSort> {
+        match current {
+            Some(sort) if sort.key == key && sort.descending => None,
+            Some(sort) if sort.key == key => Some(TrackSort {
+                key,
+                descending: true,
+            }),
+            _ => Some(TrackSort {
+                key,
+                descending: false,
+            }),
+        }
+    }
+
+    /// Sort `tracks`, keeping tracks that tie in the order they were in. `plays` holds play
+    /// counts by URI.
+    pub fn apply(&self, tracks: &mut [Track], plays: &HashMap<String, u32>) {
+        let count = |track: &Track| plays.get(&track.uri).copied().unwrap_or(0);
+        tracks.sort_by(|a, b| {
+            let order = match self.key {
+                SortKey::Title => collate(&a.title, &b.title),
+                SortKey::Artist => collate(&a.artists.join(", "), &b.artists.join(", ")),
+                SortKey::Album => collate(&a.album, &b.album),
+                SortKey::Duration => a.duration.cmp(&b.duration),
+                SortKey::Added => a.added.cmp(&b.added),
+                SortKey::Plays => count(a).cmp(&count(b)),
+            };
+            if self.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    }
+}
+
+/// A button for each sort key, the one in use lit up with an arrow for its direction.
+/// Clicking one calls `set` with the sort it leads to.
+pub fn sort_bar<V: 'static>(
+    id: &'static str,
+    current: Option<TrackSort>,
+    set: fn(&mut V, Option<TrackSort>),
+    cx: &Context<V>,
+) -> Div {
+    let theme = cx.global::<Theme>();
+    let button = theme.components.button;
+
+    div()
+        .flex()
+        .flex_wrap()
+        .items_center()
+        .gap_1()
+        .child(
+            div()
+                .text_sm()
+                .text_color(theme.tokens.text.muted)
+                .child("Sort by"),
+        )
+        .children(SortKey::ALL.into_iter().enumerate().map(|(index, key)| {
+            let picked = current.filter(|sort| sort.key == key);
+            let label = match picked {
+                Some(sort) if sort.descending => format!("{} ↓", key.label()),
+                Some(_) => format!("{} ↑", key.label()),
+                None => key.label().to_string(),
+            };
+            action((id, index), label, button)
+                .when(picked.is_some(), |this| {
+                    this.bg(button.hover_bg).text_color(button.hover_text)
+                })
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    set(this, TrackSort::pick(current, key));
+                    cx.notify();
+                }))
+        }))
+}
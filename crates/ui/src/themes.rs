@@ -0,0 +1,205 @@
+//! Theme files: TOML or JSON, kept in the `themes` folder beside the settings file or anywhere
+//! else by path. The theme in use is watched, so edits to it show up as soon as they're saved.
+//! Without one, the built-in theme is dark or light, following the system unless set otherwise.
+//! Either can have its accents tinted to match the playing track's cover art.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use backend::{
+    palette::ArtPalette,
+    settings::{Appearance, Settings},
+    watcher::ConfigWatcher,
+};
+use components::theme::Theme;
+use gpui::*;
+use smol::channel::Sender;
+
+use crate::toast;
+
+/// Where theme files are picked from. Themes in it can be set by file name alone.
+pub fn themes_dir() -> Option<PathBuf> {
+    Settings::get_settings_file().map(|file| file.with_file_name("themes"))
+}
+
+/// Theme files in [`themes_dir`], by name.
+pub fn installed() -> Vec<PathBuf> {
+    let Some(entries) = themes_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return vec![];
+    };
+    let mut themes: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml" || extension == "json")
+        })
+        .collect();
+    themes.sort();
+    themes
+}
+
+/// `theme` as set in the settings, with a bare name looked up in [`themes_dir`].
+pub fn resolve(theme: &Path) -> PathBuf {
+    match themes_dir() {
+        Some(dir) if theme.is_relative() => dir.join(theme),
+        _ => theme.to_path_buf(),
+    }
+}
+
+pub fn read(path: &Path) -> anyhow::Result<Theme> {
+    let source = fs::read_to_string(path)?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => Ok(Theme::from_json(&source)?),
+        Some("toml") => Ok(Theme::from_toml(&source)?),
+        _ => Err(anyhow!("{} isn't a .toml or .json file", path.display())),
+    }
+}
+
+/// The theme the settings name, or the built-in one when they name none or it can't be read.
+pub fn load(settings: &Settings, cx: &App) -> Theme {
+    let Some(theme) = &settings.theme else {
+        return built_in(settings.appearance, cx);
+    };
+    let path = resolve(theme);
+    read(&path).unwrap_or_else(|e| {
+        eprintln!("Could not read theme {}: {e}", path.display());
+        built_in(settings.appearance, cx)
+    })
+}
+
+fn built_in(appearance: Appearance, cx: &App) -> Theme {
+    let light = match appearance {
+        Appearance::System => matches!(
+            cx.window_appearance(),
+            WindowAppearance::Light | WindowAppearance::VibrantLight
+        ),
+        Appearance::Dark => false,
+        Appearance::Light => true,
+    };
+    if light {
+        Theme::light()
+    } else {
+        Theme::default()
+    }
+}
+
+/// Switch the built-in theme along with the system's appearance, when following it.
+pub fn follow_system(cx: &mut App) {
+    let settings = cx.global::<Settings>();
+    if settings.theme.is_some() || settings.appearance != Appearance::System {
+        return;
+    }
+    let theme = built_in(Appearance::System, cx);
+    set(theme, cx);
+}
+
+/// The theme before tinting, and the colors of the playing track's cover art.
+struct Tinting {
+    base: Theme,
+    art: Option<ArtPalette>,
+}
+
+impl Global for Tinting {}
+
+/// Use `theme`, tinted to match the cover art when that's turned on.
+pub fn set(theme: Theme, cx: &mut App) {
+    cx.global_mut::<Tinting>().base = theme;
+    apply(cx);
+}
+
+/// Take the tint from the colors of the playing track's cover art, or `None` without any.
+pub fn set_art(palette: Option<ArtPalette>, cx: &mut App) {
+    if cx.global::<Tinting>().art == palette {
+        return;
+    }
+    cx.global_mut::<Tinting>().art = palette;
+    if cx.global::<Settings>().art_accent {
+        apply(cx);
+    }
+}
+
+/// The cover art's most common color, to back the now playing view with while tinting.
+pub fn art_backdrop(cx: &App) -> Option<Rgba> {
+    if !cx.global::<Settings>().art_accent {
+        return None;
+    }
+    let art = cx.global::<Tinting>().art.as_ref()?;
+    art.dominant.first().map(|color| rgb(*color))
+}
+
+/// Recolor the app with the base theme, tinted if that's turned on and the art allows.
+pub fn apply(cx: &mut App) {
+    let tinting = cx.global::<Tinting>();
+    let accent = tinting
+        .art
+        .as_ref()
+        .and_then(|art| art.accent)
+        .filter(|_| cx.global::<Settings>().art_accent);
+    let theme = match accent {
+        Some(accent) => tinting.base.with_accent(rgb(accent)),
+        None => tinting.base,
+    };
+    cx.set_global(theme);
+    cx.refresh_windows();
+}
+
+/// Follows edits to the theme in use.
+struct ThemeWatch {
+    changes: Sender<PathBuf>,
+    watcher: Option<ConfigWatcher>,
+}
+
+impl Global for ThemeWatch {}
+
+/// Create the themes folder and start reloading the theme whenever its file is saved.
+pub fn start(settings: &Settings, cx: &mut App) {
+    if let Some(Err(e)) = themes_dir().map(fs::create_dir_all) {
+        eprintln!("Could not create the themes folder: {e}");
+    }
+    let (changes, saved) = smol::channel::unbounded::<PathBuf>();
+    cx.spawn(move |cx: AsyncApp| async move {
+        while saved.recv().await.is_ok() {
+            // Saving often takes a few writes, so let them settle.
+            cx.background_executor()
+                .timer(Duration::from_millis(200))
+                .await;
+            while saved.try_recv().is_ok() {}
+            let _ = cx.update(reload);
+        }
+    })
+    .detach();
+    cx.set_global(ThemeWatch {
+        changes,
+        watcher: None,
+    });
+    cx.set_global(Tinting {
+        base: *cx.global::<Theme>(),
+        art: None,
+    });
+    watch(settings, cx);
+}
+
+/// Watch the theme `settings` name instead of the one watched so far.
+pub fn watch(settings: &Settings, cx: &mut App) {
+    let changes = cx.global::<ThemeWatch>().changes.clone();
+    let watcher = settings.theme.as_ref().and_then(|theme| {
+        ConfigWatcher::start(vec![resolve(theme)], changes)
+            .map_err(|e| eprintln!("Could not watch theme: {e}"))
+            .ok()
+    });
+    cx.global_mut::<ThemeWatch>().watcher = watcher;
+}
+
+fn reload(cx: &mut App) {
+    let Some(theme) = cx.global::<Settings>().theme.clone() else {
+        return;
+    };
+    match read(&resolve(&theme)) {
+        Ok(theme) => set(theme, cx),
+        Err(e) => toast::show(format!("Theme not reloaded: {e}"), cx),
+    }
+}
@@ -1,4 +1,4 @@
-use crate::layout::Layout;
+use crate::layout::{Layout, Panel};
 use crate::now_playing::PlayerContext;
 use components::theme::Theme;
 
@@ -14,6 +14,7 @@ pub struct Titlebar {
 impl Render for Titlebar {
     fn render(&mut self, win: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
+        let titlebar = theme.components.titlebar;
         div()
             .w_full()
             .h_8()
@@ -38,20 +39,17 @@ impl Render for Titlebar {
                             .flex()
                             .items_center()
                             .justify_center()
-                            .hover(|this| this.bg(rgb(0x45475a)))
+                            .hover(|this| this.bg(titlebar.button_hover))
                             .rounded_md()
                             .child(
                                 Icon::new(Icons::LeftSidebar)
                                     .size(18.0)
-                                    .color(theme.icon.into()),
+                                    .color(titlebar.icon),
                             )
                             .on_mouse_down(MouseButton::Left, {
                                 let layout = self.layout.clone();
                                 move |_, _, cx| {
-                                    layout.update(cx, |this, _| {
-                                        this.left_sidebar.should_show =
-                                            !this.left_sidebar.should_show.clone();
-                                    })
+                                    layout.update(cx, |this, cx| this.toggle(Panel::Left, cx))
                                 }
                             }),
                     ),
@@ -68,9 +66,10 @@ impl Render for Titlebar {
                         let meta = cx.global::<PlayerContext>().metadata.read(cx);
                         let window_width = win.window_bounds().get_bounds().size.width.0;
 
+                        // Count characters rather than bytes so multi-byte scripts aren't split.
                         let truncate = |text: &str, limit: usize| -> String {
-                            if text.len() > limit {
-                                format!("{}...", &text[..limit])
+                            if text.chars().count() > limit {
+                                format!("{}...", text.chars().take(limit).collect::<String>())
                             } else {
                                 text.to_string()
                             }
@@ -141,36 +140,33 @@ impl Render for Titlebar {
                             .flex()
                             .items_center()
                             .justify_center()
-                            .hover(|this| this.bg(rgb(0x45475a)))
+                            .hover(|this| this.bg(titlebar.button_hover))
                             .rounded_md()
                             .child(
                                 Icon::new(Icons::RightSidebar)
                                     .size(18.0)
-                                    .color(theme.icon.into()),
+                                    .color(titlebar.icon),
                             )
                             .on_mouse_down(MouseButton::Left, {
                                 let layout = self.layout.clone();
                                 move |_, _, cx| {
-                                    layout.update(cx, |this, _| {
-                                        this.right_sidebar.should_show =
-                                            !this.right_sidebar.should_show.clone();
-                                    })
+                                    layout.update(cx, |this, cx| this.toggle(Panel::Right, cx))
                                 }
                             }),
                     )
                     .child(
                         div()
-                            .w(px(36.0))
+                            .w(rems(2.25))
                             .h_full()
                             .flex()
                             .items_center()
                             .justify_center()
                             .hover(|this| this.bg(theme.secondary))
-                            .child(Icon::new(Icons::Minimize).size(20.0).color(rgb(0xffffff))),
+                            .child(Icon::new(Icons::Minimize).size(20.0).color(titlebar.icon)),
                     )
                     .child(
                         div()
-                            .w(px(36.0))
+                            .w(rems(2.25))
                             .h_full()
                             .flex()
                             .items_center()
@@ -178,21 +174,21 @@ impl Render for Titlebar {
                             .hover(|this| this.bg(theme.secondary))
                             .child({
                                 if win.is_maximized() {
-                                    Icon::new(Icons::Restore).size(20.0).color(rgb(0xffffff))
+                                    Icon::new(Icons::Restore).size(20.0).color(titlebar.icon)
                                 } else {
-                                    Icon::new(Icons::Maximize).size(20.0).color(rgb(0xffffff))
+                                    Icon::new(Icons::Maximize).size(20.0).color(titlebar.icon)
                                 }
                             }),
                     )
                     .child(
                         div()
-                            .w(px(36.0))
+                            .w(rems(2.25))
                             .h_full()
                             .flex()
                             .items_center()
                             .justify_center()
-                            .hover(|this| this.bg(rgb(0xe81123)))
-                            .child(Icon::new(Icons::Close).size(20.0).color(rgb(0xffffff))),
+                            .hover(|this| this.bg(titlebar.close_hover))
+                            .child(Icon::new(Icons::Close).size(20.0).color(titlebar.icon)),
                     ),
             )
     }
@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use components::theme::Theme;
+use gpui::*;
+
+use crate::now_playing::PlayerContext;
+
+/// How long a toast stays up.
+pub const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Short message near the bottom of the window about something that happened without the
+/// user asking, like the settings being reloaded. Like the rating confirmation, it doesn't
+/// take clicks.
+pub struct Toast;
+
+impl Render for Toast {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let Some((message, _)) = cx.global::<PlayerContext>().toast.read(cx).clone() else {
+            return deferred(div()).with_priority(3);
+        };
+        let theme = cx.global::<Theme>();
+
+        deferred(
+            div()
+                .absolute()
+                .bottom(px(112.0))
+                .left_0()
+                .w_full()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .px_4()
+                        .py_2()
+                        .max_w(px(480.0))
+                        .rounded_lg()
+                        .bg(theme.tokens.surface.raised)
+                        .border_1()
+                        .border_color(theme.secondary)
+                        .text_sm()
+                        .text_color(theme.text)
+                        .child(message),
+                ),
+        )
+        .with_priority(3)
+    }
+}
+
+/// Show `message` as a toast, and keep it in the log for problem reports.
+pub fn show(message: String, cx: &mut App) {
+    let context = cx.global::<PlayerContext>().clone();
+    context
+        .log
+        .update(cx, |this, _| this.push(format!("info: {message}")));
+    let shown = Instant::now();
+    let toast = context.toast.clone();
+    toast.update(cx, |this, cx| {
+        *this = Some((message, shown));
+        cx.notify();
+    });
+    cx.spawn(move |cx: AsyncApp| async move {
+        cx.background_executor().timer(TOAST_DURATION).await;
+        // A newer toast keeps its own time.
+        let _ = toast.update(&mut cx.clone(), |this, cx| {
+            if this.as_ref().is_some_and(|(_, at)| *at == shown) {
+                *this = None;
+                cx.notify();
+            }
+        });
+    })
+    .detach();
+}
@@ -0,0 +1,194 @@
+//! The tray icon, with a menu to control playback and show or hide the window. With
+//! [`Settings::close_to_tray`] on, closing the window only hides it and playback carries on.
+
+use backend::{playback::Track, player::Controller, settings::Settings};
+use components::theme::Theme;
+use gpui::*;
+use tray_icon::{
+    Icon, MouseButton as TrayButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent,
+    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+};
+
+use crate::keybinds::{ChangeState, change_state};
+
+/// Side of the tray icon, in pixels.
+const ICON_SIZE: u32 = 32;
+
+/// Something done from the tray, passed from the tray's thread to the app's.
+enum TrayAction {
+    PlayPause,
+    Next,
+    Previous,
+    ToggleWindow,
+    Quit,
+}
+
+/// The tray icon and the window it shows and hides.
+pub struct Tray {
+    /// `None` on Linux, where the icon lives on a GTK thread of its own and can't show a
+    /// tooltip anyway.
+    icon: Option<TrayIcon>,
+    window: AnyWindowHandle,
+}
+
+impl Global for Tray {}
+
+/// Put the icon in the tray for `window`. Logs and carries on without one if the desktop has no
+/// tray.
+pub fn register(window: AnyWindowHandle, cx: &mut App) {
+    let (tx, rx) = smol::channel::unbounded();
+    let menu_tx = tx.clone();
+    MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+        let action = match event.id.as_ref() {
+            "play_pause" => TrayAction::PlayPause,
+            "next" => TrayAction::Next,
+            "previous" => TrayAction::Previous,
+            "window" => TrayAction::ToggleWindow,
+            "quit" => TrayAction::Quit,
+            _ => return,
+        };
+        let _ = menu_tx.try_send(action);
+    }));
+    // Clicking the icon itself brings the window back, where the platform reports clicks.
+    TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+        if let TrayIconEvent::Click {
+            button: TrayButton::Left,
+            button_state: MouseButtonState::Up,
+            ..
+        } = event
+        {
+            let _ = tx.try_send(TrayAction::ToggleWindow);
+        }
+    }));
+
+    let accent = cx.global::<Theme>().accent;
+    let icon = match build(accent) {
+        Ok(icon) => icon,
+        Err(e) => {
+            eprintln!("Could not add tray icon: {e}");
+            return;
+        }
+    };
+    cx.set_global(Tray { icon, window });
+    cx.spawn(|cx: AsyncApp| async move {
+        while let Ok(action) = rx.recv().await {
+            if cx.update(|cx| handle(action, cx)).is_err() {
+                break;
+            }
+        }
+    })
+    .detach();
+}
+
+fn menu() -> anyhow::Result<Menu> {
+    let menu = Menu::new();
+    menu.append_items(&[
+        &MenuItem::with_id("play_pause", "Play/Pause", true, None),
+        &MenuItem::with_id("next", "Next", true, None),
+        &MenuItem::with_id("previous", "Previous", true, None),
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id("window", "Show/Hide Window", true, None),
+        &MenuItem::with_id("quit", "Quit", true, None),
+    ])?;
+    Ok(menu)
+}
+
+/// Linux trays are driven by GTK, so the icon is made and kept on a GTK main loop of its own.
+#[cfg(target_os = "linux")]
+fn build(accent: Rgba) -> anyhow::Result<Option<TrayIcon>> {
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let built = gtk::init().map_err(anyhow::Error::from).and_then(|_| {
+            TrayIconBuilder::new()
+                .with_icon(icon(accent)?)
+                .with_menu(Box::new(menu()?))
+                .build()
+                .map_err(anyhow::Error::from)
+        });
+        let _ = ready_tx.send(built.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+        if let Ok(_icon) = built {
+            gtk::main();
+        }
+    });
+    match ready_rx.recv()? {
+        Ok(()) => Ok(None),
+        Err(e) => Err(anyhow::anyhow!(e)),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn build(accent: Rgba) -> anyhow::Result<Option<TrayIcon>> {
+    let icon = TrayIconBuilder::new()
+        .with_icon(icon(accent)?)
+        .with_menu(Box::new(menu()?))
+        .with_tooltip("Reyvr")
+        .build()?;
+    Ok(Some(icon))
+}
+
+/// A play symbol on a disc of the theme's accent color.
+fn icon(accent: Rgba) -> anyhow::Result<Icon> {
+    let size = ICON_SIZE as f32;
+    let [r, g, b] = [accent.r, accent.g, accent.b].map(|channel| (channel * 255.0) as u8);
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let (dx, dy) = (px - size / 2.0, py - size / 2.0);
+            let on_disc = dx * dx + dy * dy <= (size / 2.0) * (size / 2.0);
+            // Triangle pointing right, from a third to three quarters of the way across.
+            let across = (px - size / 3.0) / (size * 5.0 / 12.0);
+            let in_play = (0.0..=1.0).contains(&across)
+                && (py - size / 2.0).abs() <= (1.0 - across) * size / 4.0;
+            rgba.extend_from_slice(&match (on_disc, in_play) {
+                (true, true) => [255, 255, 255, 255],
+                (true, false) => [r, g, b, 255],
+                _ => [0, 0, 0, 0],
+            });
+        }
+    }
+    Ok(Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE)?)
+}
+
+fn handle(action: TrayAction, cx: &mut App) {
+    match action {
+        TrayAction::PlayPause => change_state(&ChangeState, cx),
+        TrayAction::Next => cx.global::<Controller>().next(),
+        TrayAction::Previous => cx.global::<Controller>().prev(),
+        TrayAction::ToggleWindow => {
+            let window = cx.global::<Tray>().window;
+            let _ = window.update(cx, |_, window, _| {
+                if window.is_window_active() {
+                    window.minimize_window();
+                } else {
+                    window.activate_window();
+                }
+            });
+        }
+        TrayAction::Quit => cx.quit(),
+    }
+}
+
+/// Whether closing the window should only hide it, leaving the tray icon to bring it back.
+pub fn hides_on_close(cx: &App) -> bool {
+    cx.global::<Settings>().close_to_tray && cx.has_global::<Tray>()
+}
+
+/// Name the track playing in the icon's tooltip.
+pub fn set_track(track: &Track, cx: &mut App) {
+    if !cx.has_global::<Tray>() {
+        return;
+    }
+    let settings = cx.global::<Settings>();
+    let tooltip = format!(
+        "{} – {}",
+        settings.display(&track.title),
+        settings.display(&track.artists.join(", "))
+    );
+    let Some(icon) = &cx.global::<Tray>().icon else {
+        return;
+    };
+    if let Err(e) = icon.set_tooltip(Some(tooltip)) {
+        eprintln!("Could not update tray tooltip: {e}");
+    }
+}
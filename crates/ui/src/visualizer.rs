@@ -0,0 +1,222 @@
+//! The visualizer: bars, a scope-like trace or a ring drawn from the spectrum feed, in the main
+//! view or over the whole window. The feed is only on while the visualizer is shown, and frames
+//! coming faster than the FPS cap are folded into the next one drawn.
+
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    time::{Duration, Instant},
+};
+
+use backend::{
+    player::Controller,
+    settings::{MAX_VISUALIZER_FPS, MAX_VISUALIZER_SMOOTHING, Settings, VisualizerStyle},
+};
+use components::theme::Theme;
+use gpui::*;
+
+pub const STYLES: [(VisualizerStyle, &str); 3] = [
+    (VisualizerStyle::Bars, "Bars"),
+    (VisualizerStyle::Oscilloscope, "Oscilloscope"),
+    (VisualizerStyle::Circular, "Circular"),
+];
+
+pub struct Visualizer {
+    /// Level of each band from 0 to 1, smoothed over the frames so far.
+    levels: Vec<f32>,
+    drawn_at: Option<Instant>,
+    /// Drawn over the whole window, which is made full screen too.
+    pub fullscreen: bool,
+}
+
+impl Visualizer {
+    pub fn new() -> Self {
+        Visualizer {
+            levels: vec![],
+            drawn_at: None,
+            fullscreen: false,
+        }
+    }
+
+    /// Turn the spectrum feed on while the visualizer is shown and off once it isn't, leaving
+    /// full screen along with it.
+    pub fn show(&mut self, shown: bool, window: &mut Window, cx: &mut Context<Self>) {
+        cx.global::<Controller>().set_spectrum(shown);
+        if !shown {
+            self.levels.clear();
+            self.set_fullscreen(false, window, cx);
+        }
+    }
+
+    pub fn set_fullscreen(
+        &mut self,
+        fullscreen: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.fullscreen == fullscreen {
+            return;
+        }
+        self.fullscreen = fullscreen;
+        if window.is_fullscreen() != fullscreen {
+            window.toggle_fullscreen();
+        }
+        cx.notify();
+    }
+
+    /// Fold a spectrum frame into the levels, redrawing unless the last frame drawn was too
+    /// recent for the FPS cap.
+    pub fn push(&mut self, frame: &[f32], cx: &mut Context<Self>) {
+        let settings = cx.global::<Settings>();
+        let smoothing = settings
+            .visualizer_smoothing
+            .clamp(0.0, MAX_VISUALIZER_SMOOTHING);
+        let fps = settings.visualizer_fps.clamp(1, MAX_VISUALIZER_FPS);
+        if self.levels.len() == frame.len() {
+            for (level, new) in self.levels.iter_mut().zip(frame) {
+                // Rising at once and only falling off slowly keeps beats sharp.
+                *level = if *new >= *level {
+                    *new
+                } else {
+                    *level * smoothing + new * (1.0 - smoothing)
+                };
+            }
+        } else {
+            self.levels = frame.to_vec();
+        }
+        // A little slack, so a frame arriving just early for a 30 FPS cap isn't dropped.
+        let interval = Duration::from_secs(1) / fps;
+        if self
+            .drawn_at
+            .is_some_and(|at| at.elapsed() + Duration::from_millis(5) < interval)
+        {
+            return;
+        }
+        self.drawn_at = Some(Instant::now());
+        cx.notify();
+    }
+}
+
+impl Render for Visualizer {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let color = theme.accent;
+        let view = div().size_full();
+        if self.levels.is_empty() {
+            return view
+                .flex()
+                .items_center()
+                .justify_center()
+                .text_color(theme.tokens.text.muted)
+                .child("Play something to see it here.");
+        }
+        let levels = self.levels.clone();
+        match cx.global::<Settings>().visualizer {
+            VisualizerStyle::Bars => {
+                view.flex()
+                    .items_end()
+                    .gap_1()
+                    .children(levels.into_iter().map(|level| {
+                        div()
+                            .flex_1()
+                            .h(relative(level.max(0.01)))
+                            .rounded_t(px(2.0))
+                            .bg(color)
+                    }))
+            }
+            VisualizerStyle::Oscilloscope => view.child(
+                canvas(
+                    |_, _, _| {},
+                    move |bounds, _, window, _| paint_scope(&levels, bounds, color, window),
+                )
+                .size_full(),
+            ),
+            VisualizerStyle::Circular => view.child(
+                canvas(
+                    |_, _, _| {},
+                    move |bounds, _, window, _| paint_ring(&levels, bounds, color, window),
+                )
+                .size_full(),
+            ),
+        }
+    }
+}
+
+/// The visualizer over the whole window, left by clicking anywhere.
+pub fn render_fullscreen(visualizer: Entity<Visualizer>, cx: &App) -> impl IntoElement {
+    let theme = cx.global::<Theme>();
+    deferred(
+        div()
+            .id("visualizer_fullscreen")
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_8()
+            .bg(theme.background)
+            .occlude()
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(theme.tokens.text.muted)
+                    .child("Click anywhere to leave full screen"),
+            )
+            .child(div().flex_grow().child(visualizer.clone()))
+            .on_click(move |_, window, cx| {
+                visualizer.update(cx, |visualizer, cx| {
+                    visualizer.set_fullscreen(false, window, cx);
+                });
+            }),
+    )
+    .with_priority(1)
+}
+
+/// A line swinging either side of the middle, each band's swing as far as it's loud.
+fn paint_scope(levels: &[f32], bounds: Bounds<Pixels>, color: Rgba, window: &mut Window) {
+    let middle = bounds.center().y;
+    let reach = bounds.size.height / 2.0;
+    let step = bounds.size.width / (levels.len().max(2) - 1) as f32;
+    let mut path = PathBuilder::stroke(px(2.0));
+    for (index, level) in levels.iter().enumerate() {
+        let side = if index % 2 == 0 { -1.0 } else { 1.0 };
+        let at = point(
+            bounds.origin.x + step * index as f32,
+            middle + reach * (level * side),
+        );
+        if index == 0 {
+            path.move_to(at);
+        } else {
+            path.line_to(at);
+        }
+    }
+    if let Ok(path) = path.build() {
+        window.paint_path(path, color);
+    }
+}
+
+/// Bars standing out from a ring, the bands going down one side and mirrored up the other.
+fn paint_ring(levels: &[f32], bounds: Bounds<Pixels>, color: Rgba, window: &mut Window) {
+    let center = bounds.center();
+    let radius = bounds.size.width.0.min(bounds.size.height.0) / 4.0;
+    let count = levels.len() * 2;
+    let bands = levels.iter().chain(levels.iter().rev());
+    for (index, level) in bands.enumerate() {
+        let angle = TAU * index as f32 / count as f32 - FRAC_PI_2;
+        let (sin, cos) = angle.sin_cos();
+        let length = radius * level.max(0.02);
+        let mut path = PathBuilder::stroke(px(3.0));
+        path.move_to(point(
+            center.x + px(radius * cos),
+            center.y + px(radius * sin),
+        ));
+        path.line_to(point(
+            center.x + px((radius + length) * cos),
+            center.y + px((radius + length) * sin),
+        ));
+        if let Ok(path) = path.build() {
+            window.paint_path(path, color);
+        }
+    }
+}